@@ -0,0 +1,78 @@
+//! Compares sequential and parallel filtering of a large synthetic tree.
+//!
+//! Run with `cargo bench -p git-filter-tree --features parallel`.
+
+use git_filter_tree::{FilterTree, TreeFilter};
+use git2::{Error, Oid, Repository};
+use std::time::{Duration, Instant};
+
+/// Directories at the root, subdirectories in each, and files in each subdirectory.
+const SHAPE: (usize, usize, usize) = (64, 32, 16);
+const RUNS: u32 = 5;
+
+fn build_tree(repo: &Repository) -> Result<Oid, Error> {
+    let (dirs, subdirs, files) = SHAPE;
+    let mut root = repo.treebuilder(None)?;
+    for d in 0..dirs {
+        let mut dir = repo.treebuilder(None)?;
+        for s in 0..subdirs {
+            let mut sub = repo.treebuilder(None)?;
+            for f in 0..files {
+                let extension = ["rs", "md", "txt", "json"][f % 4];
+                let blob = repo.blob(format!("{d}/{s}/{f}").as_bytes())?;
+                sub.insert(format!("file{f}.{extension}"), blob, 0o100644)?;
+            }
+            dir.insert(format!("sub{s}"), sub.write()?, 0o040000)?;
+        }
+        root.insert(format!("dir{d}"), dir.write()?, 0o040000)?;
+    }
+    root.write()
+}
+
+fn time(mut run: impl FnMut() -> Result<Oid, Error>) -> Result<(Duration, Oid), Error> {
+    let mut best = Duration::MAX;
+    let mut oid = Oid::zero();
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        oid = run()?;
+        best = best.min(start.elapsed());
+    }
+    Ok((best, oid))
+}
+
+fn main() -> Result<(), Error> {
+    let path = std::env::temp_dir().join(format!("git-filter-tree-bench-{}", std::process::id()));
+    let repo = Repository::init_bare(&path)?;
+    let tree = repo.find_tree(build_tree(&repo)?)?;
+
+    // Many patterns, so matching dominates over object access
+    let mut patterns: Vec<String> = (0..SHAPE.0)
+        .step_by(2)
+        .map(|d| format!("dir{d}/**/*.rs"))
+        .collect();
+    patterns.extend((0..SHAPE.1).map(|s| format!("dir*/sub{s}/file1*.md")));
+    let patterns: Vec<&str> = patterns.iter().map(String::as_str).collect();
+    let filter = TreeFilter::new()
+        .include(&patterns)?
+        .exclude(&["dir0/", "**/file15.*"])?;
+
+    let (sequential, expected) = time(|| Ok(repo.filter_by(&tree, &filter)?.id()))?;
+    let (parallel, actual) = time(|| Ok(repo.filter_by_parallel(&tree, &filter)?.id()))?;
+    assert_eq!(actual, expected, "parallel and sequential results differ");
+
+    let (dirs, subdirs, files) = SHAPE;
+    println!(
+        "{} files, {} patterns (best of {RUNS})",
+        dirs * subdirs * files,
+        patterns.len() + 2
+    );
+    println!("  sequential: {sequential:>10.2?}");
+    println!(
+        "  parallel:   {parallel:>10.2?} ({:.1}x, {} threads)",
+        sequential.as_secs_f64() / parallel.as_secs_f64(),
+        rayon::current_num_threads()
+    );
+
+    let _ = std::fs::remove_dir_all(path);
+    Ok(())
+}
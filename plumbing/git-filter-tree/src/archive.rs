@@ -0,0 +1,134 @@
+//! Writing filtered trees as tar or zip archives.
+
+use crate::paths::{bytes_to_path, walk_tree};
+use git2::{Error, ObjectType, Repository, Tree};
+use std::io::{Cursor, Write};
+
+/// The kinds of archive [`FilterTree::archive`] writes.
+///
+/// [`FilterTree::archive`]: crate::FilterTree::archive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Tar,
+    Zip,
+}
+
+/// A file to archive, with its full path and git file mode.
+struct File {
+    path: Vec<u8>,
+    mode: i32,
+    kind: Option<ObjectType>,
+    content: Vec<u8>,
+}
+
+/// The directories and files of `tree`, parents first.
+fn files(repo: &Repository, tree: &Tree) -> Result<Vec<File>, Error> {
+    let mut entries = Vec::new();
+    walk_tree(repo, tree, |path, entry| {
+        entries.push((path.to_vec(), entry.filemode(), entry.kind(), entry.id()));
+        Ok(())
+    })?;
+
+    let mut files = Vec::with_capacity(entries.len());
+    for (path, mode, kind, id) in entries {
+        let content = match kind {
+            Some(ObjectType::Blob) => repo.find_blob(id)?.content().to_vec(),
+            // Submodules have no content to archive
+            Some(ObjectType::Tree) => Vec::new(),
+            _ => continue,
+        };
+        files.push(File {
+            path,
+            mode,
+            kind,
+            content,
+        });
+    }
+    Ok(files)
+}
+
+fn io_error(e: impl std::fmt::Display) -> Error {
+    Error::from_str(&format!("Failed to write archive: {}", e))
+}
+
+/// Write `tree` to `writer` as an archive in `format`.
+pub(crate) fn write(
+    repo: &Repository,
+    tree: &Tree,
+    format: ArchiveFormat,
+    writer: &mut dyn Write,
+) -> Result<(), Error> {
+    let files = files(repo, tree)?;
+    match format {
+        ArchiveFormat::Tar => write_tar(&files, writer),
+        ArchiveFormat::Zip => write_zip(&files, writer),
+    }
+}
+
+fn write_tar(files: &[File], writer: &mut dyn Write) -> Result<(), Error> {
+    let mut builder = tar::Builder::new(writer);
+    for file in files {
+        let path = bytes_to_path(&file.path);
+        let mut header = tar::Header::new_gnu();
+        header.set_mtime(0);
+        match (file.kind, file.mode) {
+            (Some(ObjectType::Tree), _) => {
+                header.set_entry_type(tar::EntryType::Directory);
+                header.set_mode(0o755);
+                header.set_size(0);
+                builder
+                    .append_data(&mut header, path.join(""), std::io::empty())
+                    .map_err(io_error)?;
+            }
+            (_, 0o120000) => {
+                header.set_entry_type(tar::EntryType::Symlink);
+                header.set_mode(0o777);
+                header.set_size(0);
+                let target = String::from_utf8_lossy(&file.content).into_owned();
+                builder
+                    .append_link(&mut header, &path, target)
+                    .map_err(io_error)?;
+            }
+            (_, mode) => {
+                header.set_entry_type(tar::EntryType::Regular);
+                header.set_mode(if mode == 0o100755 { 0o755 } else { 0o644 });
+                header.set_size(file.content.len() as u64);
+                builder
+                    .append_data(&mut header, &path, file.content.as_slice())
+                    .map_err(io_error)?;
+            }
+        }
+    }
+    builder.finish().map_err(io_error)
+}
+
+fn write_zip(files: &[File], writer: &mut dyn Write) -> Result<(), Error> {
+    // Zip archives are written with seeks, so build the archive in memory first
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    for file in files {
+        // Zip stores names as UTF-8, so bytes that are not valid UTF-8 are replaced
+        let path = String::from_utf8_lossy(&file.path);
+        match (file.kind, file.mode) {
+            (Some(ObjectType::Tree), _) => zip
+                .add_directory(path.as_ref(), options.unix_permissions(0o755))
+                .map_err(io_error)?,
+            (_, 0o120000) => zip
+                .add_symlink(
+                    path.as_ref(),
+                    String::from_utf8_lossy(&file.content),
+                    options.unix_permissions(0o777),
+                )
+                .map_err(io_error)?,
+            (_, mode) => {
+                let permissions = if mode == 0o100755 { 0o755 } else { 0o644 };
+                zip.start_file(path.as_ref(), options.unix_permissions(permissions))
+                    .map_err(io_error)?;
+                zip.write_all(&file.content).map_err(io_error)?;
+            }
+        }
+    }
+    let archive = zip.finish().map_err(io_error)?.into_inner();
+    writer.write_all(&archive).map_err(io_error)
+}
@@ -0,0 +1,194 @@
+//! Evaluation of `.gitattributes` files read from a tree rather than the working directory.
+
+use git2::{AttrValue, Error};
+use globset::{Glob, GlobMatcher};
+use std::collections::HashMap;
+
+/// The state of one attribute on a path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum State {
+    /// `name`
+    Set,
+    /// `-name`
+    Unset,
+    /// `name=value`
+    Value(String),
+}
+
+/// The attributes a path carries, as resolved from every `.gitattributes` file above it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Attributes {
+    states: HashMap<String, State>,
+}
+
+impl Attributes {
+    /// The value of attribute `name`, like [`git2::Repository::get_attr`] returns it.
+    pub fn get(&self, name: &str) -> AttrValue<'_> {
+        match self.states.get(name) {
+            Some(State::Set) => AttrValue::True,
+            Some(State::Unset) => AttrValue::False,
+            Some(State::Value(value)) => AttrValue::String(value),
+            None => AttrValue::Unspecified,
+        }
+    }
+
+    /// Whether attribute `name` is set, as by `name` or `name=true`.
+    pub fn is_set(&self, name: &str) -> bool {
+        match self.get(name) {
+            AttrValue::True => true,
+            AttrValue::String(value) => value == "true",
+            _ => false,
+        }
+    }
+}
+
+/// One line of a `.gitattributes` file.
+struct Rule {
+    matcher: GlobMatcher,
+    /// Whether the pattern has no `/`, and so is matched against file names at any depth.
+    basename: bool,
+    states: Vec<(String, Option<State>)>,
+}
+
+/// The rules of one `.gitattributes` file, applying below `prefix`.
+pub(crate) struct RuleSet {
+    prefix: String,
+    rules: Vec<Rule>,
+}
+
+impl RuleSet {
+    /// Parse the `.gitattributes` file `content` found in the directory `prefix` (empty at the root).
+    ///
+    /// Macro definitions (`[attr]name ...`) are skipped.
+    pub(crate) fn parse(prefix: &str, content: &str) -> Result<Self, Error> {
+        let mut rules = Vec::new();
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with("[attr]") {
+                continue;
+            }
+            let mut fields = line.split_whitespace();
+            let Some(pattern) = fields.next() else {
+                continue;
+            };
+            let states = fields
+                .map(|field| {
+                    if let Some(name) = field.strip_prefix('-') {
+                        (name.to_string(), Some(State::Unset))
+                    } else if let Some(name) = field.strip_prefix('!') {
+                        (name.to_string(), None)
+                    } else if let Some((name, value)) = field.split_once('=') {
+                        (name.to_string(), Some(State::Value(value.to_string())))
+                    } else {
+                        (field.to_string(), Some(State::Set))
+                    }
+                })
+                .collect();
+
+            // As in `filter_by_patterns`, a trailing `/` stands for everything below the
+            // directory.
+            let pattern = pattern.strip_prefix('/').unwrap_or(pattern);
+            let basename = !pattern.contains('/');
+            let glob = match pattern.ends_with('/') {
+                true => format!("{pattern}**"),
+                false => pattern.to_string(),
+            };
+            let matcher = Glob::new(&glob)
+                .map_err(|e| Error::from_str(&format!("Invalid pattern '{}': {}", pattern, e)))?
+                .compile_matcher();
+            rules.push(Rule {
+                matcher,
+                basename,
+                states,
+            });
+        }
+        Ok(RuleSet {
+            prefix: prefix.to_string(),
+            rules,
+        })
+    }
+
+    /// Apply the rules matching `path`, relative to the tree root, to `attributes`.
+    fn apply(&self, path: &str, attributes: &mut Attributes) {
+        let relative = match self.prefix.is_empty() {
+            true => Some(path),
+            false => path
+                .strip_prefix(&self.prefix)
+                .and_then(|rest| rest.strip_prefix('/')),
+        };
+        let Some(relative) = relative else {
+            return;
+        };
+        let name = relative.rsplit('/').next().unwrap_or(relative);
+        for rule in &self.rules {
+            let candidate = if rule.basename { name } else { relative };
+            if !rule.matcher.is_match(candidate) {
+                continue;
+            }
+            for (attr, state) in &rule.states {
+                match state {
+                    Some(state) => {
+                        attributes.states.insert(attr.clone(), state.clone());
+                    }
+                    None => {
+                        attributes.states.remove(attr);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The attributes of `path` under `rule_sets`, ordered from the root down, so that deeper files
+/// and later lines take precedence.
+pub(crate) fn resolve(rule_sets: &[RuleSet], path: &str) -> Attributes {
+    let mut attributes = Attributes::default();
+    for rule_set in rule_sets {
+        rule_set.apply(path, &mut attributes);
+    }
+    attributes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn later_and_deeper_rules_take_precedence() {
+        let root = RuleSet::parse(
+            "",
+            "# comment\n\
+             [attr]macro text\n\
+             *.rs linguist-language=Rust diff\n\
+             lib/ vendored name=owner/repo\n\
+             lib/gen.rs -diff linguist-generated\n",
+        )
+        .unwrap();
+        let lib = RuleSet::parse("lib", "gen.rs !vendored linguist-generated=false\n").unwrap();
+        let rule_sets = [root, lib];
+
+        let attrs = resolve(&rule_sets, "lib/a.rs");
+        assert!(attrs.is_set("vendored"));
+        assert!(attrs.is_set("diff"));
+        assert_eq!(attrs.get("name"), AttrValue::String("owner/repo"));
+        assert_eq!(attrs.get("linguist-language"), AttrValue::String("Rust"));
+
+        let attrs = resolve(&rule_sets, "lib/gen.rs");
+        assert_eq!(attrs.get("vendored"), AttrValue::Unspecified);
+        assert_eq!(attrs.get("diff"), AttrValue::False);
+        assert!(!attrs.is_set("linguist-generated"));
+
+        let attrs = resolve(&rule_sets, "src/deep/main.rs");
+        assert!(attrs.is_set("diff"));
+        assert!(!attrs.is_set("vendored"));
+        assert_eq!(attrs.get("macro"), AttrValue::Unspecified);
+    }
+
+    #[test]
+    fn nested_rules_only_apply_below_their_directory() {
+        let rule_sets = [RuleSet::parse("lib", "*.txt vendored\n").unwrap()];
+        assert!(resolve(&rule_sets, "lib/sub/a.txt").is_set("vendored"));
+        assert!(!resolve(&rule_sets, "a.txt").is_set("vendored"));
+        assert!(!resolve(&rule_sets, "library/a.txt").is_set("vendored"));
+    }
+}
@@ -1,4 +1,5 @@
 use clap::Parser;
+use std::path::PathBuf;
 
 #[derive(Parser)]
 #[command(name = "git-filter-tree")]
@@ -7,13 +8,38 @@ pub struct Cli {
     /// Tree-ish reference (commit, branch, tag, or tree SHA)
     pub treeish: String,
 
-    /// Gitattributes-style patterns to filter tree entries
-    #[arg(required = true)]
+    /// Gitattributes-style patterns to filter tree entries; prefix a pattern
+    /// with `!` to exclude the entries it matches
+    #[arg(required_unless_present = "patterns_from")]
     pub patterns: Vec<String>,
 
+    /// Read patterns from a file, or `-` for stdin, one per line; blank
+    /// lines and lines starting with `#` are skipped. They apply before any
+    /// patterns given as arguments
+    #[arg(long, value_name = "FILE")]
+    pub patterns_from: Option<PathBuf>,
+
     /// Output format
     #[arg(short, long, value_enum, default_value = "tree-sha")]
     pub format: OutputFormat,
+
+    /// Terminate output lines with NUL instead of newline, and print paths
+    /// unquoted
+    #[arg(short = 'z')]
+    pub nul: bool,
+
+    /// Repository to operate on instead of the current directory
+    #[arg(long, value_name = "PATH")]
+    pub repo: Option<PathBuf>,
+
+    /// Point this ref (e.g. refs/filtered/x) at the filtered tree
+    #[arg(long, value_name = "REF")]
+    pub write_ref: Option<String>,
+
+    /// With --write-ref, point the ref at a new commit of the filtered tree
+    /// instead, on top of the commit it pointed to
+    #[arg(long, requires = "write_ref")]
+    pub commit: bool,
 }
 
 #[derive(Clone, Copy, clap::ValueEnum)]
@@ -24,4 +50,75 @@ pub enum OutputFormat {
     Entries,
     /// Output detailed tree information
     Detailed,
+    /// Output every file in the layout of `git ls-tree -r`
+    LsTree,
+    /// Output a tar archive of the filtered files
+    Tar,
+    /// Output a zip archive of the filtered files
+    Zip,
+}
+
+/// Parse a patterns file: one pattern per line, skipping blank lines and `#`
+/// comments. A leading `\#` stands for a literal `#`.
+pub fn parse_patterns(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .map(|line| line.trim_end())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| match line.strip_prefix('\\') {
+            Some(rest) if rest.starts_with('#') => rest.to_string(),
+            _ => line.to_string(),
+        })
+        .collect()
+}
+
+/// Quote `path` the way git prints paths when `core.quotePath` is on:
+/// unchanged unless it holds a quote, backslash, control character or
+/// non-ASCII byte, in which case it is wrapped in quotes with C-style
+/// escapes.
+pub fn quote_path(path: &[u8]) -> String {
+    let needs_quoting = |b: u8| b == b'"' || b == b'\\' || !(0x20..0x7f).contains(&b);
+    if !path.iter().any(|&b| needs_quoting(b)) {
+        return String::from_utf8_lossy(path).into_owned();
+    }
+    let mut quoted = String::from("\"");
+    for &b in path {
+        match b {
+            0x07 => quoted.push_str("\\a"),
+            0x08 => quoted.push_str("\\b"),
+            b'\t' => quoted.push_str("\\t"),
+            b'\n' => quoted.push_str("\\n"),
+            0x0b => quoted.push_str("\\v"),
+            0x0c => quoted.push_str("\\f"),
+            b'\r' => quoted.push_str("\\r"),
+            b'"' => quoted.push_str("\\\""),
+            b'\\' => quoted.push_str("\\\\"),
+            b if needs_quoting(b) => quoted.push_str(&format!("\\{:03o}", b)),
+            b => quoted.push(b as char),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quote_path_escapes_like_git() {
+        assert_eq!(quote_path(b"src/lib.rs"), "src/lib.rs");
+        assert_eq!(quote_path(b"a\tb"), "\"a\\tb\"");
+        assert_eq!(quote_path(b"say \"hi\""), "\"say \\\"hi\\\"\"");
+        assert_eq!(quote_path("caf\u{e9}".as_bytes()), "\"caf\\303\\251\"");
+    }
+
+    #[test]
+    fn parse_patterns_skips_comments_and_blank_lines() {
+        let content = "# vendored sources\nsrc/\n\n!src/generated/  \n\\#literal\r\n";
+        assert_eq!(
+            parse_patterns(content),
+            ["src/", "!src/generated/", "#literal"]
+        );
+    }
 }
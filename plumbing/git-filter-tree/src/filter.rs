@@ -0,0 +1,212 @@
+//! Pattern matching for tree filters, and combining pattern sets into one filter.
+
+use crate::paths::bytes_to_path;
+use git2::Error;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use std::borrow::Cow;
+
+/// Patterns in order, where the last one matching a path decides whether it is kept.
+#[derive(Debug, Clone)]
+pub(crate) struct Matcher {
+    globs: GlobSet,
+    /// Whether each glob, by index, came from a `!` pattern.
+    negated: Vec<bool>,
+}
+
+impl Matcher {
+    /// Compile gitattributes-style `patterns`, where `!` negates a pattern,
+    /// matching without regard to case if `ignore_case` is set.
+    pub(crate) fn new(patterns: &[&str], ignore_case: bool) -> Result<Self, Error> {
+        let mut glob_builder = GlobSetBuilder::new();
+        let mut negated = Vec::with_capacity(patterns.len());
+        for pattern in patterns {
+            let (negate, pat) = match pattern.strip_prefix('!') {
+                Some(rest) => (true, rest),
+                None => (false, *pattern),
+            };
+            // A trailing `/` means "this directory" in gitattributes/gitignore
+            // semantics.  Normalize to `dir/**` so globset matches all files
+            // under the directory recursively.
+            let owned;
+            let pat = if pat.ends_with('/') {
+                owned = format!("{}**", pat);
+                owned.as_str()
+            } else {
+                pat
+            };
+            let glob = GlobBuilder::new(pat)
+                .case_insensitive(ignore_case)
+                .build()
+                .map_err(|e| Error::from_str(&format!("Invalid pattern '{}': {}", pattern, e)))?;
+            glob_builder.add(glob);
+            negated.push(negate);
+        }
+
+        let globs = glob_builder
+            .build()
+            .map_err(|e| Error::from_str(&e.to_string()))?;
+        Ok(Matcher { globs, negated })
+    }
+
+    pub(crate) fn is_match(&self, path: &[u8]) -> bool {
+        self.globs
+            .matches(bytes_to_path(path))
+            .into_iter()
+            .max()
+            .is_some_and(|last| !self.negated[last])
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Include,
+    Exclude,
+    Intersect,
+}
+
+/// A set of paths built by combining pattern sets, for [`FilterTree::filter_by`] to keep.
+///
+/// Operations apply in the order they are added, starting from no paths at all:
+///
+/// ```
+/// # use git_filter_tree::TreeFilter;
+/// let filter = TreeFilter::new()
+///     .include(&["src/", "include/"])?
+///     .exclude(&["src/generated/"])?
+///     .intersect(&["*.rs", "*.h"])?;
+/// assert!(filter.is_match("src/lib.rs"));
+/// assert!(!filter.is_match("src/generated/bindings.rs"));
+/// assert!(!filter.is_match("src/README.md"));
+/// # Ok::<(), git2::Error>(())
+/// ```
+///
+/// Each pattern set is matched like the patterns of [`FilterTree::filter_by_patterns`], `!`
+/// included.
+///
+/// [`FilterTree::filter_by`]: crate::FilterTree::filter_by
+/// [`FilterTree::filter_by_patterns`]: crate::FilterTree::filter_by_patterns
+#[derive(Debug, Clone, Default)]
+pub struct TreeFilter {
+    ops: Vec<(Op, Matcher)>,
+    ignore_case: bool,
+}
+
+impl TreeFilter {
+    /// A filter keeping no paths.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A filter keeping no paths that matches case-insensitively when `repo` sets
+    /// `core.ignorecase`, as Git does on case-insensitive filesystems.
+    pub fn for_repo(repo: &git2::Repository) -> Self {
+        let ignore_case = repo
+            .config()
+            .and_then(|config| config.get_bool("core.ignorecase"))
+            .unwrap_or(false);
+        Self::new().ignore_case(ignore_case)
+    }
+
+    /// Match the pattern sets added after this call without regard to case, or with it again.
+    pub fn ignore_case(mut self, ignore_case: bool) -> Self {
+        self.ignore_case = ignore_case;
+        self
+    }
+
+    /// Also keep the paths matching `patterns` (union).
+    pub fn include(self, patterns: &[&str]) -> Result<Self, Error> {
+        self.push(Op::Include, patterns)
+    }
+
+    /// Drop the paths matching `patterns` (difference).
+    pub fn exclude(self, patterns: &[&str]) -> Result<Self, Error> {
+        self.push(Op::Exclude, patterns)
+    }
+
+    /// Keep only the paths also matching `patterns` (intersection).
+    pub fn intersect(self, patterns: &[&str]) -> Result<Self, Error> {
+        self.push(Op::Intersect, patterns)
+    }
+
+    fn push(mut self, op: Op, patterns: &[&str]) -> Result<Self, Error> {
+        self.ops
+            .push((op, Matcher::new(patterns, self.ignore_case)?));
+        Ok(self)
+    }
+
+    /// Whether the filter keeps `path`, relative to the tree root.
+    /// Windows `\\` separators in `path` are read as `/`.
+    pub fn is_match(&self, path: &str) -> bool {
+        self.is_match_bytes(path.as_bytes())
+    }
+
+    /// Whether the filter keeps `path`, relative to the tree root, which need not be valid UTF-8.
+    pub fn is_match_bytes(&self, path: &[u8]) -> bool {
+        let path: Cow<[u8]> = if path.contains(&b'\\') {
+            path.iter()
+                .map(|&b| if b == b'\\' { b'/' } else { b })
+                .collect()
+        } else {
+            path.into()
+        };
+        let path = path.as_ref();
+        self.ops.iter().fold(false, |kept, (op, matcher)| match op {
+            Op::Include => kept || matcher.is_match(path),
+            Op::Exclude => kept && !matcher.is_match(path),
+            Op::Intersect => kept && matcher.is_match(path),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn operations_apply_in_order() {
+        let filter = TreeFilter::new()
+            .exclude(&["*"])
+            .unwrap()
+            .include(&["a/", "b/"])
+            .unwrap()
+            .intersect(&["*.rs"])
+            .unwrap()
+            .include(&["c.txt"])
+            .unwrap();
+        assert!(filter.is_match("a/x.rs"));
+        assert!(!filter.is_match("a/x.txt"));
+        assert!(filter.is_match("c.txt"));
+        assert!(!filter.is_match("d.rs"));
+
+        assert!(!TreeFilter::new().is_match("a/x.rs"));
+        assert!(TreeFilter::new().include(&["[invalid"]).is_err());
+    }
+
+    #[test]
+    fn ignore_case_applies_to_later_pattern_sets() {
+        let filter = TreeFilter::new()
+            .include(&["Vendor/"])
+            .unwrap()
+            .ignore_case(true)
+            .include(&["third_party/*.C"])
+            .unwrap();
+        assert!(filter.is_match("Vendor/a.c"));
+        assert!(!filter.is_match("vendor/a.c"));
+        assert!(filter.is_match("Third_Party/x.c"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_paths_match() {
+        let filter = TreeFilter::new().include(&["vendor/*.c"]).unwrap();
+        assert!(filter.is_match_bytes(b"vendor/caf\xe9.c"));
+        assert!(!filter.is_match_bytes(b"other/caf\xe9.c"));
+    }
+
+    #[test]
+    fn backslash_separators_match_as_slashes() {
+        let filter = TreeFilter::new().include(&["vendor/lib/"]).unwrap();
+        assert!(filter.is_match("vendor\\lib\\a.c"));
+        assert!(!filter.is_match("vendor\\other\\a.c"));
+    }
+}
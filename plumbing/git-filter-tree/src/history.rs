@@ -0,0 +1,96 @@
+//! Rewriting a range of commits so they only touch filtered paths.
+
+use crate::{FilterTree, TreeFilter};
+use git2::{Error, Oid, Repository, Sort};
+use std::collections::HashMap;
+
+/// Options for [`FilterTree::filter_history`].
+///
+/// [`FilterTree::filter_history`]: crate::FilterTree::filter_history
+#[derive(Debug, Clone, Default)]
+pub struct HistoryOptions {
+    /// Drop commits that change nothing the filter keeps, as `git filter-repo` does.
+    pub prune_empty: bool,
+    /// Rewritten commits from an earlier run, by original commit. Parents outside the range are
+    /// looked up here, so histories can be rewritten incrementally; parents found in neither are
+    /// dropped.
+    pub rewritten: HashMap<Oid, Option<Oid>>,
+}
+
+/// The outcome of [`FilterTree::filter_history`].
+///
+/// [`FilterTree::filter_history`]: crate::FilterTree::filter_history
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HistoryRewrite {
+    /// The new commit for each original commit, including those of
+    /// [`HistoryOptions::rewritten`]. A pruned commit maps to its nearest rewritten ancestor, or
+    /// `None` if it has none.
+    pub commits: HashMap<Oid, Option<Oid>>,
+    /// The new commit for the tip of the range.
+    pub head: Option<Oid>,
+}
+
+/// Rewrite the commits of `range` in `repo`; see [`FilterTree::filter_history`].
+///
+/// [`FilterTree::filter_history`]: crate::FilterTree::filter_history
+pub(crate) fn rewrite(
+    repo: &Repository,
+    range: &str,
+    filter: &TreeFilter,
+    opts: &HistoryOptions,
+) -> Result<HistoryRewrite, Error> {
+    let mut walk = repo.revwalk()?;
+    walk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+    let tip = if range.contains("..") {
+        walk.push_range(range)?;
+        repo.revparse(range)?.to().map(|object| object.id())
+    } else {
+        let tip = repo.revparse_single(range)?.peel_to_commit()?.id();
+        walk.push(tip)?;
+        Some(tip)
+    };
+
+    let mut commits = opts.rewritten.clone();
+    for oid in walk {
+        let commit = repo.find_commit(oid?)?;
+        let tree = commit.tree()?;
+        let filtered = repo.filter_by(&tree, filter)?;
+
+        let mut parents: Vec<Oid> = Vec::new();
+        for parent in commit.parent_ids() {
+            if let Some(Some(new)) = commits.get(&parent)
+                && !parents.contains(new)
+            {
+                parents.push(*new);
+            }
+        }
+
+        let rewritten = match parents.as_slice() {
+            [parent]
+                if opts.prune_empty && repo.find_commit(*parent)?.tree_id() == filtered.id() =>
+            {
+                Some(*parent)
+            }
+            [] if opts.prune_empty && filtered.is_empty() => None,
+            _ => {
+                let parents = parents
+                    .iter()
+                    .map(|parent| repo.find_commit(*parent))
+                    .collect::<Result<Vec<_>, Error>>()?;
+                let message = String::from_utf8_lossy(commit.message_raw_bytes()).into_owned();
+                Some(repo.commit(
+                    None,
+                    &commit.author(),
+                    &commit.committer(),
+                    &message,
+                    &filtered,
+                    &parents.iter().collect::<Vec<_>>(),
+                )?)
+            }
+        };
+        commits.insert(commit.id(), rewritten);
+    }
+
+    let head = tip.and_then(|tip| commits.get(&tip).copied().flatten());
+    Ok(HistoryRewrite { commits, head })
+}
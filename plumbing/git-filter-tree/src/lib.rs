@@ -1,19 +1,133 @@
 //! Provides a `FilterTree` trait, and an implementation for `git2::Repository`, which allows for pruning trees by Git pathspec patterns.
 
+#[cfg(feature = "archive")]
+pub use archive::ArchiveFormat;
+pub use attributes::Attributes;
+pub use filter::TreeFilter;
 pub use git2::{Error, Repository};
-use globset::{GlobSet, GlobSetBuilder};
+pub use history::{HistoryOptions, HistoryRewrite};
+pub use paths::{bytes_to_path, walk_tree};
 
+#[cfg(feature = "archive")]
+mod archive;
+mod attributes;
 #[cfg(feature = "cli")]
 pub mod cli;
+mod filter;
+mod history;
+#[cfg(feature = "parallel")]
+mod parallel;
+mod paths;
 
 pub trait FilterTree {
     /// Filters tree entries by gitattributes-style patterns and returns a new tree with contents filtered through the provided patterns.
     /// Recursively walks the tree and matches patterns against full paths from the tree root.
+    ///
+    /// As in gitignore, a pattern prefixed with `!` excludes the paths it matches, and the last
+    /// pattern matching a path decides whether it is kept: `["src/", "!src/generated/"]` keeps
+    /// everything under `src/` except `src/generated/`. A leading `\!` matches a literal `!`.
+    /// Patterns match case-insensitively when the repository sets `core.ignorecase`.
     fn filter_by_patterns<'a>(
         &'a self,
         tree: &'a git2::Tree<'a>,
         patterns: &[&str],
     ) -> Result<git2::Tree<'a>, Error>;
+
+    /// Filters tree entries through `filter`, which may combine several pattern sets, in a single
+    /// pass over the tree.
+    fn filter_by<'a>(
+        &'a self,
+        tree: &'a git2::Tree<'a>,
+        filter: &TreeFilter,
+    ) -> Result<git2::Tree<'a>, Error>;
+
+    /// Filters tree entries through `filter` like [`FilterTree::filter_by`], walking subtrees in
+    /// parallel on rayon's thread pool.
+    /// Each worker thread opens the repository again by path, so repositories whose objects
+    /// live only in memory cannot be filtered this way.
+    #[cfg(feature = "parallel")]
+    fn filter_by_parallel<'a>(
+        &'a self,
+        tree: &'a git2::Tree<'a>,
+        filter: &TreeFilter,
+    ) -> Result<git2::Tree<'a>, Error>;
+
+    /// Writes the entries of `tree` matching `patterns`, filtered as by
+    /// [`FilterTree::filter_by_patterns`], to `writer` as a tar or zip archive.
+    /// Executable bits and symbolic links are kept; submodules are left out.
+    #[cfg(feature = "archive")]
+    fn archive(
+        &self,
+        tree: &git2::Tree<'_>,
+        patterns: &[&str],
+        format: ArchiveFormat,
+        writer: &mut dyn std::io::Write,
+    ) -> Result<(), Error>;
+
+    /// Rewrites the commits of `range` (a commit, or `base..tip`) so that each one only holds the
+    /// paths `filter` keeps, with parents remapped to their rewritten commits.
+    /// Authors, committers and messages are kept. No refs are updated: the rewritten tip is
+    /// [`HistoryRewrite::head`].
+    fn filter_history(
+        &self,
+        range: &str,
+        filter: &TreeFilter,
+        opts: &HistoryOptions,
+    ) -> Result<HistoryRewrite, Error>;
+
+    /// Returns an in-memory index holding the entries of `index`, at every stage, whose paths
+    /// `filter` keeps.
+    /// Its tree can be written with [`git2::Index::write_tree_to`].
+    fn filter_index(&self, index: &git2::Index, filter: &TreeFilter) -> Result<git2::Index, Error>;
+
+    /// Lists the files in the working directory whose paths `filter` keeps: tracked files, and
+    /// untracked files not excluded by `.gitignore`, `.git/info/exclude` or `core.excludesFile`.
+    /// Paths are relative to the repository root, use `/` as separator, and are sorted.
+    fn workdir_paths(&self, filter: &TreeFilter) -> Result<Vec<String>, Error>;
+
+    /// Diffs `old` against `new` like [`git2::Repository::diff_tree_to_tree`], keeping only the
+    /// changes to paths `filter` keeps.
+    /// Unlike diffing two filtered trees, no filtered trees are written: unchanged subtrees are
+    /// skipped by object id, and only changed paths are matched against `filter`. Pathspecs set
+    /// in `opts` are replaced.
+    fn diff_filtered<'a>(
+        &'a self,
+        old: Option<&git2::Tree<'_>>,
+        new: Option<&git2::Tree<'_>>,
+        filter: &TreeFilter,
+        opts: Option<&mut git2::DiffOptions>,
+    ) -> Result<git2::Diff<'a>, Error>;
+
+    /// Filters tree entries by the attributes their paths carry and returns a new tree with the
+    /// files for which `predicate` returns `true`.
+    /// Attributes are read from the `.gitattributes` files in the tree itself, not the working
+    /// directory, so any commit's tree can be filtered as it was committed. `predicate` is given
+    /// each file's full path from the tree root along with its attributes:
+    ///
+    /// ```no_run
+    /// # use git_filter_tree::FilterTree;
+    /// # let repo = git2::Repository::open(".")?;
+    /// # let tree = repo.head()?.peel_to_tree()?;
+    /// let vendored = repo.filter_by_attributes(&tree, &|_, attrs| attrs.is_set("vendored"))?;
+    /// # Ok::<(), git2::Error>(())
+    /// ```
+    fn filter_by_attributes<'a>(
+        &'a self,
+        tree: &'a git2::Tree<'a>,
+        predicate: &dyn Fn(&str, &Attributes) -> bool,
+    ) -> Result<git2::Tree<'a>, Error>;
+
+    /// Returns a new tree holding the contents of the directory `from` of `tree` under the
+    /// directory `to` instead; everything outside `from` is left out.
+    /// An empty `from` takes the whole tree, and an empty `to` places the contents at the root.
+    /// The subtree at `from` is reused as is, so only the trees leading to `to` are written.
+    /// If `tree` has nothing at `from`, the result is empty.
+    fn rebase_prefix<'a>(
+        &'a self,
+        tree: &'a git2::Tree<'a>,
+        from: &str,
+        to: &str,
+    ) -> Result<git2::Tree<'a>, Error>;
 }
 
 impl FilterTree for git2::Repository {
@@ -26,31 +140,226 @@ impl FilterTree for git2::Repository {
             return Err(Error::from_str("At least one pattern is required"));
         }
 
-        // Build GlobSet matcher
-        let mut glob_builder = GlobSetBuilder::new();
-        for pattern in patterns {
-            // A trailing `/` means "this directory" in gitattributes/gitignore
-            // semantics.  Normalize to `dir/**` so globset matches all files
-            // under the directory recursively.
-            let owned;
-            let pat = if pattern.ends_with('/') {
-                owned = format!("{}**", pattern);
-                owned.as_str()
-            } else {
-                pattern
-            };
-            let glob = globset::Glob::new(pat)
-                .map_err(|e| Error::from_str(&format!("Invalid pattern '{}': {}", pattern, e)))?;
-            glob_builder.add(glob);
+        let filter = TreeFilter::for_repo(self).include(patterns)?;
+
+        // Recursively filter the tree
+        filter_tree_recursive(self, tree, b"", &filter)
+    }
+
+    fn filter_by<'a>(
+        &'a self,
+        tree: &'a git2::Tree<'a>,
+        filter: &TreeFilter,
+    ) -> Result<git2::Tree<'a>, Error> {
+        filter_tree_recursive(self, tree, b"", filter)
+    }
+
+    #[cfg(feature = "parallel")]
+    fn filter_by_parallel<'a>(
+        &'a self,
+        tree: &'a git2::Tree<'a>,
+        filter: &TreeFilter,
+    ) -> Result<git2::Tree<'a>, Error> {
+        let oid = match parallel::filter_tree(self.path(), tree.id(), b"", filter)? {
+            Some(oid) => oid,
+            None => self.treebuilder(None)?.write()?,
+        };
+        self.find_tree(oid)
+    }
+
+    #[cfg(feature = "archive")]
+    fn archive(
+        &self,
+        tree: &git2::Tree<'_>,
+        patterns: &[&str],
+        format: ArchiveFormat,
+        writer: &mut dyn std::io::Write,
+    ) -> Result<(), Error> {
+        let tree = self.find_tree(tree.id())?;
+        let filtered = self.filter_by_patterns(&tree, patterns)?;
+        archive::write(self, &filtered, format, writer)
+    }
+
+    fn filter_history(
+        &self,
+        range: &str,
+        filter: &TreeFilter,
+        opts: &HistoryOptions,
+    ) -> Result<HistoryRewrite, Error> {
+        history::rewrite(self, range, filter, opts)
+    }
+
+    fn filter_index(&self, index: &git2::Index, filter: &TreeFilter) -> Result<git2::Index, Error> {
+        let mut filtered = git2::Index::new()?;
+        for entry in index.iter() {
+            if filter.is_match_bytes(&entry.path) {
+                filtered.add(&entry)?;
+            }
         }
+        Ok(filtered)
+    }
 
-        let matcher = glob_builder
-            .build()
-            .map_err(|e| Error::from_str(&e.to_string()))?;
+    fn workdir_paths(&self, filter: &TreeFilter) -> Result<Vec<String>, Error> {
+        let workdir = self
+            .workdir()
+            .ok_or_else(|| Error::from_str("Repository has no working directory"))?;
+        let mut opts = git2::StatusOptions::new();
+        opts.include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .include_unmodified(true)
+            .include_ignored(false)
+            .exclude_submodules(true);
+
+        let mut paths: Vec<String> = self
+            .statuses(Some(&mut opts))?
+            .iter()
+            .filter_map(|status| status.path().map(|path| path.replace('\\', "/")))
+            .filter(|path| filter.is_match(path))
+            // Deleted files are still listed, so keep only those on disk
+            .filter(|path| workdir.join(path).symlink_metadata().is_ok())
+            .collect();
+        paths.sort();
+        paths.dedup();
+        Ok(paths)
+    }
 
-        // Recursively filter the tree
-        filter_tree_recursive(self, tree, "", &matcher)
+    fn diff_filtered<'a>(
+        &'a self,
+        old: Option<&git2::Tree<'_>>,
+        new: Option<&git2::Tree<'_>>,
+        filter: &TreeFilter,
+        opts: Option<&mut git2::DiffOptions>,
+    ) -> Result<git2::Diff<'a>, Error> {
+        // Find the changed paths first, then diff exactly the ones the filter keeps
+        let changed = self.diff_tree_to_tree(old, new, None)?;
+        let mut paths: Vec<&[u8]> = changed
+            .deltas()
+            .flat_map(|delta| [delta.old_file().path_bytes(), delta.new_file().path_bytes()])
+            .flatten()
+            .filter(|path| filter.is_match_bytes(path))
+            .collect();
+        paths.sort();
+        paths.dedup();
+
+        if paths.is_empty() {
+            // An empty pathspec would match everything; diff a tree against itself instead
+            return self.diff_tree_to_tree(old, old, None);
+        }
+        let mut own_opts = git2::DiffOptions::new();
+        let opts = opts.unwrap_or(&mut own_opts);
+        opts.disable_pathspec_match(true);
+        for path in paths {
+            opts.pathspec(path);
+        }
+        self.diff_tree_to_tree(old, new, Some(opts))
+    }
+
+    fn filter_by_attributes<'a>(
+        &'a self,
+        tree: &'a git2::Tree<'a>,
+        predicate: &dyn Fn(&str, &Attributes) -> bool,
+    ) -> Result<git2::Tree<'a>, Error> {
+        filter_attributes_recursive(self, tree, b"", &mut Vec::new(), predicate)
+    }
+
+    fn rebase_prefix<'a>(
+        &'a self,
+        tree: &'a git2::Tree<'a>,
+        from: &str,
+        to: &str,
+    ) -> Result<git2::Tree<'a>, Error> {
+        let from = prefix_components(from)?;
+        let to = prefix_components(to)?;
+
+        let mut oid = if from.is_empty() {
+            tree.id()
+        } else {
+            match tree.get_path(std::path::Path::new(&from.join("/"))) {
+                Ok(entry) if entry.kind() == Some(git2::ObjectType::Tree) => entry.id(),
+                Ok(_) => {
+                    return Err(Error::from_str(&format!(
+                        "'{}' is not a directory",
+                        from.join("/")
+                    )));
+                }
+                Err(e) if e.code() == git2::ErrorCode::NotFound => {
+                    let empty = self.treebuilder(None)?.write()?;
+                    return self.find_tree(empty);
+                }
+                Err(e) => return Err(e),
+            }
+        };
+
+        // Wrap the subtree in one new tree per component of `to`, innermost first
+        for component in to.iter().rev() {
+            let mut builder = self.treebuilder(None)?;
+            builder.insert(component, oid, 0o040000)?;
+            oid = builder.write()?;
+        }
+        self.find_tree(oid)
+    }
+}
+
+/// Splits a directory prefix such as `a/b/` into its components, rejecting `.` and `..`.
+fn prefix_components(prefix: &str) -> Result<Vec<&str>, Error> {
+    let components: Vec<&str> = prefix.split('/').filter(|c| !c.is_empty()).collect();
+    if components.iter().any(|c| *c == "." || *c == "..") {
+        return Err(Error::from_str(&format!("Invalid prefix '{}'", prefix)));
+    }
+    Ok(components)
+}
+
+/// Recursively filters a tree by attributes, with `rule_sets` holding the parsed
+/// `.gitattributes` files of the directories above it.
+fn filter_attributes_recursive<'a>(
+    repo: &'a Repository,
+    tree: &git2::Tree<'_>,
+    prefix: &[u8],
+    rule_sets: &mut Vec<attributes::RuleSet>,
+    predicate: &dyn Fn(&str, &Attributes) -> bool,
+) -> Result<git2::Tree<'a>, Error> {
+    let pushed = match tree.get_name(".gitattributes") {
+        Some(entry) if entry.kind() == Some(git2::ObjectType::Blob) => {
+            let blob = repo.find_blob(entry.id())?;
+            let content = String::from_utf8_lossy(blob.content());
+            let prefix = String::from_utf8_lossy(prefix);
+            rule_sets.push(attributes::RuleSet::parse(&prefix, &content)?);
+            true
+        }
+        _ => false,
+    };
+
+    let mut builder = repo.treebuilder(None)?;
+    for entry in tree.iter() {
+        let name = entry.name_bytes();
+        let full_path = paths::join(prefix, name);
+
+        match entry.kind() {
+            Some(git2::ObjectType::Blob) => {
+                // Attribute patterns are text, so match names that are not UTF-8 approximately
+                let full_path = String::from_utf8_lossy(&full_path);
+                if predicate(&full_path, &attributes::resolve(rule_sets, &full_path)) {
+                    builder.insert(name, entry.id(), entry.filemode())?;
+                }
+            }
+            Some(git2::ObjectType::Tree) => {
+                let subtree = repo.find_tree(entry.id())?;
+                let filtered =
+                    filter_attributes_recursive(repo, &subtree, &full_path, rule_sets, predicate)?;
+                // Only include the subtree if it has matching entries
+                if !filtered.is_empty() {
+                    builder.insert(name, filtered.id(), entry.filemode())?;
+                }
+            }
+            _ => continue,
+        }
+    }
+
+    if pushed {
+        rule_sets.pop();
     }
+    let tree_oid = builder.write()?;
+    repo.find_tree(tree_oid)
 }
 
 /// Recursively filters a tree, matching patterns against full paths.
@@ -58,30 +367,26 @@ impl FilterTree for git2::Repository {
 fn filter_tree_recursive<'a>(
     repo: &'a Repository,
     tree: &'a git2::Tree<'a>,
-    prefix: &str,
-    matcher: &GlobSet,
+    prefix: &[u8],
+    filter: &TreeFilter,
 ) -> Result<git2::Tree<'a>, Error> {
     let mut builder = repo.treebuilder(None)?;
 
     for entry in tree.iter() {
-        let name = entry.name().unwrap_or("");
-        let full_path = if prefix.is_empty() {
-            name.to_string()
-        } else {
-            format!("{}/{}", prefix, name)
-        };
+        let name = entry.name_bytes();
+        let full_path = paths::join(prefix, name);
 
         match entry.kind() {
             Some(git2::ObjectType::Blob) => {
                 // Check if this file matches the pattern
-                if matcher.is_match(&full_path) {
+                if filter.is_match_bytes(&full_path) {
                     builder.insert(name, entry.id(), entry.filemode())?;
                 }
             }
             Some(git2::ObjectType::Tree) => {
                 // Recursively filter the subtree
                 let subtree = entry.to_object(repo)?.peel_to_tree()?;
-                match filter_tree_recursive(repo, &subtree, &full_path, matcher) {
+                match filter_tree_recursive(repo, &subtree, &full_path, filter) {
                     Ok(filtered_subtree) => {
                         // Only include the subtree if it has matching entries
                         if !filtered_subtree.is_empty() {
@@ -108,6 +413,7 @@ fn filter_tree_recursive<'a>(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use git2::Oid;
     use std::fs;
     use std::path::PathBuf;
 
@@ -400,4 +706,439 @@ mod tests {
         cleanup_test_repo(temp_path);
         Ok(())
     }
+
+    #[test]
+    fn test_filter_by_attributes() -> Result<(), Error> {
+        let (repo, temp_path) = setup_test_repo();
+
+        // .gitattributes, README.md, lib/{.gitattributes,a.rs,gen.rs}
+        let blob = repo.blob(b"content")?;
+        let root_attrs = repo.blob(b"lib/ vendored name=owner/repo\n")?;
+        let lib_attrs = repo.blob(b"gen.rs -vendored linguist-generated\n")?;
+
+        let mut lib_builder = repo.treebuilder(None)?;
+        lib_builder.insert(".gitattributes", lib_attrs, 0o100644)?;
+        lib_builder.insert("a.rs", blob, 0o100644)?;
+        lib_builder.insert("gen.rs", blob, 0o100644)?;
+        let lib_oid = lib_builder.write()?;
+
+        let mut root_builder = repo.treebuilder(None)?;
+        root_builder.insert(".gitattributes", root_attrs, 0o100644)?;
+        root_builder.insert("README.md", blob, 0o100644)?;
+        root_builder.insert("lib", lib_oid, 0o040000)?;
+        let tree = repo.find_tree(root_builder.write()?)?;
+
+        let filtered = repo.filter_by_attributes(&tree, &|_, attrs| attrs.is_set("vendored"))?;
+        assert_eq!(filtered.len(), 1);
+        let lib = repo.find_tree(filtered.get_name("lib").unwrap().id())?;
+        let names: Vec<_> = lib.iter().map(|e| e.name().unwrap().to_string()).collect();
+        assert_eq!(names, [".gitattributes", "a.rs"]);
+
+        let filtered = repo.filter_by_attributes(&tree, &|path, attrs| {
+            path.starts_with("lib/") && attrs.is_set("linguist-generated")
+        })?;
+        let lib = repo.find_tree(filtered.get_name("lib").unwrap().id())?;
+        assert_eq!(lib.len(), 1);
+        assert!(lib.get_name("gen.rs").is_some());
+
+        cleanup_test_repo(temp_path);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rebase_prefix() -> Result<(), Error> {
+        let (repo, temp_path) = setup_test_repo();
+
+        // src/lib.rs, README.md
+        let blob = repo.blob(b"content")?;
+        let mut src_builder = repo.treebuilder(None)?;
+        src_builder.insert("lib.rs", blob, 0o100644)?;
+        let src_oid = src_builder.write()?;
+        let mut root_builder = repo.treebuilder(None)?;
+        root_builder.insert("src", src_oid, 0o040000)?;
+        root_builder.insert("README.md", blob, 0o100644)?;
+        let tree = repo.find_tree(root_builder.write()?)?;
+
+        // The moved subtree keeps its object id.
+        let rebased = repo.rebase_prefix(&tree, "src/", "vendor/owner/repo")?;
+        assert_eq!(rebased.len(), 1);
+        let moved = rebased.get_path(std::path::Path::new("vendor/owner/repo"))?;
+        assert_eq!(moved.id(), src_oid);
+
+        let rebased = repo.rebase_prefix(&tree, "src", "")?;
+        assert_eq!(rebased.id(), src_oid);
+        let rebased = repo.rebase_prefix(&tree, "", "lib")?;
+        assert_eq!(rebased.get_name("lib").unwrap().id(), tree.id());
+        assert!(repo.rebase_prefix(&tree, "missing", "lib")?.is_empty());
+
+        assert!(repo.rebase_prefix(&tree, "README.md", "lib").is_err());
+        assert!(repo.rebase_prefix(&tree, "src", "../lib").is_err());
+
+        cleanup_test_repo(temp_path);
+        Ok(())
+    }
+
+    #[test]
+    fn test_filter_by_tree_filter() -> Result<(), Error> {
+        let (repo, temp_path) = setup_test_repo();
+
+        let tree = create_test_tree(&repo)?;
+        let filter = TreeFilter::new()
+            .include(&["file*"])?
+            .include(&["*.md"])?
+            .exclude(&["*.rs"])?;
+        let filtered = repo.filter_by(&tree, &filter)?;
+        assert_eq!(filtered.len(), 2);
+        assert!(filtered.get_name("file1.txt").is_some());
+        assert!(filtered.get_name("test.md").is_some());
+
+        let filter = filter.intersect(&["*.txt"])?;
+        let filtered = repo.filter_by(&tree, &filter)?;
+        assert_eq!(filtered.len(), 1);
+        assert!(filtered.get_name("file1.txt").is_some());
+
+        cleanup_test_repo(temp_path);
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff_filtered() -> Result<(), Error> {
+        let (repo, temp_path) = setup_test_repo();
+
+        let old = create_test_tree(&repo)?;
+        let mut builder = repo.treebuilder(Some(&old))?;
+        builder.insert("file1.txt", repo.blob(b"changed")?, 0o100644)?;
+        builder.insert("file2.rs", repo.blob(b"changed")?, 0o100644)?;
+        builder.insert("file3.txt", repo.blob(b"new")?, 0o100644)?;
+        builder.remove("test.md")?;
+        let new = repo.find_tree(builder.write()?)?;
+
+        let filter = TreeFilter::new().include(&["*.txt", "*.md"])?;
+        let diff = repo.diff_filtered(Some(&old), Some(&new), &filter, None)?;
+        let paths: Vec<_> = diff
+            .deltas()
+            .map(|d| {
+                (
+                    d.status(),
+                    d.new_file().path().unwrap().to_str().unwrap().to_string(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                (git2::Delta::Modified, "file1.txt".to_string()),
+                (git2::Delta::Added, "file3.txt".to_string()),
+                (git2::Delta::Deleted, "test.md".to_string()),
+            ]
+        );
+
+        let filter = TreeFilter::new().include(&["*.json"])?;
+        let diff = repo.diff_filtered(Some(&old), Some(&new), &filter, None)?;
+        assert_eq!(diff.deltas().len(), 0);
+
+        cleanup_test_repo(temp_path);
+        Ok(())
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_filter_by_parallel_matches_sequential() -> Result<(), Error> {
+        let (repo, temp_path) = setup_test_repo();
+
+        // dir0..dir7, each with sub0..sub3 holding a.rs, b.txt and c.md
+        let mut root_builder = repo.treebuilder(None)?;
+        for d in 0..8 {
+            let mut dir_builder = repo.treebuilder(None)?;
+            for s in 0..4 {
+                let mut sub_builder = repo.treebuilder(None)?;
+                for name in ["a.rs", "b.txt", "c.md"] {
+                    let blob = repo.blob(format!("{d}/{s}/{name}").as_bytes())?;
+                    sub_builder.insert(name, blob, 0o100644)?;
+                }
+                dir_builder.insert(format!("sub{s}"), sub_builder.write()?, 0o040000)?;
+            }
+            root_builder.insert(format!("dir{d}"), dir_builder.write()?, 0o040000)?;
+        }
+        let tree = repo.find_tree(root_builder.write()?)?;
+
+        let filter = TreeFilter::new()
+            .include(&["dir1/", "dir2/**/*.rs", "*.md"])?
+            .exclude(&["dir1/sub0/"])?;
+        let sequential = repo.filter_by(&tree, &filter)?;
+        let parallel = repo.filter_by_parallel(&tree, &filter)?;
+        assert!(!sequential.is_empty());
+        assert_eq!(parallel.id(), sequential.id());
+
+        let nothing = TreeFilter::new().include(&["*.json"])?;
+        assert!(repo.filter_by_parallel(&tree, &nothing)?.is_empty());
+
+        cleanup_test_repo(temp_path);
+        Ok(())
+    }
+
+    #[test]
+    fn test_filter_index_and_workdir_paths() -> Result<(), Error> {
+        let thread_id = std::thread::current().id();
+        let temp_path =
+            std::env::temp_dir().join(format!("git-filter-tree-workdir-test-{:?}", thread_id));
+        let _ = fs::remove_dir_all(&temp_path);
+        let repo = Repository::init(&temp_path)?;
+
+        for (path, content) in [
+            ("src/lib.rs", "lib"),
+            ("src/gen/out.rs", "gen"),
+            ("README.md", "readme"),
+            (".gitignore", "*.log\n"),
+        ] {
+            let full = temp_path.join(path);
+            fs::create_dir_all(full.parent().unwrap()).unwrap();
+            fs::write(full, content).unwrap();
+        }
+        let mut index = repo.index()?;
+        index.add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)?;
+        index.write()?;
+        // Untracked, ignored, and deleted but still indexed
+        fs::write(temp_path.join("src/new.rs"), "new").unwrap();
+        fs::write(temp_path.join("src/debug.log"), "log").unwrap();
+        fs::remove_file(temp_path.join("src/gen/out.rs")).unwrap();
+
+        let filter = TreeFilter::new().include(&["src/"])?;
+        let mut filtered = repo.filter_index(&index, &filter)?;
+        let paths: Vec<_> = filtered
+            .iter()
+            .map(|e| String::from_utf8(e.path).unwrap())
+            .collect();
+        assert_eq!(paths, ["src/gen/out.rs", "src/lib.rs"]);
+        let tree = repo.find_tree(filtered.write_tree_to(&repo)?)?;
+        assert!(tree.get_name("README.md").is_none());
+
+        assert_eq!(repo.workdir_paths(&filter)?, ["src/lib.rs", "src/new.rs"]);
+
+        cleanup_test_repo(temp_path);
+        Ok(())
+    }
+
+    #[cfg(feature = "archive")]
+    #[test]
+    fn test_archive() -> Result<(), Error> {
+        let (repo, temp_path) = setup_test_repo();
+
+        let blob = repo.blob(b"#!/bin/sh\n")?;
+        let target = repo.blob(b"run.sh")?;
+        let mut bin_builder = repo.treebuilder(None)?;
+        bin_builder.insert("run.sh", blob, 0o100755)?;
+        bin_builder.insert("link", target, 0o120000)?;
+        let mut root_builder = repo.treebuilder(None)?;
+        root_builder.insert("bin", bin_builder.write()?, 0o040000)?;
+        root_builder.insert("README.md", blob, 0o100644)?;
+        let tree = repo.find_tree(root_builder.write()?)?;
+
+        let mut tar = Vec::new();
+        repo.archive(&tree, &["bin/"], ArchiveFormat::Tar, &mut tar)?;
+        let mut archive = tar::Archive::new(tar.as_slice());
+        let entries: Vec<_> = archive
+            .entries()
+            .unwrap()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                let header = entry.header();
+                (
+                    entry.path().unwrap().display().to_string(),
+                    header.mode().unwrap(),
+                    header.entry_type(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                ("bin/".to_string(), 0o755, tar::EntryType::Directory),
+                ("bin/link".to_string(), 0o777, tar::EntryType::Symlink),
+                ("bin/run.sh".to_string(), 0o755, tar::EntryType::Regular),
+            ]
+        );
+
+        let mut zip = Vec::new();
+        repo.archive(&tree, &["*.md"], ArchiveFormat::Zip, &mut zip)?;
+        assert!(zip.starts_with(b"PK\x03\x04"));
+
+        cleanup_test_repo(temp_path);
+        Ok(())
+    }
+
+    #[test]
+    fn test_filter_history() -> Result<(), Error> {
+        let (repo, temp_path) = setup_test_repo();
+
+        let sig = git2::Signature::now("Test", "test@test")?;
+        let commit =
+            |files: &[(&str, &str)], parents: &[Oid], message: &str| -> Result<Oid, Error> {
+                let mut index = git2::Index::new()?;
+                for (path, content) in files {
+                    let blob = repo.blob(content.as_bytes())?;
+                    let mut entry = git2::IndexEntry {
+                        ctime: git2::IndexTime::new(0, 0),
+                        mtime: git2::IndexTime::new(0, 0),
+                        dev: 0,
+                        ino: 0,
+                        mode: 0o100644,
+                        uid: 0,
+                        gid: 0,
+                        file_size: 0,
+                        id: blob,
+                        flags: 0,
+                        flags_extended: 0,
+                        path: path.as_bytes().to_vec(),
+                    };
+                    entry.flags = path.len() as u16;
+                    index.add(&entry)?;
+                }
+                let tree = repo.find_tree(index.write_tree_to(&repo)?)?;
+                let parents = parents
+                    .iter()
+                    .map(|p| repo.find_commit(*p))
+                    .collect::<Result<Vec<_>, Error>>()?;
+                repo.commit(
+                    None,
+                    &sig,
+                    &sig,
+                    message,
+                    &tree,
+                    &parents.iter().collect::<Vec<_>>(),
+                )
+            };
+
+        let a = commit(&[("README.md", "1")], &[], "readme")?;
+        let b = commit(&[("README.md", "1"), ("lib/a.rs", "1")], &[a], "add lib")?;
+        let c = commit(
+            &[("README.md", "2"), ("lib/a.rs", "1")],
+            &[b],
+            "edit readme",
+        )?;
+        let d = commit(&[("README.md", "2"), ("lib/a.rs", "2")], &[c], "edit lib")?;
+
+        let filter = TreeFilter::new().include(&["lib/"])?;
+        let opts = HistoryOptions {
+            prune_empty: true,
+            ..Default::default()
+        };
+        let rewrite = repo.filter_history(&d.to_string(), &filter, &opts)?;
+        assert_eq!(rewrite.commits[&a], None);
+        assert_eq!(rewrite.commits[&c], rewrite.commits[&b]);
+        let head = repo.find_commit(rewrite.head.unwrap())?;
+        assert_eq!(head.message(), Some("edit lib"));
+        assert!(head.tree()?.get_name("README.md").is_none());
+        let parent = head.parent(0)?;
+        assert_eq!(parent.message(), Some("add lib"));
+        assert_eq!(parent.parent_count(), 0);
+
+        // Continuing from the earlier rewrite only rewrites the new commits.
+        let e = commit(
+            &[("README.md", "2"), ("lib/a.rs", "3")],
+            &[d],
+            "edit lib again",
+        )?;
+        let opts = HistoryOptions {
+            prune_empty: true,
+            rewritten: rewrite.commits.clone(),
+        };
+        let next = repo.filter_history(&format!("{d}..{e}"), &filter, &opts)?;
+        let head = repo.find_commit(next.head.unwrap())?;
+        assert_eq!(head.parent_id(0)?, rewrite.head.unwrap());
+
+        // Without pruning, every commit is kept.
+        let all = repo.filter_history(&d.to_string(), &filter, &HistoryOptions::default())?;
+        let mut count = 0;
+        let mut commit = repo.find_commit(all.head.unwrap())?;
+        while let Ok(parent) = commit.parent(0) {
+            count += 1;
+            commit = parent;
+        }
+        assert_eq!(count, 3);
+
+        cleanup_test_repo(temp_path);
+        Ok(())
+    }
+
+    #[test]
+    fn test_filter_negated_patterns() -> Result<(), Error> {
+        let (repo, temp_path) = setup_test_repo();
+
+        // src/lib.rs, src/generated/bindings.rs, src/generated/keep.rs
+        let blob = repo.blob(b"content")?;
+
+        let mut generated_builder = repo.treebuilder(None)?;
+        generated_builder.insert("bindings.rs", blob, 0o100644)?;
+        generated_builder.insert("keep.rs", blob, 0o100644)?;
+        let generated_oid = generated_builder.write()?;
+
+        let mut src_builder = repo.treebuilder(None)?;
+        src_builder.insert("lib.rs", blob, 0o100644)?;
+        src_builder.insert("generated", generated_oid, 0o040000)?;
+        let src_oid = src_builder.write()?;
+
+        let mut root_builder = repo.treebuilder(None)?;
+        root_builder.insert("src", src_oid, 0o040000)?;
+        let tree = repo.find_tree(root_builder.write()?)?;
+
+        // Excluding a directory drops it entirely.
+        let filtered = repo.filter_by_patterns(&tree, &["src/", "!src/generated/"])?;
+        let src = repo.find_tree(filtered.get_name("src").unwrap().id())?;
+        assert!(src.get_name("lib.rs").is_some());
+        assert!(src.get_name("generated").is_none());
+
+        // A later pattern re-includes what an earlier one excluded.
+        let filtered =
+            repo.filter_by_patterns(&tree, &["src/", "!src/generated/", "src/generated/keep.rs"])?;
+        let src = repo.find_tree(filtered.get_name("src").unwrap().id())?;
+        let generated = repo.find_tree(src.get_name("generated").unwrap().id())?;
+        assert_eq!(generated.len(), 1);
+        assert!(generated.get_name("keep.rs").is_some());
+
+        // Only negated patterns keep nothing.
+        let filtered = repo.filter_by_patterns(&tree, &["!src/generated/"])?;
+        assert_eq!(filtered.len(), 0);
+
+        cleanup_test_repo(temp_path);
+        Ok(())
+    }
+
+    #[test]
+    fn test_filter_non_utf8_names() -> Result<(), Error> {
+        let (repo, temp_path) = setup_test_repo();
+
+        // vendor/caf\xe9/a.c, vendor/b\xff.c, other.c
+        let blob = repo.blob(b"content")?;
+        let mut dir_builder = repo.treebuilder(None)?;
+        dir_builder.insert(b"a.c".as_slice(), blob, 0o100644)?;
+        let mut vendor_builder = repo.treebuilder(None)?;
+        vendor_builder.insert(b"caf\xe9".as_slice(), dir_builder.write()?, 0o040000)?;
+        vendor_builder.insert(b"b\xff.c".as_slice(), blob, 0o100644)?;
+        let mut root_builder = repo.treebuilder(None)?;
+        root_builder.insert("vendor", vendor_builder.write()?, 0o040000)?;
+        root_builder.insert("other.c", blob, 0o100644)?;
+        let tree = repo.find_tree(root_builder.write()?)?;
+
+        let filtered = repo.filter_by_patterns(&tree, &["vendor/"])?;
+        let mut paths = Vec::new();
+        walk_tree(&repo, &filtered, |path, entry| {
+            if entry.kind() == Some(git2::ObjectType::Blob) {
+                paths.push(path.to_vec());
+            }
+            Ok(())
+        })?;
+        assert_eq!(
+            paths,
+            [b"vendor/b\xff.c".to_vec(), b"vendor/caf\xe9/a.c".to_vec()]
+        );
+
+        let filtered = repo.filter_by(&tree, &TreeFilter::new().include(&["vendor/b*"])?)?;
+        let vendor = repo.find_tree(filtered.get_name("vendor").unwrap().id())?;
+        let names: Vec<_> = vendor.iter().map(|e| e.name_bytes().to_vec()).collect();
+        assert_eq!(names, [b"b\xff.c".to_vec()]);
+
+        cleanup_test_repo(temp_path);
+        Ok(())
+    }
 }
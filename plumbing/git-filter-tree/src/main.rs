@@ -4,6 +4,8 @@ use clap::Parser;
 use cli::{Cli, OutputFormat};
 use git_filter_tree::FilterTree;
 use git2 as git;
+use std::io::{Read, Write};
+use std::path::Path;
 use std::process;
 
 fn main() {
@@ -16,54 +18,135 @@ fn main() {
 fn run() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
 
-    // Open the repository in current directory
-    let repo = git::Repository::open(".")?;
+    // Open the repository given, or the one in the current directory
+    let repo = git::Repository::open(cli.repo.as_deref().unwrap_or(Path::new(".")))?;
 
     // Resolve the tree-ish to a tree
     let obj = repo.revparse_single(&cli.treeish)?;
     let tree = obj.peel_to_tree()?;
 
+    // Read patterns from a file or stdin, ahead of those given as arguments
+    let mut from_file = Vec::new();
+    if let Some(path) = &cli.patterns_from {
+        let content = if path.as_os_str() == "-" {
+            let mut content = String::new();
+            std::io::stdin().read_to_string(&mut content)?;
+            content
+        } else {
+            std::fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+        };
+        from_file = cli::parse_patterns(&content);
+    }
+
     // Convert patterns to string slices
-    let patterns: Vec<&str> = cli.patterns.iter().map(|s| s.as_str()).collect();
+    let patterns: Vec<&str> = from_file
+        .iter()
+        .chain(&cli.patterns)
+        .map(|s| s.as_str())
+        .collect();
 
     // Filter the tree by patterns
     let filtered_tree = repo.filter_by_patterns(&tree, &patterns)?;
 
-    // Output based on format
+    if let Some(ref_name) = &cli.write_ref {
+        let message = format!("filter-tree: {} {}", cli.treeish, patterns.join(" "));
+        let target = if cli.commit {
+            // Stack commits on the ref so it records each filtered result
+            let parent = repo
+                .find_reference(ref_name)
+                .ok()
+                .and_then(|reference| reference.peel_to_commit().ok());
+            let signature = repo.signature()?;
+            repo.commit(
+                None,
+                &signature,
+                &signature,
+                &message,
+                &filtered_tree,
+                &parent.iter().collect::<Vec<_>>(),
+            )?
+        } else {
+            filtered_tree.id()
+        };
+        repo.reference(ref_name, target, true, &message)?;
+    }
+
+    // Output based on format, one record per line, or per NUL with -z
+    let eol: &[u8] = if cli.nul { b"\0" } else { b"\n" };
+    let mut out = std::io::stdout().lock();
     match cli.format {
         OutputFormat::TreeSha => {
-            println!("{}", filtered_tree.id());
+            write!(out, "{}", filtered_tree.id())?;
+            out.write_all(eol)?;
         }
         OutputFormat::Entries => {
             for entry in filtered_tree.iter() {
-                let name = entry.name().unwrap_or("<invalid-utf8>");
-                let kind = match entry.kind() {
-                    Some(git::ObjectType::Blob) => "blob",
-                    Some(git::ObjectType::Tree) => "tree",
-                    Some(git::ObjectType::Commit) => "commit",
-                    _ => "unknown",
-                };
-                println!("{}\t{}", kind, name);
+                write!(out, "{}\t", kind_name(entry.kind()))?;
+                out.write_all(entry.name_bytes())?;
+                out.write_all(eol)?;
             }
         }
         OutputFormat::Detailed => {
-            println!("Tree: {}", filtered_tree.id());
-            println!("Entries: {}", filtered_tree.len());
-            println!();
+            write!(out, "Tree: {}", filtered_tree.id())?;
+            out.write_all(eol)?;
+            write!(out, "Entries: {}", filtered_tree.len())?;
+            out.write_all(eol)?;
+            out.write_all(eol)?;
             for entry in filtered_tree.iter() {
-                let name = entry.name().unwrap_or("<invalid-utf8>");
-                let kind = match entry.kind() {
-                    Some(git::ObjectType::Blob) => "blob",
-                    Some(git::ObjectType::Tree) => "tree",
-                    Some(git::ObjectType::Commit) => "commit",
-                    _ => "unknown",
-                };
                 let mode = entry.filemode();
                 let id = entry.id();
-                println!("{:06o} {} {}\t{}", mode, kind, id, name);
+                write!(out, "{:06o} {} {}\t", mode, kind_name(entry.kind()), id)?;
+                out.write_all(entry.name_bytes())?;
+                out.write_all(eol)?;
             }
         }
+        OutputFormat::Tar | OutputFormat::Zip => {
+            let format = match cli.format {
+                OutputFormat::Tar => git_filter_tree::ArchiveFormat::Tar,
+                _ => git_filter_tree::ArchiveFormat::Zip,
+            };
+            repo.archive(&filtered_tree, &["*"], format, &mut out)?;
+        }
+        OutputFormat::LsTree => {
+            // `git ls-tree -r` lists every non-tree entry with its full path
+            let mut result = Ok(());
+            let walked = git_filter_tree::walk_tree(&repo, &filtered_tree, |path, entry| {
+                if entry.kind() == Some(git::ObjectType::Tree) {
+                    return Ok(());
+                }
+                let line = write!(
+                    out,
+                    "{:06o} {} {}\t",
+                    entry.filemode(),
+                    kind_name(entry.kind()),
+                    entry.id()
+                )
+                .and_then(|()| match cli.nul {
+                    true => out.write_all(path),
+                    false => out.write_all(cli::quote_path(path).as_bytes()),
+                })
+                .and_then(|()| out.write_all(eol));
+                line.map_err(|e| {
+                    result = Err(e);
+                    git::Error::from_str("Failed to write the listing")
+                })
+            });
+            // An aborted walk fails too, so report the write error first
+            result?;
+            walked?;
+        }
     }
 
     Ok(())
 }
+
+/// The name git uses for an object type in tree listings.
+fn kind_name(kind: Option<git::ObjectType>) -> &'static str {
+    match kind {
+        Some(git::ObjectType::Blob) => "blob",
+        Some(git::ObjectType::Tree) => "tree",
+        Some(git::ObjectType::Commit) => "commit",
+        _ => "unknown",
+    }
+}
@@ -0,0 +1,90 @@
+//! Filtering trees on all cores with rayon.
+//!
+//! A `git2::Repository` cannot be shared between threads, so every worker thread opens its own
+//! handle on the repository and keeps it for later calls. Each subtree is a separate task, which
+//! lets rayon split the work wherever the tree is widest.
+
+use crate::{TreeFilter, paths};
+use git2::{Error, ObjectType, Oid, Repository};
+use rayon::prelude::*;
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+thread_local! {
+    static REPOSITORY: RefCell<Option<(PathBuf, Rc<Repository>)>> = const { RefCell::new(None) };
+}
+
+/// This thread's handle on the repository at `path`.
+fn repository(path: &Path) -> Result<Rc<Repository>, Error> {
+    REPOSITORY.with(|cell| {
+        let mut cached = cell.borrow_mut();
+        if let Some((cached_path, repo)) = cached.as_ref()
+            && cached_path == path
+        {
+            return Ok(Rc::clone(repo));
+        }
+        let repo = Rc::new(Repository::open(path)?);
+        *cached = Some((path.to_path_buf(), Rc::clone(&repo)));
+        Ok(repo)
+    })
+}
+
+/// An entry of a tree, owned so it can cross threads.
+struct Entry {
+    name: Vec<u8>,
+    id: Oid,
+    kind: Option<ObjectType>,
+    filemode: i32,
+}
+
+/// Filters the tree `tree` of the repository at `path`, returning the id of the filtered tree, or
+/// `None` if nothing in it matches.
+pub(crate) fn filter_tree(
+    path: &Path,
+    tree: Oid,
+    prefix: &[u8],
+    filter: &TreeFilter,
+) -> Result<Option<Oid>, Error> {
+    // git2 objects cannot cross threads, so copy the entries out first
+    let entries: Vec<Entry> = {
+        let repo = repository(path)?;
+        let tree = repo.find_tree(tree)?;
+        tree.iter()
+            .map(|entry| Entry {
+                name: entry.name_bytes().to_vec(),
+                id: entry.id(),
+                kind: entry.kind(),
+                filemode: entry.filemode(),
+            })
+            .collect()
+    };
+
+    let kept: Vec<Option<(&Entry, Oid)>> = entries
+        .par_iter()
+        .map(|entry| {
+            let full_path = paths::join(prefix, &entry.name);
+            match entry.kind {
+                Some(ObjectType::Blob) if filter.is_match_bytes(&full_path) => {
+                    Some((entry, entry.id))
+                }
+                Some(ObjectType::Tree) => {
+                    // Skip subtrees that cause errors, as the sequential walk does
+                    let filtered = filter_tree(path, entry.id, &full_path, filter).ok()??;
+                    Some((entry, filtered))
+                }
+                _ => None,
+            }
+        })
+        .collect();
+
+    let repo = repository(path)?;
+    let mut builder = repo.treebuilder(None)?;
+    for (entry, id) in kept.into_iter().flatten() {
+        builder.insert(entry.name.as_slice(), id, entry.filemode)?;
+    }
+    if builder.is_empty() {
+        return Ok(None);
+    }
+    builder.write().map(Some)
+}
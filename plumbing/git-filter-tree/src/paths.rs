@@ -0,0 +1,58 @@
+//! Tree entry paths as bytes, since Git does not require them to be valid UTF-8.
+
+use git2::{Error, ObjectType, Repository, Tree, TreeEntry};
+use std::borrow::Cow;
+use std::path::Path;
+
+/// `name` appended to the directory path `dir`, which is empty at the tree root.
+pub(crate) fn join(dir: &[u8], name: &[u8]) -> Vec<u8> {
+    if dir.is_empty() {
+        return name.to_vec();
+    }
+    let mut path = Vec::with_capacity(dir.len() + 1 + name.len());
+    path.extend_from_slice(dir);
+    path.push(b'/');
+    path.extend_from_slice(name);
+    path
+}
+
+/// The path spelled by `bytes`, for APIs taking a [`Path`].
+/// On Unix every byte is kept; elsewhere invalid UTF-8 is replaced, as such paths cannot be
+/// represented there.
+pub fn bytes_to_path(bytes: &[u8]) -> Cow<'_, Path> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        Cow::Borrowed(Path::new(std::ffi::OsStr::from_bytes(bytes)))
+    }
+    #[cfg(not(unix))]
+    match String::from_utf8_lossy(bytes) {
+        Cow::Borrowed(path) => Cow::Borrowed(Path::new(path)),
+        Cow::Owned(path) => Cow::Owned(path.into()),
+    }
+}
+
+/// Calls `f` with the full path from the root of `tree`, `/`-separated, and each entry of `tree`
+/// and its subtrees, parents first.
+/// Unlike [`git2::Tree::walk`], which fails on directories whose names are not valid UTF-8, paths
+/// are given as bytes, so no entry is left out.
+pub fn walk_tree<F>(repo: &Repository, tree: &Tree<'_>, mut f: F) -> Result<(), Error>
+where
+    F: FnMut(&[u8], &TreeEntry<'_>) -> Result<(), Error>,
+{
+    walk_recursive(repo, tree, b"", &mut f)
+}
+
+fn walk_recursive<F>(repo: &Repository, tree: &Tree<'_>, dir: &[u8], f: &mut F) -> Result<(), Error>
+where
+    F: FnMut(&[u8], &TreeEntry<'_>) -> Result<(), Error>,
+{
+    for entry in tree.iter() {
+        let path = join(dir, entry.name_bytes());
+        f(&path, &entry)?;
+        if entry.kind() == Some(ObjectType::Tree) {
+            walk_recursive(repo, &repo.find_tree(entry.id())?, &path, f)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,113 @@
+//! Tests of the `git-filter-tree` command line.
+
+use git2::{Oid, Repository};
+use std::{fs, path::Path, process::Command};
+use tempfile::TempDir;
+
+/// A repository with `files` committed on `HEAD`.
+fn setup_repo(files: &[(&str, &str)]) -> (Repository, TempDir) {
+    let dir = TempDir::new().unwrap();
+    let repo = Repository::init(dir.path()).unwrap();
+    {
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Test").unwrap();
+        config.set_str("user.email", "test@test").unwrap();
+    }
+    for (path, content) in files {
+        let path = dir.path().join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+    {
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = repo.signature().unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "initial", &tree, &[])
+            .unwrap();
+    }
+    (repo, dir)
+}
+
+/// Run git-filter-tree from an unrelated directory, returning its trimmed
+/// standard output.
+fn filter_tree(args: &[&str]) -> String {
+    let elsewhere = TempDir::new().unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_git-filter-tree"))
+        .current_dir(elsewhere.path())
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap().trim().to_string()
+}
+
+fn repo_arg(dir: &TempDir) -> &str {
+    dir.path().to_str().unwrap()
+}
+
+#[test]
+fn repo_selects_repository_outside_current_directory() {
+    let (repo, dir) = setup_repo(&[("lib/a.txt", "a\n"), ("README.md", "r\n")]);
+
+    let tree = filter_tree(&["--repo", repo_arg(&dir), "HEAD", "lib/"]);
+    let tree = repo.find_tree(Oid::from_str(&tree).unwrap()).unwrap();
+    assert!(tree.get_path(Path::new("lib/a.txt")).is_ok());
+    assert!(tree.get_path(Path::new("README.md")).is_err());
+}
+
+#[test]
+fn write_ref_points_at_filtered_tree() {
+    let (repo, dir) = setup_repo(&[("lib/a.txt", "a\n"), ("README.md", "r\n")]);
+
+    let tree = filter_tree(&[
+        "--repo",
+        repo_arg(&dir),
+        "--write-ref",
+        "refs/filtered/lib",
+        "HEAD",
+        "lib/",
+    ]);
+    assert_eq!(
+        repo.refname_to_id("refs/filtered/lib").unwrap().to_string(),
+        tree
+    );
+}
+
+#[test]
+fn write_ref_with_commit_stacks_commits() {
+    let (repo, dir) = setup_repo(&[("lib/a.txt", "a\n"), ("README.md", "r\n")]);
+    let args = [
+        "--repo",
+        repo_arg(&dir),
+        "--write-ref",
+        "refs/filtered/lib",
+        "--commit",
+        "HEAD",
+        "lib/",
+    ];
+
+    let tree = filter_tree(&args);
+    let first = repo
+        .find_reference("refs/filtered/lib")
+        .unwrap()
+        .peel_to_commit()
+        .unwrap();
+    assert_eq!(first.tree_id().to_string(), tree);
+    assert_eq!(first.parent_count(), 0);
+
+    filter_tree(&args);
+    let second = repo
+        .find_reference("refs/filtered/lib")
+        .unwrap()
+        .peel_to_commit()
+        .unwrap();
+    assert_eq!(second.parent_id(0).unwrap(), first.id());
+    assert_eq!(second.tree_id(), first.tree_id());
+}
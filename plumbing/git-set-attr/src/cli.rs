@@ -1,18 +1,76 @@
-use clap::Parser;
+use clap::{Args, Parser, Subcommand};
 use std::path::PathBuf;
 
 #[derive(Parser)]
 #[command(name = "git-set-attr")]
 #[command(author, version, about = "Set gitattributes via patterns and key-value pairs", long_about = None)]
 pub struct Cli {
-    /// Gitattributes-style pattern (e.g. "*.txt", "path/to/*.bin")
-    pub pattern: String,
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Set attributes for a pattern
+    Add {
+        /// Gitattributes-style pattern (e.g. "*.txt", "path/to/*.bin")
+        pattern: String,
+
+        /// Attributes to set (e.g. "diff", "-text", "filter=lfs")
+        #[arg(required = true, allow_hyphen_values = true)]
+        attributes: Vec<String>,
+
+        /// Print the change as a diff instead of writing it
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+
+        #[command(flatten)]
+        target: Target,
+    },
+
+    /// Remove attributes from a pattern, whatever their state
+    Remove {
+        /// Gitattributes-style pattern (e.g. "*.txt", "path/to/*.bin")
+        pattern: String,
+
+        /// Names of the attributes to remove (e.g. "diff", "filter")
+        #[arg(required = true)]
+        attributes: Vec<String>,
 
-    /// Attributes to set (e.g. "diff", "-text", "filter=lfs")
-    #[arg(required = true)]
-    pub attributes: Vec<String>,
+        /// Print the change as a diff instead of writing it
+        #[arg(short = 'n', long)]
+        dry_run: bool,
 
-    /// Path to the .gitattributes file to modify
+        #[command(flatten)]
+        target: Target,
+    },
+
+    /// Print the attributes set for a pattern, as `git check-attr` does
+    Check {
+        /// Gitattributes-style pattern (e.g. "*.txt", "path/to/*.bin")
+        pattern: String,
+
+        /// Names of the attributes to print; all of them when omitted
+        attributes: Vec<String>,
+
+        #[command(flatten)]
+        target: Target,
+    },
+}
+
+/// Where attributes are read and written.
+#[derive(Args)]
+#[group(multiple = false)]
+pub struct Target {
+    /// Path to the attributes file to use
     #[arg(short, long)]
     pub file: Option<PathBuf>,
+
+    /// Use the .gitattributes file at the root of the working directory
+    #[arg(long)]
+    pub root: bool,
+
+    /// Use $GIT_DIR/info/attributes, which is not shared with other clones
+    #[arg(long)]
+    pub info: bool,
 }
@@ -16,28 +16,170 @@
 
 pub use git2::{Error, Repository};
 use std::{
+    borrow::Cow,
+    collections::HashMap,
     fs::{self, OpenOptions},
-    io::{BufRead, BufReader, Write},
+    io::Write,
     path::{Path, PathBuf},
 };
 
 #[cfg(feature = "cli")]
 pub mod cli;
 
+/// The attributes file that [`SetAttr`] edits.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AttrFile<'a> {
+    /// The `.gitattributes` file at the root of the working directory.
+    Root,
+    /// The `.gitattributes` file in the current directory if one exists;
+    /// otherwise, the `.gitattributes` file found first while walking up the
+    /// directory tree from the current directory to the repository's root
+    /// directory. Without either, a new one in the current directory.
+    #[default]
+    Nearest,
+    /// The file at the given path.
+    Path(&'a Path),
+    /// `$GIT_DIR/info/attributes`, which applies to this clone only.
+    Info,
+}
+
+impl AttrFile<'_> {
+    /// The path of the attributes file in `repo`.
+    pub fn resolve(&self, repo: &Repository) -> Result<PathBuf, Error> {
+        match self {
+            AttrFile::Root => repo
+                .workdir()
+                .map(|workdir| workdir.join(".gitattributes"))
+                .ok_or_else(|| Error::from_str("Repository has no working directory")),
+            AttrFile::Nearest => find_gitattributes_file(repo),
+            AttrFile::Path(path) => Ok(path.to_path_buf()),
+            AttrFile::Info => Ok(repo.path().join("info").join("attributes")),
+        }
+    }
+}
+
 /// A trait which provides methods for settings attributes in a Git repository.
 pub trait SetAttr {
-    /// Set attributes in the appropriate `.gitattributes` file.
+    /// Set attributes in the attributes file chosen by `file`.
     ///
-    /// The `.gitattributes` file in the current directory is used if one exists;
-    /// otherwise, the `.gitattributes` file found first while
-    /// walking up the directory tree from the current directory to the
-    /// repository's root directory is used.
-    fn set_attr(
+    /// An attribute the pattern already carries in another state is changed
+    /// on the line where it appears; new attributes are appended on a new
+    /// line at the end of the file. All other lines, including comments,
+    /// spacing and line endings, are left as they are.
+    fn set_attr(&self, pattern: &str, attributes: &[&str], file: AttrFile<'_>)
+    -> Result<(), Error>;
+
+    /// Set attributes for several patterns at once, as by calling
+    /// [`SetAttr::set_attr`] for each `(pattern, attributes)` pair in order,
+    /// but reading and writing the attributes file only once.
+    ///
+    /// Every edit is validated before the file is touched, so an invalid
+    /// attribute leaves the file unchanged.
+    fn set_attrs(&self, edits: &[(&str, &[&str])], file: AttrFile<'_>) -> Result<(), Error>;
+
+    /// Remove attributes from the lines for `pattern` in the attributes file
+    /// chosen by `file`.
+    ///
+    /// Attributes are matched by name, whatever their state, so `diff`
+    /// also removes `-diff` and `diff=astextplain`. Other attributes on the
+    /// same lines are kept; lines left without attributes are dropped.
+    fn remove_attr(
         &self,
         pattern: &str,
         attributes: &[&str],
-        gitattributes: Option<&Path>,
+        file: AttrFile<'_>,
     ) -> Result<(), Error>;
+
+    /// The change [`SetAttr::set_attrs`] would make, without writing it.
+    fn preview_set_attrs(
+        &self,
+        edits: &[(&str, &[&str])],
+        file: AttrFile<'_>,
+    ) -> Result<AttrChange, Error>;
+
+    /// The change [`SetAttr::remove_attr`] would make, without writing it.
+    fn preview_remove_attr(
+        &self,
+        pattern: &str,
+        attributes: &[&str],
+        file: AttrFile<'_>,
+    ) -> Result<AttrChange, Error>;
+
+    /// The attributes the lines for `pattern` in the attributes file chosen
+    /// by `file` give it, in the order they first appear.
+    ///
+    /// Each attribute is returned with its state as `git check-attr` prints
+    /// it: `set`, `unset`, `unspecified` or the value.
+    fn get_attrs(&self, pattern: &str, file: AttrFile<'_>) -> Result<Vec<(String, String)>, Error>;
+
+    /// The attributes that apply to `path`, relative to the working
+    /// directory, through the full attribute stack, as `git check-attr`
+    /// evaluates them: every `.gitattributes` file above the path,
+    /// `$GIT_DIR/info/attributes`, and the global and system files.
+    ///
+    /// Each of `names` is returned with its state as in
+    /// [`SetAttr::get_attrs`].
+    fn check_attr(&self, path: &Path, names: &[&str]) -> Result<Vec<(String, String)>, Error>;
+}
+
+/// An edit of an attributes file, computed but not yet written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttrChange {
+    /// The attributes file.
+    pub path: PathBuf,
+    /// The path shown in [`AttrChange::diff`]: relative to the working
+    /// directory when the file is inside it.
+    pub label: String,
+    /// The contents of the file, empty if it does not exist.
+    pub before: String,
+    /// The contents of the file after the edit.
+    pub after: String,
+}
+
+impl AttrChange {
+    fn new(repo: &Repository, path: PathBuf, before: String, after: String) -> Self {
+        let label = repo
+            .workdir()
+            .and_then(|workdir| path.strip_prefix(workdir).ok())
+            .unwrap_or(&path)
+            .display()
+            .to_string();
+        AttrChange {
+            path,
+            label,
+            before,
+            after,
+        }
+    }
+
+    /// Whether the edit leaves the file as it is.
+    pub fn is_empty(&self) -> bool {
+        self.before == self.after
+    }
+
+    /// The edit as a unified diff, empty if nothing changes.
+    pub fn diff(&self) -> Result<String, Error> {
+        if self.is_empty() {
+            return Ok(String::new());
+        }
+        let mut patch = git2::Patch::from_buffers(
+            self.before.as_bytes(),
+            Some(Path::new(&self.label)),
+            self.after.as_bytes(),
+            Some(Path::new(&self.label)),
+            None,
+        )?;
+        let buf = patch.to_buf()?;
+        Ok(String::from_utf8_lossy(&buf).into_owned())
+    }
+
+    /// Write the edit, unless it changes nothing.
+    pub fn apply(&self) -> Result<(), Error> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        write_file(&self.path, &self.after)
+    }
 }
 
 impl SetAttr for Repository {
@@ -45,83 +187,339 @@ impl SetAttr for Repository {
         &self,
         pattern: &str,
         attributes: &[&str],
-        gitattributes: Option<&Path>,
+        file: AttrFile<'_>,
     ) -> Result<(), Error> {
-        let gitattributes_path = if let Some(path) = gitattributes {
-            path.to_path_buf()
-        } else {
-            find_gitattributes_file(self)?
-        };
+        self.set_attrs(&[(pattern, attributes)], file)
+    }
 
-        validate_attributes(attributes)?;
+    fn set_attrs(&self, edits: &[(&str, &[&str])], file: AttrFile<'_>) -> Result<(), Error> {
+        self.preview_set_attrs(edits, file)?.apply()
+    }
 
-        let mut lines = if gitattributes_path.exists() {
-            let file = fs::File::open(&gitattributes_path)
-                .map_err(|e| Error::from_str(&format!("Failed to open .gitattributes: {e}")))?;
-            let reader = BufReader::new(file);
-            reader
-                .lines()
-                .collect::<Result<Vec<_>, _>>()
-                .map_err(|e| Error::from_str(&format!("Failed to read .gitattributes: {e}")))?
-        } else {
-            Vec::new()
-        };
+    fn remove_attr(
+        &self,
+        pattern: &str,
+        attributes: &[&str],
+        file: AttrFile<'_>,
+    ) -> Result<(), Error> {
+        self.preview_remove_attr(pattern, attributes, file)?.apply()
+    }
 
-        let new_attrs = filter_new_attributes(pattern, attributes, &lines);
+    fn preview_set_attrs(
+        &self,
+        edits: &[(&str, &[&str])],
+        file: AttrFile<'_>,
+    ) -> Result<AttrChange, Error> {
+        let gitattributes_path = file.resolve(self)?;
 
-        if !new_attrs.is_empty() {
-            let attr_line = format_attribute_line(pattern, &new_attrs);
-            lines.push(attr_line);
+        for (_, attributes) in edits {
+            validate_attributes(attributes)?;
         }
 
-        let mut file = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .truncate(true)
-            .open(&gitattributes_path)
-            .map_err(|e| {
-                Error::from_str(&format!("Failed to open .gitattributes for writing: {e}"))
-            })?;
-
-        for line in lines {
-            writeln!(file, "{line}")
-                .map_err(|e| Error::from_str(&format!("Failed to write to .gitattributes: {e}")))?;
+        let mut lines = read_lines(&gitattributes_path)?;
+        let before = lines.concat();
+        let mut existing = existing_attributes(&lines);
+
+        for (pattern, attributes) in edits {
+            let new_attrs = filter_new_attributes(pattern, attributes, &existing);
+            let states = existing.entry(pattern.to_string()).or_default();
+
+            // Attributes the pattern already mentions change state where
+            // they are; the rest go on a new line at the end of the file.
+            let mut appended = Vec::new();
+            for attr_str in new_attrs {
+                let (name, state) = parse_attribute_string(&attr_str);
+                if states.contains_key(&name) {
+                    replace_attribute(&mut lines, pattern, &name, &attr_str);
+                } else {
+                    appended.push(attr_str);
+                }
+                states.insert(name, state);
+            }
+
+            if !appended.is_empty() {
+                push_line(&mut lines, &format_attribute_line(pattern, &appended));
+            }
         }
 
-        file.flush()
-            .map_err(|e| Error::from_str(&format!("Failed to flush .gitattributes: {e}")))?;
+        Ok(AttrChange::new(
+            self,
+            gitattributes_path,
+            before,
+            lines.concat(),
+        ))
+    }
+
+    fn preview_remove_attr(
+        &self,
+        pattern: &str,
+        attributes: &[&str],
+        file: AttrFile<'_>,
+    ) -> Result<AttrChange, Error> {
+        let gitattributes_path = file.resolve(self)?;
+
+        validate_attributes(attributes)?;
+
+        let lines = read_lines(&gitattributes_path)?;
+        let kept = remove_attributes(pattern, attributes, &lines);
 
-        Ok(())
+        Ok(AttrChange::new(
+            self,
+            gitattributes_path,
+            lines.concat(),
+            kept.concat(),
+        ))
+    }
+
+    fn get_attrs(&self, pattern: &str, file: AttrFile<'_>) -> Result<Vec<(String, String)>, Error> {
+        let lines = read_lines(&file.resolve(self)?)?;
+
+        let mut attrs: Vec<(String, String)> = Vec::new();
+        for line in lines.iter().filter(|line| is_pattern_line(line, pattern)) {
+            let Some((_, rest)) = split_pattern(line) else {
+                continue;
+            };
+            for attr_str in rest.split_whitespace() {
+                let (name, state) = parse_attribute_string(attr_str);
+                let state = match state.strip_prefix("value:") {
+                    Some(value) => value.to_string(),
+                    None => state,
+                };
+                match attrs.iter_mut().find(|(existing, _)| *existing == name) {
+                    Some(attr) => attr.1 = state,
+                    None => attrs.push((name, state)),
+                }
+            }
+        }
+
+        Ok(attrs)
+    }
+
+    fn check_attr(&self, path: &Path, names: &[&str]) -> Result<Vec<(String, String)>, Error> {
+        names
+            .iter()
+            .map(|name| {
+                let value = self.get_attr(path, name, git2::AttrCheckFlags::FILE_THEN_INDEX)?;
+                let state = match git2::AttrValue::from_string(value) {
+                    git2::AttrValue::True => "set".to_string(),
+                    git2::AttrValue::False => "unset".to_string(),
+                    git2::AttrValue::String(value) => value.to_string(),
+                    git2::AttrValue::Bytes(value) => String::from_utf8_lossy(value).into_owned(),
+                    git2::AttrValue::Unspecified => "unspecified".to_string(),
+                };
+                Ok((name.to_string(), state))
+            })
+            .collect()
     }
 }
 
-/// Filter out attributes that already exist for the given pattern.
+/// Read the lines of a `.gitattributes` file, or none if it does not exist.
 ///
-/// Parses every existing line that matches `pattern` and collects its
-/// attribute name/state pairs, then returns only those entries from
-/// `attributes` whose state differs (or that are completely new).
-fn filter_new_attributes(pattern: &str, attributes: &[&str], lines: &[String]) -> Vec<String> {
-    use std::collections::HashMap;
+/// Each line keeps its line ending, so that lines which are not edited are
+/// written back byte for byte.
+fn read_lines(path: &Path) -> Result<Vec<String>, Error> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
 
-    let mut existing_attrs: HashMap<String, String> = HashMap::new();
+    let content = fs::read_to_string(path)
+        .map_err(|e| Error::from_str(&format!("Failed to read .gitattributes: {e}")))?;
+    Ok(content.split_inclusive('\n').map(str::to_string).collect())
+}
 
-    for line in lines {
-        let trimmed = line.trim();
-        if trimmed.is_empty() || trimmed.starts_with('#') {
+/// Replace the contents of a `.gitattributes` file with `content`.
+fn write_file(path: &Path, content: &str) -> Result<(), Error> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| Error::from_str(&format!("Failed to create {}: {e}", parent.display())))?;
+    }
+
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)
+        .map_err(|e| Error::from_str(&format!("Failed to open .gitattributes for writing: {e}")))?;
+
+    file.write_all(content.as_bytes())
+        .map_err(|e| Error::from_str(&format!("Failed to write to .gitattributes: {e}")))?;
+
+    file.flush()
+        .map_err(|e| Error::from_str(&format!("Failed to flush .gitattributes: {e}")))?;
+
+    Ok(())
+}
+
+/// The line ending used in `lines`: that of the first line which has one,
+/// or `\n`.
+fn line_ending(lines: &[String]) -> &'static str {
+    match lines.iter().find(|line| line.ends_with('\n')) {
+        Some(line) if line.ends_with("\r\n") => "\r\n",
+        _ => "\n",
+    }
+}
+
+/// Append `line` at the end of `lines`, ending the last line first if the
+/// file has no final newline.
+fn push_line(lines: &mut Vec<String>, line: &str) {
+    let ending = line_ending(lines);
+    if let Some(last) = lines.last_mut()
+        && !last.ends_with('\n')
+        && !last.trim_start_matches(BOM).is_empty()
+    {
+        last.push_str(ending);
+    }
+    lines.push(format!("{line}{ending}"));
+}
+
+/// The byte ranges of the whitespace-separated fields of `line`.
+fn fields(line: &str) -> Vec<(usize, usize)> {
+    let mut fields = Vec::new();
+    let mut start = None;
+    for (i, c) in line.char_indices() {
+        match (c.is_whitespace(), start) {
+            (false, None) => start = Some(i),
+            (true, Some(s)) => {
+                fields.push((s, i));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        fields.push((s, line.len()));
+    }
+    fields
+}
+
+/// The byte ranges within `line` of the attributes following its pattern.
+fn attribute_fields(line: &str) -> Vec<(usize, usize)> {
+    let Some((_, rest)) = split_pattern(line) else {
+        return Vec::new();
+    };
+    let offset = line.len() - rest.len();
+    fields(rest)
+        .into_iter()
+        .map(|(start, end)| (offset + start, offset + end))
+        .collect()
+}
+
+/// Whether `line` is an attribute line for `pattern`.
+fn is_pattern_line(line: &str, pattern: &str) -> bool {
+    split_pattern(line).is_some_and(|(line_pattern, _)| line_pattern == pattern)
+}
+
+/// Replace the last mention of attribute `name` on the lines for `pattern`
+/// with `attr`, leaving the rest of the line as it is.
+fn replace_attribute(lines: &mut [String], pattern: &str, name: &str, attr: &str) {
+    for line in lines.iter_mut().rev() {
+        if !is_pattern_line(line, pattern) {
             continue;
         }
+        let found = attribute_fields(line)
+            .into_iter()
+            .rev()
+            .find(|&(start, end)| parse_attribute_string(&line[start..end]).0 == name);
+        if let Some((start, end)) = found {
+            line.replace_range(start..end, attr);
+            return;
+        }
+    }
+}
 
-        let mut parts = trimmed.split_whitespace();
-        let line_pattern = parts.next().unwrap_or("");
+/// Remove the attributes named in `attributes` from every line for `pattern`.
+///
+/// Lines for other patterns, comments and blank lines are returned
+/// unchanged. A line for `pattern` that has no attributes left is dropped.
+fn remove_attributes(pattern: &str, attributes: &[&str], lines: &[String]) -> Vec<String> {
+    let names: Vec<String> = attributes
+        .iter()
+        .map(|attr| attr.trim())
+        .filter(|attr| !attr.is_empty())
+        .map(|attr| parse_attribute_string(attr).0)
+        .collect();
+
+    let mut kept = Vec::with_capacity(lines.len());
+    for line in lines {
+        if !is_pattern_line(line, pattern) {
+            kept.push(line.clone());
+            continue;
+        }
 
-        if line_pattern == pattern {
-            for attr_str in parts {
-                let (name, state) = parse_attribute_string(attr_str);
-                existing_attrs.insert(name, state);
+        // Drop each removed attribute with the whitespace before it, so the
+        // spacing between the remaining fields is kept.
+        let fields = attribute_fields(line);
+        let Some(&(first, _)) = fields.first() else {
+            kept.push(line.clone());
+            continue;
+        };
+        let mut edited = line[..first].trim_end().to_string();
+        let mut previous_end = edited.len();
+        let mut remaining = 0;
+        for (start, end) in fields.iter().copied() {
+            if !names.contains(&parse_attribute_string(&line[start..end]).0) {
+                edited.push_str(&line[previous_end..end]);
+                remaining += 1;
             }
+            previous_end = end;
+        }
+
+        if remaining == fields.len() {
+            kept.push(line.clone());
+        } else if remaining > 0 {
+            edited.push_str(&line[previous_end..]);
+            kept.push(edited);
+        }
+    }
+
+    // A dropped first line takes the byte order mark with it; put it back.
+    if lines.first().is_some_and(|line| line.starts_with(BOM))
+        && !kept.first().is_some_and(|line| line.starts_with(BOM))
+    {
+        match kept.first_mut() {
+            Some(first) => first.insert(0, BOM),
+            None => kept.push(BOM.to_string()),
         }
     }
 
+    kept
+}
+
+/// Attribute name/state pairs, as returned by [`parse_attribute_string`].
+type States = HashMap<String, String>;
+
+/// Collect the attribute states of every pattern in `lines`, later lines
+/// taking precedence.
+fn existing_attributes(lines: &[String]) -> HashMap<String, States> {
+    let mut existing: HashMap<String, States> = HashMap::new();
+
+    for line in lines {
+        let Some((line_pattern, rest)) = split_pattern(line) else {
+            continue;
+        };
+        let states = existing.entry(line_pattern.into_owned()).or_default();
+
+        for attr_str in rest.split_whitespace() {
+            let (name, state) = parse_attribute_string(attr_str);
+            states.insert(name, state);
+        }
+    }
+
+    existing
+}
+
+/// Filter out attributes that already exist for the given pattern.
+///
+/// Looks up the attribute name/state pairs `existing` holds for `pattern`,
+/// then returns only those entries from `attributes` whose state differs
+/// (or that are completely new).
+fn filter_new_attributes(
+    pattern: &str,
+    attributes: &[&str],
+    existing: &HashMap<String, States>,
+) -> Vec<String> {
+    let existing_attrs = existing.get(pattern);
+
     let mut new_attrs = Vec::new();
     for attr_str in attributes {
         let attr_str = attr_str.trim();
@@ -131,7 +529,7 @@ fn filter_new_attributes(pattern: &str, attributes: &[&str], lines: &[String]) -
 
         let (name, state) = parse_attribute_string(attr_str);
 
-        if existing_attrs.get(&name) != Some(&state) {
+        if existing_attrs.and_then(|states| states.get(&name)) != Some(&state) {
             new_attrs.push(attr_str.to_string());
         }
     }
@@ -171,6 +569,8 @@ fn parse_attribute_string(attr: &str) -> (String, String) {
 }
 
 /// Validate attribute strings.
+///
+/// As in Git, attribute names may not contain whitespace or start with `-`.
 fn validate_attributes(attributes: &[&str]) -> Result<(), Error> {
     for attr in attributes {
         let attr = attr.trim();
@@ -178,18 +578,19 @@ fn validate_attributes(attributes: &[&str]) -> Result<(), Error> {
             continue;
         }
 
-        let has_whitespace = |s: &str| s.is_empty() || s.contains(char::is_whitespace);
+        let invalid_name =
+            |s: &str| s.is_empty() || s.starts_with('-') || s.contains(char::is_whitespace);
 
         if let Some(stripped) = attr.strip_prefix('-') {
-            if has_whitespace(stripped) {
+            if invalid_name(stripped) {
                 return Err(Error::from_str(&format!("Invalid attribute '{attr}'")));
             }
         } else if let Some(stripped) = attr.strip_prefix('!') {
-            if has_whitespace(stripped) {
+            if invalid_name(stripped) {
                 return Err(Error::from_str(&format!("Invalid attribute '{attr}'")));
             }
         } else if let Some((name, _value)) = attr.split_once('=') {
-            if has_whitespace(name) {
+            if invalid_name(name) {
                 return Err(Error::from_str(&format!("Invalid attribute '{attr}'")));
             }
         } else if attr.contains(char::is_whitespace) {
@@ -200,9 +601,142 @@ fn validate_attributes(attributes: &[&str]) -> Result<(), Error> {
     Ok(())
 }
 
+/// Split a gitattributes line into its pattern and the rest of the line,
+/// which holds the attributes.
+///
+/// As in Git, a pattern that starts with `"` is a C-style quoted string,
+/// which may contain whitespace; it is returned unquoted. A quoted pattern
+/// that does not unquote is taken as it is, up to the first whitespace.
+/// Blank lines and comments have no pattern, and a byte order mark at the
+/// start of the line is skipped.
+pub fn split_pattern(line: &str) -> Option<(Cow<'_, str>, &str)> {
+    let trimmed = line.strip_prefix(BOM).unwrap_or(line).trim_start();
+    if trimmed.is_empty() || trimmed.starts_with('#') {
+        return None;
+    }
+
+    if let Some(quoted) = trimmed.strip_prefix('"')
+        && let Some((pattern, len)) = unquote(quoted)
+    {
+        return Some((Cow::Owned(pattern), &quoted[len..]));
+    }
+
+    let end = trimmed.find(char::is_whitespace).unwrap_or(trimmed.len());
+    Some((Cow::Borrowed(&trimmed[..end]), &trimmed[end..]))
+}
+
+/// Unquote the C-style quoted string that `quoted` starts with, just past
+/// its opening quote, returning it and the length of `quoted` it took up
+/// (including the closing quote).
+fn unquote(quoted: &str) -> Option<(String, usize)> {
+    let mut bytes = Vec::new();
+    let mut chars = quoted.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((String::from_utf8_lossy(&bytes).into_owned(), i + 1)),
+            '\\' => {
+                let (_, escaped) = chars.next()?;
+                let byte = match escaped {
+                    'a' => 0x07,
+                    'b' => 0x08,
+                    'f' => 0x0c,
+                    'n' => b'\n',
+                    'r' => b'\r',
+                    't' => b'\t',
+                    'v' => 0x0b,
+                    '\\' | '"' => escaped as u8,
+                    '0'..='3' => {
+                        let mut value = escaped as u8 - b'0';
+                        for _ in 0..2 {
+                            let (_, digit) = chars.next_if(|(_, c)| matches!(c, '0'..='7'))?;
+                            value = value * 8 + (digit as u8 - b'0');
+                        }
+                        value
+                    }
+                    _ => return None,
+                };
+                bytes.push(byte);
+            }
+            c => bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+        }
+    }
+    None
+}
+
+/// Quote `pattern` for a gitattributes line if it needs it: when it
+/// contains whitespace or control characters, or starts with `"`.
+pub fn quote_pattern(pattern: &str) -> Cow<'_, str> {
+    let needs_quotes =
+        pattern.starts_with('"') || pattern.chars().any(|c| c.is_whitespace() || c.is_control());
+    if !needs_quotes {
+        return Cow::Borrowed(pattern);
+    }
+
+    let mut quoted = String::from("\"");
+    for c in pattern.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\t' => quoted.push_str("\\t"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            c if c.is_ascii_control() => quoted.push_str(&format!("\\{:03o}", c as u8)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    Cow::Owned(quoted)
+}
+
+/// The byte order mark that may start an attributes file written on Windows.
+const BOM: char = '\u{feff}';
+
+/// Whether an attributes file starts with a byte order mark and which line
+/// ending it uses, so that it can be rewritten without changing either.
+///
+/// The default format has neither: no byte order mark and `\n` endings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TextFormat {
+    bom: bool,
+    crlf: bool,
+}
+
+impl TextFormat {
+    /// The format of `content`, whose first line ending decides between
+    /// `\r\n` and `\n`.
+    pub fn detect(content: &str) -> Self {
+        TextFormat {
+            bom: content.starts_with(BOM),
+            crlf: content
+                .find('\n')
+                .is_some_and(|i| content[..i].ends_with('\r')),
+        }
+    }
+
+    /// `content` in this format, whatever byte order mark and line endings
+    /// it has.
+    pub fn apply(&self, content: &str) -> String {
+        let content = content.strip_prefix(BOM).unwrap_or(content);
+        let mut formatted = String::with_capacity(content.len() + 3);
+        if self.bom {
+            formatted.push(BOM);
+        }
+        for line in content.split_inclusive('\n') {
+            match line.strip_suffix('\n') {
+                Some(line) => {
+                    formatted.push_str(line.strip_suffix('\r').unwrap_or(line));
+                    formatted.push_str(if self.crlf { "\r\n" } else { "\n" });
+                }
+                None => formatted.push_str(line),
+            }
+        }
+        formatted
+    }
+}
+
 /// Format a pattern and attributes into a gitattributes line.
 fn format_attribute_line(pattern: &str, attributes: &[impl AsRef<str>]) -> String {
-    let mut line = pattern.to_string();
+    let mut line = quote_pattern(pattern).into_owned();
 
     for attr in attributes {
         let attr = attr.as_ref().trim();
@@ -329,6 +863,12 @@ mod tests {
         assert!(validate_attributes(&["!"]).is_err());
     }
 
+    #[test]
+    fn validate_rejects_names_starting_with_dash() {
+        assert!(validate_attributes(&["--root"]).is_err());
+        assert!(validate_attributes(&["!-diff"]).is_err());
+    }
+
     #[test]
     fn validate_rejects_whitespace_in_name() {
         assert!(validate_attributes(&["my attr"]).is_err());
@@ -374,21 +914,24 @@ mod tests {
 
     #[test]
     fn filter_returns_all_for_empty_file() {
-        let result = filter_new_attributes("*.txt", &["diff", "-text", "filter=lfs"], &[]);
+        let result =
+            filter_new_attributes("*.txt", &["diff", "-text", "filter=lfs"], &HashMap::new());
         assert_eq!(result, vec!["diff", "-text", "filter=lfs"]);
     }
 
     #[test]
     fn filter_removes_exact_duplicates() {
         let lines = vec!["*.txt diff -text".into()];
-        let result = filter_new_attributes("*.txt", &["diff", "-text"], &lines);
+        let result =
+            filter_new_attributes("*.txt", &["diff", "-text"], &existing_attributes(&lines));
         assert!(result.is_empty());
     }
 
     #[test]
     fn filter_keeps_new_attributes() {
         let lines = vec!["*.txt diff -text".into()];
-        let result = filter_new_attributes("*.txt", &["diff", "eol=lf"], &lines);
+        let result =
+            filter_new_attributes("*.txt", &["diff", "eol=lf"], &existing_attributes(&lines));
         assert_eq!(result, vec!["eol=lf"]);
     }
 
@@ -396,20 +939,25 @@ mod tests {
     fn filter_semantic_set_equivalence() {
         // diff=true is the same as diff
         let lines = vec!["*.txt diff".into()];
-        assert!(filter_new_attributes("*.txt", &["diff=true"], &lines).is_empty());
+        assert!(
+            filter_new_attributes("*.txt", &["diff=true"], &existing_attributes(&lines)).is_empty()
+        );
     }
 
     #[test]
     fn filter_semantic_unset_equivalence() {
         // diff=false is the same as -diff
         let lines = vec!["*.txt -diff".into()];
-        assert!(filter_new_attributes("*.txt", &["diff=false"], &lines).is_empty());
+        assert!(
+            filter_new_attributes("*.txt", &["diff=false"], &existing_attributes(&lines))
+                .is_empty()
+        );
     }
 
     #[test]
     fn filter_set_differs_from_unset() {
         let lines = vec!["*.txt diff".into()];
-        let result = filter_new_attributes("*.txt", &["-diff"], &lines);
+        let result = filter_new_attributes("*.txt", &["-diff"], &existing_attributes(&lines));
         assert_eq!(result, vec!["-diff"]);
     }
 
@@ -421,14 +969,19 @@ mod tests {
             "*.txt -text".into(),
         ];
         assert!(
-            filter_new_attributes("*.txt", &["diff", "filter=lfs", "-text"], &lines).is_empty()
+            filter_new_attributes(
+                "*.txt",
+                &["diff", "filter=lfs", "-text"],
+                &existing_attributes(&lines)
+            )
+            .is_empty()
         );
     }
 
     #[test]
     fn filter_ignores_other_patterns() {
         let lines = vec!["*.md diff".into()];
-        let result = filter_new_attributes("*.txt", &["diff"], &lines);
+        let result = filter_new_attributes("*.txt", &["diff"], &existing_attributes(&lines));
         assert_eq!(result, vec!["diff"]);
     }
 
@@ -440,17 +993,132 @@ mod tests {
             "  ".into(),
             "  # indented comment".into(),
         ];
-        let result = filter_new_attributes("*.txt", &["diff", "-text"], &lines);
+        let result =
+            filter_new_attributes("*.txt", &["diff", "-text"], &existing_attributes(&lines));
         assert_eq!(result, vec!["-text"]);
     }
 
     #[test]
     fn filter_distinguishes_different_values() {
         let lines = vec!["*.txt filter=foo".into()];
-        assert!(filter_new_attributes("*.txt", &["filter=foo"], &lines).is_empty());
+        assert!(
+            filter_new_attributes("*.txt", &["filter=foo"], &existing_attributes(&lines))
+                .is_empty()
+        );
         assert_eq!(
-            filter_new_attributes("*.txt", &["filter=bar"], &lines),
+            filter_new_attributes("*.txt", &["filter=bar"], &existing_attributes(&lines)),
             vec!["filter=bar"]
         );
     }
+    #[test]
+    fn remove_keeps_unrelated_attributes() {
+        let lines = vec!["*.txt vendored name=o/r diff -text".into()];
+        assert_eq!(
+            remove_attributes("*.txt", &["vendored", "name"], &lines),
+            vec!["*.txt diff -text"]
+        );
+    }
+
+    #[test]
+    fn remove_matches_by_name_in_any_state() {
+        let lines = vec!["*.txt -diff text=auto".into(), "*.md diff".into()];
+        assert_eq!(
+            remove_attributes("*.txt", &["diff", "-text"], &lines),
+            vec!["*.md diff"]
+        );
+    }
+
+    #[test]
+    fn remove_leaves_other_lines_untouched() {
+        let lines = vec![
+            "# *.txt diff".into(),
+            "".into(),
+            "*.rs   diff".into(),
+            "*.txt  text".into(),
+        ];
+        assert_eq!(remove_attributes("*.txt", &["diff"], &lines), lines);
+    }
+
+    #[test]
+    fn split_plain_pattern() {
+        assert_eq!(
+            split_pattern("  *.txt  diff -text\n"),
+            Some((Cow::Borrowed("*.txt"), "  diff -text\n"))
+        );
+        assert_eq!(split_pattern("*.txt"), Some((Cow::Borrowed("*.txt"), "")));
+        assert_eq!(split_pattern("  # *.txt diff"), None);
+        assert_eq!(split_pattern("   "), None);
+    }
+
+    #[test]
+    fn split_quoted_pattern() {
+        let (pattern, rest) = split_pattern(r#""my docs/*.txt" diff"#).unwrap();
+        assert_eq!(pattern, "my docs/*.txt");
+        assert_eq!(rest, " diff");
+
+        let (pattern, _) = split_pattern(r#""a\"b\\c\tx\303\251" diff"#).unwrap();
+        assert_eq!(pattern, "a\"b\\c\txé");
+    }
+
+    #[test]
+    fn split_unterminated_quote_takes_pattern_as_is() {
+        assert_eq!(
+            split_pattern(r#""my docs diff"#),
+            Some((Cow::Borrowed("\"my"), " docs diff"))
+        );
+    }
+
+    #[test]
+    fn quote_only_when_needed() {
+        assert_eq!(quote_pattern("*.txt"), "*.txt");
+        assert_eq!(quote_pattern(r"a\*b"), r"a\*b");
+        assert_eq!(quote_pattern("my docs/*.txt"), r#""my docs/*.txt""#);
+        assert_eq!(quote_pattern("\"a\\b\t"), r#""\"a\\b\t""#);
+    }
+
+    #[test]
+    fn quote_round_trips_through_split() {
+        for pattern in ["my docs/*.txt", "\"x", "tab\there", "bell\x07", "é ö"] {
+            let line = format_attribute_line(pattern, &["diff"]);
+            let (parsed, rest) = split_pattern(&line).unwrap();
+            assert_eq!(parsed, pattern);
+            assert_eq!(rest, " diff");
+        }
+    }
+
+    #[test]
+    fn remove_from_quoted_pattern() {
+        let lines = vec![r#""my docs/" vendored  diff"#.into()];
+        assert_eq!(
+            remove_attributes("my docs/", &["vendored"], &lines),
+            vec![r#""my docs/"  diff"#]
+        );
+    }
+
+    #[test]
+    fn split_skips_byte_order_mark() {
+        let (pattern, rest) = split_pattern("\u{feff}*.txt diff\r\n").unwrap();
+        assert_eq!(pattern, "*.txt");
+        assert_eq!(rest, " diff\r\n");
+    }
+
+    #[test]
+    fn text_format_keeps_bom_and_crlf() {
+        let format = TextFormat::detect("\u{feff}a x\r\nb y\n");
+        assert_eq!(format.apply("a x\nb y\nc z"), "\u{feff}a x\r\nb y\r\nc z");
+        assert_eq!(
+            TextFormat::default().apply("\u{feff}a x\r\nb y\r\n"),
+            "a x\nb y\n"
+        );
+        assert_eq!(TextFormat::detect(""), TextFormat::default());
+    }
+
+    #[test]
+    fn remove_first_line_keeps_byte_order_mark() {
+        let lines = vec!["\u{feff}*.txt diff\r\n".into(), "*.md text\r\n".into()];
+        assert_eq!(
+            remove_attributes("*.txt", &["diff"], &lines),
+            vec!["\u{feff}*.md text\r\n"]
+        );
+    }
 }
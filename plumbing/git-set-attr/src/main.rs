@@ -1,8 +1,8 @@
 mod cli;
 
 use clap::Parser;
-use cli::Cli;
-use git_set_attr::SetAttr;
+use cli::{Cli, Command, Target};
+use git_set_attr::{AttrChange, AttrFile, SetAttr};
 use git2 as git;
 use std::process;
 
@@ -19,11 +19,69 @@ fn run() -> Result<(), Box<dyn std::error::Error>> {
     // Open the repository in current directory
     let repo = git::Repository::open(".")?;
 
-    // Convert attributes to string slices
-    let attributes: Vec<&str> = cli.attributes.iter().map(|s| s.as_str()).collect();
-
-    // Set attributes in the appropriate .gitattributes file
-    repo.set_attr(&cli.pattern, &attributes, cli.file.as_deref())?;
+    match &cli.command {
+        Command::Add {
+            pattern,
+            attributes,
+            dry_run,
+            target,
+        } => {
+            let attributes: Vec<&str> = attributes.iter().map(|s| s.as_str()).collect();
+            let change = repo.preview_set_attrs(&[(pattern, &attributes)], attr_file(target))?;
+            finish(&change, *dry_run)?;
+        }
+        Command::Remove {
+            pattern,
+            attributes,
+            dry_run,
+            target,
+        } => {
+            let attributes: Vec<&str> = attributes.iter().map(|s| s.as_str()).collect();
+            let change = repo.preview_remove_attr(pattern, &attributes, attr_file(target))?;
+            finish(&change, *dry_run)?;
+        }
+        Command::Check {
+            pattern,
+            attributes,
+            target,
+        } => {
+            let set = repo.get_attrs(pattern, attr_file(target))?;
+            if attributes.is_empty() {
+                for (name, state) in &set {
+                    println!("{pattern}: {name}: {state}");
+                }
+            } else {
+                for name in attributes {
+                    let state = set
+                        .iter()
+                        .find(|(set_name, _)| set_name == name)
+                        .map_or("unspecified", |(_, state)| state.as_str());
+                    println!("{pattern}: {name}: {state}");
+                }
+            }
+        }
+    }
 
     Ok(())
 }
+
+/// The attributes file chosen by `target`, defaulting to the nearest
+/// `.gitattributes`.
+fn attr_file(target: &Target) -> AttrFile<'_> {
+    match (&target.file, target.root, target.info) {
+        (Some(path), _, _) => AttrFile::Path(path),
+        (None, true, _) => AttrFile::Root,
+        (None, false, true) => AttrFile::Info,
+        (None, false, false) => AttrFile::Nearest,
+    }
+}
+
+/// Write `change`, or print it as a diff when `dry_run` is set.
+fn finish(change: &AttrChange, dry_run: bool) -> Result<(), git::Error> {
+    if dry_run {
+        print!("{}", change.diff()?);
+        Ok(())
+    } else {
+        change.apply()
+    }
+}
@@ -1,4 +1,4 @@
-use git_set_attr::SetAttr;
+use git_set_attr::{AttrFile, SetAttr};
 use git2::Repository;
 use std::fs;
 use tempfile::TempDir;
@@ -13,7 +13,7 @@ fn creates_file_from_scratch() {
     let repo = Repository::init(&tmp).unwrap();
     let ga = tmp.path().join(".gitattributes");
 
-    repo.set_attr("*.txt", &["diff", "-text"], Some(&ga))
+    repo.set_attr("*.txt", &["diff", "-text"], AttrFile::Path(&ga))
         .unwrap();
 
     assert!(ga.exists());
@@ -27,7 +27,7 @@ fn appends_to_existing_file() {
     let ga = tmp.path().join(".gitattributes");
 
     fs::write(&ga, "*.md text\n").unwrap();
-    repo.set_attr("*.txt", &["diff", "-text"], Some(&ga))
+    repo.set_attr("*.txt", &["diff", "-text"], AttrFile::Path(&ga))
         .unwrap();
 
     let content = read(&ga);
@@ -45,7 +45,8 @@ fn exact_duplicate_is_noop() {
     let ga = tmp.path().join(".gitattributes");
 
     fs::write(&ga, "*.txt diff\n").unwrap();
-    repo.set_attr("*.txt", &["diff"], Some(&ga)).unwrap();
+    repo.set_attr("*.txt", &["diff"], AttrFile::Path(&ga))
+        .unwrap();
 
     let content = read(&ga);
     assert_eq!(
@@ -63,7 +64,8 @@ fn semantic_duplicate_set_is_noop() {
 
     // `diff` and `diff=true` are semantically identical
     fs::write(&ga, "*.txt diff\n").unwrap();
-    repo.set_attr("*.txt", &["diff=true"], Some(&ga)).unwrap();
+    repo.set_attr("*.txt", &["diff=true"], AttrFile::Path(&ga))
+        .unwrap();
 
     let content = read(&ga);
     assert_eq!(
@@ -81,7 +83,8 @@ fn semantic_duplicate_unset_is_noop() {
 
     // `-diff` and `diff=false` are semantically identical
     fs::write(&ga, "*.txt -diff\n").unwrap();
-    repo.set_attr("*.txt", &["diff=false"], Some(&ga)).unwrap();
+    repo.set_attr("*.txt", &["diff=false"], AttrFile::Path(&ga))
+        .unwrap();
 
     let content = read(&ga);
     assert_eq!(
@@ -98,8 +101,12 @@ fn additive_appends_only_new_attributes() {
     let ga = tmp.path().join(".gitattributes");
 
     fs::write(&ga, "*.txt diff\n").unwrap();
-    repo.set_attr("*.txt", &["diff", "filter=lfs", "-text"], Some(&ga))
-        .unwrap();
+    repo.set_attr(
+        "*.txt",
+        &["diff", "filter=lfs", "-text"],
+        AttrFile::Path(&ga),
+    )
+    .unwrap();
 
     let content = read(&ga);
     // Original line preserved
@@ -124,11 +131,14 @@ fn different_value_is_not_duplicate() {
     let ga = tmp.path().join(".gitattributes");
 
     fs::write(&ga, "*.txt filter=foo\n").unwrap();
-    repo.set_attr("*.txt", &["filter=bar"], Some(&ga)).unwrap();
+    repo.set_attr("*.txt", &["filter=bar"], AttrFile::Path(&ga))
+        .unwrap();
 
-    let content = read(&ga);
-    assert!(content.contains("filter=foo"), "original value missing");
-    assert!(content.contains("filter=bar"), "new value missing");
+    assert_eq!(
+        read(&ga),
+        "*.txt filter=bar\n",
+        "value should change in place"
+    );
 }
 
 #[test]
@@ -138,14 +148,10 @@ fn changing_state_is_not_duplicate() {
     let ga = tmp.path().join(".gitattributes");
 
     fs::write(&ga, "*.txt diff\n").unwrap();
-    repo.set_attr("*.txt", &["-diff"], Some(&ga)).unwrap();
+    repo.set_attr("*.txt", &["-diff"], AttrFile::Path(&ga))
+        .unwrap();
 
-    let content = read(&ga);
-    assert_eq!(
-        content.lines().filter(|l| l.starts_with("*.txt")).count(),
-        2,
-        "set and unset are different states: {content}"
-    );
+    assert_eq!(read(&ga), "*.txt -diff\n", "state should change in place");
 }
 
 #[test]
@@ -155,7 +161,8 @@ fn different_patterns_are_independent() {
     let ga = tmp.path().join(".gitattributes");
 
     fs::write(&ga, "*.md diff\n").unwrap();
-    repo.set_attr("*.txt", &["diff"], Some(&ga)).unwrap();
+    repo.set_attr("*.txt", &["diff"], AttrFile::Path(&ga))
+        .unwrap();
 
     let content = read(&ga);
     assert!(content.contains("*.md diff"));
@@ -169,8 +176,12 @@ fn collects_existing_attributes_across_lines() {
     let ga = tmp.path().join(".gitattributes");
 
     fs::write(&ga, "*.txt diff\n*.txt filter=lfs\n*.txt -text\n").unwrap();
-    repo.set_attr("*.txt", &["diff", "filter=lfs", "-text"], Some(&ga))
-        .unwrap();
+    repo.set_attr(
+        "*.txt",
+        &["diff", "filter=lfs", "-text"],
+        AttrFile::Path(&ga),
+    )
+    .unwrap();
 
     let content = read(&ga);
     assert_eq!(
@@ -188,7 +199,8 @@ fn preserves_comments_and_blank_lines() {
 
     let original = "# Top comment\n\n*.md text\n# Middle comment\n";
     fs::write(&ga, original).unwrap();
-    repo.set_attr("*.txt", &["diff"], Some(&ga)).unwrap();
+    repo.set_attr("*.txt", &["diff"], AttrFile::Path(&ga))
+        .unwrap();
 
     let content = read(&ga);
     assert!(content.contains("# Top comment"));
@@ -206,7 +218,8 @@ fn custom_path_in_subdirectory() {
     fs::create_dir(&sub).unwrap();
     let ga = sub.join(".gitattributes");
 
-    repo.set_attr("*.bin", &["binary"], Some(&ga)).unwrap();
+    repo.set_attr("*.bin", &["binary"], AttrFile::Path(&ga))
+        .unwrap();
 
     assert!(ga.exists());
     assert_eq!(read(&ga).trim(), "*.bin binary");
@@ -218,10 +231,16 @@ fn rejects_invalid_attributes() {
     let repo = Repository::init(&tmp).unwrap();
     let ga = tmp.path().join(".gitattributes");
 
-    assert!(repo.set_attr("*.txt", &["has space"], Some(&ga)).is_err());
-    assert!(repo.set_attr("*.txt", &["-"], Some(&ga)).is_err());
-    assert!(repo.set_attr("*.txt", &["!"], Some(&ga)).is_err());
-    assert!(repo.set_attr("*.txt", &["=value"], Some(&ga)).is_err());
+    assert!(
+        repo.set_attr("*.txt", &["has space"], AttrFile::Path(&ga))
+            .is_err()
+    );
+    assert!(repo.set_attr("*.txt", &["-"], AttrFile::Path(&ga)).is_err());
+    assert!(repo.set_attr("*.txt", &["!"], AttrFile::Path(&ga)).is_err());
+    assert!(
+        repo.set_attr("*.txt", &["=value"], AttrFile::Path(&ga))
+            .is_err()
+    );
 }
 
 #[test]
@@ -231,7 +250,7 @@ fn empty_attributes_is_noop() {
     let ga = tmp.path().join(".gitattributes");
 
     fs::write(&ga, "*.txt diff\n").unwrap();
-    repo.set_attr("*.txt", &[], Some(&ga)).unwrap();
+    repo.set_attr("*.txt", &[], AttrFile::Path(&ga)).unwrap();
 
     assert_eq!(read(&ga), "*.txt diff\n", "file should be unchanged");
 }
@@ -242,9 +261,12 @@ fn multiple_calls_accumulate() {
     let repo = Repository::init(&tmp).unwrap();
     let ga = tmp.path().join(".gitattributes");
 
-    repo.set_attr("*.txt", &["diff"], Some(&ga)).unwrap();
-    repo.set_attr("*.txt", &["-text"], Some(&ga)).unwrap();
-    repo.set_attr("*.txt", &["filter=lfs"], Some(&ga)).unwrap();
+    repo.set_attr("*.txt", &["diff"], AttrFile::Path(&ga))
+        .unwrap();
+    repo.set_attr("*.txt", &["-text"], AttrFile::Path(&ga))
+        .unwrap();
+    repo.set_attr("*.txt", &["filter=lfs"], AttrFile::Path(&ga))
+        .unwrap();
 
     let content = read(&ga);
     assert!(content.contains("diff"));
@@ -258,13 +280,284 @@ fn idempotent_over_repeated_calls() {
     let repo = Repository::init(&tmp).unwrap();
     let ga = tmp.path().join(".gitattributes");
 
-    repo.set_attr("*.txt", &["diff", "filter=lfs"], Some(&ga))
+    repo.set_attr("*.txt", &["diff", "filter=lfs"], AttrFile::Path(&ga))
         .unwrap();
     let first = read(&ga);
 
-    repo.set_attr("*.txt", &["diff", "filter=lfs"], Some(&ga))
+    repo.set_attr("*.txt", &["diff", "filter=lfs"], AttrFile::Path(&ga))
         .unwrap();
     let second = read(&ga);
 
     assert_eq!(first, second, "repeated call should be idempotent");
 }
+
+#[test]
+fn remove_attr_keeps_other_attributes_and_lines() {
+    let tmp = TempDir::new().unwrap();
+    let repo = Repository::init(&tmp).unwrap();
+    let ga = tmp.path().join(".gitattributes");
+
+    fs::write(
+        &ga,
+        "# header\n*.txt diff filter=lfs\n*.txt -diff\n*.md diff\n",
+    )
+    .unwrap();
+    repo.remove_attr("*.txt", &["diff"], AttrFile::Path(&ga))
+        .unwrap();
+
+    assert_eq!(read(&ga), "# header\n*.txt filter=lfs\n*.md diff\n");
+}
+
+#[test]
+fn remove_attr_is_noop_for_missing_file() {
+    let tmp = TempDir::new().unwrap();
+    let repo = Repository::init(&tmp).unwrap();
+    let ga = tmp.path().join(".gitattributes");
+
+    repo.remove_attr("*.txt", &["diff"], AttrFile::Path(&ga))
+        .unwrap();
+
+    assert!(!ga.exists());
+}
+
+#[test]
+fn set_attrs_applies_every_edit_in_order() {
+    let tmp = TempDir::new().unwrap();
+    let repo = Repository::init(&tmp).unwrap();
+    let ga = tmp.path().join(".gitattributes");
+
+    fs::write(&ga, "*.md text\n").unwrap();
+    repo.set_attrs(
+        &[
+            ("*.txt", &["diff", "-text"]),
+            ("*.md", &["text", "eol=lf"]),
+            ("*.txt", &["diff", "filter=lfs"]),
+        ],
+        AttrFile::Path(&ga),
+    )
+    .unwrap();
+
+    assert_eq!(
+        read(&ga),
+        "*.md text\n*.txt diff -text\n*.md eol=lf\n*.txt filter=lfs\n"
+    );
+}
+
+#[test]
+fn set_attrs_rejects_invalid_edits_without_writing() {
+    let tmp = TempDir::new().unwrap();
+    let repo = Repository::init(&tmp).unwrap();
+    let ga = tmp.path().join(".gitattributes");
+
+    fs::write(&ga, "*.md text\n").unwrap();
+    let result = repo.set_attrs(
+        &[("*.txt", &["diff"]), ("*.rs", &["-"])],
+        AttrFile::Path(&ga),
+    );
+
+    assert!(result.is_err());
+    assert_eq!(read(&ga), "*.md text\n");
+}
+
+#[test]
+fn preserves_formatting_of_untouched_lines() {
+    let tmp = TempDir::new().unwrap();
+    let repo = Repository::init(&tmp).unwrap();
+    let ga = tmp.path().join(".gitattributes");
+
+    fs::write(
+        &ga,
+        "# Docs\r\n*.md    text   \r\n  *.png  binary\r\n*.txt  diff  eol=lf",
+    )
+    .unwrap();
+    repo.set_attrs(
+        &[("*.txt", &["-diff", "filter=lfs"]), ("*.rs", &["diff"])],
+        AttrFile::Path(&ga),
+    )
+    .unwrap();
+
+    assert_eq!(
+        read(&ga),
+        "# Docs\r\n*.md    text   \r\n  *.png  binary\r\n*.txt  -diff  eol=lf\r\n\
+         *.txt filter=lfs\r\n*.rs diff\r\n"
+    );
+}
+
+#[test]
+fn remove_attr_preserves_spacing() {
+    let tmp = TempDir::new().unwrap();
+    let repo = Repository::init(&tmp).unwrap();
+    let ga = tmp.path().join(".gitattributes");
+
+    fs::write(&ga, "*.txt   diff   text=auto  eol=lf\r\n*.md text\r\n").unwrap();
+    repo.remove_attr("*.txt", &["text"], AttrFile::Path(&ga))
+        .unwrap();
+
+    assert_eq!(read(&ga), "*.txt   diff  eol=lf\r\n*.md text\r\n");
+}
+
+#[test]
+fn root_target_writes_at_working_directory_root() {
+    let tmp = TempDir::new().unwrap();
+    let repo = Repository::init(&tmp).unwrap();
+
+    repo.set_attr("*.txt", &["diff"], AttrFile::Root).unwrap();
+
+    assert_eq!(read(&tmp.path().join(".gitattributes")), "*.txt diff\n");
+}
+
+#[test]
+fn info_target_writes_to_git_dir() {
+    let tmp = TempDir::new().unwrap();
+    let repo = Repository::init(&tmp).unwrap();
+    let info = repo.path().join("info");
+    let _ = fs::remove_dir_all(&info);
+
+    repo.set_attr("*.txt", &["-diff"], AttrFile::Info).unwrap();
+
+    assert_eq!(read(&info.join("attributes")), "*.txt -diff\n");
+    assert!(!tmp.path().join(".gitattributes").exists());
+}
+
+#[test]
+fn root_target_fails_in_bare_repository() {
+    let tmp = TempDir::new().unwrap();
+    let repo = Repository::init_bare(&tmp).unwrap();
+
+    assert!(repo.set_attr("*.txt", &["diff"], AttrFile::Root).is_err());
+}
+
+#[test]
+fn preview_does_not_write() {
+    let tmp = TempDir::new().unwrap();
+    let repo = Repository::init(&tmp).unwrap();
+    let ga = tmp.path().join(".gitattributes");
+
+    fs::write(&ga, "*.md text\n").unwrap();
+    let change = repo
+        .preview_set_attrs(&[("*.txt", &["diff"])], AttrFile::Path(&ga))
+        .unwrap();
+
+    assert_eq!(read(&ga), "*.md text\n");
+    assert_eq!(change.after, "*.md text\n*.txt diff\n");
+    assert_eq!(change.label, ".gitattributes");
+    let diff = change.diff().unwrap();
+    assert!(diff.contains("+++ b/.gitattributes"), "{diff}");
+    assert!(diff.contains("\n+*.txt diff\n"), "{diff}");
+
+    change.apply().unwrap();
+    assert_eq!(read(&ga), "*.md text\n*.txt diff\n");
+}
+
+#[test]
+fn preview_remove_of_missing_attribute_is_empty() {
+    let tmp = TempDir::new().unwrap();
+    let repo = Repository::init(&tmp).unwrap();
+    let ga = tmp.path().join(".gitattributes");
+
+    fs::write(&ga, "*.md text\n").unwrap();
+    let change = repo
+        .preview_remove_attr("*.md", &["diff"], AttrFile::Path(&ga))
+        .unwrap();
+
+    assert!(change.is_empty());
+    assert_eq!(change.diff().unwrap(), "");
+}
+
+#[test]
+fn get_attrs_merges_lines_for_pattern() {
+    let tmp = TempDir::new().unwrap();
+    let repo = Repository::init(&tmp).unwrap();
+    let ga = tmp.path().join(".gitattributes");
+
+    fs::write(
+        &ga,
+        "*.txt diff eol=lf\n*.md text\n*.txt -diff !text filter=lfs\n",
+    )
+    .unwrap();
+
+    assert_eq!(
+        repo.get_attrs("*.txt", AttrFile::Path(&ga)).unwrap(),
+        vec![
+            ("diff".to_string(), "unset".to_string()),
+            ("eol".to_string(), "lf".to_string()),
+            ("text".to_string(), "unspecified".to_string()),
+            ("filter".to_string(), "lfs".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn check_attr_evaluates_the_attribute_stack() {
+    let tmp = TempDir::new().unwrap();
+    let repo = Repository::init(&tmp).unwrap();
+    let ga = tmp.path().join(".gitattributes");
+
+    fs::create_dir(tmp.path().join("sub")).unwrap();
+    fs::write(&ga, "*.txt diff eol=lf\n*.txt -diff\n").unwrap();
+    fs::write(tmp.path().join("sub/.gitattributes"), "*.txt !eol text\n").unwrap();
+
+    assert_eq!(
+        repo.check_attr(std::path::Path::new("a.txt"), &["diff", "eol", "text"])
+            .unwrap(),
+        vec![
+            ("diff".to_string(), "unset".to_string()),
+            ("eol".to_string(), "lf".to_string()),
+            ("text".to_string(), "unspecified".to_string()),
+        ]
+    );
+    assert_eq!(
+        repo.check_attr(std::path::Path::new("sub/a.txt"), &["eol", "text"])
+            .unwrap(),
+        vec![
+            ("eol".to_string(), "unspecified".to_string()),
+            ("text".to_string(), "set".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn quotes_patterns_with_spaces() {
+    let tmp = TempDir::new().unwrap();
+    let repo = Repository::init(&tmp).unwrap();
+    let ga = tmp.path().join(".gitattributes");
+
+    repo.set_attr("my docs/*.txt", &["diff"], AttrFile::Path(&ga))
+        .unwrap();
+    repo.set_attr("my docs/*.txt", &["-diff", "text"], AttrFile::Path(&ga))
+        .unwrap();
+
+    assert_eq!(
+        read(&ga),
+        "\"my docs/*.txt\" -diff\n\"my docs/*.txt\" text\n"
+    );
+    assert_eq!(
+        repo.get_attrs("my docs/*.txt", AttrFile::Path(&ga))
+            .unwrap(),
+        vec![
+            ("diff".to_string(), "unset".to_string()),
+            ("text".to_string(), "set".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn keeps_byte_order_mark_and_crlf() {
+    let tmp = TempDir::new().unwrap();
+    let repo = Repository::init(&tmp).unwrap();
+    let ga = tmp.path().join(".gitattributes");
+    fs::write(&ga, "\u{feff}*.txt diff\r\n*.md text\r\n").unwrap();
+
+    repo.set_attr("*.txt", &["-diff"], AttrFile::Path(&ga))
+        .unwrap();
+    repo.set_attr("*.rs", &["eol=lf"], AttrFile::Path(&ga))
+        .unwrap();
+    assert_eq!(
+        read(&ga),
+        "\u{feff}*.txt -diff\r\n*.md text\r\n*.rs eol=lf\r\n"
+    );
+
+    repo.remove_attr("*.txt", &["diff"], AttrFile::Path(&ga))
+        .unwrap();
+    assert_eq!(read(&ga), "\u{feff}*.md text\r\n*.rs eol=lf\r\n");
+}
@@ -0,0 +1,210 @@
+//! Findings about dependencies attached to the line defining them, for CI
+//! systems that show them inline, such as GitHub Actions workflow commands.
+
+use super::{
+    VendorDep, config::Config, lock, outdated, paths, source::DependencySource, verify,
+    verify::Verification,
+};
+use git2::{Error, Repository, Tree};
+use serde::Serialize;
+use std::path::Path;
+
+/// What an annotation reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AnnotationKind {
+    /// Fetched upstream commits are not merged yet.
+    Outdated,
+    /// Vendored content differs from its provenance record.
+    Drift,
+    /// The dependency breaks a rule in force, such as locked mode.
+    Policy,
+}
+
+/// How serious an annotation is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Level {
+    Warning,
+    Error,
+}
+
+/// A finding about one dependency.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Annotation {
+    pub kind: AnnotationKind,
+    pub level: Level,
+    /// The dependency's name.
+    pub name: String,
+    /// The file defining the dependency, relative to the repository root.
+    pub file: String,
+    /// The 1-based line defining the dependency in `file`, if found.
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl Annotation {
+    /// The annotation as a GitHub Actions workflow command, e.g.
+    /// `::warning file=.gitattributes,line=3,title=owner/repo::...`.
+    pub fn to_github(&self) -> String {
+        let level = match self.level {
+            Level::Warning => "warning",
+            Level::Error => "error",
+        };
+        let mut properties = vec![format!("file={}", escape_property(&self.file))];
+        if let Some(line) = self.line {
+            properties.push(format!("line={line}"));
+        }
+        properties.push(format!("title={}", escape_property(&self.name)));
+        format!(
+            "::{level} {}::{}",
+            properties.join(","),
+            escape_data(&self.message)
+        )
+    }
+}
+
+/// Annotate each of `deps` with findings of the `kinds` given.
+pub(crate) fn annotate(
+    repo: &Repository,
+    source: &dyn DependencySource,
+    tree: &Tree,
+    deps: &[&VendorDep],
+    kinds: &[AnnotationKind],
+    config: &Config,
+) -> Result<Vec<Annotation>, Error> {
+    let file = relative(repo, source.path());
+    let mut annotations = Vec::new();
+
+    for &dep in deps {
+        let line = source.line(dep)?;
+        let mut annotate = |kind, level, message: String| {
+            if kinds.contains(&kind) {
+                annotations.push(Annotation {
+                    kind,
+                    level,
+                    name: dep.name.clone(),
+                    file: file.clone(),
+                    line,
+                    message,
+                });
+            }
+        };
+
+        if config.locked
+            && let Err(e) = lock::locked_commit(repo, tree, dep)
+        {
+            annotate(
+                AnnotationKind::Policy,
+                Level::Error,
+                e.message().to_string(),
+            );
+        }
+
+        if kinds.contains(&AnnotationKind::Drift) || kinds.contains(&AnnotationKind::Policy) {
+            match verify::verify(repo, tree, dep)? {
+                Verification::Modified { recorded, actual } => annotate(
+                    AnnotationKind::Drift,
+                    Level::Error,
+                    format!(
+                        "Vendored content of {} is tree {actual}, but {recorded} was merged",
+                        dep.name
+                    ),
+                ),
+                Verification::Mismatch {
+                    commit,
+                    recorded,
+                    upstream,
+                } => annotate(
+                    AnnotationKind::Drift,
+                    Level::Error,
+                    format!(
+                        "Recorded tree {recorded} of {} is not what upstream {commit} yields ({upstream})",
+                        dep.name
+                    ),
+                ),
+                // Locked, the record itself must be confirmed too.
+                Verification::Unverifiable { commit } if config.locked => annotate(
+                    AnnotationKind::Policy,
+                    Level::Error,
+                    format!(
+                        "Upstream {commit} of {} is not available to verify",
+                        dep.name
+                    ),
+                ),
+                Verification::Unrecorded => annotate(
+                    AnnotationKind::Drift,
+                    Level::Warning,
+                    format!("{} has no provenance record", dep.name),
+                ),
+                Verification::Verified { .. } | Verification::Unverifiable { .. } => {}
+            }
+        }
+
+        if kinds.contains(&AnnotationKind::Outdated)
+            && let Some(entry) = outdated::check(repo, tree, dep)?
+        {
+            let plural = if entry.new_commits == 1 { "" } else { "s" };
+            let mut message = format!(
+                "{} has {} new upstream commit{plural}",
+                dep.name, entry.new_commits
+            );
+            if let Some(tag) = &entry.latest_tag {
+                message.push_str(&format!(" (latest tag {tag})"));
+            }
+            annotate(AnnotationKind::Outdated, Level::Warning, message);
+        }
+    }
+
+    Ok(annotations)
+}
+
+/// `path` relative to the working tree of `repo`, with `/` separators.
+fn relative(repo: &Repository, path: &Path) -> String {
+    match paths::workdir_relative(repo, path) {
+        Some(relative) => paths::to_slash(&relative),
+        None => paths::to_slash(path),
+    }
+}
+
+fn escape_data(s: &str) -> String {
+    s.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn escape_property(s: &str) -> String {
+    escape_data(s).replace(':', "%3A").replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_github_escapes_properties_and_data() {
+        let annotation = Annotation {
+            kind: AnnotationKind::Drift,
+            level: Level::Error,
+            name: "owner/repo:x,y".into(),
+            file: ".gitattributes".into(),
+            line: Some(3),
+            message: "100% modified\nsee log".into(),
+        };
+        assert_eq!(
+            annotation.to_github(),
+            "::error file=.gitattributes,line=3,title=owner/repo%3Ax%2Cy::100%25 modified%0Asee log"
+        );
+
+        let annotation = Annotation {
+            level: Level::Warning,
+            line: None,
+            ..annotation
+        };
+        assert!(
+            annotation
+                .to_github()
+                .starts_with("::warning file=.gitattributes,title=")
+        );
+    }
+}
@@ -0,0 +1,185 @@
+//! Import of upstream `.gitattributes` entries for vendored content.
+//!
+//! With the `import-attributes` attribute, merges copy the upstream's
+//! checkout-relevant attributes (line endings, diff and merge drivers) into
+//! a managed block of our `.gitattributes`, rewritten so they only apply
+//! beneath the vendored path.
+
+use super::VendorDep;
+use git_filter_tree::walk_tree;
+use git_set_attr::{quote_pattern, split_pattern};
+use git2::{Error, ObjectType, Repository, Tree};
+
+/// Attributes worth carrying over; anything else is dropped.
+const IMPORTED: [&str; 6] = [
+    "text",
+    "eol",
+    "diff",
+    "merge",
+    "binary",
+    "working-tree-encoding",
+];
+
+/// Every `.gitattributes` file in `tree` as `(dir, content)`, shallowest
+/// first, where `dir` is empty for the root and otherwise ends in `/`.
+pub(crate) fn upstream_files(
+    repo: &Repository,
+    tree: &Tree,
+) -> Result<Vec<(String, String)>, Error> {
+    let mut files = Vec::new();
+    walk_tree(repo, tree, |path, entry| {
+        if entry.name_bytes() != b".gitattributes" || entry.kind() != Some(ObjectType::Blob) {
+            return Ok(());
+        }
+        let dir = &path[..path.len() - entry.name_bytes().len()];
+        match std::str::from_utf8(dir) {
+            Ok(dir) => files.push((dir.to_string(), entry.id())),
+            // Patterns are rewritten as text, so such a directory cannot be named in them
+            Err(_) => tracing::warn!(
+                dir = %String::from_utf8_lossy(dir),
+                "skipping .gitattributes in a directory whose name is not valid UTF-8"
+            ),
+        }
+        Ok(())
+    })?;
+    files.sort_by_key(|(dir, _)| dir.matches('/').count());
+
+    files
+        .into_iter()
+        .map(|(dir, oid)| {
+            let blob = repo.find_blob(oid)?;
+            Ok((dir, String::from_utf8_lossy(blob.content()).into_owned()))
+        })
+        .collect()
+}
+
+/// Render the managed block of imported attributes for `dep`.
+///
+/// Returns an empty string when the upstream has nothing to import.
+pub(crate) fn render_block(
+    repo: &Repository,
+    upstream: &Tree,
+    dep: &VendorDep,
+) -> Result<String, Error> {
+    let prefix = literal_prefix(&dep.pattern);
+    let mut lines = Vec::new();
+    for (dir, content) in upstream_files(repo, upstream)? {
+        lines.extend(import_lines(&dir, prefix, &content));
+    }
+
+    if lines.is_empty() {
+        return Ok(String::new());
+    }
+
+    let (begin, end) = markers(&dep.name);
+    Ok(format!("{begin}\n{}\n{end}\n", lines.join("\n")))
+}
+
+/// The lines delimiting the managed block for the dependency `name`.
+pub(crate) fn markers(name: &str) -> (String, String) {
+    (
+        format!("# BEGIN git-vendor attributes {name}"),
+        format!("# END git-vendor attributes {name}"),
+    )
+}
+
+/// Select and rewrite the relevant lines of a `.gitattributes` file located
+/// in `dir` of the upstream tree.
+fn import_lines(dir: &str, prefix: &str, content: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    for line in content.lines() {
+        let Some((pattern, rest)) = split_pattern(line) else {
+            continue;
+        };
+        if pattern.starts_with("[attr]") {
+            continue;
+        }
+        let attrs: Vec<&str> = rest
+            .split_whitespace()
+            .filter(|attr| is_imported(attr))
+            .collect();
+        if attrs.is_empty() {
+            continue;
+        }
+
+        lines.push(format!(
+            "{} {}",
+            quote_pattern(&rewrite(dir, prefix, &pattern)),
+            attrs.join(" ")
+        ));
+    }
+
+    lines
+}
+
+/// Whether `attr` (in any of its set, unset, unspecified, or value forms)
+/// is one of the [`IMPORTED`] attributes.
+fn is_imported(attr: &str) -> bool {
+    let name = attr.trim_start_matches(['-', '!']);
+    let name = name.split_once('=').map_or(name, |(name, _)| name);
+    IMPORTED.contains(&name)
+}
+
+/// Rewrite an upstream pattern so that it matches the same paths in our
+/// tree and nothing outside the vendored `prefix`.
+fn rewrite(dir: &str, prefix: &str, pattern: &str) -> String {
+    if pattern.trim_end_matches('/').contains('/') {
+        format!("{dir}{}", pattern.trim_start_matches('/'))
+    } else if !dir.is_empty() {
+        format!("{dir}**/{pattern}")
+    } else if !prefix.is_empty() {
+        format!("{prefix}**/{pattern}")
+    } else {
+        pattern.to_string()
+    }
+}
+
+/// The directory part of `pattern` preceding its first wildcard.
+fn literal_prefix(pattern: &str) -> &str {
+    let literal = pattern
+        .find(['*', '?', '[', '\\'])
+        .map_or(pattern, |i| &pattern[..i]);
+    literal.rfind('/').map_or("", |i| &literal[..=i])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn literal_prefix_stops_at_wildcards() {
+        assert_eq!(literal_prefix("lib/"), "lib/");
+        assert_eq!(literal_prefix("vendor/lib/*"), "vendor/lib/");
+        assert_eq!(literal_prefix("vendor/*/src"), "vendor/");
+        assert_eq!(literal_prefix("*.txt"), "");
+    }
+
+    #[test]
+    fn rewrite_confines_patterns_to_vendored_prefix() {
+        assert_eq!(rewrite("", "lib/", "*.sh"), "lib/**/*.sh");
+        assert_eq!(rewrite("", "lib/", "/lib/gen.c"), "lib/gen.c");
+        assert_eq!(rewrite("lib/sub/", "lib/", "*.bat"), "lib/sub/**/*.bat");
+        assert_eq!(rewrite("", "", "*.sh"), "*.sh");
+    }
+
+    #[test]
+    fn import_lines_keeps_relevant_attributes_only() {
+        let content = "\
+# comment
+[attr]custom text
+*.sh text eol=lf export-ignore
+*.png binary
+*.md linguist-documentation
+*.lock -diff merge=ours
+";
+        assert_eq!(
+            import_lines("", "lib/", content),
+            [
+                "lib/**/*.sh text eol=lf",
+                "lib/**/*.png binary",
+                "lib/**/*.lock -diff merge=ours",
+            ]
+        );
+    }
+}
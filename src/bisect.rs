@@ -0,0 +1,376 @@
+//! Finding the upstream commit that introduced a regression.
+//!
+//! Bisection tests the upstream commits of a dependency between a good and
+//! a bad one. Each candidate is vendored into `HEAD` in a temporary,
+//! detached worktree under `.git/vendor-bisect/`, where the test command
+//! runs through `sh`. As with `git bisect run`, exit status 0 marks the
+//! commit good, 125 skips it, and any other status marks it bad.
+//!
+//! Only the first-parent history between the two commits is searched, and
+//! the host repository's branches and working tree are left untouched.
+
+use super::{
+    VendorDep, clean, config::Config, provenance, run_git, upstream_tree, vendor_ref_name,
+    vendor_tags_ref_prefix,
+};
+use git_filter_tree::{bytes_to_path, walk_tree};
+use git2::{
+    Error, Index, IndexEntry, IndexTime, ObjectType, Oid, Repository, Signature, Sort, Tree,
+    build::CheckoutBuilder,
+};
+use serde::Serialize;
+use std::{fs, process::Command};
+
+/// The exit status with which a test command skips a commit.
+const SKIP_STATUS: i32 = 125;
+
+/// The verdict of the test command on one upstream commit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Outcome {
+    Good,
+    Bad,
+    Skip,
+}
+
+/// One run of the test command.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BisectStep {
+    pub commit: String,
+    pub summary: String,
+    pub outcome: Outcome,
+}
+
+/// The result of bisecting a dependency's upstream history.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Bisection {
+    pub name: String,
+    /// The first upstream commit the test command fails at.
+    pub first_bad: String,
+    pub summary: String,
+    /// Skipped commits just before [`Bisection::first_bad`], any of which
+    /// may be the first bad one instead, oldest first.
+    pub maybe_first_bad: Vec<String>,
+    /// The commits tested, in order.
+    pub steps: Vec<BisectStep>,
+}
+
+/// Bisect `dep` between the upstream commits `good` and `bad`, defaulting
+/// to the commit last merged and the fetched vendor ref, running `command`
+/// on each candidate.
+pub(crate) fn bisect(
+    repo: &Repository,
+    deps: &[&VendorDep],
+    dep: &VendorDep,
+    good: Option<&str>,
+    bad: Option<&str>,
+    command: &str,
+    config: &Config,
+) -> Result<Bisection, Error> {
+    let head = repo.head()?.peel_to_commit()?;
+    let head_tree = head.tree()?;
+
+    let good = match good {
+        Some(rev) => resolve(repo, dep, rev)?,
+        None => provenance::read(repo, &head_tree, &dep.name)?
+            .and_then(|record| Oid::from_str(&record.commit).ok())
+            .ok_or_else(|| {
+                Error::from_str(&format!(
+                    "{} has no provenance record; pass a good upstream commit",
+                    dep.name
+                ))
+            })?,
+    };
+    let bad = match bad {
+        Some(rev) => resolve(repo, dep, rev)?,
+        None => {
+            let ref_name = vendor_ref_name(&config.ref_prefix, &dep.name);
+            repo.refname_to_id(&ref_name).map_err(|_| {
+                Error::from_str(&format!(
+                    "Vendor ref {ref_name} not found. Run fetch first."
+                ))
+            })?
+        }
+    };
+    if !repo.graph_descendant_of(bad, good)? {
+        return Err(Error::from_str(&format!(
+            "Bad commit {bad} of {} does not descend from good commit {good}",
+            dep.name
+        )));
+    }
+
+    let mut walk = repo.revwalk()?;
+    walk.push(bad)?;
+    walk.hide(good)?;
+    walk.simplify_first_parent()?;
+    walk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+    let candidates = walk.collect::<Result<Vec<_>, _>>()?;
+
+    let worktree = Worktree::add(repo, &dep.name, head.id())?;
+    let mut steps = Vec::new();
+    let result = search(&candidates, |commit| {
+        let tree = repo.find_tree(vendored_tree(repo, &head_tree, deps, dep, commit)?)?;
+        let signature = repo
+            .signature()
+            .or_else(|_| Signature::now("git-vendor", "git-vendor@localhost"))?;
+        let message = format!("Vendor {} at {commit} for bisection", dep.name);
+        let candidate = repo.commit(None, &signature, &signature, &message, &tree, &[&head])?;
+        worktree.checkout(candidate)?;
+
+        let summary = summary(repo, commit)?;
+        progress!("Testing {} {summary}", &commit.to_string()[..7]);
+        let outcome = worktree.run(command, dep, commit)?;
+        steps.push(BisectStep {
+            commit: commit.to_string(),
+            summary,
+            outcome,
+        });
+        Ok(outcome)
+    });
+    let removed = worktree.remove();
+    let (first_bad, maybe_first_bad) = result?;
+    removed?;
+
+    Ok(Bisection {
+        name: dep.name.clone(),
+        first_bad: first_bad.to_string(),
+        summary: summary(repo, first_bad)?,
+        maybe_first_bad: maybe_first_bad.iter().map(Oid::to_string).collect(),
+        steps,
+    })
+}
+
+/// Binary search `candidates`, oldest first and ending with the known bad
+/// commit, for the first one `test` fails at. Also returns the skipped
+/// commits that may be the first bad one instead.
+fn search(
+    candidates: &[Oid],
+    mut test: impl FnMut(Oid) -> Result<Outcome, Error>,
+) -> Result<(Oid, Vec<Oid>), Error> {
+    // The bad commit is checked first, so a test that never fails is not
+    // blamed on the newest commit.
+    let Some(&bad) = candidates.last() else {
+        return Err(Error::from_str("No upstream commits to bisect"));
+    };
+    match test(bad)? {
+        Outcome::Bad => {}
+        Outcome::Good => {
+            return Err(Error::from_str(&format!(
+                "The test command passes at bad commit {bad}; nothing to bisect"
+            )));
+        }
+        Outcome::Skip => {
+            return Err(Error::from_str(&format!(
+                "The test command skipped bad commit {bad}; nothing to bisect"
+            )));
+        }
+    }
+
+    // Candidates before `low` are good; the one at `high` is bad.
+    let mut remaining = candidates.to_vec();
+    let (mut low, mut high) = (0, remaining.len() - 1);
+    let mut skipped = Vec::new();
+    while low < high {
+        let mid = low + (high - low) / 2;
+        match test(remaining[mid])? {
+            Outcome::Good => low = mid + 1,
+            Outcome::Bad => high = mid,
+            Outcome::Skip => {
+                skipped.push(remaining.remove(mid));
+                high -= 1;
+            }
+        }
+    }
+    let first_bad = remaining[high];
+
+    // Skipped commits between the last good one and the first bad one are
+    // just as likely to be the culprit.
+    let position = |oid: &Oid| candidates.iter().position(|c| c == oid);
+    let last_good = high.checked_sub(1).and_then(|i| position(&remaining[i]));
+    let mut maybe_first_bad: Vec<Oid> = skipped
+        .into_iter()
+        .filter(|oid| position(oid) > last_good && position(oid) < position(&first_bad))
+        .collect();
+    maybe_first_bad.sort_by_key(position);
+    Ok((first_bad, maybe_first_bad))
+}
+
+/// `head_tree` with `dep`'s content replaced by what it vendors from the
+/// upstream `commit`.
+fn vendored_tree(
+    repo: &Repository,
+    head_tree: &Tree,
+    deps: &[&VendorDep],
+    dep: &VendorDep,
+    commit: Oid,
+) -> Result<Oid, Error> {
+    let mut index = Index::new()?;
+    index.read_tree(head_tree)?;
+    for path in clean::owned_paths(repo, &index, deps, &[dep])? {
+        index.remove_path(&bytes_to_path(&path))?;
+    }
+
+    let upstream = repo.find_commit(commit)?.tree()?;
+    let filtered = upstream_tree(repo, dep, &upstream)?;
+    let mut entries = Vec::new();
+    walk_tree(repo, &filtered, |path, entry| {
+        if entry.kind() == Some(ObjectType::Blob) {
+            entries.push((path.to_vec(), entry.id(), entry.filemode() as u32));
+        }
+        Ok(())
+    })?;
+    for (path, id, mode) in entries {
+        index.add(&IndexEntry {
+            ctime: IndexTime::new(0, 0),
+            mtime: IndexTime::new(0, 0),
+            dev: 0,
+            ino: 0,
+            mode,
+            uid: 0,
+            gid: 0,
+            file_size: 0,
+            id,
+            flags: path.len().min(0xfff) as u16,
+            flags_extended: 0,
+            path,
+        })?;
+    }
+    index.write_tree_to(repo)
+}
+
+/// A detached worktree of the host repository to test candidates in.
+struct Worktree<'r> {
+    repo: &'r Repository,
+    path: std::path::PathBuf,
+}
+
+impl<'r> Worktree<'r> {
+    fn add(repo: &'r Repository, name: &str, head: Oid) -> Result<Self, Error> {
+        let path = repo
+            .path()
+            .join("vendor-bisect")
+            .join(name.replace('/', "-"));
+        // Left over from an interrupted bisection.
+        if path.exists() {
+            fs::remove_dir_all(&path).map_err(|e| {
+                Error::from_str(&format!("Failed to remove {}: {e}", path.display()))
+            })?;
+        }
+        run_git(repo.path(), &["worktree", "prune"])?;
+        let path_str = path
+            .to_str()
+            .ok_or_else(|| Error::from_str("Repository path is not valid UTF-8"))?;
+        run_git(
+            repo.path(),
+            &["worktree", "add", "--detach", path_str, &head.to_string()],
+        )?;
+        Ok(Self { repo, path })
+    }
+
+    fn checkout(&self, commit: Oid) -> Result<(), Error> {
+        let worktree = Repository::open(&self.path)?;
+        worktree.set_head_detached(commit)?;
+        let mut checkout = CheckoutBuilder::new();
+        checkout.force().remove_untracked(true);
+        worktree.checkout_head(Some(&mut checkout))
+    }
+
+    fn run(&self, command: &str, dep: &VendorDep, commit: Oid) -> Result<Outcome, Error> {
+        let status = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .current_dir(&self.path)
+            .env("GIT_VENDOR_NAME", &dep.name)
+            .env("GIT_VENDOR_COMMIT", commit.to_string())
+            .status()
+            .map_err(|e| Error::from_str(&format!("Failed to run '{command}': {e}")))?;
+        Ok(match status.code() {
+            Some(0) => Outcome::Good,
+            Some(SKIP_STATUS) => Outcome::Skip,
+            _ => Outcome::Bad,
+        })
+    }
+
+    fn remove(self) -> Result<(), Error> {
+        let path = self
+            .path
+            .to_str()
+            .ok_or_else(|| Error::from_str("Repository path is not valid UTF-8"))?;
+        run_git(self.repo.path(), &["worktree", "remove", "--force", path]).map(|_| ())
+    }
+}
+
+/// Resolve `rev`, an upstream tag of `dep` or a fetched commit, to a commit.
+fn resolve(repo: &Repository, dep: &VendorDep, rev: &str) -> Result<Oid, Error> {
+    let tag_ref = format!("{}{rev}", vendor_tags_ref_prefix(&dep.name));
+    repo.revparse_single(&tag_ref)
+        .or_else(|_| repo.revparse_single(rev))
+        .and_then(|object| object.peel_to_commit())
+        .map(|commit| commit.id())
+        .map_err(|_| {
+            Error::from_str(&format!(
+                "No tag or fetched commit '{rev}' found for {}",
+                dep.name
+            ))
+        })
+}
+
+fn summary(repo: &Repository, commit: Oid) -> Result<String, Error> {
+    Ok(repo
+        .find_commit(commit)?
+        .summary()
+        .unwrap_or_default()
+        .to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn oids(n: u8) -> Vec<Oid> {
+        (1..=n)
+            .map(|i| Oid::from_bytes(&[i; 20]).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn search_finds_first_bad_commit() {
+        let candidates = oids(10);
+        let mut tested = 0;
+        let (first_bad, maybe) = search(&candidates, |oid| {
+            tested += 1;
+            Ok(if oid >= candidates[6] {
+                Outcome::Bad
+            } else {
+                Outcome::Good
+            })
+        })
+        .unwrap();
+        assert_eq!(first_bad, candidates[6]);
+        assert!(maybe.is_empty());
+        assert!(tested <= 5, "{tested} tests");
+    }
+
+    #[test]
+    fn search_reports_skipped_commits_before_first_bad() {
+        let candidates = oids(6);
+        let (first_bad, maybe) = search(&candidates, |oid| {
+            Ok(if oid == candidates[2] {
+                Outcome::Skip
+            } else if oid >= candidates[3] {
+                Outcome::Bad
+            } else {
+                Outcome::Good
+            })
+        })
+        .unwrap();
+        assert_eq!(first_bad, candidates[3]);
+        assert_eq!(maybe, vec![candidates[2]]);
+    }
+
+    #[test]
+    fn search_rejects_passing_bad_commit() {
+        let candidates = oids(3);
+        assert!(search(&candidates, |_| Ok(Outcome::Good)).is_err());
+    }
+}
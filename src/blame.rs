@@ -0,0 +1,197 @@
+//! Attributing vendored lines to the upstream commits that last changed
+//! them.
+//!
+//! The host history of a vendored file is a series of merges, so `git
+//! blame` credits every line to whoever updated the dependency. Blaming the
+//! fetched upstream history instead, starting from the commit the
+//! dependency was last merged from, answers who wrote each line upstream.
+//! Lines edited in the host since then are reported without a commit.
+
+use super::{
+    VendorDep, config::Config, fetch_commit, format_time, ownership::Ownership, provenance,
+    vendor_ref_name,
+};
+use git2::{BlameOptions, Error, Oid, Repository};
+use serde::Serialize;
+use std::path::Path;
+
+/// One line of a vendored file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BlameLine {
+    /// The 1-based line number in the file.
+    pub line: usize,
+    /// The upstream commit that last changed the line, or `None` if it was
+    /// changed in the host repository.
+    pub commit: Option<String>,
+    pub author: Option<String>,
+    /// When the commit was authored, as an RFC 3339 timestamp.
+    pub time: Option<String>,
+    pub content: String,
+}
+
+/// A vendored file blamed against its upstream history.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Blame {
+    /// The dependency owning the file.
+    pub name: String,
+    /// The file's path, relative to the repository root.
+    pub path: String,
+    /// The upstream commit blame started from.
+    pub upstream: String,
+    pub lines: Vec<BlameLine>,
+}
+
+impl Blame {
+    /// The blame in the style of `git blame`, one line per line of the file.
+    pub fn to_text(&self) -> String {
+        let author_width = self
+            .lines
+            .iter()
+            .map(|line| {
+                line.author
+                    .as_deref()
+                    .unwrap_or(NOT_UPSTREAM)
+                    .chars()
+                    .count()
+            })
+            .max()
+            .unwrap_or(0);
+        let number_width = self.lines.len().to_string().len();
+        let mut out = String::new();
+        for line in &self.lines {
+            let commit = line.commit.as_deref().map_or("00000000", |c| &c[..8]);
+            let author = line.author.as_deref().unwrap_or(NOT_UPSTREAM);
+            let date = line.time.as_deref().map_or("          ", |t| &t[..10]);
+            out.push_str(&format!(
+                "{commit} ({author:<author_width$} {date} {:>number_width$}) {}\n",
+                line.line, line.content
+            ));
+        }
+        out
+    }
+}
+
+const NOT_UPSTREAM: &str = "Not upstream";
+
+/// Blame the vendored file at `path`, relative to the repository root,
+/// against the upstream history of the dependency among `deps` owning it.
+pub(crate) fn blame(
+    repo: &Repository,
+    deps: &[&VendorDep],
+    path: &str,
+    config: &Config,
+) -> Result<Blame, Error> {
+    let ownership = Ownership::new(repo, deps)?;
+    let dep = ownership
+        .owner(path)
+        .ok_or_else(|| Error::from_str(&format!("{path} is not vendored")))?;
+
+    let head_tree = repo.head()?.peel_to_tree()?;
+    let upstream = match provenance::read(repo, &head_tree, &dep.name)?
+        .and_then(|record| Oid::from_str(&record.commit).ok())
+    {
+        Some(commit) => {
+            if repo.find_commit(commit).is_err() {
+                fetch_commit(repo, dep, commit, config)?;
+            }
+            commit
+        }
+        None => {
+            let ref_name = vendor_ref_name(&config.ref_prefix, &dep.name);
+            repo.refname_to_id(&ref_name).map_err(|_| {
+                Error::from_str(&format!(
+                    "{} was never merged and vendor ref {ref_name} is not found. \
+                     Run fetch first.",
+                    dep.name
+                ))
+            })?
+        }
+    };
+    if repo
+        .find_commit(upstream)?
+        .tree()?
+        .get_path(Path::new(path))
+        .is_err()
+    {
+        return Err(Error::from_str(&format!(
+            "{path} does not exist upstream in {} at {upstream}",
+            dep.name
+        )));
+    }
+
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| Error::from_str("Repository has no working directory"))?;
+    let content = std::fs::read(workdir.join(path))
+        .map_err(|e| Error::from_str(&format!("Failed to read {path}: {e}")))?;
+
+    let mut opts = BlameOptions::new();
+    opts.newest_commit(upstream);
+    let committed = repo.blame_file(Path::new(path), Some(&mut opts))?;
+    let blame = committed.blame_buffer(&content)?;
+
+    let text = String::from_utf8_lossy(&content);
+    let mut lines = Vec::new();
+    for (i, content) in text.lines().enumerate() {
+        let hunk = blame.get_line(i + 1);
+        let commit = hunk
+            .as_ref()
+            .map(|hunk| hunk.final_commit_id())
+            .filter(|id| !id.is_zero());
+        let signature = hunk
+            .as_ref()
+            .filter(|_| commit.is_some())
+            .map(|hunk| hunk.final_signature());
+        lines.push(BlameLine {
+            line: i + 1,
+            commit: commit.map(|id| id.to_string()),
+            author: signature
+                .as_ref()
+                .map(|s| s.name().unwrap_or_default().to_string()),
+            time: signature.as_ref().map(|s| format_time(s.when().seconds())),
+            content: content.to_string(),
+        });
+    }
+
+    Ok(Blame {
+        name: dep.name.clone(),
+        path: path.to_string(),
+        upstream: upstream.to_string(),
+        lines,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_text_aligns_columns() {
+        let blame = Blame {
+            name: "owner/repo".into(),
+            path: "lib.txt".into(),
+            upstream: "a".repeat(40),
+            lines: vec![
+                BlameLine {
+                    line: 1,
+                    commit: Some("b".repeat(40)),
+                    author: Some("Ada".into()),
+                    time: Some("2026-01-02T03:04:05Z".into()),
+                    content: "upstream".into(),
+                },
+                BlameLine {
+                    line: 2,
+                    commit: None,
+                    author: None,
+                    time: None,
+                    content: "local".into(),
+                },
+            ],
+        };
+        assert_eq!(
+            blame.to_text(),
+            "bbbbbbbb (Ada          2026-01-02 1) upstream\n\
+             00000000 (Not upstream            2) local\n"
+        );
+    }
+}
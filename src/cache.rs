@@ -0,0 +1,143 @@
+//! Shared object caches for upstream fetches.
+//!
+//! With `vendor.cacheDir` set, each upstream is first fetched into a bare
+//! repository under that directory, one per URL. The vendoring repository
+//! borrows the cache's objects through `objects/info/alternates`, so its
+//! vendor refs are updated from the cache without copying anything, and
+//! repositories vendoring the same upstream share a single copy.
+//!
+//! A repository borrowing objects breaks when the cache is removed;
+//! [`dissociate`] copies them in and drops the link.
+
+use super::{VendorDep, run_git as git};
+use git2::{Error, Repository};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// Serializes edits of the alternates file by parallel fetches.
+static ALTERNATES: Mutex<()> = Mutex::new(());
+
+/// Open (creating it if needed) the cache for `url` under `dir`, and make
+/// `repo` borrow its objects.
+pub(crate) fn open(repo: &Repository, dir: &Path, url: &str) -> Result<Repository, Error> {
+    let path = dir.join(format!("{}.git", key(url)));
+    tracing::debug!(url, cache = %path.display(), "Using shared object cache");
+    let cache = match Repository::open_bare(&path) {
+        Ok(cache) => cache,
+        Err(_) => Repository::init_bare(&path)?,
+    };
+
+    let objects = cache.path().join("objects");
+    let line = objects
+        .to_str()
+        .ok_or_else(|| Error::from_str("Cache directory is not valid UTF-8"))?;
+    let _guard = ALTERNATES.lock().unwrap_or_else(|e| e.into_inner());
+    let mut lines = alternates(repo)?;
+    if !lines.iter().any(|l| l == line) {
+        lines.push(line.to_string());
+        write_alternates(repo, &lines)?;
+    }
+    // The open handle does not reread the alternates file.
+    repo.odb()?.add_disk_alternate(line)?;
+
+    Ok(cache)
+}
+
+/// The ref under which a cache keeps the upstream ref fetched for `dep`.
+pub(crate) fn mirror_ref(dep: &VendorDep) -> String {
+    match (&dep.rev, &dep.branch) {
+        (Some(rev), _) => format!("refs/pinned/{rev}"),
+        (None, Some(branch)) => format!("refs/heads/{branch}"),
+        (None, None) => "refs/upstream/HEAD".to_string(),
+    }
+}
+
+/// Copy every object `repo` borrows from caches under `dir` into it and
+/// stop borrowing from them, returning whether there were any.
+pub(crate) fn dissociate(repo: &Repository, dir: &Path) -> Result<bool, Error> {
+    let _guard = ALTERNATES.lock().unwrap_or_else(|e| e.into_inner());
+    let dir = canonical(dir);
+    let (cached, kept): (Vec<String>, Vec<String>) = alternates(repo)?
+        .into_iter()
+        .partition(|line| canonical(Path::new(line)).starts_with(&dir));
+    if cached.is_empty() {
+        return Ok(false);
+    }
+
+    // Without `-l`, objects reachable from alternates are packed too.
+    git(repo.path(), &["repack", "-a", "-d", "-q"])?;
+    write_alternates(repo, &kept)?;
+    Ok(true)
+}
+
+/// The cache directory name for `url`, e.g. `github.com_owner_repo`.
+fn key(url: &str) -> String {
+    let url = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let url = url.trim_end_matches('/').trim_end_matches(".git");
+    url.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>()
+        .trim_matches(['_', '.'])
+        .to_string()
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn alternates_path(repo: &Repository) -> PathBuf {
+    repo.path().join("objects").join("info").join("alternates")
+}
+
+fn alternates(repo: &Repository) -> Result<Vec<String>, Error> {
+    match fs::read_to_string(alternates_path(repo)) {
+        Ok(content) => Ok(content
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(Error::from_str(&format!("Failed to read alternates: {e}"))),
+    }
+}
+
+fn write_alternates(repo: &Repository, lines: &[String]) -> Result<(), Error> {
+    let path = alternates_path(repo);
+    let result = if lines.is_empty() {
+        fs::remove_file(&path).or_else(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => Ok(()),
+            _ => Err(e),
+        })
+    } else {
+        fs::create_dir_all(path.parent().expect("alternates has a parent"))
+            .and_then(|()| fs::write(&path, lines.join("\n") + "\n"))
+    };
+    result.map_err(|e| Error::from_str(&format!("Failed to write alternates: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_flattens_url() {
+        assert_eq!(
+            key("https://github.com/owner/repo.git"),
+            "github.com_owner_repo"
+        );
+        assert_eq!(
+            key("git@github.com:owner/repo"),
+            "git_github.com_owner_repo"
+        );
+        assert_eq!(key("/srv/git/repo/"), "srv_git_repo");
+    }
+}
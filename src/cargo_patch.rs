@@ -0,0 +1,149 @@
+//! Generation of a Cargo `[patch]` section pointing at vendored crates.
+//!
+//! Each `Cargo.toml` with a `[package]` table inside a dependency's
+//! patterns is a vendored crate. It is patched for the source `Cargo.lock`
+//! resolves it from, so the build uses the vendored copy whether the host
+//! depends on it through crates.io, another registry or git; crates the
+//! lockfile does not mention are patched for their dependency's URL.
+
+use super::{VendorDep, import, ownership::Ownership};
+use git_filter_tree::walk_tree;
+use git2::{Error, ObjectType, Repository, Tree};
+use std::collections::BTreeMap;
+
+const BEGIN_MARKER: &str = "# BEGIN git-vendor";
+const END_MARKER: &str = "# END git-vendor";
+
+/// The source crates.io packages are patched for.
+const CRATES_IO: &str = "crates-io";
+
+/// The vendored crates in `tree`, as `[patch]` source to crate name to
+/// directory.
+pub(crate) fn crates(
+    repo: &Repository,
+    tree: &Tree,
+    deps: &[&VendorDep],
+    lockfile: Option<&str>,
+) -> Result<BTreeMap<String, BTreeMap<String, String>>, Error> {
+    let ownership = Ownership::new(repo, deps)?;
+    let locked = lockfile.map(import::locked_packages).unwrap_or_default();
+    let mut patches: BTreeMap<String, BTreeMap<String, String>> = BTreeMap::new();
+    walk_tree(repo, tree, |path, entry| {
+        let Ok(path) = std::str::from_utf8(path) else {
+            return Ok(());
+        };
+        let Some(dir) = path.strip_suffix("/Cargo.toml") else {
+            return Ok(());
+        };
+        if entry.kind() != Some(ObjectType::Blob) {
+            return Ok(());
+        }
+        let Some(dep) = ownership.owner(path) else {
+            return Ok(());
+        };
+        let blob = repo.find_blob(entry.id())?;
+        let Some(name) = std::str::from_utf8(blob.content())
+            .ok()
+            .and_then(import::package_name)
+        else {
+            return Ok(());
+        };
+        let source = locked
+            .iter()
+            .find(|package| package.name == name)
+            .and_then(|package| package.source.as_deref())
+            .map_or_else(|| dep.url.clone(), patch_source);
+        patches
+            .entry(source)
+            .or_default()
+            .insert(name, dir.to_string());
+        Ok(())
+    })?;
+    Ok(patches)
+}
+
+/// The `[patch]` key for packages locked from `source`.
+fn patch_source(source: &str) -> String {
+    if let Some(git) = source.strip_prefix("git+") {
+        let end = git.find(['?', '#']).unwrap_or(git.len());
+        return git[..end].to_string();
+    }
+    match source.strip_prefix("registry+").unwrap_or(source) {
+        "https://github.com/rust-lang/crates.io-index" | "sparse+https://index.crates.io/" => {
+            CRATES_IO.to_string()
+        }
+        registry => registry.to_string(),
+    }
+}
+
+/// Render the managed section patching each source's crates to their
+/// directories.
+pub(crate) fn render_section(patches: &BTreeMap<String, BTreeMap<String, String>>) -> String {
+    let mut section = format!("{BEGIN_MARKER}\n");
+    for (source, crates) in patches {
+        match source.as_str() {
+            CRATES_IO => section.push_str(&format!("[patch.{CRATES_IO}]\n")),
+            source => section.push_str(&format!("[patch.{}]\n", quote(source))),
+        }
+        for (name, dir) in crates {
+            section.push_str(&format!("{name} = {{ path = {} }}\n", quote(dir)));
+        }
+    }
+    section.push_str(END_MARKER);
+    section.push('\n');
+    section
+}
+
+/// Replace the managed section in `content`, or append it if absent.
+pub(crate) fn update(content: &str, section: &str) -> String {
+    super::replace_block(content, BEGIN_MARKER, END_MARKER, section)
+}
+
+/// `s` as a TOML basic string.
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patch_source_maps_lockfile_sources() {
+        assert_eq!(
+            patch_source("registry+https://github.com/rust-lang/crates.io-index"),
+            "crates-io"
+        );
+        assert_eq!(patch_source("sparse+https://index.crates.io/"), "crates-io");
+        assert_eq!(
+            patch_source("git+https://github.com/o/r?branch=main#0123abcd"),
+            "https://github.com/o/r"
+        );
+        assert_eq!(
+            patch_source("registry+https://example.com/index"),
+            "https://example.com/index"
+        );
+    }
+
+    #[test]
+    fn render_section_groups_by_source() {
+        let mut patches = BTreeMap::new();
+        patches.insert(
+            "crates-io".to_string(),
+            BTreeMap::from([("foo".to_string(), "vendor/foo".to_string())]),
+        );
+        patches.insert(
+            "https://github.com/o/r".to_string(),
+            BTreeMap::from([("bar".to_string(), "crates/bar".to_string())]),
+        );
+        assert_eq!(
+            render_section(&patches),
+            "# BEGIN git-vendor\n\
+             [patch.crates-io]\n\
+             foo = { path = \"vendor/foo\" }\n\
+             [patch.\"https://github.com/o/r\"]\n\
+             bar = { path = \"crates/bar\" }\n\
+             # END git-vendor\n"
+        );
+    }
+}
@@ -0,0 +1,405 @@
+//! Summaries of the upstream changes a dependency update brings in.
+//!
+//! The update runs from the commit recorded by the dependency's provenance
+//! to its fetched vendor ref. Each end is labelled with a fetched upstream
+//! tag where one points at it, and commits link to the upstream's web
+//! interface for the hosts whose URL layout is known.
+//!
+//! Optionally the upstream's own changelog file (`CHANGELOG`, `CHANGES`,
+//! `NEWS` or `HISTORY`, with or without an extension, at the root of its
+//! tree) is diffed between the two ends, and the sections that gained lines
+//! are included as release notes.
+
+use super::{VendorDep, config::Config, forge::Forge, provenance, tags, vendor_ref_name};
+use git2::{Error, Oid, Patch, Repository, Tree};
+use serde::Serialize;
+
+/// One end of an update.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Revision {
+    pub commit: String,
+    /// The newest upstream tag pointing at `commit`, if any was fetched.
+    pub tag: Option<String>,
+}
+
+impl Revision {
+    /// The tag, or else the abbreviated commit id.
+    pub fn label(&self) -> &str {
+        self.tag.as_deref().unwrap_or(short(&self.commit))
+    }
+}
+
+/// An upstream commit brought in by an update.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChangelogCommit {
+    pub id: String,
+    pub summary: String,
+    /// The commit's page on the upstream host, if known.
+    pub url: Option<String>,
+}
+
+/// The upstream changes between the vendored and the fetched commit of a
+/// dependency.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Changelog {
+    pub name: String,
+    /// The vendored commit; `None` if the dependency was never merged.
+    pub from: Option<Revision>,
+    /// The fetched commit.
+    pub to: Revision,
+    /// Commits in `to` but not `from`, newest first; empty when never merged.
+    pub commits: Vec<ChangelogCommit>,
+    /// A page comparing `from` with `to` on the upstream host, if known.
+    pub compare_url: Option<String>,
+    /// Sections added to the upstream changelog file, when requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notes: Option<Notes>,
+}
+
+/// The sections of an upstream changelog file that changed in an update.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Notes {
+    /// Path of the file in the upstream tree, e.g. `CHANGELOG.md`.
+    pub path: String,
+    /// The new or changed sections, in file order.
+    pub sections: Vec<String>,
+}
+
+impl Changelog {
+    /// A one-line summary followed by the commit subjects.
+    pub fn to_text(&self) -> String {
+        let mut out = format!("{}: {}\n", self.name, self.headline(false));
+        for commit in &self.commits {
+            out.push_str(&format!("  {} {}\n", short(&commit.id), commit.summary));
+        }
+        if let Some(notes) = &self.notes {
+            out.push_str(&format!("\n{}:\n", notes.path));
+            for line in notes.sections.join("\n\n").lines() {
+                match line {
+                    "" => out.push('\n'),
+                    line => out.push_str(&format!("  {line}\n")),
+                }
+            }
+        }
+        out
+    }
+
+    /// A summary ready to paste into a pull request description.
+    pub fn to_markdown(&self) -> String {
+        let arrow = match &self.from {
+            Some(from) => format!("{} → ", from.label()),
+            None => String::new(),
+        };
+        let mut out = format!("### {}: {arrow}{}\n\n", self.name, self.to.label());
+        out.push_str(&format!("Updates `{}` {}.", self.name, self.headline(true)));
+        if let Some(url) = &self.compare_url {
+            out.push_str(&format!(" [Compare]({url})"));
+        }
+        out.push('\n');
+        if !self.commits.is_empty() {
+            out.push('\n');
+        }
+        for commit in &self.commits {
+            let id = format!("`{}`", short(&commit.id));
+            let id = match &commit.url {
+                Some(url) => format!("[{id}]({url})"),
+                None => id,
+            };
+            out.push_str(&format!("- {id} {}\n", escape(&commit.summary)));
+        }
+        if let Some(notes) = &self.notes {
+            // Collapsed, so the upstream's own headings do not clash with
+            // the summary's.
+            out.push_str(&format!(
+                "\n<details>\n<summary>{}</summary>\n\n{}\n\n</details>\n",
+                notes.path,
+                notes.sections.join("\n\n")
+            ));
+        }
+        out
+    }
+
+    fn headline(&self, markdown: bool) -> String {
+        let describe = |rev: &Revision| match (&rev.tag, markdown) {
+            (Some(tag), true) => format!("{tag} (`{}`)", short(&rev.commit)),
+            (Some(tag), false) => format!("{tag} ({})", short(&rev.commit)),
+            (None, true) => format!("`{}`", short(&rev.commit)),
+            (None, false) => short(&rev.commit).to_string(),
+        };
+        let to = describe(&self.to);
+        let Some(from) = &self.from else {
+            return format!("to {to} (not merged before)");
+        };
+        let count = self.commits.len();
+        let plural = if count == 1 { "" } else { "s" };
+        match markdown {
+            true => format!("from {} to {to}: {count} commit{plural}", describe(from)),
+            false => format!("{} -> {to}, {count} commit{plural}", describe(from)),
+        }
+    }
+}
+
+pub(crate) fn changelog(
+    repo: &Repository,
+    dep: &VendorDep,
+    with_notes: bool,
+    config: &Config,
+) -> Result<Changelog, Error> {
+    let ref_name = vendor_ref_name(&config.ref_prefix, &dep.name);
+    let to = repo.refname_to_id(&ref_name).map_err(|_| {
+        Error::from_str(&format!(
+            "Vendor ref {ref_name} not found. Run fetch first."
+        ))
+    })?;
+    let head_tree = repo.head()?.peel_to_tree()?;
+    let from = provenance::read(repo, &head_tree, &dep.name)?
+        .and_then(|record| Oid::from_str(&record.commit).ok());
+
+    let tags = tags::list(repo, dep, false, config)?;
+    // Tags are sorted by version, so the last one is the newest.
+    let revision = |commit: Oid| Revision {
+        commit: commit.to_string(),
+        tag: tags
+            .iter()
+            .rev()
+            .find(|tag| tag.commit == commit.to_string())
+            .map(|tag| tag.name.clone()),
+    };
+
+    let mut commits = Vec::new();
+    if let Some(from) = from {
+        let mut walk = repo.revwalk()?;
+        walk.push(to)?;
+        if repo.find_commit(from).is_ok() {
+            walk.hide(from)?;
+        }
+        for id in walk {
+            let commit = repo.find_commit(id?)?;
+            commits.push(ChangelogCommit {
+                id: commit.id().to_string(),
+                summary: commit.summary().unwrap_or_default().to_string(),
+                url: web_url(&dep.url, &format!("commit/{}", commit.id())),
+            });
+        }
+    }
+
+    let notes = match from {
+        Some(from) if with_notes && from != to => match repo.find_commit(from) {
+            Ok(from) => notes(repo, &from.tree()?, &repo.find_commit(to)?.tree()?)?,
+            // Without the vendored commit (e.g. beyond a shallow fetch)
+            // there is nothing to diff against.
+            Err(_) => None,
+        },
+        _ => None,
+    };
+
+    Ok(Changelog {
+        name: dep.name.clone(),
+        notes,
+        compare_url: from
+            .filter(|&from| from != to)
+            .and_then(|from| web_url(&dep.url, &format!("compare/{from}...{to}"))),
+        from: from.map(revision),
+        to: revision(to),
+        commits,
+    })
+}
+
+/// Stems of the file names upstreams keep their changelog under, by
+/// preference.
+const NOTES_FILES: &[&str] = &["changelog", "changes", "news", "history"];
+
+/// The sections of the changelog file at the root of `new` that differ from
+/// the same file in `old`.
+fn notes(repo: &Repository, old: &Tree, new: &Tree) -> Result<Option<Notes>, Error> {
+    let stem = |name: &str| {
+        let name = name.to_ascii_lowercase();
+        match name.split_once('.') {
+            Some((stem, _)) => stem.to_string(),
+            None => name,
+        }
+    };
+    let Some(entry) = NOTES_FILES.iter().find_map(|file| {
+        new.iter().find(|entry| {
+            entry.kind() == Some(git2::ObjectType::Blob)
+                && entry.name().is_some_and(|name| stem(name) == *file)
+        })
+    }) else {
+        return Ok(None);
+    };
+    let path = entry.name().unwrap_or_default().to_string();
+
+    let new_blob = repo.find_blob(entry.id())?;
+    let old_blob = match old.get_name(&path) {
+        Some(entry) => Some(repo.find_blob(entry.id())?),
+        None => None,
+    };
+    if old_blob
+        .as_ref()
+        .is_some_and(|old| old.id() == new_blob.id())
+    {
+        return Ok(None);
+    }
+    let old_text = old_blob.as_ref().map_or(&[][..], |blob| blob.content());
+    let patch = Patch::from_buffers(old_text, None, new_blob.content(), None, None)?;
+
+    let mut added = Vec::new();
+    for hunk in 0..patch.num_hunks() {
+        for line in 0..patch.num_lines_in_hunk(hunk)? {
+            let line = patch.line_in_hunk(hunk, line)?;
+            if line.origin() == '+'
+                && let Some(number) = line.new_lineno()
+            {
+                added.push(number as usize - 1);
+            }
+        }
+    }
+
+    let text = String::from_utf8_lossy(new_blob.content());
+    let sections = sections(&text.lines().collect::<Vec<_>>(), &added);
+    Ok((!sections.is_empty()).then_some(Notes { path, sections }))
+}
+
+/// The sections of a changelog `lines` that contain any of the `added`
+/// (zero-based, ascending) line numbers.
+///
+/// Sections start at Markdown headings. Files without any are split into
+/// the runs of consecutive added lines instead.
+fn sections(lines: &[&str], added: &[usize]) -> Vec<String> {
+    let mut starts: Vec<usize> = (0..lines.len())
+        .filter(|&i| lines[i].starts_with('#'))
+        .collect();
+    let headed = !starts.is_empty();
+    if !headed {
+        starts = added
+            .iter()
+            .enumerate()
+            .filter(|&(i, &line)| i == 0 || added[i - 1] + 1 != line)
+            .map(|(_, &line)| line)
+            .collect();
+    } else if starts[0] != 0 {
+        starts.insert(0, 0);
+    }
+
+    let mut sections = Vec::new();
+    for (i, &start) in starts.iter().enumerate() {
+        let end = starts.get(i + 1).copied().unwrap_or(lines.len());
+        let end = match headed {
+            true => end,
+            // Runs of added lines end at the first line not added.
+            false => (start..end)
+                .find(|line| added.binary_search(line).is_err())
+                .unwrap_or(end),
+        };
+        if !added.iter().any(|line| (start..end).contains(line)) {
+            continue;
+        }
+        let section = lines[start..end].join("\n");
+        let section = section.trim_end().trim_start_matches('\n');
+        if !section.is_empty() {
+            sections.push(section.to_string());
+        }
+    }
+    sections
+}
+
+/// The page `page` (e.g. `commit/<id>`) of the upstream at `url` on hosts
+/// with a known web layout.
+fn web_url(url: &str, page: &str) -> Option<String> {
+    Forge::from_url(url).map(|forge| forge.web_url(page))
+}
+
+fn short(id: &str) -> &str {
+    &id[..id.len().min(7)]
+}
+
+/// Escape the characters that would start Markdown markup in a commit
+/// subject.
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn web_url_knows_common_hosts() {
+        assert_eq!(
+            web_url("https://github.com/owner/repo.git", "commit/abc").as_deref(),
+            Some("https://github.com/owner/repo/commit/abc")
+        );
+        assert_eq!(
+            web_url("git@gitlab.com:group/repo.git", "compare/a...b").as_deref(),
+            Some("https://gitlab.com/group/repo/-/compare/a...b")
+        );
+        assert_eq!(
+            web_url("https://git.example.com/repo.git", "commit/abc"),
+            None
+        );
+        assert_eq!(web_url("/srv/git/repo", "commit/abc"), None);
+    }
+
+    #[test]
+    fn sections_keeps_headed_sections_with_added_lines() {
+        let lines = [
+            "# Changelog",
+            "",
+            "## 1.1.0",
+            "- New thing",
+            "",
+            "## 1.0.0",
+            "- Old thing",
+        ];
+        assert_eq!(sections(&lines, &[2, 3, 4]), ["## 1.1.0\n- New thing"]);
+        assert_eq!(
+            sections(&lines, &[3, 6]),
+            ["## 1.1.0\n- New thing", "## 1.0.0\n- Old thing"]
+        );
+    }
+
+    #[test]
+    fn sections_splits_unheaded_files_into_added_runs() {
+        let lines = ["2.0", "  new", "", "1.0", "  old", "  fixed"];
+        assert_eq!(sections(&lines, &[0, 1, 5]), ["2.0\n  new", "  fixed"]);
+    }
+
+    #[test]
+    fn markdown_lists_linked_commits() {
+        let changelog = Changelog {
+            name: "owner/repo".into(),
+            from: Some(Revision {
+                commit: "0123456789abcdef0123456789abcdef01234567".into(),
+                tag: Some("v1.0.0".into()),
+            }),
+            to: Revision {
+                commit: "89abcdef0123456789abcdef0123456789abcdef".into(),
+                tag: None,
+            },
+            commits: vec![ChangelogCommit {
+                id: "89abcdef0123456789abcdef0123456789abcdef".into(),
+                summary: "Fix *all* the things".into(),
+                url: Some("https://github.com/owner/repo/commit/89abcdef".into()),
+            }],
+            compare_url: None,
+            notes: None,
+        };
+        assert_eq!(
+            changelog.to_markdown(),
+            "### owner/repo: v1.0.0 → 89abcde\n\n\
+             Updates `owner/repo` from v1.0.0 (`0123456`) to `89abcde`: 1 commit.\n\n\
+             - [`89abcde`](https://github.com/owner/repo/commit/89abcdef) Fix \\*all\\* the things\n"
+        );
+        assert_eq!(
+            changelog.to_text(),
+            "owner/repo: v1.0.0 (0123456) -> 89abcde, 1 commit\n  89abcde Fix *all* the things\n"
+        );
+    }
+}
@@ -0,0 +1,103 @@
+//! Removal of vendored content while keeping its tracking entry.
+
+use super::{VendorDep, ownership::Ownership, provenance::provenance_path};
+use git_filter_tree::bytes_to_path;
+use git2::{Error, Index, Repository};
+use std::fs;
+
+/// Remove from the index and working tree every tracked file owned by one
+/// of `selected`, along with their provenance records, and return the
+/// removed paths.  With `dry_run`, nothing is removed.
+///
+/// Ownership is decided among `deps`, so paths claimed by an earlier
+/// dependency are left alone.
+pub(crate) fn clean(
+    repo: &Repository,
+    deps: &[&VendorDep],
+    selected: &[&VendorDep],
+    dry_run: bool,
+) -> Result<Vec<String>, Error> {
+    let mut index = repo.index()?;
+    let records: Vec<Vec<u8>> = selected
+        .iter()
+        .map(|d| provenance_path(&d.name).into_bytes())
+        .collect();
+    let mut removed: Vec<Vec<u8>> = owned_paths(repo, &index, deps, selected)?
+        .into_iter()
+        .chain(index_paths(&index).filter(|path| records.contains(path)))
+        .collect();
+    removed.sort();
+    removed.dedup();
+
+    if !dry_run {
+        remove(repo, &mut index, &removed)?;
+        index.write()?;
+    }
+
+    Ok(removed.iter().map(|p| display_path(p)).collect())
+}
+
+/// `path` for display, with bytes that are not valid UTF-8 replaced.
+pub(crate) fn display_path(path: &[u8]) -> String {
+    String::from_utf8_lossy(path).into_owned()
+}
+
+/// Paths in `index` owned by one of `selected`, deciding ownership among
+/// `deps`.  `.gitattributes` files are never considered owned.
+pub(crate) fn owned_paths(
+    repo: &Repository,
+    index: &Index,
+    deps: &[&VendorDep],
+    selected: &[&VendorDep],
+) -> Result<Vec<Vec<u8>>, Error> {
+    let ownership = Ownership::new(repo, deps)?;
+    Ok(index_paths(index)
+        .filter(|path| {
+            ownership
+                .owner(path)
+                .is_some_and(|owner| selected.iter().any(|d| d.name == owner.name))
+        })
+        .filter(|path| path.rsplit(|&b| b == b'/').next() != Some(b".gitattributes"))
+        .collect())
+}
+
+/// Remove `paths` from `index` and the working tree, pruning directories
+/// left empty.  The index is not written.
+pub(crate) fn remove(
+    repo: &Repository,
+    index: &mut Index,
+    paths: &[impl AsRef<[u8]>],
+) -> Result<(), Error> {
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| Error::from_str("Repository has no working directory"))?;
+    for path in paths {
+        let path = bytes_to_path(path.as_ref());
+        index.remove_path(&path)?;
+
+        let full = workdir.join(&path);
+        match fs::remove_file(&full) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                return Err(Error::from_str(&format!(
+                    "Failed to remove {}: {e}",
+                    full.display()
+                )));
+            }
+        }
+        // Prune directories left empty, stopping at the first non-empty one.
+        let mut dir = full.parent();
+        while let Some(d) = dir {
+            if d == workdir || fs::remove_dir(d).is_err() {
+                break;
+            }
+            dir = d.parent();
+        }
+    }
+    Ok(())
+}
+
+fn index_paths(index: &Index) -> impl Iterator<Item = Vec<u8>> + '_ {
+    index.iter().map(|entry| entry.path)
+}
@@ -1,15 +1,89 @@
+use crate::complete;
 use clap::{Parser, Subcommand};
+use clap_complete::{ArgValueCandidates, Shell};
+use std::path::PathBuf;
 
 #[derive(Parser)]
 #[command(name = "git-vendor")]
 #[command(author, version, about = "In-source vendoring alternative to Git submodules and subtrees", long_about = None)]
+#[command(after_help = "\
+Exit codes:
+  0  Success
+  1  Any other failure
+  2  Invalid command line
+  3  Updates available (status, outdated)
+  4  Vendored content differs from its provenance (verify, sync --check, pre-commit --block)
+  5  Merge conflicts, or problems in the vendor graph (merge, check --strict)
+  6  An upstream could not be reached
+  7  Invalid settings or dependency definitions")]
 pub struct Cli {
+    /// Do not pipe output into a pager
+    #[arg(long, global = true)]
+    pub no_pager: bool,
+
+    /// Log more details to standard error; repeat for more
+    #[arg(short, long, global = true, action = clap::ArgAction::Count, conflicts_with = "quiet")]
+    pub verbose: u8,
+
+    /// Log only errors, and print no progress messages
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+
+    /// How to write log messages to standard error
+    #[arg(long, global = true, value_enum, default_value_t = LogFormat::Text)]
+    pub log_format: LogFormat,
+
+    /// Fetch, or run foreach commands for, up to this many dependencies at once
+    /// [env: GIT_VENDOR_JOBS]
+    #[arg(short, long, global = true, value_name = "N")]
+    pub jobs: Option<usize>,
+
+    /// Fetch only this many commits of upstream history [env: GIT_VENDOR_DEPTH]
+    #[arg(long, global = true, value_name = "N")]
+    pub depth: Option<u32>,
+
+    /// Refuse to contact upstream remotes [env: GIT_VENDOR_OFFLINE]
+    #[arg(long, global = true)]
+    pub offline: bool,
+
+    /// Resolve dependencies only to the commits their provenance records pin,
+    /// failing if a definition disagrees with its record [env: GIT_VENDOR_LOCKED]
+    #[arg(long, global = true)]
+    pub locked: bool,
+
+    /// Directory for shared upstream object caches [env: GIT_VENDOR_CACHE_DIR]
+    #[arg(long, global = true, value_name = "DIR")]
+    pub cache_dir: Option<PathBuf>,
+
+    /// How to report fetch and merge progress
+    #[arg(long, global = true, value_enum, default_value_t = ProgressFormat::Text)]
+    pub progress_format: ProgressFormat,
+
+    /// Report progress as NDJSON events; same as --progress-format ndjson
+    #[arg(long, global = true)]
+    pub events: bool,
+
     #[command(subcommand)]
     pub command: Commands,
 }
 
 #[derive(Subcommand)]
 pub enum Commands {
+    /// Set up the repository for vendoring, or adopt vendored directories already in it
+    Init {
+        /// Look for directories that look vendored and offer to track each
+        #[arg(long)]
+        scan: bool,
+
+        /// Track every directory whose upstream URL could be guessed, without asking
+        #[arg(short, long, requires = "scan", conflicts_with = "dry_run")]
+        yes: bool,
+
+        /// List what was found without tracking anything
+        #[arg(short = 'n', long, requires = "scan")]
+        dry_run: bool,
+    },
+
     /// Track a new vendored dependency pattern
     Track {
         /// Gitattributes-style pattern (e.g. "vendor/lib/*", "deps/*/")
@@ -25,29 +99,209 @@ pub enum Commands {
         /// Explicit dependency name (defaults to owner/repo from URL)
         #[arg(short, long)]
         name: Option<String>,
+
+        /// Mark the pattern linguist-vendored (defaults to vendor.linguist)
+        #[arg(long, value_enum, num_args = 0..=1, default_missing_value = "vendored")]
+        linguist: Option<Linguist>,
+
+        /// Contact the remote to confirm it is reachable and has the branch
+        #[arg(long)]
+        verify: bool,
+
+        /// Fetch the dependency right after tracking it
+        #[arg(long)]
+        fetch: bool,
+
+        /// Commit the tracking change, then fetch and merge the dependency
+        #[arg(long)]
+        merge: bool,
     },
 
     /// Untrack a vendored dependency pattern
     Untrack {
         /// Gitattributes-style pattern to untrack
+        #[arg(add = ArgValueCandidates::new(complete::patterns))]
         pattern: String,
     },
 
+    /// Change the upstream URL of a vendored dependency
+    SetUrl {
+        /// Pattern of the dependency to update
+        #[arg(add = ArgValueCandidates::new(complete::patterns))]
+        pattern: String,
+
+        /// New upstream URL
+        url: String,
+    },
+
+    /// Pin a dependency to an upstream tag or commit
+    Pin {
+        /// Name of the dependency, or a pattern it tracks
+        #[arg(add = ArgValueCandidates::new(complete::names))]
+        dep: String,
+
+        /// Upstream tag or commit id to pin to
+        version: String,
+
+        /// Fetch the pinned version right away
+        #[arg(long)]
+        fetch: bool,
+
+        /// Fetch and merge the pinned version right away
+        #[arg(long)]
+        merge: bool,
+    },
+
+    /// Switch a pinned dependency back to following a branch
+    Unpin {
+        /// Name of the dependency, or a pattern it tracks
+        #[arg(add = ArgValueCandidates::new(complete::names))]
+        dep: String,
+
+        /// Branch to follow (defaults to the remote's default branch)
+        #[arg(short, long)]
+        branch: Option<String>,
+
+        /// Fetch the branch right away
+        #[arg(long)]
+        fetch: bool,
+
+        /// Fetch and merge the branch right away
+        #[arg(long)]
+        merge: bool,
+    },
+
+    /// Remove vendored files while keeping the dependency tracked
+    Clean {
+        /// Optional pattern to filter which dependencies to clean
+        #[arg(add = ArgValueCandidates::new(complete::patterns))]
+        pattern: Option<String>,
+
+        /// List the files that would be removed without removing them
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+    },
+
+    /// Discard local modifications to vendored files, restoring the merged content
+    Restore {
+        /// Optional pattern to filter which dependencies to restore
+        #[arg(add = ArgValueCandidates::new(complete::patterns))]
+        pattern: Option<String>,
+    },
+
+    /// Make vendored files match exactly what the provenance records pin
+    Sync {
+        /// Optional pattern to filter which dependencies to sync
+        #[arg(add = ArgValueCandidates::new(complete::patterns))]
+        pattern: Option<String>,
+
+        /// Only report differences, failing if there are any
+        #[arg(long)]
+        check: bool,
+    },
+
+    /// Untrack dependencies whose vendored files no longer exist
+    Prune {
+        /// Remove attribute lines, vendor refs and provenance records of
+        /// merged dependencies whose pattern matches nothing (the default)
+        #[arg(long)]
+        attrs: bool,
+
+        /// List the dependencies that would be pruned without pruning them
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+    },
+
+    /// Fetch the full upstream history of shallow-fetched dependencies
+    Unshallow {
+        /// Optional pattern to filter which dependencies to unshallow
+        #[arg(add = ArgValueCandidates::new(complete::patterns))]
+        pattern: Option<String>,
+    },
+
+    /// Expire vendor refs and drop the objects only they kept alive
+    Gc {
+        /// Keep reflog entries and refs of branches deleted upstream for
+        /// this long (e.g. "90d", "12w"; "0" expires them all)
+        #[arg(long, value_name = "AGE", value_parser = parse_age, default_value = "90d")]
+        expire: i64,
+
+        /// Also compact the shared object caches in the cache directory
+        #[arg(long)]
+        cache: bool,
+
+        /// List what would be expired without changing anything
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+    },
+
     /// Show status of vendored dependencies
     Status {
         /// Optional pattern to filter status output
+        #[arg(add = ArgValueCandidates::new(complete::patterns))]
         pattern: Option<String>,
+
+        /// Print one line per dependency from a template such as
+        /// '{name}\t{vendored_sha:.8}\t{behind}'.
+        ///
+        /// Placeholders: {name}, {host}, {pattern}, {url}, {branch}, {rev}, {ref},
+        /// {vendored_sha}, {upstream_sha}, {behind}, {merged_at}, {age_days},
+        /// {merged_by}, {fetched}, {dirty}, {size}, {state}. A ':' spec pads
+        /// ('<20', '>5') and/or truncates ('.8'). Use '{{' and '}}' for literal
+        /// braces; '\t' and '\n' are unescaped.
+        #[arg(long, value_name = "TEMPLATE")]
+        format: Option<String>,
+
+        /// Order dependencies by name, pending commits, size, or last update
+        #[arg(long, value_enum)]
+        sort: Option<StatusSort>,
+
+        /// Group dependencies by upstream host
+        #[arg(long, value_enum)]
+        group_by: Option<GroupBy>,
+
+        /// Contact each upstream and report unreachable hosts, moved
+        /// repositories, and deleted branches (no objects are downloaded)
+        #[arg(long, conflicts_with = "format")]
+        check_remotes: bool,
     },
 
     /// Fetch latest content from vendored dependency sources
     Fetch {
         /// Optional pattern to filter which dependencies to fetch
+        #[arg(add = ArgValueCandidates::new(complete::patterns))]
         pattern: Option<String>,
+
+        /// Also fetch the dependencies vendored by the fetched upstreams
+        #[arg(short, long)]
+        recursive: bool,
+
+        /// Delete vendor refs of tags and tracked branches deleted upstream
+        #[arg(short, long)]
+        prune: bool,
+
+        /// Fetch upstream tags (defaults to vendor.fetchTags)
+        #[arg(long, overrides_with = "no_tags")]
+        tags: bool,
+
+        /// Do not fetch upstream tags, unless a dependency sets fetch-tags=true
+        #[arg(long, overrides_with = "tags")]
+        no_tags: bool,
+
+        /// Copy objects borrowed from the shared cache into the repository
+        /// so it no longer depends on the cache directory
+        #[arg(long)]
+        dissociate: bool,
+
+        /// How many levels of transitive dependencies to follow
+        #[arg(long, value_name = "N", default_value_t = 3, requires = "recursive")]
+        max_depth: usize,
     },
 
     /// Merge latest content from vendored dependency sources
     Merge {
         /// Optional pattern to filter which dependencies to merge
+        #[arg(add = ArgValueCandidates::new(complete::patterns))]
         pattern: Option<String>,
 
         /// Perform the merge but do not create a commit
@@ -55,11 +309,538 @@ pub enum Commands {
         no_commit: bool,
 
         /// Create a single non-merge commit instead of a merge commit
-        #[arg(long)]
+        /// (defaults to vendor.squash when merging a single dependency)
+        #[arg(long, overrides_with = "no_squash")]
         squash: bool,
 
+        /// Create a merge commit even if vendor.squash is set
+        #[arg(long, overrides_with = "squash")]
+        no_squash: bool,
+
         /// Custom merge commit message
         #[arg(short, long)]
         message: Option<String>,
+
+        /// Refuse to merge upstream commits without a good signature
+        #[arg(long, visible_alias = "verify")]
+        verify_signatures: bool,
+
+        /// Fail instead of deepening a shallow fetch that lacks the
+        /// previously vendored commit
+        #[arg(long)]
+        no_deepen: bool,
+
+        /// Print the files each dependency's merge adds, modifies and deletes
+        #[arg(long)]
+        stat: bool,
+
+        /// Ask before applying each dependency's merge, showing its changes
+        #[arg(long)]
+        confirm: bool,
+
+        /// Attach each merge's provenance record to its commit as a note in
+        /// refs/notes/vendor (defaults to vendor.notes)
+        #[arg(long)]
+        notes: bool,
+    },
+
+    /// List dependencies with upstream content not yet merged
+    Outdated {
+        /// Optional pattern to filter which dependencies to check
+        #[arg(add = ArgValueCandidates::new(complete::patterns))]
+        pattern: Option<String>,
+
+        /// Fetch from vendor sources before comparing, or with
+        /// vendor.forgeApi ask the forge hosting them
+        #[arg(long)]
+        fetch: bool,
+
+        /// Instead list dependencies not merged within this window (e.g. "90d", "12w")
+        #[arg(long, value_name = "AGE", value_parser = parse_age)]
+        stale: Option<i64>,
+
+        /// Print the result as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Print outdated dependencies as annotations for a CI system
+        #[arg(long, value_enum, value_name = "FORMAT", conflicts_with_all = ["json", "stale"])]
+        output: Option<OutputFormat>,
+    },
+
+    /// Periodically fetch vendor sources and report new upstream content
+    Watch {
+        /// Optional pattern to filter which dependencies to watch
+        #[arg(add = ArgValueCandidates::new(complete::patterns))]
+        pattern: Option<String>,
+
+        /// Time between polls (e.g. "30m", "1h", "1d")
+        #[arg(short, long, value_name = "AGE", default_value = "1h", value_parser = parse_interval)]
+        interval: i64,
+
+        /// Print one JSON event per line instead of human-readable text
+        #[arg(long)]
+        json: bool,
+
+        /// Shell command to run for each update, with details in GIT_VENDOR_* variables
+        #[arg(long, value_name = "COMMAND")]
+        exec: Option<String>,
+
+        /// Poll once and exit
+        #[arg(long)]
+        once: bool,
+    },
+
+    /// List the upstream tags of a dependency, marking the vendored one
+    Tags {
+        /// Name of the dependency, or a pattern it tracks
+        #[arg(add = ArgValueCandidates::new(complete::names))]
+        dep: String,
+
+        /// Ask the upstream instead of reading fetched tags
+        #[arg(long)]
+        remote: bool,
+
+        /// Print the result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Summarize the upstream commits a fetched update brings in
+    Changelog {
+        /// Name of the dependency, or a pattern it tracks
+        #[arg(add = ArgValueCandidates::new(complete::names))]
+        dep: String,
+
+        /// Print a Markdown summary ready to paste into a pull request
+        #[arg(long)]
+        markdown: bool,
+
+        /// Include the sections added to the upstream CHANGELOG or NEWS file
+        #[arg(long)]
+        notes: bool,
+    },
+
+    /// Find the upstream commit that broke a test, vendoring each candidate
+    /// into a temporary worktree
+    Bisect {
+        /// Name of the dependency, or a pattern it tracks
+        #[arg(add = ArgValueCandidates::new(complete::names))]
+        dep: String,
+
+        /// Test command, run through sh in the worktree; exit status 0 marks
+        /// a commit good, 125 skips it, and any other marks it bad
+        command: String,
+
+        /// Upstream tag or commit known to be good [default: the commit
+        /// last merged]
+        #[arg(long, value_name = "REV")]
+        good: Option<String>,
+
+        /// Upstream tag or commit known to be bad [default: the fetched
+        /// vendor ref]
+        #[arg(long, value_name = "REV")]
+        bad: Option<String>,
+
+        /// Print the result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show how vendored content differs from an upstream revision, or how
+    /// two upstream revisions differ in the content vendored
+    Diff {
+        /// Name of the dependency, or a pattern it tracks
+        #[arg(add = ArgValueCandidates::new(complete::names))]
+        dep: String,
+
+        /// Compare two upstream revisions instead, e.g. v1.4..v2.0; an empty
+        /// side means the commit last merged or the fetched vendor ref
+        #[arg(value_name = "REV1..REV2", value_parser = parse_range, conflicts_with = "upstream")]
+        range: Option<(Option<String>, Option<String>)>,
+
+        /// Upstream branch, tag or commit to compare with [default: the
+        /// fetched vendor ref]
+        #[arg(long, value_name = "REV")]
+        upstream: Option<String>,
+    },
+
+    /// Show the upstream commit and author that last changed each line of a
+    /// vendored file
+    Blame {
+        /// Path of the vendored file
+        path: PathBuf,
+
+        /// Print the result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show the resolved configuration of a dependency and where each value
+    /// comes from
+    Explain {
+        /// Name of the dependency, or a pattern it tracks
+        #[arg(add = ArgValueCandidates::new(complete::names))]
+        dep: String,
+
+        /// Print the result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Verify vendored content against its recorded upstream provenance
+    Verify {
+        /// Optional pattern to filter which dependencies to verify
+        #[arg(add = ArgValueCandidates::new(complete::patterns))]
+        pattern: Option<String>,
+
+        /// Print failures as annotations for a CI system
+        #[arg(long, value_enum, value_name = "FORMAT")]
+        output: Option<OutputFormat>,
+    },
+
+    /// Run a command in each vendored dependency's directory, like git submodule foreach
+    #[command(after_help = "\
+The command runs with these environment variables set:
+  VENDOR_NAME     The dependency's name
+  VENDOR_PATH     Its path, relative to the repository root
+  VENDOR_PATTERN  Its pattern
+  VENDOR_URL      Its upstream URL
+  VENDOR_BRANCH   Its branch, if any
+  VENDOR_SHA      The upstream commit last merged, if any")]
+    Foreach {
+        /// Optional pattern to filter which dependencies to run the command for
+        #[arg(add = ArgValueCandidates::new(complete::patterns))]
+        pattern: Option<String>,
+
+        /// The command; a single argument is run by the shell
+        #[arg(last = true, required = true, value_name = "COMMAND")]
+        command: Vec<String>,
+    },
+
+    /// Install a pre-commit hook guarding vendored paths against edits
+    InstallHooks {
+        /// Only warn about edits to vendored paths instead of rejecting the commit
+        #[arg(long)]
+        warn: bool,
+
+        /// Replace an existing pre-commit hook
+        #[arg(short, long)]
+        force: bool,
+    },
+
+    /// Configure aliases, the pre-commit hook, a merge driver and vendor.*
+    /// defaults; safe to run again after tracking new dependencies
+    Setup {
+        /// Write aliases, the merge driver and defaults to the global git config
+        #[arg(long)]
+        global: bool,
+
+        /// Replace values set differently and a foreign pre-commit hook
+        #[arg(short, long)]
+        force: bool,
+    },
+
+    /// Print a script completing commands, dependency names and patterns
+    #[command(after_help = "\
+Load it from your shell's startup file, e.g. for bash:
+  source <(git-vendor completions bash)")]
+    Completions {
+        /// Shell to complete for
+        shell: Shell,
+    },
+
+    /// Check staged changes for edits to vendored paths (run by the pre-commit hook)
+    #[command(hide = true)]
+    PreCommit {
+        /// Fail if vendored paths were edited
+        #[arg(long)]
+        block: bool,
+    },
+
+    /// Open an interactive dashboard of vendored dependencies
+    #[cfg(feature = "tui")]
+    Ui {
+        /// Optional pattern to filter which dependencies to show
+        #[arg(add = ArgValueCandidates::new(complete::patterns))]
+        pattern: Option<String>,
+    },
+
+    /// Generate or update the CODEOWNERS section for vendored paths
+    Codeowners {
+        /// Fail if the CODEOWNERS file is out of date instead of updating it
+        #[arg(long)]
+        check: bool,
+    },
+
+    /// Point Cargo at vendored crates with a [patch] section in Cargo.toml
+    #[command(name = "cargo-patch")]
+    CargoPatch {
+        /// Fail if Cargo.toml is out of date instead of updating it
+        #[arg(long)]
+        check: bool,
+    },
+
+    /// Generate a software bill of materials for vendored dependencies
+    Sbom {
+        /// Optional pattern to filter which dependencies to include
+        #[arg(add = ArgValueCandidates::new(complete::patterns))]
+        pattern: Option<String>,
+
+        /// SBOM document format
+        #[arg(short, long, value_enum, default_value = "cyclonedx")]
+        format: SbomFormat,
+
+        /// Write the document to a file instead of standard output
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Show the vendor relationships between this repository and its upstreams
+    Graph {
+        /// Optional pattern to filter which dependencies to include
+        #[arg(add = ArgValueCandidates::new(complete::patterns))]
+        pattern: Option<String>,
+
+        /// Graph format
+        #[arg(short, long, value_enum, default_value = "dot")]
+        format: GraphFormat,
+
+        /// How many levels of transitive dependencies to follow
+        #[arg(long, value_name = "N", default_value_t = 3)]
+        max_depth: usize,
+    },
+
+    /// Check the vendor graph for cycles and upstreams vendored at different versions
+    Check {
+        /// Optional pattern to filter which dependencies to check
+        #[arg(add = ArgValueCandidates::new(complete::patterns))]
+        pattern: Option<String>,
+
+        /// How many levels of transitive dependencies to follow
+        #[arg(long, value_name = "N", default_value_t = 3)]
+        max_depth: usize,
+
+        /// Exit with an error if any problem is found
+        #[arg(long)]
+        strict: bool,
+    },
+
+    /// Move dependency definitions between .gitattributes and .gitvendor.toml
+    Migrate {
+        /// Format to store the definitions in
+        #[arg(long, value_enum)]
+        to: SourceFormat,
     },
+
+    /// Create dependencies from another tool's locked git dependencies
+    Import {
+        /// Tool whose lockfile to read
+        #[arg(value_enum)]
+        from: ImportFormat,
+
+        /// Lockfile, go.mod or modules.txt, or a vendor directory; defaults to
+        /// Cargo.lock, go.mod, or package-lock.json or yarn.lock
+        path: Option<PathBuf>,
+
+        /// Report what would be imported without writing definitions
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+    },
+
+    /// Describe vendored dependencies in another tool's metadata format
+    Export {
+        /// Tool whose metadata to write
+        #[arg(value_enum)]
+        to: ExportFormat,
+
+        /// File to write; defaults to vendor/modules.txt
+        path: Option<PathBuf>,
+
+        /// Fail if the file is out of date instead of updating it
+        #[arg(long)]
+        check: bool,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum LogFormat {
+    /// Human-readable lines
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ProgressFormat {
+    /// Human-readable messages
+    Text,
+    /// One JSON object per line
+    Ndjson,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum Linguist {
+    /// Write no linguist attributes
+    Off,
+    /// Write linguist-vendored
+    Vendored,
+    /// Write linguist-vendored and -diff
+    NoDiff,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum StatusSort {
+    /// Alphabetically by name
+    Name,
+    /// Most pending upstream commits first
+    Behind,
+    /// Largest vendored content first
+    Size,
+    /// Least recently merged first
+    LastUpdated,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum GroupBy {
+    /// Upstream host
+    Host,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum SbomFormat {
+    /// CycloneDX JSON
+    #[value(name = "cyclonedx")]
+    CycloneDx,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// GitHub Actions `::warning` and `::error` workflow commands
+    Github,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum GraphFormat {
+    /// Graphviz DOT
+    Dot,
+    /// JSON nodes and edges
+    Json,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum SourceFormat {
+    /// A .gitvendor.toml manifest
+    Toml,
+    /// Vendor lines in .gitattributes
+    Attributes,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum ImportFormat {
+    /// Git dependencies locked in Cargo.lock
+    Cargo,
+    /// Go modules required by go.mod or recorded in vendor/modules.txt
+    Gomod,
+    /// Git dependencies locked in package-lock.json or yarn.lock
+    Npm,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// vendor/modules.txt describing the vendored Go modules
+    Gomod,
+}
+
+/// Parse an age or interval such as `90d` into seconds.
+///
+/// Supported units are `s`, `m`, `h`, `d`, and `w`; a bare number is days.
+fn parse_age(s: &str) -> Result<i64, String> {
+    let (digits, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => s.split_at(i),
+        None => (s, "d"),
+    };
+    let n: i64 = digits.parse().map_err(|_| format!("invalid age '{s}'"))?;
+    let scale = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86_400,
+        "w" => 7 * 86_400,
+        _ => {
+            return Err(format!(
+                "invalid age unit in '{s}' (expected s, m, h, d, or w)"
+            ));
+        }
+    };
+    n.checked_mul(scale)
+        .ok_or_else(|| format!("age out of range: {s}"))
+}
+
+/// Parse a polling interval like [`parse_age`], rejecting 0, which would
+/// poll without pause.
+fn parse_interval(s: &str) -> Result<i64, String> {
+    match parse_age(s)? {
+        0 => Err(format!("interval must be longer than 0: {s}")),
+        seconds => Ok(seconds),
+    }
+}
+
+/// Parse a `REV1..REV2` range into its sides, `None` where a side is
+/// empty.
+fn parse_range(s: &str) -> Result<(Option<String>, Option<String>), String> {
+    let (from, to) = s
+        .split_once("..")
+        .filter(|(from, to)| !from.ends_with('.') && !to.starts_with('.'))
+        .ok_or_else(|| format!("invalid range '{s}'; expected REV1..REV2"))?;
+    let side = |rev: &str| Some(rev.to_string()).filter(|rev| !rev.is_empty());
+    Ok((side(from), side(to)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_age_units() {
+        assert_eq!(parse_age("90d"), Ok(90 * 86_400));
+        assert_eq!(parse_age("2w"), Ok(14 * 86_400));
+        assert_eq!(parse_age("30"), Ok(30 * 86_400));
+        assert_eq!(parse_age("12h"), Ok(12 * 3600));
+    }
+
+    #[test]
+    fn watch_interval_must_be_positive() {
+        let parse = |interval| Cli::try_parse_from(["git-vendor", "watch", "--interval", interval]);
+        match parse("30m").unwrap().command {
+            Commands::Watch { interval, .. } => assert_eq!(interval, 30 * 60),
+            _ => unreachable!(),
+        }
+        assert!(parse("0").is_err());
+        assert!(parse("0s").is_err());
+    }
+
+    #[test]
+    fn parse_range_sides() {
+        assert_eq!(
+            parse_range("v1.4..v2.0"),
+            Ok((Some("v1.4".into()), Some("v2.0".into())))
+        );
+        assert_eq!(parse_range("v1.4.."), Ok((Some("v1.4".into()), None)));
+        assert_eq!(parse_range("..v2.0"), Ok((None, Some("v2.0".into()))));
+        assert_eq!(parse_range(".."), Ok((None, None)));
+    }
+
+    #[test]
+    fn parse_range_rejects_invalid() {
+        assert!(parse_range("v1.4").is_err());
+        assert!(parse_range("v1.4...v2.0").is_err());
+    }
+
+    #[test]
+    fn parse_age_rejects_invalid() {
+        assert!(parse_age("d").is_err());
+        assert!(parse_age("5y").is_err());
+        assert!(parse_age("9223372036854775807w").is_err());
+    }
 }
@@ -0,0 +1,106 @@
+//! Generation of a CODEOWNERS section for vendored paths.
+
+use super::VendorDep;
+
+const BEGIN_MARKER: &str = "# BEGIN git-vendor";
+const END_MARKER: &str = "# END git-vendor";
+
+/// Candidate CODEOWNERS locations, in the order forges look them up.
+pub(crate) const LOCATIONS: [&str; 3] = [".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+
+/// Render the managed section mapping each pattern to its owners.
+///
+/// Owners come from the dependency's `codeowners=` attribute (comma
+/// separated), falling back to `default_owners`. Dependencies without
+/// owners are omitted.
+pub(crate) fn render_section(deps: &[&VendorDep], default_owners: Option<&str>) -> String {
+    let mut section = format!("{BEGIN_MARKER}\n");
+    for dep in deps {
+        let Some(owners) = dep.codeowners.as_deref().or(default_owners) else {
+            continue;
+        };
+        let owners: Vec<&str> = owners.split([',', ' ']).filter(|o| !o.is_empty()).collect();
+        if owners.is_empty() {
+            continue;
+        }
+        for pattern in dep.patterns() {
+            // CODEOWNERS escapes spaces in paths with a backslash.
+            let pattern = pattern.replace(' ', "\\ ");
+            section.push_str(&format!("{pattern} {}\n", owners.join(" ")));
+        }
+    }
+    section.push_str(END_MARKER);
+    section.push('\n');
+    section
+}
+
+/// Replace the managed section in `content`, or append it if absent.
+pub(crate) fn update(content: &str, section: &str) -> String {
+    super::replace_block(content, BEGIN_MARKER, END_MARKER, section)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dep(pattern: &str, codeowners: Option<&str>) -> VendorDep {
+        VendorDep {
+            name: "o/r".into(),
+            pattern: pattern.into(),
+            url: "u".into(),
+            codeowners: codeowners.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn render_section_uses_attribute_then_default() {
+        let a = dep("vendor/a/", Some("@org/a,@alice"));
+        let b = dep("vendor/b/", None);
+        assert_eq!(
+            render_section(&[&a, &b], Some("@org/third-party")),
+            "# BEGIN git-vendor\n\
+             vendor/a/ @org/a @alice\n\
+             vendor/b/ @org/third-party\n\
+             # END git-vendor\n"
+        );
+    }
+
+    #[test]
+    fn render_section_escapes_spaces() {
+        let a = dep("third party/a/", Some("@alice"));
+        assert_eq!(
+            render_section(&[&a], None),
+            "# BEGIN git-vendor\nthird\\ party/a/ @alice\n# END git-vendor\n"
+        );
+    }
+
+    #[test]
+    fn render_section_skips_unowned() {
+        let a = dep("vendor/a/", None);
+        assert_eq!(
+            render_section(&[&a], None),
+            "# BEGIN git-vendor\n# END git-vendor\n"
+        );
+    }
+
+    #[test]
+    fn update_appends_section() {
+        let section = "# BEGIN git-vendor\nx @y\n# END git-vendor\n";
+        assert_eq!(update("", section), section);
+        assert_eq!(
+            update("* @owner", section),
+            format!("* @owner\n\n{section}")
+        );
+    }
+
+    #[test]
+    fn update_replaces_existing_section() {
+        let content = "* @owner\n\n# BEGIN git-vendor\nold @x\n# END git-vendor\n/docs @docs\n";
+        let section = "# BEGIN git-vendor\nnew @y\n# END git-vendor\n";
+        assert_eq!(
+            update(content, section),
+            "* @owner\n\n# BEGIN git-vendor\nnew @y\n# END git-vendor\n/docs @docs\n"
+        );
+    }
+}
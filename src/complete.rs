@@ -0,0 +1,75 @@
+//! Shell completion, including the dependencies of the current repository.
+//!
+//! The script printed by `git-vendor completions <shell>` calls back into
+//! `git-vendor` with [`VAR`] set each time it completes, so dependency
+//! names and patterns are read from the repository the shell is in.
+
+use clap_complete::{CompletionCandidate, Shell, env::Shells};
+use git_vendor::{VendorDep, source};
+use std::io::Write;
+
+/// Environment variable naming the shell to complete for.
+pub const VAR: &str = "GIT_VENDOR_COMPLETE";
+
+const BIN: &str = "git-vendor";
+
+/// Write the script registering completions for `shell`.
+pub fn write_registration(shell: Shell, out: &mut dyn Write) -> std::io::Result<()> {
+    Shells::builtins()
+        .completer(&shell.to_string())
+        .expect("every clap_complete shell has a completer")
+        .write_registration(VAR, BIN, BIN, BIN, out)
+}
+
+/// The patterns of the current repository's dependencies, described by
+/// their names.
+pub fn patterns() -> Vec<CompletionCandidate> {
+    deps()
+        .iter()
+        .flat_map(|dep| {
+            dep.patterns().into_iter().map(|pattern| {
+                CompletionCandidate::new(pattern).help(Some(dep.name.clone().into()))
+            })
+        })
+        .collect()
+}
+
+/// The names of the current repository's dependencies, described by their
+/// patterns.
+pub fn names() -> Vec<CompletionCandidate> {
+    deps()
+        .iter()
+        .map(|dep| CompletionCandidate::new(&dep.name).help(Some(dep.patterns().join(" ").into())))
+        .collect()
+}
+
+/// The dependencies defined at the root of the current repository; none if
+/// there is no repository or its definitions cannot be read.
+fn deps() -> Vec<VendorDep> {
+    let Ok(repo) = git2::Repository::discover(".") else {
+        return Vec::new();
+    };
+    let Some(workdir) = repo.workdir() else {
+        return Vec::new();
+    };
+    source::open(&repo, &workdir.join(".gitattributes"))
+        .and_then(|source| source.load())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::ValueEnum;
+
+    #[test]
+    fn registration_calls_back_with_the_variable() {
+        for shell in Shell::value_variants() {
+            let mut script = Vec::new();
+            write_registration(*shell, &mut script).unwrap();
+            let script = String::from_utf8(script).unwrap();
+            assert!(script.contains(VAR), "{shell}");
+            assert!(script.contains(BIN), "{shell}");
+        }
+    }
+}
@@ -0,0 +1,614 @@
+//! Settings shared by the library and the command line.
+//!
+//! Each setting is resolved from, in order of precedence:
+//!
+//! 1. an override set with [`set_overrides`] (the CLI's global flags),
+//! 2. a `GIT_VENDOR_*` environment variable,
+//! 3. a `vendor.*` git config key,
+//! 4. the built-in default.
+//!
+//! | Setting     | Environment            | Git config        | Default |
+//! |-------------|------------------------|-------------------|---------|
+//! | `jobs`      | `GIT_VENDOR_JOBS`      | `vendor.jobs`     | 1       |
+//! | `depth`     | `GIT_VENDOR_DEPTH`     | `vendor.depth`    | full    |
+//! | `offline`   | `GIT_VENDOR_OFFLINE`   | `vendor.offline`  | false   |
+//! | `cache_dir` | `GIT_VENDOR_CACHE_DIR` | `vendor.cacheDir` | none    |
+//! | `default_branch` | `GIT_VENDOR_DEFAULT_BRANCH` | `vendor.defaultBranch` | remote `HEAD` |
+//! | `squash`    | `GIT_VENDOR_SQUASH`    | `vendor.squash`   | false   |
+//! | `ref_prefix` | `GIT_VENDOR_REF_PREFIX` | `vendor.refPrefix` | `refs/vendor/` |
+//! | `remotes`   | `GIT_VENDOR_REMOTES`   | `vendor.remotes`  | false   |
+//! | `prune`     | `GIT_VENDOR_PRUNE`     | `vendor.prune`    | false   |
+//! | `dissociate` | `GIT_VENDOR_DISSOCIATE` | `vendor.dissociate` | false |
+//! | `protocol_version` | `GIT_VENDOR_PROTOCOL_VERSION` | `vendor.protocolVersion` | 2 |
+//! | `fetch_tags` | `GIT_VENDOR_FETCH_TAGS` | `vendor.fetchTags` | true |
+//! | `locked`    | `GIT_VENDOR_LOCKED`    | `vendor.locked`   | false   |
+//! | `forge_api` | `GIT_VENDOR_FORGE_API` | `vendor.forgeApi` | false   |
+//! | `notes`     | `GIT_VENDOR_NOTES`     | `vendor.notes`    | false   |
+//!
+//! Git config values can be committed to an included file so a team shares
+//! the same defaults.
+//!
+//! Settings that only make sense for one machine can also be given per
+//! dependency in a `[vendor "<name>"]` section, which is merged over the
+//! dependency's attributes by [`apply_dep_overrides`]:
+//!
+//! ```text
+//! [vendor "owner/repo"]
+//!     url = https://mirror.example.com/owner/repo.git
+//!     depth = 1
+//!     proxy = http://proxy.example.com:3128
+//!     credentialHelper = store
+//! ```
+//!
+//! A per-dependency `depth` wins over the global setting; `0` fetches the
+//! full history. Likewise a dependency's `fetch-tags=` attribute wins over
+//! `fetch_tags`.
+
+use super::VendorDep;
+use git2::{Error, ErrorClass, ErrorCode, Repository};
+use std::{path::PathBuf, sync::Mutex};
+
+/// One layer of settings; unset values fall through to the next layer.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Settings {
+    /// Number of dependencies fetched concurrently.
+    pub jobs: Option<usize>,
+    /// Fetch only this many commits of upstream history.
+    pub depth: Option<u32>,
+    /// Refuse operations that contact upstream remotes.
+    pub offline: Option<bool>,
+    /// Directory for shared upstream object caches.
+    pub cache_dir: Option<PathBuf>,
+    /// Branch tracked by new dependencies when none is given.
+    pub default_branch: Option<String>,
+    /// Squash merges by default.
+    pub squash: Option<bool>,
+    /// Namespace holding fetched upstreams, e.g. `refs/vendor/`.
+    pub ref_prefix: Option<String>,
+    /// Register each dependency as a `vendor/<name>` git remote.
+    pub remotes: Option<bool>,
+    /// Prune vendor refs of upstream branches and tags deleted upstream.
+    pub prune: Option<bool>,
+    /// Copy objects borrowed from the shared cache into the repository.
+    pub dissociate: Option<bool>,
+    /// Git protocol version for upstream fetches; `2` fetches with the git
+    /// command line when there is one, `0` and `1` with libgit2.
+    pub protocol_version: Option<u32>,
+    /// Fetch upstream tags into `refs/vendor-tags/<name>/`.
+    pub fetch_tags: Option<bool>,
+    /// Resolve dependencies only to the commits their provenance records
+    /// pin.
+    pub locked: Option<bool>,
+    /// Ask the GitHub, GitLab or Gitea API about upstreams hosted there
+    /// instead of contacting them with git.
+    pub forge_api: Option<bool>,
+    /// Attach each merge's provenance record to its commit as a note in
+    /// `refs/notes/vendor`.
+    pub notes: Option<bool>,
+}
+
+/// Fully resolved settings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    pub jobs: usize,
+    /// `None` fetches the full history.
+    pub depth: Option<u32>,
+    pub offline: bool,
+    pub cache_dir: Option<PathBuf>,
+    pub default_branch: Option<String>,
+    pub squash: bool,
+    /// Always ends with `/`.
+    pub ref_prefix: String,
+    pub remotes: bool,
+    pub prune: bool,
+    pub dissociate: bool,
+    pub protocol_version: u32,
+    pub fetch_tags: bool,
+    pub locked: bool,
+    pub forge_api: bool,
+    pub notes: bool,
+}
+
+/// Where a resolved setting came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Origin {
+    /// An override set with [`set_overrides`], i.e. a command-line flag.
+    Override,
+    /// The named environment variable.
+    Env(String),
+    /// The named git config key.
+    GitConfig(String),
+    /// The built-in default.
+    Default,
+}
+
+impl std::fmt::Display for Origin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Origin::Override => f.write_str("command line"),
+            Origin::Env(name) => write!(f, "environment {name}"),
+            Origin::GitConfig(key) => write!(f, "git config {key}"),
+            Origin::Default => f.write_str("default"),
+        }
+    }
+}
+
+/// The default namespace for fetched upstreams.
+pub const DEFAULT_REF_PREFIX: &str = "refs/vendor/";
+
+static OVERRIDES: Mutex<Settings> = Mutex::new(Settings {
+    jobs: None,
+    depth: None,
+    offline: None,
+    cache_dir: None,
+    default_branch: None,
+    squash: None,
+    ref_prefix: None,
+    remotes: None,
+    prune: None,
+    dissociate: None,
+    protocol_version: None,
+    fetch_tags: None,
+    locked: None,
+    forge_api: None,
+    notes: None,
+});
+
+/// Set the highest-precedence layer, typically from command-line flags.
+pub fn set_overrides(settings: Settings) {
+    *OVERRIDES.lock().unwrap_or_else(|e| e.into_inner()) = settings;
+}
+
+impl Settings {
+    /// Read the `GIT_VENDOR_*` environment variables.
+    pub fn from_env() -> Result<Self, Error> {
+        Self::from_lookup(|name| {
+            let key = env_key(name);
+            std::env::var(&key).ok().map(|value| (key, value))
+        })
+    }
+
+    /// Read the `vendor.*` keys of `config`.
+    pub fn from_git_config(config: &git2::Config) -> Result<Self, Error> {
+        Self::from_lookup(|name| {
+            let key = format!("vendor.{name}");
+            config.get_string(&key).ok().map(|value| (key, value))
+        })
+    }
+
+    /// Build a layer from `lookup`, which maps a setting's git config name
+    /// (e.g. `cacheDir`) to the key it was found under and its value.
+    fn from_lookup(lookup: impl Fn(&str) -> Option<(String, String)>) -> Result<Self, Error> {
+        let get = |name: &str| lookup(name).filter(|(_, value)| !value.is_empty());
+
+        let jobs = match get("jobs") {
+            Some((key, value)) => Some(parse_number(&key, &value).and_then(|n| {
+                if n == 0 {
+                    Err(invalid(&format!("{key} must be at least 1")))
+                } else {
+                    Ok(n as usize)
+                }
+            })?),
+            None => None,
+        };
+        // 0 is kept rather than treated as unset, so that it overrides a
+        // depth from a lower layer; fetches take it as the full history.
+        let depth = match get("depth") {
+            Some((key, value)) => Some(parse_number(&key, &value)?),
+            None => None,
+        };
+        let offline = match get("offline") {
+            Some((key, value)) => Some(parse_bool(&key, &value)?),
+            None => None,
+        };
+        let cache_dir = get("cacheDir").map(|(_, value)| PathBuf::from(value));
+        let default_branch = match get("defaultBranch") {
+            Some((key, value)) => {
+                if !git2::Reference::is_valid_name(&format!("refs/heads/{value}")) {
+                    return Err(invalid(&format!("Invalid branch name '{value}' for {key}")));
+                }
+                Some(value)
+            }
+            None => None,
+        };
+        let squash = match get("squash") {
+            Some((key, value)) => Some(parse_bool(&key, &value)?),
+            None => None,
+        };
+        let ref_prefix = match get("refPrefix") {
+            Some((key, value)) => Some(parse_ref_prefix(&key, &value)?),
+            None => None,
+        };
+
+        let remotes = match get("remotes") {
+            Some((key, value)) => Some(parse_bool(&key, &value)?),
+            None => None,
+        };
+        let prune = match get("prune") {
+            Some((key, value)) => Some(parse_bool(&key, &value)?),
+            None => None,
+        };
+        let dissociate = match get("dissociate") {
+            Some((key, value)) => Some(parse_bool(&key, &value)?),
+            None => None,
+        };
+        let fetch_tags = match get("fetchTags") {
+            Some((key, value)) => Some(parse_bool(&key, &value)?),
+            None => None,
+        };
+        let locked = match get("locked") {
+            Some((key, value)) => Some(parse_bool(&key, &value)?),
+            None => None,
+        };
+        let forge_api = match get("forgeApi") {
+            Some((key, value)) => Some(parse_bool(&key, &value)?),
+            None => None,
+        };
+        let notes = match get("notes") {
+            Some((key, value)) => Some(parse_bool(&key, &value)?),
+            None => None,
+        };
+        let protocol_version = match get("protocolVersion") {
+            Some((key, value)) => Some(parse_number(&key, &value).and_then(|n| {
+                if n > 2 {
+                    Err(invalid(&format!("{key} must be 0, 1 or 2")))
+                } else {
+                    Ok(n)
+                }
+            })?),
+            None => None,
+        };
+
+        Ok(Self {
+            jobs,
+            depth,
+            offline,
+            cache_dir,
+            default_branch,
+            squash,
+            ref_prefix,
+            remotes,
+            prune,
+            dissociate,
+            protocol_version,
+            fetch_tags,
+            locked,
+            forge_api,
+            notes,
+        })
+    }
+
+    /// Whether the setting with git config name `name` (e.g. `cacheDir`) is
+    /// set in this layer.
+    fn has(&self, name: &str) -> bool {
+        match name {
+            "jobs" => self.jobs.is_some(),
+            "depth" => self.depth.is_some(),
+            "offline" => self.offline.is_some(),
+            "cacheDir" => self.cache_dir.is_some(),
+            "defaultBranch" => self.default_branch.is_some(),
+            "squash" => self.squash.is_some(),
+            "refPrefix" => self.ref_prefix.is_some(),
+            "remotes" => self.remotes.is_some(),
+            "prune" => self.prune.is_some(),
+            "dissociate" => self.dissociate.is_some(),
+            "protocolVersion" => self.protocol_version.is_some(),
+            "fetchTags" => self.fetch_tags.is_some(),
+            "locked" => self.locked.is_some(),
+            "forgeApi" => self.forge_api.is_some(),
+            "notes" => self.notes.is_some(),
+            _ => false,
+        }
+    }
+
+    /// Fill values unset in `self` from `lower`.
+    pub fn or(self, lower: Settings) -> Settings {
+        Settings {
+            jobs: self.jobs.or(lower.jobs),
+            depth: self.depth.or(lower.depth),
+            offline: self.offline.or(lower.offline),
+            cache_dir: self.cache_dir.or(lower.cache_dir),
+            default_branch: self.default_branch.or(lower.default_branch),
+            squash: self.squash.or(lower.squash),
+            ref_prefix: self.ref_prefix.or(lower.ref_prefix),
+            remotes: self.remotes.or(lower.remotes),
+            prune: self.prune.or(lower.prune),
+            dissociate: self.dissociate.or(lower.dissociate),
+            protocol_version: self.protocol_version.or(lower.protocol_version),
+            fetch_tags: self.fetch_tags.or(lower.fetch_tags),
+            locked: self.locked.or(lower.locked),
+            forge_api: self.forge_api.or(lower.forge_api),
+            notes: self.notes.or(lower.notes),
+        }
+    }
+}
+
+impl Config {
+    /// Resolve every setting for `repo`.
+    pub fn resolve(repo: &Repository) -> Result<Self, Error> {
+        let overrides = OVERRIDES.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        let settings = overrides
+            .or(Settings::from_env()?)
+            .or(Settings::from_git_config(&repo.config()?)?);
+        Ok(settings.into())
+    }
+
+    /// Where the value [`resolve`](Self::resolve) gives the setting with
+    /// git config name `name` (e.g. `cacheDir`) comes from.
+    pub fn origin(repo: &Repository, name: &str) -> Result<Origin, Error> {
+        if OVERRIDES
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .has(name)
+        {
+            Ok(Origin::Override)
+        } else if Settings::from_env()?.has(name) {
+            Ok(Origin::Env(env_key(name)))
+        } else if Settings::from_git_config(&repo.config()?)?.has(name) {
+            Ok(Origin::GitConfig(format!("vendor.{name}")))
+        } else {
+            Ok(Origin::Default)
+        }
+    }
+
+    /// Fail with a message naming `action` when offline.
+    pub(crate) fn require_online(&self, action: &str) -> Result<(), Error> {
+        if self.offline {
+            return Err(invalid(&format!(
+                "Cannot {action} in offline mode (unset GIT_VENDOR_OFFLINE or vendor.offline)"
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl From<Settings> for Config {
+    fn from(settings: Settings) -> Self {
+        Config {
+            jobs: settings.jobs.unwrap_or(1),
+            depth: settings.depth,
+            offline: settings.offline.unwrap_or(false),
+            cache_dir: settings.cache_dir,
+            default_branch: settings.default_branch,
+            squash: settings.squash.unwrap_or(false),
+            ref_prefix: settings
+                .ref_prefix
+                .unwrap_or_else(|| DEFAULT_REF_PREFIX.to_string()),
+            remotes: settings.remotes.unwrap_or(false),
+            prune: settings.prune.unwrap_or(false),
+            dissociate: settings.dissociate.unwrap_or(false),
+            protocol_version: settings.protocol_version.unwrap_or(2),
+            fetch_tags: settings.fetch_tags.unwrap_or(true),
+            locked: settings.locked.unwrap_or(false),
+            forge_api: settings.forge_api.unwrap_or(false),
+            notes: settings.notes.unwrap_or(false),
+        }
+    }
+}
+
+/// Merge the `[vendor "<name>"]` section of `config` over `dep`.
+pub(crate) fn apply_dep_overrides(config: &git2::Config, dep: &mut VendorDep) -> Result<(), Error> {
+    let get = |setting: &str| {
+        let key = format!("vendor.{}.{setting}", dep.name);
+        config
+            .get_string(&key)
+            .ok()
+            .filter(|value| !value.is_empty())
+            .map(|value| (key, value))
+    };
+
+    if let Some((_, url)) = get("url") {
+        dep.url = url;
+    }
+    if let Some((key, value)) = get("depth") {
+        dep.depth = Some(parse_number(&key, &value)?);
+    }
+    if let Some((_, proxy)) = get("proxy") {
+        dep.proxy = Some(proxy);
+    }
+    if let Some((_, helper)) = get("credentialHelper") {
+        dep.credential_helper = Some(helper);
+    }
+    Ok(())
+}
+
+/// An error for an invalid or conflicting setting.
+pub(crate) fn invalid(message: &str) -> Error {
+    Error::new(ErrorCode::Invalid, ErrorClass::Config, message)
+}
+
+/// The environment variable for a setting, e.g. `cacheDir` becomes
+/// `GIT_VENDOR_CACHE_DIR`.
+fn env_key(name: &str) -> String {
+    let mut key = String::from("GIT_VENDOR_");
+    for c in name.chars() {
+        if c.is_ascii_uppercase() {
+            key.push('_');
+        }
+        key.push(c.to_ascii_uppercase());
+    }
+    key
+}
+
+fn parse_number(key: &str, value: &str) -> Result<u32, Error> {
+    value.trim().parse().map_err(|_| {
+        invalid(&format!(
+            "Invalid value '{value}' for {key}: expected a number"
+        ))
+    })
+}
+
+/// Normalize a ref namespace to end with `/`, rejecting anything outside
+/// `refs/`.
+fn parse_ref_prefix(key: &str, value: &str) -> Result<String, Error> {
+    let prefix = format!("{}/", value.trim().trim_end_matches('/'));
+    if !prefix.starts_with("refs/")
+        || prefix == "refs/"
+        || !git2::Reference::is_valid_name(&format!("{prefix}x"))
+    {
+        return Err(invalid(&format!(
+            "Invalid value '{value}' for {key}: expected a ref namespace such as refs/vendor/"
+        )));
+    }
+    Ok(prefix)
+}
+
+fn parse_bool(key: &str, value: &str) -> Result<bool, Error> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Ok(true),
+        "0" | "false" | "no" | "off" => Ok(false),
+        _ => Err(invalid(&format!(
+            "Invalid value '{value}' for {key}: expected a boolean"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env<'a>(vars: &'a [(&str, &str)]) -> impl Fn(&str) -> Option<(String, String)> + 'a {
+        |name| {
+            let key = env_key(name);
+            vars.iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, v)| (key, v.to_string()))
+        }
+    }
+
+    #[test]
+    fn env_keys_are_screaming_snake_case() {
+        assert_eq!(env_key("jobs"), "GIT_VENDOR_JOBS");
+        assert_eq!(env_key("cacheDir"), "GIT_VENDOR_CACHE_DIR");
+    }
+
+    #[test]
+    fn env_layer_parses_values() {
+        let vars = [
+            ("GIT_VENDOR_JOBS", "4"),
+            ("GIT_VENDOR_DEPTH", "1"),
+            ("GIT_VENDOR_OFFLINE", "yes"),
+            ("GIT_VENDOR_CACHE_DIR", "/tmp/cache"),
+        ];
+        let settings = Settings::from_lookup(env(&vars)).unwrap();
+        assert_eq!(
+            settings,
+            Settings {
+                jobs: Some(4),
+                depth: Some(1),
+                offline: Some(true),
+                cache_dir: Some("/tmp/cache".into()),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn zero_depth_means_full_history() {
+        let vars = [("GIT_VENDOR_DEPTH", "0")];
+        assert_eq!(Settings::from_lookup(env(&vars)).unwrap().depth, Some(0));
+    }
+
+    #[test]
+    fn zero_depth_in_env_overrides_git_config() {
+        let vars = [("GIT_VENDOR_DEPTH", "0")];
+        let env = Settings::from_lookup(env(&vars)).unwrap();
+        let git = Settings {
+            depth: Some(5),
+            ..Default::default()
+        };
+        let config: Config = env.or(git).into();
+        assert_eq!(config.depth, Some(0));
+        assert_eq!(config.depth.filter(|&depth| depth > 0), None);
+    }
+
+    #[test]
+    fn ref_prefix_is_normalized() {
+        let vars = [("GIT_VENDOR_REF_PREFIX", "refs/upstreams")];
+        let settings = Settings::from_lookup(env(&vars)).unwrap();
+        assert_eq!(settings.ref_prefix.as_deref(), Some("refs/upstreams/"));
+
+        for bad in ["refs/", "vendor/", "refs/a b"] {
+            let vars = [("GIT_VENDOR_REF_PREFIX", bad)];
+            assert!(Settings::from_lookup(env(&vars)).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn dep_overrides_merge_over_attributes() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("config");
+        std::fs::write(
+            &path,
+            "[vendor \"owner/repo\"]\n\
+             \turl = https://mirror.example.com/owner/repo.git\n\
+             \tdepth = 1\n\
+             \tproxy = http://proxy.example.com:3128\n\
+             [vendor \"other/repo\"]\n\
+             \tdepth = 5\n",
+        )
+        .unwrap();
+        let config = git2::Config::open(&path).unwrap();
+
+        let mut dep = VendorDep {
+            name: "owner/repo".into(),
+            pattern: "vendor/repo/".into(),
+            url: "https://example.com/owner/repo.git".into(),
+            ..Default::default()
+        };
+        apply_dep_overrides(&config, &mut dep).unwrap();
+        assert_eq!(dep.url, "https://mirror.example.com/owner/repo.git");
+        assert_eq!(dep.depth, Some(1));
+        assert_eq!(dep.proxy.as_deref(), Some("http://proxy.example.com:3128"));
+        assert_eq!(dep.credential_helper, None);
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        let vars = [("GIT_VENDOR_JOBS", "0")];
+        assert!(Settings::from_lookup(env(&vars)).is_err());
+        let vars = [("GIT_VENDOR_OFFLINE", "maybe")];
+        let err = Settings::from_lookup(env(&vars)).unwrap_err();
+        assert!(err.message().contains("GIT_VENDOR_OFFLINE"));
+    }
+
+    #[test]
+    fn precedence_is_cli_env_config_default() {
+        let cli = Settings {
+            jobs: Some(8),
+            ..Default::default()
+        };
+        let env = Settings {
+            jobs: Some(2),
+            offline: Some(true),
+            ..Default::default()
+        };
+        let git = Settings {
+            jobs: Some(3),
+            offline: Some(false),
+            depth: Some(10),
+            ..Default::default()
+        };
+        let config: Config = cli.or(env).or(git).into();
+        assert_eq!(
+            config,
+            Config {
+                jobs: 8,
+                depth: Some(10),
+                offline: true,
+                cache_dir: None,
+                default_branch: None,
+                squash: false,
+                ref_prefix: DEFAULT_REF_PREFIX.into(),
+                remotes: false,
+                prune: false,
+                dissociate: false,
+                protocol_version: 2,
+                fetch_tags: true,
+                locked: false,
+                forge_api: false,
+                notes: false,
+            }
+        );
+    }
+}
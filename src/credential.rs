@@ -0,0 +1,162 @@
+//! Credentials from a per-dependency git credential helper.
+//!
+//! The helper is run through `git credential fill`, so every helper syntax
+//! git accepts (`store`, `cache --timeout=300`, `!f() { ...; }; f`, absolute
+//! paths) works the same way here.
+//!
+//! Credentials are remembered for the rest of the process by helper and host,
+//! as git does without `credential.useHttpPath`, so fetching many
+//! dependencies from one host runs the helper once rather than once each.
+
+use git2::{Cred, CredentialType, Error, RemoteCallbacks};
+use std::{
+    collections::BTreeMap,
+    io::Write,
+    process::{Command, Stdio},
+    sync::Mutex,
+};
+
+/// Credentials filled in this process, by helper and [`origin`].
+static FILLED: Mutex<BTreeMap<(String, String), (String, String)>> = Mutex::new(BTreeMap::new());
+
+/// Remote callbacks answering credential requests with `helper`.
+pub(crate) fn callbacks(helper: &str) -> RemoteCallbacks<'_> {
+    let mut callbacks = RemoteCallbacks::new();
+    let mut attempted = false;
+    let mut reused = false;
+    callbacks.credentials(move |url, username, allowed| {
+        let key = (helper.to_string(), origin(url, username));
+        // libgit2 asks again after a rejected credential; a remembered one
+        // may have expired, so fill it anew once, but don't loop.
+        if attempted {
+            FILLED
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .remove(&key);
+            if !reused {
+                return Err(Error::from_str(&format!(
+                    "Authentication to {url} failed with credential helper '{helper}'"
+                )));
+            }
+        }
+        attempted = true;
+        reused = false;
+
+        if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) {
+            let filled = FILLED
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .get(&key)
+                .cloned();
+            let (username, password) = match filled {
+                Some(filled) => {
+                    reused = true;
+                    filled
+                }
+                None => {
+                    let filled = fill(helper, url, username)?;
+                    FILLED
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .insert(key, filled.clone());
+                    filled
+                }
+            };
+            Cred::userpass_plaintext(&username, &password)
+        } else if allowed.contains(CredentialType::SSH_KEY) {
+            Cred::ssh_key_from_agent(username.unwrap_or("git"))
+        } else {
+            Cred::default()
+        }
+    });
+    callbacks
+}
+
+/// The protocol, username and host of `url`, e.g. `https://me@example.com`
+/// for `https://example.com/owner/repo.git` asked as `me`.
+fn origin(url: &str, username: Option<&str>) -> String {
+    let (scheme, rest) = url.split_once("://").unwrap_or(("", url));
+    let authority = rest.split('/').next().unwrap_or(rest);
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    match username {
+        Some(username) => format!("{scheme}://{username}@{host}"),
+        None => format!("{scheme}://{host}"),
+    }
+}
+
+/// Ask `helper` for the username and password to use for `url`.
+fn fill(helper: &str, url: &str, username: Option<&str>) -> Result<(String, String), Error> {
+    let mut child = Command::new("git")
+        .args(["-c", "credential.helper="])
+        .args(["-c", &format!("credential.helper={helper}")])
+        .args(["credential", "fill"])
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .map_err(|e| Error::from_str(&format!("Failed to run git credential: {e}")))?;
+
+    let mut request = format!("url={url}\n");
+    if let Some(username) = username {
+        request.push_str(&format!("username={username}\n"));
+    }
+    request.push('\n');
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(request.as_bytes())
+            .map_err(|e| Error::from_str(&format!("Failed to run git credential: {e}")))?;
+    }
+
+    let output = child
+        .wait_with_output()
+        .map_err(|e| Error::from_str(&format!("Failed to run git credential: {e}")))?;
+    if !output.status.success() {
+        return Err(Error::from_str(&format!(
+            "Credential helper '{helper}' provided no credentials for {url}"
+        )));
+    }
+    parse(&String::from_utf8_lossy(&output.stdout)).ok_or_else(|| {
+        Error::from_str(&format!(
+            "Credential helper '{helper}' provided no credentials for {url}"
+        ))
+    })
+}
+
+/// Extract the username and password from `git credential fill` output.
+fn parse(output: &str) -> Option<(String, String)> {
+    let field = |name: &str| {
+        output
+            .lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix('='))
+            .map(str::to_string)
+    };
+    Some((field("username")?, field("password")?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_fill_output() {
+        let output = "protocol=https\nhost=example.com\nusername=me\npassword=s3cr=t\n";
+        assert_eq!(parse(output), Some(("me".into(), "s3cr=t".into())));
+        assert_eq!(parse("protocol=https\nusername=me\n"), None);
+    }
+
+    #[test]
+    fn origin_drops_the_path() {
+        assert_eq!(
+            origin("https://example.com/owner/repo.git", None),
+            "https://example.com"
+        );
+        assert_eq!(
+            origin("https://old@example.com:8443/repo", Some("me")),
+            "https://me@example.com:8443"
+        );
+        assert_eq!(origin("file:///srv/repo", None), "file://");
+    }
+}
@@ -0,0 +1,135 @@
+//! Comparing vendored content with upstream revisions.
+
+use super::{
+    BOLD, GREEN, RED, VendorDep, config::Config, fetch_commit, fetch_history, is_commit_id, paint,
+    protocol, provenance, upstream_tree, vendor_ref_name, vendor_tags_ref_prefix,
+};
+use git2::{DiffFormat, DiffOptions, Error, Oid, Repository, Tree};
+
+/// Resolve `rev`, a tag, branch or commit of `dep`'s upstream, to a commit,
+/// fetching it if it is not available yet.
+///
+/// Fetched tags and commits are used as they are; branches are always
+/// looked up upstream, since only the tracked one is fetched.
+pub(crate) fn resolve(
+    repo: &Repository,
+    dep: &VendorDep,
+    rev: &str,
+    config: &Config,
+) -> Result<Oid, Error> {
+    let tag_ref = format!("{}{rev}", vendor_tags_ref_prefix(&dep.name));
+    if let Ok(commit) = repo
+        .revparse_single(&tag_ref)
+        .and_then(|object| object.peel_to_commit())
+    {
+        return Ok(commit.id());
+    }
+    // Only commit ids are looked up locally; names would resolve to the
+    // host repository's own branches and tags.
+    if rev.len() >= 4
+        && rev.bytes().all(|b| b.is_ascii_hexdigit())
+        && let Ok(commit) = repo
+            .revparse_single(rev)
+            .and_then(|object| object.peel_to_commit())
+    {
+        return Ok(commit.id());
+    }
+    if is_commit_id(rev) {
+        let commit = Oid::from_str(rev)?;
+        fetch_commit(repo, dep, commit, config)?;
+        return Ok(commit);
+    }
+
+    config.require_online("look up upstream revisions")?;
+    let (branch, tag) = (format!("refs/heads/{rev}"), format!("refs/tags/{rev}"));
+    let advertised = protocol::ls_remote(repo, dep, &[&branch, &tag])?;
+    let find = |name: &str| {
+        advertised
+            .iter()
+            .find(|(advertised, _)| advertised == name)
+            .map(|(_, oid)| *oid)
+    };
+    let (pinned, commit) = if let Some(commit) = find(&branch) {
+        let pinned = VendorDep {
+            branch: Some(rev.to_string()),
+            rev: None,
+            ..dep.clone()
+        };
+        (pinned, commit)
+    } else if let Some(commit) = find(&format!("{tag}^{{}}")).or_else(|| find(&tag)) {
+        let pinned = VendorDep {
+            rev: Some(rev.to_string()),
+            ..dep.clone()
+        };
+        (pinned, commit)
+    } else {
+        return Err(Error::from_str(&format!(
+            "No branch, tag or commit '{rev}' found for {}",
+            dep.name
+        )));
+    };
+    if repo.find_commit(commit).is_err() {
+        progress!("Fetching {} at {rev}", dep.name);
+        fetch_history(repo, &pinned, 1, config)?;
+    }
+    Ok(commit)
+}
+
+/// The commit `dep`'s vendor ref points to.
+pub(crate) fn fetched(repo: &Repository, dep: &VendorDep, config: &Config) -> Result<Oid, Error> {
+    let ref_name = vendor_ref_name(&config.ref_prefix, &dep.name);
+    repo.refname_to_id(&ref_name).map_err(|_| {
+        Error::from_str(&format!(
+            "Vendor ref {ref_name} not found. Run fetch first."
+        ))
+    })
+}
+
+/// The upstream commit `dep` was last merged from.
+pub(crate) fn merged(repo: &Repository, dep: &VendorDep) -> Result<Oid, Error> {
+    let head_tree = repo.head()?.peel_to_tree()?;
+    provenance::merged_commit(repo, &head_tree, dep)?
+        .ok_or_else(|| Error::from_str(&format!("{} has no provenance record", dep.name)))
+}
+
+/// The content `dep` vendors from the upstream `commit`.
+pub(crate) fn vendored_tree<'r>(
+    repo: &'r Repository,
+    dep: &VendorDep,
+    commit: Oid,
+) -> Result<Tree<'r>, Error> {
+    let tree = repo.find_commit(commit)?.tree()?;
+    let filtered = upstream_tree(repo, dep, &tree)?;
+    repo.find_tree(filtered.id())
+}
+
+/// The changes from `old` to `new` as a unified diff.
+pub(crate) fn patch(repo: &Repository, old: &Tree, new: &Tree) -> Result<String, Error> {
+    let mut opts = DiffOptions::new();
+    opts.show_binary(false);
+    let diff = repo.diff_tree_to_tree(Some(old), Some(new), Some(&mut opts))?;
+
+    let mut out = String::new();
+    diff.print(DiffFormat::Patch, |_, _, line| {
+        let content = String::from_utf8_lossy(line.content());
+        let content = content.trim_end_matches('\n');
+        let text = match line.origin() {
+            origin @ ('+' | '-' | ' ') => format!("{origin}{content}"),
+            _ => content.to_string(),
+        };
+        let text = match line.origin() {
+            '+' => paint(text, GREEN),
+            '-' => paint(text, RED),
+            'F' => text
+                .lines()
+                .map(|header| paint(header, BOLD))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            _ => text,
+        };
+        out.push_str(&text);
+        out.push('\n');
+        true
+    })?;
+    Ok(out)
+}
@@ -0,0 +1,192 @@
+//! Summaries of the files a merge changes, in the style of `git diff --stat`.
+
+use git2::{Delta, Diff, Error, Patch, Repository, Tree};
+use serde::Serialize;
+
+/// How a merge changes a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FileChange {
+    Added,
+    Modified,
+    Deleted,
+}
+
+/// The lines a merge changes in one file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileStat {
+    pub path: String,
+    pub change: FileChange,
+    pub insertions: usize,
+    pub deletions: usize,
+    /// Whether the file is binary, in which case no lines are counted.
+    pub binary: bool,
+}
+
+/// The files merging a dependency changes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MergeStat {
+    pub name: String,
+    /// The upstream commit being merged.
+    pub upstream: String,
+    /// Changed files, sorted by path.
+    pub files: Vec<FileStat>,
+}
+
+impl MergeStat {
+    /// The number of files with each kind of change: added, modified and
+    /// deleted.
+    pub fn counts(&self) -> (usize, usize, usize) {
+        let count = |change| self.files.iter().filter(|f| f.change == change).count();
+        (
+            count(FileChange::Added),
+            count(FileChange::Modified),
+            count(FileChange::Deleted),
+        )
+    }
+
+    /// The summary as `git diff --stat` prints it, headed by the
+    /// dependency's name.
+    pub fn to_text(&self) -> String {
+        let mut out = format!("{} ({})\n", self.name, &self.upstream[..7]);
+        let path_width = self.files.iter().map(|f| f.path.len()).max().unwrap_or(0);
+        let count_width = self
+            .files
+            .iter()
+            .map(|f| (f.insertions + f.deletions).to_string().len())
+            .max()
+            .unwrap_or(0);
+        let most = self
+            .files
+            .iter()
+            .map(|f| f.insertions + f.deletions)
+            .max()
+            .unwrap_or(0);
+        // Scale the graph down to at most GRAPH_WIDTH characters.
+        let scale = |n: usize| match most > GRAPH_WIDTH {
+            true => (n * GRAPH_WIDTH).div_ceil(most),
+            false => n,
+        };
+        for file in &self.files {
+            let path = &file.path;
+            if file.binary {
+                out.push_str(&format!(" {path:<path_width$} | Bin\n"));
+                continue;
+            }
+            let total = file.insertions + file.deletions;
+            out.push_str(&format!(
+                " {path:<path_width$} | {total:>count_width$} {}{}\n",
+                "+".repeat(scale(file.insertions)),
+                "-".repeat(scale(file.deletions)),
+            ));
+        }
+
+        let (added, modified, deleted) = self.counts();
+        let insertions: usize = self.files.iter().map(|f| f.insertions).sum();
+        let deletions: usize = self.files.iter().map(|f| f.deletions).sum();
+        let plural = |n: usize, word: &str| match n {
+            1 => format!("{n} {word}"),
+            _ => format!("{n} {word}s"),
+        };
+        out.push_str(&format!(
+            " {} ({added} added, {modified} modified, {deleted} deleted), {}(+), {}(-)\n",
+            plural(self.files.len(), "file"),
+            plural(insertions, "insertion"),
+            plural(deletions, "deletion"),
+        ));
+        out
+    }
+}
+
+/// The widest the `+`/`-` graph of a file gets.
+const GRAPH_WIDTH: usize = 40;
+
+/// The files that differ between `old` and `new`.
+pub(crate) fn stat(
+    repo: &Repository,
+    name: &str,
+    upstream: git2::Oid,
+    old: &Tree,
+    new: &Tree,
+) -> Result<MergeStat, Error> {
+    let diff = repo.diff_tree_to_tree(Some(old), Some(new), None)?;
+    Ok(MergeStat {
+        name: name.to_string(),
+        upstream: upstream.to_string(),
+        files: file_stats(&diff)?,
+    })
+}
+
+fn file_stats(diff: &Diff) -> Result<Vec<FileStat>, Error> {
+    let mut files = Vec::new();
+    for i in 0..diff.deltas().len() {
+        let Some(delta) = diff.get_delta(i) else {
+            continue;
+        };
+        let change = match delta.status() {
+            Delta::Added => FileChange::Added,
+            Delta::Deleted => FileChange::Deleted,
+            _ => FileChange::Modified,
+        };
+        let file = match change {
+            FileChange::Deleted => delta.old_file(),
+            _ => delta.new_file(),
+        };
+        let path = file
+            .path()
+            .map(|p| p.to_string_lossy().replace('\\', "/"))
+            .unwrap_or_default();
+        let (insertions, deletions, binary) = match Patch::from_diff(diff, i)? {
+            Some(patch) if !delta.flags().is_binary() => {
+                let (_, insertions, deletions) = patch.line_stats()?;
+                (insertions, deletions, false)
+            }
+            _ => (0, 0, true),
+        };
+        files.push(FileStat {
+            path,
+            change,
+            insertions,
+            deletions,
+            binary,
+        });
+    }
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_text_prints_diffstat() {
+        let stat = MergeStat {
+            name: "owner/repo".into(),
+            upstream: "0123456789".repeat(4),
+            files: vec![
+                FileStat {
+                    path: "lib/a.rs".into(),
+                    change: FileChange::Modified,
+                    insertions: 3,
+                    deletions: 1,
+                    binary: false,
+                },
+                FileStat {
+                    path: "lib/logo.png".into(),
+                    change: FileChange::Added,
+                    insertions: 0,
+                    deletions: 0,
+                    binary: true,
+                },
+            ],
+        };
+        assert_eq!(
+            stat.to_text(),
+            "owner/repo (0123456)\n \
+             lib/a.rs     | 4 +++-\n \
+             lib/logo.png | Bin\n \
+             2 files (1 added, 1 modified, 0 deleted), 3 insertions(+), 1 deletion(-)\n"
+        );
+    }
+}
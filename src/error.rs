@@ -0,0 +1,187 @@
+//! Stable codes for failures, so automation can react to specific errors
+//! without matching on messages, which may change between versions.
+//!
+//! Errors remain [`git2::Error`]s; a [`Code`] is derived from the error's
+//! [`ErrorCode`] and [`ErrorClass`], and [`new`] builds an error that maps
+//! back to the code given.
+//!
+//! | Code              | Meaning                                               |
+//! |-------------------|-------------------------------------------------------|
+//! | `E_DIRTY_INDEX`   | The index has staged changes                          |
+//! | `E_NO_MERGE_BASE` | The previously merged upstream commit is unavailable  |
+//! | `E_NO_DEPENDENCY` | No vendored dependency matches                        |
+//! | `E_CONFLICT`      | A merge stopped at conflicts                          |
+//! | `E_UNMERGED`      | The index has unresolved conflicts                    |
+//! | `E_DRIFT`         | Vendored content differs from its provenance          |
+//! | `E_AUTH`          | An upstream rejected the credentials                  |
+//! | `E_CERTIFICATE`   | An upstream's certificate was rejected                |
+//! | `E_TIMEOUT`       | An upstream did not answer in time                    |
+//! | `E_NETWORK`       | An upstream could not be reached                      |
+//! | `E_CONFIG`        | Invalid settings or dependency definitions            |
+//! | `E_NOT_FOUND`     | A ref, object or file does not exist                  |
+//! | `E_UNBORN_HEAD`   | The repository has no commits yet                     |
+//! | `E_LOCKED`        | A lock file is held by another process                |
+//! | `E_EXISTS`        | Something to be created already exists                |
+//! | `E_FAILURE`       | Any other failure                                     |
+
+use git2::{Error, ErrorClass, ErrorCode};
+use serde::Serialize;
+use std::fmt;
+
+/// A stable error code; see the [module documentation](self).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(into = "&'static str")]
+pub enum Code {
+    DirtyIndex,
+    NoMergeBase,
+    NoDependency,
+    Conflict,
+    Unmerged,
+    Drift,
+    Auth,
+    Certificate,
+    Timeout,
+    Network,
+    Config,
+    NotFound,
+    UnbornHead,
+    Locked,
+    Exists,
+    Failure,
+}
+
+impl Code {
+    /// The code as printed, e.g. `E_DIRTY_INDEX`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::DirtyIndex => "E_DIRTY_INDEX",
+            Self::NoMergeBase => "E_NO_MERGE_BASE",
+            Self::NoDependency => "E_NO_DEPENDENCY",
+            Self::Conflict => "E_CONFLICT",
+            Self::Unmerged => "E_UNMERGED",
+            Self::Drift => "E_DRIFT",
+            Self::Auth => "E_AUTH",
+            Self::Certificate => "E_CERTIFICATE",
+            Self::Timeout => "E_TIMEOUT",
+            Self::Network => "E_NETWORK",
+            Self::Config => "E_CONFIG",
+            Self::NotFound => "E_NOT_FOUND",
+            Self::UnbornHead => "E_UNBORN_HEAD",
+            Self::Locked => "E_LOCKED",
+            Self::Exists => "E_EXISTS",
+            Self::Failure => "E_FAILURE",
+        }
+    }
+
+    /// The libgit2 code and class errors with this code are built from.
+    fn parts(self) -> (ErrorCode, ErrorClass) {
+        match self {
+            Self::DirtyIndex => (ErrorCode::Uncommitted, ErrorClass::Index),
+            Self::NoMergeBase => (ErrorCode::NotFound, ErrorClass::Merge),
+            Self::NoDependency => (ErrorCode::NotFound, ErrorClass::Invalid),
+            Self::Conflict => (ErrorCode::MergeConflict, ErrorClass::Merge),
+            Self::Unmerged => (ErrorCode::Unmerged, ErrorClass::Index),
+            Self::Drift => (ErrorCode::Modified, ErrorClass::None),
+            Self::Auth => (ErrorCode::Auth, ErrorClass::Net),
+            Self::Certificate => (ErrorCode::Certificate, ErrorClass::Net),
+            Self::Timeout => (ErrorCode::Timeout, ErrorClass::Net),
+            Self::Network => (ErrorCode::GenericError, ErrorClass::Net),
+            Self::Config => (ErrorCode::Invalid, ErrorClass::Config),
+            Self::NotFound => (ErrorCode::NotFound, ErrorClass::None),
+            Self::UnbornHead => (ErrorCode::UnbornBranch, ErrorClass::Reference),
+            Self::Locked => (ErrorCode::Locked, ErrorClass::None),
+            Self::Exists => (ErrorCode::Exists, ErrorClass::None),
+            Self::Failure => (ErrorCode::GenericError, ErrorClass::None),
+        }
+    }
+}
+
+impl fmt::Display for Code {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<Code> for &'static str {
+    fn from(code: Code) -> Self {
+        code.as_str()
+    }
+}
+
+/// An error with `message` that [`code`] maps to `code`.
+pub fn new(code: Code, message: &str) -> Error {
+    let (code, class) = code.parts();
+    Error::new(code, class, message)
+}
+
+/// The stable code for `error`.
+pub fn code(error: &Error) -> Code {
+    match (error.code(), error.class()) {
+        (ErrorCode::Uncommitted, _) => Code::DirtyIndex,
+        (ErrorCode::NotFound, ErrorClass::Merge) => Code::NoMergeBase,
+        (ErrorCode::NotFound, ErrorClass::Invalid) => Code::NoDependency,
+        (ErrorCode::MergeConflict | ErrorCode::Conflict, _) => Code::Conflict,
+        (ErrorCode::Unmerged, _) => Code::Unmerged,
+        (ErrorCode::Modified, _) => Code::Drift,
+        (ErrorCode::Auth, _) => Code::Auth,
+        (ErrorCode::Certificate, _) => Code::Certificate,
+        (ErrorCode::Timeout, _) => Code::Timeout,
+        (_, ErrorClass::Net | ErrorClass::Http | ErrorClass::Ssh | ErrorClass::Ssl) => {
+            Code::Network
+        }
+        (_, ErrorClass::Config) => Code::Config,
+        (ErrorCode::NotFound, _) => Code::NotFound,
+        (ErrorCode::UnbornBranch, _) => Code::UnbornHead,
+        (ErrorCode::Locked, _) => Code::Locked,
+        (ErrorCode::Exists, _) => Code::Exists,
+        _ => Code::Failure,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [Code; 16] = [
+        Code::DirtyIndex,
+        Code::NoMergeBase,
+        Code::NoDependency,
+        Code::Conflict,
+        Code::Unmerged,
+        Code::Drift,
+        Code::Auth,
+        Code::Certificate,
+        Code::Timeout,
+        Code::Network,
+        Code::Config,
+        Code::NotFound,
+        Code::UnbornHead,
+        Code::Locked,
+        Code::Exists,
+        Code::Failure,
+    ];
+
+    #[test]
+    fn new_round_trips_through_code() {
+        for expected in ALL {
+            assert_eq!(code(&new(expected, "failed")), expected);
+        }
+    }
+
+    #[test]
+    fn code_classifies_library_errors() {
+        assert_eq!(code(&Error::from_str("failed")), Code::Failure);
+        assert_eq!(
+            code(&Error::new(
+                ErrorCode::GenericError,
+                ErrorClass::Ssh,
+                "failed"
+            )),
+            Code::Network
+        );
+        assert_eq!(
+            serde_json::to_string(&Code::DirtyIndex).unwrap(),
+            "\"E_DIRTY_INDEX\""
+        );
+    }
+}
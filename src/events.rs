@@ -0,0 +1,117 @@
+//! Structured progress events for automation.
+//!
+//! With [`set_events`], fetches and merges report their progress as one
+//! JSON object per line on standard output instead of the human-readable
+//! messages, so wrappers and dashboards can follow long runs as they
+//! happen:
+//!
+//! ```text
+//! {"time":"2026-01-01T12:00:00Z","event":"fetch-started","name":"owner/repo","url":"...","reference":"main"}
+//! {"time":"2026-01-01T12:00:03Z","event":"fetch-finished","name":"owner/repo","old":"...","new":"...","bytes":48213}
+//! ```
+
+use super::{error::Code, format_time, now};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static EVENTS: AtomicBool = AtomicBool::new(false);
+
+/// Report progress as NDJSON events (or restore human-readable messages).
+pub fn set_events(enabled: bool) {
+    EVENTS.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether progress is reported as events.
+pub(crate) fn enabled() -> bool {
+    EVENTS.load(Ordering::Relaxed)
+}
+
+/// A step in a long-running operation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum Event {
+    /// Fetching a dependency began.
+    FetchStarted {
+        name: String,
+        url: String,
+        /// The branch, tag or commit fetched, or `HEAD`.
+        reference: String,
+    },
+    /// A dependency was fetched.
+    FetchFinished {
+        name: String,
+        /// The previous target of the vendor ref, if any.
+        old: Option<String>,
+        /// The new target of the vendor ref; `None` when the tracked branch
+        /// was deleted upstream and its ref pruned.
+        new: Option<String>,
+        /// Bytes received, when the transport reports them.
+        bytes: Option<usize>,
+    },
+    /// Fetching a dependency failed.
+    FetchFailed {
+        name: String,
+        /// The stable code of the failure.
+        code: Code,
+        error: String,
+    },
+    /// Merging a dependency began.
+    MergeStarted { name: String, pattern: String },
+    /// A dependency was merged.
+    MergeFinished {
+        name: String,
+        /// The upstream commit merged.
+        upstream: String,
+        /// The commit created, or `None` when the merge was left
+        /// uncommitted.
+        commit: Option<String>,
+    },
+    /// Merging a dependency stopped at conflicts.
+    MergeConflict {
+        name: String,
+        /// The conflicted paths, sorted.
+        paths: Vec<String>,
+    },
+}
+
+#[derive(Serialize)]
+struct Line<'a> {
+    time: String,
+    #[serde(flatten)]
+    event: &'a Event,
+}
+
+/// Print `event` if events are enabled.
+pub(crate) fn emit(event: Event) {
+    if !enabled() {
+        return;
+    }
+    let line = Line {
+        time: format_time(now()),
+        event: &event,
+    };
+    if let Ok(json) = serde_json::to_string(&line) {
+        println!("{json}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_serialize_with_kebab_case_tag() {
+        let event = Event::MergeConflict {
+            name: "owner/repo".into(),
+            paths: vec!["a.txt".into()],
+        };
+        let line = Line {
+            time: "2026-01-01T00:00:00Z".into(),
+            event: &event,
+        };
+        assert_eq!(
+            serde_json::to_string(&line).unwrap(),
+            r#"{"time":"2026-01-01T00:00:00Z","event":"merge-conflict","name":"owner/repo","paths":["a.txt"]}"#
+        );
+    }
+}
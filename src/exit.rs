@@ -0,0 +1,63 @@
+//! Exit codes shared by all subcommands, so scripts can branch on the
+//! outcome without parsing standard error.
+//!
+//! | Code | Meaning                                                        |
+//! |------|----------------------------------------------------------------|
+//! | 0    | Success                                                        |
+//! | 1    | Any other failure                                              |
+//! | 2    | Invalid command line                                           |
+//! | 3    | Updates available: upstream commits fetched but not merged     |
+//! | 4    | Local drift: vendored content differs from its provenance      |
+//! | 5    | Conflicts: merge conflicts, or problems in the vendor graph    |
+//! | 6    | Network failure: an upstream could not be reached              |
+//! | 7    | Configuration error: invalid settings or dependency definitions |
+//!
+//! Failures reported by the library are classified by their
+//! [`git_vendor::error::Code`].
+
+use git_vendor::error::{self, Code};
+
+pub const SUCCESS: i32 = 0;
+pub const FAILURE: i32 = 1;
+pub const UPDATES_AVAILABLE: i32 = 3;
+pub const DRIFT: i32 = 4;
+pub const CONFLICTS: i32 = 5;
+pub const NETWORK: i32 = 6;
+pub const CONFIG: i32 = 7;
+
+/// The exit code for a command that failed with `error`.
+pub fn code(error: &(dyn std::error::Error + 'static)) -> i32 {
+    let Some(error) = error.downcast_ref::<git2::Error>() else {
+        return FAILURE;
+    };
+    match error::code(error) {
+        Code::Conflict | Code::Unmerged => CONFLICTS,
+        Code::Drift => DRIFT,
+        Code::Auth | Code::Certificate | Code::Timeout | Code::Network => NETWORK,
+        Code::Config => CONFIG,
+        _ => FAILURE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::{ErrorClass, ErrorCode};
+
+    #[test]
+    fn code_classifies_library_errors() {
+        let error = |code, class| git2::Error::new(code, class, "failed");
+        assert_eq!(
+            code(&error(ErrorCode::MergeConflict, ErrorClass::Merge)),
+            CONFLICTS
+        );
+        assert_eq!(code(&error(ErrorCode::Modified, ErrorClass::None)), DRIFT);
+        assert_eq!(
+            code(&error(ErrorCode::GenericError, ErrorClass::Net)),
+            NETWORK
+        );
+        assert_eq!(code(&error(ErrorCode::Invalid, ErrorClass::Config)), CONFIG);
+        assert_eq!(code(&git2::Error::from_str("failed")), FAILURE);
+        assert_eq!(code(&std::fmt::Error), FAILURE);
+    }
+}
@@ -0,0 +1,260 @@
+//! The fully resolved configuration of one dependency, with where each
+//! value came from.
+//!
+//! A dependency is defined in `.gitattributes` or the manifest, may be
+//! overridden by its `[vendor "<name>"]` git config section, and is fetched
+//! and merged according to settings from flags, the environment, git config
+//! or the defaults (see [`crate::config`]). Its URL is rewritten by git's
+//! `url.<base>.insteadOf` rules, and in locked mode its ref is replaced by
+//! the commit its provenance record pins. [`explain`] follows each of those
+//! steps so it is clear why a dependency is fetched the way it is.
+
+use super::{
+    config, config::Config, fetches_tags, find_dep, lock, protocol, provenance, source,
+    upstream_ref, vendor_ref_name, vendor_tags_ref_prefix,
+};
+use git2::{Error, Repository};
+use serde::Serialize;
+use std::path::Path;
+
+/// The resolved configuration of a dependency.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Explanation {
+    pub name: String,
+    /// The resolved values, in the order they apply to a fetch and merge.
+    pub values: Vec<Resolved>,
+}
+
+/// One resolved value.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Resolved {
+    /// What the value is, e.g. `url` or `depth`.
+    pub key: &'static str,
+    pub value: String,
+    /// Where it came from, e.g. `.gitattributes:3` or `git config vendor.depth`.
+    pub source: String,
+}
+
+pub(crate) fn explain(
+    repo: &Repository,
+    gitattributes: &Path,
+    key: &str,
+) -> Result<Explanation, Error> {
+    let source = source::open(repo, gitattributes)?;
+    let defined = source.load()?;
+    let defined = find_dep(&defined, key)?;
+    let git_config = repo.config()?;
+    let mut dep = defined.clone();
+    config::apply_dep_overrides(&git_config, &mut dep)?;
+    let config = Config::resolve(repo)?;
+
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| Error::from_str("Repository has no working directory"))?;
+    let file = source.path();
+    let file = file.strip_prefix(workdir).unwrap_or(file).display();
+    let definition = match source.line(defined)? {
+        Some(line) => format!("{file}:{line}"),
+        None => file.to_string(),
+    };
+    let dep_key = |setting: &str| {
+        let key = format!("vendor.{}.{setting}", dep.name);
+        git_config
+            .get_string(&key)
+            .is_ok_and(|value| !value.is_empty())
+            .then(|| format!("git config {key}"))
+    };
+    let origin = |name: &str| Config::origin(repo, name).map(|origin| origin.to_string());
+
+    let mut values = Vec::new();
+    let mut push = |key: &'static str, value: String, source: String| {
+        values.push(Resolved { key, value, source })
+    };
+
+    push("patterns", dep.patterns().join(" "), definition.clone());
+    push(
+        "url",
+        dep.url.clone(),
+        dep_key("url").unwrap_or_else(|| definition.clone()),
+    );
+    if let Some((url, rule)) = instead_of(&git_config, &dep.url)? {
+        push("fetch url", url, format!("git config {rule}"));
+    }
+
+    push(
+        "ref",
+        upstream_ref(&dep),
+        match (&dep.rev, &dep.branch) {
+            (Some(_), _) => format!("rev= in {definition}"),
+            (None, Some(_)) => format!("branch= in {definition}"),
+            (None, None) => "remote default branch".to_string(),
+        },
+    );
+    let head_tree = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+    if config.locked {
+        let value = match &head_tree {
+            Some(tree) => match lock::pin(repo, tree, &dep) {
+                Ok(pinned) => upstream_ref(&pinned),
+                Err(e) => format!("error: {}", e.message()),
+            },
+            None => "error: no commit to read provenance records from".to_string(),
+        };
+        push("locked ref", value, origin("locked")?);
+    }
+    if let Some(tree) = &dep.tree {
+        push(
+            "pinned tree",
+            tree.clone(),
+            format!("tree= in {definition}"),
+        );
+    }
+
+    push(
+        "vendor ref",
+        vendor_ref_name(&config.ref_prefix, &dep.name),
+        origin("refPrefix")?,
+    );
+    push(
+        "tags",
+        if fetches_tags(&dep, &config) {
+            format!("{}*", vendor_tags_ref_prefix(&dep.name))
+        } else {
+            "not fetched".to_string()
+        },
+        match dep.fetch_tags {
+            Some(_) => format!("fetch-tags= in {definition}"),
+            None => origin("fetchTags")?,
+        },
+    );
+    push(
+        "depth",
+        match dep.depth.or(config.depth) {
+            Some(0) | None => "full history".to_string(),
+            Some(depth) => depth.to_string(),
+        },
+        match dep_key("depth") {
+            Some(key) => key,
+            None if defined.depth.is_some() => format!("depth= in {definition}"),
+            None => origin("depth")?,
+        },
+    );
+    push(
+        "transport",
+        match config.protocol_version {
+            2 if protocol::git_available() => "git command line, protocol v2".to_string(),
+            2 => "libgit2, protocol v0 (git not found)".to_string(),
+            version => format!("libgit2, protocol v{version}"),
+        },
+        origin("protocolVersion")?,
+    );
+    if let Some(dir) = &config.cache_dir {
+        push("cache", dir.display().to_string(), origin("cacheDir")?);
+    }
+    if let Some(proxy) = &dep.proxy {
+        push("proxy", proxy.clone(), dep_key("proxy").unwrap_or_default());
+    }
+    if let Some(helper) = &dep.credential_helper {
+        push(
+            "credential helper",
+            helper.clone(),
+            dep_key("credentialHelper").unwrap_or_default(),
+        );
+    }
+    push(
+        "strategy",
+        match config.squash {
+            true => "squash".to_string(),
+            false => "merge commit".to_string(),
+        },
+        origin("squash")?,
+    );
+    if config.notes {
+        push("notes", "refs/notes/vendor".to_string(), origin("notes")?);
+    }
+    if config.offline {
+        push("offline", "true".to_string(), origin("offline")?);
+    }
+
+    if let Some(tree) = &head_tree
+        && let Some(record) = provenance::read(repo, tree, &dep.name)?
+    {
+        push(
+            "merged",
+            format!("{} from {}", record.commit, record.reference),
+            provenance::provenance_path(&dep.name),
+        );
+    }
+
+    Ok(Explanation {
+        name: dep.name.clone(),
+        values,
+    })
+}
+
+/// `url` rewritten by the longest matching `url.<base>.insteadOf` rule in
+/// `config`, and that rule's key; `None` if no rule matches.
+fn instead_of(config: &git2::Config, url: &str) -> Result<Option<(String, String)>, Error> {
+    let mut best: Option<(String, String, usize)> = None;
+    let mut entries = config.entries(Some(r"^url\..*\.insteadof$"))?;
+    while let Some(entry) = entries.next() {
+        let entry = entry?;
+        let (Some(name), Some(prefix)) = (entry.name(), entry.value()) else {
+            continue;
+        };
+        let Some(base) = name
+            .strip_prefix("url.")
+            .and_then(|name| name.get(..name.len() - ".insteadof".len()))
+        else {
+            continue;
+        };
+        if url.starts_with(prefix) && best.as_ref().is_none_or(|(.., len)| prefix.len() > *len) {
+            best = Some((
+                format!("{base}{}", &url[prefix.len()..]),
+                format!("url.{base}.insteadOf"),
+                prefix.len(),
+            ));
+        }
+    }
+    Ok(best.map(|(url, rule, _)| (url, rule)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn instead_of_uses_the_longest_match() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = git2::Config::open(&dir.path().join("config")).unwrap();
+        config
+            .set_str(
+                "url.https://mirror.example.com/.insteadOf",
+                "https://example.com/",
+            )
+            .unwrap();
+        config
+            .set_str(
+                "url.ssh://git@example.com/o/.insteadOf",
+                "https://example.com/o/",
+            )
+            .unwrap();
+
+        assert_eq!(
+            instead_of(&config, "https://example.com/o/a.git").unwrap(),
+            Some((
+                "ssh://git@example.com/o/a.git".to_string(),
+                "url.ssh://git@example.com/o/.insteadOf".to_string()
+            ))
+        );
+        assert_eq!(
+            instead_of(&config, "https://example.com/p/b.git")
+                .unwrap()
+                .map(|(url, _)| url),
+            Some("https://mirror.example.com/p/b.git".to_string())
+        );
+        assert_eq!(
+            instead_of(&config, "https://other.example/x.git").unwrap(),
+            None
+        );
+    }
+}
@@ -0,0 +1,168 @@
+//! Pruning of upstream paths marked `export-ignore`.
+//!
+//! With the `honor-export-ignore` attribute, a dependency's filtered tree
+//! drops everything the upstream's own `.gitattributes` files exclude from
+//! `git archive`, so the vendored content matches a release tarball.
+
+use git_filter_tree::bytes_to_path;
+use git_set_attr::split_pattern;
+use git2::{Error, ObjectType, Oid, Repository, Tree};
+use globset::{Glob, GlobMatcher};
+
+/// A single `export-ignore` rule from an upstream `.gitattributes` file.
+struct Rule {
+    matcher: GlobMatcher,
+    /// Whether the rule only matches directories (trailing `/`).
+    dir_only: bool,
+    ignore: bool,
+}
+
+/// Return a copy of `filtered` without the paths that `upstream`'s
+/// `.gitattributes` files mark `export-ignore`.
+pub(crate) fn prune(repo: &Repository, filtered: &Tree, upstream: &Tree) -> Result<Oid, Error> {
+    let rules = collect_rules(repo, upstream)?;
+    if rules.is_empty() {
+        return Ok(filtered.id());
+    }
+    prune_recursive(repo, filtered, b"", &rules)
+}
+
+/// Gather rules from every `.gitattributes` file in `tree`, shallowest
+/// first, so that later rules take precedence as in Git.
+fn collect_rules(repo: &Repository, tree: &Tree) -> Result<Vec<Rule>, Error> {
+    let mut rules = Vec::new();
+    for (dir, content) in super::attributes::upstream_files(repo, tree)? {
+        rules.extend(parse_rules(&dir, &content)?);
+    }
+    Ok(rules)
+}
+
+/// Parse the `export-ignore` rules of a `.gitattributes` file located in
+/// `dir` (empty for the root, otherwise ending in `/`).
+fn parse_rules(dir: &str, content: &str) -> Result<Vec<Rule>, Error> {
+    let mut rules = Vec::new();
+
+    for line in content.lines() {
+        let Some((pattern, rest)) = split_pattern(line) else {
+            continue;
+        };
+        let Some(ignore) = rest
+            .split_whitespace()
+            .fold(None, |state, attr| match attr {
+                "export-ignore" => Some(true),
+                "-export-ignore" | "!export-ignore" => Some(false),
+                _ => state,
+            })
+        else {
+            continue;
+        };
+
+        let dir_only = pattern.ends_with('/');
+        let pattern = pattern.trim_end_matches('/');
+        // Like gitignore: a pattern containing a slash is anchored to the
+        // directory of its `.gitattributes`; otherwise it matches at any depth.
+        let glob = if pattern.contains('/') {
+            format!("{dir}{}", pattern.trim_start_matches('/'))
+        } else {
+            format!("{dir}**/{pattern}")
+        };
+        let matcher = globset::GlobBuilder::new(&glob)
+            .literal_separator(true)
+            .build()
+            .map(|g: Glob| g.compile_matcher())
+            .map_err(|e| Error::from_str(&format!("Invalid pattern '{pattern}': {e}")))?;
+
+        rules.push(Rule {
+            matcher,
+            dir_only,
+            ignore,
+        });
+    }
+
+    Ok(rules)
+}
+
+/// Whether the last rule matching `path` marks it `export-ignore`.
+fn is_ignored(rules: &[Rule], path: impl AsRef<[u8]>, is_dir: bool) -> bool {
+    let path = bytes_to_path(path.as_ref());
+    rules
+        .iter()
+        .rev()
+        .find(|rule| (is_dir || !rule.dir_only) && rule.matcher.is_match(&path))
+        .is_some_and(|rule| rule.ignore)
+}
+
+fn prune_recursive(
+    repo: &Repository,
+    tree: &Tree,
+    prefix: &[u8],
+    rules: &[Rule],
+) -> Result<Oid, Error> {
+    let mut builder = repo.treebuilder(None)?;
+
+    for entry in tree.iter() {
+        let name = entry.name_bytes();
+        let path = [prefix, name].concat();
+
+        match entry.kind() {
+            Some(ObjectType::Tree) => {
+                if is_ignored(rules, &path, true) {
+                    continue;
+                }
+                let subtree = repo.find_tree(entry.id())?;
+                let pruned =
+                    prune_recursive(repo, &subtree, &[&path, b"/".as_slice()].concat(), rules)?;
+                if !repo.find_tree(pruned)?.is_empty() {
+                    builder.insert(name, pruned, entry.filemode())?;
+                }
+            }
+            _ => {
+                if !is_ignored(rules, &path, false) {
+                    builder.insert(name, entry.id(), entry.filemode())?;
+                }
+            }
+        }
+    }
+
+    builder.write()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unanchored_pattern_matches_at_any_depth() {
+        let rules = parse_rules("", "*.md export-ignore\n").unwrap();
+        assert!(is_ignored(&rules, "README.md", false));
+        assert!(is_ignored(&rules, "docs/guide.md", false));
+        assert!(!is_ignored(&rules, "src/lib.rs", false));
+    }
+
+    #[test]
+    fn anchored_pattern_is_relative_to_its_file() {
+        let rules = parse_rules("lib/", "/tests export-ignore\n").unwrap();
+        assert!(is_ignored(&rules, "lib/tests", true));
+        assert!(!is_ignored(&rules, "tests", true));
+        assert!(!is_ignored(&rules, "lib/src/tests", true));
+    }
+
+    #[test]
+    fn directory_only_pattern_skips_files() {
+        let rules = parse_rules("", "ci/ export-ignore\n").unwrap();
+        assert!(is_ignored(&rules, "ci", true));
+        assert!(!is_ignored(&rules, "ci", false));
+    }
+
+    #[test]
+    fn later_rules_override_earlier_ones() {
+        let rules = parse_rules(
+            "",
+            "*.md export-ignore\nREADME.md -export-ignore\nother.txt text\n",
+        )
+        .unwrap();
+        assert_eq!(rules.len(), 2);
+        assert!(!is_ignored(&rules, "README.md", false));
+        assert!(is_ignored(&rules, "CHANGES.md", false));
+    }
+}
@@ -0,0 +1,198 @@
+//! Running a command once per dependency, like `git submodule foreach`.
+//!
+//! A single argument is run by the shell; several are run as a program and
+//! its arguments. Each command runs in the dependency's directory (its
+//! pattern up to the first wildcard), or else the repository root, with the
+//! dependency described by environment variables:
+//!
+//! | Variable         | Value                                            |
+//! |------------------|--------------------------------------------------|
+//! | `VENDOR_NAME`    | the dependency's name                            |
+//! | `VENDOR_PATH`    | its path, relative to the repository root        |
+//! | `VENDOR_PATTERN` | its pattern                                      |
+//! | `VENDOR_URL`     | its upstream URL                                 |
+//! | `VENDOR_BRANCH`  | its branch, or empty                             |
+//! | `VENDOR_SHA`     | the upstream commit last merged, or empty        |
+//!
+//! With more than one job, commands run concurrently and the output of each
+//! is printed once it finishes. A failing command stops further commands
+//! from being started.
+
+use super::{VendorDep, provenance};
+use git2::{Error, Repository};
+use std::{
+    io::Write,
+    path::Path,
+    process::{Command, Output, Stdio},
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+};
+
+/// Run `command` for each of `deps` on up to `jobs` threads.
+pub(crate) fn run(
+    repo: &Repository,
+    deps: &[&VendorDep],
+    command: &[String],
+    jobs: usize,
+) -> Result<(), Error> {
+    if command.is_empty() {
+        return Err(Error::from_str("No command to run"));
+    }
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| Error::from_str("Repository has no working directory"))?;
+    let head_tree = repo.head()?.peel_to_tree()?;
+    let targets = deps
+        .iter()
+        .map(|&dep| {
+            let sha = provenance::merged_commit(repo, &head_tree, dep)?;
+            Ok(Target {
+                dep,
+                path: dep_path(&dep.pattern),
+                sha: sha.map(|sha| sha.to_string()).unwrap_or_default(),
+            })
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    if jobs <= 1 {
+        for target in &targets {
+            progress!("Entering '{}'", target.path);
+            let status = target
+                .command(workdir, command)
+                .status()
+                .map_err(|e| spawn_error(target, e))?;
+            if !status.success() {
+                return Err(failed(&[(target.dep.name.as_str(), status.to_string())]));
+            }
+        }
+        return Ok(());
+    }
+
+    let next = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let failures = Mutex::new(Vec::new());
+    let print = Mutex::new(());
+    std::thread::scope(|scope| {
+        for _ in 0..jobs.min(targets.len()) {
+            scope.spawn(|| {
+                while !stop.load(Ordering::Relaxed) {
+                    let Some(target) = targets.get(next.fetch_add(1, Ordering::Relaxed)) else {
+                        return;
+                    };
+                    let output = target
+                        .command(workdir, command)
+                        .stdin(Stdio::null())
+                        .output();
+                    let _guard = print.lock().unwrap_or_else(|e| e.into_inner());
+                    progress!("Entering '{}'", target.path);
+                    let status = match output {
+                        Ok(Output {
+                            status,
+                            stdout,
+                            stderr,
+                        }) => {
+                            let _ = std::io::stdout().write_all(&stdout);
+                            let _ = std::io::stderr().write_all(&stderr);
+                            if status.success() {
+                                continue;
+                            }
+                            status.to_string()
+                        }
+                        Err(e) => spawn_error(target, e).message().to_string(),
+                    };
+                    stop.store(true, Ordering::Relaxed);
+                    failures
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .push((target.dep.name.as_str(), status));
+                }
+            });
+        }
+    });
+
+    let failures = failures.into_inner().unwrap_or_else(|e| e.into_inner());
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(failed(&failures))
+    }
+}
+
+/// A dependency to run the command for.
+struct Target<'a> {
+    dep: &'a VendorDep,
+    path: String,
+    sha: String,
+}
+
+impl Target<'_> {
+    fn command(&self, workdir: &Path, command: &[String]) -> Command {
+        let mut cmd = match command {
+            [script] => {
+                let mut cmd = Command::new("sh");
+                cmd.arg("-c").arg(script);
+                cmd
+            }
+            [program, args @ ..] => {
+                let mut cmd = Command::new(program);
+                cmd.args(args);
+                cmd
+            }
+            [] => unreachable!("checked by run"),
+        };
+        let dir = workdir.join(&self.path);
+        cmd.current_dir(if dir.is_dir() { dir } else { workdir.into() })
+            .env("VENDOR_NAME", &self.dep.name)
+            .env("VENDOR_PATH", &self.path)
+            .env("VENDOR_PATTERN", &self.dep.pattern)
+            .env("VENDOR_URL", &self.dep.url)
+            .env("VENDOR_BRANCH", self.dep.branch.as_deref().unwrap_or(""))
+            .env("VENDOR_SHA", &self.sha);
+        cmd
+    }
+}
+
+/// The path `pattern` vendors: the pattern itself without wildcards, or else
+/// the directory before its first wildcard, `.` for the repository root.
+fn dep_path(pattern: &str) -> String {
+    let pattern = pattern.trim_start_matches('/');
+    let path = match pattern.find(['*', '?', '[', '\\']) {
+        None => pattern.trim_end_matches('/'),
+        Some(i) => pattern[..i].rfind('/').map_or("", |end| &pattern[..end]),
+    };
+    match path {
+        "" => ".".to_string(),
+        path => path.to_string(),
+    }
+}
+
+fn spawn_error(target: &Target, e: std::io::Error) -> Error {
+    Error::from_str(&format!(
+        "Failed to run the command for {}: {e}",
+        target.dep.name
+    ))
+}
+
+fn failed(failures: &[(&str, String)]) -> Error {
+    let list: Vec<String> = failures
+        .iter()
+        .map(|(name, status)| format!("{name} ({status})"))
+        .collect();
+    Error::from_str(&format!("Command failed for {}", list.join(", ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dep_path_is_the_literal_directory() {
+        assert_eq!(dep_path("vendor/lib/"), "vendor/lib");
+        assert_eq!(dep_path("/vendor/lib/**"), "vendor/lib");
+        assert_eq!(dep_path("vendor/*/src"), "vendor");
+        assert_eq!(dep_path("*.txt"), ".");
+        assert_eq!(dep_path("lib.txt"), "lib.txt");
+    }
+}
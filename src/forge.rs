@@ -0,0 +1,334 @@
+//! Lightweight queries against the web APIs of common forges.
+//!
+//! For upstreams hosted on GitHub, GitLab or Gitea (including Codeberg),
+//! the `forge_api` setting answers questions that would otherwise need a
+//! `git ls-remote` or a fetch — the default branch, the commit a ref points
+//! to, the tags, and how far one commit is ahead of another — with a few
+//! small HTTP requests. Requests are made with `curl` and authenticated
+//! with the token in `GITHUB_TOKEN` (or `GH_TOKEN`), `GITLAB_TOKEN` or
+//! `GITEA_TOKEN` when set, which also raises the anonymous rate limits.
+//!
+//! Callers fall back to git whenever a query fails.
+
+use super::{remote_check::RemoteStatus, split_remote_url};
+use git2::{Error, Oid};
+use serde_json::Value;
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    GitHub,
+    GitLab,
+    Gitea,
+}
+
+/// A repository on a forge with a known API.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Forge {
+    kind: Kind,
+    host: String,
+    /// The repository's path on the host, e.g. `owner/repo`.
+    path: String,
+}
+
+impl Forge {
+    /// The forge repository `url` points to, if hosted on a known forge.
+    pub(crate) fn from_url(url: &str) -> Option<Self> {
+        let (host, path) = split_remote_url(url)?;
+        let kind = match host {
+            "github.com" => Kind::GitHub,
+            "gitlab.com" => Kind::GitLab,
+            "codeberg.org" | "gitea.com" => Kind::Gitea,
+            _ => return None,
+        };
+        Some(Self {
+            kind,
+            host: host.to_string(),
+            path: path.to_string(),
+        })
+    }
+
+    /// The page `page` (e.g. `commit/<id>`) of the repository's web
+    /// interface.
+    pub(crate) fn web_url(&self, page: &str) -> String {
+        let Self { host, path, .. } = self;
+        match self.kind {
+            Kind::GitLab => format!("https://{host}/{path}/-/{page}"),
+            Kind::GitHub | Kind::Gitea => format!("https://{host}/{path}/{page}"),
+        }
+    }
+
+    /// The repository's default branch and its canonical path, which
+    /// differs from [`Forge::path`] when the repository has moved.
+    pub(crate) fn repository(&self) -> Result<Option<(String, String)>, Error> {
+        let Some(repo) = self.get(&self.repo_endpoint())? else {
+            return Ok(None);
+        };
+        let path_key = match self.kind {
+            Kind::GitHub | Kind::Gitea => "full_name",
+            Kind::GitLab => "path_with_namespace",
+        };
+        Ok(Some((
+            string(&repo, "default_branch")?,
+            string(&repo, path_key)?,
+        )))
+    }
+
+    /// The commit the branch, tag or commit `reference` resolves to, or
+    /// `None` if there is no such ref.
+    pub(crate) fn resolve(&self, reference: &str) -> Result<Option<Oid>, Error> {
+        let reference = encode(reference);
+        let repo = self.repo_endpoint();
+        let commit = match self.kind {
+            Kind::GitHub => self.get(&format!("{repo}/commits/{reference}"))?,
+            Kind::GitLab => self.get(&format!("{repo}/repository/commits/{reference}"))?,
+            Kind::Gitea => self
+                .get(&format!(
+                    "{repo}/commits?sha={reference}&limit=1&stat=false"
+                ))?
+                .and_then(|list| list.get(0).cloned()),
+        };
+        let key = match self.kind {
+            Kind::GitHub | Kind::Gitea => "sha",
+            Kind::GitLab => "id",
+        };
+        commit.map(|commit| oid(&commit, key)).transpose()
+    }
+
+    /// The number of commits in `head` but not in `base`.
+    pub(crate) fn ahead_by(&self, base: Oid, head: Oid) -> Result<usize, Error> {
+        let repo = self.repo_endpoint();
+        let endpoint = match self.kind {
+            Kind::GitHub | Kind::Gitea => format!("{repo}/compare/{base}...{head}"),
+            Kind::GitLab => format!("{repo}/repository/compare?from={base}&to={head}"),
+        };
+        let comparison = self.get(&endpoint)?.ok_or_else(|| {
+            Error::from_str(&format!(
+                "Cannot compare {base} with {head} on {}",
+                self.host
+            ))
+        })?;
+        let count = match self.kind {
+            Kind::GitHub => comparison.get("ahead_by").and_then(Value::as_u64),
+            Kind::Gitea => comparison.get("total_commits").and_then(Value::as_u64),
+            Kind::GitLab => comparison
+                .get("commits")
+                .and_then(Value::as_array)
+                .map(|commits| commits.len() as u64),
+        };
+        count
+            .map(|count| count as usize)
+            .ok_or_else(|| unexpected(&self.host))
+    }
+
+    /// The repository's tags and the commits they point to, as the forge
+    /// lists them (newest first on all supported forges).
+    pub(crate) fn tags(&self) -> Result<Vec<(String, Oid)>, Error> {
+        let repo = self.repo_endpoint();
+        let endpoint = match self.kind {
+            Kind::GitHub => format!("{repo}/tags?per_page=100"),
+            Kind::GitLab => format!("{repo}/repository/tags?per_page=100"),
+            Kind::Gitea => format!("{repo}/tags?limit=50"),
+        };
+        let Some(Value::Array(tags)) = self.get(&endpoint)? else {
+            return Err(unexpected(&self.host));
+        };
+        let key = match self.kind {
+            Kind::GitHub | Kind::Gitea => "sha",
+            Kind::GitLab => "id",
+        };
+        tags.iter()
+            .map(|tag| {
+                let commit = tag.get("commit").ok_or_else(|| unexpected(&self.host))?;
+                Ok((string(tag, "name")?, oid(commit, key)?))
+            })
+            .collect()
+    }
+
+    /// Check the repository like [`super::remote_check::check`] does.
+    pub(crate) fn check(&self, branch: Option<&str>) -> Result<RemoteStatus, Error> {
+        let Some((default_branch, path)) = self.repository()? else {
+            return Err(Error::from_str(&format!(
+                "{} not found on {}",
+                self.path, self.host
+            )));
+        };
+        let branch = branch.unwrap_or(&default_branch);
+        let head = self.resolve(branch)?;
+        if !path.eq_ignore_ascii_case(&self.path) {
+            return Ok(RemoteStatus::Moved {
+                to: format!("https://{}/{path}.git", self.host),
+                head: head.map(|oid| oid.to_string()),
+            });
+        }
+        Ok(match head {
+            Some(head) => RemoteStatus::Reachable {
+                head: head.to_string(),
+            },
+            None => RemoteStatus::BranchMissing {
+                branch: branch.to_string(),
+            },
+        })
+    }
+
+    fn repo_endpoint(&self) -> String {
+        match self.kind {
+            Kind::GitHub => format!("https://api.github.com/repos/{}", self.path),
+            Kind::GitLab => format!(
+                "https://{}/api/v4/projects/{}",
+                self.host,
+                encode(&self.path)
+            ),
+            Kind::Gitea => format!("https://{}/api/v1/repos/{}", self.host, self.path),
+        }
+    }
+
+    fn token(&self) -> Option<String> {
+        let vars: &[&str] = match self.kind {
+            Kind::GitHub => &["GITHUB_TOKEN", "GH_TOKEN"],
+            Kind::GitLab => &["GITLAB_TOKEN"],
+            Kind::Gitea => &["GITEA_TOKEN"],
+        };
+        vars.iter()
+            .find_map(|var| std::env::var(var).ok().filter(|token| !token.is_empty()))
+    }
+
+    /// GET `url` and parse the JSON response, or `None` on a 404.
+    fn get(&self, url: &str) -> Result<Option<Value>, Error> {
+        let mut headers = match self.kind {
+            Kind::GitHub => "Accept: application/vnd.github+json\n".to_string(),
+            Kind::GitLab | Kind::Gitea => "Accept: application/json\n".to_string(),
+        };
+        if let Some(token) = self.token() {
+            headers.push_str(&match self.kind {
+                Kind::GitHub => format!("Authorization: Bearer {token}\n"),
+                Kind::GitLab => format!("PRIVATE-TOKEN: {token}\n"),
+                Kind::Gitea => format!("Authorization: token {token}\n"),
+            });
+        }
+
+        tracing::debug!(url, "Querying forge API");
+        // Headers are read from stdin, keeping the token out of the process
+        // list.
+        let failed = |e: std::io::Error| Error::from_str(&format!("Failed to run curl: {e}"));
+        let mut child = Command::new("curl")
+            .args(["--silent", "--show-error", "--location", "--max-time", "30"])
+            .args(["--header", "@-", "--write-out", "\n%{http_code}", "--", url])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(failed)?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(headers.as_bytes()).map_err(failed)?;
+        }
+        let output = child.wait_with_output().map_err(failed)?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(Error::from_str(&format!(
+                "Request to {url} failed: {}",
+                stderr.trim().trim_start_matches("curl: ")
+            )));
+        }
+        parse_response(&String::from_utf8_lossy(&output.stdout), url)
+    }
+}
+
+/// Split curl's output into the body and the status code appended by
+/// `--write-out`, and parse the body.
+fn parse_response(output: &str, url: &str) -> Result<Option<Value>, Error> {
+    let (body, status) = output.rsplit_once('\n').unwrap_or(("", output));
+    match status.trim() {
+        "404" => Ok(None),
+        status if status.starts_with('2') => serde_json::from_str(body)
+            .map(Some)
+            .map_err(|e| Error::from_str(&format!("Invalid response from {url}: {e}"))),
+        status => Err(Error::from_str(&format!(
+            "Request to {url} failed with HTTP status {status}"
+        ))),
+    }
+}
+
+/// Percent-encode `s` for use as one URL path segment or query value.
+fn encode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(b as char)
+            }
+            _ => out.push_str(&format!("%{b:02X}")),
+        }
+    }
+    out
+}
+
+fn string(value: &Value, key: &str) -> Result<String, Error> {
+    value
+        .get(key)
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| Error::from_str(&format!("Unexpected API response: missing {key}")))
+}
+
+fn oid(value: &Value, key: &str) -> Result<Oid, Error> {
+    Oid::from_str(&string(value, key)?)
+}
+
+fn unexpected(host: &str) -> Error {
+    Error::from_str(&format!("Unexpected API response from {host}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_url_recognizes_known_forges() {
+        let forge = Forge::from_url("git@github.com:owner/repo.git").unwrap();
+        assert_eq!(forge.kind, Kind::GitHub);
+        assert_eq!(forge.path, "owner/repo");
+        assert_eq!(
+            forge.repo_endpoint(),
+            "https://api.github.com/repos/owner/repo"
+        );
+
+        let forge = Forge::from_url("https://gitlab.com/group/sub/repo").unwrap();
+        assert_eq!(
+            forge.repo_endpoint(),
+            "https://gitlab.com/api/v4/projects/group%2Fsub%2Frepo"
+        );
+
+        let forge = Forge::from_url("https://codeberg.org/owner/repo.git").unwrap();
+        assert_eq!(forge.kind, Kind::Gitea);
+        assert_eq!(
+            forge.repo_endpoint(),
+            "https://codeberg.org/api/v1/repos/owner/repo"
+        );
+
+        assert_eq!(Forge::from_url("https://git.example.com/owner/repo"), None);
+        assert_eq!(Forge::from_url("/srv/git/repo"), None);
+    }
+
+    #[test]
+    fn parse_response_reads_status_line() {
+        let url = "https://api.github.com/repos/owner/repo";
+        assert_eq!(
+            parse_response("{\"a\":1}\n200", url).unwrap(),
+            Some(serde_json::json!({"a": 1}))
+        );
+        assert_eq!(parse_response("{}\n404", url).unwrap(), None);
+        assert!(parse_response("{}\n403", url).is_err());
+        assert!(parse_response("not json\n200", url).is_err());
+    }
+
+    #[test]
+    fn encode_escapes_path_separators() {
+        assert_eq!(encode("release/1.x"), "release%2F1.x");
+        assert_eq!(encode("v1.0.0"), "v1.0.0");
+    }
+}
@@ -0,0 +1,204 @@
+//! Cleanup of the refs and objects left behind by vendor fetches.
+//!
+//! Refs of dependencies that are no longer tracked are deleted, as are refs
+//! kept for branches deleted upstream once they are older than the
+//! retention window. Reflog entries of vendor refs older than the window
+//! are expired, keeping the newest one, which records when the dependency
+//! was last fetched. `git gc` then drops the objects nothing references
+//! any more.
+
+use super::{VendorDep, now, run_git as git};
+use git2::{Error, Repository};
+use std::{path::Path, path::PathBuf};
+
+/// Options for [`crate::Vendor::vendor_gc`].
+#[derive(Debug, Clone)]
+pub struct GcOpts {
+    /// Seconds for which reflog entries and refs of branches deleted
+    /// upstream are kept; `0` expires them all.
+    pub expire: i64,
+    /// Also compact the shared object caches under `vendor.cacheDir`.
+    pub cache: bool,
+    /// Only report what would be expired.
+    pub dry_run: bool,
+}
+
+impl Default for GcOpts {
+    fn default() -> Self {
+        GcOpts {
+            expire: 90 * 86_400,
+            cache: false,
+            dry_run: false,
+        }
+    }
+}
+
+/// What [`crate::Vendor::vendor_gc`] expired.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct GcReport {
+    /// Vendor refs deleted, sorted.
+    pub deleted_refs: Vec<String>,
+    /// Reflog entries of the remaining vendor refs that were expired.
+    pub expired_entries: usize,
+    /// Objects in the repository before and after `git gc`; `None` with
+    /// `dry_run`.
+    pub objects: Option<(u64, u64)>,
+    /// Shared caches that were compacted.
+    pub compacted: Vec<PathBuf>,
+}
+
+pub(crate) fn gc(
+    repo: &Repository,
+    deps: &[VendorDep],
+    ref_prefix: &str,
+    cache_dir: Option<&Path>,
+    opts: &GcOpts,
+) -> Result<GcReport, Error> {
+    let cutoff = now() - opts.expire;
+    let mut report = GcReport::default();
+
+    let mut kept = Vec::new();
+    for reference in repo.references()? {
+        let reference = reference?;
+        let Some(name) = reference.name() else {
+            continue;
+        };
+        let expired = if let Some(dep) = name.strip_prefix(ref_prefix) {
+            !deps.iter().any(|d| d.name == dep)
+        } else if let Some(dep) = name.strip_prefix("refs/vendor-gone/") {
+            !deps.iter().any(|d| d.name == dep) || updated_at(repo, name) < cutoff
+        } else if let Some(rest) = name.strip_prefix("refs/vendor-tags/") {
+            !deps.iter().any(|d| {
+                rest.strip_prefix(&d.name)
+                    .is_some_and(|tag| tag.starts_with('/'))
+            })
+        } else {
+            continue;
+        };
+        if expired {
+            report.deleted_refs.push(name.to_string());
+        } else if name.starts_with(ref_prefix) {
+            kept.push(name.to_string());
+        }
+    }
+    report.deleted_refs.sort();
+
+    for name in &kept {
+        report.expired_entries += expire_reflog(repo, name, cutoff, opts.dry_run)?;
+    }
+
+    if opts.dry_run {
+        return Ok(report);
+    }
+
+    for name in &report.deleted_refs {
+        repo.find_reference(name)?.delete()?;
+    }
+
+    let before = count_objects(repo.path())?;
+    run_gc(repo.path(), opts.expire)?;
+    report.objects = Some((before, count_objects(repo.path())?));
+
+    if opts.cache {
+        let dir = cache_dir.ok_or_else(|| {
+            Error::from_str("No shared cache configured; set vendor.cacheDir or --cache-dir")
+        })?;
+        for cache in caches(dir)? {
+            run_gc(&cache, opts.expire)?;
+            report.compacted.push(cache);
+        }
+    }
+
+    Ok(report)
+}
+
+/// Seconds since the epoch at which `ref_name` was last updated, falling
+/// back to the committer time of its target when it has no reflog.
+fn updated_at(repo: &Repository, ref_name: &str) -> i64 {
+    repo.reflog(ref_name)
+        .ok()
+        .and_then(|log| log.get(0).map(|entry| entry.committer().when().seconds()))
+        .or_else(|| {
+            let commit = repo.find_reference(ref_name).ok()?.peel_to_commit().ok()?;
+            Some(commit.committer().when().seconds())
+        })
+        .unwrap_or(i64::MIN)
+}
+
+/// Drop the entries of `ref_name`'s reflog older than `cutoff`, always
+/// keeping the newest, and return how many were (or would be) dropped.
+fn expire_reflog(
+    repo: &Repository,
+    ref_name: &str,
+    cutoff: i64,
+    dry_run: bool,
+) -> Result<usize, Error> {
+    let mut log = repo.reflog(ref_name)?;
+    // Entries are ordered newest first.
+    let expired: Vec<usize> = (1..log.len())
+        .filter(|&i| {
+            log.get(i)
+                .is_some_and(|entry| entry.committer().when().seconds() < cutoff)
+        })
+        .collect();
+    if !dry_run && !expired.is_empty() {
+        for &i in expired.iter().rev() {
+            log.remove(i, true)?;
+        }
+        log.write()?;
+    }
+    Ok(expired.len())
+}
+
+/// The repositories making up the shared cache: `dir` itself if it is one,
+/// otherwise each repository directly inside it.
+fn caches(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    if Repository::open_bare(dir).is_ok() {
+        return Ok(vec![dir.to_path_buf()]);
+    }
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| Error::from_str(&format!("Failed to read {}: {e}", dir.display())))?;
+    let mut caches: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| Repository::open_bare(path).is_ok())
+        .collect();
+    caches.sort();
+    Ok(caches)
+}
+
+/// Repack the repository at `git_dir` and prune unreachable objects older
+/// than `expire` seconds.
+fn run_gc(git_dir: &Path, expire: i64) -> Result<(), Error> {
+    let prune = if expire <= 0 {
+        "--prune=now".to_string()
+    } else {
+        format!("--prune={expire}.seconds.ago")
+    };
+    git(git_dir, &["gc", "--quiet", &prune]).map(|_| ())
+}
+
+/// The number of loose and packed objects in the repository at `git_dir`.
+fn count_objects(git_dir: &Path) -> Result<u64, Error> {
+    let output = git(git_dir, &["count-objects", "-v"])?;
+    Ok(parse_count_objects(&output))
+}
+
+fn parse_count_objects(output: &str) -> u64 {
+    output
+        .lines()
+        .filter_map(|line| line.split_once(": "))
+        .filter(|(key, _)| matches!(*key, "count" | "in-pack"))
+        .filter_map(|(_, value)| value.trim().parse::<u64>().ok())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_count_objects_sums_loose_and_packed() {
+        let output = "count: 3\nsize: 12\nin-pack: 40\npacks: 1\nsize-pack: 9\n";
+        assert_eq!(parse_count_objects(output), 43);
+    }
+}
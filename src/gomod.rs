@@ -0,0 +1,543 @@
+//! Interoperation with Go modules.
+//!
+//! [`import`] reads the requirements of a `go.mod`, or the modules recorded
+//! in a `vendor/modules.txt`, and creates a dependency for each module's
+//! source repository at the required version. A version is a tag, prefixed
+//! with the module's directory for modules below the repository root, or a
+//! pseudo-version naming a commit, which is resolved on the fetched
+//! default branch. As with [`crate::import`], content keeps its upstream
+//! paths, so only modules below the root of their repository can be
+//! vendored, each tracked by its directory.
+//!
+//! [`modules_txt`] goes the other way, describing the Go modules found in
+//! the vendored trees in the format of `vendor/modules.txt`.
+
+use super::{
+    VendorDep, config::Config, fetch_dep, format_time, import::Imported, is_commit_id,
+    name_from_url, provenance, source, vendor_ref_name,
+};
+use git_filter_tree::walk_tree;
+use git2::{Error, ObjectType, Repository, Tree};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::Path,
+};
+
+/// A module requirement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Module {
+    path: String,
+    version: String,
+}
+
+/// Where a module's source lives.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Location {
+    url: String,
+    /// The module's directory in the repository; empty for the root.
+    dir: String,
+    /// The upstream ref or abbreviated commit the version names.
+    rev: String,
+}
+
+/// Import the modules required by the `go.mod`, or recorded in the
+/// `modules.txt`, at `path`; a directory is read as a vendor directory
+/// holding `modules.txt`. Definitions are written to `gitattributes`
+/// unless `dry_run`.
+pub(crate) fn import(
+    repo: &Repository,
+    gitattributes: &Path,
+    path: &Path,
+    dry_run: bool,
+) -> Result<Vec<Imported>, Error> {
+    let (file, vendor_dir) = if path.is_dir() {
+        (path.join("modules.txt"), Some(path.to_path_buf()))
+    } else {
+        let vendor_dir = match path.file_name().and_then(|name| name.to_str()) {
+            Some("modules.txt") => path.parent().map(Path::to_path_buf),
+            _ => path.parent().map(|dir| dir.join("vendor")),
+        };
+        (path.to_path_buf(), vendor_dir)
+    };
+    let content = fs::read_to_string(&file)
+        .map_err(|e| Error::from_str(&format!("Failed to read {}: {e}", file.display())))?;
+    let modules = if file.ends_with("modules.txt") {
+        recorded_modules(&content)
+    } else {
+        required_modules(&content)
+    };
+    let config = Config::resolve(repo)?;
+
+    let mut upstreams: BTreeMap<(String, String), Vec<(Module, String)>> = BTreeMap::new();
+    for module in modules {
+        let location = location(&module);
+        upstreams
+            .entry((location.url, location.rev))
+            .or_default()
+            .push((module, location.dir));
+    }
+    if !upstreams.is_empty() {
+        config.require_online("fetch the source repositories of Go modules")?;
+    }
+
+    let source = source::open(repo, gitattributes)?;
+    let mut names = BTreeSet::new();
+    let mut imported = Vec::new();
+    for ((url, rev), modules) in upstreams {
+        let valid = |name: &String| {
+            !name.contains(char::is_whitespace)
+                && git2::Reference::is_valid_name(&vendor_ref_name(&config.ref_prefix, name))
+        };
+        let mut name = name_from_url(&url)
+            .filter(valid)
+            .unwrap_or_else(|| modules[0].0.path.clone());
+        if names.contains(&name) {
+            // Modules of one repository at different versions.
+            let module = &modules[0].0.path;
+            name = format!("{name}-{}", module.rsplit('/').next().unwrap_or(module));
+        }
+        names.insert(name.clone());
+
+        // Versions start with `v`, so only pseudo-versions name bare commits.
+        let pseudo = rev.len() == 12 && is_hex(&rev);
+        let mut dep = VendorDep {
+            name,
+            url,
+            rev: (!pseudo).then(|| rev.clone()),
+            ..Default::default()
+        };
+        let mut result = Imported {
+            name: dep.name.clone(),
+            url: dep.url.clone(),
+            rev: rev.clone(),
+            branch: None,
+            packages: Vec::new(),
+            skipped: Vec::new(),
+            replaces: Vec::new(),
+        };
+        fetch_dep(repo, &dep, None, &config)?;
+        let commit = if pseudo {
+            match repo
+                .revparse_single(&rev)
+                .and_then(|object| object.peel_to_commit())
+            {
+                Ok(commit) => commit,
+                Err(_) => {
+                    let reason = format!("commit {rev} is not on the default branch");
+                    result.skipped.extend(
+                        modules
+                            .iter()
+                            .map(|(module, _)| (module.path.clone(), reason.clone())),
+                    );
+                    imported.push(result);
+                    continue;
+                }
+            }
+        } else {
+            repo.find_reference(&vendor_ref_name(&config.ref_prefix, &dep.name))?
+                .peel_to_commit()?
+        };
+        if pseudo {
+            result.rev = commit.id().to_string();
+            dep.rev = Some(result.rev.clone());
+        }
+        let tree = commit.tree()?;
+
+        for (module, dir) in &modules {
+            let dir = if dir.is_empty() || tree.get_path(Path::new(dir)).is_ok() {
+                dir.clone()
+            } else {
+                major_prefix(dir).to_string()
+            };
+            if dir.is_empty() {
+                result.skipped.push((
+                    module.path.clone(),
+                    "at the root of its repository".to_string(),
+                ));
+                continue;
+            }
+            dep.pattern = format!("{dir}/");
+            if !dry_run {
+                source.upsert(&dep, &[])?;
+            }
+            result
+                .packages
+                .push((module.path.clone(), dep.pattern.clone()));
+            if let Some(vendor_dir) = &vendor_dir {
+                let copy = vendor_dir.join(&module.path);
+                if copy.is_dir() {
+                    result.replaces.push(copy);
+                }
+            }
+        }
+        imported.push(result);
+    }
+    Ok(imported)
+}
+
+/// The requirements of `go.mod` content, with replacements applied.
+/// Modules replaced by local directories are left out.
+fn required_modules(content: &str) -> Vec<Module> {
+    let mut required = Vec::new();
+    let mut replaced = BTreeMap::new();
+    let mut block = None;
+    for line in content.lines() {
+        let line = line.split("//").next().unwrap_or_default().trim();
+        let (directive, rest) = match block {
+            Some(_) if line == ")" => {
+                block = None;
+                continue;
+            }
+            Some(directive) => (directive, line),
+            None => match line.split_once(char::is_whitespace) {
+                Some((directive, rest)) if rest.trim() == "(" => {
+                    block = Some(directive);
+                    continue;
+                }
+                Some((directive, rest)) => (directive, rest.trim()),
+                None => continue,
+            },
+        };
+        let fields: Vec<&str> = rest.split_whitespace().collect();
+        match (directive, fields.as_slice()) {
+            ("require", [path, version]) => required.push(Module {
+                path: path.to_string(),
+                version: version.to_string(),
+            }),
+            ("replace", [old, "=>", new, version] | [old, _, "=>", new, version]) => {
+                replaced.insert(old.to_string(), (new.to_string(), version.to_string()));
+            }
+            ("replace", [old, "=>", _] | [old, _, "=>", _]) => {
+                replaced.insert(old.to_string(), (String::new(), String::new()));
+            }
+            _ => {}
+        }
+    }
+    required
+        .into_iter()
+        .filter_map(|module| match replaced.get(&module.path) {
+            Some((path, _)) if path.is_empty() => None,
+            Some((path, version)) => Some(Module {
+                path: path.clone(),
+                version: version.clone(),
+            }),
+            None => Some(module),
+        })
+        .collect()
+}
+
+/// The modules recorded in `modules.txt` content, with replacements
+/// applied. Modules replaced by local directories are left out.
+fn recorded_modules(content: &str) -> Vec<Module> {
+    content
+        .lines()
+        .filter_map(|line| line.strip_prefix("# "))
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let (path, version) = match fields.as_slice() {
+                [_, _, "=>", path, version] | [_, "=>", path, version] => (path, version),
+                [path, version] => (path, version),
+                _ => return None,
+            };
+            Some(Module {
+                path: path.to_string(),
+                version: version.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// The source repository, directory and ref of `module`.
+fn location(module: &Module) -> Location {
+    let path = module.path.as_str();
+    let components: Vec<&str> = path.split('/').collect();
+    let (url, dir) = match components.as_slice() {
+        [
+            "github.com" | "gitlab.com" | "bitbucket.org" | "codeberg.org",
+            owner,
+            repo,
+            dir @ ..,
+        ] => (
+            format!("https://{}/{owner}/{repo}", components[0]),
+            dir.join("/"),
+        ),
+        ["golang.org", "x", repo, dir @ ..] => {
+            (format!("https://go.googlesource.com/{repo}"), dir.join("/"))
+        }
+        ["gopkg.in", package] => match package.split_once('.') {
+            Some((repo, _)) => (
+                format!("https://github.com/go-{repo}/{repo}"),
+                String::new(),
+            ),
+            None => (format!("https://{path}"), String::new()),
+        },
+        ["gopkg.in", owner, package] => match package.split_once('.') {
+            Some((repo, _)) => (format!("https://github.com/{owner}/{repo}"), String::new()),
+            None => (format!("https://{path}"), String::new()),
+        },
+        _ => match path.split_once(".git/") {
+            Some((repo, dir)) => (format!("https://{repo}.git"), dir.to_string()),
+            None => (format!("https://{path}"), String::new()),
+        },
+    };
+
+    let version = module.version.trim_end_matches("+incompatible");
+    let rev = match pseudo_commit(version) {
+        Some(commit) => commit.to_string(),
+        None => match major_prefix(&dir) {
+            "" => version.to_string(),
+            prefix => format!("{prefix}/{version}"),
+        },
+    };
+    Location { url, dir, rev }
+}
+
+/// `dir` without a trailing major version component such as `v2`, which
+/// Go leaves out of tag names.
+fn major_prefix(dir: &str) -> &str {
+    let (prefix, last) = dir.rsplit_once('/').unwrap_or(("", dir));
+    match last.strip_prefix('v') {
+        Some(major) if !major.is_empty() && major.bytes().all(|b| b.is_ascii_digit()) => prefix,
+        _ => dir,
+    }
+}
+
+/// The abbreviated commit of a pseudo-version such as
+/// `v0.0.0-20240229123456-0123456789ab`.
+fn pseudo_commit(version: &str) -> Option<&str> {
+    let mut parts = version.rsplitn(3, '-');
+    let commit = parts.next()?;
+    let time = parts.next()?;
+    let time = time.rsplit('.').next()?;
+    (commit.len() == 12
+        && is_hex(commit)
+        && time.len() == 14
+        && time.bytes().all(|b| b.is_ascii_digit()))
+    .then_some(commit)
+}
+
+fn is_hex(s: &str) -> bool {
+    s.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// The `modules.txt` describing the Go modules vendored in `tree`: each
+/// directory tracked by one of `deps` holding a `go.mod`, at the version
+/// its provenance record names, with its packages.
+pub(crate) fn modules_txt(
+    repo: &Repository,
+    tree: &Tree,
+    deps: &[&VendorDep],
+) -> Result<String, Error> {
+    let mut content = String::new();
+    let mut modules = Vec::new();
+    for dep in deps {
+        let Some(record) = provenance::read(repo, tree, &dep.name)? else {
+            continue;
+        };
+        for pattern in dep.patterns() {
+            let dir = pattern.trim_end_matches('/');
+            if dir.contains(['*', '?', '[']) {
+                continue;
+            }
+            let Ok(entry) = tree.get_path(&Path::new(dir).join("go.mod")) else {
+                continue;
+            };
+            let go_mod = repo.find_blob(entry.id())?;
+            let go_mod = String::from_utf8_lossy(go_mod.content());
+            let Some(module) = directive(&go_mod, "module") else {
+                continue;
+            };
+            let version = match &dep.rev {
+                Some(rev) if !is_commit_id(rev) => {
+                    rev.rsplit('/').next().unwrap_or(rev).to_string()
+                }
+                _ => pseudo_version(repo, &record.commit)?,
+            };
+            let explicit = match directive(&go_mod, "go") {
+                Some(go) => format!("## explicit; go {go}"),
+                None => "## explicit".to_string(),
+            };
+            let packages = packages(repo, &tree.get_path(Path::new(dir))?.to_object(repo)?)?;
+            modules.push((module.to_string(), version, explicit, packages));
+        }
+    }
+    modules.sort();
+    for (module, version, explicit, packages) in modules {
+        content.push_str(&format!("# {module} {version}\n{explicit}\n"));
+        for package in packages {
+            match package.as_str() {
+                "" => content.push_str(&format!("{module}\n")),
+                package => content.push_str(&format!("{module}/{package}\n")),
+            }
+        }
+    }
+    Ok(content)
+}
+
+/// The value of the first `name` directive of `go.mod` content.
+fn directive<'a>(go_mod: &'a str, name: &str) -> Option<&'a str> {
+    go_mod.lines().find_map(|line| {
+        let value = line.trim().strip_prefix(name)?;
+        value
+            .starts_with(char::is_whitespace)
+            .then(|| value.trim().trim_matches('"'))
+    })
+}
+
+/// The pseudo-version of the upstream `commit`.
+fn pseudo_version(repo: &Repository, commit: &str) -> Result<String, Error> {
+    let commit = repo
+        .revparse_single(commit)
+        .and_then(|object| object.peel_to_commit())
+        .map_err(|_| {
+            Error::from_str(&format!(
+                "Commit {commit} is not available; run git vendor fetch"
+            ))
+        })?;
+    let time: String = format_time(commit.time().seconds())
+        .chars()
+        .filter(char::is_ascii_digit)
+        .collect();
+    Ok(format!("v0.0.0-{time}-{}", &commit.id().to_string()[..12]))
+}
+
+/// The directories of the module tree `module` holding Go sources other
+/// than tests, relative to it; `""` for its root. Nested modules are left
+/// out along with their packages.
+fn packages(repo: &Repository, module: &git2::Object) -> Result<Vec<String>, Error> {
+    let tree = module
+        .as_tree()
+        .ok_or_else(|| Error::from_str("Module directory is not a tree"))?;
+    let mut nested = BTreeSet::new();
+    let mut packages = BTreeSet::new();
+    walk_tree(repo, tree, |path, entry| {
+        let Ok(path) = std::str::from_utf8(path) else {
+            return Ok(());
+        };
+        if entry.kind() != Some(ObjectType::Blob) {
+            return Ok(());
+        }
+        let (dir, file) = path.rsplit_once('/').unwrap_or(("", path));
+        if file == "go.mod" && !dir.is_empty() {
+            nested.insert(dir.to_string());
+        } else if file.ends_with(".go")
+            && !file.ends_with("_test.go")
+            && !dir
+                .split('/')
+                .any(|c| c == "testdata" || c.starts_with(['.', '_']))
+        {
+            packages.insert(dir.to_string());
+        }
+        Ok(())
+    })?;
+    Ok(packages
+        .into_iter()
+        .filter(|package| {
+            !nested
+                .iter()
+                .any(|nested| package == nested || package.starts_with(&format!("{nested}/")))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn module(path: &str, version: &str) -> Module {
+        Module {
+            path: path.into(),
+            version: version.into(),
+        }
+    }
+
+    #[test]
+    fn required_modules_applies_replacements() {
+        let go_mod = "module example.com/host\n\n\
+                      go 1.22\n\n\
+                      require github.com/o/a v1.2.3\n\
+                      require (\n\
+                      \tgithub.com/o/b v0.1.0 // indirect\n\
+                      \tgithub.com/o/c v1.0.0\n\
+                      \tgithub.com/o/d v1.0.0\n\
+                      )\n\n\
+                      replace github.com/o/c => github.com/fork/c v1.0.1\n\
+                      replace github.com/o/d v1.0.0 => ../d\n";
+        assert_eq!(
+            required_modules(go_mod),
+            [
+                module("github.com/o/a", "v1.2.3"),
+                module("github.com/o/b", "v0.1.0"),
+                module("github.com/fork/c", "v1.0.1"),
+            ]
+        );
+    }
+
+    #[test]
+    fn recorded_modules_reads_module_lines() {
+        let modules_txt = "# github.com/o/a v1.2.3\n\
+                           ## explicit; go 1.21\n\
+                           github.com/o/a\n\
+                           # github.com/o/c v1.0.0 => github.com/fork/c v1.0.1\n\
+                           ## explicit\n\
+                           github.com/o/c/sub\n\
+                           # github.com/o/d => ../d\n";
+        assert_eq!(
+            recorded_modules(modules_txt),
+            [
+                module("github.com/o/a", "v1.2.3"),
+                module("github.com/fork/c", "v1.0.1"),
+            ]
+        );
+    }
+
+    #[test]
+    fn location_maps_module_paths_and_versions() {
+        let location_of = |path, version| {
+            let location = location(&module(path, version));
+            (location.url, location.dir, location.rev)
+        };
+        assert_eq!(
+            location_of("github.com/o/r/sub/v2", "v2.1.0"),
+            (
+                "https://github.com/o/r".into(),
+                "sub/v2".into(),
+                "sub/v2.1.0".into()
+            )
+        );
+        assert_eq!(
+            location_of("golang.org/x/net", "v0.0.0-20240229123456-0123456789ab"),
+            (
+                "https://go.googlesource.com/net".into(),
+                String::new(),
+                "0123456789ab".into()
+            )
+        );
+        assert_eq!(
+            location_of("gopkg.in/yaml.v3", "v3.0.1"),
+            (
+                "https://github.com/go-yaml/yaml".into(),
+                String::new(),
+                "v3.0.1".into()
+            )
+        );
+        assert_eq!(
+            location_of("github.com/o/r", "v2.0.0+incompatible").2,
+            "v2.0.0"
+        );
+    }
+
+    #[test]
+    fn pseudo_commit_accepts_all_pseudo_version_forms() {
+        for version in [
+            "v0.0.0-20240229123456-0123456789ab",
+            "v1.2.4-0.20240229123456-0123456789ab",
+            "v1.2.3-pre.0.20240229123456-0123456789ab",
+        ] {
+            assert_eq!(pseudo_commit(version), Some("0123456789ab"), "{version}");
+        }
+        assert_eq!(pseudo_commit("v1.2.3"), None);
+        assert_eq!(pseudo_commit("v1.2.3-rc.1"), None);
+    }
+}
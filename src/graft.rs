@@ -0,0 +1,135 @@
+//! Merging several dependencies in a single pass.
+//!
+//! Each dependency's side of a merge covers only its own content: the
+//! previously vendored tree as the ancestor and the filtered upstream tree
+//! as theirs, both at the paths they occupy in the host. The sides of
+//! dependencies that don't overlap are grafted into one ancestor and one
+//! upstream tree and merged with `HEAD` once, rather than once per
+//! dependency, with its index and working tree update each time.
+//!
+//! The result is then split up again: [`Merged::apply`] carries one
+//! dependency's merged paths onto a tree, so each can still be committed,
+//! recorded and confirmed on its own.
+
+use git_filter_tree::{bytes_to_path, walk_tree};
+use git2::{
+    Error, FileMode, Index, IndexConflict, MergeOptions, ObjectType, Oid, Repository, Tree,
+    build::TreeUpdateBuilder,
+};
+use std::collections::BTreeSet;
+
+/// The single merge of several dependencies' upstream content.
+pub(crate) struct Merged {
+    index: Index,
+    /// The paths each dependency's side of the merge touches, by position.
+    paths: Vec<BTreeSet<Vec<u8>>>,
+}
+
+/// Merge the `(ancestor, upstream)` side of each dependency into `head` in
+/// one three-way merge.
+///
+/// Only the leading dependencies whose paths don't overlap are merged; the
+/// rest, starting with the first that overlaps an earlier one, are left for
+/// another merge into the result, so each sees the content merged before it.
+pub(crate) fn merge(
+    repo: &Repository,
+    head: &Tree<'_>,
+    sides: &[(Tree<'_>, Tree<'_>)],
+    merge_opts: Option<&MergeOptions>,
+) -> Result<Merged, Error> {
+    let mut merged = BTreeSet::new();
+    let mut paths = Vec::new();
+    let mut ancestor = TreeUpdateBuilder::new();
+    let mut upstream = TreeUpdateBuilder::new();
+    for (ancestor_tree, upstream_tree) in sides {
+        let mut entries = Vec::new();
+        for (tree, upstream) in [(ancestor_tree, false), (upstream_tree, true)] {
+            walk_tree(repo, tree, |path, entry| {
+                if entry.kind() != Some(ObjectType::Tree) {
+                    entries.push((upstream, path.to_vec(), entry.id(), entry.filemode()));
+                }
+                Ok(())
+            })?;
+        }
+        let owned: BTreeSet<Vec<u8>> = entries.iter().map(|(_, path, ..)| path.clone()).collect();
+        if !paths.is_empty() && !owned.is_disjoint(&merged) {
+            break;
+        }
+        for (is_upstream, path, id, mode) in entries {
+            let update = if is_upstream {
+                &mut upstream
+            } else {
+                &mut ancestor
+            };
+            update.upsert(path, id, file_mode(mode as u32));
+        }
+        merged.extend(owned.iter().cloned());
+        paths.push(owned);
+    }
+
+    let empty = repo.find_tree(repo.treebuilder(None)?.write()?)?;
+    let ancestor = repo.find_tree(ancestor.create_updated(repo, &empty)?)?;
+    let upstream = repo.find_tree(upstream.create_updated(repo, &empty)?)?;
+    tracing::debug!(
+        deps = paths.len(),
+        paths = merged.len(),
+        "Merging grafted upstream trees"
+    );
+    let index = repo.merge_trees(&ancestor, head, &upstream, merge_opts)?;
+    Ok(Merged { index, paths })
+}
+
+impl Merged {
+    /// How many of the dependencies given to [`merge`] were merged.
+    pub(crate) fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    /// The conflicts in the merge of the `dep`th dependency.
+    pub(crate) fn conflicts(&self, dep: usize) -> Result<Vec<IndexConflict>, Error> {
+        let mut conflicts = Vec::new();
+        for conflict in self.index.conflicts()? {
+            let conflict = conflict?;
+            let entry = [&conflict.our, &conflict.their, &conflict.ancestor]
+                .into_iter()
+                .flatten()
+                .next();
+            if entry.is_some_and(|entry| self.paths[dep].contains(&entry.path)) {
+                conflicts.push(conflict);
+            }
+        }
+        Ok(conflicts)
+    }
+
+    /// `tree` with the paths of the `dep`th dependency as merged, which must
+    /// be free of conflicts.
+    pub(crate) fn apply(
+        &self,
+        repo: &Repository,
+        tree: &Tree<'_>,
+        dep: usize,
+    ) -> Result<Oid, Error> {
+        let mut update = TreeUpdateBuilder::new();
+        for path in &self.paths[dep] {
+            match self.index.get_path(&bytes_to_path(path), 0) {
+                Some(entry) => {
+                    update.upsert(path.as_slice(), entry.id, file_mode(entry.mode));
+                }
+                None if tree.get_path(&bytes_to_path(path)).is_ok() => {
+                    update.remove(path.as_slice());
+                }
+                None => {}
+            }
+        }
+        update.create_updated(repo, tree)
+    }
+}
+
+fn file_mode(mode: u32) -> FileMode {
+    match mode {
+        0o100755 => FileMode::BlobExecutable,
+        0o120000 => FileMode::Link,
+        0o160000 => FileMode::Commit,
+        _ => FileMode::Blob,
+    }
+}
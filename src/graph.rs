@@ -0,0 +1,317 @@
+//! Rendering of the vendor relationships between the host and upstreams.
+
+use super::{Transitive, VendorDep, provenance};
+use git2::{Error, Repository};
+use serde::Serialize;
+use serde_json::json;
+use std::fmt;
+
+/// Supported dependency graph formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GraphFormat {
+    /// A Graphviz `digraph`.
+    #[default]
+    Dot,
+    /// A JSON object of `nodes` and `edges`.
+    Json,
+}
+
+/// An edge from a vendoring repository to a dependency it vendors.
+struct Edge<'a> {
+    from: &'a str,
+    to: &'a str,
+    pattern: &'a str,
+    /// The upstream commit vendored, if it has been merged.
+    version: Option<&'a str>,
+}
+
+/// A dependency node.
+struct Node<'a> {
+    name: &'a str,
+    url: &'a str,
+}
+
+/// A problem found in the vendor graph.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum GraphIssue {
+    /// Dependencies vendoring each other in a loop, starting and ending
+    /// with the same name.
+    Cycle { path: Vec<String> },
+    /// An upstream vendored at different commits, each paired with the
+    /// repository vendoring it.
+    Duplicate {
+        name: String,
+        versions: Vec<(String, String)>,
+    },
+}
+
+impl fmt::Display for GraphIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GraphIssue::Cycle { path } => write!(f, "cycle: {}", path.join(" -> ")),
+            GraphIssue::Duplicate { name, versions } => {
+                let versions: Vec<String> = versions
+                    .iter()
+                    .map(|(from, commit)| format!("{} via {from}", &commit[..commit.len().min(8)]))
+                    .collect();
+                write!(f, "{name} vendored at {}", versions.join(", "))
+            }
+        }
+    }
+}
+
+/// Render the graph of `host` vendoring `deps`, which in turn vendor
+/// `transitive`.
+pub(crate) fn render(
+    repo: &Repository,
+    host: &str,
+    deps: &[&VendorDep],
+    transitive: &[Transitive],
+    format: GraphFormat,
+) -> Result<String, Error> {
+    let versions = versions(repo, deps)?;
+    let edges = edges(host, deps, &versions, transitive);
+
+    let mut nodes: Vec<Node> = Vec::new();
+    for dep in deps {
+        add_node(&mut nodes, &dep.name, &dep.url);
+    }
+    for dep in transitive {
+        add_node(&mut nodes, &dep.name, &dep.url);
+    }
+
+    Ok(match format {
+        GraphFormat::Dot => dot(host, &nodes, &edges),
+        GraphFormat::Json => {
+            let doc = json!({
+                "host": host,
+                "nodes": nodes
+                    .iter()
+                    .map(|node| json!({ "name": node.name, "url": node.url }))
+                    .collect::<Vec<_>>(),
+                "edges": edges
+                    .iter()
+                    .map(|edge| json!({
+                        "from": edge.from,
+                        "to": edge.to,
+                        "pattern": edge.pattern,
+                        "version": edge.version,
+                    }))
+                    .collect::<Vec<_>>(),
+            });
+            serde_json::to_string_pretty(&doc)
+                .map(|s| s + "\n")
+                .map_err(|e| Error::from_str(&e.to_string()))?
+        }
+    })
+}
+
+/// Find cycles and upstreams vendored at more than one commit in the graph
+/// of `host` vendoring `deps`, which in turn vendor `transitive`.
+pub(crate) fn issues(
+    repo: &Repository,
+    host: &str,
+    deps: &[&VendorDep],
+    transitive: &[Transitive],
+) -> Result<Vec<GraphIssue>, Error> {
+    let versions = versions(repo, deps)?;
+    let edges = edges(host, deps, &versions, transitive);
+
+    let mut issues: Vec<GraphIssue> = cycles(&edges)
+        .into_iter()
+        .map(|path| GraphIssue::Cycle { path })
+        .collect();
+
+    issues.extend(duplicates(&edges));
+    Ok(issues)
+}
+
+/// The commit each of `deps` was merged from, per the records in `HEAD`.
+fn versions(repo: &Repository, deps: &[&VendorDep]) -> Result<Vec<Option<String>>, Error> {
+    let head_tree = repo.head()?.peel_to_tree()?;
+    deps.iter()
+        .map(|dep| {
+            provenance::read(repo, &head_tree, &dep.name)
+                .map(|record| record.map(|record| record.commit))
+        })
+        .collect()
+}
+
+fn edges<'a>(
+    host: &'a str,
+    deps: &[&'a VendorDep],
+    versions: &'a [Option<String>],
+    transitive: &'a [Transitive],
+) -> Vec<Edge<'a>> {
+    let direct = deps.iter().zip(versions).map(|(dep, version)| Edge {
+        from: host,
+        to: &dep.name,
+        pattern: &dep.pattern,
+        version: version.as_deref(),
+    });
+    let nested = transitive.iter().map(|dep| Edge {
+        from: &dep.parent,
+        to: &dep.name,
+        pattern: &dep.pattern,
+        version: dep.commit.as_deref(),
+    });
+    direct.chain(nested).collect()
+}
+
+/// Upstreams the `edges` lead to at more than one commit.
+fn duplicates(edges: &[Edge]) -> Vec<GraphIssue> {
+    let mut names: Vec<&str> = edges.iter().map(|edge| edge.to).collect();
+    names.sort_unstable();
+    names.dedup();
+
+    let mut found = Vec::new();
+    for name in names {
+        let mut versions: Vec<(String, String)> = Vec::new();
+        for edge in edges.iter().filter(|edge| edge.to == name) {
+            if let Some(version) = edge.version
+                && !versions.iter().any(|(_, v)| v == version)
+            {
+                versions.push((edge.from.to_string(), version.to_string()));
+            }
+        }
+        if versions.len() > 1 {
+            found.push(GraphIssue::Duplicate {
+                name: name.to_string(),
+                versions,
+            });
+        }
+    }
+    found
+}
+
+/// Every cycle among the dependencies of `edges`, each starting from its
+/// alphabetically first name and reported once.
+fn cycles(edges: &[Edge]) -> Vec<Vec<String>> {
+    fn walk<'a>(edges: &[Edge<'a>], path: &mut Vec<&'a str>, found: &mut Vec<Vec<String>>) {
+        let last = path[path.len() - 1];
+        for edge in edges.iter().filter(|edge| edge.from == last) {
+            if edge.to == path[0] {
+                let mut cycle: Vec<String> = path.iter().map(|name| name.to_string()).collect();
+                cycle.push(path[0].to_string());
+                found.push(cycle);
+            } else if edge.to > path[0] && !path.contains(&edge.to) {
+                path.push(edge.to);
+                walk(edges, path, found);
+                path.pop();
+            }
+        }
+    }
+
+    let mut starts: Vec<&str> = edges.iter().map(|edge| edge.from).collect();
+    starts.sort_unstable();
+    starts.dedup();
+    let mut found = Vec::new();
+    for start in starts {
+        walk(edges, &mut vec![start], &mut found);
+    }
+    found.dedup();
+    found
+}
+
+fn add_node<'a>(nodes: &mut Vec<Node<'a>>, name: &'a str, url: &'a str) {
+    if !nodes.iter().any(|node| node.name == name) {
+        nodes.push(Node { name, url });
+    }
+}
+
+/// Build a Graphviz `digraph` labelling each edge with its pattern and the
+/// abbreviated version.
+fn dot(host: &str, nodes: &[Node], edges: &[Edge]) -> String {
+    let mut out = String::from("digraph vendor {\n");
+    out.push_str(&format!("  {} [shape=box];\n", quote(host)));
+    for node in nodes {
+        out.push_str(&format!(
+            "  {} [tooltip={}];\n",
+            quote(node.name),
+            quote(node.url)
+        ));
+    }
+    for edge in edges {
+        let label = match edge.version {
+            Some(version) => format!("{}\\n{}", edge.pattern, &version[..version.len().min(8)]),
+            None => edge.pattern.to_string(),
+        };
+        out.push_str(&format!(
+            "  {} -> {} [label={}];\n",
+            quote(edge.from),
+            quote(edge.to),
+            quote(&label)
+        ));
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// Quote `id` as a DOT string, keeping `\n` line breaks in labels.
+fn quote(id: &str) -> String {
+    format!("\"{}\"", id.replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edge<'a>(from: &'a str, to: &'a str, version: &'a str) -> Edge<'a> {
+        Edge {
+            from,
+            to,
+            pattern: "p/",
+            version: Some(version),
+        }
+    }
+
+    #[test]
+    fn cycles_are_reported_once_from_their_first_name() {
+        let edges = [
+            edge("host", "o/b", "1"),
+            edge("o/b", "o/c", "2"),
+            edge("o/c", "o/b", "3"),
+            edge("o/c", "o/d", "4"),
+        ];
+        assert_eq!(cycles(&edges), [["o/b", "o/c", "o/b"]]);
+    }
+
+    #[test]
+    fn duplicates_need_distinct_versions() {
+        let edges = [
+            edge("host", "o/b", "1"),
+            edge("host", "o/c", "2"),
+            edge("o/c", "o/b", "3"),
+            edge("o/c", "o/d", "4"),
+            edge("o/b", "o/d", "4"),
+        ];
+        assert_eq!(
+            duplicates(&edges),
+            [GraphIssue::Duplicate {
+                name: "o/b".into(),
+                versions: vec![("host".into(), "1".into()), ("o/c".into(), "3".into())],
+            }]
+        );
+    }
+
+    #[test]
+    fn dot_labels_edges_with_pattern_and_version() {
+        let nodes = [Node {
+            name: "o/a",
+            url: "https://a.com/o/a.git",
+        }];
+        let edges = [Edge {
+            from: "host",
+            to: "o/a",
+            pattern: "vendor/a/",
+            version: Some("0123456789abcdef"),
+        }];
+        assert_eq!(
+            dot("host", &nodes, &edges),
+            "digraph vendor {\n  \"host\" [shape=box];\n  \
+             \"o/a\" [tooltip=\"https://a.com/o/a.git\"];\n  \
+             \"host\" -> \"o/a\" [label=\"vendor/a/\\n01234567\"];\n}\n"
+        );
+    }
+}
@@ -0,0 +1,120 @@
+//! A pre-commit hook guarding vendored paths against accidental edits.
+
+use super::{VendorDep, ownership::Ownership, provenance::provenance_path};
+use git2::{Error, Repository};
+use std::{fs, path::PathBuf};
+
+/// Environment variable that lets a commit through the installed hook.
+pub const ALLOW_EDITS_ENV: &str = "GIT_VENDOR_ALLOW_EDITS";
+
+/// Marker identifying hooks written by `git-vendor`.
+const HOOK_MARKER: &str = "# Installed by git-vendor install-hooks";
+
+/// A staged change to a path owned by a vendored dependency.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StagedEdit {
+    pub path: String,
+    pub dep: String,
+}
+
+/// Find staged changes to vendored paths that are not part of a vendor
+/// update.
+///
+/// Changes accompanied by a staged update to the dependency's provenance
+/// record (as written by a `--no-commit` or `--squash` merge) are treated
+/// as vendor updates and allowed.
+pub(crate) fn staged_edits(
+    repo: &Repository,
+    deps: &[&VendorDep],
+) -> Result<Vec<StagedEdit>, Error> {
+    let ownership = Ownership::new(repo, deps)?;
+    let head_tree = match repo.head() {
+        Ok(head) => Some(head.peel_to_tree()?),
+        Err(_) => None,
+    };
+    let diff = repo.diff_tree_to_index(head_tree.as_ref(), None, None)?;
+
+    let mut changed = Vec::new();
+    for delta in diff.deltas() {
+        for file in [delta.old_file(), delta.new_file()] {
+            if let Some(path) = file.path().and_then(|p| p.to_str())
+                && !changed.iter().any(|c| c == path)
+            {
+                changed.push(path.to_string());
+            }
+        }
+    }
+
+    let mut edits = Vec::new();
+    for path in &changed {
+        let Some(dep) = ownership.owner(path) else {
+            continue;
+        };
+        if changed.contains(&provenance_path(&dep.name)) {
+            continue;
+        }
+        edits.push(StagedEdit {
+            path: path.clone(),
+            dep: dep.name.clone(),
+        });
+    }
+
+    Ok(edits)
+}
+
+/// Write a `pre-commit` hook that runs `git-vendor pre-commit`.
+///
+/// An existing hook not written by `git-vendor` is only replaced when
+/// `force` is set.
+pub(crate) fn install(repo: &Repository, block: bool, force: bool) -> Result<PathBuf, Error> {
+    let hook = hook_path(repo)?;
+    let hooks_dir = hook.parent().expect("hook path has a parent");
+
+    if let Ok(existing) = fs::read_to_string(&hook)
+        && !existing.contains(HOOK_MARKER)
+        && !force
+    {
+        return Err(Error::from_str(&format!(
+            "{} already exists; use --force to replace it",
+            hook.display()
+        )));
+    }
+
+    let mode = if block { " --block" } else { "" };
+    let script = format!(
+        "#!/bin/sh\n\
+         {HOOK_MARKER}\n\
+         # Set {ALLOW_EDITS_ENV}=1 to commit changes to vendored paths.\n\
+         [ -n \"${ALLOW_EDITS_ENV}\" ] && exit 0\n\
+         exec git-vendor pre-commit{mode}\n"
+    );
+
+    fs::create_dir_all(hooks_dir).map_err(|e| Error::from_str(&e.to_string()))?;
+    fs::write(&hook, script).map_err(|e| Error::from_str(&e.to_string()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&hook, fs::Permissions::from_mode(0o755))
+            .map_err(|e| Error::from_str(&e.to_string()))?;
+    }
+
+    Ok(hook)
+}
+
+/// Whether the `pre-commit` hook of `repo` was written by [`install`].
+pub(crate) fn installed(repo: &Repository) -> Result<bool, Error> {
+    Ok(fs::read_to_string(hook_path(repo)?).is_ok_and(|hook| hook.contains(HOOK_MARKER)))
+}
+
+/// Where the `pre-commit` hook of `repo` lives, honoring `core.hooksPath`.
+pub(crate) fn hook_path(repo: &Repository) -> Result<PathBuf, Error> {
+    let hooks_dir = match repo.config()?.get_path("core.hooksPath") {
+        Ok(path) if path.is_absolute() => path,
+        Ok(path) => repo
+            .workdir()
+            .ok_or_else(|| Error::from_str("Repository has no working directory"))?
+            .join(path),
+        Err(_) => repo.path().join("hooks"),
+    };
+    Ok(hooks_dir.join("pre-commit"))
+}
@@ -0,0 +1,80 @@
+//! User commands run around vendor operations.
+//!
+//! Hooks are shell commands configured in Git config:
+//!
+//! | Key                    | Runs                                 |
+//! |------------------------|--------------------------------------|
+//! | `vendor.postFetchHook` | after each dependency is fetched     |
+//! | `vendor.preMergeHook`  | before each dependency is merged     |
+//! | `vendor.postMergeHook` | after each dependency merges cleanly |
+//!
+//! Each command runs from the repository root with the dependency described
+//! by `GIT_VENDOR_*` environment variables. A failing hook aborts the
+//! operation.
+
+use super::VendorDep;
+use git2::{Error, Oid, Repository};
+use std::process::Command;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Hook {
+    PostFetch,
+    PreMerge,
+    PostMerge,
+}
+
+impl Hook {
+    fn config_key(self) -> &'static str {
+        match self {
+            Hook::PostFetch => "vendor.postFetchHook",
+            Hook::PreMerge => "vendor.preMergeHook",
+            Hook::PostMerge => "vendor.postMergeHook",
+        }
+    }
+}
+
+/// Run `hook` for `dep` if one is configured.
+///
+/// `old` is the commit the dependency was at before the operation (the
+/// previously fetched or merged commit), and `new` the commit it moves to.
+pub(crate) fn run(
+    repo: &Repository,
+    hook: Hook,
+    dep: &VendorDep,
+    old: Option<Oid>,
+    new: Oid,
+) -> Result<(), Error> {
+    let Ok(cmd) = repo.config()?.get_string(hook.config_key()) else {
+        return Ok(());
+    };
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| Error::from_str("Repository has no working directory"))?;
+
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(&cmd)
+        .current_dir(workdir)
+        .env("GIT_VENDOR_HOOK", hook.config_key())
+        .env("GIT_VENDOR_NAME", &dep.name)
+        .env("GIT_VENDOR_PATTERN", &dep.pattern)
+        .env("GIT_VENDOR_URL", &dep.url)
+        .env("GIT_VENDOR_BRANCH", dep.branch.as_deref().unwrap_or(""))
+        .env(
+            "GIT_VENDOR_OLD",
+            old.map(|o| o.to_string()).unwrap_or_default(),
+        )
+        .env("GIT_VENDOR_NEW", new.to_string())
+        .status()
+        .map_err(|e| Error::from_str(&format!("Failed to run {}: {e}", hook.config_key())))?;
+
+    if !status.success() {
+        return Err(Error::from_str(&format!(
+            "{} for {} failed ({status})",
+            hook.config_key(),
+            dep.name
+        )));
+    }
+
+    Ok(())
+}
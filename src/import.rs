@@ -0,0 +1,323 @@
+//! Importing git dependencies from `Cargo.lock`.
+//!
+//! Cargo records a git dependency as a package whose `source` is
+//! `git+<url>?<branch|tag|rev>=<value>#<commit>`. The packages locked to the
+//! same repository and commit become one dependency pinned to that commit,
+//! with one pattern per crate.
+//!
+//! Vendored content keeps the paths it has upstream, so each crate's
+//! pattern is its directory in the upstream repository. The lockfile does
+//! not say where that is, so each upstream is fetched and searched for the
+//! `Cargo.toml` declaring the crate. A crate at the root of its repository
+//! cannot be vendored without overlapping the host's own files, and is
+//! reported as skipped instead.
+//!
+//! A `cargo vendor` directory is read through the `Cargo.lock` of the
+//! project it belongs to, and its copies of the imported crates are
+//! reported so they can be removed once the dependencies are merged.
+
+use super::{VendorDep, config::Config, fetch_dep, name_from_url, source, vendor_ref_name};
+use git_filter_tree::walk_tree;
+use git2::{Error, ObjectType, Repository};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// A dependency created or updated from a lockfile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Imported {
+    pub name: String,
+    pub url: String,
+    /// The locked commit the dependency is pinned to.
+    pub rev: String,
+    /// The branch the lockfile followed, if any, for unpinning later.
+    pub branch: Option<String>,
+    /// The crates or modules imported, with their patterns.
+    pub packages: Vec<(String, String)>,
+    /// The crates or modules that could not be imported, with the reason.
+    pub skipped: Vec<(String, String)>,
+    /// Copies of the imported packages in the vendor directory.
+    pub replaces: Vec<PathBuf>,
+}
+
+/// A package from a git source in `Cargo.lock`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct GitPackage {
+    name: String,
+    version: String,
+    url: String,
+    branch: Option<String>,
+    commit: String,
+}
+
+/// Import the git dependencies locked by the `Cargo.lock` at `path`, or by
+/// the project of the `cargo vendor` directory at `path`, writing their
+/// definitions to `gitattributes` unless `dry_run`.
+pub(crate) fn cargo(
+    repo: &Repository,
+    gitattributes: &Path,
+    path: &Path,
+    dry_run: bool,
+) -> Result<Vec<Imported>, Error> {
+    let (lockfile, vendor_dir) = if path.is_dir() {
+        let project = path.parent().unwrap_or(Path::new("."));
+        (project.join("Cargo.lock"), Some(path))
+    } else {
+        (path.to_path_buf(), None)
+    };
+    let content = fs::read_to_string(&lockfile)
+        .map_err(|e| Error::from_str(&format!("Failed to read {}: {e}", lockfile.display())))?;
+    let config = Config::resolve(repo)?;
+
+    let mut upstreams: BTreeMap<(String, String), Vec<GitPackage>> = BTreeMap::new();
+    for package in git_packages(&content) {
+        upstreams
+            .entry((package.url.clone(), package.commit.clone()))
+            .or_default()
+            .push(package);
+    }
+    if !upstreams.is_empty() {
+        config.require_online("fetch the upstreams of locked git dependencies")?;
+    }
+
+    let source = source::open(repo, gitattributes)?;
+    let mut imported = Vec::new();
+    for ((url, commit), packages) in upstreams {
+        let name = name_from_url(&url)
+            .filter(|name| {
+                !name.contains(char::is_whitespace)
+                    && git2::Reference::is_valid_name(&vendor_ref_name(&config.ref_prefix, name))
+            })
+            .unwrap_or_else(|| packages[0].name.clone());
+        let mut dep = VendorDep {
+            name,
+            url,
+            branch: packages[0].branch.clone(),
+            rev: Some(commit.clone()),
+            ..Default::default()
+        };
+        fetch_dep(repo, &dep, None, &config)?;
+        let tree = repo
+            .find_reference(&vendor_ref_name(&config.ref_prefix, &dep.name))?
+            .peel_to_tree()?;
+        let dirs = crate_dirs(repo, &tree)?;
+
+        let mut result = Imported {
+            name: dep.name.clone(),
+            url: dep.url.clone(),
+            rev: commit,
+            branch: dep.branch.clone(),
+            packages: Vec::new(),
+            skipped: Vec::new(),
+            replaces: Vec::new(),
+        };
+        for package in &packages {
+            match dirs.get(&package.name).map(String::as_str) {
+                Some("") => result.skipped.push((
+                    package.name.clone(),
+                    "at the root of its repository".to_string(),
+                )),
+                Some(dir) => {
+                    dep.pattern = format!("{dir}/");
+                    if !dry_run {
+                        source.upsert(&dep, &[])?;
+                    }
+                    result
+                        .packages
+                        .push((package.name.clone(), dep.pattern.clone()));
+                    if let Some(vendor_dir) = vendor_dir {
+                        result.replaces.extend(
+                            [
+                                package.name.clone(),
+                                format!("{}-{}", package.name, package.version),
+                            ]
+                            .into_iter()
+                            .map(|copy| vendor_dir.join(copy))
+                            .filter(|copy| copy.is_dir()),
+                        );
+                    }
+                }
+                None => result.skipped.push((
+                    package.name.clone(),
+                    format!("no Cargo.toml declares it at {}", result.rev),
+                )),
+            }
+        }
+        imported.push(result);
+    }
+    Ok(imported)
+}
+
+/// A package entry of `Cargo.lock`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct LockedPackage {
+    pub name: String,
+    pub version: String,
+    /// Where the package comes from; `None` for path packages.
+    pub source: Option<String>,
+}
+
+/// The packages of `Cargo.lock` content.
+pub(crate) fn locked_packages(content: &str) -> Vec<LockedPackage> {
+    let mut packages = Vec::new();
+    let mut fields: BTreeMap<&str, &str> = BTreeMap::new();
+    for line in content.lines().chain(["[[package]]"]) {
+        let line = line.trim();
+        if line.starts_with('[') {
+            if let Some(name) = fields.get("name") {
+                packages.push(LockedPackage {
+                    name: name.to_string(),
+                    version: fields.get("version").unwrap_or(&"").to_string(),
+                    source: fields.get("source").map(|source| source.to_string()),
+                });
+            }
+            fields.clear();
+        } else if let Some((key, value)) = line.split_once('=') {
+            fields.insert(key.trim(), value.trim().trim_matches('"'));
+        }
+    }
+    packages
+}
+
+/// The packages of `Cargo.lock` content from git sources.
+fn git_packages(content: &str) -> Vec<GitPackage> {
+    locked_packages(content)
+        .iter()
+        .filter_map(git_package)
+        .collect()
+}
+
+/// `package` as a git package, if it comes from a git source.
+fn git_package(package: &LockedPackage) -> Option<GitPackage> {
+    let (location, commit) = package
+        .source
+        .as_deref()?
+        .strip_prefix("git+")?
+        .split_once('#')?;
+    let (url, query) = location.split_once('?').unwrap_or((location, ""));
+    let branch = query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("branch="))
+        .map(str::to_string);
+    Some(GitPackage {
+        name: package.name.clone(),
+        version: package.version.clone(),
+        url: url.to_string(),
+        branch,
+        commit: commit.to_string(),
+    })
+}
+
+/// The directory of each crate in `tree`, by name; `""` for the root. The
+/// shallowest wins when several declare the same name.
+fn crate_dirs(repo: &Repository, tree: &git2::Tree) -> Result<BTreeMap<String, String>, Error> {
+    let mut dirs: BTreeMap<String, String> = BTreeMap::new();
+    walk_tree(repo, tree, |path, entry| {
+        let Ok(path) = std::str::from_utf8(path) else {
+            return Ok(());
+        };
+        let dir = match path.strip_suffix("Cargo.toml") {
+            Some("") => "",
+            Some(dir) if dir.ends_with('/') => dir.trim_end_matches('/'),
+            _ => return Ok(()),
+        };
+        if entry.kind() != Some(ObjectType::Blob) {
+            return Ok(());
+        }
+        let blob = repo.find_blob(entry.id())?;
+        if let Some(name) = std::str::from_utf8(blob.content())
+            .ok()
+            .and_then(package_name)
+        {
+            let shallower = dirs
+                .get(&name)
+                .is_none_or(|known| dir.matches('/').count() < known.matches('/').count());
+            if shallower {
+                dirs.insert(name, dir.to_string());
+            }
+        }
+        Ok(())
+    })?;
+    Ok(dirs)
+}
+
+/// The `name` of the `[package]` table of a `Cargo.toml`.
+pub(crate) fn package_name(manifest: &str) -> Option<String> {
+    let mut in_package = false;
+    for line in manifest.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_package = line == "[package]";
+        } else if in_package
+            && let Some((key, value)) = line.split_once('=')
+            && key.trim() == "name"
+        {
+            return Some(value.trim().trim_matches('"').to_string());
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn git_packages_skips_registry_and_path_packages() {
+        let lock = r#"
+version = 4
+
+[[package]]
+name = "serde"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "abc"
+
+[[package]]
+name = "foo"
+version = "0.1.0"
+source = "git+https://github.com/o/foo?branch=dev#0123456789abcdef0123456789abcdef01234567"
+
+[[package]]
+name = "bar"
+version = "0.2.0"
+source = "git+https://github.com/o/bar#89abcdef0123456789abcdef0123456789abcdef"
+dependencies = [
+ "foo",
+]
+
+[[package]]
+name = "host"
+version = "0.1.0"
+"#;
+        let packages = git_packages(lock);
+        assert_eq!(
+            packages,
+            [
+                GitPackage {
+                    name: "foo".into(),
+                    version: "0.1.0".into(),
+                    url: "https://github.com/o/foo".into(),
+                    branch: Some("dev".into()),
+                    commit: "0123456789abcdef0123456789abcdef01234567".into(),
+                },
+                GitPackage {
+                    name: "bar".into(),
+                    version: "0.2.0".into(),
+                    url: "https://github.com/o/bar".into(),
+                    branch: None,
+                    commit: "89abcdef0123456789abcdef0123456789abcdef".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn package_name_reads_only_the_package_table() {
+        let manifest = "[workspace]\nname = \"no\"\n\n[package]\nname = \"yes\"\n";
+        assert_eq!(package_name(manifest).as_deref(), Some("yes"));
+        assert_eq!(package_name("[workspace]\nmembers = []\n"), None);
+    }
+}
@@ -6,18 +6,140 @@
 //! path/to/dep/* vendored name=owner/repo url=https://example.com/owner/repo.git branch=main
 //! ```
 //!
-//! Fetched content is stored under `refs/vendor/<name>`.
+//! Fetched content is stored under `refs/vendor/<name>` (with upstream tags
+//! under `refs/vendor-tags/<name>/`), and every merge
+//! records its origin in `.git-vendor/<name>.json`.
+
+/// Print a progress message unless [`set_quiet`] is in effect or progress
+/// is reported as [`events`].
+macro_rules! progress {
+    ($($arg:tt)*) => {
+        if !$crate::QUIET.load(std::sync::atomic::Ordering::Relaxed)
+            && !$crate::events::enabled()
+        {
+            println!($($arg)*);
+        }
+    };
+}
+
+pub mod annotations;
+mod attributes;
+pub mod bisect;
+pub mod blame;
+mod cache;
+mod cargo_patch;
+pub mod changelog;
+mod clean;
+mod codeowners;
+pub mod config;
+mod credential;
+mod diff;
+pub mod diffstat;
+pub mod error;
+pub mod events;
+mod explain;
+mod export_ignore;
+mod foreach;
+mod forge;
+mod gc;
+mod gomod;
+mod graft;
+pub mod graph;
+pub mod guard;
+mod hooks;
+mod import;
+mod lock;
+mod manifest;
+mod migrate;
+mod npm;
+pub mod outdated;
+pub mod overview;
+mod ownership;
+mod parsed;
+mod paths;
+mod protocol;
+pub mod provenance;
+pub mod remote_check;
+mod remotes;
+mod restore;
+pub mod sbom;
+mod scan;
+mod semver;
+mod setup;
+mod signature;
+pub mod source;
+mod status_cache;
+pub mod sync;
+pub mod tags;
+pub mod template;
+pub mod transitive;
+pub mod verify;
+
+pub use annotations::{Annotation, AnnotationKind};
+pub use bisect::Bisection;
+pub use blame::Blame;
+pub use changelog::Changelog;
+pub use diffstat::MergeStat;
+pub use explain::{Explanation, Resolved};
+pub use gc::{GcOpts, GcReport};
+pub use graph::{GraphFormat, GraphIssue};
+pub use guard::StagedEdit;
+pub use import::Imported;
+pub use migrate::SourceFormat;
+pub use outdated::{Outdated, Stale};
+pub use overview::{Overview, StatusIter, StatusSort};
+pub use provenance::Provenance;
+pub use remote_check::RemoteStatus;
+pub use sbom::SbomFormat;
+pub use scan::Candidate;
+pub use setup::{SetupOpts, SetupStep};
+pub use source::DependencySource;
+pub use sync::OutOfSync;
+pub use tags::UpstreamTag;
+pub use template::Template;
+pub use transitive::Transitive;
+pub use verify::Verification;
 
 use git_filter_tree::FilterTree;
-use git_set_attr::SetAttr;
+use git_set_attr::{AttrFile, SetAttr, TextFormat, split_pattern};
 use git2::build::CheckoutBuilder;
-use git2::{Error, FetchOptions, MergeOptions, Repository};
+use git2::{Error, ErrorClass, ErrorCode, FetchOptions, MergeOptions, Repository};
 use std::{
+    collections::HashMap,
     fs,
-    io::{BufRead, BufReader, Write},
     path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
 };
 
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Suppress (or restore) progress messages printed by fetch and merge.
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+static COLOR: AtomicBool = AtomicBool::new(false);
+
+/// Enable (or disable) ANSI colors in human-readable output.
+pub fn set_color(color: bool) {
+    COLOR.store(color, Ordering::Relaxed);
+}
+
+const BOLD: &str = "1";
+const RED: &str = "31";
+const GREEN: &str = "32";
+const YELLOW: &str = "33";
+
+/// Wrap `text` in the ANSI SGR `code` if colors are enabled.
+fn paint(text: impl std::fmt::Display, code: &str) -> String {
+    if COLOR.load(Ordering::Relaxed) {
+        format!("\x1b[{code}m{text}\x1b[m")
+    } else {
+        text.to_string()
+    }
+}
+
 /// High-level options for [`Vendor::vendor_merge`], mirroring `git merge` flags.
 ///
 /// These control the commit/staging behavior of the merge. The low-level
@@ -31,17 +153,128 @@ pub struct VendorMergeOpts {
     /// Like `no_commit`, but also omits `MERGE_HEAD` so the eventual commit
     /// is an ordinary (non-merge) commit (`--squash`).
     pub squash: bool,
+    /// Create a merge commit even if the `squash` setting is on
+    /// (`--no-squash`). The setting only applies when a single dependency
+    /// is merged; merges of several always commit each one.
+    pub no_squash: bool,
     /// Override the default merge commit message (`-m`).
     pub message: Option<String>,
+    /// Refuse to merge upstream commits without a good signature from a key
+    /// in the configured keyring (`--verify-signatures`).  Dependencies can
+    /// also opt in individually with the `verify-signatures` attribute.
+    pub verify_signatures: bool,
+    /// Fail instead of deepening a shallow fetch when the previously
+    /// vendored commit, the merge base, is missing (`--no-deepen`).
+    pub no_deepen: bool,
+    /// Print the files each dependency's merge changes before applying it
+    /// (`--stat`).
+    pub stat: bool,
+    /// Asked with the files each dependency's merge changes before applying
+    /// it; the merge of a dependency is skipped unless it returns `true`
+    /// (`--confirm`).
+    pub confirm: Option<fn(&MergeStat) -> bool>,
+}
+
+/// Linguist attributes written alongside a tracked pattern.
+///
+/// GitHub excludes `linguist-vendored` paths from language statistics and
+/// collapses `-diff` paths in diffs.  Configured with `vendor.linguist`
+/// (`true`, `false`, or `no-diff`) or per invocation of
+/// [`Vendor::track_pattern`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Linguist {
+    /// Write no linguist attributes.
+    #[default]
+    Off,
+    /// Mark the pattern `linguist-vendored`.
+    Vendored,
+    /// Mark the pattern `linguist-vendored` and `-diff`.
+    VendoredNoDiff,
+}
+
+impl Linguist {
+    /// Read the `vendor.linguist` setting, defaulting to [`Linguist::Off`].
+    pub fn from_config(config: &git2::Config) -> Result<Self, Error> {
+        match config.get_string("vendor.linguist") {
+            Ok(value) if value.eq_ignore_ascii_case("no-diff") => Ok(Linguist::VendoredNoDiff),
+            Ok(_) => match config.get_bool("vendor.linguist")? {
+                true => Ok(Linguist::Vendored),
+                false => Ok(Linguist::Off),
+            },
+            Err(e) if e.code() == git2::ErrorCode::NotFound => Ok(Linguist::Off),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn attributes(self) -> &'static [&'static str] {
+        match self {
+            Linguist::Off => &[],
+            Linguist::Vendored => &["linguist-vendored"],
+            Linguist::VendoredNoDiff => &["linguist-vendored", "-diff"],
+        }
+    }
+}
+
+/// Presentation options for [`Vendor::vendor_status`].
+#[derive(Debug, Default)]
+pub struct StatusOpts {
+    /// Order dependencies by this key instead of their tracking order.
+    pub sort: Option<StatusSort>,
+    /// Group dependencies under a heading per upstream host.
+    pub group_by_host: bool,
+    /// Contact each upstream (without downloading objects) and report
+    /// unreachable hosts, moved repositories and deleted branches.
+    pub check_remotes: bool,
 }
 
 /// A vendored dependency parsed from `.gitattributes`.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct VendorDep {
     pub name: String,
     pub pattern: String,
     pub url: String,
     pub branch: Option<String>,
+    /// Pin the upstream to this tag or commit instead of following a branch
+    /// (`rev=`).
+    pub rev: Option<String>,
+    /// Require upstream commits to be signed (`verify-signatures`).
+    pub verify_signatures: bool,
+    /// Pin the exact filtered tree to be merged (`tree=<sha>`).
+    pub tree: Option<String>,
+    /// Place the upstream content under this directory (`prefix=`), so
+    /// that the patterns select host paths below it rather than the
+    /// upstream's own paths.
+    pub prefix: Option<String>,
+    /// Comma-separated CODEOWNERS owners for the pattern (`codeowners=`).
+    pub codeowners: Option<String>,
+    /// Drop upstream paths marked `export-ignore` by the upstream's own
+    /// `.gitattributes` (`honor-export-ignore`).
+    pub honor_export_ignore: bool,
+    /// Copy relevant upstream attributes into a managed block of our
+    /// `.gitattributes` on merge (`import-attributes`).
+    pub import_attributes: bool,
+    /// Fetch only this many commits of upstream history (`depth=`); `0`
+    /// means the full history.
+    pub depth: Option<u32>,
+    /// Whether upstream tags are fetched (`fetch-tags=`), overriding the
+    /// `fetch_tags` setting.
+    pub fetch_tags: Option<bool>,
+    /// Proxy URL from the `[vendor "<name>"]` git config section.
+    pub proxy: Option<String>,
+    /// Credential helper from the `[vendor "<name>"]` git config section.
+    pub credential_helper: Option<String>,
+    /// Further patterns vendored from the same upstream, from other lines
+    /// sharing the dependency's `name=`.
+    pub extra_patterns: Vec<String>,
+}
+
+impl VendorDep {
+    /// Every pattern vendored for this dependency, `pattern` first.
+    pub fn patterns(&self) -> Vec<&str> {
+        std::iter::once(self.pattern.as_str())
+            .chain(self.extra_patterns.iter().map(String::as_str))
+            .collect()
+    }
 }
 
 pub trait Vendor {
@@ -53,15 +286,34 @@ pub trait Vendor {
     ///
     /// If the pattern is already specified, the `url` and `branch` are updated if necessary.
     ///
+    /// When a `.gitvendor.toml` manifest exists at the repository root, the
+    /// dependency is recorded there instead, and `.gitattributes` only marks
+    /// the pattern `vendored`.
+    ///
+    /// When `maybe_branch` is `None`, the `vendor.defaultBranch` setting is
+    /// used, if any; otherwise the remote's default branch is tracked.
+    ///
     /// The `maybe_name` argument overrides the dependency name. When `None`, the name is
     /// derived from the URL as `owner/repo`. Local paths (non-URL remotes)
     /// require an explicit name.
+    ///
+    /// The `maybe_linguist` argument selects the linguist attributes written
+    /// for the pattern. When `None`, the `vendor.linguist` config is used.
+    ///
+    /// The pattern, URL, branch and name are validated before anything is
+    /// written. With `verify_remote`, the remote is also contacted to confirm
+    /// that it is reachable and has the branch.
+    ///
+    /// With `vendor.remotes` set, the dependency is also registered as the
+    /// `vendor/<name>` git remote.
     fn track_pattern(
         &self,
         pattern: &str,
         url: &str,
         maybe_branch: Option<&str>,
         maybe_name: Option<&str>,
+        maybe_linguist: Option<Linguist>,
+        verify_remote: bool,
     ) -> Result<(), Error>;
 
     /// Remove the pattern from the appropriate `.gitattributes` file using `git_set_attr`.
@@ -69,31 +321,379 @@ pub trait Vendor {
     /// If there is a `.gitattributes` file in the current directory, that file is used.
     /// Otherwise, the first found `.gitattributes` file when walking up the directory
     /// tree from the current directory to the repository root directory is used.
+    ///
+    /// When no pattern of the dependency is left, its `vendor/<name>` remote
+    /// is unregistered.
     fn untrack_pattern(&self, pattern: &str) -> Result<(), Error>;
 
-    /// Return the status of all vendored content, or any errors encountered along the way.
-    fn vendor_status(&self, maybe_pattern: Option<&str>) -> Result<(), Error>;
+    /// Change the upstream URL of the dependency tracking `pattern`, and of
+    /// its `vendor/<name>` remote with `vendor.remotes` set.
+    fn vendor_set_url(&self, pattern: &str, url: &str) -> Result<(), Error>;
+
+    /// Pin the dependency named `dep` (or tracking the pattern `dep`) to
+    /// the upstream tag or commit `version`, returning its updated
+    /// definition.
+    ///
+    /// A tag is looked up among the fetched tags, then upstream; an
+    /// abbreviated commit id must name a commit already fetched. The pin
+    /// replaces the dependency's branch, so later fetches and merges stay
+    /// at `version` until it is pinned again.
+    fn vendor_pin(&self, dep: &str, version: &str) -> Result<VendorDep, Error>;
+
+    /// Switch the dependency named `dep` (or tracking the pattern `dep`)
+    /// from its pinned tag or commit back to following `maybe_branch`,
+    /// returning its updated definition.
+    ///
+    /// When `maybe_branch` is `None`, the `vendor.defaultBranch` setting is
+    /// used, if any; otherwise the remote's default branch is tracked.
+    fn vendor_unpin(&self, dep: &str, maybe_branch: Option<&str>) -> Result<VendorDep, Error>;
+
+    /// Show the status of all vendored content, returning the dependencies
+    /// shown, or any errors encountered along the way.
+    ///
+    /// With [`StatusOpts::check_remotes`], an error is also returned if any
+    /// upstream remote is unreachable, has moved, or lost its branch.
+    fn vendor_status(
+        &self,
+        maybe_pattern: Option<&str>,
+        opts: &StatusOpts,
+    ) -> Result<Vec<Overview>, Error>;
 
     /// Fetch the latest content from all relevant vendor sources.
     ///
-    /// All vendor refs are stored under `/refs/vendor/`.
+    /// All vendor refs are stored under `/refs/vendor/`. With the `locked`
+    /// setting, each dependency is fetched at the commit its provenance
+    /// record pins instead.
     fn vendor_fetch(
         &self,
         maybe_pattern: Option<&str>,
         fetch_opts: Option<&mut FetchOptions<'_>>,
     ) -> Result<(), Error>;
 
+    /// Fetch like [`Vendor::vendor_fetch`], then the dependencies vendored by
+    /// the fetched upstreams, up to `max_depth` levels down, and return the
+    /// transitive dependencies resolved, once per parent vendoring them.
+    ///
+    /// A transitive dependency is declared by `vendored` lines in an
+    /// upstream's `.gitattributes`. It is only followed when its content
+    /// falls within its parent's patterns, so that the parent's merge brings
+    /// it into the host at the same nested prefix. Dependencies tracked by
+    /// the host, or already resolved through another parent, are not fetched
+    /// again.
+    fn vendor_fetch_recursive(
+        &self,
+        maybe_pattern: Option<&str>,
+        max_depth: usize,
+    ) -> Result<Vec<Transitive>, Error>;
+
     /// Merge the latest content from all relevant vendor sources.
     ///
     /// Behaves like `git merge`: updates the working tree and index, optionally
     /// creates a merge commit, and records `MERGE_HEAD`/`MERGE_MSG` when
-    /// appropriate.
+    /// appropriate. With the `locked` setting, a vendor ref at any other
+    /// commit than the one recorded in its provenance is refused.
     fn vendor_merge(
         &self,
         maybe_pattern: Option<&str>,
         opts: &VendorMergeOpts,
         merge_opts: Option<&MergeOptions>,
     ) -> Result<(), Error>;
+
+    /// List dependencies whose fetched upstream has commits not yet merged.
+    ///
+    /// Uses the refs under `refs/vendor/` as they are; fetch first to compare
+    /// against the current upstream state.
+    fn vendor_outdated(&self, maybe_pattern: Option<&str>) -> Result<Vec<Outdated>, Error>;
+
+    /// Like [`Vendor::vendor_outdated`], but against the current upstream
+    /// state.
+    ///
+    /// With the `forge_api` setting, dependencies hosted on a known forge
+    /// are compared through its API without fetching; the rest, and any the
+    /// API cannot answer for, are fetched first.
+    fn vendor_outdated_remote(&self, maybe_pattern: Option<&str>) -> Result<Vec<Outdated>, Error>;
+
+    /// List dependencies that have not been merged within `max_age` seconds,
+    /// including those that have never been merged.
+    fn vendor_stale(&self, maybe_pattern: Option<&str>, max_age: i64) -> Result<Vec<Stale>, Error>;
+
+    /// Summarize every dependency's freshness, local modifications and
+    /// fetched-but-unmerged upstream commits.
+    ///
+    /// Summaries are cached under `.git/vendor/` and only recomputed for
+    /// dependencies whose definition, vendor refs, provenance record or
+    /// content changed since.
+    fn vendor_overview(&self, maybe_pattern: Option<&str>) -> Result<Vec<Overview>, Error>;
+
+    /// Like [`Vendor::vendor_overview`], but yield each summary as soon as
+    /// it is computed, so that long lists can be shown progressively.
+    fn vendor_status_iter(&self, maybe_pattern: Option<&str>) -> Result<StatusIter<'_>, Error>;
+
+    /// Verify that vendored content corresponds to the upstream commit recorded
+    /// in its provenance record.
+    ///
+    /// The filtered tree of each dependency is recomputed from `HEAD` and from
+    /// the recorded upstream commit, and both are compared against the
+    /// recorded tree. Returns an error if any dependency fails verification;
+    /// with the `locked` setting, also if any is unrecorded, unverifiable
+    /// or defined differently than its record.
+    fn vendor_verify(&self, maybe_pattern: Option<&str>) -> Result<(), Error>;
+
+    /// Run `command` once for each matching dependency, from its directory
+    /// and with `VENDOR_NAME`, `VENDOR_PATH`, `VENDOR_URL`, `VENDOR_SHA` and
+    /// more describing it, like `git submodule foreach`.
+    ///
+    /// A single argument is run by the shell. With the `jobs` setting above
+    /// one, commands run concurrently. Returns an error if any command
+    /// fails, after which no further commands are started.
+    fn vendor_foreach(&self, maybe_pattern: Option<&str>, command: &[String]) -> Result<(), Error>;
+
+    /// Report findings of the given `kinds` about each matching dependency,
+    /// each attached to the line defining the dependency, e.g. for CI
+    /// annotations.
+    fn vendor_annotations(
+        &self,
+        maybe_pattern: Option<&str>,
+        kinds: &[AnnotationKind],
+    ) -> Result<Vec<Annotation>, Error>;
+
+    /// Delete the tracked content owned by matching dependencies from the
+    /// index and working tree, keeping their `.gitattributes` entries, and
+    /// return the removed paths.
+    ///
+    /// Provenance records are removed too, so a later merge re-vendors from
+    /// scratch. With `dry_run`, only the paths that would be removed are
+    /// returned.
+    fn vendor_clean(
+        &self,
+        maybe_pattern: Option<&str>,
+        dry_run: bool,
+    ) -> Result<Vec<String>, Error>;
+
+    /// Restore the content of matching dependencies in the index and working
+    /// tree to the trees recorded when they were last merged, discarding
+    /// local modifications, and return the paths that changed.
+    fn vendor_restore(&self, maybe_pattern: Option<&str>) -> Result<Vec<String>, Error>;
+
+    /// Make the vendored content of matching dependencies match exactly the
+    /// trees their provenance records pin, and return those that differed.
+    ///
+    /// Recorded trees missing from the repository, as after a fresh clone,
+    /// are derived again from the recorded upstream commits, which are
+    /// fetched if needed. Dependencies never merged are skipped. With
+    /// `check`, nothing is changed, for CI to fail on any difference.
+    fn vendor_sync(
+        &self,
+        maybe_pattern: Option<&str>,
+        check: bool,
+    ) -> Result<Vec<OutOfSync>, Error>;
+
+    /// Untrack dependencies that were merged but whose pattern no longer
+    /// matches anything in `HEAD`, and return them.
+    ///
+    /// Their `.gitattributes` lines, vendor refs and provenance records are
+    /// removed. With `dry_run`, the dependencies are only returned.
+    fn vendor_prune_attributes(&self, dry_run: bool) -> Result<Vec<Overview>, Error>;
+
+    /// Commit the `.gitattributes` file holding the vendor configuration on
+    /// top of `HEAD`, returning the new commit, or `HEAD` if it is unchanged.
+    /// Other staged changes are left staged and not committed.
+    ///
+    /// Merges check out whole trees, so configuration written by
+    /// [`Vendor::track_pattern`] must be committed before merging.
+    fn vendor_commit_attributes(&self, message: &str) -> Result<git2::Oid, Error>;
+
+    /// List staged changes to vendored paths that are not vendor updates.
+    fn vendor_staged_edits(&self) -> Result<Vec<StagedEdit>, Error>;
+
+    /// Install a `pre-commit` hook that rejects (`block`) or warns about
+    /// commits editing vendored paths, returning the hook's path.
+    ///
+    /// Commits are let through when `GIT_VENDOR_ALLOW_EDITS` is set.
+    fn vendor_install_hooks(&self, block: bool, force: bool) -> Result<PathBuf, Error>;
+
+    /// Generate or update the `git-vendor` section of the CODEOWNERS file,
+    /// mapping each vendored pattern to its owners, and return its path.
+    ///
+    /// Owners come from each dependency's `codeowners=` attribute, falling
+    /// back to the `vendor.codeowners` config value. The existing
+    /// CODEOWNERS file is used if there is one; otherwise
+    /// `.github/CODEOWNERS` is created. With `check`, the file is left
+    /// untouched and an error is returned if it is out of date.
+    fn vendor_codeowners(&self, check: bool) -> Result<PathBuf, Error>;
+
+    /// Generate or update the `git-vendor` section of the root `Cargo.toml`,
+    /// patching each vendored crate in `HEAD` to its directory, and return
+    /// the manifest's path.
+    ///
+    /// Crates are patched for the source `Cargo.lock` resolves them from,
+    /// or for their dependency's URL. The section is rewritten on every
+    /// run, so it drops crates no longer vendored. With `check`, the file
+    /// is left untouched and an error is returned if it is out of date.
+    fn vendor_cargo_patch(&self, check: bool) -> Result<PathBuf, Error>;
+
+    /// Render a software bill of materials for all relevant vendored dependencies.
+    ///
+    /// Each dependency is identified by the commit under `refs/vendor/<name>`
+    /// when it has been fetched.
+    fn vendor_sbom(&self, maybe_pattern: Option<&str>, format: SbomFormat)
+    -> Result<String, Error>;
+
+    /// Move the dependency definitions between `.gitattributes` and the
+    /// `.gitvendor.toml` manifest, returning how many were migrated.
+    ///
+    /// The `vendored` markings stay in `.gitattributes` either way. The
+    /// migrated definitions are read back and must match the originals;
+    /// otherwise both files are left as they were.
+    fn vendor_migrate(&self, to: SourceFormat) -> Result<usize, Error>;
+
+    /// Render the vendor relationships between the repository, its relevant
+    /// dependencies and, up to `max_depth` levels down, the dependencies
+    /// their fetched upstreams vendor in turn. Nothing is fetched.
+    ///
+    /// Each edge carries the vendoring pattern and the upstream commit
+    /// recorded by the vendoring repository's provenance, if merged.
+    fn vendor_graph(
+        &self,
+        maybe_pattern: Option<&str>,
+        max_depth: usize,
+        format: GraphFormat,
+    ) -> Result<String, Error>;
+
+    /// Check the graph of [`Vendor::vendor_graph`] for cycles and for
+    /// upstreams vendored at different commits along different paths.
+    fn vendor_check_graph(
+        &self,
+        maybe_pattern: Option<&str>,
+        max_depth: usize,
+    ) -> Result<Vec<GraphIssue>, Error>;
+
+    /// Fetch the full upstream history of matching dependencies whose vendor
+    /// refs were fetched shallow, returning their names.
+    ///
+    /// No refs are updated. Later fetches honor the configured depth again,
+    /// so unset it to keep the history complete.
+    fn vendor_unshallow(&self, maybe_pattern: Option<&str>) -> Result<Vec<String>, Error>;
+
+    /// List the upstream tags of the dependency named `dep` (or tracking
+    /// the pattern `dep`), marking the one at the vendored commit.
+    ///
+    /// Tags are read from the fetched vendor refs, or with `remote` from
+    /// the upstream itself. Tags naming a semantic version come last, in
+    /// version order.
+    fn vendor_tags(&self, dep: &str, remote: bool) -> Result<Vec<UpstreamTag>, Error>;
+
+    /// Summarize the upstream changes between the commit the dependency
+    /// named `dep` (or tracking the pattern `dep`) was last merged from and
+    /// its fetched vendor ref. With `notes`, include the sections added to
+    /// the upstream's changelog file in between.
+    fn vendor_changelog(&self, dep: &str, notes: bool) -> Result<Changelog, Error>;
+
+    /// Find the first upstream commit of the dependency named `dep` (or
+    /// tracking the pattern `dep`) that `command` fails at, searching from
+    /// `good` (default: the commit last merged) to `bad` (default: the
+    /// fetched vendor ref). Each candidate is vendored into `HEAD` in a
+    /// temporary worktree, where `command` runs through `sh`; exit status
+    /// 125 skips a candidate.
+    fn vendor_bisect(
+        &self,
+        dep: &str,
+        good: Option<&str>,
+        bad: Option<&str>,
+        command: &str,
+    ) -> Result<Bisection, Error>;
+
+    /// Show how the content vendored for the dependency named `dep` (or
+    /// tracking the pattern `dep`) in `HEAD` differs from what it would
+    /// vendor from the upstream branch, tag or commit `upstream`, by default
+    /// the fetched vendor ref, as a unified diff. Revisions not fetched yet
+    /// are fetched.
+    fn vendor_diff(&self, dep: &str, upstream: Option<&str>) -> Result<String, Error>;
+
+    /// Show the changes between two upstream revisions of the dependency
+    /// named `dep` (or tracking the pattern `dep`), filtered to the content
+    /// it vendors, as a unified diff. `from` defaults to the commit last
+    /// merged and `to` to the fetched vendor ref.
+    fn vendor_diff_revisions(
+        &self,
+        dep: &str,
+        from: Option<&str>,
+        to: Option<&str>,
+    ) -> Result<String, Error>;
+
+    /// Blame the vendored file at `path` (relative to the current directory)
+    /// against the upstream history of the dependency owning it, starting
+    /// from the commit last merged, so each line is attributed to the
+    /// upstream commit that last changed it. Lines changed in the host
+    /// repository since have no commit.
+    fn vendor_blame(&self, path: &Path) -> Result<Blame, Error>;
+
+    /// Delete vendor refs of dependencies that are no longer tracked and,
+    /// once older than `opts.expire`, refs kept for branches deleted
+    /// upstream; expire old reflog entries of the remaining vendor refs;
+    /// then run `git gc` to drop the objects only they referenced.
+    ///
+    /// With `opts.cache`, the shared caches under `vendor.cacheDir` are
+    /// compacted too. With `opts.dry_run`, only the refs and reflog entries
+    /// that would be expired are reported.
+    fn vendor_gc(&self, opts: &GcOpts) -> Result<GcReport, Error>;
+
+    /// Wire `git-vendor` into the repository: aliases, the pre-commit hook,
+    /// a merge driver and `-diff` for vendored paths, and recommended
+    /// `vendor.*` defaults, returning what each step did.
+    ///
+    /// Safe to run repeatedly; only what is missing is configured, and
+    /// values set differently are kept unless `opts.force`.
+    fn vendor_setup(&self, opts: &SetupOpts) -> Result<Vec<SetupStep>, Error>;
+
+    /// Resolve the configuration of the dependency named `dep` (or tracking
+    /// the pattern `dep`) as a fetch and merge would, reporting where each
+    /// value came from: its definition, its `[vendor "<name>"]` section,
+    /// `insteadOf` rules, the settings, or its provenance record.
+    fn vendor_explain(&self, dep: &str) -> Result<Explanation, Error>;
+
+    /// Find directories of the working tree that look vendored but are not
+    /// tracked, such as the subdirectories of `vendor/` or `third_party/`,
+    /// leftover clones and directories with their own license, guessing
+    /// each one's upstream URL where possible.
+    ///
+    /// Patterns are relative to the repository root; track them with
+    /// [`Vendor::track_pattern`] to adopt them.
+    fn vendor_scan(&self) -> Result<Vec<Candidate>, Error>;
+
+    /// Create a dependency for each upstream repository of the git
+    /// dependencies locked in the `Cargo.lock` at `path`, pinned to the
+    /// locked commit and tracking the directories of the locked crates.
+    ///
+    /// `path` may also be a `cargo vendor` directory, in which case the
+    /// `Cargo.lock` next to it is read and the copies it holds of imported
+    /// crates are reported. Each upstream is fetched to find where its
+    /// crates live; with `dry_run`, nothing is written.
+    fn vendor_import_cargo(&self, path: &Path, dry_run: bool) -> Result<Vec<Imported>, Error>;
+
+    /// Create a dependency for each source repository of the Go modules
+    /// required by the `go.mod` at `path`, or recorded in the `modules.txt`
+    /// at `path`, pinned to the required version and tracking the
+    /// directories of the modules.
+    ///
+    /// `path` may also be a Go vendor directory holding `modules.txt`; the
+    /// copies it holds of imported modules are reported. Each repository is
+    /// fetched to resolve versions and find the modules; with `dry_run`,
+    /// nothing is written.
+    fn vendor_import_gomod(&self, path: &Path, dry_run: bool) -> Result<Vec<Imported>, Error>;
+
+    /// Write the `modules.txt` describing the Go modules vendored in `HEAD`
+    /// to `path`: each tracked directory holding a `go.mod`, at the version
+    /// its dependency is pinned to or the pseudo-version of its merged
+    /// commit, with its packages. With `check`, the file is left untouched
+    /// and an error is returned if it is out of date.
+    fn vendor_export_gomod(&self, path: &Path, check: bool) -> Result<(), Error>;
+
+    /// Create a dependency for each git package locked in the
+    /// `package-lock.json` or `yarn.lock` at `path`, pinned to the locked
+    /// commit and placing the package's repository under
+    /// `third_party/node/<name>/`. With `dry_run`, nothing is written.
+    fn vendor_import_npm(&self, path: &Path, dry_run: bool) -> Result<Vec<Imported>, Error>;
 }
 
 impl Vendor for Repository {
@@ -103,73 +703,280 @@ impl Vendor for Repository {
         url: &str,
         maybe_branch: Option<&str>,
         maybe_name: Option<&str>,
+        maybe_linguist: Option<Linguist>,
+        verify_remote: bool,
     ) -> Result<(), Error> {
         require_non_bare(self)?;
 
-        let name = resolve_name(url, maybe_name)?;
-
-        let name_attr = format!("name={name}");
-        let url_attr = format!("url={url}");
+        let config = config::Config::resolve(self)?;
+        let maybe_branch = maybe_branch.or(config.default_branch.as_deref());
 
-        let mut attrs: Vec<&str> = vec!["vendored", &name_attr, &url_attr];
+        let pattern = paths::normalize_pattern(pattern);
+        let pattern = pattern.as_ref();
+        validate_pattern(pattern)?;
+        validate_url(url)?;
+        if let Some(branch) = maybe_branch
+            && !git2::Reference::is_valid_name(&format!("refs/heads/{branch}"))
+        {
+            return Err(Error::from_str(&format!("Invalid branch name '{branch}'")));
+        }
+        let name = resolve_name(url, maybe_name)?;
+        if name.contains(char::is_whitespace)
+            || !git2::Reference::is_valid_name(&vendor_ref_name(&config.ref_prefix, &name))
+        {
+            return Err(Error::from_str(&format!(
+                "Invalid vendor dependency name '{name}'"
+            )));
+        }
 
-        let branch_attr;
-        if let Some(branch) = maybe_branch {
-            branch_attr = format!("branch={branch}");
-            attrs.push(&branch_attr);
+        if verify_remote {
+            config.require_online("verify the remote")?;
+            match remote_check::check(url, maybe_branch, config.forge_api)? {
+                RemoteStatus::Reachable { .. } => {}
+                RemoteStatus::Moved { to, .. } => {
+                    return Err(Error::from_str(&format!(
+                        "{url} has moved to {to}; track the new URL instead"
+                    )));
+                }
+                RemoteStatus::BranchMissing { branch } => {
+                    return Err(Error::from_str(&format!(
+                        "Branch '{branch}' not found at {url}"
+                    )));
+                }
+                RemoteStatus::Unreachable { reason } => {
+                    return Err(Error::new(
+                        ErrorCode::GenericError,
+                        ErrorClass::Net,
+                        format!("Cannot reach {url}: {reason}"),
+                    ));
+                }
+            }
         }
 
-        self.set_attr(pattern, &attrs, None)
+        let path = find_gitattributes(self)?;
+        let dep = VendorDep {
+            name,
+            pattern: pattern.to_string(),
+            url: url.to_string(),
+            branch: maybe_branch.map(str::to_string),
+            ..Default::default()
+        };
+        let linguist = match maybe_linguist {
+            Some(linguist) => linguist,
+            None => Linguist::from_config(&self.config()?)?,
+        };
+        source::open(self, &path)?.upsert(&dep, linguist.attributes())?;
+        let mut written = vec!["vendored"];
+        written.extend_from_slice(linguist.attributes());
+        for (name, written, effective) in overridden_attributes(self, &path, pattern, &written)? {
+            tracing::warn!(
+                pattern,
+                "'{name}' is written as {written} for the pattern but evaluates to {effective}; \
+                 another attributes line overrides it"
+            );
+        }
+        if config.remotes {
+            remotes::sync(self, &dep, &config)?;
+        }
+        Ok(())
     }
 
     fn untrack_pattern(&self, pattern: &str) -> Result<(), Error> {
         require_non_bare(self)?;
 
+        let pattern = paths::normalize_pattern(pattern);
+        let pattern = pattern.as_ref();
+        let path = find_gitattributes(self)?;
+        let source = source::open(self, &path)?;
+        let deps = source.load()?;
+        source.remove(pattern)?;
+        remove_vendor_lines(self, &path, pattern)?;
+
+        let remaining = source.load()?;
+        for dep in filter_deps(&deps, Some(pattern)) {
+            if !remaining.iter().any(|d| d.name == dep.name) {
+                remotes::remove(self, &dep.name)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn vendor_set_url(&self, pattern: &str, url: &str) -> Result<(), Error> {
+        require_non_bare(self)?;
+        validate_url(url)?;
+
         let path = find_gitattributes(self)?;
-        if !path.exists() {
-            return Ok(());
+        let source = source::open(self, &path)?;
+        let deps = source.load()?;
+        let Some(&dep) = filter_deps(&deps, Some(pattern)).first() else {
+            return Err(error::new(
+                error::Code::NoDependency,
+                &format!("No vendored dependency tracks '{pattern}'"),
+            ));
+        };
+        let dep = VendorDep {
+            url: url.to_string(),
+            ..dep.clone()
+        };
+        source.upsert(&dep, &[])?;
+
+        let config = config::Config::resolve(self)?;
+        if config.remotes {
+            remotes::sync(self, &dep, &config)?;
         }
+        Ok(())
+    }
 
-        remove_vendor_lines(&path, pattern)
+    fn vendor_pin(&self, dep: &str, version: &str) -> Result<VendorDep, Error> {
+        require_non_bare(self)?;
+
+        let path = find_gitattributes(self)?;
+        let source = source::open(self, &path)?;
+        let deps = source.load()?;
+        let dep = find_dep(&deps, dep)?;
+        let config = config::Config::resolve(self)?;
+        let dep = VendorDep {
+            rev: Some(resolve_rev(self, dep, version, &config)?),
+            branch: None,
+            ..dep.clone()
+        };
+        source.upsert(&dep, &[])?;
+
+        if config.remotes {
+            remotes::sync(self, &dep, &config)?;
+        }
+        Ok(dep)
     }
 
-    fn vendor_status(&self, maybe_pattern: Option<&str>) -> Result<(), Error> {
+    fn vendor_unpin(&self, dep: &str, maybe_branch: Option<&str>) -> Result<VendorDep, Error> {
         require_non_bare(self)?;
 
         let path = find_gitattributes(self)?;
-        let deps = parse_vendor_deps(&path)?;
-        let deps = filter_deps(&deps, maybe_pattern);
+        let source = source::open(self, &path)?;
+        let deps = source.load()?;
+        let dep = find_dep(&deps, dep)?;
+        if dep.rev.is_none() && maybe_branch.is_none() {
+            return Err(Error::from_str(&format!("{} is not pinned", dep.name)));
+        }
+        let config = config::Config::resolve(self)?;
+        let maybe_branch = maybe_branch.or(config.default_branch.as_deref());
+        if let Some(branch) = maybe_branch
+            && !git2::Reference::is_valid_name(&format!("refs/heads/{branch}"))
+        {
+            return Err(Error::from_str(&format!("Invalid branch name '{branch}'")));
+        }
+        let dep = VendorDep {
+            rev: None,
+            branch: maybe_branch.map(str::to_string),
+            ..dep.clone()
+        };
+        source.upsert(&dep, &[])?;
+
+        if config.remotes {
+            remotes::sync(self, &dep, &config)?;
+        }
+        Ok(dep)
+    }
+
+    fn vendor_status(
+        &self,
+        maybe_pattern: Option<&str>,
+        opts: &StatusOpts,
+    ) -> Result<Vec<Overview>, Error> {
+        // Without sorting, grouping or remotes to check first, each
+        // dependency is shown as soon as its summary is computed.
+        if opts.sort.is_none() && !opts.group_by_host && !opts.check_remotes {
+            let mut shown = Vec::new();
+            for dep in self.vendor_status_iter(maybe_pattern)? {
+                let dep = dep?;
+                print_overview(&dep);
+                println!();
+                shown.push(dep);
+            }
+            if shown.is_empty() {
+                println!("No vendored dependencies tracked");
+            }
+            return Ok(shown);
+        }
+
+        let mut deps = self.vendor_overview(maybe_pattern)?;
 
         if deps.is_empty() {
             println!("No vendored dependencies tracked");
-            return Ok(());
+            return Ok(deps);
         }
 
-        for dep in deps {
-            println!("{} ({})", dep.name, dep.pattern);
-            println!("  URL: {}", dep.url);
-            match &dep.branch {
-                Some(b) => println!("  Branch: {b}"),
-                None => println!("  Branch: (default)"),
-            }
-
-            let ref_name = vendor_ref_name(&dep.name);
-            match self.find_reference(&ref_name) {
-                Ok(reference) => {
-                    if let Some(oid) = reference.target() {
-                        println!("  Ref: {ref_name} ({oid})");
-                    } else {
-                        println!("  Ref: {ref_name} (symbolic)");
+        if let Some(sort) = opts.sort {
+            overview::sort(&mut deps, sort);
+        }
+        let groups = if opts.group_by_host {
+            overview::group_by_host(deps)
+        } else {
+            vec![(String::new(), deps)]
+        };
+
+        let config = config::Config::resolve(self)?;
+        let mut remotes = Vec::new().into_iter();
+        if opts.check_remotes {
+            config.require_online("check remotes")?;
+            let checked: Vec<(&str, Option<&str>)> = groups
+                .iter()
+                .flat_map(|(_, deps)| deps)
+                .map(|dep| (dep.url.as_str(), dep.branch.as_deref()))
+                .collect();
+            remotes = remote_check::check_all(&checked, config.forge_api)?.into_iter();
+        }
+
+        let mut problems = 0;
+        let mut unreachable = false;
+        let mut shown = Vec::new();
+        for (host, deps) in groups {
+            if opts.group_by_host {
+                println!("{}", paint(format!("[{host}]"), YELLOW));
+            }
+            for dep in deps {
+                print_overview(&dep);
+                if let Some(status) = remotes.next() {
+                    if status.is_problem() {
+                        problems += 1;
                     }
+                    let remote = match status {
+                        RemoteStatus::Reachable { head } => {
+                            format!("{} ({head})", paint("reachable", GREEN))
+                        }
+                        RemoteStatus::Moved { to, .. } => {
+                            format!("{} to {to}", paint("moved", YELLOW))
+                        }
+                        RemoteStatus::BranchMissing { branch } => {
+                            paint(format!("branch {branch} not found"), RED)
+                        }
+                        RemoteStatus::Unreachable { reason } => {
+                            unreachable = true;
+                            format!("{}: {reason}", paint("unreachable", RED))
+                        }
+                    };
+                    println!("  Remote: {remote}");
                 }
-                Err(_) => {
-                    println!("  Ref: {ref_name} (not fetched)");
-                }
+                println!();
+                shown.push(dep);
             }
-            println!();
         }
 
-        Ok(())
+        if problems > 0 {
+            // Unreachable upstreams outweigh ones that merely need
+            // repointing.
+            let class = match unreachable {
+                true => ErrorClass::Net,
+                false => ErrorClass::Config,
+            };
+            return Err(Error::new(
+                ErrorCode::GenericError,
+                class,
+                format!("{problems} upstream remotes need attention"),
+            ));
+        }
+
+        Ok(shown)
     }
 
     fn vendor_fetch(
@@ -180,33 +987,74 @@ impl Vendor for Repository {
         require_non_bare(self)?;
 
         let path = find_gitattributes(self)?;
-        let deps = parse_vendor_deps(&path)?;
+        let deps = vendor_deps(self, &path)?;
         let deps = filter_deps(&deps, maybe_pattern);
 
         if deps.is_empty() {
-            return Err(Error::from_str("No vendored dependencies to fetch"));
+            return Err(error::new(
+                error::Code::NoDependency,
+                "No vendored dependencies to fetch",
+            ));
         }
 
-        for dep in deps {
-            let ref_target = vendor_ref_name(&dep.name);
+        let config = config::Config::resolve(self)?;
+        config.require_online("fetch")?;
+        if config.remotes {
+            for dep in &deps {
+                remotes::sync(self, dep, &config)?;
+            }
+        }
+        let locked: Vec<VendorDep>;
+        let deps = if config.locked {
+            let head_tree = self.head()?.peel_to_tree()?;
+            locked = deps
+                .iter()
+                .map(|dep| lock::pin(self, &head_tree, dep))
+                .collect::<Result<_, _>>()?;
+            locked.iter().collect()
+        } else {
+            deps
+        };
 
-            let branch_display = dep.branch.as_deref().unwrap_or("HEAD");
-            println!(
-                "Fetching {} from {} ({})",
-                dep.name, dep.url, branch_display
-            );
+        // Caller-supplied options cannot be shared between threads.
+        if config.jobs > 1 && fetch_opts.is_none() {
+            let updates = fetch_parallel(self, &deps, &config)?;
+            for (dep, update) in deps.into_iter().zip(updates) {
+                if let Some((old, new)) = update {
+                    hooks::run(self, hooks::Hook::PostFetch, dep, old, new)?;
+                }
+            }
+        } else {
+            for dep in deps {
+                if let Some((old, new)) = fetch_dep(self, dep, fetch_opts.as_deref_mut(), &config)?
+                {
+                    hooks::run(self, hooks::Hook::PostFetch, dep, old, new)?;
+                }
+            }
+        }
 
-            let mut remote = self.remote_anonymous(&dep.url)?;
-            let refspec = match &dep.branch {
-                Some(branch) => format!("+refs/heads/{branch}:{ref_target}"),
-                None => format!("+HEAD:{ref_target}"),
-            };
-            remote.fetch(&[&refspec], fetch_opts.as_deref_mut(), None)?;
+        dissociate_cache(self, &config)
+    }
 
-            println!("  Fetched to {ref_target}");
-        }
+    fn vendor_fetch_recursive(
+        &self,
+        maybe_pattern: Option<&str>,
+        max_depth: usize,
+    ) -> Result<Vec<Transitive>, Error> {
+        self.vendor_fetch(maybe_pattern, None)?;
 
-        Ok(())
+        let path = find_gitattributes(self)?;
+        let deps = vendor_deps(self, &path)?;
+        let config = config::Config::resolve(self)?;
+        let resolved = transitive::resolve(
+            self,
+            &deps,
+            &filter_deps(&deps, maybe_pattern),
+            max_depth,
+            |dep| fetch_dep(self, dep, None, &config).map(|_| ()),
+        )?;
+        dissociate_cache(self, &config)?;
+        Ok(resolved)
     }
 
     fn vendor_merge(
@@ -222,7 +1070,8 @@ impl Vendor for Repository {
             let head_tree = self.head()?.peel_to_tree()?;
             let index_oid = self.index()?.write_tree()?;
             if index_oid != head_tree.id() {
-                return Err(Error::from_str(
+                return Err(error::new(
+                    error::Code::DirtyIndex,
                     "Your index contains uncommitted changes. \
                      Please commit or stash them before merging.",
                 ));
@@ -230,11 +1079,14 @@ impl Vendor for Repository {
         }
 
         let path = find_gitattributes(self)?;
-        let deps = parse_vendor_deps(&path)?;
+        let deps = vendor_deps(self, &path)?;
         let deps = filter_deps(&deps, maybe_pattern);
 
         if deps.is_empty() {
-            return Err(Error::from_str("No vendored dependencies to merge"));
+            return Err(error::new(
+                error::Code::NoDependency,
+                "No vendored dependencies to merge",
+            ));
         }
 
         // Reject if there are staged but uncommitted changes — the merge
@@ -244,25 +1096,36 @@ impl Vendor for Repository {
             let head_tree = head_commit.tree()?;
             let diff = self.diff_tree_to_index(Some(&head_tree), None, None)?;
             if diff.deltas().count() > 0 {
-                return Err(Error::from_str(
+                return Err(error::new(
+                    error::Code::DirtyIndex,
                     "uncommitted changes in the index; \
                      please commit or stash before merging",
                 ));
             }
         }
 
-        let skip_commit = opts.no_commit || opts.squash;
-        if skip_commit && deps.len() > 1 {
+        if (opts.no_commit || opts.squash) && deps.len() > 1 {
             return Err(Error::from_str(
                 "--no-commit and --squash require a single dependency; \
                  specify a pattern to select one",
             ));
         }
 
+        let config = config::Config::resolve(self)?;
+        let squash = opts.squash || (config.squash && !opts.no_squash && deps.len() == 1);
+        let skip_commit = opts.no_commit || squash;
+        let head_commit = self.head()?.peel_to_commit()?;
+        let head_tree = head_commit.tree()?;
+
+        let mut sides = Vec::new();
         for dep in &deps {
-            let ref_name = vendor_ref_name(&dep.name);
+            let ref_name = vendor_ref_name(&config.ref_prefix, &dep.name);
 
-            println!("Merging {} ({})", dep.name, dep.pattern);
+            progress!("Merging {} ({})", dep.name, dep.pattern);
+            events::emit(events::Event::MergeStarted {
+                name: dep.name.clone(),
+                pattern: dep.pattern.clone(),
+            });
 
             let reference = self.find_reference(&ref_name).map_err(|_| {
                 Error::from_str(&format!(
@@ -273,125 +1136,1036 @@ impl Vendor for Repository {
             let vendor_oid = reference
                 .target()
                 .ok_or_else(|| Error::from_str("Invalid vendor reference"))?;
+            if config.locked {
+                let locked = lock::locked_commit(self, &head_tree, dep)?;
+                if vendor_oid != locked {
+                    return Err(Error::from_str(&format!(
+                        "{ref_name} is at {vendor_oid}, but {} is locked to {locked}; \
+                         fetch it with --locked first",
+                        dep.name
+                    )));
+                }
+            }
+            if opts.verify_signatures || dep.verify_signatures {
+                let fingerprint = match pinned_tag(self, dep, vendor_oid) {
+                    Some(tag) => signature::verify_tag(self, &tag)?,
+                    None => signature::verify_commit(self, vendor_oid)?,
+                };
+                progress!("  Good signature from {fingerprint}");
+            }
+
             let vendor_commit = self.find_commit(vendor_oid)?;
             let vendor_tree = vendor_commit.tree()?;
 
-            let filtered_tree = self.filter_by_patterns(&vendor_tree, &[&dep.pattern])?;
+            let filtered_tree = upstream_tree(self, dep, &vendor_tree)?.id();
 
-            let head = self.head()?;
-            let head_commit = head.peel_to_commit()?;
-            let head_tree = head_commit.tree()?;
+            if let Some(pin) = &dep.tree {
+                check_tree_pin(filtered_tree, pin)?;
+            }
 
-            // The ancestor must be the previous vendor content in HEAD, not
-            // the full HEAD tree.  Using head_tree as ancestor would cause
-            // the three-way merge to treat every non-vendor file as a
-            // deletion by "theirs" (the filtered vendor tree), wiping out
-            // the entire working tree.  Filtering head_tree by the full
-            // pattern produces the correct base: non-vendor files appear
-            // only in "ours" and are preserved as our-side additions.
-            let ancestor_tree = self.filter_by_patterns(&head_tree, &[&dep.pattern])?;
-
-            let mut index =
-                self.merge_trees(&ancestor_tree, &head_tree, &filtered_tree, merge_opts)?;
-
-            let default_message = format!("Merge vendored dependency: {}", dep.name);
-            let message = opts.message.as_deref().unwrap_or(&default_message);
-
-            if index.has_conflicts() {
-                // Write the conflicted index to the repository so the user can
-                // resolve in the working tree.
-                let mut repo_index = self.index()?;
-                repo_index.read_tree(&head_tree)?;
-                for conflict in index.conflicts()? {
-                    let conflict = conflict?;
-                    if let Some(entry) = &conflict.our {
-                        repo_index.add(entry)?;
-                    }
-                    if let Some(entry) = &conflict.their {
-                        repo_index.add(entry)?;
+            // The ancestor must be the previously vendored content, not the
+            // full HEAD tree.  Using head_tree as ancestor would cause the
+            // three-way merge to treat every non-vendor file as a deletion
+            // by "theirs" (the filtered vendor tree), wiping out the entire
+            // working tree.  The upstream content at the recorded commit is
+            // the true merge base, so local edits to vendored files merge
+            // with upstream's; without a record or a `Vendored-Commit:`
+            // trailer, the vendored content in HEAD stands in for it.
+            let previous = provenance::merged_commit(self, &head_tree, dep)?;
+            let base = match previous {
+                Some(previous) => merge_base_tree(self, dep, previous, &config, opts.no_deepen)?,
+                None => None,
+            };
+            tracing::debug!(
+                name = %dep.name,
+                previous = ?previous,
+                base = ?base,
+                "Resolved merge base tree"
+            );
+            hooks::run(self, hooks::Hook::PreMerge, dep, previous, vendor_oid)?;
+
+            sides.push(MergeSide {
+                dep,
+                ref_name,
+                vendor_oid,
+                vendor_tree,
+                filtered_tree,
+                previous,
+                base,
+            });
+        }
+
+        // The dependencies are merged together, see [`graft`], and then
+        // committed one by one in memory; the index, working tree and HEAD
+        // are only updated once all are, or before reporting a conflict.
+        let mut tree = head_tree.clone();
+        let mut parent = head_commit.clone();
+        let mut merged_sides = Vec::new();
+        let mut next = 0;
+        while next < sides.len() {
+            let trees = sides[next..]
+                .iter()
+                .map(|side| {
+                    let ancestor = match side.base {
+                        Some(base) => base,
+                        None => self.filter_by_patterns(&tree, &side.dep.patterns())?.id(),
+                    };
+                    Ok((
+                        self.find_tree(ancestor)?,
+                        self.find_tree(side.filtered_tree)?,
+                    ))
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+            let merged = graft::merge(self, &tree, &trees, merge_opts)?;
+
+            for (i, side) in sides[next..next + merged.len()].iter().enumerate() {
+                let dep = side.dep;
+                let default_message = format!("Merge vendored dependency: {}", dep.name);
+                let message = opts.message.as_deref().unwrap_or(&default_message);
+
+                let conflicts = merged.conflicts(i)?;
+                if !conflicts.is_empty() {
+                    check_out_merged(self, &head_commit, &parent, &tree, &merged_sides)?;
+
+                    // Write the conflicted index to the repository so the user
+                    // can resolve in the working tree.
+                    let mut repo_index = self.index()?;
+                    repo_index.read_tree(&tree)?;
+                    let mut paths = Vec::new();
+                    for conflict in conflicts {
+                        if let Some(entry) = &conflict.our {
+                            repo_index.add(entry)?;
+                        }
+                        if let Some(entry) = &conflict.their {
+                            repo_index.add(entry)?;
+                        }
+                        if let Some(entry) = conflict.our.or(conflict.their).or(conflict.ancestor) {
+                            paths.push(String::from_utf8_lossy(&entry.path).into_owned());
+                        }
                     }
+                    repo_index.write()?;
+                    paths.sort();
+                    events::emit(events::Event::MergeConflict {
+                        name: dep.name.clone(),
+                        paths,
+                    });
+
+                    let mut co = CheckoutBuilder::new();
+                    co.allow_conflicts(true).conflict_style_merge(true);
+                    self.checkout_index(Some(&mut repo_index), Some(&mut co))?;
+
+                    set_merge_msg(self, message)?;
+
+                    return Err(Error::new(
+                        ErrorCode::MergeConflict,
+                        ErrorClass::Merge,
+                        format!(
+                            "Conflicts detected while merging {}. \
+                             Resolve them and commit the result.",
+                            dep.name
+                        ),
+                    ));
                 }
-                repo_index.write()?;
 
-                let mut co = CheckoutBuilder::new();
-                co.allow_conflicts(true).conflict_style_merge(true);
-                self.checkout_index(Some(&mut repo_index), Some(&mut co))?;
-
-                set_merge_msg(self, message)?;
-
-                return Err(Error::from_str(&format!(
-                    "Conflicts detected while merging {}. \
-                     Resolve them and commit the result.",
-                    dep.name
-                )));
+                // Clean merge — write the tree along with its provenance
+                // record.
+                let merged_oid = merged.apply(self, &tree, i)?;
+                if opts.stat || opts.confirm.is_some() {
+                    let stat = diffstat::stat(
+                        self,
+                        &dep.name,
+                        side.vendor_oid,
+                        &tree,
+                        &self.find_tree(merged_oid)?,
+                    )?;
+                    print!("{}", stat.to_text());
+                    if let Some(confirm) = opts.confirm
+                        && !confirm(&stat)
+                    {
+                        progress!("  Skipped");
+                        continue;
+                    }
+                }
+                let provenance = Provenance {
+                    name: dep.name.clone(),
+                    url: dep.url.clone(),
+                    reference: upstream_ref(dep),
+                    commit: side.vendor_oid.to_string(),
+                    fetched_at: format_time(provenance::fetched_at(self, &side.ref_name)),
+                    merged_at: format_time(now()),
+                    merged_by: self.signature().ok().map(|signature| signature.to_string()),
+                    tree: side.filtered_tree.to_string(),
+                };
+                let mut merged_oid =
+                    provenance::record(self, &self.find_tree(merged_oid)?, &provenance)?;
+                if dep.import_attributes {
+                    merged_oid = import_attributes(
+                        self,
+                        &path,
+                        &self.find_tree(merged_oid)?,
+                        dep,
+                        &side.vendor_tree,
+                    )?;
+                }
+                tree = self.find_tree(merged_oid)?;
+
+                let commit = if skip_commit {
+                    set_merge_msg(self, message)?;
+                    progress!("  Merged (not committed)");
+                    None
+                } else {
+                    let signature = self.signature()?;
+                    let commit =
+                        self.commit(None, &signature, &signature, message, &tree, &[&parent])?;
+                    if config.notes {
+                        provenance::annotate(self, commit, &provenance)?;
+                    }
+                    parent = self.find_commit(commit)?;
+                    progress!("  Merged successfully");
+                    Some(commit)
+                };
+                merged_sides.push((side, commit));
             }
+            next += merged.len();
+        }
 
-            // Clean merge — write the tree, update index and working directory.
-            let merged_oid = index.write_tree_to(self)?;
-            let merged_tree = self.find_tree(merged_oid)?;
+        check_out_merged(self, &head_commit, &parent, &tree, &merged_sides)
+    }
 
-            let mut repo_index = self.index()?;
-            repo_index.read_tree(&merged_tree)?;
-            repo_index.write()?;
+    fn vendor_outdated(&self, maybe_pattern: Option<&str>) -> Result<Vec<Outdated>, Error> {
+        require_non_bare(self)?;
 
-            let mut co = CheckoutBuilder::new();
-            co.force();
-            self.checkout_tree(merged_tree.as_object(), Some(&mut co))?;
+        let path = find_gitattributes(self)?;
+        let deps = vendor_deps(self, &path)?;
+        let deps = filter_deps(&deps, maybe_pattern);
 
-            if skip_commit {
-                set_merge_msg(self, message)?;
-                println!("  Merged (not committed)");
-            } else {
-                let signature = self.signature()?;
-                self.commit(
-                    Some("HEAD"),
-                    &signature,
-                    &signature,
-                    message,
-                    &merged_tree,
-                    &[&head_commit],
-                )?;
-                println!("  Merged successfully");
+        let head_tree = self.head()?.peel_to_tree()?;
+        let mut outdated = Vec::new();
+        for dep in deps {
+            if let Some(entry) = outdated::check(self, &head_tree, dep)? {
+                outdated.push(entry);
             }
         }
 
-        Ok(())
+        Ok(outdated)
     }
-}
 
-// ---------------------------------------------------------------------------
-// Helpers
-// ---------------------------------------------------------------------------
+    fn vendor_outdated_remote(&self, maybe_pattern: Option<&str>) -> Result<Vec<Outdated>, Error> {
+        require_non_bare(self)?;
 
-// ---------------------------------------------------------------------------
-// Merge state helpers
-// ---------------------------------------------------------------------------
+        let config = config::Config::resolve(self)?;
+        if !config.forge_api {
+            self.vendor_fetch(maybe_pattern, None)?;
+            return self.vendor_outdated(maybe_pattern);
+        }
+        config.require_online("check upstreams")?;
 
-/// Write `MERGE_MSG` so that `git commit` picks up the message.
-fn set_merge_msg(repo: &Repository, msg: &str) -> Result<(), Error> {
-    let path = repo.path().join("MERGE_MSG");
-    fs::write(&path, format!("{msg}\n")).map_err(|e| Error::from_str(&e.to_string()))
-}
+        let path = find_gitattributes(self)?;
+        let deps = vendor_deps(self, &path)?;
+        let deps = filter_deps(&deps, maybe_pattern);
 
-// ---------------------------------------------------------------------------
-// Repository helpers
-// ---------------------------------------------------------------------------
+        let head_tree = self.head()?.peel_to_tree()?;
+        let mut outdated = Vec::new();
+        for dep in deps {
+            let answered = forge::Forge::from_url(&dep.url)
+                .map(|forge| outdated::check_forge(self, &head_tree, dep, &forge));
+            let entry = match answered {
+                Some(Ok(entry)) => entry,
+                _ => {
+                    self.vendor_fetch(Some(&dep.pattern), None)?;
+                    outdated::check(self, &head_tree, dep)?
+                }
+            };
+            outdated.extend(entry);
+        }
 
-fn require_non_bare(repo: &Repository) -> Result<(), Error> {
-    if repo.is_bare() {
-        Err(Error::from_str(
-            "This operation is not supported in a bare repository",
-        ))
-    } else {
-        Ok(())
+        Ok(outdated)
     }
-}
 
-/// Resolve the vendor dependency name.
-///
-/// If `maybe_name` is provided, it is used as-is. Otherwise the name is
-/// derived from the URL by extracting the last two path segments (typically
-/// `owner/repo`), stripping any `.git` suffix. Local (non-URL) remotes
+    fn vendor_stale(&self, maybe_pattern: Option<&str>, max_age: i64) -> Result<Vec<Stale>, Error> {
+        require_non_bare(self)?;
+
+        let path = find_gitattributes(self)?;
+        let deps = vendor_deps(self, &path)?;
+        let deps = filter_deps(&deps, maybe_pattern);
+
+        let head_tree = self.head()?.peel_to_tree()?;
+        let now = now();
+        let mut stale = Vec::new();
+        for dep in deps {
+            if let Some(entry) = outdated::check_stale(self, &head_tree, dep, now, max_age)? {
+                stale.push(entry);
+            }
+        }
+
+        Ok(stale)
+    }
+
+    fn vendor_overview(&self, maybe_pattern: Option<&str>) -> Result<Vec<Overview>, Error> {
+        self.vendor_status_iter(maybe_pattern)?.collect()
+    }
+
+    fn vendor_status_iter(&self, maybe_pattern: Option<&str>) -> Result<StatusIter<'_>, Error> {
+        require_non_bare(self)?;
+
+        let path = find_gitattributes(self)?;
+        let deps = vendor_deps(self, &path)?;
+        let cache = status_cache::StatusCache::open(self, &deps)?;
+        let selected = filter_deps(&deps, maybe_pattern)
+            .into_iter()
+            .cloned()
+            .collect();
+        StatusIter::new(self, selected, cache, now())
+    }
+
+    fn vendor_foreach(&self, maybe_pattern: Option<&str>, command: &[String]) -> Result<(), Error> {
+        require_non_bare(self)?;
+
+        let path = find_gitattributes(self)?;
+        let deps = vendor_deps(self, &path)?;
+        let deps = filter_deps(&deps, maybe_pattern);
+        if deps.is_empty() {
+            return Err(error::new(
+                error::Code::NoDependency,
+                "No vendored dependencies to run the command for",
+            ));
+        }
+
+        let config = config::Config::resolve(self)?;
+        foreach::run(self, &deps, command, config.jobs)
+    }
+
+    fn vendor_verify(&self, maybe_pattern: Option<&str>) -> Result<(), Error> {
+        require_non_bare(self)?;
+
+        let path = find_gitattributes(self)?;
+        let deps = vendor_deps(self, &path)?;
+        let deps = filter_deps(&deps, maybe_pattern);
+
+        if deps.is_empty() {
+            return Err(error::new(
+                error::Code::NoDependency,
+                "No vendored dependencies to verify",
+            ));
+        }
+
+        let config = config::Config::resolve(self)?;
+        let head_tree = self.head()?.peel_to_tree()?;
+        let mut failures = 0;
+
+        for dep in deps {
+            println!("{} ({})", paint(&dep.name, BOLD), dep.pattern);
+
+            if config.locked
+                && let Err(e) = lock::locked_commit(self, &head_tree, dep)
+            {
+                println!("  {}: {}", paint("UNLOCKED", RED), e.message());
+                failures += 1;
+                continue;
+            }
+
+            let verification = verify::verify(self, &head_tree, dep)?;
+            match &verification {
+                Verification::Verified { commit, tree } => {
+                    println!("  {}: tree {tree} from {commit}", paint("OK", GREEN));
+                }
+                Verification::Modified { recorded, actual } => {
+                    println!(
+                        "  {}: content is tree {actual}, but {recorded} was merged",
+                        paint("MODIFIED", RED)
+                    );
+                }
+                Verification::Mismatch {
+                    commit,
+                    recorded,
+                    upstream,
+                } => {
+                    println!(
+                        "  {}: recorded tree {recorded}, but upstream {commit} yields {upstream}",
+                        paint("MISMATCH", RED)
+                    );
+                }
+                Verification::Unverifiable { commit } => {
+                    println!(
+                        "  {}: upstream {commit} not available. Run fetch first.",
+                        paint("UNVERIFIED", YELLOW)
+                    );
+                }
+                Verification::Unrecorded => {
+                    println!("  {}: no provenance record", paint("UNVERIFIED", YELLOW));
+                }
+            }
+            // Locked, the record itself must be confirmed too.
+            if verification.is_failure()
+                || (config.locked && matches!(verification, Verification::Unverifiable { .. }))
+            {
+                failures += 1;
+            }
+        }
+
+        if failures > 0 {
+            return Err(Error::new(
+                ErrorCode::Modified,
+                ErrorClass::None,
+                format!("{failures} vendored dependencies failed verification"),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn vendor_annotations(
+        &self,
+        maybe_pattern: Option<&str>,
+        kinds: &[AnnotationKind],
+    ) -> Result<Vec<Annotation>, Error> {
+        require_non_bare(self)?;
+
+        let path = find_gitattributes(self)?;
+        let source = source::open(self, &path)?;
+        let deps = vendor_deps(self, &path)?;
+        let deps = filter_deps(&deps, maybe_pattern);
+        let head_tree = self.head()?.peel_to_tree()?;
+        annotations::annotate(
+            self,
+            source.as_ref(),
+            &head_tree,
+            &deps,
+            kinds,
+            &config::Config::resolve(self)?,
+        )
+    }
+
+    fn vendor_clean(
+        &self,
+        maybe_pattern: Option<&str>,
+        dry_run: bool,
+    ) -> Result<Vec<String>, Error> {
+        require_non_bare(self)?;
+
+        let path = find_gitattributes(self)?;
+        let deps = vendor_deps(self, &path)?;
+        let selected = filter_deps(&deps, maybe_pattern);
+        if selected.is_empty() {
+            return Err(error::new(
+                error::Code::NoDependency,
+                "No vendored dependencies to clean",
+            ));
+        }
+
+        clean::clean(self, &filter_deps(&deps, None), &selected, dry_run)
+    }
+
+    fn vendor_restore(&self, maybe_pattern: Option<&str>) -> Result<Vec<String>, Error> {
+        require_non_bare(self)?;
+
+        let path = find_gitattributes(self)?;
+        let deps = vendor_deps(self, &path)?;
+        let selected = filter_deps(&deps, maybe_pattern);
+        if selected.is_empty() {
+            return Err(error::new(
+                error::Code::NoDependency,
+                "No vendored dependencies to restore",
+            ));
+        }
+
+        let head_tree = self.head()?.peel_to_tree()?;
+        let all = filter_deps(&deps, None);
+        let mut restored = Vec::new();
+        for dep in selected {
+            restored.extend(restore::restore(self, &head_tree, &all, dep, false)?);
+        }
+        Ok(restored)
+    }
+
+    fn vendor_sync(
+        &self,
+        maybe_pattern: Option<&str>,
+        check: bool,
+    ) -> Result<Vec<OutOfSync>, Error> {
+        require_non_bare(self)?;
+
+        let path = find_gitattributes(self)?;
+        let deps = vendor_deps(self, &path)?;
+        let selected = filter_deps(&deps, maybe_pattern);
+        if selected.is_empty() {
+            return Err(error::new(
+                error::Code::NoDependency,
+                "No vendored dependencies to sync",
+            ));
+        }
+
+        let config = config::Config::resolve(self)?;
+        let head_tree = self.head()?.peel_to_tree()?;
+        let all = filter_deps(&deps, None);
+        let mut out_of_sync = Vec::new();
+        for dep in selected {
+            out_of_sync.extend(sync::sync(self, &head_tree, &all, dep, check, &config)?);
+        }
+        Ok(out_of_sync)
+    }
+
+    fn vendor_prune_attributes(&self, dry_run: bool) -> Result<Vec<Overview>, Error> {
+        let stale: Vec<Overview> = self
+            .vendor_overview(None)?
+            .into_iter()
+            .filter(|dep| dep.missing)
+            .collect();
+        if dry_run || stale.is_empty() {
+            return Ok(stale);
+        }
+
+        let mut index = self.index()?;
+        for dep in &stale {
+            self.untrack_pattern(&dep.pattern)?;
+            if let Ok(mut reference) = self.find_reference(&dep.reference) {
+                reference.delete()?;
+            }
+            let record = provenance::provenance_path(&dep.name);
+            if index.get_path(Path::new(&record), 0).is_some() {
+                clean::remove(self, &mut index, &[record])?;
+            }
+        }
+        index.write()?;
+
+        Ok(stale)
+    }
+
+    fn vendor_commit_attributes(&self, message: &str) -> Result<git2::Oid, Error> {
+        require_non_bare(self)?;
+
+        let path = find_gitattributes(self)?;
+        let workdir = self
+            .workdir()
+            .ok_or_else(|| Error::from_str("Repository has no working directory"))?;
+        let relative = paths::workdir_relative(self, &path).ok_or_else(|| {
+            Error::from_str(&format!("{} is outside the working tree", path.display()))
+        })?;
+
+        let mut paths = vec![relative.to_path_buf()];
+        if workdir.join(source::MANIFEST_FILE).exists() {
+            paths.push(PathBuf::from(source::MANIFEST_FILE));
+        }
+
+        // Stage the configuration, but commit it alone on top of HEAD's
+        // tree so that whatever else is staged stays uncommitted.
+        let head = self.head()?.peel_to_commit()?;
+        let mut index = self.index()?;
+        let mut commit_index = git2::Index::new()?;
+        commit_index.read_tree(&head.tree()?)?;
+        for path in &paths {
+            index.add_path(path)?;
+            let entry = index
+                .get_path(path, 0)
+                .ok_or_else(|| Error::from_str(&format!("{} was not staged", path.display())))?;
+            commit_index.add(&entry)?;
+        }
+        index.write()?;
+        let tree = self.find_tree(commit_index.write_tree_to(self)?)?;
+        if tree.id() == head.tree_id() {
+            return Ok(head.id());
+        }
+
+        let signature = self.signature()?;
+        self.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &[&head],
+        )
+    }
+
+    fn vendor_staged_edits(&self) -> Result<Vec<StagedEdit>, Error> {
+        require_non_bare(self)?;
+
+        let path = find_gitattributes(self)?;
+        let deps = vendor_deps(self, &path)?;
+        let deps = filter_deps(&deps, None);
+
+        guard::staged_edits(self, &deps)
+    }
+
+    fn vendor_install_hooks(&self, block: bool, force: bool) -> Result<PathBuf, Error> {
+        require_non_bare(self)?;
+
+        guard::install(self, block, force)
+    }
+
+    fn vendor_codeowners(&self, check: bool) -> Result<PathBuf, Error> {
+        require_non_bare(self)?;
+
+        let path = find_gitattributes(self)?;
+        let deps = vendor_deps(self, &path)?;
+        let deps = filter_deps(&deps, None);
+
+        let workdir = self
+            .workdir()
+            .ok_or_else(|| Error::from_str("Repository has no working directory"))?;
+        let target = codeowners::LOCATIONS
+            .iter()
+            .map(|location| workdir.join(location))
+            .find(|candidate| candidate.exists())
+            .unwrap_or_else(|| workdir.join(codeowners::LOCATIONS[0]));
+
+        let default_owners = self.config()?.get_string("vendor.codeowners").ok();
+        let section = codeowners::render_section(&deps, default_owners.as_deref());
+
+        let content = if target.exists() {
+            fs::read_to_string(&target).map_err(|e| {
+                Error::from_str(&format!("Failed to read {}: {e}", target.display()))
+            })?
+        } else {
+            String::new()
+        };
+        let updated = codeowners::update(&content, &section);
+
+        if updated != content {
+            if check {
+                return Err(Error::new(
+                    ErrorCode::Modified,
+                    ErrorClass::None,
+                    format!("{} is out of date", target.display()),
+                ));
+            }
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent).map_err(|e| Error::from_str(&e.to_string()))?;
+            }
+            fs::write(&target, updated).map_err(|e| {
+                Error::from_str(&format!("Failed to write {}: {e}", target.display()))
+            })?;
+        }
+
+        Ok(target)
+    }
+
+    fn vendor_cargo_patch(&self, check: bool) -> Result<PathBuf, Error> {
+        require_non_bare(self)?;
+
+        let path = find_gitattributes(self)?;
+        let deps = vendor_deps(self, &path)?;
+        let deps = filter_deps(&deps, None);
+
+        let workdir = self
+            .workdir()
+            .ok_or_else(|| Error::from_str("Repository has no working directory"))?;
+        let target = workdir.join("Cargo.toml");
+        let content = fs::read_to_string(&target)
+            .map_err(|e| Error::from_str(&format!("Failed to read {}: {e}", target.display())))?;
+        let lockfile = fs::read_to_string(workdir.join("Cargo.lock")).ok();
+
+        let patches = match self.head().ok().and_then(|head| head.peel_to_tree().ok()) {
+            Some(tree) => cargo_patch::crates(self, &tree, &deps, lockfile.as_deref())?,
+            None => Default::default(),
+        };
+        let section = cargo_patch::render_section(&patches);
+        let updated = cargo_patch::update(&content, &section);
+
+        if updated != content {
+            if check {
+                return Err(Error::new(
+                    ErrorCode::Modified,
+                    ErrorClass::None,
+                    format!("{} is out of date", target.display()),
+                ));
+            }
+            fs::write(&target, updated).map_err(|e| {
+                Error::from_str(&format!("Failed to write {}: {e}", target.display()))
+            })?;
+        }
+
+        Ok(target)
+    }
+
+    fn vendor_sbom(
+        &self,
+        maybe_pattern: Option<&str>,
+        format: SbomFormat,
+    ) -> Result<String, Error> {
+        require_non_bare(self)?;
+
+        let path = find_gitattributes(self)?;
+        let deps = vendor_deps(self, &path)?;
+        let deps = filter_deps(&deps, maybe_pattern);
+
+        sbom::render(self, &deps, format)
+    }
+
+    fn vendor_migrate(&self, to: SourceFormat) -> Result<usize, Error> {
+        require_non_bare(self)?;
+
+        let path = find_gitattributes(self)?;
+        migrate::migrate(self, &path, to)
+    }
+
+    fn vendor_graph(
+        &self,
+        maybe_pattern: Option<&str>,
+        max_depth: usize,
+        format: GraphFormat,
+    ) -> Result<String, Error> {
+        require_non_bare(self)?;
+
+        let path = find_gitattributes(self)?;
+        let deps = vendor_deps(self, &path)?;
+        let selected = filter_deps(&deps, maybe_pattern);
+        let transitive = transitive::resolve(self, &deps, &selected, max_depth, |_| Ok(()))?;
+
+        graph::render(self, &host_name(self), &selected, &transitive, format)
+    }
+
+    fn vendor_check_graph(
+        &self,
+        maybe_pattern: Option<&str>,
+        max_depth: usize,
+    ) -> Result<Vec<GraphIssue>, Error> {
+        require_non_bare(self)?;
+
+        let path = find_gitattributes(self)?;
+        let deps = vendor_deps(self, &path)?;
+        let selected = filter_deps(&deps, maybe_pattern);
+        let transitive = transitive::resolve(self, &deps, &selected, max_depth, |_| Ok(()))?;
+
+        graph::issues(self, &host_name(self), &selected, &transitive)
+    }
+
+    fn vendor_unshallow(&self, maybe_pattern: Option<&str>) -> Result<Vec<String>, Error> {
+        require_non_bare(self)?;
+
+        let path = find_gitattributes(self)?;
+        let deps = vendor_deps(self, &path)?;
+        let selected = filter_deps(&deps, maybe_pattern);
+        if selected.is_empty() {
+            return Err(error::new(
+                error::Code::NoDependency,
+                "No vendored dependencies to unshallow",
+            ));
+        }
+
+        let config = config::Config::resolve(self)?;
+        config.require_online("unshallow")?;
+        let mut unshallowed = Vec::new();
+        for dep in selected {
+            let ref_name = vendor_ref_name(&config.ref_prefix, &dep.name);
+            let tip = self.refname_to_id(&ref_name).map_err(|_| {
+                Error::from_str(&format!(
+                    "Vendor ref {ref_name} not found. Run fetch first."
+                ))
+            })?;
+            let cache = match &config.cache_dir {
+                Some(dir) => Some(cache::open(self, dir, &dep.url)?),
+                None => None,
+            };
+            let target = cache.as_ref().unwrap_or(self);
+            if !is_shallow(target, tip)? {
+                continue;
+            }
+
+            progress!("Fetching the full history of {}", dep.name);
+            fetch_history(target, dep, UNSHALLOW_DEPTH, &config)?;
+            unshallowed.push(dep.name.clone());
+        }
+        Ok(unshallowed)
+    }
+
+    fn vendor_tags(&self, dep: &str, remote: bool) -> Result<Vec<UpstreamTag>, Error> {
+        require_non_bare(self)?;
+
+        let path = find_gitattributes(self)?;
+        let deps = vendor_deps(self, &path)?;
+        let dep = find_dep(&deps, dep)?;
+        tags::list(self, dep, remote, &config::Config::resolve(self)?)
+    }
+
+    fn vendor_changelog(&self, dep: &str, notes: bool) -> Result<Changelog, Error> {
+        require_non_bare(self)?;
+
+        let path = find_gitattributes(self)?;
+        let deps = vendor_deps(self, &path)?;
+        let dep = find_dep(&deps, dep)?;
+        changelog::changelog(self, dep, notes, &config::Config::resolve(self)?)
+    }
+
+    fn vendor_bisect(
+        &self,
+        dep: &str,
+        good: Option<&str>,
+        bad: Option<&str>,
+        command: &str,
+    ) -> Result<Bisection, Error> {
+        require_non_bare(self)?;
+
+        let path = find_gitattributes(self)?;
+        let deps = vendor_deps(self, &path)?;
+        let all: Vec<&VendorDep> = deps.iter().collect();
+        let dep = find_dep(&deps, dep)?;
+        bisect::bisect(
+            self,
+            &all,
+            dep,
+            good,
+            bad,
+            command,
+            &config::Config::resolve(self)?,
+        )
+    }
+
+    fn vendor_diff(&self, dep: &str, upstream: Option<&str>) -> Result<String, Error> {
+        require_non_bare(self)?;
+
+        let config = config::Config::resolve(self)?;
+        let path = find_gitattributes(self)?;
+        let deps = vendor_deps(self, &path)?;
+        let dep = find_dep(&deps, dep)?;
+
+        let commit = match upstream {
+            Some(rev) => diff::resolve(self, dep, rev, &config)?,
+            None => diff::fetched(self, dep, &config)?,
+        };
+        let head_tree = self.head()?.peel_to_tree()?;
+        let vendored = self.filter_by_patterns(&head_tree, &dep.patterns())?;
+        let upstream = diff::vendored_tree(self, dep, commit)?;
+        diff::patch(self, &vendored, &upstream)
+    }
+
+    fn vendor_diff_revisions(
+        &self,
+        dep: &str,
+        from: Option<&str>,
+        to: Option<&str>,
+    ) -> Result<String, Error> {
+        require_non_bare(self)?;
+
+        let config = config::Config::resolve(self)?;
+        let path = find_gitattributes(self)?;
+        let deps = vendor_deps(self, &path)?;
+        let dep = find_dep(&deps, dep)?;
+
+        let from = match from {
+            Some(rev) => diff::resolve(self, dep, rev, &config)?,
+            None => diff::merged(self, dep)?,
+        };
+        let to = match to {
+            Some(rev) => diff::resolve(self, dep, rev, &config)?,
+            None => diff::fetched(self, dep, &config)?,
+        };
+        let old = diff::vendored_tree(self, dep, from)?;
+        let new = diff::vendored_tree(self, dep, to)?;
+        diff::patch(self, &old, &new)
+    }
+
+    fn vendor_blame(&self, path: &Path) -> Result<Blame, Error> {
+        require_non_bare(self)?;
+
+        let workdir = self
+            .workdir()
+            .ok_or_else(|| Error::from_str("Repository has no working directory"))?;
+        let canonical = |path: &Path| {
+            path.canonicalize()
+                .map_err(|e| Error::from_str(&format!("Failed to read {}: {e}", path.display())))
+        };
+        let absolute = canonical(path)?;
+        let relative = paths::strip_dir(&canonical(workdir)?, &absolute, paths::ignore_case(self))
+            .ok_or_else(|| {
+                Error::from_str(&format!("{} is outside the working tree", path.display()))
+            })?;
+        let relative = paths::to_slash(&relative);
+
+        let gitattributes = find_gitattributes(self)?;
+        let deps = vendor_deps(self, &gitattributes)?;
+        let deps: Vec<&VendorDep> = deps.iter().collect();
+        blame::blame(self, &deps, &relative, &config::Config::resolve(self)?)
+    }
+
+    fn vendor_gc(&self, opts: &GcOpts) -> Result<GcReport, Error> {
+        require_non_bare(self)?;
+
+        let config = config::Config::resolve(self)?;
+        let path = find_gitattributes(self)?;
+        let deps = vendor_deps(self, &path)?;
+        gc::gc(
+            self,
+            &deps,
+            &config.ref_prefix,
+            config.cache_dir.as_deref(),
+            opts,
+        )
+    }
+
+    fn vendor_setup(&self, opts: &SetupOpts) -> Result<Vec<SetupStep>, Error> {
+        require_non_bare(self)?;
+
+        let path = find_gitattributes(self)?;
+        let deps = vendor_deps(self, &path)?;
+        setup::setup(self, &filter_deps(&deps, None), opts)
+    }
+
+    fn vendor_explain(&self, dep: &str) -> Result<Explanation, Error> {
+        require_non_bare(self)?;
+
+        let path = find_gitattributes(self)?;
+        explain::explain(self, &path, dep)
+    }
+
+    fn vendor_scan(&self) -> Result<Vec<Candidate>, Error> {
+        require_non_bare(self)?;
+
+        let path = find_gitattributes(self)?;
+        let deps = vendor_deps(self, &path)?;
+        scan::scan(self, &filter_deps(&deps, None))
+    }
+
+    fn vendor_import_cargo(&self, path: &Path, dry_run: bool) -> Result<Vec<Imported>, Error> {
+        require_non_bare(self)?;
+
+        let gitattributes = find_gitattributes(self)?;
+        import::cargo(self, &gitattributes, path, dry_run)
+    }
+
+    fn vendor_import_gomod(&self, path: &Path, dry_run: bool) -> Result<Vec<Imported>, Error> {
+        require_non_bare(self)?;
+
+        let gitattributes = find_gitattributes(self)?;
+        gomod::import(self, &gitattributes, path, dry_run)
+    }
+
+    fn vendor_import_npm(&self, path: &Path, dry_run: bool) -> Result<Vec<Imported>, Error> {
+        require_non_bare(self)?;
+
+        let gitattributes = find_gitattributes(self)?;
+        npm::import(self, &gitattributes, path, dry_run)
+    }
+
+    fn vendor_export_gomod(&self, path: &Path, check: bool) -> Result<(), Error> {
+        require_non_bare(self)?;
+
+        let gitattributes = find_gitattributes(self)?;
+        let deps = vendor_deps(self, &gitattributes)?;
+        let deps = filter_deps(&deps, None);
+
+        let updated = match self.head().ok().and_then(|head| head.peel_to_tree().ok()) {
+            Some(tree) => gomod::modules_txt(self, &tree, &deps)?,
+            None => String::new(),
+        };
+        let content = fs::read_to_string(path).unwrap_or_default();
+
+        if updated != content {
+            if check {
+                return Err(Error::new(
+                    ErrorCode::Modified,
+                    ErrorClass::None,
+                    format!("{} is out of date", path.display()),
+                ));
+            }
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).map_err(|e| Error::from_str(&e.to_string()))?;
+            }
+            fs::write(path, updated).map_err(|e| {
+                Error::from_str(&format!("Failed to write {}: {e}", path.display()))
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Helpers
+// ---------------------------------------------------------------------------
+
+// ---------------------------------------------------------------------------
+// Merge state helpers
+// ---------------------------------------------------------------------------
+
+/// Write `MERGE_MSG` so that `git commit` picks up the message.
+fn set_merge_msg(repo: &Repository, msg: &str) -> Result<(), Error> {
+    let path = repo.path().join("MERGE_MSG");
+    fs::write(&path, format!("{msg}\n")).map_err(|e| Error::from_str(&e.to_string()))
+}
+
+/// One dependency's part in [`Vendor::vendor_merge`].
+struct MergeSide<'a, 'r> {
+    dep: &'a VendorDep,
+    ref_name: String,
+    vendor_oid: git2::Oid,
+    vendor_tree: git2::Tree<'r>,
+    filtered_tree: git2::Oid,
+    previous: Option<git2::Oid>,
+    base: Option<git2::Oid>,
+}
+
+/// Bring the index and working tree to `tree` and `HEAD` from `head` to
+/// `parent`, the result of merging `merged`, and report each dependency's
+/// merge as finished.
+fn check_out_merged(
+    repo: &Repository,
+    head: &git2::Commit<'_>,
+    parent: &git2::Commit<'_>,
+    tree: &git2::Tree<'_>,
+    merged: &[(&MergeSide<'_, '_>, Option<git2::Oid>)],
+) -> Result<(), Error> {
+    if tree.id() != head.tree_id() {
+        let mut repo_index = repo.index()?;
+        repo_index.read_tree(tree)?;
+        repo_index.write()?;
+
+        let mut co = CheckoutBuilder::new();
+        co.force();
+        repo.checkout_tree(tree.as_object(), Some(&mut co))?;
+    }
+    if parent.id() != head.id() {
+        repo.head()?.set_target(
+            parent.id(),
+            &format!("commit: {}", parent.summary().unwrap_or_default()),
+        )?;
+    }
+
+    for (side, commit) in merged {
+        events::emit(events::Event::MergeFinished {
+            name: side.dep.name.clone(),
+            upstream: side.vendor_oid.to_string(),
+            commit: commit.map(|commit| commit.to_string()),
+        });
+        hooks::run(
+            repo,
+            hooks::Hook::PostMerge,
+            side.dep,
+            side.previous,
+            side.vendor_oid,
+        )?;
+    }
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// Repository helpers
+// ---------------------------------------------------------------------------
+
+fn require_non_bare(repo: &Repository) -> Result<(), Error> {
+    if repo.is_bare() {
+        Err(Error::from_str(
+            "This operation is not supported in a bare repository",
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// The name of the repository's working directory, standing for the
+/// repository itself in the vendor graph.
+fn host_name(repo: &Repository) -> String {
+    repo.workdir().and_then(Path::file_name).map_or_else(
+        || ".".to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    )
+}
+
+/// Resolve the vendor dependency name.
+///
+/// If `maybe_name` is provided, it is used as-is. Otherwise the name is
+/// derived from the URL by extracting the last two path segments (typically
+/// `owner/repo`), stripping any `.git` suffix. Local (non-URL) remotes
 /// **must** supply an explicit name.
 fn resolve_name(url: &str, maybe_name: Option<&str>) -> Result<String, Error> {
     if let Some(name) = maybe_name {
@@ -409,6 +2183,55 @@ fn resolve_name(url: &str, maybe_name: Option<&str>) -> Result<String, Error> {
     })
 }
 
+/// Check that `pattern` can be written to `.gitattributes` and matched.
+fn validate_pattern(pattern: &str) -> Result<(), Error> {
+    if pattern.trim().is_empty() || pattern.contains(['\n', '\r']) {
+        return Err(Error::from_str(&format!(
+            "Invalid pattern '{pattern}': must be non-empty and fit on one line"
+        )));
+    }
+    if pattern.starts_with('!') {
+        return Err(Error::from_str(&format!(
+            "Invalid pattern '{pattern}': negative patterns are not allowed in .gitattributes"
+        )));
+    }
+    let glob = match pattern.strip_suffix('/') {
+        Some(dir) => format!("{dir}/**"),
+        None => pattern.to_string(),
+    };
+    globset::Glob::new(&glob)
+        .map(|_| ())
+        .map_err(|e| Error::from_str(&format!("Invalid pattern '{pattern}': {e}")))
+}
+
+/// Check that `url` is a well-formed remote URL or a local path.
+fn validate_url(url: &str) -> Result<(), Error> {
+    if url.is_empty() || url.contains(char::is_whitespace) {
+        return Err(Error::from_str(&format!(
+            "Invalid URL '{url}': must be non-empty and contain no whitespace"
+        )));
+    }
+    if let Some((scheme, rest)) = url.split_once("://") {
+        const SCHEMES: [&str; 6] = ["http", "https", "ssh", "git", "file", "git+ssh"];
+        if !SCHEMES.contains(&scheme) {
+            return Err(Error::from_str(&format!(
+                "Invalid URL '{url}': unsupported scheme '{scheme}'"
+            )));
+        }
+        if scheme != "file" && split_remote_url(url).is_none_or(|(host, _)| host.is_empty()) {
+            return Err(Error::from_str(&format!(
+                "Invalid URL '{url}': missing host or path"
+            )));
+        }
+        if scheme == "file" && rest.is_empty() {
+            return Err(Error::from_str(&format!(
+                "Invalid URL '{url}': missing path"
+            )));
+        }
+    }
+    Ok(())
+}
+
 /// Return `true` if `url` looks like a remote URL rather than a local path.
 ///
 /// Recognizes `scheme://...` and SCP-style `user@host:path`.
@@ -440,6 +2263,24 @@ fn is_remote_url(url: &str) -> bool {
 ///
 /// Returns `None` for local paths or URLs with fewer than two path segments.
 fn name_from_url(url: &str) -> Option<String> {
+    let (_, path) = split_remote_url(url)?;
+
+    // Take the last two segments.
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    if segments.len() < 2 {
+        return None;
+    }
+
+    let owner = segments[segments.len() - 2];
+    let repo = segments[segments.len() - 1];
+    Some(format!("{owner}/{repo}"))
+}
+
+/// Split a remote URL into its host and path, with any trailing `/` and
+/// `.git` suffix removed from the path.
+///
+/// Returns `None` for local paths.
+fn split_remote_url(url: &str) -> Option<(&str, &str)> {
     if !is_remote_url(url) {
         return None;
     }
@@ -448,31 +2289,680 @@ fn name_from_url(url: &str) -> Option<String> {
     let mut cleaned = url.trim_end_matches('/');
     cleaned = cleaned.strip_suffix(".git").unwrap_or(cleaned);
 
-    // Extract the path portion.
-    let path = if let Some(rest) = cleaned.split("://").nth(1) {
-        // scheme://[user@]host/path... → everything after the first `/`
-        rest.find('/').map(|i| &rest[i + 1..])
-    } else if let Some(at) = cleaned.find('@') {
-        // SCP-style: user@host:path
-        cleaned[at..].find(':').map(|i| &cleaned[at + i + 1..])
+    if let Some(rest) = cleaned.split("://").nth(1) {
+        // scheme://[user@]host[:port]/path...
+        let slash = rest.find('/')?;
+        let authority = &rest[..slash];
+        let host = authority.rsplit('@').next().unwrap_or(authority);
+        let host = host.split(':').next().unwrap_or(host);
+        Some((host, &rest[slash + 1..]))
     } else {
-        None
-    }?;
+        // SCP-style: user@host:path
+        let at = cleaned.find('@')?;
+        let colon = at + cleaned[at..].find(':')?;
+        Some((&cleaned[at + 1..colon], &cleaned[colon + 1..]))
+    }
+}
 
-    // Take the last two segments.
-    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
-    if segments.len() < 2 {
+/// Build the full ref path for a vendor dependency under `prefix`, e.g.
+/// `refs/vendor/owner/repo`.
+fn vendor_ref_name(prefix: &str, name: &str) -> String {
+    format!("{prefix}{name}")
+}
+
+/// Ensure `tree` matches the pinned (possibly abbreviated) tree id `pin`.
+fn check_tree_pin(tree: git2::Oid, pin: &str) -> Result<(), Error> {
+    let pin = pin.to_ascii_lowercase();
+    if pin.len() < 4 || !pin.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(Error::from_str(&format!("Invalid tree pin '{pin}'")));
+    }
+    if !tree.to_string().starts_with(&pin) {
+        return Err(Error::from_str(&format!(
+            "Filtered tree {tree} does not match pinned tree {pin}"
+        )));
+    }
+    Ok(())
+}
+
+/// A vendor ref's previous and new targets after a fetch.
+type FetchUpdate = (Option<git2::Oid>, git2::Oid);
+
+/// Fetch `dep` into its vendor ref, returning the ref's previous and new
+/// targets.
+///
+/// With `config.prune`, tag refs gone upstream are deleted, and when the
+/// tracked branch itself is gone, the vendor ref is moved to its
+/// `refs/vendor-gone/` ref and `None` is returned.
+fn fetch_dep(
+    repo: &Repository,
+    dep: &VendorDep,
+    fetch_opts: Option<&mut FetchOptions<'_>>,
+    config: &config::Config,
+) -> Result<Option<FetchUpdate>, Error> {
+    let branch_display = dep
+        .rev
+        .as_deref()
+        .or(dep.branch.as_deref())
+        .unwrap_or("HEAD");
+    progress!(
+        "Fetching {} from {} ({})",
+        dep.name,
+        dep.url,
+        branch_display
+    );
+    events::emit(events::Event::FetchStarted {
+        name: dep.name.clone(),
+        url: dep.url.clone(),
+        reference: branch_display.to_string(),
+    });
+
+    let old = repo
+        .refname_to_id(&vendor_ref_name(&config.ref_prefix, &dep.name))
+        .ok();
+    match fetch_dep_into_ref(repo, dep, fetch_opts, config) {
+        Ok((update, bytes)) => {
+            events::emit(events::Event::FetchFinished {
+                name: dep.name.clone(),
+                old: old.map(|oid| oid.to_string()),
+                new: update.map(|(_, new)| new.to_string()),
+                bytes,
+            });
+            Ok(update)
+        }
+        Err(e) => {
+            events::emit(events::Event::FetchFailed {
+                name: dep.name.clone(),
+                code: error::code(&e),
+                error: e.message().to_string(),
+            });
+            Err(e)
+        }
+    }
+}
+
+/// The work of [`fetch_dep`], also returning the bytes received when the
+/// transport reports them.
+fn fetch_dep_into_ref(
+    repo: &Repository,
+    dep: &VendorDep,
+    fetch_opts: Option<&mut FetchOptions<'_>>,
+    config: &config::Config,
+) -> Result<(Option<FetchUpdate>, Option<usize>), Error> {
+    let ref_target = vendor_ref_name(&config.ref_prefix, &dep.name);
+    let branch_display = dep
+        .rev
+        .as_deref()
+        .or(dep.branch.as_deref())
+        .unwrap_or("HEAD");
+
+    // Keep a reflog for the vendor ref so fetch times are recorded.
+    repo.reference_ensure_log(&ref_target)?;
+    let old = repo.refname_to_id(&ref_target).ok();
+
+    let tags_refspec = fetches_tags(dep, config)
+        .then(|| format!("+refs/tags/*:{}*", vendor_tags_ref_prefix(&dep.name)));
+    // With a shared cache, the upstream is fetched into the cache, whose
+    // objects the repository borrows, and the vendor refs are then updated
+    // from the cache.
+    let cache = match &config.cache_dir {
+        Some(dir) => Some(cache::open(repo, dir, &dep.url)?),
+        None => None,
+    };
+    let (target, mut refspecs) = match &cache {
+        Some(cache) => (
+            cache,
+            vec![format!("+{}:{}", upstream_ref(dep), cache::mirror_ref(dep))],
+        ),
+        None => (repo, vec![format!("+{}:{ref_target}", upstream_ref(dep))]),
+    };
+    if let Some(tags_refspec) = &tags_refspec {
+        refspecs.push(match cache {
+            Some(_) => "+refs/tags/*:refs/tags/*".to_string(),
+            None => tags_refspec.clone(),
+        });
+    }
+    tracing::debug!(name = %dep.name, ?refspecs, "Fetching refspecs");
+    let (deleted, bytes) = match fetch_opts {
+        // Caller-supplied options keep their own callbacks.
+        Some(fetch_opts) => {
+            configure_fetch(fetch_opts, dep, config);
+            let _haves = seed_haves(target, dep, &negotiation_tips(repo, target, dep, old)?)?;
+            let mut remote = target.remote_anonymous(&dep.url)?;
+            remote.fetch(&refspecs, Some(fetch_opts), None)?;
+            (
+                branch_deleted(&remote, dep)?,
+                Some(remote.stats().received_bytes()),
+            )
+        }
+        None if protocol::enabled(config) => {
+            let tips = negotiation_tips(repo, target, dep, old)?;
+            (
+                protocol::fetch(target, dep, &refspecs, &tips, config)?,
+                None,
+            )
+        }
+        None => {
+            let mut fetch_opts = FetchOptions::new();
+            if let Some(helper) = &dep.credential_helper {
+                fetch_opts.remote_callbacks(credential::callbacks(helper));
+            }
+            configure_fetch(&mut fetch_opts, dep, config);
+            let _haves = seed_haves(target, dep, &negotiation_tips(repo, target, dep, old)?)?;
+            let mut remote = target.remote_anonymous(&dep.url)?;
+            remote.fetch(&refspecs, Some(&mut fetch_opts), None)?;
+            (
+                branch_deleted(&remote, dep)?,
+                Some(remote.stats().received_bytes()),
+            )
+        }
+    };
+
+    if let Some(cache) = &cache {
+        let path = cache
+            .path()
+            .to_str()
+            .ok_or_else(|| Error::from_str("Cache directory is not valid UTF-8"))?;
+        let refspecs: Vec<String> =
+            std::iter::once(format!("+{}:{ref_target}", cache::mirror_ref(dep)))
+                .chain(tags_refspec)
+                .collect();
+        let mut fetch_opts = FetchOptions::new();
+        if config.prune {
+            fetch_opts.prune(git2::FetchPrune::On);
+        }
+        repo.remote_anonymous(path)?
+            .fetch(&refspecs, Some(&mut fetch_opts), None)?;
+    }
+
+    let gone_ref = vendor_gone_ref_name(&dep.name);
+    if config.prune && deleted {
+        if let Some(old) = old {
+            // Record when the branch went away so gc can expire the ref.
+            repo.reference_ensure_log(&gone_ref)?;
+            repo.reference(&gone_ref, old, true, "vendor: upstream branch deleted")?;
+        }
+        if let Ok(mut reference) = repo.find_reference(&ref_target) {
+            reference.delete()?;
+        }
+        progress!(
+            "  {}: branch {branch_display} no longer exists upstream; pruned {ref_target}",
+            paint("gone", YELLOW)
+        );
+        return Ok((None, bytes));
+    }
+    if let Ok(mut reference) = repo.find_reference(&gone_ref) {
+        reference.delete()?;
+    }
+
+    progress!("  Fetched to {ref_target}");
+
+    // A pinned annotated tag is fetched as the tag object; merges expect
+    // the commit. The tag is kept among the vendor tags so its signature
+    // can be verified.
+    let mut new = repo.refname_to_id(&ref_target)?;
+    if let Some(rev) = &dep.rev {
+        let commit = repo.find_object(new, None)?.peel_to_commit()?.id();
+        if commit != new {
+            let tag_ref = format!("{}{rev}", vendor_tags_ref_prefix(&dep.name));
+            repo.reference(&tag_ref, new, true, "vendor: keep pinned tag")?;
+            repo.reference(&ref_target, commit, true, "vendor: peel pinned tag")?;
+            new = commit;
+        }
+    }
+    Ok((Some((old, new)), bytes))
+}
+
+/// The commits of `dep` already in `target` to seed fetch negotiation
+/// with: the last fetch (`old`), the last fetch before its branch was
+/// deleted upstream, and the commit recorded by its provenance in `HEAD`,
+/// which survives losing the vendor refs.
+fn negotiation_tips(
+    repo: &Repository,
+    target: &Repository,
+    dep: &VendorDep,
+    old: Option<git2::Oid>,
+) -> Result<Vec<git2::Oid>, Error> {
+    let gone = repo.refname_to_id(&vendor_gone_ref_name(&dep.name)).ok();
+    let vendored = match repo.head().and_then(|head| head.peel_to_tree()) {
+        Ok(tree) => provenance::read(repo, &tree, &dep.name)?
+            .and_then(|provenance| git2::Oid::from_str(&provenance.commit).ok()),
+        Err(_) => None,
+    };
+
+    let mut tips: Vec<git2::Oid> = [old, gone, vendored]
+        .into_iter()
+        .flatten()
+        .filter(|&tip| target.find_commit(tip).is_ok())
+        .collect();
+    tips.sort();
+    tips.dedup();
+    Ok(tips)
+}
+
+/// Refs pointing at negotiation tips for the duration of a libgit2 fetch,
+/// deleted when dropped.
+struct SeededHaves<'r>(Vec<git2::Reference<'r>>);
+
+impl Drop for SeededHaves<'_> {
+    fn drop(&mut self) {
+        for reference in &mut self.0 {
+            if let Err(e) = reference.delete() {
+                tracing::warn!("Failed to delete {:?}: {}", reference.name(), e.message());
+            }
+        }
+    }
+}
+
+/// Point refs under `refs/vendor-haves/<name>/` in `target` at `tips`.
+/// libgit2 takes no negotiation tips, but offers the commits of every
+/// local ref as already present, so the tips are offered as well.
+fn seed_haves<'r>(
+    target: &'r Repository,
+    dep: &VendorDep,
+    tips: &[git2::Oid],
+) -> Result<SeededHaves<'r>, Error> {
+    let mut haves = SeededHaves(Vec::new());
+    for tip in tips {
+        let name = format!("refs/vendor-haves/{}/{tip}", dep.name);
+        haves
+            .0
+            .push(target.reference(&name, *tip, true, "vendor: seed negotiation")?);
+    }
+    Ok(haves)
+}
+
+/// Whether upstream tags are fetched for `dep`.
+fn fetches_tags(dep: &VendorDep, config: &config::Config) -> bool {
+    dep.fetch_tags.unwrap_or(config.fetch_tags)
+}
+
+/// Whether the tracked branch of `dep` was missing from what `remote`
+/// advertised on its last fetch.
+fn branch_deleted(remote: &git2::Remote<'_>, dep: &VendorDep) -> Result<bool, Error> {
+    Ok(dep.branch.is_some()
+        && !remote
+            .list()?
+            .iter()
+            .any(|head| head.name() == upstream_ref(dep)))
+}
+
+/// With the dissociate setting, copy the objects `repo` borrows from the
+/// shared cache into it so it no longer depends on the cache directory.
+fn dissociate_cache(repo: &Repository, config: &config::Config) -> Result<(), Error> {
+    if let (true, Some(dir)) = (config.dissociate, &config.cache_dir)
+        && cache::dissociate(repo, dir)?
+    {
+        progress!("Dissociated from the shared cache at {}", dir.display());
+    }
+    Ok(())
+}
+
+/// Apply the prune setting and `dep`'s depth and proxy settings to
+/// `fetch_opts`. The depth is left out for local upstreams, which libgit2
+/// cannot fetch shallowly.
+fn configure_fetch(fetch_opts: &mut FetchOptions<'_>, dep: &VendorDep, config: &config::Config) {
+    if config.prune {
+        fetch_opts.prune(git2::FetchPrune::On);
+    }
+    let local = dep.url.starts_with("file://")
+        || (!dep.url.contains("://") && Path::new(&dep.url).exists());
+    if let Some(depth) = dep.depth.or(config.depth).filter(|&depth| depth > 0)
+        && !local
+    {
+        fetch_opts.depth(depth.try_into().unwrap_or(i32::MAX));
+    }
+    if let Some(proxy) = &dep.proxy {
+        let mut proxy_opts = git2::ProxyOptions::new();
+        proxy_opts.url(proxy);
+        fetch_opts.proxy_options(proxy_opts);
+    }
+}
+
+/// Fetch `deps` on up to `config.jobs` threads, each with its own handle on
+/// the repository, returning the updates in the order of `deps`.
+fn fetch_parallel(
+    repo: &Repository,
+    deps: &[&VendorDep],
+    config: &config::Config,
+) -> Result<Vec<Option<FetchUpdate>>, Error> {
+    let path = repo.path();
+    let next = std::sync::atomic::AtomicUsize::new(0);
+
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..config.jobs.min(deps.len()))
+            .map(|_| {
+                scope.spawn(|| {
+                    let repo = Repository::open(path)?;
+                    let mut done = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(dep) = deps.get(i) else {
+                            return Ok::<_, Error>(done);
+                        };
+                        done.push((i, fetch_dep(&repo, dep, None, config)?));
+                    }
+                })
+            })
+            .collect();
+
+        let mut updates = vec![None; deps.len()];
+        for worker in workers {
+            for (i, update) in worker.join().expect("fetch worker panicked")? {
+                updates[i] = update;
+            }
+        }
+        Ok(updates)
+    })
+}
+
+/// The upstream ref fetched for `dep`: the commit or tag it is pinned to,
+/// its branch, or the remote's `HEAD`.
+fn upstream_ref(dep: &VendorDep) -> String {
+    match (&dep.rev, &dep.branch) {
+        (Some(rev), _) if is_commit_id(rev) => rev.clone(),
+        (Some(tag), _) => format!("refs/tags/{tag}"),
+        (None, Some(branch)) => format!("refs/heads/{branch}"),
+        (None, None) => "HEAD".to_string(),
+    }
+}
+
+/// The annotated tag `dep` is pinned to, if its `rev` names one that
+/// points to `commit`.
+fn pinned_tag<'r>(
+    repo: &'r Repository,
+    dep: &VendorDep,
+    commit: git2::Oid,
+) -> Option<git2::Tag<'r>> {
+    let rev = dep.rev.as_deref().filter(|rev| !is_commit_id(rev))?;
+    let tag = repo
+        .find_reference(&format!("{}{rev}", vendor_tags_ref_prefix(&dep.name)))
+        .ok()?
+        .peel_to_tag()
+        .ok()?;
+    let target = tag.target().ok()?.peel_to_commit().ok()?;
+    (target.id() == commit).then_some(tag)
+}
+
+/// Whether `rev` is a full commit id rather than a tag name.
+fn is_commit_id(rev: &str) -> bool {
+    rev.len() == 40 && rev.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Run git on the repository at `git_dir`, returning its standard output.
+fn run_git(git_dir: &Path, args: &[&str]) -> Result<String, Error> {
+    let output = std::process::Command::new("git")
+        .arg("--git-dir")
+        .arg(git_dir)
+        .args(args)
+        .output()
+        .map_err(|e| Error::from_str(&format!("Failed to run git {}: {e}", args[0])))?;
+    if !output.status.success() {
+        return Err(Error::from_str(&format!(
+            "git {} failed: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The deepest history [`deepen`] fetches looking for a merge base.
+const MAX_DEEPEN_DEPTH: u32 = 4096;
+
+/// The depth git and libgit2 treat as the full history.
+const UNSHALLOW_DEPTH: u32 = i32::MAX as u32;
+
+/// The upstream content vendored for `dep` at `commit`, the base of the
+/// next merge.
+///
+/// When a shallow fetch lacks `commit`, the history is deepened until it is
+/// found, unless `no_deepen`. `None` when a full fetch lacks it, e.g. after
+/// upstream rewrote its history.
+fn merge_base_tree(
+    repo: &Repository,
+    dep: &VendorDep,
+    commit: git2::Oid,
+    config: &config::Config,
+    no_deepen: bool,
+) -> Result<Option<git2::Oid>, Error> {
+    if repo.find_commit(commit).is_err() {
+        let Some(depth) = dep.depth.or(config.depth).filter(|&depth| depth > 0) else {
+            return Ok(None);
+        };
+        if no_deepen {
+            return Err(error::new(
+                error::Code::NoMergeBase,
+                &format!(
+                    "Previously vendored commit {commit} of {} is missing from the shallow \
+                     fetch; fetch with a larger depth or merge without --no-deepen",
+                    dep.name
+                ),
+            ));
+        }
+        if !deepen(repo, dep, commit, depth, config)? {
+            return Err(error::new(
+                error::Code::NoMergeBase,
+                &format!(
+                    "Previously vendored commit {commit} of {} is not within the last \
+                     {MAX_DEEPEN_DEPTH} upstream commits; fetch its full history",
+                    dep.name
+                ),
+            ));
+        }
+    }
+    let tree = repo.find_commit(commit)?.tree()?;
+    Ok(Some(upstream_tree(repo, dep, &tree)?.id()))
+}
+
+/// Fetch more of `dep`'s upstream history, doubling the depth from `depth`
+/// up to [`MAX_DEEPEN_DEPTH`], until `commit` is present, and return whether
+/// it was found.  No refs are updated.
+fn deepen(
+    repo: &Repository,
+    dep: &VendorDep,
+    commit: git2::Oid,
+    mut depth: u32,
+    config: &config::Config,
+) -> Result<bool, Error> {
+    config.require_online("deepen")?;
+    let cache = match &config.cache_dir {
+        Some(dir) => Some(cache::open(repo, dir, &dep.url)?),
+        None => None,
+    };
+    let target = cache.as_ref().unwrap_or(repo);
+
+    while depth < MAX_DEEPEN_DEPTH {
+        depth = depth.saturating_mul(2).min(MAX_DEEPEN_DEPTH);
+        progress!(
+            "  Deepening {} to {depth} commits to find the merge base",
+            dep.name
+        );
+        fetch_history(target, dep, depth, config)?;
+        if repo.find_commit(commit).is_ok() {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Fetch `depth` commits of `dep`'s upstream history into `target` without
+/// updating any refs.
+fn fetch_history(
+    target: &Repository,
+    dep: &VendorDep,
+    depth: u32,
+    config: &config::Config,
+) -> Result<(), Error> {
+    let deeper = VendorDep {
+        depth: Some(depth),
+        ..dep.clone()
+    };
+    if protocol::enabled(config) {
+        return protocol::fetch_objects(target, &deeper, &upstream_ref(dep), config);
+    }
+    let mut fetch_opts = FetchOptions::new();
+    if let Some(helper) = &dep.credential_helper {
+        fetch_opts.remote_callbacks(credential::callbacks(helper));
+    }
+    configure_fetch(&mut fetch_opts, &deeper, config);
+    target
+        .remote_anonymous(&dep.url)?
+        .fetch(&[upstream_ref(dep)], Some(&mut fetch_opts), None)
+}
+
+/// Fetch the upstream commit `commit` of `dep`, e.g. one recorded by its
+/// provenance, without updating any refs.
+fn fetch_commit(
+    repo: &Repository,
+    dep: &VendorDep,
+    commit: git2::Oid,
+    config: &config::Config,
+) -> Result<(), Error> {
+    config.require_online("fetch recorded commits")?;
+    let cache = match &config.cache_dir {
+        Some(dir) => Some(cache::open(repo, dir, &dep.url)?),
+        None => None,
+    };
+    let pinned = VendorDep {
+        rev: Some(commit.to_string()),
+        ..dep.clone()
+    };
+    progress!("Fetching {} at {commit}", dep.name);
+    fetch_history(cache.as_ref().unwrap_or(repo), &pinned, 1, config)
+}
+
+/// Whether the history of `tip` in `repo` is cut short by a shallow fetch.
+fn is_shallow(repo: &Repository, tip: git2::Oid) -> Result<bool, Error> {
+    let boundaries = match fs::read_to_string(repo.path().join("shallow")) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(Error::from_str(&format!("Failed to read shallow: {e}"))),
+    };
+    for boundary in boundaries
+        .lines()
+        .filter_map(|line| git2::Oid::from_str(line).ok())
+    {
+        if boundary == tip || repo.graph_descendant_of(tip, boundary)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Seconds since the Unix epoch.
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Parse an RFC 3339 UTC timestamp as produced by [`format_time`].
+fn parse_time(s: &str) -> Option<i64> {
+    let (date, time) = s.strip_suffix('Z')?.split_once('T')?;
+    let mut date = date.splitn(3, '-').map(|p| p.parse::<i64>().ok());
+    let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+    let mut time = time.splitn(3, ':').map(|p| p.parse::<i64>().ok());
+    let (hour, minute, second) = (time.next()??, time.next()??, time.next()??);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
         return None;
     }
 
-    let owner = segments[segments.len() - 2];
-    let repo = segments[segments.len() - 1];
-    Some(format!("{owner}/{repo}"))
+    // Days-from-civil (Howard Hinnant's algorithm).
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+
+    Some(days * 86_400 + hour * 3600 + minute * 60 + second)
+}
+
+/// Print the summary of `dep` shown by [`Vendor::vendor_status`].
+fn print_overview(dep: &Overview) {
+    println!("{} ({})", paint(&dep.name, BOLD), dep.pattern);
+    println!("  URL: {}", dep.url);
+    match &dep.branch {
+        Some(b) if dep.branch_gone => println!(
+            "  Branch: {b} ({}; repoint it with track)",
+            paint("deleted upstream", RED)
+        ),
+        Some(b) => println!("  Branch: {b}"),
+        None if dep.rev.is_some() => {}
+        None => println!("  Branch: (default)"),
+    }
+    if let Some(rev) = &dep.rev {
+        println!("  Pinned: {rev}");
+    }
+    match &dep.latest {
+        Some(oid) => println!("  Ref: {} ({oid})", dep.reference),
+        None => println!("  Ref: {} ({})", dep.reference, paint("not fetched", RED)),
+    }
+    match (dep.age_days, &dep.merged_by) {
+        (Some(days), Some(by)) => {
+            println!("  Last updated: {} by {by}", format_age(days * 86_400))
+        }
+        (Some(days), None) => {
+            println!("  Last updated: {}", format_age(days * 86_400))
+        }
+        (None, _) => match &dep.vendored {
+            Some(oid) => println!("  Last updated: {oid} (from commit trailers)"),
+            None => println!("  Last updated: {}", paint("never", RED)),
+        },
+    }
+    if dep.missing {
+        println!(
+            "  Content: {} (run prune --attrs to untrack)",
+            paint("no files match the pattern", RED)
+        );
+    }
+}
+
+/// Describe an age in seconds for humans, e.g. `today` or `12 days ago`.
+fn format_age(seconds: i64) -> String {
+    match seconds / 86_400 {
+        i64::MIN..=0 => "today".to_string(),
+        1 => "1 day ago".to_string(),
+        days => format!("{days} days ago"),
+    }
+}
+
+/// Format seconds since the Unix epoch as an RFC 3339 UTC timestamp.
+fn format_time(seconds: i64) -> String {
+    let days = seconds.div_euclid(86_400);
+    let secs = seconds.rem_euclid(86_400);
+
+    // Civil-from-days (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
 }
 
-/// Build the full ref path for a vendor dependency, e.g. `refs/vendor/owner/repo`.
-fn vendor_ref_name(name: &str) -> String {
-    format!("refs/vendor/{name}")
+/// The ref prefix under which upstream tags for a dependency are stored,
+/// e.g. `refs/vendor-tags/owner/repo/`.
+fn vendor_tags_ref_prefix(name: &str) -> String {
+    format!("refs/vendor-tags/{name}/")
+}
+
+/// The ref keeping the last fetched upstream of a dependency whose tracked
+/// branch was deleted upstream, e.g. `refs/vendor-gone/owner/repo`.
+fn vendor_gone_ref_name(name: &str) -> String {
+    format!("refs/vendor-gone/{name}")
 }
 
 /// Find the appropriate `.gitattributes` file by walking from the current
@@ -488,8 +2978,9 @@ fn find_gitattributes(repo: &Repository) -> Result<PathBuf, Error> {
     let current_dir = std::env::current_dir()
         .map_err(|e| Error::from_str(&format!("Failed to get current directory: {e}")))?;
 
+    let ignore_case = paths::ignore_case(repo);
     let mut dir = current_dir.as_path();
-    while dir.starts_with(workdir) {
+    while paths::strip_dir(workdir, dir, ignore_case).is_some() {
         let candidate = dir.join(".gitattributes");
         if candidate.exists() {
             return Ok(candidate);
@@ -503,42 +2994,128 @@ fn find_gitattributes(repo: &Repository) -> Result<PathBuf, Error> {
     Ok(current_dir.join(".gitattributes"))
 }
 
+/// Load the vendor dependencies from the repository's dependency source
+/// (`.gitattributes` at `path`, or the manifest) and merge each one's
+/// `[vendor "<name>"]` git config section over it.
+fn vendor_deps(repo: &Repository, path: &Path) -> Result<Vec<VendorDep>, Error> {
+    let config = repo.config()?;
+    let mut deps = source::open(repo, path)?.load()?;
+    for dep in &mut deps {
+        config::apply_dep_overrides(&config, dep)?;
+    }
+    Ok(deps)
+}
+
 /// Parse vendor dependencies from a `.gitattributes` file.
 ///
 /// A line is recognized as a vendor dependency when it carries at least
 /// `name=` and `url=`. The `branch=` attribute is
 /// optional — when absent, the dependency tracks the remote's default branch.
+///
+/// Further `vendored` lines with the same `name=` add their patterns to that
+/// dependency; they may omit the other attributes.
+///
+/// Attribute macros (`[attr]vendor-dep vendored ...`) defined in the file,
+/// or in the `.gitattributes` at the root of the repository, are expanded.
+///
+/// The result is cached for the rest of the process; see [`parsed`].
 fn parse_vendor_deps(path: &Path) -> Result<Vec<VendorDep>, Error> {
     if !path.exists() {
         return Ok(Vec::new());
     }
 
-    let file = fs::File::open(path)
-        .map_err(|e| Error::from_str(&format!("Failed to open {}: {e}", path.display())))?;
+    let root = root_gitattributes(path);
+    parsed::get_or_parse(path, root.as_deref(), || {
+        let content = fs::read_to_string(path)
+            .map_err(|e| Error::from_str(&format!("Failed to read {}: {e}", path.display())))?;
+        parse_vendor_lines(&content, &root_macros(root.as_deref()))
+    })
+}
 
-    let mut deps = Vec::new();
+/// The `.gitattributes` at the root of the repository containing the
+/// `.gitattributes` file at `path`, if that is another file.
+fn root_gitattributes(path: &Path) -> Option<PathBuf> {
+    path.parent()
+        .and_then(|dir| dir.ancestors().find(|dir| dir.join(".git").exists()))
+        .map(|root| root.join(".gitattributes"))
+        .filter(|root| root != path)
+}
+
+/// The attribute macros defined in the root `.gitattributes` file `root`.
+fn root_macros(root: Option<&Path>) -> Macros {
+    let mut macros = Macros::new();
+    if let Some(root) = root
+        && let Ok(content) = fs::read_to_string(root)
+    {
+        parse_macros(&content, &mut macros);
+    }
+    macros
+}
 
-    for line in BufReader::new(file).lines() {
-        let line =
-            line.map_err(|e| Error::from_str(&format!("Failed to read .gitattributes: {e}")))?;
-        let trimmed = line.trim();
+/// Attribute macros, by name, with the attributes they stand for already
+/// expanded.
+type Macros = HashMap<String, Vec<String>>;
 
-        if trimmed.is_empty() || trimmed.starts_with('#') {
+/// Add the attribute macros (`[attr]name attr...`) defined in `content` to
+/// `macros`.
+fn parse_macros(content: &str, macros: &mut Macros) {
+    for line in content.lines() {
+        let Some(definition) = line.trim().strip_prefix("[attr]") else {
+            continue;
+        };
+        let mut parts = definition.split_whitespace();
+        let Some(name) = parts.next() else {
             continue;
+        };
+        let attrs = expand_macros(parts, macros);
+        macros.insert(name.to_string(), attrs);
+    }
+}
+
+/// `attrs` with the attributes of each macro in `macros` inserted after it,
+/// as Git expands them.
+fn expand_macros<'a>(attrs: impl Iterator<Item = &'a str>, macros: &Macros) -> Vec<String> {
+    let mut expanded = Vec::new();
+    for attr in attrs {
+        expanded.push(attr.to_string());
+        if let Some(definition) = macros.get(attr) {
+            expanded.extend(definition.iter().cloned());
         }
+    }
+    expanded
+}
+
+/// Parse vendor dependencies from the content of a `.gitattributes` file,
+/// as described for [`parse_vendor_deps`], with `macros` defined before it.
+fn parse_vendor_lines(content: &str, macros: &Macros) -> Result<Vec<VendorDep>, Error> {
+    let mut deps = Vec::new();
+    let mut macros = macros.clone();
+    parse_macros(content, &mut macros);
 
-        let mut parts = trimmed.split_whitespace();
-        let pattern = match parts.next() {
-            Some(p) => p,
-            None => continue,
+    for line in content.lines() {
+        let Some((pattern, rest)) = split_pattern(line) else {
+            continue;
         };
+        if pattern.starts_with("[attr]") {
+            continue;
+        }
+        let attrs = expand_macros(rest.split_whitespace(), &macros);
 
         let mut name = None;
         let mut url = None;
         let mut branch = None;
+        let mut rev = None;
+        let mut verify_signatures = false;
+        let mut tree = None;
+        let mut prefix = None;
+        let mut codeowners = None;
+        let mut honor_export_ignore = false;
+        let mut import_attributes = false;
+        let mut depth = None;
+        let mut fetch_tags = None;
         let mut is_vendored = false;
 
-        for attr in parts {
+        for attr in attrs.iter().map(String::as_str) {
             if attr == "vendored" {
                 is_vendored = true;
             } else if let Some(v) = attr.strip_prefix("name=") {
@@ -547,6 +3124,24 @@ fn parse_vendor_deps(path: &Path) -> Result<Vec<VendorDep>, Error> {
                 url = Some(v.to_string());
             } else if let Some(v) = attr.strip_prefix("branch=") {
                 branch = Some(v.to_string());
+            } else if let Some(v) = attr.strip_prefix("rev=") {
+                rev = Some(v.to_string());
+            } else if attr == "verify-signatures" {
+                verify_signatures = true;
+            } else if let Some(v) = attr.strip_prefix("tree=") {
+                tree = Some(v.to_string());
+            } else if let Some(v) = attr.strip_prefix("prefix=") {
+                prefix = Some(v.to_string());
+            } else if let Some(v) = attr.strip_prefix("codeowners=") {
+                codeowners = Some(v.to_string());
+            } else if attr == "honor-export-ignore" {
+                honor_export_ignore = true;
+            } else if attr == "import-attributes" {
+                import_attributes = true;
+            } else if let Some(v) = attr.strip_prefix("depth=") {
+                depth = v.parse().ok();
+            } else if let Some(v) = attr.strip_prefix("fetch-tags=") {
+                fetch_tags = v.parse().ok();
             }
         }
 
@@ -554,85 +3149,374 @@ fn parse_vendor_deps(path: &Path) -> Result<Vec<VendorDep>, Error> {
             continue;
         }
 
-        if let (Some(name), Some(url)) = (name, url) {
+        if let Some(name) = name {
             deps.push(VendorDep {
                 name,
                 pattern: pattern.to_string(),
-                url,
+                url: url.unwrap_or_default(),
                 branch,
+                rev,
+                verify_signatures,
+                tree,
+                prefix,
+                codeowners,
+                honor_export_ignore,
+                import_attributes,
+                depth,
+                fetch_tags,
+                ..Default::default()
             });
         }
     }
 
-    Ok(deps)
+    group_patterns(deps)
 }
 
-/// Remove all lines from a `.gitattributes` file that match `pattern` **and**
-/// carry vendor attributes.  Non-vendor lines for the same pattern are kept.
-fn remove_vendor_lines(path: &Path, pattern: &str) -> Result<(), Error> {
+/// The attributes git-vendor writes on the lines of a dependency, by name.
+const VENDOR_ATTRIBUTES: &[&str] = &[
+    "vendored",
+    "name",
+    "url",
+    "branch",
+    "rev",
+    "verify-signatures",
+    "tree",
+    "prefix",
+    "codeowners",
+    "honor-export-ignore",
+    "import-attributes",
+    "depth",
+    "fetch-tags",
+    "linguist-vendored",
+];
+
+/// Remove the vendor attributes from the lines of a `.gitattributes` file
+/// that match `pattern`.  Other attributes on those lines are kept, and
+/// lines left without attributes are dropped.
+///
+/// A `-diff` next to `linguist-vendored` on a vendor line is taken to be
+/// the mark written by [`Linguist::VendoredNoDiff`] and removed as well.
+fn remove_vendor_lines(repo: &Repository, path: &Path, pattern: &str) -> Result<(), Error> {
     if !path.exists() {
         return Ok(());
     }
 
     let content = fs::read_to_string(path)
         .map_err(|e| Error::from_str(&format!("Failed to read {}: {e}", path.display())))?;
-
-    let mut kept = Vec::new();
-    for line in content.lines() {
-        if is_vendor_line_for_pattern(line, pattern) {
-            // FIXME: what if other non-vendor-related attributes are on this line?
-            continue;
+    let no_diff = content.lines().any(|line| {
+        is_vendor_line_for_pattern(line, pattern) && {
+            let attrs: Vec<&str> = line.split_whitespace().collect();
+            attrs.contains(&"linguist-vendored") && attrs.contains(&"-diff")
         }
-        kept.push(line);
-    }
+    });
 
-    let mut file = fs::File::create(path)
-        .map_err(|e| Error::from_str(&format!("Failed to write {}: {e}", path.display())))?;
+    // Macros that mark paths `vendored` are vendor attributes too.
+    let mut macros = root_macros(root_gitattributes(path).as_deref());
+    parse_macros(&content, &mut macros);
 
-    for line in &kept {
-        writeln!(file, "{line}")
-            .map_err(|e| Error::from_str(&format!("Failed to write .gitattributes: {e}")))?;
+    let mut attrs = VENDOR_ATTRIBUTES.to_vec();
+    if no_diff {
+        attrs.push("diff");
     }
+    attrs.extend(
+        macros
+            .iter()
+            .filter(|(_, expanded)| expanded.iter().any(|attr| attr == "vendored"))
+            .map(|(name, _)| name.as_str()),
+    );
+    repo.remove_attr(pattern, &attrs, AttrFile::Path(path))
+}
+
+/// The attributes of `attrs`, written for `pattern` in the attributes file
+/// at `path`, that evaluate differently through the full attribute stack,
+/// as `(name, written, effective)` states.
+///
+/// They are evaluated on a path that `pattern` matches; patterns ending in
+/// `/`, which Git never applies to files, are not checked.
+fn overridden_attributes(
+    repo: &Repository,
+    path: &Path,
+    pattern: &str,
+    attrs: &[&str],
+) -> Result<Vec<(String, String, String)>, Error> {
+    let Some(sample) = sample_path(pattern) else {
+        return Ok(Vec::new());
+    };
+    let dir = path
+        .parent()
+        .and_then(|parent| paths::workdir_relative(repo, parent))
+        .unwrap_or_default();
+
+    let written: Vec<(&str, &str)> = attrs
+        .iter()
+        .map(|attr| match attr.split_once('=') {
+            Some((name, value)) => (name, value),
+            None => match attr.strip_prefix('-') {
+                Some(name) => (name, "unset"),
+                None => (*attr, "set"),
+            },
+        })
+        .collect();
+    let names: Vec<&str> = written.iter().map(|(name, _)| *name).collect();
+    let effective = repo.check_attr(&dir.join(sample), &names)?;
+
+    Ok(written
+        .into_iter()
+        .zip(effective)
+        .filter(|((_, written), (_, effective))| written != effective)
+        .map(|((name, written), (_, effective))| (name.to_string(), written.to_string(), effective))
+        .collect())
+}
 
-    file.flush()
-        .map_err(|e| Error::from_str(&format!("Failed to flush .gitattributes: {e}")))?;
+/// A path that the gitattributes `pattern` matches, with each wildcard
+/// replaced by `x`, or `None` if the pattern ends in `/`.
+fn sample_path(pattern: &str) -> Option<String> {
+    let pattern = pattern.strip_prefix('/').unwrap_or(pattern);
+    if pattern.is_empty() || pattern.ends_with('/') {
+        return None;
+    }
 
-    Ok(())
+    let mut sample = String::new();
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => {
+                while chars.next_if_eq(&'*').is_some() {}
+                sample.push('x');
+            }
+            '?' => sample.push('x'),
+            '\\' => sample.extend(chars.next()),
+            '[' => {
+                chars.next_if(|&c| c == '!' || c == '^');
+                sample.extend(chars.next());
+                for c in chars.by_ref() {
+                    if c == ']' {
+                        break;
+                    }
+                }
+            }
+            c => sample.push(c),
+        }
+    }
+    Some(sample)
 }
 
 /// Return `true` if `line` starts with `pattern` and contains at least one
 /// vendor attribute (`vendored`, `name=`, `url=`, or
 /// `branch=`).
 fn is_vendor_line_for_pattern(line: &str, pattern: &str) -> bool {
-    let trimmed = line.trim();
-    if trimmed.is_empty() || trimmed.starts_with('#') {
+    let Some((line_pattern, rest)) = split_pattern(line) else {
         return false;
-    }
-
-    let mut parts = trimmed.split_whitespace();
-    let line_pattern = match parts.next() {
-        Some(p) => p,
-        None => return false,
     };
 
     if line_pattern != pattern {
         return false;
     }
 
-    parts.any(|attr| {
+    rest.split_whitespace().any(|attr| {
         attr == "vendored"
             || attr.starts_with("name=")
             || attr.starts_with("url=")
             || attr.starts_with("branch=")
+            || attr == "linguist-vendored"
     })
 }
 
-/// Filter dependencies by exact pattern match.
+/// Return a copy of `tree` whose `.gitattributes` at `path` carries the
+/// upstream attributes imported for `dep`.
+fn import_attributes(
+    repo: &Repository,
+    path: &Path,
+    tree: &git2::Tree,
+    dep: &VendorDep,
+    upstream: &git2::Tree,
+) -> Result<git2::Oid, Error> {
+    let relative = paths::workdir_relative(repo, path).ok_or_else(|| {
+        Error::from_str(&format!("{} is outside the working tree", path.display()))
+    })?;
+
+    let content = match tree.get_path(&relative) {
+        Ok(entry) => {
+            let blob = repo.find_blob(entry.id())?;
+            String::from_utf8_lossy(blob.content()).into_owned()
+        }
+        Err(e) if e.code() == git2::ErrorCode::NotFound => String::new(),
+        Err(e) => return Err(e),
+    };
+
+    let block = attributes::render_block(repo, upstream, dep)?;
+    let (begin, end) = attributes::markers(&dep.name);
+    let updated = replace_block(&content, &begin, &end, &block);
+    if updated == content {
+        return Ok(tree.id());
+    }
+
+    let blob = repo.blob(updated.as_bytes())?;
+    let mut update = git2::build::TreeUpdateBuilder::new();
+    update.upsert(relative, blob, git2::FileMode::Blob);
+    update.create_updated(repo, tree)
+}
+
+/// Replace the block delimited by the `begin` and `end` marker lines in
+/// `content` with `block`, or append `block` if there is none.
+///
+/// An empty `block` removes an existing block. The byte order mark and line
+/// endings of `content` are kept.
+pub(crate) fn replace_block(content: &str, begin: &str, end: &str, block: &str) -> String {
+    let format = TextFormat::detect(content);
+    let content = TextFormat::default().apply(content);
+    format.apply(&replace_lf_block(&content, begin, end, block))
+}
+
+fn replace_lf_block(content: &str, begin: &str, end: &str, block: &str) -> String {
+    let begin_line = content.match_indices(begin).map(|(i, _)| i).find(|&i| {
+        (i == 0 || content[..i].ends_with('\n'))
+            && matches!(content[i + begin.len()..].chars().next(), None | Some('\n'))
+    });
+    if let Some(start) = begin_line
+        && let Some(len) = content[start..].find(end)
+    {
+        let mut stop = start + len + end.len();
+        if content[stop..].starts_with('\n') {
+            stop += 1;
+        }
+        return format!("{}{block}{}", &content[..start], &content[stop..]);
+    }
+
+    let mut updated = content.to_string();
+    if block.is_empty() {
+        return updated;
+    }
+    if !updated.is_empty() && !updated.ends_with('\n') {
+        updated.push('\n');
+    }
+    if !updated.is_empty() {
+        updated.push('\n');
+    }
+    updated.push_str(block);
+    updated
+}
+
+/// Filter an upstream tree down to the content vendored for `dep`, moved
+/// under its `prefix=` directory if it has one.
+pub(crate) fn upstream_tree<'r>(
+    repo: &'r Repository,
+    dep: &VendorDep,
+    tree: &'r git2::Tree<'r>,
+) -> Result<git2::Tree<'r>, Error> {
+    let Some(prefix) = &dep.prefix else {
+        return filter_upstream(repo, dep, tree);
+    };
+    let rebased = repo.rebase_prefix(tree, "", prefix)?;
+    let filtered = filter_upstream(repo, dep, &rebased)?.id();
+    repo.find_tree(filtered)
+}
+
+fn filter_upstream<'r>(
+    repo: &'r Repository,
+    dep: &VendorDep,
+    tree: &'r git2::Tree<'r>,
+) -> Result<git2::Tree<'r>, Error> {
+    let filtered = repo.filter_by_patterns(tree, &dep.patterns())?;
+    if !dep.honor_export_ignore {
+        return Ok(filtered);
+    }
+    let pruned = export_ignore::prune(repo, &filtered, tree)?;
+    repo.find_tree(pruned)
+}
+
+/// Filter dependencies by exact match on any of their patterns.
 fn filter_deps<'a>(deps: &'a [VendorDep], filter: Option<&str>) -> Vec<&'a VendorDep> {
-    match filter {
+    match filter.map(paths::normalize_pattern) {
         None => deps.iter().collect(),
-        Some(f) => deps.iter().filter(|d| d.pattern == f).collect(),
+        Some(f) => deps
+            .iter()
+            .filter(|d| d.patterns().contains(&f.as_ref()))
+            .collect(),
+    }
+}
+
+/// Resolve `version` to the `rev=` value pinning `dep` to it: a tag name if
+/// the upstream has such a tag, otherwise a full commit id.
+fn resolve_rev(
+    repo: &Repository,
+    dep: &VendorDep,
+    version: &str,
+    config: &config::Config,
+) -> Result<String, Error> {
+    let tag_ref = format!("{}{version}", vendor_tags_ref_prefix(&dep.name));
+    if repo.find_reference(&tag_ref).is_ok() || is_commit_id(version) {
+        return Ok(version.to_string());
+    }
+    if version.len() >= 4
+        && version.bytes().all(|b| b.is_ascii_hexdigit())
+        && let Ok(commit) = repo
+            .revparse_single(version)
+            .and_then(|object| object.peel_to_commit())
+    {
+        return Ok(commit.id().to_string());
+    }
+    if git2::Reference::is_valid_name(&format!("refs/tags/{version}")) {
+        config.require_online("look up upstream tags")?;
+        let tag = format!("refs/tags/{version}");
+        if !protocol::ls_remote(repo, dep, &[&tag])?.is_empty() {
+            return Ok(version.to_string());
+        }
+    }
+    Err(Error::from_str(&format!(
+        "No tag or fetched commit '{version}' found for {}",
+        dep.name
+    )))
+}
+
+/// Find the dependency named `key`, or else the one tracking the pattern
+/// `key`.
+fn find_dep<'a>(deps: &'a [VendorDep], key: &str) -> Result<&'a VendorDep, Error> {
+    deps.iter()
+        .find(|d| d.name == key)
+        .or_else(|| deps.iter().find(|d| d.patterns().contains(&key)))
+        .ok_or_else(|| {
+            error::new(
+                error::Code::NoDependency,
+                &format!("No vendored dependency named '{key}'"),
+            )
+        })
+}
+
+/// Fold dependencies sharing a name into the first one, whose extra
+/// patterns collect the others'.
+///
+/// A grouped entry without a URL inherits the first one's; a different URL
+/// is an error. Entries without a URL and no other entry defining their
+/// name are dropped.
+pub(crate) fn group_patterns(entries: Vec<VendorDep>) -> Result<Vec<VendorDep>, Error> {
+    let mut deps: Vec<VendorDep> = Vec::new();
+    let mut orphans = Vec::new();
+    for entry in entries {
+        if entry.url.is_empty() {
+            orphans.push(entry);
+            continue;
+        }
+        match deps.iter_mut().find(|d| d.name == entry.name) {
+            Some(dep) if dep.url != entry.url => {
+                return Err(Error::from_str(&format!(
+                    "Dependency {} is tracked from both {} and {}",
+                    dep.name, dep.url, entry.url
+                )));
+            }
+            Some(dep) => {
+                dep.extra_patterns.push(entry.pattern);
+                dep.extra_patterns.extend(entry.extra_patterns);
+            }
+            None => deps.push(entry),
+        }
+    }
+    for orphan in orphans {
+        if let Some(dep) = deps.iter_mut().find(|d| d.name == orphan.name) {
+            dep.extra_patterns.push(orphan.pattern);
+        }
     }
+    Ok(deps)
 }
 
 // ---------------------------------------------------------------------------
@@ -738,6 +3622,37 @@ mod tests {
         assert_eq!(name_from_url("../repos/mylib"), None);
     }
 
+    // -- split_remote_url ---------------------------------------------------
+
+    #[test]
+    fn split_remote_url_https() {
+        assert_eq!(
+            split_remote_url("https://github.com/owner/repo.git"),
+            Some(("github.com", "owner/repo"))
+        );
+    }
+
+    #[test]
+    fn split_remote_url_strips_user_and_port() {
+        assert_eq!(
+            split_remote_url("ssh://git@example.com:2222/owner/repo.git"),
+            Some(("example.com", "owner/repo"))
+        );
+    }
+
+    #[test]
+    fn split_remote_url_scp_style() {
+        assert_eq!(
+            split_remote_url("git@github.com:owner/repo.git"),
+            Some(("github.com", "owner/repo"))
+        );
+    }
+
+    #[test]
+    fn split_remote_url_local_path() {
+        assert_eq!(split_remote_url("/home/user/repos/mylib"), None);
+    }
+
     // -- resolve_name -------------------------------------------------------
 
     #[test]
@@ -766,26 +3681,125 @@ mod tests {
         assert!(resolve_name("https://github.com/a/b.git", Some("")).is_err());
     }
 
+    // -- validate_pattern / validate_url -----------------------------------
+
+    #[test]
+    fn validate_pattern_accepts_globs() {
+        assert!(validate_pattern("vendor/lib/").is_ok());
+        assert!(validate_pattern("deps/*/src/**").is_ok());
+        assert!(validate_pattern("vendor lib/").is_ok());
+    }
+
+    #[test]
+    fn validate_pattern_rejects_bad_syntax() {
+        assert!(validate_pattern("").is_err());
+        assert!(validate_pattern("  ").is_err());
+        assert!(validate_pattern("vendor\nlib/").is_err());
+        assert!(validate_pattern("!vendor/").is_err());
+        assert!(validate_pattern("vendor/[lib").is_err());
+    }
+
+    #[test]
+    fn validate_url_accepts_remotes_and_paths() {
+        assert!(validate_url("https://github.com/owner/repo.git").is_ok());
+        assert!(validate_url("git@github.com:owner/repo.git").is_ok());
+        assert!(validate_url("file:///srv/git/repo").is_ok());
+        assert!(validate_url("../repos/mylib").is_ok());
+    }
+
+    #[test]
+    fn validate_url_rejects_malformed() {
+        assert!(validate_url("").is_err());
+        assert!(validate_url("https://github.com/owner/my repo").is_err());
+        assert!(validate_url("htps://github.com/owner/repo").is_err());
+        assert!(validate_url("https://github.com").is_err());
+    }
+
     // -- vendor_ref_name ----------------------------------------------------
 
     #[test]
     fn vendor_ref_name_owner_repo() {
-        assert_eq!(vendor_ref_name("owner/repo"), "refs/vendor/owner/repo");
+        assert_eq!(
+            vendor_ref_name(config::DEFAULT_REF_PREFIX, "owner/repo"),
+            "refs/vendor/owner/repo"
+        );
     }
 
     #[test]
     fn vendor_ref_name_custom_name() {
-        assert_eq!(vendor_ref_name("custom-name"), "refs/vendor/custom-name");
+        assert_eq!(
+            vendor_ref_name(config::DEFAULT_REF_PREFIX, "custom-name"),
+            "refs/vendor/custom-name"
+        );
     }
 
     #[test]
     fn vendor_ref_name_multiple_slashes() {
         assert_eq!(
-            vendor_ref_name("multiple/slash/names"),
+            vendor_ref_name(config::DEFAULT_REF_PREFIX, "multiple/slash/names"),
             "refs/vendor/multiple/slash/names"
         );
     }
 
+    // -- check_tree_pin -----------------------------------------------------
+
+    #[test]
+    fn check_tree_pin_accepts_full_and_abbreviated() {
+        let oid = git2::Oid::from_str("0123456789abcdef0123456789abcdef01234567").unwrap();
+        assert!(check_tree_pin(oid, "0123456789abcdef0123456789abcdef01234567").is_ok());
+        assert!(check_tree_pin(oid, "0123456").is_ok());
+        assert!(check_tree_pin(oid, "0123456789ABCDEF").is_ok());
+    }
+
+    #[test]
+    fn check_tree_pin_rejects_mismatch_and_invalid() {
+        let oid = git2::Oid::from_str("0123456789abcdef0123456789abcdef01234567").unwrap();
+        assert!(check_tree_pin(oid, "fedcba9").is_err());
+        assert!(check_tree_pin(oid, "012").is_err());
+        assert!(check_tree_pin(oid, "not-hex").is_err());
+    }
+
+    // -- format_time --------------------------------------------------------
+
+    #[test]
+    fn format_time_epoch() {
+        assert_eq!(format_time(0), "1970-01-01T00:00:00Z");
+    }
+
+    #[test]
+    fn format_time_leap_day() {
+        assert_eq!(format_time(1_709_210_096), "2024-02-29T12:34:56Z");
+    }
+
+    #[test]
+    fn vendor_tags_ref_prefix_owner_repo() {
+        assert_eq!(
+            vendor_tags_ref_prefix("owner/repo"),
+            "refs/vendor-tags/owner/repo/"
+        );
+    }
+
+    #[test]
+    fn parse_time_round_trips() {
+        for seconds in [0, 951_782_400, 1_709_210_096, 4_102_444_799] {
+            assert_eq!(parse_time(&format_time(seconds)), Some(seconds));
+        }
+    }
+
+    #[test]
+    fn parse_time_rejects_garbage() {
+        assert_eq!(parse_time("2026-01-01"), None);
+        assert_eq!(parse_time("2026-13-01T00:00:00Z"), None);
+        assert_eq!(parse_time("yesterday"), None);
+    }
+
+    #[test]
+    fn format_age_days() {
+        assert_eq!(format_age(3600), "today");
+        assert_eq!(format_age(86_400), "1 day ago");
+        assert_eq!(format_age(90 * 86_400 + 5), "90 days ago");
+    }
+
     // -- parse_vendor_deps --------------------------------------------------
 
     #[test]
@@ -861,6 +3875,128 @@ mod tests {
         assert_eq!(deps[0].branch, None);
     }
 
+    #[test]
+    fn parse_vendor_deps_groups_lines_by_name() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(".gitattributes");
+
+        fs::write(
+            &path,
+            "src/foo/** vendored name=o/foo\n\
+             include/foo/** vendored name=o/foo url=https://a.com/o/foo.git\n\
+             lib/foo/** vendored name=o/foo url=https://a.com/o/foo.git\n",
+        )
+        .unwrap();
+        let deps = parse_vendor_deps(&path).unwrap();
+        assert_eq!(deps.len(), 1);
+        assert_eq!(
+            deps[0].patterns(),
+            ["include/foo/**", "lib/foo/**", "src/foo/**"]
+        );
+
+        fs::write(
+            &path,
+            "a/ vendored name=o/foo url=https://a.com/o/foo.git\n\
+             b/ vendored name=o/foo url=https://b.com/o/foo.git\n",
+        )
+        .unwrap();
+        assert!(parse_vendor_deps(&path).is_err());
+    }
+
+    #[test]
+    fn parse_vendor_deps_expands_macros() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(".gitattributes");
+
+        fs::write(
+            &path,
+            "[attr]vendor-dep vendored linguist-vendored\n\
+             [attr]owner-dep vendor-dep url=https://a.com/o/r.git\n\
+             lib/ vendor-dep name=o/r url=https://a.com/o/r.git branch=main\n\
+             src/ owner-dep name=o/s\n",
+        )
+        .unwrap();
+        let deps = parse_vendor_deps(&path).unwrap();
+        assert_eq!(deps.len(), 2);
+        assert_eq!(deps[0].name, "o/r");
+        assert_eq!(deps[0].branch.as_deref(), Some("main"));
+        assert_eq!(deps[1].name, "o/s");
+        assert_eq!(deps[1].url, "https://a.com/o/r.git");
+    }
+
+    #[test]
+    fn parse_vendor_deps_expands_root_macros_in_nested_files() {
+        let dir = TempDir::new().unwrap();
+        Repository::init(dir.path()).unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(
+            dir.path().join(".gitattributes"),
+            "[attr]vendor-dep vendored\n",
+        )
+        .unwrap();
+        let path = dir.path().join("sub/.gitattributes");
+        fs::write(
+            &path,
+            "lib/ vendor-dep name=o/r url=https://a.com/o/r.git\n",
+        )
+        .unwrap();
+
+        let deps = parse_vendor_deps(&path).unwrap();
+        assert_eq!(deps.len(), 1);
+        assert_eq!(deps[0].pattern, "lib/");
+    }
+
+    #[test]
+    fn parse_vendor_deps_unquotes_patterns() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(".gitattributes");
+
+        fs::write(
+            &path,
+            "\"third party/lib/\" vendored name=o/r url=https://a.com/o/r.git\n",
+        )
+        .unwrap();
+        let deps = parse_vendor_deps(&path).unwrap();
+        assert_eq!(deps.len(), 1);
+        assert_eq!(deps[0].pattern, "third party/lib/");
+        assert!(is_vendor_line_for_pattern(
+            "\"third party/lib/\" vendored",
+            "third party/lib/"
+        ));
+    }
+
+    #[test]
+    fn parse_vendor_deps_skips_byte_order_mark() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(".gitattributes");
+
+        fs::write(
+            &path,
+            "\u{feff}lib/ vendored name=o/r url=https://a.com/o/r.git\r\n",
+        )
+        .unwrap();
+        let deps = parse_vendor_deps(&path).unwrap();
+        assert_eq!(deps.len(), 1);
+        assert_eq!(deps[0].pattern, "lib/");
+        assert_eq!(deps[0].url, "https://a.com/o/r.git");
+    }
+
+    #[test]
+    fn parse_vendor_deps_verify_signatures() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(".gitattributes");
+
+        fs::write(
+            &path,
+            "*.txt vendored name=o/r url=https://a.com/o/r.git verify-signatures\n\
+             *.rs vendored name=o/s url=https://a.com/o/s.git\n",
+        )
+        .unwrap();
+        let deps = parse_vendor_deps(&path).unwrap();
+        assert!(deps[0].verify_signatures);
+        assert!(!deps[1].verify_signatures);
+    }
+
     // -- is_vendor_line_for_pattern -----------------------------------------
 
     #[test]
@@ -901,6 +4037,7 @@ mod tests {
     #[test]
     fn remove_vendor_lines_keeps_non_vendor() {
         let dir = TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
         let path = dir.path().join(".gitattributes");
 
         let original = "\
@@ -911,7 +4048,7 @@ mod tests {
 ";
         fs::write(&path, original).unwrap();
 
-        remove_vendor_lines(&path, "*.txt").unwrap();
+        remove_vendor_lines(&repo, &path, "*.txt").unwrap();
 
         let content = fs::read_to_string(&path).unwrap();
         assert!(!content.contains("url=https://a.com"));
@@ -920,9 +4057,125 @@ mod tests {
         assert!(content.contains("# comment"));
     }
 
+    #[test]
+    fn remove_vendor_lines_keeps_other_attributes_on_vendor_line() {
+        let dir = TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let path = dir.path().join(".gitattributes");
+
+        fs::write(
+            &path,
+            "*.txt vendored name=o/r url=https://a.com rev=abc linguist-vendored -text eol=lf\n",
+        )
+        .unwrap();
+
+        remove_vendor_lines(&repo, &path, "*.txt").unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "*.txt -text eol=lf\n");
+    }
+
+    #[test]
+    fn overridden_attributes_reports_later_lines() {
+        let dir = TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let path = dir.path().join(".gitattributes");
+        let written = ["vendored", "linguist-vendored", "-diff"];
+
+        fs::write(
+            &path,
+            "*.txt vendored name=o/r url=https://a.com linguist-vendored -diff\n\
+             *.md vendored name=o/s url=https://a.com linguist-vendored -diff\n\
+             *.txt -linguist-vendored\n\
+             x.txt diff\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            overridden_attributes(&repo, &path, "*.txt", &written).unwrap(),
+            vec![
+                ("linguist-vendored".into(), "set".into(), "unset".into()),
+                ("diff".into(), "unset".into(), "set".into()),
+            ]
+        );
+        assert!(
+            overridden_attributes(&repo, &path, "*.md", &written)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn overridden_attributes_skips_directory_patterns() {
+        let dir = TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let path = dir.path().join(".gitattributes");
+
+        fs::write(&path, "lib/ vendored name=o/r url=https://a.com\n").unwrap();
+
+        assert!(
+            overridden_attributes(&repo, &path, "lib/", &["vendored"])
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn sample_path_replaces_wildcards() {
+        assert_eq!(sample_path("*.txt").as_deref(), Some("x.txt"));
+        assert_eq!(sample_path("/lib/**").as_deref(), Some("lib/x"));
+        assert_eq!(sample_path("a/**/b?.[!ch]").as_deref(), Some("a/x/bx.c"));
+        assert_eq!(sample_path("\\*.txt").as_deref(), Some("*.txt"));
+        assert_eq!(sample_path("vendor/a/"), None);
+    }
+
+    #[test]
+    fn remove_vendor_lines_removes_vendor_macros() {
+        let dir = TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let path = dir.path().join(".gitattributes");
+
+        fs::write(
+            &path,
+            "[attr]vendor-dep vendored\n\
+             [attr]binary -diff -text\n\
+             lib/ vendor-dep name=o/r url=https://a.com binary\n",
+        )
+        .unwrap();
+
+        remove_vendor_lines(&repo, &path, "lib/").unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "[attr]vendor-dep vendored\n[attr]binary -diff -text\nlib/ binary\n"
+        );
+    }
+
     #[test]
     fn remove_vendor_lines_noop_for_missing_file() {
-        assert!(remove_vendor_lines(Path::new("/nonexistent/.gitattributes"), "*.txt").is_ok());
+        let dir = TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let path = dir.path().join("missing/.gitattributes");
+        assert!(remove_vendor_lines(&repo, &path, "*.txt").is_ok());
+    }
+
+    // -- replace_block ------------------------------------------------------
+
+    #[test]
+    fn replace_block_keeps_line_endings() {
+        let content = "*.md text\r\n\r\n# BEGIN x\r\nold\r\n# END x\r\n*.rs diff\r\n";
+        assert_eq!(
+            replace_block(content, "# BEGIN x", "# END x", "# BEGIN x\nnew\n# END x\n"),
+            "*.md text\r\n\r\n# BEGIN x\r\nnew\r\n# END x\r\n*.rs diff\r\n"
+        );
+        assert_eq!(
+            replace_block(
+                "\u{feff}*.md text\r\n",
+                "# BEGIN x",
+                "# END x",
+                "# BEGIN x\n# END x\n"
+            ),
+            "\u{feff}*.md text\r\n\r\n# BEGIN x\r\n# END x\r\n"
+        );
     }
 
     // -- filter_deps --------------------------------------------------------
@@ -935,12 +4188,14 @@ mod tests {
                 pattern: "a".into(),
                 url: "u".into(),
                 branch: Some("b".into()),
+                ..Default::default()
             },
             VendorDep {
                 name: "c/d".into(),
                 pattern: "b".into(),
                 url: "u".into(),
                 branch: None,
+                ..Default::default()
             },
         ];
         assert_eq!(filter_deps(&deps, None).len(), 2);
@@ -954,12 +4209,14 @@ mod tests {
                 pattern: "*.txt".into(),
                 url: "u".into(),
                 branch: Some("b".into()),
+                ..Default::default()
             },
             VendorDep {
                 name: "c/d".into(),
                 pattern: "*.rs".into(),
                 url: "u".into(),
                 branch: None,
+                ..Default::default()
             },
         ];
         let filtered = filter_deps(&deps, Some("*.txt"));
@@ -974,6 +4231,7 @@ mod tests {
             pattern: "*.txt".into(),
             url: "u".into(),
             branch: Some("b".into()),
+            ..Default::default()
         }];
         assert!(filter_deps(&deps, Some("*.rs")).is_empty());
     }
@@ -0,0 +1,49 @@
+//! Locked mode, in which the provenance records act as a lockfile.
+//!
+//! Normally fetches follow each dependency's branch (or pinned rev) to
+//! whatever it currently resolves to upstream. With the `locked` setting,
+//! every operation instead resolves a dependency to the upstream commit its
+//! provenance record in `HEAD` pins, and fails if the dependency has no
+//! record or its definition no longer agrees with the record.
+
+use super::{VendorDep, config::invalid, provenance, upstream_ref};
+use git2::{Error, Oid, Repository, Tree};
+
+/// The upstream commit `dep` is locked to by its record in `tree`.
+pub(crate) fn locked_commit(repo: &Repository, tree: &Tree, dep: &VendorDep) -> Result<Oid, Error> {
+    let Some(record) = provenance::read(repo, tree, &dep.name)? else {
+        return Err(invalid(&format!(
+            "{} has no provenance record to lock it; merge it without --locked first",
+            dep.name
+        )));
+    };
+    if record.url != dep.url {
+        return Err(disagree(dep, "URL", &dep.url, &record.url));
+    }
+    let reference = upstream_ref(dep);
+    if record.reference != reference {
+        return Err(disagree(dep, "ref", &reference, &record.reference));
+    }
+    Oid::from_str(&record.commit).map_err(|_| {
+        invalid(&format!(
+            "Invalid object id in provenance record: {}",
+            record.commit
+        ))
+    })
+}
+
+/// `dep` pinned to the commit it is locked to, for fetching exactly that.
+pub(crate) fn pin(repo: &Repository, tree: &Tree, dep: &VendorDep) -> Result<VendorDep, Error> {
+    let commit = locked_commit(repo, tree, dep)?;
+    Ok(VendorDep {
+        rev: Some(commit.to_string()),
+        ..dep.clone()
+    })
+}
+
+fn disagree(dep: &VendorDep, what: &str, defined: &str, recorded: &str) -> Error {
+    invalid(&format!(
+        "{} is defined with {what} {defined}, but its provenance records {recorded}",
+        dep.name
+    ))
+}
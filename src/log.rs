@@ -0,0 +1,57 @@
+//! Diagnostics on standard error, through [`tracing`].
+//!
+//! Command output goes to standard output as before; warnings, errors and
+//! the library's debug messages are logged at the level chosen with
+//! `-q`/`-v`:
+//!
+//! | Flags  | Level |
+//! |--------|-------|
+//! | `-q`   | error |
+//! | (none) | warn  |
+//! | `-v`   | info  |
+//! | `-vv`  | debug |
+//! | `-vvv` | trace |
+
+use crate::cli::LogFormat;
+use std::io::IsTerminal;
+use tracing::level_filters::LevelFilter;
+
+/// The level logged with `verbose` `-v` flags, or errors only if `quiet`.
+fn level(verbose: u8, quiet: bool) -> LevelFilter {
+    match (quiet, verbose) {
+        (true, _) => LevelFilter::ERROR,
+        (false, 0) => LevelFilter::WARN,
+        (false, 1) => LevelFilter::INFO,
+        (false, 2) => LevelFilter::DEBUG,
+        (false, _) => LevelFilter::TRACE,
+    }
+}
+
+/// Install the global subscriber writing log messages to standard error.
+pub fn init(verbose: u8, quiet: bool, format: LogFormat) {
+    let builder = tracing_subscriber::fmt()
+        .with_max_level(level(verbose, quiet))
+        .with_writer(std::io::stderr);
+    match format {
+        LogFormat::Text => builder
+            .without_time()
+            .with_target(verbose > 1)
+            .with_ansi(std::io::stderr().is_terminal())
+            .init(),
+        LogFormat::Json => builder.json().init(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn level_follows_verbosity_flags() {
+        assert_eq!(level(0, true), LevelFilter::ERROR);
+        assert_eq!(level(0, false), LevelFilter::WARN);
+        assert_eq!(level(1, false), LevelFilter::INFO);
+        assert_eq!(level(2, false), LevelFilter::DEBUG);
+        assert_eq!(level(5, false), LevelFilter::TRACE);
+    }
+}
@@ -1,32 +1,195 @@
 mod cli;
+mod complete;
+mod exit;
+mod log;
+mod pager;
+#[cfg(feature = "tui")]
+mod ui;
 
-use clap::Parser;
-use cli::{Cli, Commands};
-use git_vendor::{Vendor, VendorMergeOpts};
+use clap::{CommandFactory, Parser};
+use clap_complete::CompleteEnv;
+use cli::{
+    Cli, Commands, ExportFormat, GraphFormat, GroupBy, ImportFormat, Linguist, OutputFormat,
+    ProgressFormat, SbomFormat, SourceFormat, StatusSort,
+};
+use git_vendor::{
+    Annotation, AnnotationKind, Candidate, GcOpts, MergeStat, Outdated, SetupOpts, StatusOpts,
+    Template, Vendor, VendorMergeOpts, annotations::Level,
+};
 use git2 as git;
-use std::process;
+use std::{
+    collections::HashMap, fs, io::IsTerminal, path::PathBuf, process, thread, time::Duration,
+};
 
 fn main() {
-    if let Err(e) = run() {
-        eprintln!("Error: {}", e);
-        process::exit(1);
+    CompleteEnv::with_factory(Cli::command)
+        .var(complete::VAR)
+        .complete();
+
+    match run() {
+        Ok(code) => process::exit(code),
+        Err(e) => {
+            log_error(e.as_ref());
+            process::exit(exit::code(e.as_ref()));
+        }
+    }
+}
+
+/// Log `error` with its stable code (see [`git_vendor::error`]).
+fn log_error(error: &(dyn std::error::Error + 'static)) {
+    match error.downcast_ref::<git::Error>() {
+        Some(error) => {
+            let code = git_vendor::error::code(error);
+            tracing::error!(code = %code, "{}", error.message());
+        }
+        None => tracing::error!(code = %git_vendor::error::Code::Failure, "{error}"),
     }
 }
 
-fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// Run the command, returning its exit code (see [`exit`]).
+fn run() -> Result<i32, Box<dyn std::error::Error>> {
     let cli = Cli::parse();
+    log::init(cli.verbose, cli.quiet, cli.log_format);
+    if cli.quiet {
+        git_vendor::set_quiet(true);
+    }
+    let mut code = exit::SUCCESS;
+
+    if let Commands::Completions { shell } = cli.command {
+        complete::write_registration(shell, &mut std::io::stdout())?;
+        return Ok(code);
+    }
 
     // Open the repository in current directory
     let repo = git::Repository::open(".")?;
 
+    let paged = matches!(
+        cli.command,
+        Commands::Status { .. }
+            | Commands::Verify { .. }
+            | Commands::Outdated { json: false, .. }
+            | Commands::Diff { .. }
+    );
+    let pager = if paged && !cli.no_pager {
+        pager::start(&repo)
+    } else {
+        None
+    };
+    git_vendor::set_color(pager::use_color(&repo, pager.is_some()));
+    git_vendor::events::set_events(cli.events || cli.progress_format == ProgressFormat::Ndjson);
+    git_vendor::config::set_overrides(git_vendor::config::Settings {
+        jobs: cli.jobs,
+        depth: cli.depth,
+        offline: cli.offline.then_some(true),
+        locked: cli.locked.then_some(true),
+        cache_dir: cli.cache_dir.clone(),
+        prune: matches!(cli.command, Commands::Fetch { prune: true, .. }).then_some(true),
+        dissociate: matches!(
+            cli.command,
+            Commands::Fetch {
+                dissociate: true,
+                ..
+            }
+        )
+        .then_some(true),
+        notes: matches!(cli.command, Commands::Merge { notes: true, .. }).then_some(true),
+        fetch_tags: match cli.command {
+            Commands::Fetch { tags: true, .. } => Some(true),
+            Commands::Fetch { no_tags: true, .. } => Some(false),
+            _ => None,
+        },
+        ..Default::default()
+    });
+
     match cli.command {
+        Commands::Init { scan: false, .. } => {
+            let workdir = repo
+                .workdir()
+                .ok_or_else(|| git::Error::from_str("Repository has no working directory"))?;
+            let path = workdir.join(".gitattributes");
+            if path.exists() {
+                println!("{} already exists", path.display());
+            } else {
+                fs::write(&path, "")?;
+                println!("Created {}", path.display());
+            }
+            println!(
+                "Track dependencies with git vendor track, or run git vendor init --scan to adopt vendored directories"
+            );
+        }
+
+        Commands::Init {
+            scan: true,
+            yes,
+            dry_run,
+        } => {
+            let candidates = repo.vendor_scan()?;
+            if candidates.is_empty() {
+                println!("No untracked vendored directories found");
+            }
+            let interactive = !yes && !dry_run && std::io::stdin().is_terminal();
+            // Patterns are relative to the root, so track them there.
+            if let Some(workdir) = repo.workdir() {
+                std::env::set_current_dir(workdir)?;
+            }
+            let mut tracked = 0;
+            for candidate in &candidates {
+                println!(
+                    "{}  {}  ({})",
+                    candidate.pattern,
+                    candidate.url.as_deref().unwrap_or("unknown upstream"),
+                    candidate.reason
+                );
+                let url = match (interactive, yes) {
+                    (true, _) => ask_upstream(candidate),
+                    (false, true) => candidate.url.clone(),
+                    (false, false) => None,
+                };
+                let Some(url) = url else {
+                    continue;
+                };
+                match repo.track_pattern(&candidate.pattern, &url, None, None, None, false) {
+                    Ok(()) => tracked += 1,
+                    Err(e) => tracing::warn!(
+                        pattern = %candidate.pattern,
+                        "Not tracked: {}",
+                        e.message()
+                    ),
+                }
+            }
+            if tracked > 0 {
+                let plural = if tracked == 1 { "y" } else { "ies" };
+                println!(
+                    "Tracked {tracked} dependenc{plural}; commit .gitattributes, then run git vendor fetch"
+                );
+            } else if !candidates.is_empty() && !interactive && !yes && !dry_run {
+                println!("Run with --yes to track those with a known upstream");
+            }
+        }
+
         Commands::Track {
             pattern,
             url,
             branch,
             name,
+            linguist,
+            verify,
+            fetch,
+            merge,
         } => {
-            repo.track_pattern(&pattern, &url, branch.as_deref(), name.as_deref())?;
+            let linguist = linguist.map(|linguist| match linguist {
+                Linguist::Off => git_vendor::Linguist::Off,
+                Linguist::Vendored => git_vendor::Linguist::Vendored,
+                Linguist::NoDiff => git_vendor::Linguist::VendoredNoDiff,
+            });
+            repo.track_pattern(
+                &pattern,
+                &url,
+                branch.as_deref(),
+                name.as_deref(),
+                linguist,
+                verify,
+            )?;
             println!("Tracked pattern: {}", pattern);
             if let Some(ref n) = name {
                 println!("  name: {}", n);
@@ -35,6 +198,15 @@ fn run() -> Result<(), Box<dyn std::error::Error>> {
             if let Some(ref b) = branch {
                 println!("  branch: {}", b);
             }
+            if merge {
+                repo.vendor_commit_attributes(&format!("Track vendored pattern: {pattern}"))?;
+            }
+            if fetch || merge {
+                repo.vendor_fetch(Some(&pattern), None)?;
+            }
+            if merge {
+                repo.vendor_merge(Some(&pattern), &VendorMergeOpts::default(), None)?;
+            }
         }
 
         Commands::Untrack { pattern } => {
@@ -42,28 +214,721 @@ fn run() -> Result<(), Box<dyn std::error::Error>> {
             println!("Untracked pattern: {}", pattern);
         }
 
-        Commands::Status { pattern } => {
-            repo.vendor_status(pattern.as_deref())?;
+        Commands::SetUrl { pattern, url } => {
+            repo.vendor_set_url(&pattern, &url)?;
+            println!("Updated URL of {pattern}: {url}");
+        }
+
+        Commands::Pin {
+            dep,
+            version,
+            fetch,
+            merge,
+        } => {
+            let dep = repo.vendor_pin(&dep, &version)?;
+            println!(
+                "Pinned {} to {}",
+                dep.name,
+                dep.rev.as_deref().unwrap_or(&version)
+            );
+            if fetch || merge {
+                repo.vendor_fetch(Some(&dep.pattern), None)?;
+            }
+            if merge {
+                repo.vendor_merge(Some(&dep.pattern), &VendorMergeOpts::default(), None)?;
+            }
+        }
+
+        Commands::Unpin {
+            dep,
+            branch,
+            fetch,
+            merge,
+        } => {
+            let dep = repo.vendor_unpin(&dep, branch.as_deref())?;
+            match &dep.branch {
+                Some(branch) => println!("Unpinned {}; following {branch}", dep.name),
+                None => println!("Unpinned {}; following the default branch", dep.name),
+            }
+            if fetch || merge {
+                repo.vendor_fetch(Some(&dep.pattern), None)?;
+            }
+            if merge {
+                repo.vendor_merge(Some(&dep.pattern), &VendorMergeOpts::default(), None)?;
+            }
+        }
+
+        Commands::Clean { pattern, dry_run } => {
+            let verb = if dry_run { "Would remove" } else { "Removed" };
+            for path in repo.vendor_clean(pattern.as_deref(), dry_run)? {
+                println!("{verb} {path}");
+            }
+        }
+
+        Commands::Restore { pattern } => {
+            for path in repo.vendor_restore(pattern.as_deref())? {
+                println!("Restored {path}");
+            }
+        }
+
+        Commands::Sync { pattern, check } => {
+            let out_of_sync = repo.vendor_sync(pattern.as_deref(), check)?;
+            let verb = if check { "differs" } else { "restored" };
+            for dep in &out_of_sync {
+                for path in &dep.paths {
+                    println!("{path}: {verb} ({})", dep.name);
+                }
+            }
+            if check && !out_of_sync.is_empty() {
+                tracing::warn!(
+                    "Vendored content is out of sync; run git vendor sync to repair it."
+                );
+                code = exit::DRIFT;
+            }
+        }
+
+        Commands::Prune { dry_run, .. } => {
+            let verb = if dry_run { "Would prune" } else { "Pruned" };
+            for dep in repo.vendor_prune_attributes(dry_run)? {
+                println!("{verb} {} ({})", dep.name, dep.pattern);
+            }
+        }
+
+        Commands::Unshallow { pattern } => {
+            let unshallowed = repo.vendor_unshallow(pattern.as_deref())?;
+            if unshallowed.is_empty() {
+                println!("No shallow dependencies");
+            }
+            for name in unshallowed {
+                println!("Unshallowed {name}");
+            }
+        }
+
+        Commands::Gc {
+            expire,
+            cache,
+            dry_run,
+        } => {
+            let report = repo.vendor_gc(&GcOpts {
+                expire,
+                cache,
+                dry_run,
+            })?;
+            let verb = if dry_run { "Would delete" } else { "Deleted" };
+            for reference in &report.deleted_refs {
+                println!("{verb} {reference}");
+            }
+            if report.expired_entries > 0 {
+                let verb = if dry_run { "Would expire" } else { "Expired" };
+                let plural = if report.expired_entries == 1 {
+                    "y"
+                } else {
+                    "ies"
+                };
+                println!("{verb} {} reflog entr{plural}", report.expired_entries);
+            }
+            if let Some((before, after)) = report.objects {
+                println!("Objects: {before} -> {after}");
+            }
+            for cache in &report.compacted {
+                println!("Compacted {}", cache.display());
+            }
         }
 
-        Commands::Fetch { pattern } => {
+        Commands::Status {
+            pattern,
+            format,
+            sort,
+            group_by,
+            check_remotes,
+        } => {
+            let opts = StatusOpts {
+                sort: sort.map(|sort| match sort {
+                    StatusSort::Name => git_vendor::StatusSort::Name,
+                    StatusSort::Behind => git_vendor::StatusSort::Behind,
+                    StatusSort::Size => git_vendor::StatusSort::Size,
+                    StatusSort::LastUpdated => git_vendor::StatusSort::LastUpdated,
+                }),
+                group_by_host: group_by == Some(GroupBy::Host),
+                check_remotes,
+            };
+            let deps = match format {
+                Some(format) => {
+                    let template = Template::parse(&format)?;
+                    if opts.sort.is_none() && !opts.group_by_host {
+                        let mut deps = Vec::new();
+                        for dep in repo.vendor_status_iter(pattern.as_deref())? {
+                            let dep = dep?;
+                            println!("{}", template.render(&dep));
+                            deps.push(dep);
+                        }
+                        deps
+                    } else {
+                        let mut deps = repo.vendor_overview(pattern.as_deref())?;
+                        if let Some(sort) = opts.sort {
+                            git_vendor::overview::sort(&mut deps, sort);
+                        }
+                        if opts.group_by_host {
+                            deps = git_vendor::overview::group_by_host(deps)
+                                .into_iter()
+                                .flat_map(|(_, deps)| deps)
+                                .collect();
+                        }
+                        for dep in &deps {
+                            println!("{}", template.render(dep));
+                        }
+                        deps
+                    }
+                }
+                None => repo.vendor_status(pattern.as_deref(), &opts)?,
+            };
+            if deps.iter().any(|dep| dep.pending > 0) {
+                code = exit::UPDATES_AVAILABLE;
+            }
+        }
+
+        Commands::Fetch {
+            pattern,
+            recursive: false,
+            ..
+        } => {
             repo.vendor_fetch(pattern.as_deref(), None)?;
         }
 
+        Commands::Fetch {
+            pattern,
+            recursive: true,
+            max_depth,
+            ..
+        } => {
+            let resolved = repo.vendor_fetch_recursive(pattern.as_deref(), max_depth)?;
+            if resolved.is_empty() {
+                println!("No transitive dependencies");
+            }
+            for dep in resolved {
+                print!("{} ({}) via {}", dep.name, dep.pattern, dep.parent);
+                match &dep.commit {
+                    Some(commit) => println!(" at {commit}"),
+                    None => println!(),
+                }
+            }
+        }
+
         Commands::Merge {
             pattern,
             no_commit,
             squash,
+            no_squash,
             message,
+            verify_signatures,
+            no_deepen,
+            stat,
+            confirm,
+            ..
         } => {
             let opts = VendorMergeOpts {
                 no_commit,
                 squash,
+                no_squash,
                 message,
+                verify_signatures,
+                no_deepen,
+                stat,
+                confirm: confirm.then_some(confirm_merge as fn(&MergeStat) -> bool),
             };
             repo.vendor_merge(pattern.as_deref(), &opts, None)?;
         }
+
+        Commands::Outdated {
+            pattern,
+            fetch,
+            stale,
+            json,
+            output,
+        } => {
+            if let Some(OutputFormat::Github) = output {
+                if fetch {
+                    repo.vendor_fetch(pattern.as_deref(), None)?;
+                }
+                let kinds = [AnnotationKind::Outdated];
+                let annotations = repo.vendor_annotations(pattern.as_deref(), &kinds)?;
+                return Ok(annotations_code(&annotations));
+            }
+            if let Some(max_age) = stale {
+                if fetch {
+                    repo.vendor_fetch(pattern.as_deref(), None)?;
+                }
+                let stale = repo.vendor_stale(pattern.as_deref(), max_age)?;
+                if json {
+                    println!("{}", serde_json::to_string_pretty(&stale)?);
+                } else {
+                    for dep in &stale {
+                        match dep.age_days {
+                            Some(days) => println!("{}: last updated {days} days ago", dep.name),
+                            None => println!("{}: never merged", dep.name),
+                        }
+                    }
+                }
+                if !stale.is_empty() {
+                    code = exit::UPDATES_AVAILABLE;
+                }
+                return Ok(code);
+            }
+            let outdated = if fetch {
+                repo.vendor_outdated_remote(pattern.as_deref())?
+            } else {
+                repo.vendor_outdated(pattern.as_deref())?
+            };
+            if json {
+                println!("{}", serde_json::to_string_pretty(&outdated)?);
+            } else {
+                for dep in &outdated {
+                    let plural = if dep.new_commits == 1 { "" } else { "s" };
+                    print!("{}: {} new commit{plural}", dep.name, dep.new_commits);
+                    if let Some(tag) = &dep.latest_tag {
+                        print!(" (latest tag {tag})");
+                    }
+                    println!();
+                }
+            }
+            if !outdated.is_empty() {
+                code = exit::UPDATES_AVAILABLE;
+            }
+        }
+
+        Commands::Tags { dep, remote, json } => {
+            let tags = repo.vendor_tags(&dep, remote)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&tags)?);
+            } else {
+                for tag in &tags {
+                    let marker = if tag.vendored { '*' } else { ' ' };
+                    println!("{marker} {}", tag.name);
+                }
+            }
+        }
+
+        Commands::Changelog {
+            dep,
+            markdown,
+            notes,
+        } => {
+            let changelog = repo.vendor_changelog(&dep, notes)?;
+            if markdown {
+                print!("{}", changelog.to_markdown());
+            } else {
+                print!("{}", changelog.to_text());
+            }
+        }
+
+        Commands::Bisect {
+            dep,
+            command,
+            good,
+            bad,
+            json,
+        } => {
+            let bisection = repo.vendor_bisect(&dep, good.as_deref(), bad.as_deref(), &command)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&bisection)?);
+            } else {
+                println!(
+                    "First bad commit of {}: {} {}",
+                    bisection.name, bisection.first_bad, bisection.summary
+                );
+                if !bisection.maybe_first_bad.is_empty() {
+                    println!("Skipped commits that may be the first bad one instead:");
+                    for commit in &bisection.maybe_first_bad {
+                        println!("  {commit}");
+                    }
+                }
+            }
+        }
+
+        Commands::Diff {
+            dep,
+            range,
+            upstream,
+        } => {
+            let patch = match range {
+                Some((from, to)) => {
+                    repo.vendor_diff_revisions(&dep, from.as_deref(), to.as_deref())?
+                }
+                None => repo.vendor_diff(&dep, upstream.as_deref())?,
+            };
+            print!("{patch}");
+        }
+
+        Commands::Blame { path, json } => {
+            let blame = repo.vendor_blame(&path)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&blame)?);
+            } else {
+                print!("{}", blame.to_text());
+            }
+        }
+
+        Commands::Watch {
+            pattern,
+            interval,
+            json,
+            exec,
+            once,
+        } => {
+            watch(
+                &repo,
+                pattern.as_deref(),
+                Duration::from_secs(interval as u64),
+                json,
+                exec.as_deref(),
+                once,
+            )?;
+        }
+
+        Commands::Explain { dep, json } => {
+            let explanation = repo.vendor_explain(&dep)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&explanation)?);
+            } else {
+                let width = explanation
+                    .values
+                    .iter()
+                    .map(|resolved| resolved.key.len())
+                    .max()
+                    .unwrap_or(0);
+                println!("{}", explanation.name);
+                for resolved in &explanation.values {
+                    println!(
+                        "  {:width$}  {}  ({})",
+                        resolved.key, resolved.value, resolved.source
+                    );
+                }
+            }
+        }
+
+        Commands::Verify {
+            pattern,
+            output: Some(OutputFormat::Github),
+        } => {
+            let kinds = [AnnotationKind::Drift, AnnotationKind::Policy];
+            let annotations = repo.vendor_annotations(pattern.as_deref(), &kinds)?;
+            code = annotations_code(&annotations);
+        }
+
+        Commands::Verify {
+            pattern,
+            output: None,
+        } => {
+            repo.vendor_verify(pattern.as_deref())?;
+        }
+
+        Commands::Foreach { pattern, command } => {
+            repo.vendor_foreach(pattern.as_deref(), &command)?;
+        }
+
+        Commands::InstallHooks { warn, force } => {
+            let hook = repo.vendor_install_hooks(!warn, force)?;
+            println!("Installed {}", hook.display());
+        }
+
+        Commands::Completions { .. } => unreachable!("handled before opening the repository"),
+
+        Commands::Setup { global, force } => {
+            for step in repo.vendor_setup(&SetupOpts { global, force })? {
+                match (step.changed, step.skipped) {
+                    (true, _) => println!("Configured {}", step.what),
+                    (false, Some(reason)) => println!("Skipped {}: {reason}", step.what),
+                    (false, None) => println!("Already configured {}", step.what),
+                }
+            }
+        }
+
+        Commands::PreCommit { block } => {
+            let edits = repo.vendor_staged_edits()?;
+            if !edits.is_empty() {
+                for edit in &edits {
+                    tracing::warn!(
+                        path = %edit.path,
+                        dep = %edit.dep,
+                        "Staged change modifies a vendored dependency"
+                    );
+                }
+                tracing::warn!(
+                    "Edit upstream instead, or set {}=1 to commit anyway.",
+                    git_vendor::guard::ALLOW_EDITS_ENV
+                );
+                if block {
+                    code = exit::DRIFT;
+                }
+            }
+        }
+
+        #[cfg(feature = "tui")]
+        Commands::Ui { pattern } => ui::run(&repo, pattern.as_deref())?,
+
+        Commands::Codeowners { check } => {
+            let path = repo.vendor_codeowners(check)?;
+            if !check {
+                println!("Updated {}", path.display());
+            }
+        }
+
+        Commands::CargoPatch { check } => {
+            let path = repo.vendor_cargo_patch(check)?;
+            if !check {
+                println!("Updated {}", path.display());
+            }
+        }
+
+        Commands::Sbom {
+            pattern,
+            format,
+            output,
+        } => {
+            let format = match format {
+                SbomFormat::CycloneDx => git_vendor::SbomFormat::CycloneDx,
+            };
+            let document = repo.vendor_sbom(pattern.as_deref(), format)?;
+            match output {
+                Some(path) => fs::write(path, document + "\n")?,
+                None => println!("{document}"),
+            }
+        }
+
+        Commands::Graph {
+            pattern,
+            format,
+            max_depth,
+        } => {
+            let format = match format {
+                GraphFormat::Dot => git_vendor::GraphFormat::Dot,
+                GraphFormat::Json => git_vendor::GraphFormat::Json,
+            };
+            print!(
+                "{}",
+                repo.vendor_graph(pattern.as_deref(), max_depth, format)?
+            );
+        }
+
+        Commands::Check {
+            pattern,
+            max_depth,
+            strict,
+        } => {
+            let issues = repo.vendor_check_graph(pattern.as_deref(), max_depth)?;
+            if issues.is_empty() {
+                println!("No problems found in the vendor graph");
+            }
+            for issue in &issues {
+                println!("{issue}");
+            }
+            if strict && !issues.is_empty() {
+                tracing::error!("{} problems found in the vendor graph", issues.len());
+                code = exit::CONFLICTS;
+            }
+        }
+
+        Commands::Migrate { to } => {
+            let (to, file) = match to {
+                SourceFormat::Toml => (git_vendor::SourceFormat::Manifest, ".gitvendor.toml"),
+                SourceFormat::Attributes => {
+                    (git_vendor::SourceFormat::Attributes, ".gitattributes")
+                }
+            };
+            let count = repo.vendor_migrate(to)?;
+            let plural = if count == 1 { "y" } else { "ies" };
+            println!("Migrated {count} dependenc{plural} to {file}");
+        }
+
+        Commands::Import {
+            from,
+            path,
+            dry_run,
+        } => {
+            let imported = match from {
+                ImportFormat::Cargo => {
+                    let path = path.unwrap_or_else(|| "Cargo.lock".into());
+                    repo.vendor_import_cargo(&path, dry_run)?
+                }
+                ImportFormat::Gomod => {
+                    let path = path.unwrap_or_else(|| "go.mod".into());
+                    repo.vendor_import_gomod(&path, dry_run)?
+                }
+                ImportFormat::Npm => {
+                    let path = path.unwrap_or_else(|| {
+                        ["package-lock.json", "yarn.lock"]
+                            .into_iter()
+                            .map(PathBuf::from)
+                            .find(|path| path.exists())
+                            .unwrap_or_else(|| "package-lock.json".into())
+                    });
+                    repo.vendor_import_npm(&path, dry_run)?
+                }
+            };
+            if imported.is_empty() {
+                println!("Nothing to import");
+            }
+            for dep in &imported {
+                println!("{}  {} @ {}", dep.name, dep.url, dep.rev);
+                for (package, pattern) in &dep.packages {
+                    println!("  {package}  {pattern}");
+                }
+                for (package, reason) in &dep.skipped {
+                    println!("  {package}  skipped: {reason}");
+                }
+                for copy in &dep.replaces {
+                    println!("  replaces {}", copy.display());
+                }
+            }
+            let count = imported
+                .iter()
+                .filter(|dep| !dep.packages.is_empty())
+                .count();
+            if count > 0 && !dry_run {
+                let plural = if count == 1 { "y" } else { "ies" };
+                println!(
+                    "Imported {count} dependenc{plural}; commit .gitattributes, then run git vendor fetch and git vendor merge"
+                );
+            }
+        }
+
+        Commands::Export {
+            to: ExportFormat::Gomod,
+            path,
+            check,
+        } => {
+            let path = match path {
+                Some(path) => path,
+                None => repo
+                    .workdir()
+                    .ok_or_else(|| git::Error::from_str("Repository has no working directory"))?
+                    .join("vendor/modules.txt"),
+            };
+            repo.vendor_export_gomod(&path, check)?;
+            if !check {
+                println!("Updated {}", path.display());
+            }
+        }
     }
 
+    Ok(code)
+}
+
+/// Ask on the terminal whether to apply the merge summarized by `stat`.
+fn confirm_merge(stat: &MergeStat) -> bool {
+    eprint!("Apply the merge of {}? [y/N] ", stat.name);
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim(), "y" | "Y" | "yes" | "Yes")
+}
+
+/// Ask on the terminal for the upstream of `candidate`, offering the
+/// guessed URL; `None` to leave it untracked.
+fn ask_upstream(candidate: &Candidate) -> Option<String> {
+    match &candidate.url {
+        Some(url) => eprint!("Track {} from {url}? [Y/n/URL] ", candidate.pattern),
+        None => eprint!("Upstream URL of {} (empty to skip): ", candidate.pattern),
+    }
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer).ok()?;
+    match (answer.trim(), &candidate.url) {
+        ("" | "y" | "Y" | "yes" | "Yes", Some(url)) => Some(url.clone()),
+        ("" | "n" | "N" | "no" | "No", _) => None,
+        (url, _) => Some(url.to_string()),
+    }
+}
+
+/// Print `annotations` as GitHub Actions workflow commands, returning the
+/// highest exit code among them.
+fn annotations_code(annotations: &[Annotation]) -> i32 {
+    let mut code = exit::SUCCESS;
+    for annotation in annotations {
+        println!("{}", annotation.to_github());
+        code = code.max(match annotation.kind {
+            AnnotationKind::Outdated => exit::UPDATES_AVAILABLE,
+            // Like `verify`, only fail on errors, not on missing records.
+            _ if annotation.level == Level::Warning => continue,
+            AnnotationKind::Drift => exit::DRIFT,
+            AnnotationKind::Policy => exit::CONFIG,
+        });
+    }
+    code
+}
+
+/// Poll vendor sources, reporting each new upstream tip once.
+fn watch(
+    repo: &git::Repository,
+    pattern: Option<&str>,
+    interval: Duration,
+    json: bool,
+    exec: Option<&str>,
+    once: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    git_vendor::set_quiet(true);
+    let mut reported: HashMap<String, String> = HashMap::new();
+
+    loop {
+        match repo
+            .vendor_fetch(pattern, None)
+            .and_then(|()| repo.vendor_outdated(pattern))
+        {
+            Ok(outdated) => {
+                for dep in outdated {
+                    if reported.get(&dep.name) == Some(&dep.latest) {
+                        continue;
+                    }
+                    report_update(&dep, json)?;
+                    if let Some(cmd) = exec {
+                        run_update_command(cmd, &dep);
+                    }
+                    reported.insert(dep.name.clone(), dep.latest.clone());
+                }
+            }
+            Err(e) => log_error(&e),
+        }
+
+        if once {
+            return Ok(());
+        }
+        thread::sleep(interval);
+    }
+}
+
+fn report_update(dep: &Outdated, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    if json {
+        let mut event = serde_json::to_value(dep)?;
+        event["event"] = "update".into();
+        println!("{event}");
+    } else {
+        let plural = if dep.new_commits == 1 { "" } else { "s" };
+        println!(
+            "{}: {} new commit{plural} (now at {})",
+            dep.name, dep.new_commits, dep.latest
+        );
+    }
     Ok(())
 }
+
+/// Run `cmd` through the shell with the update described in the environment.
+fn run_update_command(cmd: &str, dep: &Outdated) {
+    let status = process::Command::new("sh")
+        .arg("-c")
+        .arg(cmd)
+        .env("GIT_VENDOR_NAME", &dep.name)
+        .env("GIT_VENDOR_PATTERN", &dep.pattern)
+        .env("GIT_VENDOR_MERGED", dep.merged.as_deref().unwrap_or(""))
+        .env("GIT_VENDOR_LATEST", &dep.latest)
+        .env("GIT_VENDOR_NEW_COMMITS", dep.new_commits.to_string())
+        .env(
+            "GIT_VENDOR_LATEST_TAG",
+            dep.latest_tag.as_deref().unwrap_or(""),
+        )
+        .status();
+    match status {
+        Ok(status) if !status.success() => tracing::error!("'{cmd}' exited with {status}"),
+        Err(e) => tracing::error!("Failed to run '{cmd}': {e}"),
+        Ok(_) => {}
+    }
+}
@@ -0,0 +1,397 @@
+//! The `.gitvendor.toml` manifest format.
+//!
+//! Each dependency is a `[[dependency]]` table whose keys mirror the
+//! attributes of a `.gitattributes` vendor line:
+//!
+//! ```toml
+//! [[dependency]]
+//! name = "owner/repo"
+//! pattern = "vendor/repo/"
+//! url = "https://github.com/owner/repo.git"
+//! branch = "main"
+//! depth = 1
+//! ```
+//!
+//! Tables sharing a `name` and `url` are one dependency vendored at several
+//! patterns; the first table's other keys apply to all of them.
+//!
+//! Only the subset of TOML needed for this layout is understood: comments,
+//! `[[dependency]]` headers, and `key = value` pairs whose values are
+//! strings, booleans or integers.
+
+use super::VendorDep;
+use git2::Error;
+
+/// File name of the manifest, relative to the repository root.
+pub const MANIFEST_FILE: &str = ".gitvendor.toml";
+
+/// A parsed TOML value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Value {
+    String(String),
+    Bool(bool),
+    Integer(i64),
+}
+
+/// A `key = value` pair and the line it is on.
+type Field = (usize, String, Value);
+
+/// Parse the dependencies declared in `content`.
+pub(crate) fn parse(content: &str) -> Result<Vec<VendorDep>, Error> {
+    let mut deps = Vec::new();
+    let mut current: Option<(usize, Vec<Field>)> = None;
+
+    for (i, line) in content.lines().enumerate() {
+        let lineno = i + 1;
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if line.starts_with('[') {
+            if line != "[[dependency]]" {
+                return Err(syntax(lineno, &format!("unexpected table {line}")));
+            }
+            if let Some((start, fields)) = current.take() {
+                deps.push(dependency(start, fields)?);
+            }
+            current = Some((lineno, Vec::new()));
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(syntax(lineno, "expected key = value"));
+        };
+        let key = key.trim();
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(syntax(lineno, &format!("invalid key '{key}'")));
+        }
+        let value = parse_value(value.trim()).map_err(|msg| syntax(lineno, &msg))?;
+        let Some((_, fields)) = current.as_mut() else {
+            return Err(syntax(lineno, "key outside of a [[dependency]] table"));
+        };
+        if fields.iter().any(|(_, k, _)| k == key) {
+            return Err(syntax(lineno, &format!("duplicate key '{key}'")));
+        }
+        fields.push((lineno, key.to_string(), value));
+    }
+    if let Some((start, fields)) = current {
+        deps.push(dependency(start, fields)?);
+    }
+
+    super::group_patterns(deps)
+}
+
+/// The 1-based line of the `[[dependency]]` header of the table whose
+/// `pattern` is `pattern`.
+pub(crate) fn table_line(content: &str, pattern: &str) -> Option<usize> {
+    let mut header = None;
+    for (i, line) in content.lines().enumerate() {
+        let line = strip_comment(line).trim();
+        if line == "[[dependency]]" {
+            header = Some(i + 1);
+            continue;
+        }
+        if let Some((key, value)) = line.split_once('=')
+            && key.trim() == "pattern"
+            && parse_value(value.trim()) == Ok(Value::String(pattern.to_string()))
+        {
+            return header;
+        }
+    }
+    None
+}
+
+/// Render `deps` as a manifest.
+pub(crate) fn render(deps: &[VendorDep]) -> String {
+    let mut out = String::from("# Vendored dependencies managed by git-vendor.\n");
+    for dep in deps {
+        out.push_str("\n[[dependency]]\n");
+        let mut string = |key: &str, value: &str| {
+            out.push_str(&format!("{key} = {}\n", quote(value)));
+        };
+        string("name", &dep.name);
+        string("pattern", &dep.pattern);
+        string("url", &dep.url);
+        if let Some(branch) = &dep.branch {
+            string("branch", branch);
+        }
+        if let Some(rev) = &dep.rev {
+            string("rev", rev);
+        }
+        if let Some(tree) = &dep.tree {
+            string("tree", tree);
+        }
+        if let Some(prefix) = &dep.prefix {
+            string("prefix", prefix);
+        }
+        if let Some(codeowners) = &dep.codeowners {
+            string("codeowners", codeowners);
+        }
+        for (key, set) in [
+            ("verify-signatures", dep.verify_signatures),
+            ("honor-export-ignore", dep.honor_export_ignore),
+            ("import-attributes", dep.import_attributes),
+        ] {
+            if set {
+                out.push_str(&format!("{key} = true\n"));
+            }
+        }
+        if let Some(depth) = dep.depth {
+            out.push_str(&format!("depth = {depth}\n"));
+        }
+        if let Some(fetch_tags) = dep.fetch_tags {
+            out.push_str(&format!("fetch-tags = {fetch_tags}\n"));
+        }
+        for pattern in &dep.extra_patterns {
+            out.push_str(&format!(
+                "\n[[dependency]]\nname = {}\npattern = {}\nurl = {}\n",
+                quote(&dep.name),
+                quote(pattern),
+                quote(&dep.url)
+            ));
+        }
+    }
+    out
+}
+
+fn dependency(table: usize, fields: Vec<Field>) -> Result<VendorDep, Error> {
+    let mut dep = VendorDep::default();
+    let (mut name, mut pattern, mut url) = (None, None, None);
+
+    for (lineno, key, value) in fields {
+        let invalid = |expected: &str| syntax(lineno, &format!("'{key}' must be {expected}"));
+        match (key.as_str(), value) {
+            ("name", Value::String(v)) => name = Some(v),
+            ("pattern", Value::String(v)) => pattern = Some(v),
+            ("url", Value::String(v)) => url = Some(v),
+            ("branch", Value::String(v)) => dep.branch = Some(v),
+            ("rev", Value::String(v)) => dep.rev = Some(v),
+            ("tree", Value::String(v)) => dep.tree = Some(v),
+            ("prefix", Value::String(v)) => dep.prefix = Some(v),
+            ("codeowners", Value::String(v)) => dep.codeowners = Some(v),
+            ("verify-signatures", Value::Bool(v)) => dep.verify_signatures = v,
+            ("honor-export-ignore", Value::Bool(v)) => dep.honor_export_ignore = v,
+            ("import-attributes", Value::Bool(v)) => dep.import_attributes = v,
+            ("fetch-tags", Value::Bool(v)) => dep.fetch_tags = Some(v),
+            ("depth", Value::Integer(v)) => {
+                dep.depth = Some(u32::try_from(v).map_err(|_| invalid("a non-negative integer"))?)
+            }
+            (
+                "name" | "pattern" | "url" | "branch" | "rev" | "tree" | "prefix" | "codeowners",
+                _,
+            ) => {
+                return Err(invalid("a string"));
+            }
+            (
+                "verify-signatures" | "honor-export-ignore" | "import-attributes" | "fetch-tags",
+                _,
+            ) => {
+                return Err(invalid("a boolean"));
+            }
+            ("depth", _) => return Err(invalid("an integer")),
+            (other, _) => {
+                return Err(syntax(lineno, &format!("unknown key '{other}'")));
+            }
+        }
+    }
+
+    let missing = |key: &str| syntax(table, &format!("dependency is missing '{key}'"));
+    dep.name = name.ok_or_else(|| missing("name"))?;
+    dep.pattern = pattern.ok_or_else(|| missing("pattern"))?;
+    dep.url = url.ok_or_else(|| missing("url"))?;
+    Ok(dep)
+}
+
+fn syntax(lineno: usize, msg: &str) -> Error {
+    Error::from_str(&format!("{MANIFEST_FILE}:{lineno}: {msg}"))
+}
+
+/// Drop a trailing `#` comment that is not inside a string.
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (Some('"'), '\\') if !escaped => {
+                escaped = true;
+                continue;
+            }
+            (Some(q), c) if c == q && !escaped => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '#') => return &line[..i],
+            _ => {}
+        }
+        escaped = false;
+    }
+    line
+}
+
+fn parse_value(value: &str) -> Result<Value, String> {
+    match value {
+        "true" => return Ok(Value::Bool(true)),
+        "false" => return Ok(Value::Bool(false)),
+        _ => {}
+    }
+    if value.starts_with('"') || value.starts_with('\'') {
+        let (s, rest) = parse_string(value)?;
+        if !rest.trim().is_empty() {
+            return Err(format!("unexpected '{}' after string", rest.trim()));
+        }
+        return Ok(Value::String(s));
+    }
+    value
+        .replace('_', "")
+        .parse()
+        .map(Value::Integer)
+        .map_err(|_| format!("unsupported value '{value}'"))
+}
+
+/// Parse a basic (`"..."`) or literal (`'...'`) string at the start of
+/// `input`, returning it and the remaining input.
+fn parse_string(input: &str) -> Result<(String, &str), String> {
+    let mut chars = input.char_indices();
+    let quote = match chars.next() {
+        Some((_, q @ ('"' | '\''))) => q,
+        _ => return Err(format!("expected a string at '{input}'")),
+    };
+
+    let mut out = String::new();
+    while let Some((i, c)) = chars.next() {
+        if c == quote {
+            return Ok((out, &input[i + 1..]));
+        }
+        if c != '\\' || quote == '\'' {
+            out.push(c);
+            continue;
+        }
+        match chars.next().map(|(_, c)| c) {
+            Some('"') => out.push('"'),
+            Some('\\') => out.push('\\'),
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some('u') => {
+                let hex: String = chars.by_ref().take(4).map(|(_, c)| c).collect();
+                let c = u32::from_str_radix(&hex, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| format!("invalid escape \\u{hex}"))?;
+                out.push(c);
+            }
+            Some(other) => return Err(format!("invalid escape \\{other}")),
+            None => break,
+        }
+    }
+    Err("unterminated string".into())
+}
+
+fn quote(value: &str) -> String {
+    let mut out = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_dependencies() {
+        let content = r#"
+# Vendored dependencies
+[[dependency]]
+name = "owner/repo"   # inline comment
+pattern = 'vendor/repo/'
+url = "https://example.com/owner/repo.git"
+branch = "main"
+honor-export-ignore = true
+depth = 1
+
+[[dependency]]
+name = "other/lib"
+pattern = "lib/#1/"
+url = "/srv/git/lib"
+"#;
+        let deps = parse(content).unwrap();
+        assert_eq!(deps.len(), 2);
+        assert_eq!(deps[0].name, "owner/repo");
+        assert_eq!(deps[0].pattern, "vendor/repo/");
+        assert_eq!(deps[0].branch.as_deref(), Some("main"));
+        assert!(deps[0].honor_export_ignore);
+        assert_eq!(deps[0].depth, Some(1));
+        assert_eq!(deps[1].pattern, "lib/#1/");
+        assert_eq!(deps[1].branch, None);
+    }
+
+    #[test]
+    fn render_round_trips() {
+        let deps = vec![VendorDep {
+            name: "owner/repo".into(),
+            pattern: "vendor/\"odd\" dir/".into(),
+            url: "https://example.com/owner/repo.git".into(),
+            branch: Some("main".into()),
+            codeowners: Some("@team,@me".into()),
+            verify_signatures: true,
+            depth: Some(3),
+            fetch_tags: Some(false),
+            ..Default::default()
+        }];
+        let rendered = render(&deps);
+        assert!(rendered.contains("pattern = \"vendor/\\\"odd\\\" dir/\"\n"));
+        assert_eq!(parse(&rendered).unwrap(), deps);
+    }
+
+    #[test]
+    fn table_line_finds_header_of_pattern() {
+        let content = "# deps\n\n[[dependency]]\nname = \"a/b\"\npattern = \"a/\"\n\n\
+                       [[dependency]]\npattern = \"b/\" # second\n";
+        assert_eq!(table_line(content, "a/"), Some(3));
+        assert_eq!(table_line(content, "b/"), Some(7));
+        assert_eq!(table_line(content, "c/"), None);
+    }
+
+    #[test]
+    fn parse_rejects_invalid_manifests() {
+        let err = parse("[[dependency]]\nname = \"a/b\"\nurl = \"u\"\n").unwrap_err();
+        assert!(
+            err.message().contains("missing 'pattern'"),
+            "{}",
+            err.message()
+        );
+
+        let err = parse("[[dependency]]\nnmae = \"a/b\"\n").unwrap_err();
+        assert!(err.message().contains(":2: unknown key 'nmae'"));
+
+        assert!(parse("name = \"a/b\"\n").is_err());
+        assert!(parse("[deps]\n").is_err());
+        assert!(parse("[[dependency]]\nname = \"a/b\n").is_err());
+        assert!(parse("[[dependency]]\ndepth = \"1\"\n").is_err());
+    }
+
+    #[test]
+    fn parse_value_kinds() {
+        assert_eq!(parse_value("1_000"), Ok(Value::Integer(1000)));
+        assert_eq!(
+            parse_value(r#"'C:\dir'"#),
+            Ok(Value::String(r"C:\dir".into()))
+        );
+        assert_eq!(
+            parse_value(r#""tab\there \u00e9""#),
+            Ok(Value::String("tab\there é".into()))
+        );
+        assert!(parse_value("\"a\" \"b\"").is_err());
+        assert!(parse_value("[\"a\"]").is_err());
+    }
+}
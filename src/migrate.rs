@@ -0,0 +1,289 @@
+//! Conversion between `.gitattributes` vendor lines and the manifest.
+
+use super::{VendorDep, manifest, parse_vendor_deps, source::MANIFEST_FILE};
+use git_set_attr::{TextFormat, quote_pattern, split_pattern};
+use git2::{Error, Repository};
+use std::{fs, path::Path};
+
+/// A format dependency definitions can be stored in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceFormat {
+    /// Vendor lines in `.gitattributes`.
+    Attributes,
+    /// A `.gitvendor.toml` manifest.
+    Manifest,
+}
+
+/// Attributes holding a dependency's definition rather than marking paths.
+const DEFINITION_KEYS: [&str; 9] = [
+    "name",
+    "url",
+    "branch",
+    "rev",
+    "tree",
+    "prefix",
+    "codeowners",
+    "depth",
+    "fetch-tags",
+];
+const DEFINITION_FLAGS: [&str; 3] = [
+    "verify-signatures",
+    "honor-export-ignore",
+    "import-attributes",
+];
+
+/// Move the dependency definitions of `repo` to `to`, returning how many
+/// were migrated.
+///
+/// The result is read back and compared with the original definitions;
+/// on any difference, both files are restored and an error is returned.
+pub(crate) fn migrate(
+    repo: &Repository,
+    gitattributes: &Path,
+    to: SourceFormat,
+) -> Result<usize, Error> {
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| Error::from_str("Repository has no working directory"))?;
+    let manifest_path = workdir.join(MANIFEST_FILE);
+    let attributes = read(gitattributes)?;
+    let format = TextFormat::detect(&attributes);
+
+    let (deps, new_attributes, new_manifest) = match (to, manifest_path.exists()) {
+        (SourceFormat::Manifest, true) | (SourceFormat::Attributes, false) => {
+            return Err(Error::from_str(&format!(
+                "Dependencies are already defined in {}",
+                match to {
+                    SourceFormat::Manifest => MANIFEST_FILE,
+                    SourceFormat::Attributes => ".gitattributes",
+                }
+            )));
+        }
+        (SourceFormat::Manifest, false) => {
+            let deps = parse_vendor_deps(gitattributes)?;
+            let stripped = format.apply(&strip_definitions(&attributes));
+            (deps.clone(), stripped, Some(manifest::render(&deps)))
+        }
+        (SourceFormat::Attributes, true) => {
+            let deps = manifest::parse(&read(&manifest_path)?)?;
+            let added = format.apply(&add_definitions(&attributes, &deps)?);
+            (deps.clone(), added, None)
+        }
+    };
+
+    write(gitattributes, &new_attributes)?;
+    match &new_manifest {
+        Some(content) => write(&manifest_path, content)?,
+        None => fs::remove_file(&manifest_path).map_err(|e| {
+            Error::from_str(&format!(
+                "Failed to remove {}: {e}",
+                manifest_path.display()
+            ))
+        })?,
+    }
+
+    let migrated = match to {
+        SourceFormat::Manifest => manifest::parse(&read(&manifest_path)?)?,
+        SourceFormat::Attributes => parse_vendor_deps(gitattributes)?,
+    };
+    if migrated != deps {
+        write(gitattributes, &attributes)?;
+        if let SourceFormat::Attributes = to {
+            write(&manifest_path, &manifest::render(&deps))?;
+        } else {
+            let _ = fs::remove_file(&manifest_path);
+        }
+        return Err(Error::from_str(
+            "Migration would not preserve every dependency; nothing was changed",
+        ));
+    }
+
+    Ok(deps.len())
+}
+
+/// The attributes defining `dep` on a `.gitattributes` line.
+pub(crate) fn definition(dep: &VendorDep) -> Result<Vec<String>, Error> {
+    let mut attrs = vec![format!("name={}", dep.name), format!("url={}", dep.url)];
+    if let Some(branch) = &dep.branch {
+        attrs.push(format!("branch={branch}"));
+    }
+    if let Some(rev) = &dep.rev {
+        attrs.push(format!("rev={rev}"));
+    }
+    for (flag, set) in DEFINITION_FLAGS.iter().zip([
+        dep.verify_signatures,
+        dep.honor_export_ignore,
+        dep.import_attributes,
+    ]) {
+        if set {
+            attrs.push(flag.to_string());
+        }
+    }
+    if let Some(tree) = &dep.tree {
+        attrs.push(format!("tree={tree}"));
+    }
+    if let Some(prefix) = &dep.prefix {
+        attrs.push(format!("prefix={prefix}"));
+    }
+    if let Some(codeowners) = &dep.codeowners {
+        attrs.push(format!("codeowners={codeowners}"));
+    }
+    if let Some(depth) = dep.depth {
+        attrs.push(format!("depth={depth}"));
+    }
+    if let Some(fetch_tags) = dep.fetch_tags {
+        attrs.push(format!("fetch-tags={fetch_tags}"));
+    }
+
+    let unrepresentable = dep
+        .patterns()
+        .into_iter()
+        .chain(attrs.iter().map(String::as_str))
+        .find(|value| value.is_empty() || value.contains(char::is_whitespace));
+    if let Some(value) = unrepresentable {
+        return Err(Error::from_str(&format!(
+            "Cannot write '{value}' of {} as a .gitattributes attribute",
+            dep.name
+        )));
+    }
+    Ok(attrs)
+}
+
+fn is_definition(attr: &str) -> bool {
+    DEFINITION_FLAGS.contains(&attr)
+        || attr
+            .split_once('=')
+            .is_some_and(|(key, _)| DEFINITION_KEYS.contains(&key))
+}
+
+/// Remove definition attributes from every vendor line in `content`.
+fn strip_definitions(content: &str) -> String {
+    let mut out = String::new();
+    for line in content.lines() {
+        match split_pattern(line) {
+            Some((pattern, rest)) if rest.split_whitespace().any(|attr| attr == "vendored") => {
+                let kept: Vec<&str> = rest
+                    .split_whitespace()
+                    .filter(|attr| !is_definition(attr))
+                    .collect();
+                let pattern = quote_pattern(&pattern);
+                out.push_str(&format!("{pattern} {}\n", kept.join(" ")));
+            }
+            _ => {
+                out.push_str(line);
+                out.push('\n');
+            }
+        }
+    }
+    out
+}
+
+/// Add each dependency's definition to the first line marking its pattern
+/// `vendored`, appending a line where there is none.
+///
+/// The lines of a dependency's extra patterns only get its `name=`.
+fn add_definitions(content: &str, deps: &[VendorDep]) -> Result<String, Error> {
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    let mut definitions = Vec::new();
+    for dep in deps {
+        definitions.push((dep.pattern.as_str(), definition(dep)?));
+        for pattern in &dep.extra_patterns {
+            definitions.push((pattern.as_str(), vec![format!("name={}", dep.name)]));
+        }
+    }
+    for (pattern, attrs) in definitions {
+        let marking = lines.iter().position(|line| {
+            split_pattern(line).is_some_and(|(line_pattern, rest)| {
+                line_pattern == pattern && rest.split_whitespace().any(|attr| attr == "vendored")
+            })
+        });
+        let pattern = quote_pattern(pattern);
+        match marking {
+            Some(i) => {
+                let rest = split_pattern(&lines[i]).map_or("", |(_, rest)| rest);
+                let others: Vec<&str> = rest
+                    .split_whitespace()
+                    .filter(|attr| *attr != "vendored" && !is_definition(attr))
+                    .collect();
+                lines[i] = format!(
+                    "{pattern} vendored {} {}",
+                    attrs.join(" "),
+                    others.join(" ")
+                )
+                .trim_end()
+                .to_string();
+            }
+            None => lines.push(format!("{pattern} vendored {}", attrs.join(" "))),
+        }
+    }
+    Ok(lines.iter().map(|line| format!("{line}\n")).collect())
+}
+
+fn read(path: &Path) -> Result<String, Error> {
+    if !path.exists() {
+        return Ok(String::new());
+    }
+    fs::read_to_string(path)
+        .map_err(|e| Error::from_str(&format!("Failed to read {}: {e}", path.display())))
+}
+
+fn write(path: &Path, content: &str) -> Result<(), Error> {
+    fs::write(path, content)
+        .map_err(|e| Error::from_str(&format!("Failed to write {}: {e}", path.display())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_definitions_keeps_marks() {
+        let content = "\
+# comment name=x
+vendor/a/ vendored name=o/a url=https://a.com/o/a.git branch=main depth=1 linguist-vendored
+*.txt diff
+";
+        assert_eq!(
+            strip_definitions(content),
+            "# comment name=x\nvendor/a/ vendored linguist-vendored\n*.txt diff\n"
+        );
+    }
+
+    #[test]
+    fn add_definitions_fills_marking_lines() {
+        let deps = vec![
+            VendorDep {
+                name: "o/a".into(),
+                pattern: "vendor/a/".into(),
+                url: "https://a.com/o/a.git".into(),
+                branch: Some("main".into()),
+                honor_export_ignore: true,
+                ..Default::default()
+            },
+            VendorDep {
+                name: "o/b".into(),
+                pattern: "vendor/b/".into(),
+                url: "https://a.com/o/b.git".into(),
+                ..Default::default()
+            },
+        ];
+        let content = "vendor/a/ vendored linguist-vendored -diff\n";
+        assert_eq!(
+            add_definitions(content, &deps).unwrap(),
+            "vendor/a/ vendored name=o/a url=https://a.com/o/a.git branch=main \
+             honor-export-ignore linguist-vendored -diff\n\
+             vendor/b/ vendored name=o/b url=https://a.com/o/b.git\n"
+        );
+    }
+
+    #[test]
+    fn definition_rejects_whitespace() {
+        let dep = VendorDep {
+            name: "o/a".into(),
+            pattern: "vendor/my lib/".into(),
+            url: "u".into(),
+            ..Default::default()
+        };
+        assert!(definition(&dep).is_err());
+    }
+}
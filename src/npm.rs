@@ -0,0 +1,278 @@
+//! Importing git dependencies from `package-lock.json` and `yarn.lock`.
+//!
+//! npm and Yarn lock a git dependency to the commit it resolved, with the
+//! package's repository URL. Each such package becomes a dependency pinned
+//! to that commit whose upstream content is placed under
+//! `third_party/node/<name>/` with `prefix=`, since a package installed
+//! from git is the root of its repository.
+
+use super::{VendorDep, import::Imported, is_commit_id, name_from_url, source, vendor_ref_name};
+use git2::{Error, Repository};
+use serde_json::Value;
+use std::{collections::BTreeSet, fs, path::Path};
+
+/// The directory npm packages are vendored under.
+const NODE_DIR: &str = "third_party/node";
+
+/// A package resolved from git.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct GitPackage {
+    name: String,
+    url: String,
+    commit: String,
+}
+
+/// Import the git dependencies locked in the `package-lock.json` or
+/// `yarn.lock` at `path`, one per package, writing their definitions to
+/// `gitattributes` unless `dry_run`.
+pub(crate) fn import(
+    repo: &Repository,
+    gitattributes: &Path,
+    path: &Path,
+    dry_run: bool,
+) -> Result<Vec<Imported>, Error> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| Error::from_str(&format!("Failed to read {}: {e}", path.display())))?;
+    let mut packages = if path.ends_with("yarn.lock") {
+        yarn_packages(&content)
+    } else {
+        let lock: Value = serde_json::from_str(&content)
+            .map_err(|e| Error::from_str(&format!("Failed to parse {}: {e}", path.display())))?;
+        npm_packages(&lock)
+    };
+    // A package nested under several others is vendored once.
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+    packages.dedup_by(|a, b| a.name == b.name);
+
+    let config = super::config::Config::resolve(repo)?;
+    let valid = |name: &String| {
+        !name.contains(char::is_whitespace)
+            && git2::Reference::is_valid_name(&vendor_ref_name(&config.ref_prefix, name))
+    };
+    let source = source::open(repo, gitattributes)?;
+    let mut names = BTreeSet::new();
+    let mut imported = Vec::new();
+    for package in packages {
+        let name = name_from_url(&package.url)
+            .filter(|name| valid(name) && !names.contains(name))
+            .or_else(|| Some(package.name.clone()).filter(valid))
+            .ok_or_else(|| {
+                Error::from_str(&format!(
+                    "Cannot derive a dependency name for {}",
+                    package.name
+                ))
+            })?;
+        names.insert(name.clone());
+        let prefix = format!("{NODE_DIR}/{}", package.name);
+        let dep = VendorDep {
+            name,
+            pattern: format!("{prefix}/"),
+            url: package.url,
+            rev: Some(package.commit),
+            prefix: Some(prefix),
+            ..Default::default()
+        };
+        if !dry_run {
+            source.upsert(&dep, &[])?;
+        }
+        imported.push(Imported {
+            name: dep.name.clone(),
+            url: dep.url.clone(),
+            rev: dep.rev.clone().unwrap_or_default(),
+            branch: None,
+            packages: vec![(package.name, dep.pattern.clone())],
+            skipped: Vec::new(),
+            replaces: Vec::new(),
+        });
+    }
+    Ok(imported)
+}
+
+/// The git packages of a `package-lock.json`: from `packages` in lockfile
+/// versions 2 and 3, or the nested `dependencies` of version 1.
+fn npm_packages(lock: &Value) -> Vec<GitPackage> {
+    let mut packages = Vec::new();
+    if let Some(entries) = lock["packages"].as_object() {
+        for (key, entry) in entries {
+            if key.is_empty() {
+                continue;
+            }
+            let name = key.rsplit("node_modules/").next().unwrap_or(key);
+            if let Some(resolved) = entry["resolved"].as_str()
+                && let Some((url, commit)) = git_source(resolved)
+            {
+                packages.push(GitPackage {
+                    name: name.to_string(),
+                    url,
+                    commit,
+                });
+            }
+        }
+    } else {
+        collect_dependencies(&lock["dependencies"], &mut packages);
+    }
+    packages
+}
+
+fn collect_dependencies(dependencies: &Value, packages: &mut Vec<GitPackage>) {
+    let Some(dependencies) = dependencies.as_object() else {
+        return;
+    };
+    for (name, entry) in dependencies {
+        if let Some(version) = entry["version"].as_str()
+            && let Some((url, commit)) = git_source(version)
+        {
+            packages.push(GitPackage {
+                name: name.clone(),
+                url,
+                commit,
+            });
+        }
+        collect_dependencies(&entry["dependencies"], packages);
+    }
+}
+
+/// The git packages of a `yarn.lock`, in the format of Yarn 1 or of later
+/// versions.
+fn yarn_packages(content: &str) -> Vec<GitPackage> {
+    let mut packages = Vec::new();
+    let mut name = None;
+    for line in content.lines() {
+        if !line.starts_with([' ', '#']) && line.ends_with(':') {
+            // `"foo@git+https://…", foo@github:o/foo:` names the entry.
+            let spec = line.trim_end_matches(':').split(',').next().unwrap_or("");
+            name = package_name(spec.trim().trim_matches('"'));
+            continue;
+        }
+        let line = line.trim();
+        let resolved = line
+            .strip_prefix("resolved ")
+            .or_else(|| line.strip_prefix("resolution: "))
+            .map(|value| value.trim_matches('"'));
+        if let (Some(name), Some(resolved)) = (&name, resolved) {
+            // Yarn 2+ resolutions are prefixed with `<name>@`.
+            let resolved = resolved
+                .strip_prefix(name.as_str())
+                .and_then(|rest| rest.strip_prefix('@'))
+                .unwrap_or(resolved);
+            if let Some((url, commit)) = git_source(resolved) {
+                packages.push(GitPackage {
+                    name: name.clone(),
+                    url,
+                    commit,
+                });
+            }
+        }
+    }
+    packages
+}
+
+/// The package name of a `<name>@<range>` spec, including its scope.
+fn package_name(spec: &str) -> Option<String> {
+    let at = spec.get(1..)?.find('@')? + 1;
+    Some(spec[..at].to_string())
+}
+
+/// The repository URL and commit of a resolved git source, such as
+/// `git+ssh://git@github.com/o/r.git#<commit>`,
+/// `https://github.com/o/r.git#commit=<commit>` or a GitHub tarball URL.
+fn git_source(resolved: &str) -> Option<(String, String)> {
+    if let Some(rest) = resolved.strip_prefix("https://codeload.github.com/") {
+        let (repo, commit) = rest.split_once("/tar.gz/")?;
+        return is_commit_id(commit)
+            .then(|| (format!("https://github.com/{repo}"), commit.to_string()));
+    }
+    let (url, fragment) = resolved.split_once('#')?;
+    let commit = fragment
+        .split('&')
+        .map(|param| param.strip_prefix("commit=").unwrap_or(param))
+        .find(|param| is_commit_id(param))?;
+    let url = url.strip_prefix("git+").unwrap_or(url);
+    let git = url.starts_with("git://")
+        || url.starts_with("ssh://")
+        || url.starts_with("file://")
+        || url.ends_with(".git")
+        || url.starts_with("https://github.com/");
+    git.then(|| (url.to_string(), commit.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMMIT: &str = "0123456789abcdef0123456789abcdef01234567";
+
+    fn package(name: &str, url: &str) -> GitPackage {
+        GitPackage {
+            name: name.into(),
+            url: url.into(),
+            commit: COMMIT.into(),
+        }
+    }
+
+    #[test]
+    fn npm_packages_reads_lockfile_versions() {
+        let v3 = serde_json::json!({
+            "lockfileVersion": 3,
+            "packages": {
+                "": {"name": "host"},
+                "node_modules/left-pad": {
+                    "version": "1.3.0",
+                    "resolved": "https://registry.npmjs.org/left-pad/-/left-pad-1.3.0.tgz"
+                },
+                "node_modules/a/node_modules/@s/b": {
+                    "version": "1.0.0",
+                    "resolved": format!("git+ssh://git@github.com/o/b.git#{COMMIT}")
+                }
+            }
+        });
+        assert_eq!(
+            npm_packages(&v3),
+            [package("@s/b", "ssh://git@github.com/o/b.git")]
+        );
+
+        let v1 = serde_json::json!({
+            "lockfileVersion": 1,
+            "dependencies": {
+                "a": {
+                    "version": "1.0.0",
+                    "dependencies": {
+                        "c": {"version": format!("git+https://gitlab.com/o/c.git#{COMMIT}")}
+                    }
+                }
+            }
+        });
+        assert_eq!(
+            npm_packages(&v1),
+            [package("c", "https://gitlab.com/o/c.git")]
+        );
+    }
+
+    #[test]
+    fn yarn_packages_reads_classic_and_berry_entries() {
+        let classic = format!(
+            "# yarn lockfile v1\n\n\
+             \"@s/a@github:o/a\":\n  \
+             version \"1.0.0\"\n  \
+             resolved \"https://codeload.github.com/o/a/tar.gz/{COMMIT}\"\n\n\
+             left-pad@^1.3.0:\n  \
+             version \"1.3.0\"\n  \
+             resolved \"https://registry.yarnpkg.com/left-pad/-/left-pad-1.3.0.tgz#abc\"\n"
+        );
+        assert_eq!(
+            yarn_packages(&classic),
+            [package("@s/a", "https://github.com/o/a")]
+        );
+
+        let berry = format!(
+            "__metadata:\n  version: 8\n\n\
+             \"b@https://github.com/o/b.git\":\n  \
+             version: 1.0.0\n  \
+             resolution: \"b@https://github.com/o/b.git#commit={COMMIT}\"\n"
+        );
+        assert_eq!(
+            yarn_packages(&berry),
+            [package("b", "https://github.com/o/b.git")]
+        );
+    }
+}
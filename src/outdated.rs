@@ -0,0 +1,209 @@
+//! Detection of vendored dependencies with newer upstream content.
+
+use super::{
+    Provenance, VendorDep, config, forge::Forge, parse_time, provenance, semver::Version,
+    upstream_ref, vendor_ref_name, vendor_tags_ref_prefix,
+};
+use git2::{Error, Oid, Repository, Tree};
+use serde::Serialize;
+
+/// A dependency whose fetched upstream is ahead of its vendored content.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Outdated {
+    pub name: String,
+    pub pattern: String,
+    /// The upstream commit last merged, if the dependency has been merged.
+    pub merged: Option<String>,
+    /// The fetched upstream commit.
+    pub latest: String,
+    /// Number of upstream commits not yet merged.
+    pub new_commits: usize,
+    /// The most recent upstream tag reachable from `latest`.
+    pub latest_tag: Option<String>,
+}
+
+/// A dependency that has not been merged within a given window.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Stale {
+    pub name: String,
+    pub pattern: String,
+    /// When the dependency was last merged, if ever (RFC 3339, UTC).
+    pub merged_at: Option<String>,
+    /// Whole days since the last merge.
+    pub age_days: Option<i64>,
+    /// Who last merged the dependency, as `Name <email>`, if recorded.
+    pub merged_by: Option<String>,
+}
+
+/// `dep`'s record in `tree` and the seconds since it was merged.
+///
+/// Returns `None` when the dependency has never been merged.
+pub(crate) fn merged_age(
+    repo: &Repository,
+    tree: &Tree,
+    dep: &VendorDep,
+    now: i64,
+) -> Result<Option<(Provenance, i64)>, Error> {
+    let Some(record) = provenance::read(repo, tree, &dep.name)? else {
+        return Ok(None);
+    };
+    let merged = parse_time(&record.merged_at).ok_or_else(|| {
+        Error::from_str(&format!(
+            "Invalid merge time in provenance record: {}",
+            record.merged_at
+        ))
+    })?;
+    Ok(Some((record, now - merged)))
+}
+
+/// Check whether `dep` has gone at least `max_age` seconds without a merge.
+pub(crate) fn check_stale(
+    repo: &Repository,
+    tree: &Tree,
+    dep: &VendorDep,
+    now: i64,
+    max_age: i64,
+) -> Result<Option<Stale>, Error> {
+    let (merged_at, age, merged_by) = match merged_age(repo, tree, dep, now)? {
+        Some((_, age)) if age < max_age => return Ok(None),
+        Some((record, age)) => (Some(record.merged_at), Some(age / 86_400), record.merged_by),
+        None => (None, None, None),
+    };
+
+    Ok(Some(Stale {
+        name: dep.name.clone(),
+        pattern: dep.pattern.clone(),
+        merged_at,
+        age_days: age,
+        merged_by,
+    }))
+}
+
+/// Compare `dep`'s merged commit (from `tree`) with its fetched vendor ref.
+///
+/// Returns `None` when the dependency is up to date or has not been fetched.
+pub(crate) fn check(
+    repo: &Repository,
+    tree: &Tree,
+    dep: &VendorDep,
+) -> Result<Option<Outdated>, Error> {
+    let prefix = config::Config::resolve(repo)?.ref_prefix;
+    let Ok(latest) = repo.refname_to_id(&vendor_ref_name(&prefix, &dep.name)) else {
+        return Ok(None);
+    };
+
+    let merged = provenance::merged_commit(repo, tree, dep)?;
+    if merged == Some(latest) {
+        return Ok(None);
+    }
+
+    let mut walk = repo.revwalk()?;
+    walk.push(latest)?;
+    if let Some(merged) = merged
+        && repo.find_commit(merged).is_ok()
+    {
+        walk.hide(merged)?;
+    }
+    let new_commits = walk.count();
+    if new_commits == 0 {
+        return Ok(None);
+    }
+
+    Ok(Some(Outdated {
+        name: dep.name.clone(),
+        pattern: dep.pattern.clone(),
+        merged: merged.map(|oid| oid.to_string()),
+        latest: latest.to_string(),
+        new_commits,
+        latest_tag: latest_tag(repo, dep, latest)?,
+    }))
+}
+
+/// Compare `dep`'s merged commit (from `tree`) with the current upstream
+/// commit, asking `forge` instead of fetching.
+///
+/// Returns `None` when the dependency is up to date. Dependencies that were
+/// never merged cannot be compared this way and are an error.
+pub(crate) fn check_forge(
+    repo: &Repository,
+    tree: &Tree,
+    dep: &VendorDep,
+    forge: &Forge,
+) -> Result<Option<Outdated>, Error> {
+    let merged = provenance::read(repo, tree, &dep.name)?
+        .and_then(|record| Oid::from_str(&record.commit).ok())
+        .ok_or_else(|| Error::from_str(&format!("{} has not been merged", dep.name)))?;
+
+    let reference = upstream_ref(dep);
+    let reference = match reference.strip_prefix("refs/heads/") {
+        Some(branch) => branch.to_string(),
+        None if reference == "HEAD" => forge
+            .repository()?
+            .map(|(default_branch, _)| default_branch)
+            .ok_or_else(|| Error::from_str(&format!("{} not found upstream", dep.name)))?,
+        None => reference
+            .strip_prefix("refs/tags/")
+            .unwrap_or(&reference)
+            .to_string(),
+    };
+    let latest = forge
+        .resolve(&reference)?
+        .ok_or_else(|| Error::from_str(&format!("{reference} not found upstream")))?;
+    if latest == merged {
+        return Ok(None);
+    }
+    let new_commits = forge.ahead_by(merged, latest)?;
+    if new_commits == 0 {
+        return Ok(None);
+    }
+
+    // The highest version the forge lists, if `latest` contains it.
+    let tags = forge.tags()?;
+    let newest = tags
+        .iter()
+        .filter_map(|(name, commit)| Some((Version::parse(name)?, name, *commit)))
+        .max_by(|a, b| a.0.cmp(&b.0))
+        .map(|(_, name, commit)| (name, commit))
+        .or_else(|| tags.first().map(|(name, commit)| (name, *commit)));
+    let latest_tag = match newest {
+        Some((name, commit)) if commit == latest || forge.ahead_by(latest, commit)? == 0 => {
+            Some(name.clone())
+        }
+        _ => None,
+    };
+
+    Ok(Some(Outdated {
+        name: dep.name.clone(),
+        pattern: dep.pattern.clone(),
+        merged: Some(merged.to_string()),
+        latest: latest.to_string(),
+        new_commits,
+        latest_tag,
+    }))
+}
+
+/// The fetched upstream tag on the newest commit reachable from `tip`.
+fn latest_tag(repo: &Repository, dep: &VendorDep, tip: Oid) -> Result<Option<String>, Error> {
+    let prefix = vendor_tags_ref_prefix(&dep.name);
+    let mut best: Option<(i64, String)> = None;
+
+    for reference in repo.references_glob(&format!("{prefix}*"))? {
+        let reference = reference?;
+        let Some(tag) = reference.name().and_then(|n| n.strip_prefix(&prefix)) else {
+            continue;
+        };
+        let Ok(commit) = reference.peel_to_commit() else {
+            continue;
+        };
+        if commit.id() != tip && !repo.graph_descendant_of(tip, commit.id())? {
+            continue;
+        }
+
+        let time = commit.time().seconds();
+        if best.as_ref().is_none_or(|(t, _)| time > *t) {
+            best = Some((time, tag.to_string()));
+        }
+    }
+
+    Ok(best.map(|(_, tag)| tag))
+}
@@ -0,0 +1,307 @@
+//! A per-dependency summary combining freshness, dirtiness and pending
+//! upstream commits.
+
+use super::{
+    VendorDep, config, outdated, provenance, split_remote_url, status_cache::StatusCache,
+    vendor_gone_ref_name, vendor_ref_name, verify,
+};
+use git_filter_tree::{FilterTree, walk_tree};
+use git2::{Commit, Error, ObjectType, Repository, Tree};
+use serde::{Deserialize, Serialize};
+
+/// The state of one vendored dependency at a glance.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Overview {
+    pub name: String,
+    pub pattern: String,
+    pub url: String,
+    pub branch: Option<String>,
+    /// The tag or commit the dependency is pinned to.
+    pub rev: Option<String>,
+    /// The ref holding the fetched upstream, e.g. `refs/vendor/owner/repo`.
+    pub reference: String,
+    /// Whether the upstream has been fetched.
+    pub fetched: bool,
+    /// The upstream commit last merged, if the dependency has been merged.
+    pub vendored: Option<String>,
+    /// The fetched upstream commit, if the dependency has been fetched.
+    pub latest: Option<String>,
+    /// When the dependency was last merged, if ever (RFC 3339, UTC).
+    pub merged_at: Option<String>,
+    /// Whole days since the last merge.
+    pub age_days: Option<i64>,
+    /// Who last merged the dependency, as `Name <email>`, if recorded.
+    pub merged_by: Option<String>,
+    /// Number of fetched upstream commits not yet merged.
+    pub pending: usize,
+    /// Whether the vendored content was modified since it was merged.
+    pub dirty: bool,
+    /// Total size in bytes of the vendored files.
+    pub size: u64,
+    /// Whether the dependency was merged but its pattern no longer matches
+    /// anything, e.g. because its directory was deleted by hand.
+    pub missing: bool,
+    /// Whether `fetch --prune` found the tracked branch deleted upstream.
+    pub branch_gone: bool,
+}
+
+/// Orderings for lists of dependencies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusSort {
+    /// Alphabetically by name.
+    Name,
+    /// Most pending upstream commits first.
+    Behind,
+    /// Largest vendored content first.
+    Size,
+    /// Least recently merged first, never-merged dependencies leading.
+    LastUpdated,
+}
+
+impl Overview {
+    /// The upstream host, or `local` for path remotes.
+    pub fn host(&self) -> &str {
+        split_remote_url(&self.url).map_or("local", |(host, _)| host)
+    }
+}
+
+/// Sort `deps` in place; ties keep their tracking order.
+pub fn sort(deps: &mut [Overview], by: StatusSort) {
+    match by {
+        StatusSort::Name => deps.sort_by(|a, b| a.name.cmp(&b.name)),
+        StatusSort::Behind => deps.sort_by_key(|d| std::cmp::Reverse(d.pending)),
+        StatusSort::Size => deps.sort_by_key(|d| std::cmp::Reverse(d.size)),
+        // RFC 3339 UTC timestamps order lexicographically, and `None` first.
+        StatusSort::LastUpdated => deps.sort_by(|a, b| a.merged_at.cmp(&b.merged_at)),
+    }
+}
+
+/// Split `deps` into per-host groups ordered by host, keeping the order of
+/// dependencies within each group.
+pub fn group_by_host(deps: Vec<Overview>) -> Vec<(String, Vec<Overview>)> {
+    let mut groups: Vec<(String, Vec<Overview>)> = Vec::new();
+    for dep in deps {
+        let host = dep.host().to_string();
+        match groups.iter_mut().find(|(h, _)| *h == host) {
+            Some((_, group)) => group.push(dep),
+            None => groups.push((host, vec![dep])),
+        }
+    }
+    groups.sort_by(|(a, _), (b, _)| a.cmp(b));
+    groups
+}
+
+/// The summaries of dependencies, each computed as it is asked for, so they
+/// can be shown as they come in; returned by
+/// [`Vendor::vendor_status_iter`](super::Vendor::vendor_status_iter).
+///
+/// The status cache is written once all summaries have been yielded, or the
+/// iterator is dropped.
+pub struct StatusIter<'r> {
+    repo: &'r Repository,
+    head: Commit<'r>,
+    tree: Tree<'r>,
+    deps: std::vec::IntoIter<VendorDep>,
+    cache: StatusCache,
+    now: i64,
+}
+
+impl<'r> StatusIter<'r> {
+    pub(crate) fn new(
+        repo: &'r Repository,
+        deps: Vec<VendorDep>,
+        cache: StatusCache,
+        now: i64,
+    ) -> Result<Self, Error> {
+        let head = repo.head()?.peel_to_commit()?;
+        let tree = head.tree()?;
+        Ok(Self {
+            repo,
+            head,
+            tree,
+            deps: deps.into_iter(),
+            cache,
+            now,
+        })
+    }
+}
+
+impl Iterator for StatusIter<'_> {
+    type Item = Result<Overview, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Some(dep) = self.deps.next() else {
+            self.cache.save(self.repo);
+            return None;
+        };
+        Some(
+            self.cache
+                .overview(self.repo, &self.head, &self.tree, &dep, self.now),
+        )
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.deps.size_hint()
+    }
+}
+
+impl ExactSizeIterator for StatusIter<'_> {}
+
+impl Drop for StatusIter<'_> {
+    fn drop(&mut self) {
+        self.cache.save(self.repo);
+    }
+}
+
+/// Summarize `dep` as recorded in `tree`.
+pub(crate) fn overview(
+    repo: &Repository,
+    tree: &Tree,
+    dep: &VendorDep,
+    now: i64,
+) -> Result<Overview, Error> {
+    let reference = vendor_ref_name(&config::Config::resolve(repo)?.ref_prefix, &dep.name);
+    let (vendored, merged_at, age_days, merged_by) =
+        match outdated::merged_age(repo, tree, dep, now)? {
+            Some((record, age)) => (
+                Some(record.commit),
+                Some(record.merged_at),
+                Some(age / 86_400),
+                record.merged_by,
+            ),
+            None => (
+                provenance::merged_commit(repo, tree, dep)?.map(|oid| oid.to_string()),
+                None,
+                None,
+                None,
+            ),
+        };
+    let latest = repo
+        .refname_to_id(&reference)
+        .ok()
+        .map(|oid| oid.to_string());
+    let pending = outdated::check(repo, tree, dep)?.map_or(0, |o| o.new_commits);
+    let dirty = matches!(
+        verify::verify(repo, tree, dep)?,
+        verify::Verification::Modified { .. }
+    );
+    let content = repo.filter_by_patterns(tree, &dep.patterns())?;
+    let size = vendored_size(repo, &content)?;
+    let missing = vendored.is_some() && content.is_empty();
+    let branch_gone = repo
+        .find_reference(&vendor_gone_ref_name(&dep.name))
+        .is_ok();
+
+    Ok(Overview {
+        name: dep.name.clone(),
+        pattern: dep.pattern.clone(),
+        url: dep.url.clone(),
+        branch: dep.branch.clone(),
+        rev: dep.rev.clone(),
+        fetched: latest.is_some(),
+        reference,
+        vendored,
+        latest,
+        merged_at,
+        age_days,
+        merged_by,
+        pending,
+        dirty,
+        size,
+        missing,
+        branch_gone,
+    })
+}
+
+fn vendored_size(repo: &Repository, tree: &Tree) -> Result<u64, Error> {
+    let odb = repo.odb()?;
+    let mut size = 0;
+    walk_tree(repo, tree, |_, entry| {
+        if entry.kind() == Some(ObjectType::Blob) {
+            size += odb.read_header(entry.id())?.0 as u64;
+        }
+        Ok(())
+    })?;
+    Ok(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overview(name: &str, url: &str, pending: usize, merged_at: Option<&str>) -> Overview {
+        Overview {
+            name: name.into(),
+            pattern: format!("vendor/{name}/"),
+            url: url.into(),
+            branch: None,
+            rev: None,
+            reference: vendor_ref_name(config::DEFAULT_REF_PREFIX, name),
+            fetched: true,
+            vendored: None,
+            latest: None,
+            merged_at: merged_at.map(str::to_string),
+            age_days: None,
+            merged_by: None,
+            pending,
+            dirty: false,
+            size: pending as u64 * 10,
+            missing: false,
+            branch_gone: false,
+        }
+    }
+
+    fn names(deps: &[Overview]) -> Vec<&str> {
+        deps.iter().map(|d| d.name.as_str()).collect()
+    }
+
+    #[test]
+    fn sort_orders() {
+        let mut deps = vec![
+            overview(
+                "b",
+                "https://github.com/o/b.git",
+                1,
+                Some("2026-02-01T00:00:00Z"),
+            ),
+            overview("c", "https://gitlab.com/o/c.git", 5, None),
+            overview(
+                "a",
+                "https://github.com/o/a.git",
+                3,
+                Some("2026-01-01T00:00:00Z"),
+            ),
+        ];
+        sort(&mut deps, StatusSort::Name);
+        assert_eq!(names(&deps), ["a", "b", "c"]);
+        sort(&mut deps, StatusSort::Behind);
+        assert_eq!(names(&deps), ["c", "a", "b"]);
+        sort(&mut deps, StatusSort::Size);
+        assert_eq!(names(&deps), ["c", "a", "b"]);
+        sort(&mut deps, StatusSort::LastUpdated);
+        assert_eq!(names(&deps), ["c", "a", "b"]);
+    }
+
+    #[test]
+    fn group_by_host_keeps_order_within_groups() {
+        let deps = vec![
+            overview("x", "https://gitlab.com/o/x.git", 0, None),
+            overview("b", "https://github.com/o/b.git", 0, None),
+            overview("l", "/srv/git/l", 0, None),
+            overview("a", "git@github.com:o/a.git", 0, None),
+        ];
+        let groups = group_by_host(deps);
+        let summary: Vec<(&str, Vec<&str>)> = groups
+            .iter()
+            .map(|(host, deps)| (host.as_str(), names(deps)))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("github.com", vec!["b", "a"]),
+                ("gitlab.com", vec!["x"]),
+                ("local", vec!["l"]),
+            ]
+        );
+    }
+}
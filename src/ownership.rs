@@ -0,0 +1,110 @@
+//! Mapping of repository paths to the vendored dependencies that own them.
+
+use super::VendorDep;
+use git_filter_tree::TreeFilter;
+use git2::{Error, Repository};
+
+/// An index from paths to the dependency whose pattern covers them.
+pub(crate) struct Ownership<'a> {
+    /// Each dependency with the filter selecting its content.
+    owners: Vec<(&'a VendorDep, TreeFilter)>,
+}
+
+impl<'a> Ownership<'a> {
+    /// Build an ownership index over `deps`, matching paths the same way
+    /// `git-filter-tree` selects vendored content in `repo`.
+    pub(crate) fn new(repo: &Repository, deps: &[&'a VendorDep]) -> Result<Self, Error> {
+        let owners = deps
+            .iter()
+            .map(|dep| Ok((*dep, TreeFilter::for_repo(repo).include(&dep.patterns())?)))
+            .collect::<Result<_, Error>>()?;
+        Ok(Self { owners })
+    }
+
+    /// The first dependency (in `.gitattributes` order) owning `path`.
+    pub(crate) fn owner(&self, path: impl AsRef<[u8]>) -> Option<&'a VendorDep> {
+        let path = path.as_ref();
+        if path.starts_with(format!("{}/", super::provenance::PROVENANCE_DIR).as_bytes()) {
+            return None;
+        }
+        self.owners
+            .iter()
+            .find(|(_, filter)| filter.is_match_bytes(path))
+            .map(|(dep, _)| *dep)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dep(name: &str, pattern: &str) -> VendorDep {
+        VendorDep {
+            name: name.into(),
+            pattern: pattern.into(),
+            url: "u".into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn owner_matches_directory_and_glob_patterns() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let a = dep("a/lib", "vendor/lib/");
+        let b = dep("b/txt", "*.txt");
+        let ownership = Ownership::new(&repo, &[&a, &b]).unwrap();
+
+        assert_eq!(ownership.owner("vendor/lib/src/x.rs"), Some(&a));
+        assert_eq!(ownership.owner("vendor/lib/notes.txt"), Some(&a));
+        assert_eq!(ownership.owner("docs/readme.txt"), Some(&b));
+        assert_eq!(ownership.owner("src/main.rs"), None);
+    }
+
+    #[test]
+    fn owner_matches_every_pattern_of_a_dependency() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let mut a = dep("a/foo", "include/foo/");
+        a.extra_patterns.push("src/foo/".into());
+        let b = dep("b/src", "src/");
+        let ownership = Ownership::new(&repo, &[&a, &b]).unwrap();
+
+        assert_eq!(ownership.owner("include/foo/foo.h"), Some(&a));
+        assert_eq!(ownership.owner("src/foo/foo.c"), Some(&a));
+        assert_eq!(ownership.owner("src/main.c"), Some(&b));
+    }
+
+    #[test]
+    fn provenance_records_are_unowned() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let a = dep("a/json", "*.json");
+        let ownership = Ownership::new(&repo, &[&a]).unwrap();
+        assert_eq!(ownership.owner(".git-vendor/a/json.json"), None);
+    }
+
+    #[test]
+    fn owner_ignores_case_with_core_ignorecase() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let a = dep("a/lib", "vendor/lib/");
+        assert_eq!(
+            Ownership::new(&repo, &[&a])
+                .unwrap()
+                .owner("Vendor/Lib/x.c"),
+            None
+        );
+
+        repo.config()
+            .unwrap()
+            .set_bool("core.ignorecase", true)
+            .unwrap();
+        assert_eq!(
+            Ownership::new(&repo, &[&a])
+                .unwrap()
+                .owner("Vendor/Lib/x.c"),
+            Some(&a)
+        );
+    }
+}
@@ -0,0 +1,205 @@
+//! Color and pager setup for human-readable output, following Git's rules.
+
+use git2 as git;
+use std::io::{IsTerminal, Write};
+
+/// A running pager receiving our standard output.
+///
+/// Dropping it closes standard output and waits for the pager to exit.
+pub struct Pager {
+    #[cfg(unix)]
+    child: std::process::Child,
+}
+
+/// The pager Git would use: `GIT_PAGER`, `core.pager`, `PAGER`, then `less`.
+///
+/// Returns `None` when paging is disabled with an empty value or `cat`.
+fn pager_command(repo: &git::Repository) -> Option<String> {
+    choose_pager(
+        std::env::var("GIT_PAGER").ok(),
+        || repo.config().ok()?.get_string("core.pager").ok(),
+        std::env::var("PAGER").ok(),
+    )
+}
+
+/// The first pager set of `git_pager`, `core_pager` and `pager`, or `less`;
+/// `None` when it disables paging.
+fn choose_pager(
+    git_pager: Option<String>,
+    core_pager: impl FnOnce() -> Option<String>,
+    pager: Option<String>,
+) -> Option<String> {
+    let command = git_pager
+        .or_else(core_pager)
+        .or(pager)
+        .unwrap_or_else(|| "less".into());
+
+    match command.trim() {
+        "" | "cat" => None,
+        _ => Some(command),
+    }
+}
+
+/// Start the configured pager if standard output is a terminal.
+#[cfg(unix)]
+pub fn start(repo: &git::Repository) -> Option<Pager> {
+    use std::os::fd::AsRawFd;
+    use std::process::{Command, Stdio};
+
+    if !std::io::stdout().is_terminal() {
+        return None;
+    }
+    let command = pager_command(repo)?;
+
+    let mut pager = Command::new("sh");
+    pager.arg("-c").arg(&command).stdin(Stdio::piped());
+    if std::env::var_os("LESS").is_none() {
+        pager.env("LESS", "FRX");
+    }
+    if std::env::var_os("LV").is_none() {
+        pager.env("LV", "-c");
+    }
+    let mut child = pager.spawn().ok()?;
+
+    let stdin = child.stdin.take()?;
+    // SAFETY: both descriptors are valid; fd 1 is replaced atomically and
+    // the pipe's original descriptor is closed when `stdin` is dropped.
+    if unsafe { libc::dup2(stdin.as_raw_fd(), libc::STDOUT_FILENO) } < 0 {
+        let _ = child.kill();
+        return None;
+    }
+
+    Some(Pager { child })
+}
+
+#[cfg(not(unix))]
+pub fn start(_repo: &git::Repository) -> Option<Pager> {
+    None
+}
+
+#[cfg(unix)]
+impl Drop for Pager {
+    fn drop(&mut self) {
+        let _ = std::io::stdout().flush();
+        // SAFETY: closing our standard output signals end of input to the pager.
+        unsafe { libc::close(libc::STDOUT_FILENO) };
+        let _ = self.child.wait();
+    }
+}
+
+/// Whether to color output, honoring `NO_COLOR` and `color.ui`.
+///
+/// With `auto` (the default), output is colored when it goes to a
+/// terminal, or to a pager while `color.pager` is enabled.
+pub fn use_color(repo: &git::Repository, paging: bool) -> bool {
+    let config = repo.config().ok();
+    color_enabled(&Color {
+        no_color: std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()),
+        ui: config.as_ref().and_then(|c| c.get_string("color.ui").ok()),
+        pager: config.as_ref().and_then(|c| c.get_bool("color.pager").ok()),
+        paging,
+        terminal: std::io::stdout().is_terminal(),
+    })
+}
+
+/// What the color decision depends on.
+struct Color {
+    /// `NO_COLOR` is set to a non-empty value.
+    no_color: bool,
+    /// `color.ui`; `auto` when unset.
+    ui: Option<String>,
+    /// `color.pager`; enabled when unset.
+    pager: Option<bool>,
+    /// Output goes to a pager.
+    paging: bool,
+    /// Standard output is a terminal.
+    terminal: bool,
+}
+
+fn color_enabled(color: &Color) -> bool {
+    if color.no_color {
+        return false;
+    }
+    match color
+        .ui
+        .as_deref()
+        .unwrap_or("auto")
+        .to_ascii_lowercase()
+        .as_str()
+    {
+        "always" => true,
+        "never" | "false" | "no" | "off" | "0" => false,
+        _ if color.paging => color.pager.unwrap_or(true),
+        _ => color.terminal,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn auto(paging: bool, terminal: bool) -> Color {
+        Color {
+            no_color: false,
+            ui: None,
+            pager: None,
+            paging,
+            terminal,
+        }
+    }
+
+    #[test]
+    fn color_auto_follows_terminal_and_pager() {
+        assert!(color_enabled(&auto(false, true)));
+        assert!(!color_enabled(&auto(false, false)));
+        assert!(color_enabled(&auto(true, false)));
+        let pager_off = Color {
+            pager: Some(false),
+            ..auto(true, true)
+        };
+        assert!(!color_enabled(&pager_off));
+    }
+
+    #[test]
+    fn color_ui_overrides_terminal() {
+        let always = Color {
+            ui: Some("Always".into()),
+            ..auto(false, false)
+        };
+        assert!(color_enabled(&always));
+        for never in ["never", "false", "off"] {
+            let never = Color {
+                ui: Some(never.into()),
+                ..auto(true, true)
+            };
+            assert!(!color_enabled(&never));
+        }
+    }
+
+    #[test]
+    fn no_color_wins() {
+        let color = Color {
+            no_color: true,
+            ui: Some("always".into()),
+            ..auto(true, true)
+        };
+        assert!(!color_enabled(&color));
+    }
+
+    #[test]
+    fn choose_pager_follows_git_order() {
+        let some = |s: &str| Some(s.to_string());
+        assert_eq!(
+            choose_pager(some("more"), || some("most"), some("pg")),
+            some("more")
+        );
+        assert_eq!(
+            choose_pager(None, || some("most"), some("pg")),
+            some("most")
+        );
+        assert_eq!(choose_pager(None, || None, some("pg")), some("pg"));
+        assert_eq!(choose_pager(None, || None, None), some("less"));
+        assert_eq!(choose_pager(some("cat"), || some("most"), None), None);
+        assert_eq!(choose_pager(some(" "), || None, None), None);
+    }
+}
@@ -0,0 +1,169 @@
+//! A per-process cache of the vendor dependencies parsed from `.gitattributes`
+//! files.
+//!
+//! Most commands load the dependencies several times, so each file is parsed
+//! once and reused until its modification time or size changes. The root
+//! `.gitattributes`, whose attribute macros a nested file may use, is checked
+//! as well.
+//!
+//! Like Git's index, this cannot see a file rewritten with the same size
+//! within the resolution of its timestamp, so files modified in the last
+//! [`RACY_WINDOW`] are parsed every time rather than cached.
+
+use super::VendorDep;
+use git2::Error;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, SystemTime},
+};
+
+/// How recently a file may have been modified and still be cached; two
+/// seconds covers the coarsest filesystem timestamps (FAT).
+const RACY_WINDOW: Duration = Duration::from_secs(2);
+
+/// The modification time and size of a file, or `None` if it is missing.
+type Stamp = Option<(SystemTime, u64)>;
+
+struct Entry {
+    stamp: Stamp,
+    root: Stamp,
+    deps: Vec<VendorDep>,
+}
+
+static PARSED: Mutex<Option<HashMap<PathBuf, Entry>>> = Mutex::new(None);
+
+/// The dependencies parsed from `path` by `parse`, or those cached for it if
+/// neither `path` nor the root `.gitattributes` file `root` changed since.
+pub(crate) fn get_or_parse(
+    path: &Path,
+    root: Option<&Path>,
+    parse: impl FnOnce() -> Result<Vec<VendorDep>, Error>,
+) -> Result<Vec<VendorDep>, Error> {
+    let file_stamp = stamp(path);
+    let root_stamp = root.and_then(stamp);
+    {
+        let cache = PARSED.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(entry) = cache.as_ref().and_then(|cache| cache.get(path))
+            && entry.stamp == file_stamp
+            && entry.root == root_stamp
+        {
+            tracing::trace!(path = %path.display(), "Using cached vendor dependencies");
+            return Ok(entry.deps.clone());
+        }
+    }
+
+    // Stamped before reading, so a change made while parsing is seen next time.
+    let deps = parse()?;
+    let mut cache = PARSED.lock().unwrap_or_else(|e| e.into_inner());
+    let cache = cache.get_or_insert_with(HashMap::new);
+    if settled(file_stamp) && settled(root_stamp) {
+        cache.insert(
+            path.to_path_buf(),
+            Entry {
+                stamp: file_stamp,
+                root: root_stamp,
+                deps: deps.clone(),
+            },
+        );
+    } else {
+        cache.remove(path);
+    }
+    Ok(deps)
+}
+
+fn stamp(path: &Path) -> Stamp {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// Whether a file with `stamp` was last modified long enough ago that a
+/// later change must show in its stamp.
+fn settled(stamp: Stamp) -> bool {
+    stamp.is_none_or(|(modified, _)| {
+        SystemTime::now()
+            .duration_since(modified)
+            .is_ok_and(|age| age >= RACY_WINDOW)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{cell::Cell, fs::File};
+
+    fn dep(name: &str) -> VendorDep {
+        VendorDep {
+            name: name.into(),
+            url: format!("https://example.com/{name}.git"),
+            ..Default::default()
+        }
+    }
+
+    fn backdate(path: &Path, age: Duration) {
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(SystemTime::now() - age)
+            .unwrap();
+    }
+
+    #[test]
+    fn reparses_only_when_the_file_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".gitattributes");
+        fs::write(&path, "a").unwrap();
+        backdate(&path, Duration::from_secs(60));
+
+        let calls = Cell::new(0);
+        let parse = |name: &str| {
+            calls.set(calls.get() + 1);
+            Ok(vec![dep(name)])
+        };
+        assert_eq!(
+            get_or_parse(&path, None, || parse("a")).unwrap()[0].name,
+            "a"
+        );
+        assert_eq!(
+            get_or_parse(&path, None, || parse("b")).unwrap()[0].name,
+            "a"
+        );
+        assert_eq!(calls.get(), 1);
+
+        fs::write(&path, "bb").unwrap();
+        assert_eq!(
+            get_or_parse(&path, None, || parse("b")).unwrap()[0].name,
+            "b"
+        );
+        // Modified just now, so not trusted to be unchanged.
+        assert_eq!(
+            get_or_parse(&path, None, || parse("c")).unwrap()[0].name,
+            "c"
+        );
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[test]
+    fn reparses_when_the_root_file_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join(".gitattributes");
+        let path = dir.path().join("sub/.gitattributes");
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(&root, "").unwrap();
+        fs::write(&path, "").unwrap();
+        backdate(&root, Duration::from_secs(60));
+        backdate(&path, Duration::from_secs(60));
+
+        let parse = |name: &str| Ok(vec![dep(name)]);
+        get_or_parse(&path, Some(&root), || parse("a")).unwrap();
+        fs::write(&root, "[attr]x vendored").unwrap();
+        backdate(&root, Duration::from_secs(30));
+        assert_eq!(
+            get_or_parse(&path, Some(&root), || parse("b")).unwrap()[0].name,
+            "b"
+        );
+    }
+}
@@ -0,0 +1,107 @@
+//! Platform-neutral handling of working tree paths and user-given patterns.
+//!
+//! Git stores paths with `/` separators and, with `core.ignorecase` set
+//! (as `git init` does on case-insensitive filesystems), compares them
+//! without regard to case. Paths from the operating system may use `\` and
+//! differ in case from the working directory libgit2 reports, so they are
+//! compared here component by component.
+
+use git2::Repository;
+use std::borrow::Cow;
+use std::path::{Component, Path, PathBuf};
+
+/// Whether `repo` matches paths case-insensitively (`core.ignorecase`).
+pub(crate) fn ignore_case(repo: &Repository) -> bool {
+    repo.config()
+        .and_then(|config| config.get_bool("core.ignorecase"))
+        .unwrap_or(false)
+}
+
+/// `path` relative to the working directory of `repo`, or `None` if it lies
+/// outside it (or the repository is bare).
+pub(crate) fn workdir_relative(repo: &Repository, path: &Path) -> Option<PathBuf> {
+    strip_dir(repo.workdir()?, path, ignore_case(repo))
+}
+
+/// `path` relative to `dir`, comparing components case-insensitively when
+/// `ignore_case` is set.
+pub(crate) fn strip_dir(dir: &Path, path: &Path, ignore_case: bool) -> Option<PathBuf> {
+    let same = |a: Component, b: Component| {
+        let (a, b) = (
+            a.as_os_str().to_string_lossy(),
+            b.as_os_str().to_string_lossy(),
+        );
+        a == b || (ignore_case && a.to_lowercase() == b.to_lowercase())
+    };
+
+    let mut rest = path.components();
+    for component in dir.components() {
+        if !rest.next().is_some_and(|c| same(component, c)) {
+            return None;
+        }
+    }
+    Some(rest.as_path().to_path_buf())
+}
+
+/// `path` with `/` separators, as Git writes it.
+pub(crate) fn to_slash(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// A pattern given on the command line, with `\` path separators replaced by
+/// `/` on Windows, where shells complete paths with them.
+///
+/// Elsewhere `\` only escapes the character after it, as in `.gitattributes`.
+pub(crate) fn normalize_pattern(pattern: &str) -> Cow<'_, str> {
+    if cfg!(windows) {
+        separators_to_slash(pattern)
+    } else {
+        Cow::Borrowed(pattern)
+    }
+}
+
+/// Replace each `\` in `pattern` with `/`, except a leading `\!` or `\#`,
+/// which keep the pattern from reading as a negation or a comment.
+///
+/// Windows file names cannot hold `*` or `?`, so there is little else for a
+/// backslash to escape there.
+fn separators_to_slash(pattern: &str) -> Cow<'_, str> {
+    let (escape, rest) = match pattern.get(..2) {
+        Some("\\!" | "\\#") => pattern.split_at(2),
+        _ => ("", pattern),
+    };
+    if !rest.contains('\\') {
+        return Cow::Borrowed(pattern);
+    }
+    Cow::Owned(format!("{escape}{}", rest.replace('\\', "/")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_dir_compares_case_when_asked() {
+        let dir = Path::new("/work/Repo");
+        let path = Path::new("/work/repo/vendor/a");
+        assert_eq!(strip_dir(dir, path, false), None);
+        assert_eq!(strip_dir(dir, path, true), Some(PathBuf::from("vendor/a")));
+        assert_eq!(
+            strip_dir(dir, Path::new("/work/Repo"), false),
+            Some(PathBuf::new())
+        );
+        assert_eq!(strip_dir(dir, Path::new("/work"), true), None);
+    }
+
+    #[test]
+    fn separators_to_slash_keeps_leading_escapes() {
+        assert_eq!(separators_to_slash("vendor\\lib\\"), "vendor/lib/");
+        assert_eq!(separators_to_slash("vendor\\*.c"), "vendor/*.c");
+        assert_eq!(separators_to_slash("\\#notes\\"), "\\#notes/");
+        assert_eq!(separators_to_slash("\\!x"), "\\!x");
+        assert_eq!(separators_to_slash("vendor/lib/"), "vendor/lib/");
+    }
+}
@@ -0,0 +1,212 @@
+//! Upstream fetches over git protocol v2.
+//!
+//! libgit2 only speaks protocol v0, in which the server advertises every
+//! ref before anything else happens; for upstreams with many thousands of
+//! branches and tags that advertisement dominates the fetch. Protocol v2
+//! lets the client send `ref-prefix` arguments so only the refs being
+//! fetched are listed. libgit2 cannot, so these fetches run the git command
+//! line, which derives the prefixes from the refspecs. Without a git
+//! command line to run, fetches fall back to libgit2.
+//!
+//! Negotiation is seeded with the commits last fetched and vendored for the
+//! dependency, so the server learns what is already present without the
+//! client offering every unrelated local ref, and only new objects are sent.
+
+use super::{VendorDep, config::Config};
+use git2::{Error, ErrorClass, ErrorCode, Oid, Repository};
+use std::{process::Command, sync::OnceLock};
+
+/// Whether fetches for `config` go through the git command line.
+pub(crate) fn enabled(config: &Config) -> bool {
+    config.protocol_version == 2 && git_available()
+}
+
+/// Whether a git command line can be run, checked once per process.
+pub(crate) fn git_available() -> bool {
+    static AVAILABLE: OnceLock<bool> = OnceLock::new();
+    *AVAILABLE.get_or_init(|| {
+        let available = Command::new("git")
+            .arg("--version")
+            .output()
+            .is_ok_and(|output| output.status.success());
+        if !available {
+            tracing::debug!("git not found; fetching with libgit2");
+        }
+        available
+    })
+}
+
+/// Fetch `refspecs` of `dep` into `repo`, offering only `tips` (and their
+/// ancestors) as commits already present, unless there are none; the first
+/// refspec fetches the tracked branch and the others its tags. Returns
+/// whether the tracked branch no longer exists upstream, in which case only
+/// the tags were fetched.
+pub(crate) fn fetch(
+    repo: &Repository,
+    dep: &VendorDep,
+    refspecs: &[String],
+    tips: &[Oid],
+    config: &Config,
+) -> Result<bool, Error> {
+    let Err(e) = git(repo, dep, &fetch_args(dep, refspecs, tips, config)) else {
+        return Ok(false);
+    };
+    // A missing source ref fails the whole fetch.
+    if dep.branch.is_none() || advertised(repo, dep, &refspecs[0])? {
+        return Err(e);
+    }
+    if refspecs.len() > 1 {
+        git(repo, dep, &fetch_args(dep, &refspecs[1..], tips, config))?;
+    }
+    Ok(true)
+}
+
+/// Fetch the objects of `source` of `dep` into `repo` without updating any
+/// refs, e.g. to deepen a shallow history.
+pub(crate) fn fetch_objects(
+    repo: &Repository,
+    dep: &VendorDep,
+    source: &str,
+    config: &Config,
+) -> Result<(), Error> {
+    git(
+        repo,
+        dep,
+        &fetch_args(dep, &[source.to_string()], &[], config),
+    )
+    .map(|_| ())
+}
+
+/// Whether the upstream has the source ref of `refspec`.
+fn advertised(repo: &Repository, dep: &VendorDep, refspec: &str) -> Result<bool, Error> {
+    let source = source_ref(refspec);
+    Ok(ls_remote(repo, dep, &[source])?
+        .iter()
+        .any(|(name, _)| name == source))
+}
+
+/// The refs `dep`'s upstream advertises matching `patterns`, as listed by
+/// `git ls-remote`, with the commits annotated tags point to listed as
+/// `<tag>^{}`.
+pub(crate) fn ls_remote(
+    repo: &Repository,
+    dep: &VendorDep,
+    patterns: &[&str],
+) -> Result<Vec<(String, Oid)>, Error> {
+    let args: Vec<String> = ["ls-remote", "--", dep.url.as_str()]
+        .iter()
+        .chain(patterns)
+        .map(|arg| arg.to_string())
+        .collect();
+    Ok(git(repo, dep, &args)?
+        .lines()
+        .filter_map(|line| {
+            let (oid, name) = line.split_once('\t')?;
+            Some((name.to_string(), Oid::from_str(oid).ok()?))
+        })
+        .collect())
+}
+
+fn fetch_args(dep: &VendorDep, refspecs: &[String], tips: &[Oid], config: &Config) -> Vec<String> {
+    let mut args: Vec<String> = ["fetch", "--quiet", "--no-tags", "--no-write-fetch-head"]
+        .map(str::to_string)
+        .to_vec();
+    if config.prune {
+        args.push("--prune".into());
+    }
+    if let Some(depth) = dep.depth.or(config.depth).filter(|&depth| depth > 0) {
+        args.push(format!("--depth={depth}"));
+    }
+    args.extend(tips.iter().map(|tip| format!("--negotiation-tip={tip}")));
+    args.push("--".into());
+    args.push(dep.url.clone());
+    args.extend(refspecs.iter().cloned());
+    args
+}
+
+/// The source side of `refspec`, e.g. `refs/heads/main` for
+/// `+refs/heads/main:refs/vendor/owner/repo`.
+fn source_ref(refspec: &str) -> &str {
+    let refspec = refspec.strip_prefix('+').unwrap_or(refspec);
+    refspec
+        .split_once(':')
+        .map_or(refspec, |(source, _)| source)
+}
+
+/// Run git on `repo` over protocol v2 with `dep`'s proxy and credential
+/// helper, returning its standard output.
+fn git(repo: &Repository, dep: &VendorDep, args: &[String]) -> Result<String, Error> {
+    let mut command = Command::new("git");
+    command
+        .arg("--git-dir")
+        .arg(repo.path())
+        .args(["-c", "protocol.version=2"])
+        .env("GIT_TERMINAL_PROMPT", "0");
+    if let Some(proxy) = &dep.proxy {
+        command.args(["-c", &format!("http.proxy={proxy}")]);
+    }
+    if let Some(helper) = &dep.credential_helper {
+        command
+            .args(["-c", "credential.helper="])
+            .args(["-c", &format!("credential.helper={helper}")]);
+    }
+    tracing::debug!(?args, "Running git over protocol v2");
+    let output = command.args(args).output().map_err(|e| {
+        Error::from_str(&format!(
+            "Failed to run git {}: {e} (set vendor.protocolVersion to 0 to fetch with libgit2)",
+            args[0]
+        ))
+    })?;
+    if !output.status.success() {
+        return Err(Error::new(
+            ErrorCode::GenericError,
+            ErrorClass::Net,
+            format!(
+                "git {} failed: {}",
+                args[0],
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn source_ref_strips_force_and_destination() {
+        assert_eq!(
+            source_ref("+refs/heads/main:refs/vendor/owner/repo"),
+            "refs/heads/main"
+        );
+        assert_eq!(source_ref("HEAD"), "HEAD");
+    }
+
+    #[test]
+    fn fetch_args_limit_to_refspecs() {
+        let dep = VendorDep {
+            url: "https://example.com/repo.git".into(),
+            depth: Some(1),
+            ..Default::default()
+        };
+        let config = Config::from(crate::config::Settings::default());
+        let refspecs = ["+refs/heads/main:refs/vendor/repo".to_string()];
+        let tip = Oid::from_str("0123456789abcdef0123456789abcdef01234567").unwrap();
+        assert_eq!(
+            fetch_args(&dep, &refspecs, &[tip], &config),
+            [
+                "fetch",
+                "--quiet",
+                "--no-tags",
+                "--no-write-fetch-head",
+                "--depth=1",
+                "--negotiation-tip=0123456789abcdef0123456789abcdef01234567",
+                "--",
+                "https://example.com/repo.git",
+                "+refs/heads/main:refs/vendor/repo",
+            ]
+        );
+    }
+}
@@ -0,0 +1,297 @@
+//! Provenance records committed alongside vendored content.
+//!
+//! Every merge writes `.git-vendor/<name>.json` describing where the merged
+//! content came from, so its origin can be audited without `git-vendor`.
+//!
+//! Histories vendored before records were kept may instead name the upstream
+//! commit in a `Vendored-Commit:` trailer on the commit that vendored it,
+//! optionally after the dependency's name:
+//!
+//! ```text
+//! Vendored-Commit: owner/repo 0123456789abcdef0123456789abcdef01234567
+//! ```
+//!
+//! With the `notes` setting, each merge commit also carries its record as a
+//! note in [`NOTES_REF`]. Notes are shared with
+//! `git push origin refs/notes/vendor` and
+//! `git fetch origin refs/notes/vendor:refs/notes/vendor`, and survive
+//! rewrites of the records in the tree.
+
+use super::VendorDep;
+use git_filter_tree::FilterTree;
+use git2::{Error, ObjectType, Oid, Repository, Sort, Tree, build::TreeUpdateBuilder};
+use serde::{Deserialize, Serialize};
+
+/// Directory (relative to the repository root) holding provenance records.
+pub const PROVENANCE_DIR: &str = ".git-vendor";
+
+/// The notes ref holding provenance records attached to merge commits.
+pub const NOTES_REF: &str = "refs/notes/vendor";
+
+/// Where a vendored dependency's merged content came from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Provenance {
+    pub name: String,
+    pub url: String,
+    /// The upstream ref that was fetched, e.g. `refs/heads/main` or `HEAD`.
+    #[serde(rename = "ref")]
+    pub reference: String,
+    /// The upstream commit the content was merged from.
+    pub commit: String,
+    /// When the commit was fetched (RFC 3339, UTC).
+    pub fetched_at: String,
+    /// When the content was merged (RFC 3339, UTC).
+    #[serde(rename = "updated-at", alias = "merged_at")]
+    pub merged_at: String,
+    /// Who merged the content, as `Name <email>`; absent from records
+    /// written before it was tracked.
+    #[serde(
+        rename = "updated-by",
+        alias = "merged_by",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub merged_by: Option<String>,
+    /// The filtered upstream tree that was merged.
+    pub tree: String,
+}
+
+impl Provenance {
+    /// Serialize to the pretty-printed JSON stored in the repository.
+    pub fn to_json(&self) -> Result<String, Error> {
+        serde_json::to_string_pretty(self)
+            .map(|s| s + "\n")
+            .map_err(|e| Error::from_str(&e.to_string()))
+    }
+
+    /// Parse a record previously written by [`Provenance::to_json`].
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        serde_json::from_str(json)
+            .map_err(|e| Error::from_str(&format!("Invalid provenance record: {e}")))
+    }
+}
+
+/// Path of the provenance record for `name`, relative to the repository root.
+pub fn provenance_path(name: &str) -> String {
+    format!("{PROVENANCE_DIR}/{name}.json")
+}
+
+/// Return a copy of `tree` with `provenance` written to its record path.
+pub(crate) fn record(
+    repo: &Repository,
+    tree: &Tree,
+    provenance: &Provenance,
+) -> Result<Oid, Error> {
+    let blob = repo.blob(provenance.to_json()?.as_bytes())?;
+    let mut update = TreeUpdateBuilder::new();
+    update.upsert(
+        provenance_path(&provenance.name),
+        blob,
+        git2::FileMode::Blob,
+    );
+    update.create_updated(repo, tree)
+}
+
+/// Read the provenance record for `name` from `tree`, if present.
+pub(crate) fn read(
+    repo: &Repository,
+    tree: &Tree,
+    name: &str,
+) -> Result<Option<Provenance>, Error> {
+    let entry = match tree.get_path(std::path::Path::new(&provenance_path(name))) {
+        Ok(entry) => entry,
+        Err(e) if e.code() == git2::ErrorCode::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    if entry.kind() != Some(ObjectType::Blob) {
+        return Ok(None);
+    }
+
+    let blob = repo.find_blob(entry.id())?;
+    let json = std::str::from_utf8(blob.content())
+        .map_err(|e| Error::from_str(&format!("Invalid provenance record: {e}")))?;
+    Provenance::from_json(json).map(Some)
+}
+
+/// Attach `provenance` to `commit` as a note in [`NOTES_REF`], replacing
+/// any note already there.
+pub(crate) fn annotate(
+    repo: &Repository,
+    commit: Oid,
+    provenance: &Provenance,
+) -> Result<Oid, Error> {
+    let signature = repo.signature()?;
+    repo.note(
+        &signature,
+        &signature,
+        Some(NOTES_REF),
+        commit,
+        &provenance.to_json()?,
+        true,
+    )
+}
+
+/// The provenance record for `name` attached to `commit` in [`NOTES_REF`],
+/// if any.
+pub fn read_note(repo: &Repository, commit: Oid, name: &str) -> Option<Provenance> {
+    let note = repo.find_note(Some(NOTES_REF), commit).ok()?;
+    Provenance::from_json(note.message()?)
+        .ok()
+        .filter(|provenance| provenance.name == name)
+}
+
+/// The trailer naming the upstream commit vendored by a host commit.
+pub const VENDORED_COMMIT_TRAILER: &str = "Vendored-Commit";
+
+/// The upstream commit `dep` was last merged from: the one in its record
+/// in `tree`, or when `tree` is `HEAD`'s and has no record, the one
+/// recovered from `HEAD`'s history by [`recover_commit`].
+pub(crate) fn merged_commit(
+    repo: &Repository,
+    tree: &Tree,
+    dep: &VendorDep,
+) -> Result<Option<Oid>, Error> {
+    if let Some(record) = read(repo, tree, &dep.name)? {
+        return Ok(Oid::from_str(&record.commit).ok());
+    }
+    let Ok(head) = repo.head().and_then(|head| head.peel_to_commit()) else {
+        return Ok(None);
+    };
+    if head.tree_id() != tree.id() {
+        return Ok(None);
+    }
+    recover_commit(repo, head.id(), dep)
+}
+
+/// The upstream commit named by the note or `Vendored-Commit:` trailer of
+/// the most recent commit in the history of `head` that changed `dep`'s
+/// content.
+pub(crate) fn recover_commit(
+    repo: &Repository,
+    head: Oid,
+    dep: &VendorDep,
+) -> Result<Option<Oid>, Error> {
+    let mut walk = repo.revwalk()?;
+    walk.push(head)?;
+    walk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+    for oid in walk {
+        let commit = repo.find_commit(oid?)?;
+        let noted = read_note(repo, commit.id(), &dep.name)
+            .and_then(|provenance| Oid::from_str(&provenance.commit).ok());
+        let Some(upstream) =
+            noted.or_else(|| commit.message().and_then(|m| trailer_commit(m, &dep.name)))
+        else {
+            continue;
+        };
+        let tree = commit.tree()?;
+        let content = repo.filter_by_patterns(&tree, &dep.patterns())?;
+        let changed = match commit.parent(0) {
+            Ok(parent) => {
+                repo.filter_by_patterns(&parent.tree()?, &dep.patterns())?
+                    .id()
+                    != content.id()
+            }
+            Err(_) => !content.is_empty(),
+        };
+        if changed {
+            return Ok(Some(upstream));
+        }
+    }
+    Ok(None)
+}
+
+/// The commit a `Vendored-Commit:` trailer in `message` names for the
+/// dependency `name`. Trailers naming another dependency are ignored.
+fn trailer_commit(message: &str, name: &str) -> Option<Oid> {
+    let trailers = git2::message_trailers_strs(message).ok()?;
+    trailers
+        .iter()
+        .filter(|(key, _)| key.eq_ignore_ascii_case(VENDORED_COMMIT_TRAILER))
+        .find_map(|(_, value)| {
+            let (named, commit) = match value.split_whitespace().collect::<Vec<_>>()[..] {
+                [commit] => (None, commit),
+                [named, commit] => (Some(named), commit),
+                _ => return None,
+            };
+            if named.is_some_and(|named| named != name) || commit.len() != 40 {
+                return None;
+            }
+            Oid::from_str(commit).ok()
+        })
+}
+
+/// Seconds since the epoch at which `ref_name` was last updated by a fetch.
+///
+/// Falls back to the current time when the ref has no reflog.
+pub(crate) fn fetched_at(repo: &Repository, ref_name: &str) -> i64 {
+    repo.reflog(ref_name)
+        .ok()
+        .and_then(|log| log.get(0).map(|entry| entry.committer().when().seconds()))
+        .unwrap_or_else(super::now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trailer_commit_matches_dependency_name() {
+        let commit = "0123456789abcdef0123456789abcdef01234567";
+        let message = format!("Update lib\n\nVendored-Commit: {commit}\n");
+        assert_eq!(
+            trailer_commit(&message, "owner/repo"),
+            Some(Oid::from_str(commit).unwrap())
+        );
+
+        let message = format!("Update\n\nvendored-commit: other/repo {commit}\n");
+        assert_eq!(trailer_commit(&message, "owner/repo"), None);
+        assert!(trailer_commit(&message, "other/repo").is_some());
+
+        assert_eq!(
+            trailer_commit("Update\n\nVendored-Commit: v1.0\n", "owner/repo"),
+            None
+        );
+    }
+
+    #[test]
+    fn provenance_path_nests_owner() {
+        assert_eq!(provenance_path("owner/repo"), ".git-vendor/owner/repo.json");
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let provenance = Provenance {
+            name: "owner/repo".into(),
+            url: "https://example.com/owner/repo.git".into(),
+            reference: "refs/heads/main".into(),
+            commit: "0123456789abcdef0123456789abcdef01234567".into(),
+            fetched_at: "2026-01-02T03:04:05Z".into(),
+            merged_at: "2026-01-02T04:05:06Z".into(),
+            merged_by: Some("Test <test@test>".into()),
+            tree: "89abcdef0123456789abcdef0123456789abcdef".into(),
+        };
+        let json = provenance.to_json().unwrap();
+        assert!(json.contains("\"ref\": \"refs/heads/main\""));
+        assert!(json.contains("\"updated-at\": \"2026-01-02T04:05:06Z\""));
+        assert!(json.contains("\"updated-by\": \"Test <test@test>\""));
+        assert!(!json.contains("merged_"));
+        assert_eq!(Provenance::from_json(&json).unwrap(), provenance);
+    }
+
+    #[test]
+    fn records_without_merger_parse() {
+        let json = r#"{
+  "name": "owner/repo",
+  "url": "https://example.com/owner/repo.git",
+  "ref": "HEAD",
+  "commit": "0123456789abcdef0123456789abcdef01234567",
+  "fetched_at": "2026-01-02T03:04:05Z",
+  "merged_at": "2026-01-02T04:05:06Z",
+  "tree": "89abcdef0123456789abcdef0123456789abcdef"
+}"#;
+        let provenance = Provenance::from_json(json).unwrap();
+        assert_eq!(provenance.merged_at, "2026-01-02T04:05:06Z");
+        assert_eq!(provenance.merged_by, None);
+        assert!(!provenance.to_json().unwrap().contains("updated-by"));
+    }
+}
@@ -0,0 +1,228 @@
+//! Lightweight reachability checks of upstream remotes.
+//!
+//! Checks run `git ls-remote`, which only exchanges refs, so no objects are
+//! downloaded.  Git reports followed HTTP redirects on standard error, which
+//! is how moved repositories are detected.
+//!
+//! With the `forge_api` setting, upstreams on known forges are asked through
+//! their web API instead, falling back to `git ls-remote` if that fails.
+
+use super::forge::Forge;
+use git2::Error;
+use std::process::Command;
+
+/// The outcome of checking one dependency's upstream remote.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RemoteStatus {
+    /// The remote answered and advertises the tracked ref at `head`.
+    Reachable { head: String },
+    /// The remote answered from a different URL than the one tracked.
+    Moved { to: String, head: Option<String> },
+    /// The remote answered but does not advertise the tracked branch.
+    BranchMissing { branch: String },
+    /// The remote could not be contacted.
+    Unreachable { reason: String },
+}
+
+impl RemoteStatus {
+    /// Whether the remote needs attention.
+    pub fn is_problem(&self) -> bool {
+        !matches!(self, Self::Reachable { .. })
+    }
+}
+
+/// Check whether the remote at `url` is reachable and still has `branch`
+/// (or a `HEAD` when no branch is tracked), asking a forge's API when
+/// `forge_api` is set and it hosts `url`.
+pub(crate) fn check(
+    url: &str,
+    branch: Option<&str>,
+    forge_api: bool,
+) -> Result<RemoteStatus, Error> {
+    Ok(check_all(&[(url, branch)], forge_api)?.remove(0))
+}
+
+/// Check each `(url, branch)` of `remotes` like [`check`], returning the
+/// statuses in the same order.
+///
+/// Remotes sharing a URL are contacted once, with a single `git ls-remote`
+/// listing every branch asked about, rather than once per dependency.
+pub(crate) fn check_all(
+    remotes: &[(&str, Option<&str>)],
+    forge_api: bool,
+) -> Result<Vec<RemoteStatus>, Error> {
+    let mut statuses: Vec<Option<RemoteStatus>> = vec![None; remotes.len()];
+    let mut urls: Vec<&str> = remotes.iter().map(|&(url, _)| url).collect();
+    urls.sort_unstable();
+    urls.dedup();
+
+    for url in urls {
+        let asked: Vec<usize> = (0..remotes.len())
+            .filter(|&i| remotes[i].0 == url)
+            .collect();
+        let mut unanswered = Vec::new();
+        match Forge::from_url(url).filter(|_| forge_api) {
+            Some(forge) => {
+                for i in asked {
+                    match forge.check(remotes[i].1) {
+                        Ok(status) => statuses[i] = Some(status),
+                        Err(_) => unanswered.push(i),
+                    }
+                }
+            }
+            None => unanswered = asked,
+        }
+        if unanswered.is_empty() {
+            continue;
+        }
+
+        let references: Vec<String> = unanswered
+            .iter()
+            .map(|&i| reference(remotes[i].1))
+            .collect();
+        let mut patterns = references.clone();
+        patterns.sort_unstable();
+        patterns.dedup();
+        tracing::debug!(url, refs = ?patterns, "Checking remote");
+        let output = Command::new("git")
+            .args(["ls-remote", "--", url])
+            .args(&patterns)
+            .env("GIT_TERMINAL_PROMPT", "0")
+            .output()
+            .map_err(|e| Error::from_str(&format!("Failed to run git ls-remote: {e}")))?;
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        for (i, reference) in unanswered.into_iter().zip(&references) {
+            statuses[i] = Some(interpret(
+                output.status.success(),
+                &stdout,
+                &stderr,
+                reference,
+                remotes[i].1,
+            ));
+        }
+    }
+    Ok(statuses.into_iter().flatten().collect())
+}
+
+/// The ref `git ls-remote` is asked for to check `branch`.
+fn reference(branch: Option<&str>) -> String {
+    match branch {
+        Some(branch) => format!("refs/heads/{branch}"),
+        None => "HEAD".to_string(),
+    }
+}
+
+fn interpret(
+    success: bool,
+    stdout: &str,
+    stderr: &str,
+    reference: &str,
+    branch: Option<&str>,
+) -> RemoteStatus {
+    if !success {
+        let reason = stderr
+            .lines()
+            .rev()
+            .find(|line| !line.trim().is_empty())
+            .map(|line| line.trim_start_matches("fatal: ").trim().to_string())
+            .unwrap_or_else(|| "git ls-remote failed".into());
+        return RemoteStatus::Unreachable { reason };
+    }
+
+    let head = stdout.lines().find_map(|line| {
+        let (oid, name) = line.split_once('\t')?;
+        (name == reference).then(|| oid.to_string())
+    });
+    let moved = stderr
+        .lines()
+        .find_map(|line| line.strip_prefix("warning: redirecting to "))
+        .map(|to| to.trim().to_string());
+
+    match (moved, head) {
+        (Some(to), head) => RemoteStatus::Moved { to, head },
+        (None, Some(head)) => RemoteStatus::Reachable { head },
+        (None, None) => RemoteStatus::BranchMissing {
+            branch: branch.unwrap_or("HEAD").to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHA: &str = "0123456789abcdef0123456789abcdef01234567";
+
+    #[test]
+    fn interpret_reachable() {
+        let stdout = format!("{SHA}\trefs/heads/main\n");
+        assert_eq!(
+            interpret(true, &stdout, "", "refs/heads/main", Some("main")),
+            RemoteStatus::Reachable { head: SHA.into() }
+        );
+    }
+
+    #[test]
+    fn interpret_redirect() {
+        let stdout = format!("{SHA}\tHEAD\n");
+        let stderr = "warning: redirecting to https://example.com/new/repo.git/\n";
+        assert_eq!(
+            interpret(true, &stdout, stderr, "HEAD", None),
+            RemoteStatus::Moved {
+                to: "https://example.com/new/repo.git/".into(),
+                head: Some(SHA.into()),
+            }
+        );
+    }
+
+    #[test]
+    fn interpret_missing_branch() {
+        assert_eq!(
+            interpret(true, "", "", "refs/heads/gone", Some("gone")),
+            RemoteStatus::BranchMissing {
+                branch: "gone".into()
+            }
+        );
+    }
+
+    #[test]
+    fn interpret_unreachable_uses_last_error_line() {
+        let stderr = "ssh: connect to host example.com port 22: Connection refused\n\
+                      fatal: Could not read from remote repository.\n\n";
+        assert_eq!(
+            interpret(false, "", stderr, "HEAD", None),
+            RemoteStatus::Unreachable {
+                reason: "Could not read from remote repository.".into()
+            }
+        );
+    }
+
+    #[test]
+    fn check_all_answers_each_branch_of_a_shared_url() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let repo = git2::Repository::init(dir.path()).unwrap();
+        let sig = git2::Signature::now("Test", "test@example.com").unwrap();
+        let tree = repo
+            .find_tree(repo.index().unwrap().write_tree().unwrap())
+            .unwrap();
+        let commit = repo
+            .commit(Some("refs/heads/main"), &sig, &sig, "init", &tree, &[])
+            .unwrap();
+        let url = format!("file://{}", dir.path().display());
+
+        let statuses = check_all(&[(&url, Some("main")), (&url, Some("gone"))], false).unwrap();
+        assert_eq!(
+            statuses,
+            [
+                RemoteStatus::Reachable {
+                    head: commit.to_string()
+                },
+                RemoteStatus::BranchMissing {
+                    branch: "gone".into()
+                },
+            ]
+        );
+    }
+}
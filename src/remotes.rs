@@ -0,0 +1,83 @@
+//! Git remotes mirroring vendored dependencies.
+//!
+//! With `vendor.remotes` set, each dependency is registered as a
+//! `vendor/<name>` remote whose fetch refspecs target the vendor namespace,
+//! so `git fetch vendor/<name>` updates the same refs as `git vendor fetch`
+//! and `git log vendor/<name>` shows the fetched upstream.
+
+use super::{
+    VendorDep, config::Config, fetches_tags, upstream_ref, vendor_ref_name, vendor_tags_ref_prefix,
+};
+use git2::{Error, ErrorCode, Repository};
+
+/// The name of the remote registered for the dependency `name`.
+pub(crate) fn remote_name(name: &str) -> String {
+    format!("vendor/{name}")
+}
+
+/// Register `dep` as a remote, or update its URL and refspecs.
+pub(crate) fn sync(repo: &Repository, dep: &VendorDep, config: &Config) -> Result<(), Error> {
+    let remote = remote_name(&dep.name);
+    let branch = format!(
+        "+{}:{}",
+        upstream_ref(dep),
+        vendor_ref_name(&config.ref_prefix, &dep.name)
+    );
+    let tags = fetches_tags(dep, config)
+        .then(|| format!("+refs/tags/*:{}*", vendor_tags_ref_prefix(&dep.name)));
+    let wanted: Vec<&str> = std::iter::once(branch.as_str())
+        .chain(tags.as_deref())
+        .collect();
+
+    match repo.find_remote(&remote) {
+        Ok(existing) => {
+            if existing.url() != Some(dep.url.as_str()) {
+                repo.remote_set_url(&remote, &dep.url)?;
+            }
+            let current: Vec<String> = existing
+                .fetch_refspecs()?
+                .iter()
+                .flatten()
+                .map(str::to_string)
+                .collect();
+            if current == wanted {
+                return Ok(());
+            }
+            remove_refspecs(repo, &remote)?;
+            repo.remote_add_fetch(&remote, &branch)?;
+        }
+        Err(e) if e.code() == ErrorCode::NotFound => {
+            repo.remote_with_fetch(&remote, &dep.url, &branch)?;
+        }
+        Err(e) => return Err(e),
+    }
+    match tags {
+        Some(tags) => repo.remote_add_fetch(&remote, &tags),
+        None => Ok(()),
+    }
+}
+
+/// Unregister the remote of the dependency `name`, if there is one.
+///
+/// Unlike `git remote remove`, the fetched vendor refs are kept.
+pub(crate) fn remove(repo: &Repository, name: &str) -> Result<(), Error> {
+    let remote = remote_name(name);
+    if let Err(e) = repo.find_remote(&remote) {
+        return match e.code() {
+            ErrorCode::NotFound => Ok(()),
+            _ => Err(e),
+        };
+    }
+    remove_refspecs(repo, &remote)?;
+    repo.config()?.remove(&format!("remote.{remote}.url"))
+}
+
+fn remove_refspecs(repo: &Repository, remote: &str) -> Result<(), Error> {
+    match repo
+        .config()?
+        .remove_multivar(&format!("remote.{remote}.fetch"), ".*")
+    {
+        Err(e) if e.code() != ErrorCode::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,79 @@
+//! Re-materializing vendored content from its provenance record.
+
+use super::{VendorDep, clean, provenance};
+use git_filter_tree::{bytes_to_path, walk_tree};
+use git2::{Error, ObjectType, Oid, Repository, Tree};
+
+/// Restore `dep`'s files in the index and working tree to the tree recorded
+/// when it was last merged, and return the paths that changed.
+///
+/// Files differing from the record are checked out again, and tracked files
+/// owned by `dep` but absent from the record are removed.  Ownership is
+/// decided among `deps`.  With `dry_run`, nothing is changed.
+pub(crate) fn restore(
+    repo: &Repository,
+    head_tree: &Tree,
+    deps: &[&VendorDep],
+    dep: &VendorDep,
+    dry_run: bool,
+) -> Result<Vec<String>, Error> {
+    let Some(record) = provenance::read(repo, head_tree, &dep.name)? else {
+        return Err(Error::from_str(&format!(
+            "No provenance record for {}; merge it first",
+            dep.name
+        )));
+    };
+    let recorded = Oid::from_str(&record.tree)
+        .and_then(|oid| repo.find_tree(oid))
+        .map_err(|_| {
+            Error::from_str(&format!(
+                "Recorded tree {} for {} is not available",
+                record.tree, dep.name
+            ))
+        })?;
+
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| Error::from_str("Repository has no working directory"))?;
+    let index = repo.index()?;
+    let mut blobs = Vec::new();
+    let mut changed = Vec::new();
+    walk_tree(repo, &recorded, |path, entry| {
+        if entry.kind() != Some(ObjectType::Blob) {
+            return Ok(());
+        }
+        let staged = index
+            .get_path(&bytes_to_path(path), 0)
+            .is_some_and(|e| e.id == entry.id() && e.mode == entry.filemode() as u32);
+        let on_disk = Oid::hash_file(ObjectType::Blob, workdir.join(bytes_to_path(path)))
+            .is_ok_and(|oid| oid == entry.id());
+        if !(staged && on_disk) {
+            changed.push(path.to_vec());
+        }
+        blobs.push(path.to_vec());
+        Ok(())
+    })?;
+
+    if !dry_run && !changed.is_empty() {
+        let mut checkout = git2::build::CheckoutBuilder::new();
+        checkout.force().disable_pathspec_match(true);
+        for path in &changed {
+            checkout.path(path.as_slice());
+        }
+        repo.checkout_tree(recorded.as_object(), Some(&mut checkout))?;
+    }
+
+    let mut index = repo.index()?;
+    let extra: Vec<Vec<u8>> = clean::owned_paths(repo, &index, deps, &[dep])?
+        .into_iter()
+        .filter(|path| !blobs.contains(path))
+        .collect();
+    if !dry_run {
+        clean::remove(repo, &mut index, &extra)?;
+        index.write()?;
+    }
+
+    changed.extend(extra);
+    changed.sort();
+    Ok(changed.iter().map(|p| clean::display_path(p)).collect())
+}
@@ -0,0 +1,179 @@
+//! Software bill of materials (SBOM) generation for vendored dependencies.
+
+use super::{VendorDep, config, split_remote_url, vendor_ref_name};
+use git2::{Error, Oid, Repository};
+use serde_json::{Value, json};
+
+/// Supported SBOM document formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SbomFormat {
+    /// CycloneDX 1.5 JSON.
+    #[default]
+    CycloneDx,
+}
+
+/// Render an SBOM document describing `deps` in the requested format.
+pub(crate) fn render(
+    repo: &Repository,
+    deps: &[&VendorDep],
+    format: SbomFormat,
+) -> Result<String, Error> {
+    let prefix = config::Config::resolve(repo)?.ref_prefix;
+    let doc = match format {
+        SbomFormat::CycloneDx => cyclonedx(repo, deps, &prefix),
+    };
+
+    serde_json::to_string_pretty(&doc).map_err(|e| Error::from_str(&e.to_string()))
+}
+
+/// Build a CycloneDX document with one `library` component per dependency.
+fn cyclonedx(repo: &Repository, deps: &[&VendorDep], ref_prefix: &str) -> Value {
+    let components: Vec<Value> = deps
+        .iter()
+        .map(|dep| {
+            let commit = vendored_commit(repo, dep, ref_prefix);
+            let purl = purl(dep, commit);
+
+            let mut properties = vec![json!({
+                "name": "git-vendor:pattern",
+                "value": dep.pattern,
+            })];
+            if let Some(branch) = &dep.branch {
+                properties.push(json!({ "name": "git-vendor:branch", "value": branch }));
+            }
+
+            let mut component = json!({
+                "type": "library",
+                "bom-ref": purl,
+                "name": dep.name,
+                "purl": purl,
+                "externalReferences": [{ "type": "vcs", "url": dep.url }],
+                "properties": properties,
+            });
+            if let Some(oid) = commit {
+                component["version"] = json!(oid.to_string());
+            }
+            component
+        })
+        .collect();
+
+    json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "version": 1,
+        "metadata": {
+            "tools": {
+                "components": [{
+                    "type": "application",
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                }],
+            },
+        },
+        "components": components,
+    })
+}
+
+/// The upstream commit currently recorded for `dep`, if it has been fetched.
+fn vendored_commit(repo: &Repository, dep: &VendorDep, ref_prefix: &str) -> Option<Oid> {
+    repo.find_reference(&vendor_ref_name(ref_prefix, &dep.name))
+        .ok()?
+        .target()
+}
+
+/// Synthesize a package URL from the dependency's VCS URL and commit.
+///
+/// Well-known forges map to their dedicated purl types; anything else is a
+/// `pkg:generic` package carrying the source in a `vcs_url` qualifier.
+fn purl(dep: &VendorDep, commit: Option<Oid>) -> String {
+    let version = commit.map(|oid| format!("@{oid}")).unwrap_or_default();
+
+    if let Some((host, path)) = split_remote_url(&dep.url) {
+        let forge = match host {
+            "github.com" => Some("github"),
+            "gitlab.com" => Some("gitlab"),
+            "bitbucket.org" => Some("bitbucket"),
+            _ => None,
+        };
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        if let (Some(forge), [owner, repo]) = (forge, segments.as_slice()) {
+            return format!(
+                "pkg:{forge}/{}/{}{version}",
+                percent_encode(&owner.to_lowercase()),
+                percent_encode(&repo.to_lowercase()),
+            );
+        }
+    }
+
+    let name = dep.name.rsplit('/').next().unwrap_or(&dep.name);
+    let vcs_url = format!("git+{}{version}", dep.url);
+    format!(
+        "pkg:generic/{}{version}?vcs_url={}",
+        percent_encode(name),
+        percent_encode(&vcs_url)
+    )
+}
+
+/// Percent-encode everything but RFC 3986 unreserved characters.
+fn percent_encode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~') {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{b:02X}"));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dep(url: &str) -> VendorDep {
+        VendorDep {
+            name: "owner/repo".into(),
+            pattern: "vendor/repo/".into(),
+            url: url.into(),
+            branch: None,
+            ..Default::default()
+        }
+    }
+
+    const SHA: &str = "0123456789abcdef0123456789abcdef01234567";
+
+    #[test]
+    fn purl_github() {
+        let oid = Oid::from_str(SHA).unwrap();
+        assert_eq!(
+            purl(&dep("https://github.com/Owner/Repo.git"), Some(oid)),
+            format!("pkg:github/owner/repo@{SHA}")
+        );
+    }
+
+    #[test]
+    fn purl_github_unfetched_has_no_version() {
+        assert_eq!(
+            purl(&dep("git@github.com:owner/repo.git"), None),
+            "pkg:github/owner/repo"
+        );
+    }
+
+    #[test]
+    fn purl_generic_carries_vcs_url() {
+        let oid = Oid::from_str(SHA).unwrap();
+        assert_eq!(
+            purl(&dep("https://example.com/owner/repo.git"), Some(oid)),
+            format!(
+                "pkg:generic/repo@{SHA}?vcs_url=git%2Bhttps%3A%2F%2Fexample.com%2Fowner%2Frepo.git%40{SHA}"
+            )
+        );
+    }
+
+    #[test]
+    fn percent_encode_keeps_unreserved() {
+        assert_eq!(percent_encode("a-b.c_d~e"), "a-b.c_d~e");
+        assert_eq!(percent_encode("a b/c"), "a%20b%2Fc");
+    }
+}
@@ -0,0 +1,268 @@
+//! Discovery of vendored content that is not tracked yet, for adopting
+//! `git-vendor` in an existing repository.
+//!
+//! A directory is taken for a vendored dependency when it:
+//!
+//! - sits directly in a conventional vendor directory such as `vendor/` or
+//!   `third_party/` (Go-style `vendor/<host>/<owner>/<repo>/` included),
+//! - still contains the `.git` directory of the clone it was copied from,
+//! - or carries a license file of its own.
+//!
+//! The upstream URL is guessed from, in order, the `origin` remote of a
+//! leftover clone, `package.json`, `Cargo.toml` and `go.mod`. Directories
+//! already owned by a dependency, submodules and ignored paths are skipped.
+
+use super::{VendorDep, name_from_url, ownership::Ownership, validate_url};
+use git2::{Error, Repository};
+use std::{fs, path::Path};
+
+/// A directory that looks vendored but is not tracked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    /// The pattern to track, e.g. `vendor/lib/`.
+    pub pattern: String,
+    /// The dependency name derived from [`url`](Self::url).
+    pub name: Option<String>,
+    /// The guessed upstream URL.
+    pub url: Option<String>,
+    /// Why the directory looks vendored.
+    pub reason: String,
+}
+
+/// Directories conventionally holding vendored code.
+const VENDOR_DIRS: [&str; 8] = [
+    "vendor",
+    "vendored",
+    "third_party",
+    "third-party",
+    "thirdparty",
+    "3rdparty",
+    "external",
+    "extern",
+];
+
+const LICENSE_FILES: [&str; 4] = ["LICENSE", "LICENCE", "COPYING", "UNLICENSE"];
+
+/// How deep below the root directories are looked at.
+const MAX_DEPTH: usize = 4;
+
+pub(crate) fn scan(repo: &Repository, deps: &[&VendorDep]) -> Result<Vec<Candidate>, Error> {
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| Error::from_str("Repository has no working directory"))?;
+    let mut scan = Scan {
+        repo,
+        workdir,
+        ownership: Ownership::new(repo, deps)?,
+        gitlinks: gitlinks(repo)?,
+        candidates: Vec::new(),
+    };
+    scan.visit("", 0)?;
+    scan.candidates.sort_by(|a, b| a.pattern.cmp(&b.pattern));
+    Ok(scan.candidates)
+}
+
+struct Scan<'a> {
+    repo: &'a Repository,
+    workdir: &'a Path,
+    ownership: Ownership<'a>,
+    /// Paths of submodules, which are vendored by other means.
+    gitlinks: Vec<String>,
+    candidates: Vec<Candidate>,
+}
+
+impl Scan<'_> {
+    /// Look at the subdirectories of `dir`, relative to the root.
+    fn visit(&mut self, dir: &str, depth: usize) -> Result<(), Error> {
+        let vendor_dir = dir
+            .rsplit('/')
+            .next()
+            .is_some_and(|name| VENDOR_DIRS.contains(&name));
+        for (name, path) in self.subdirectories(dir) {
+            if vendor_dir && name.contains('.') {
+                // A Go-style host directory: vendor/<host>/<owner>/<repo>.
+                for (owner, owner_path) in self.subdirectories(&path) {
+                    for (repo, repo_path) in self.subdirectories(&owner_path) {
+                        let url = format!("https://{name}/{owner}/{repo}");
+                        self.consider(&repo_path, Some(url), &format!("inside {dir}/"));
+                    }
+                }
+                continue;
+            }
+            let absolute = self.workdir.join(&path);
+            let reason = if vendor_dir {
+                Some(format!("inside {dir}/"))
+            } else if absolute.join(".git").exists() {
+                Some("contains a .git directory".to_string())
+            } else {
+                LICENSE_FILES
+                    .iter()
+                    .find(|license| has_file_starting_with(&absolute, license))
+                    .map(|license| format!("has its own {license}"))
+            };
+            match reason {
+                _ if VENDOR_DIRS.contains(&name.as_str()) => self.visit(&path, depth + 1)?,
+                Some(reason) => self.consider(&path, guess_url(&absolute), &reason),
+                None if depth + 1 < MAX_DEPTH => self.visit(&path, depth + 1)?,
+                None => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Record `path` as a candidate unless it is tracked already.
+    fn consider(&mut self, path: &str, url: Option<String>, reason: &str) {
+        let pattern = format!("{path}/");
+        let tracked = self.ownership.owner(&pattern).is_some()
+            || first_file(&self.workdir.join(path))
+                .is_some_and(|file| self.ownership.owner(format!("{path}/{file}")).is_some());
+        if tracked {
+            return;
+        }
+        let url = url.filter(|url| validate_url(url).is_ok());
+        self.candidates.push(Candidate {
+            pattern,
+            name: url.as_deref().and_then(name_from_url),
+            url,
+            reason: reason.to_string(),
+        });
+    }
+
+    /// The subdirectories of `dir` as `(name, path relative to the root)`,
+    /// leaving out `.git`, hidden, ignored and submodule directories.
+    fn subdirectories(&self, dir: &str) -> Vec<(String, String)> {
+        let Ok(entries) = fs::read_dir(self.workdir.join(dir)) else {
+            return Vec::new();
+        };
+        let mut subdirectories: Vec<(String, String)> = entries
+            .flatten()
+            .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_dir()))
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .filter(|name| !name.starts_with('.'))
+            .map(|name| {
+                let path = match dir {
+                    "" => name.clone(),
+                    dir => format!("{dir}/{name}"),
+                };
+                (name, path)
+            })
+            .filter(|(_, path)| {
+                !self.gitlinks.contains(path)
+                    && !self
+                        .repo
+                        .is_path_ignored(format!("{path}/"))
+                        .unwrap_or(false)
+            })
+            .collect();
+        subdirectories.sort();
+        subdirectories
+    }
+}
+
+fn gitlinks(repo: &Repository) -> Result<Vec<String>, Error> {
+    Ok(repo
+        .index()?
+        .iter()
+        .filter(|entry| entry.mode == 0o160000)
+        .filter_map(|entry| String::from_utf8(entry.path).ok())
+        .collect())
+}
+
+fn has_file_starting_with(dir: &Path, prefix: &str) -> bool {
+    fs::read_dir(dir).is_ok_and(|entries| {
+        entries.flatten().any(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .is_some_and(|name| name.to_ascii_uppercase().starts_with(prefix))
+        })
+    })
+}
+
+/// The name of some file directly in `dir`.
+fn first_file(dir: &Path) -> Option<String> {
+    fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_file()))
+        .find_map(|entry| entry.file_name().into_string().ok())
+}
+
+/// Guess the upstream URL of the content in `dir`.
+fn guess_url(dir: &Path) -> Option<String> {
+    if let Ok(clone) = Repository::open(dir)
+        && let Ok(origin) = clone.find_remote("origin")
+        && let Some(url) = origin.url()
+    {
+        return Some(url.to_string());
+    }
+    if let Ok(json) = fs::read_to_string(dir.join("package.json"))
+        && let Ok(package) = serde_json::from_str::<serde_json::Value>(&json)
+    {
+        let repository = &package["repository"];
+        if let Some(url) = repository.as_str().or(repository["url"].as_str()) {
+            return Some(npm_url(url));
+        }
+    }
+    if let Ok(manifest) = fs::read_to_string(dir.join("Cargo.toml"))
+        && let Some(url) = manifest.lines().find_map(|line| {
+            let value = line
+                .strip_prefix("repository")?
+                .trim_start()
+                .strip_prefix('=')?;
+            Some(value.trim().trim_matches('"').to_string())
+        })
+    {
+        return Some(url);
+    }
+    if let Ok(module) = fs::read_to_string(dir.join("go.mod"))
+        && let Some(path) = module
+            .lines()
+            .find_map(|line| line.strip_prefix("module "))
+            .map(str::trim)
+        && path
+            .split('/')
+            .next()
+            .is_some_and(|host| host.contains('.'))
+    {
+        return Some(format!("https://{path}"));
+    }
+    None
+}
+
+/// Expand the `repository` of a `package.json`, which may be a shorthand
+/// such as `github:owner/repo` or `owner/repo`.
+fn npm_url(url: &str) -> String {
+    let url = url.strip_prefix("git+").unwrap_or(url);
+    let shorthand = [
+        ("github:", "github.com"),
+        ("gitlab:", "gitlab.com"),
+        ("bitbucket:", "bitbucket.org"),
+    ];
+    for (prefix, host) in shorthand {
+        if let Some(path) = url.strip_prefix(prefix) {
+            return format!("https://{host}/{path}");
+        }
+    }
+    if !url.contains(':') && url.matches('/').count() == 1 {
+        return format!("https://github.com/{url}");
+    }
+    url.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn npm_url_expands_shorthands() {
+        assert_eq!(
+            npm_url("git+https://github.com/o/r.git"),
+            "https://github.com/o/r.git"
+        );
+        assert_eq!(npm_url("github:o/r"), "https://github.com/o/r");
+        assert_eq!(npm_url("gitlab:o/r"), "https://gitlab.com/o/r");
+        assert_eq!(npm_url("o/r"), "https://github.com/o/r");
+        assert_eq!(npm_url("git@github.com:o/r.git"), "git@github.com:o/r.git");
+    }
+}
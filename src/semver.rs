@@ -0,0 +1,146 @@
+//! Semantic versions as found in upstream tag names.
+//!
+//! Tags such as `v1.2.3`, `1.2.3-rc.1` or `release-1.2.3+build` are
+//! understood: any prefix before the first digit is ignored, a missing
+//! minor or patch number counts as `0`, and versions order by the
+//! precedence rules of Semantic Versioning 2.0.0 (build metadata is
+//! ignored).
+
+use std::cmp::Ordering;
+
+/// A parsed version; compares by semver precedence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+    /// Pre-release identifiers, e.g. `["rc", "1"]` for `-rc.1`.
+    pub pre: Vec<String>,
+}
+
+impl Version {
+    /// Parse the version in a tag name, if it has one.
+    pub fn parse(tag: &str) -> Option<Version> {
+        let start = tag.find(|c: char| c.is_ascii_digit())?;
+        let prefix = &tag[..start];
+        if prefix.chars().any(|c| c.is_ascii_digit() || c == '.') {
+            return None;
+        }
+        let version = &tag[start..];
+        let version = version.split_once('+').map_or(version, |(v, _)| v);
+        let (core, pre) = match version.split_once('-') {
+            Some((core, pre)) => (core, Some(pre)),
+            None => (version, None),
+        };
+
+        let mut numbers = core.split('.').map(|n| {
+            (!n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+                .then(|| n.parse::<u64>().ok())
+                .flatten()
+        });
+        let major = numbers.next()??;
+        let minor = numbers.next().unwrap_or(Some(0))?;
+        let patch = numbers.next().unwrap_or(Some(0))?;
+        if numbers.next().is_some() {
+            return None;
+        }
+
+        let pre = match pre {
+            Some(pre) => {
+                let idents: Vec<String> = pre.split('.').map(str::to_string).collect();
+                let valid = |ident: &String| {
+                    !ident.is_empty()
+                        && ident
+                            .bytes()
+                            .all(|b| b.is_ascii_alphanumeric() || b == b'-')
+                };
+                if !idents.iter().all(valid) {
+                    return None;
+                }
+                idents
+            }
+            None => Vec::new(),
+        };
+
+        Some(Version {
+            major,
+            minor,
+            patch,
+            pre,
+        })
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.major, self.minor, self.patch)
+            .cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| match (self.pre.is_empty(), other.pre.is_empty()) {
+                (true, true) => Ordering::Equal,
+                // A pre-release ranks below its release.
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => compare_pre(&self.pre, &other.pre),
+            })
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+fn compare_pre(a: &[String], b: &[String]) -> Ordering {
+    for (x, y) in a.iter().zip(b) {
+        let ordering = match (x.parse::<u64>(), y.parse::<u64>()) {
+            (Ok(x), Ok(y)) => x.cmp(&y),
+            // Numeric identifiers rank below alphanumeric ones.
+            (Ok(_), Err(_)) => Ordering::Less,
+            (Err(_), Ok(_)) => Ordering::Greater,
+            (Err(_), Err(_)) => x.cmp(y),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    a.len().cmp(&b.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_tag_names() {
+        let v = Version::parse("v1.2.3-rc.1+build.5").unwrap();
+        assert_eq!((v.major, v.minor, v.patch), (1, 2, 3));
+        assert_eq!(v.pre, ["rc", "1"]);
+        assert_eq!(Version::parse("release-2.0"), Version::parse("2.0.0"));
+        assert!(Version::parse("latest").is_none());
+        assert!(Version::parse("1.2.3.4").is_none());
+        assert!(Version::parse("1..2").is_none());
+        assert!(Version::parse("1.2.x").is_none());
+    }
+
+    #[test]
+    fn precedence_follows_semver() {
+        let ordered = [
+            "1.0.0-alpha",
+            "1.0.0-alpha.1",
+            "1.0.0-alpha.beta",
+            "1.0.0-beta",
+            "1.0.0-beta.2",
+            "1.0.0-beta.11",
+            "1.0.0-rc.1",
+            "1.0.0",
+            "v1.0.1",
+            "1.10.0",
+        ];
+        let versions: Vec<Version> = ordered
+            .iter()
+            .map(|tag| Version::parse(tag).unwrap())
+            .collect();
+        assert!(versions.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+}
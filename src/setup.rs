@@ -0,0 +1,176 @@
+//! One-step wiring of `git-vendor` into a repository.
+//!
+//! Setup configures:
+//!
+//! - short git aliases for the common commands,
+//! - the pre-commit hook guarding vendored paths (see [`crate::guard`]),
+//! - a `vendored` merge driver which, instead of writing conflict markers
+//!   into vendored files, keeps ours and leaves the path conflicted,
+//! - `merge=vendored -diff` for each tracked pattern in
+//!   `.git/info/attributes`, which applies to this clone only,
+//! - recommended `vendor.*` defaults.
+//!
+//! Every step only fills in what is missing, so setup can be run again
+//! after tracking new dependencies. Aliases, the merge driver and the
+//! defaults already configured differently are kept unless forced. With
+//! `global`, they are written to the user's global config instead, so
+//! they apply to every repository; the hook and attributes are always
+//! per repository.
+
+use super::{VendorDep, guard, replace_block};
+use git_set_attr::quote_pattern;
+use git2::{Config, ConfigLevel, Error, ErrorCode, Repository};
+use std::fs;
+
+/// Options for [`crate::Vendor::vendor_setup`].
+#[derive(Debug, Clone, Default)]
+pub struct SetupOpts {
+    /// Write aliases, the merge driver and defaults to the global config.
+    pub global: bool,
+    /// Replace values and a pre-commit hook not written by setup.
+    pub force: bool,
+}
+
+/// One step of [`crate::Vendor::vendor_setup`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetupStep {
+    /// What the step configures, e.g. `alias.vstatus`.
+    pub what: String,
+    /// Whether this run changed it.
+    pub changed: bool,
+    /// Why an unchanged step was not applied, if it was not already done.
+    pub skipped: Option<String>,
+}
+
+const ALIASES: [(&str, &str); 4] = [
+    ("alias.vstatus", "vendor status"),
+    ("alias.vfetch", "vendor fetch"),
+    ("alias.vmerge", "vendor merge"),
+    ("alias.vupdate", "!git vendor fetch && git vendor merge"),
+];
+
+/// The name of the merge driver, as used by the `merge=` attribute.
+const DRIVER: &str = "vendored";
+
+const DRIVER_CONFIG: [(&str, &str); 2] = [
+    (
+        "merge.vendored.name",
+        "vendored content, resolved by git vendor merge",
+    ),
+    (
+        "merge.vendored.driver",
+        "echo '%P: vendored content changed on both sides; \
+         check out one side or run git vendor merge' >&2; false",
+    ),
+];
+
+const BEGIN_MARKER: &str = "# BEGIN git-vendor setup";
+const END_MARKER: &str = "# END git-vendor setup";
+
+pub(crate) fn setup(
+    repo: &Repository,
+    deps: &[&VendorDep],
+    opts: &SetupOpts,
+) -> Result<Vec<SetupStep>, Error> {
+    let (mut target, merged) = if opts.global {
+        let config = Config::open_default()?;
+        (config.open_level(ConfigLevel::Global)?, config)
+    } else {
+        let config = repo.config()?;
+        (config.open_level(ConfigLevel::Local)?, config)
+    };
+    let jobs = std::thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(8)
+        .to_string();
+    let defaults = [("vendor.jobs", jobs.as_str()), ("vendor.prune", "true")];
+
+    let mut steps = Vec::new();
+    for (key, value) in ALIASES.into_iter().chain(DRIVER_CONFIG).chain(defaults) {
+        steps.push(set(&mut target, &merged, key, value, opts.force)?);
+    }
+    steps.push(hook(repo, opts.force)?);
+    steps.push(attributes(repo, deps)?);
+    Ok(steps)
+}
+
+/// Set `key` in `target` unless `merged` already has a value for it.
+fn set(
+    target: &mut Config,
+    merged: &Config,
+    key: &str,
+    value: &str,
+    force: bool,
+) -> Result<SetupStep, Error> {
+    let existing = match merged.get_string(key) {
+        Ok(existing) => Some(existing),
+        Err(e) if e.code() == ErrorCode::NotFound => None,
+        Err(e) => return Err(e),
+    };
+    let mut step = SetupStep {
+        what: key.to_string(),
+        changed: false,
+        skipped: None,
+    };
+    match existing {
+        Some(existing) if existing == value => {}
+        Some(existing) if !force => {
+            step.skipped = Some(format!("already set to '{existing}'"));
+        }
+        _ => {
+            target.set_str(key, value)?;
+            step.changed = true;
+        }
+    }
+    Ok(step)
+}
+
+fn hook(repo: &Repository, force: bool) -> Result<SetupStep, Error> {
+    let path = guard::hook_path(repo)?;
+    let mut step = SetupStep {
+        what: path.display().to_string(),
+        changed: false,
+        skipped: None,
+    };
+    if guard::installed(repo)? {
+        return Ok(step);
+    }
+    if path.exists() && !force {
+        step.skipped = Some("a pre-commit hook not written by git-vendor exists".into());
+        return Ok(step);
+    }
+    guard::install(repo, true, force)?;
+    step.changed = true;
+    Ok(step)
+}
+
+/// Mark each pattern of `deps` `merge=vendored -diff` in
+/// `.git/info/attributes`, in a section rewritten on every run.
+fn attributes(repo: &Repository, deps: &[&VendorDep]) -> Result<SetupStep, Error> {
+    let path = repo.path().join("info").join("attributes");
+    let mut section = format!("{BEGIN_MARKER}\n");
+    for dep in deps {
+        for pattern in dep.patterns() {
+            section.push_str(&format!(
+                "{} merge={DRIVER} -diff\n",
+                quote_pattern(pattern)
+            ));
+        }
+    }
+    section.push_str(END_MARKER);
+    section.push('\n');
+
+    let content = fs::read_to_string(&path).unwrap_or_default();
+    let updated = replace_block(&content, BEGIN_MARKER, END_MARKER, &section);
+    let changed = updated != content;
+    if changed {
+        fs::create_dir_all(path.parent().expect("info/attributes has a parent"))
+            .and_then(|()| fs::write(&path, updated))
+            .map_err(|e| Error::from_str(&format!("Failed to write {}: {e}", path.display())))?;
+    }
+    Ok(SetupStep {
+        what: path.display().to_string(),
+        changed,
+        skipped: None,
+    })
+}
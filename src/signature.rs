@@ -0,0 +1,180 @@
+//! OpenPGP signature verification of upstream commits and tags.
+//!
+//! Signatures are checked with `gpg` (or `gpg.program`) against the keyring
+//! named by `vendor.keyring`, falling back to the user's default keyring.
+
+use git2::{Error, Oid, Repository, Tag};
+use std::{
+    fs,
+    io::Write,
+    process::{Command, Stdio},
+};
+
+/// Verify that `oid` carries a good signature from a known key.
+///
+/// Returns the fingerprint of the signing key.
+pub(crate) fn verify_commit(repo: &Repository, oid: Oid) -> Result<String, Error> {
+    let (signature, signed_data) = repo
+        .extract_signature(&oid, None)
+        .map_err(|_| Error::from_str(&format!("Upstream commit {oid} is not signed")))?;
+    verify(
+        repo,
+        &format!("Upstream commit {oid}"),
+        &signature,
+        &signed_data,
+    )
+}
+
+/// Verify that the annotated tag `tag` carries a good signature from a
+/// known key.
+///
+/// Returns the fingerprint of the signing key.
+pub(crate) fn verify_tag(repo: &Repository, tag: &Tag) -> Result<String, Error> {
+    let name = tag.name().unwrap_or("?");
+    let odb = repo.odb()?;
+    let object = odb.read(tag.id())?;
+    let (signed_data, signature) = split_tag_signature(object.data())
+        .ok_or_else(|| Error::from_str(&format!("Upstream tag {name} is not signed")))?;
+    verify(
+        repo,
+        &format!("Upstream tag {name}"),
+        signature,
+        signed_data,
+    )
+}
+
+/// Split the raw content of a tag object into the signed data and the
+/// signature git appends to the message.
+fn split_tag_signature(data: &[u8]) -> Option<(&[u8], &[u8])> {
+    const BEGIN: &[u8] = b"-----BEGIN PGP SIGNATURE-----";
+    let start = (0..data.len())
+        .rev()
+        .find(|&i| (i == 0 || data[i - 1] == b'\n') && data[i..].starts_with(BEGIN))?;
+    Some(data.split_at(start))
+}
+
+/// Check `signature` over `signed_data` of `what` with gpg, returning the
+/// signing key fingerprint.
+fn verify(
+    repo: &Repository,
+    what: &str,
+    signature: &[u8],
+    signed_data: &[u8],
+) -> Result<String, Error> {
+    let config = repo.config()?;
+    let program = config
+        .get_string("gpg.program")
+        .unwrap_or_else(|_| "gpg".to_string());
+    let keyring = config.get_path("vendor.keyring").ok();
+
+    let sig_path = repo.path().join("VENDOR_SIGNATURE");
+    fs::write(&sig_path, signature).map_err(|e| Error::from_str(&e.to_string()))?;
+
+    let mut cmd = Command::new(&program);
+    cmd.args(["--batch", "--no-tty", "--status-fd=1"]);
+    if let Some(keyring) = &keyring {
+        cmd.arg("--no-default-keyring")
+            .arg("--keyring")
+            .arg(keyring);
+    }
+    cmd.arg("--verify")
+        .arg(&sig_path)
+        .arg("-")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null());
+
+    let output = cmd
+        .spawn()
+        .and_then(|mut child| {
+            if let Some(mut stdin) = child.stdin.take() {
+                stdin.write_all(signed_data)?;
+            }
+            child.wait_with_output()
+        })
+        .map_err(|e| Error::from_str(&format!("Failed to run {program}: {e}")));
+    let _ = fs::remove_file(&sig_path);
+    let output = output?;
+
+    parse_status(&String::from_utf8_lossy(&output.stdout))
+        .map_err(|reason| Error::from_str(&format!("{what}: {reason}")))
+}
+
+/// Interpret `gpg --status-fd` output, returning the signing key fingerprint
+/// for a good signature or a reason for rejecting it.
+fn parse_status(status: &str) -> Result<String, String> {
+    let mut good = false;
+    let mut fingerprint = None;
+
+    for line in status.lines() {
+        let mut fields = line.strip_prefix("[GNUPG:] ").unwrap_or("").split(' ');
+        match fields.next() {
+            Some("GOODSIG") => good = true,
+            Some("VALIDSIG") => fingerprint = fields.next().map(str::to_string),
+            Some("BADSIG") => return Err("bad signature".into()),
+            Some("EXPSIG" | "EXPKEYSIG") => return Err("signature made by an expired key".into()),
+            Some("REVKEYSIG") => return Err("signature made by a revoked key".into()),
+            Some("NO_PUBKEY") => {
+                let key = fields.next().unwrap_or("?");
+                return Err(format!("signed by unknown key {key}"));
+            }
+            _ => {}
+        }
+    }
+
+    match (good, fingerprint) {
+        (true, Some(fpr)) => Ok(fpr),
+        _ => Err("signature could not be verified".into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_status_good_signature() {
+        let status = "\
+[GNUPG:] NEWSIG
+[GNUPG:] GOODSIG 0123456789ABCDEF Test <test@test>
+[GNUPG:] VALIDSIG FPR0123456789 2026-01-01 1767225600 0 4 0 22 8 00 FPR0123456789
+";
+        assert_eq!(parse_status(status), Ok("FPR0123456789".into()));
+    }
+
+    #[test]
+    fn parse_status_unknown_key() {
+        let status = "\
+[GNUPG:] ERRSIG 0123456789ABCDEF 22 8 00 1767225600 9 -
+[GNUPG:] NO_PUBKEY 0123456789ABCDEF
+";
+        assert_eq!(
+            parse_status(status),
+            Err("signed by unknown key 0123456789ABCDEF".into())
+        );
+    }
+
+    #[test]
+    fn parse_status_bad_signature() {
+        assert_eq!(
+            parse_status("[GNUPG:] BADSIG 0123456789ABCDEF Test <test@test>\n"),
+            Err("bad signature".into())
+        );
+    }
+
+    #[test]
+    fn parse_status_empty_is_rejected() {
+        assert!(parse_status("").is_err());
+    }
+
+    #[test]
+    fn split_tag_signature_at_last_signature_line() {
+        let tag = b"object 0123\ntype commit\ntag v1\n\n\
+                    v1 -----BEGIN PGP SIGNATURE----- in text\n\
+                    -----BEGIN PGP SIGNATURE-----\nsig\n-----END PGP SIGNATURE-----\n";
+        let (data, signature) = split_tag_signature(tag).unwrap();
+        assert!(data.ends_with(b"in text\n"));
+        assert!(signature.starts_with(b"-----BEGIN PGP SIGNATURE-----\nsig"));
+        assert_eq!(split_tag_signature(b"object 0123\n\nunsigned\n"), None);
+    }
+}
@@ -0,0 +1,256 @@
+//! Where dependency definitions are stored.
+//!
+//! Dependencies are defined by `.gitattributes` vendor lines unless a
+//! [`MANIFEST_FILE`] exists at the repository root, in which case the
+//! manifest is the source of truth and `.gitattributes` only carries the
+//! `vendored` attribute marking each pattern.
+
+use super::{
+    VendorDep, is_vendor_line_for_pattern, manifest, migrate, parse_vendor_deps,
+    remove_vendor_lines,
+};
+use git_set_attr::{AttrFile, SetAttr, TextFormat, split_pattern};
+use git2::{Error, Repository};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+pub use manifest::MANIFEST_FILE;
+
+/// A store of dependency definitions.
+pub trait DependencySource {
+    /// The file holding the definitions.
+    fn path(&self) -> &Path;
+
+    /// Read every dependency.
+    fn load(&self) -> Result<Vec<VendorDep>, Error>;
+
+    /// The 1-based line of [`DependencySource::path`] defining `dep`, if
+    /// any.
+    fn line(&self, dep: &VendorDep) -> Result<Option<usize>, Error>;
+
+    /// Add `dep`, replacing the name, URL, branch and rev of any dependency
+    /// already tracking its pattern, and mark its pattern `vendored` in
+    /// `.gitattributes` along with the `marks` attributes.
+    fn upsert(&self, dep: &VendorDep, marks: &[&str]) -> Result<(), Error>;
+
+    /// Stop tracking `pattern`, removing its dependency unless the
+    /// dependency has other patterns.
+    fn remove(&self, pattern: &str) -> Result<(), Error>;
+}
+
+/// Definitions kept as `.gitattributes` vendor lines.
+pub struct Attributes<'r> {
+    repo: &'r Repository,
+    path: PathBuf,
+}
+
+/// Definitions kept in a `.gitvendor.toml` manifest.
+pub struct Manifest<'r> {
+    repo: &'r Repository,
+    path: PathBuf,
+    gitattributes: PathBuf,
+}
+
+/// Open the dependency source of `repo`, whose `.gitattributes` file is
+/// `gitattributes`.
+pub fn open<'r>(
+    repo: &'r Repository,
+    gitattributes: &Path,
+) -> Result<Box<dyn DependencySource + 'r>, Error> {
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| Error::from_str("Repository has no working directory"))?;
+    let manifest = workdir.join(MANIFEST_FILE);
+    if manifest.exists() {
+        return Ok(Box::new(Manifest {
+            repo,
+            path: manifest,
+            gitattributes: gitattributes.to_path_buf(),
+        }));
+    }
+    Ok(Box::new(Attributes {
+        repo,
+        path: gitattributes.to_path_buf(),
+    }))
+}
+
+impl DependencySource for Attributes<'_> {
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn load(&self) -> Result<Vec<VendorDep>, Error> {
+        parse_vendor_deps(&self.path)
+    }
+
+    fn line(&self, dep: &VendorDep) -> Result<Option<usize>, Error> {
+        Ok(read(&self.path)?
+            .lines()
+            .position(|line| is_vendor_line_for_pattern(line, &dep.pattern))
+            .map(|i| i + 1))
+    }
+
+    fn upsert(&self, dep: &VendorDep, marks: &[&str]) -> Result<(), Error> {
+        let mut definition = vec![format!("name={}", dep.name), format!("url={}", dep.url)];
+        if let Some(branch) = &dep.branch {
+            definition.push(format!("branch={branch}"));
+        }
+        if let Some(rev) = &dep.rev {
+            definition.push(format!("rev={rev}"));
+        }
+        if let Some(prefix) = &dep.prefix {
+            definition.push(format!("prefix={prefix}"));
+        }
+        self.replace_definition(&dep.pattern, &definition)?;
+
+        let name_attr = format!("name={}", dep.name);
+        let url_attr = format!("url={}", dep.url);
+        let mut attrs: Vec<&str> = vec!["vendored", &name_attr, &url_attr];
+
+        let branch_attr;
+        if let Some(branch) = &dep.branch {
+            branch_attr = format!("branch={branch}");
+            attrs.push(&branch_attr);
+        }
+        let rev_attr;
+        if let Some(rev) = &dep.rev {
+            rev_attr = format!("rev={rev}");
+            attrs.push(&rev_attr);
+        }
+        let prefix_attr;
+        if let Some(prefix) = &dep.prefix {
+            prefix_attr = format!("prefix={prefix}");
+            attrs.push(&prefix_attr);
+        }
+        attrs.extend_from_slice(marks);
+
+        self.repo
+            .set_attr(&dep.pattern, &attrs, AttrFile::Path(&self.path))
+    }
+
+    fn remove(&self, pattern: &str) -> Result<(), Error> {
+        let grouped = self
+            .load()?
+            .into_iter()
+            .find(|d| d.pattern == pattern && !d.extra_patterns.is_empty());
+        remove_vendor_lines(self.repo, &self.path, pattern)?;
+
+        // Move the definition to the next pattern of the dependency.
+        let Some(mut dep) = grouped else {
+            return Ok(());
+        };
+        dep.pattern = dep.extra_patterns.remove(0);
+        self.replace_definition(&dep.pattern, &migrate::definition(&dep)?)
+    }
+}
+
+impl Attributes<'_> {
+    /// Put `definition` on the existing vendor line for `pattern` in place
+    /// of the attributes with the same keys (and any `branch=` and `rev=`),
+    /// which
+    /// `set_attr` would only append to.
+    fn replace_definition(&self, pattern: &str, definition: &[String]) -> Result<(), Error> {
+        if !self.path.exists() {
+            return Ok(());
+        }
+        let content = fs::read_to_string(&self.path).map_err(|e| {
+            Error::from_str(&format!("Failed to read {}: {e}", self.path.display()))
+        })?;
+        let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+        let Some(line) = lines
+            .iter_mut()
+            .find(|line| is_vendor_line_for_pattern(line, pattern))
+        else {
+            return Ok(());
+        };
+
+        let key = |attr: &str| attr.split('=').next().unwrap_or_default().to_string();
+        let mut replaced: Vec<String> = definition.iter().map(|attr| key(attr)).collect();
+        replaced.extend(["branch".into(), "rev".into()]);
+        // Keep the pattern as written, quotes and all.
+        let rest = split_pattern(line).map_or("", |(_, rest)| rest);
+        let head = line[..line.len() - rest.len()].trim().to_string();
+        let mut parts: Vec<String> = rest.split_whitespace().map(str::to_string).collect();
+        parts.retain(|attr| !replaced.contains(&key(attr)));
+        let at = parts
+            .iter()
+            .position(|attr| attr == "vendored")
+            .map_or(parts.len(), |i| i + 1);
+        parts.splice(at..at, definition.iter().cloned());
+        parts.insert(0, head);
+        *line = parts.join(" ");
+
+        let updated: String = lines.iter().map(|line| format!("{line}\n")).collect();
+        fs::write(&self.path, TextFormat::detect(&content).apply(&updated))
+            .map_err(|e| Error::from_str(&format!("Failed to write {}: {e}", self.path.display())))
+    }
+}
+
+impl Manifest<'_> {
+    fn write(&self, deps: &[VendorDep]) -> Result<(), Error> {
+        fs::write(&self.path, manifest::render(deps))
+            .map_err(|e| Error::from_str(&format!("Failed to write {}: {e}", self.path.display())))
+    }
+}
+
+impl DependencySource for Manifest<'_> {
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn load(&self) -> Result<Vec<VendorDep>, Error> {
+        manifest::parse(&read(&self.path)?)
+    }
+
+    fn line(&self, dep: &VendorDep) -> Result<Option<usize>, Error> {
+        Ok(manifest::table_line(&read(&self.path)?, &dep.pattern))
+    }
+
+    fn upsert(&self, dep: &VendorDep, marks: &[&str]) -> Result<(), Error> {
+        let mut deps = self.load()?;
+        match deps
+            .iter_mut()
+            .find(|d| d.patterns().contains(&dep.pattern.as_str()))
+        {
+            Some(existing) => {
+                existing.name = dep.name.clone();
+                existing.url = dep.url.clone();
+                existing.branch = dep.branch.clone();
+                existing.rev = dep.rev.clone();
+                if dep.prefix.is_some() {
+                    existing.prefix = dep.prefix.clone();
+                }
+            }
+            None => deps.push(dep.clone()),
+        }
+        self.write(&deps)?;
+
+        let mut attrs = vec!["vendored"];
+        attrs.extend_from_slice(marks);
+        self.repo
+            .set_attr(&dep.pattern, &attrs, AttrFile::Path(&self.gitattributes))
+    }
+
+    fn remove(&self, pattern: &str) -> Result<(), Error> {
+        let mut deps = self.load()?;
+        for dep in &mut deps {
+            if dep.pattern == pattern && !dep.extra_patterns.is_empty() {
+                dep.pattern = dep.extra_patterns.remove(0);
+            }
+            dep.extra_patterns.retain(|p| p != pattern);
+        }
+        deps.retain(|d| d.pattern != pattern);
+        self.write(&deps)
+    }
+}
+
+/// The content of `path`, or nothing if it does not exist.
+fn read(path: &Path) -> Result<String, Error> {
+    if !path.exists() {
+        return Ok(String::new());
+    }
+    fs::read_to_string(path)
+        .map_err(|e| Error::from_str(&format!("Failed to read {}: {e}", path.display())))
+}
@@ -0,0 +1,183 @@
+//! Summaries of dependencies kept between runs, so `status` stays cheap when
+//! run from a shell prompt or an editor.
+//!
+//! Each [`Overview`] is stored in `.git/vendor/status.json` along with what
+//! it was computed from: the dependency's definition, its vendor refs, and
+//! its provenance record and content in `HEAD`. It is reused while all of
+//! those are unchanged, with only its age brought up to date; while `HEAD`
+//! itself is unchanged, not even the content is looked at.
+//!
+//! The cache is only an optimization: one that cannot be read is ignored
+//! and one that cannot be written is left as it was.
+
+use super::{
+    VendorDep, config::Config, overview, overview::Overview, parse_time, provenance,
+    vendor_gone_ref_name, vendor_ref_name,
+};
+use git_filter_tree::FilterTree;
+use git2::{Commit, Error, Repository, Tree};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+/// The state a summary was computed from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Inputs {
+    /// The dependency as defined, including its settings.
+    definition: String,
+    reference: String,
+    latest: Option<String>,
+    gone: bool,
+    /// The provenance record in `HEAD`, by blob id.
+    record: Option<String>,
+    /// The vendored content in `HEAD`, by tree id.
+    content: String,
+    /// Without a record, the merged commit is recovered from the history of
+    /// `HEAD`, so it is part of the state.
+    history: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry {
+    /// The `HEAD` commit the summary was last checked against.
+    head: String,
+    inputs: Inputs,
+    overview: Overview,
+}
+
+/// The cached summaries, read when opened and written back by [`save`](Self::save).
+pub(crate) struct StatusCache {
+    entries: BTreeMap<String, Entry>,
+    before: BTreeMap<String, Entry>,
+    prefix: String,
+}
+
+impl StatusCache {
+    /// Read the cache of `repo`, dropping the entries of dependencies not
+    /// among `tracked`.
+    pub(crate) fn open(repo: &Repository, tracked: &[VendorDep]) -> Result<Self, Error> {
+        let before = load(repo);
+        let mut entries = before.clone();
+        entries.retain(|name, _| tracked.iter().any(|dep| &dep.name == name));
+        Ok(Self {
+            entries,
+            before,
+            prefix: Config::resolve(repo)?.ref_prefix,
+        })
+    }
+
+    /// Summarize `dep` as of `head`, whose tree is `tree`, like
+    /// [`overview::overview`], reusing the cached summary if nothing it
+    /// depends on changed.
+    pub(crate) fn overview(
+        &mut self,
+        repo: &Repository,
+        head: &Commit,
+        tree: &Tree,
+        dep: &VendorDep,
+        now: i64,
+    ) -> Result<Overview, Error> {
+        let reference = vendor_ref_name(&self.prefix, &dep.name);
+        let definition = format!("{dep:?}");
+        let latest = repo
+            .refname_to_id(&reference)
+            .ok()
+            .map(|oid| oid.to_string());
+        let gone = repo
+            .find_reference(&vendor_gone_ref_name(&dep.name))
+            .is_ok();
+        let cached = self.entries.get(&dep.name).filter(|entry| {
+            entry.inputs.definition == definition
+                && entry.inputs.reference == reference
+                && entry.inputs.latest == latest
+                && entry.inputs.gone == gone
+        });
+        if let Some(entry) = cached
+            && entry.head == head.id().to_string()
+        {
+            tracing::trace!(name = %dep.name, "Using cached status");
+            return Ok(aged(entry.overview.clone(), now));
+        }
+
+        let record = tree
+            .get_path(provenance::provenance_path(&dep.name).as_ref())
+            .ok()
+            .map(|entry| entry.id().to_string());
+        let inputs = Inputs {
+            definition,
+            reference,
+            latest,
+            gone,
+            history: record.is_none().then(|| head.id().to_string()),
+            record,
+            content: repo
+                .filter_by_patterns(tree, &dep.patterns())?
+                .id()
+                .to_string(),
+        };
+        let overview = match cached {
+            Some(entry) if entry.inputs == inputs => {
+                tracing::trace!(name = %dep.name, "Using cached status for unchanged content");
+                aged(entry.overview.clone(), now)
+            }
+            _ => overview::overview(repo, tree, dep, now)?,
+        };
+        self.entries.insert(
+            dep.name.clone(),
+            Entry {
+                head: head.id().to_string(),
+                inputs,
+                overview: overview.clone(),
+            },
+        );
+        Ok(overview)
+    }
+
+    /// Write the cache back to `repo` if it changed.
+    pub(crate) fn save(&mut self, repo: &Repository) {
+        if !same(&self.before, &self.entries) {
+            save(repo, &self.entries);
+            self.before = self.entries.clone();
+        }
+    }
+}
+
+/// `overview` with its age brought up to `now`.
+fn aged(mut overview: Overview, now: i64) -> Overview {
+    if let Some(merged) = overview.merged_at.as_deref().and_then(parse_time) {
+        overview.age_days = Some((now - merged) / 86_400);
+    }
+    overview
+}
+
+fn same(a: &BTreeMap<String, Entry>, b: &BTreeMap<String, Entry>) -> bool {
+    a.len() == b.len()
+        && a.iter().zip(b).all(|((a_name, a), (b_name, b))| {
+            a_name == b_name && a.head == b.head && a.inputs == b.inputs
+        })
+}
+
+fn path(repo: &Repository) -> PathBuf {
+    repo.path().join("vendor").join("status.json")
+}
+
+fn load(repo: &Repository) -> BTreeMap<String, Entry> {
+    fs::read_to_string(path(repo))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn save(repo: &Repository, entries: &BTreeMap<String, Entry>) {
+    let path = path(repo);
+    let tmp = path.with_extension("json.tmp");
+    let written = serde_json::to_string(entries)
+        .map_err(std::io::Error::other)
+        .and_then(|json| {
+            fs::create_dir_all(path.parent().expect("status cache has a parent"))?;
+            fs::write(&tmp, json)?;
+            fs::rename(&tmp, &path)
+        });
+    if let Err(e) = written {
+        tracing::debug!(path = %path.display(), "Failed to write status cache: {e}");
+    }
+}
@@ -0,0 +1,84 @@
+//! Reconciling the checkout with the recorded provenance of each
+//! dependency.
+//!
+//! The provenance records committed under `.git-vendor/` pin every merged
+//! dependency to an upstream commit and the filtered tree merged from it.
+//! Syncing restores the index and working tree to those trees. A fresh
+//! clone lacks the filtered trees themselves whenever the vendored content
+//! was edited after the merge, so they are derived again from the recorded
+//! upstream commits, fetching those if needed.
+
+use super::{VendorDep, config::Config, fetch_commit, provenance, restore, upstream_tree};
+use git_filter_tree::FilterTree;
+use git2::{Error, Oid, Repository, Tree};
+use serde::Serialize;
+
+/// A dependency whose checkout differed from its provenance record.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OutOfSync {
+    pub name: String,
+    pub pattern: String,
+    /// Paths that differed, sorted.
+    pub paths: Vec<String>,
+}
+
+/// Restore `dep` to its recorded tree, or with `check` only report the
+/// paths that differ. Returns `None` when nothing differs or `dep` has
+/// never been merged.
+pub(crate) fn sync(
+    repo: &Repository,
+    head_tree: &Tree,
+    deps: &[&VendorDep],
+    dep: &VendorDep,
+    check: bool,
+    config: &Config,
+) -> Result<Option<OutOfSync>, Error> {
+    let Some(record) = provenance::read(repo, head_tree, &dep.name)? else {
+        return Ok(None);
+    };
+    let recorded = parse_oid(&record.tree)?;
+    if repo.find_tree(recorded).is_err() {
+        rederive(repo, head_tree, dep, &record, recorded, config)?;
+    }
+
+    let paths = restore::restore(repo, head_tree, deps, dep, check)?;
+    Ok((!paths.is_empty()).then(|| OutOfSync {
+        name: dep.name.clone(),
+        pattern: dep.pattern.clone(),
+        paths,
+    }))
+}
+
+/// Write `dep`'s recorded tree to the object database, from the vendored
+/// content if unmodified, otherwise from the recorded upstream commit.
+fn rederive(
+    repo: &Repository,
+    head_tree: &Tree,
+    dep: &VendorDep,
+    record: &provenance::Provenance,
+    recorded: Oid,
+    config: &Config,
+) -> Result<(), Error> {
+    if repo.filter_by_patterns(head_tree, &dep.patterns())?.id() == recorded {
+        return Ok(());
+    }
+
+    let commit = parse_oid(&record.commit)?;
+    if repo.find_commit(commit).is_err() {
+        fetch_commit(repo, dep, commit, config)?;
+    }
+    let upstream = repo.find_commit(commit)?.tree()?;
+    let derived = upstream_tree(repo, dep, &upstream)?.id();
+    if derived != recorded {
+        return Err(Error::from_str(&format!(
+            "Recorded tree {recorded} for {} does not match commit {commit}, which filters to {derived}",
+            dep.name
+        )));
+    }
+    Ok(())
+}
+
+fn parse_oid(s: &str) -> Result<Oid, Error> {
+    Oid::from_str(s)
+        .map_err(|_| Error::from_str(&format!("Invalid object id in provenance record: {s}")))
+}
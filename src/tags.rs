@@ -0,0 +1,131 @@
+//! Listing of a dependency's upstream tags.
+//!
+//! Tags come from the refs fetched under `refs/vendor-tags/<name>/`, or
+//! straight from the upstream with `git ls-remote`. Tags naming a semantic
+//! version sort by version, after any that do not, so the newest release
+//! is listed last.
+
+use super::{
+    VendorDep, config::Config, protocol, provenance, semver::Version, vendor_tags_ref_prefix,
+};
+use git2::{Error, Oid, Repository};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// An upstream tag of a dependency.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UpstreamTag {
+    /// The tag name, e.g. `v1.2.3`.
+    pub name: String,
+    /// The commit the tag points to.
+    pub commit: String,
+    /// Whether this commit is the one currently vendored.
+    pub vendored: bool,
+}
+
+pub(crate) fn list(
+    repo: &Repository,
+    dep: &VendorDep,
+    remote: bool,
+    config: &Config,
+) -> Result<Vec<UpstreamTag>, Error> {
+    let commits = if remote {
+        config.require_online("list remote tags")?;
+        advertised(repo, dep)?
+    } else {
+        fetched(repo, dep)?
+    };
+
+    let vendored = match repo.head().and_then(|head| head.peel_to_tree()) {
+        Ok(tree) => provenance::read(repo, &tree, &dep.name)?
+            .and_then(|record| Oid::from_str(&record.commit).ok()),
+        Err(_) => None,
+    };
+
+    let mut tags: Vec<UpstreamTag> = commits
+        .into_iter()
+        .map(|(name, commit)| UpstreamTag {
+            vendored: Some(commit) == vendored,
+            commit: commit.to_string(),
+            name,
+        })
+        .collect();
+    sort(&mut tags);
+    Ok(tags)
+}
+
+/// Tags fetched for `dep`, peeled to commits.
+fn fetched(repo: &Repository, dep: &VendorDep) -> Result<BTreeMap<String, Oid>, Error> {
+    let prefix = vendor_tags_ref_prefix(&dep.name);
+    let mut tags = BTreeMap::new();
+    for reference in repo.references_glob(&format!("{prefix}*"))? {
+        let reference = reference?;
+        let Some(tag) = reference.name().and_then(|n| n.strip_prefix(&prefix)) else {
+            continue;
+        };
+        if let Ok(commit) = reference.peel_to_commit() {
+            tags.insert(tag.to_string(), commit.id());
+        }
+    }
+    Ok(tags)
+}
+
+/// Tags the upstream of `dep` advertises, peeled to commits.
+fn advertised(repo: &Repository, dep: &VendorDep) -> Result<BTreeMap<String, Oid>, Error> {
+    let refs = protocol::ls_remote(repo, dep, &["refs/tags/*"])?;
+    let mut tags = BTreeMap::new();
+    // An annotated tag is listed twice; the `^{}` line has its commit.
+    for (name, oid) in &refs {
+        if let Some(tag) = name.strip_prefix("refs/tags/")
+            && !tag.ends_with("^{}")
+        {
+            tags.insert(tag.to_string(), *oid);
+        }
+    }
+    for (name, oid) in &refs {
+        if let Some(tag) = name
+            .strip_prefix("refs/tags/")
+            .and_then(|n| n.strip_suffix("^{}"))
+        {
+            tags.insert(tag.to_string(), *oid);
+        }
+    }
+    Ok(tags)
+}
+
+/// Sort tags without a version by name, followed by the others by version
+/// precedence.
+fn sort(tags: &mut [UpstreamTag]) {
+    tags.sort_by(|a, b| {
+        match (Version::parse(&a.name), Version::parse(&b.name)) {
+            (Some(x), Some(y)) => x.cmp(&y),
+            (Some(_), None) => std::cmp::Ordering::Greater,
+            (None, Some(_)) => std::cmp::Ordering::Less,
+            (None, None) => std::cmp::Ordering::Equal,
+        }
+        .then_with(|| a.name.cmp(&b.name))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sort_puts_versions_last_in_precedence_order() {
+        let mut tags: Vec<UpstreamTag> = ["v1.10.0", "nightly", "v1.2.0", "v1.2.0-rc.1", "beta"]
+            .into_iter()
+            .map(|name| UpstreamTag {
+                name: name.to_string(),
+                commit: String::new(),
+                vendored: false,
+            })
+            .collect();
+        sort(&mut tags);
+        let names: Vec<&str> = tags.iter().map(|tag| tag.name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["beta", "nightly", "v1.2.0-rc.1", "v1.2.0", "v1.10.0"]
+        );
+    }
+}
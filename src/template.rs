@@ -0,0 +1,270 @@
+//! `--format` templates for per-dependency reports.
+//!
+//! A template is literal text with `{placeholder}` fields, each optionally
+//! followed by a spec: `{name:<20}` pads to a width (`<` left, `>` right)
+//! and `{vendored_sha:.8}` truncates to a length.  `{{` and `}}` produce
+//! literal braces, and `\t`, `\n` and `\\` are unescaped.
+
+use super::Overview;
+use git2::Error;
+
+/// The placeholders a template may reference.
+pub const PLACEHOLDERS: [&str; 17] = [
+    "name",
+    "host",
+    "pattern",
+    "url",
+    "branch",
+    "rev",
+    "ref",
+    "vendored_sha",
+    "upstream_sha",
+    "behind",
+    "merged_at",
+    "age_days",
+    "merged_by",
+    "fetched",
+    "dirty",
+    "size",
+    "state",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Field {
+        name: String,
+        width: Option<(Align, usize)>,
+        max: Option<usize>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Align {
+    Left,
+    Right,
+}
+
+/// A parsed `--format` template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    segments: Vec<Segment>,
+}
+
+impl Template {
+    /// Parse `template`, rejecting unknown placeholders and bad specs.
+    pub fn parse(template: &str) -> Result<Self, Error> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '\\' => match chars.next() {
+                    Some('t') => literal.push('\t'),
+                    Some('n') => literal.push('\n'),
+                    Some('\\') => literal.push('\\'),
+                    Some(other) => {
+                        literal.push('\\');
+                        literal.push(other);
+                    }
+                    None => literal.push('\\'),
+                },
+                '{' => {
+                    let mut field = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => field.push(c),
+                            None => {
+                                return Err(Error::from_str(&format!(
+                                    "Unterminated field '{{{field}' in format template"
+                                )));
+                            }
+                        }
+                    }
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(parse_field(&field)?);
+                }
+                '}' => return Err(Error::from_str("Unmatched '}' in format template")),
+                _ => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+
+        Ok(Self { segments })
+    }
+
+    /// Render the template for one dependency.
+    pub fn render(&self, dep: &Overview) -> String {
+        let mut out = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(text) => out.push_str(text),
+                Segment::Field { name, width, max } => {
+                    let mut value = value(dep, name);
+                    if let Some(max) = max
+                        && let Some((i, _)) = value.char_indices().nth(*max)
+                    {
+                        value.truncate(i);
+                    }
+                    match width {
+                        Some((Align::Left, w)) => out.push_str(&format!("{value:<w$}")),
+                        Some((Align::Right, w)) => out.push_str(&format!("{value:>w$}")),
+                        None => out.push_str(&value),
+                    }
+                }
+            }
+        }
+        out
+    }
+}
+
+fn parse_field(field: &str) -> Result<Segment, Error> {
+    let (name, spec) = field.split_once(':').unwrap_or((field, ""));
+    if !PLACEHOLDERS.contains(&name) {
+        return Err(Error::from_str(&format!(
+            "Unknown placeholder '{{{name}}}' in format template; expected one of: {}",
+            PLACEHOLDERS.join(", ")
+        )));
+    }
+
+    let invalid = || Error::from_str(&format!("Invalid format spec '{spec}' for '{{{name}}}'"));
+    let (width, max) = match spec.split_once('.') {
+        Some((width, max)) => (width, Some(max.parse().map_err(|_| invalid())?)),
+        None => (spec, None),
+    };
+    let width = match width.chars().next() {
+        None => None,
+        Some('<') => Some((Align::Left, width[1..].parse().map_err(|_| invalid())?)),
+        Some('>') => Some((Align::Right, width[1..].parse().map_err(|_| invalid())?)),
+        Some(_) => Some((Align::Left, width.parse().map_err(|_| invalid())?)),
+    };
+
+    Ok(Segment::Field {
+        name: name.to_string(),
+        width,
+        max,
+    })
+}
+
+fn value(dep: &Overview, name: &str) -> String {
+    let optional = |value: &Option<String>| value.clone().unwrap_or_default();
+    match name {
+        "name" => dep.name.clone(),
+        "host" => dep.host().to_string(),
+        "pattern" => dep.pattern.clone(),
+        "url" => dep.url.clone(),
+        "branch" => optional(&dep.branch),
+        "rev" => optional(&dep.rev),
+        "ref" => dep.reference.clone(),
+        "vendored_sha" => optional(&dep.vendored),
+        "upstream_sha" => optional(&dep.latest),
+        "behind" => dep.pending.to_string(),
+        "merged_at" => optional(&dep.merged_at),
+        "age_days" => dep.age_days.map(|d| d.to_string()).unwrap_or_default(),
+        "merged_by" => optional(&dep.merged_by),
+        "fetched" => dep.fetched.to_string(),
+        "dirty" => dep.dirty.to_string(),
+        "size" => dep.size.to_string(),
+        "state" => if dep.missing {
+            "missing"
+        } else if dep.branch_gone {
+            "gone"
+        } else if dep.dirty {
+            "modified"
+        } else if dep.vendored.is_none() {
+            "unmerged"
+        } else if dep.pending > 0 {
+            "outdated"
+        } else {
+            "ok"
+        }
+        .to_string(),
+        _ => unreachable!("placeholder validated at parse time"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overview() -> Overview {
+        Overview {
+            name: "owner/repo".into(),
+            pattern: "vendor/repo/".into(),
+            url: "https://example.com/owner/repo.git".into(),
+            branch: Some("main".into()),
+            rev: None,
+            reference: "refs/vendor/owner/repo".into(),
+            fetched: true,
+            vendored: Some("0123456789abcdef0123456789abcdef01234567".into()),
+            latest: Some("89abcdef0123456789abcdef0123456789abcdef".into()),
+            merged_at: Some("2026-01-02T03:04:05Z".into()),
+            age_days: Some(3),
+            merged_by: None,
+            pending: 2,
+            dirty: false,
+            size: 1024,
+            missing: false,
+            branch_gone: false,
+        }
+    }
+
+    #[test]
+    fn render_fields_with_escapes() {
+        let template =
+            Template::parse(r"{name}\t{vendored_sha:.8}\t{behind}\n{host} {size}").unwrap();
+        assert_eq!(
+            template.render(&overview()),
+            "owner/repo\t01234567\t2\nexample.com 1024"
+        );
+    }
+
+    #[test]
+    fn render_padding_and_braces() {
+        let template = Template::parse("{{{behind:>3}}} {state:<9}|").unwrap();
+        assert_eq!(template.render(&overview()), "{  2} outdated |");
+    }
+
+    #[test]
+    fn render_missing_values_are_empty() {
+        let mut dep = overview();
+        dep.branch = None;
+        dep.vendored = None;
+        let template = Template::parse("[{branch}] {vendored_sha} {state}").unwrap();
+        assert_eq!(template.render(&dep), "[]  unmerged");
+    }
+
+    #[test]
+    fn parse_rejects_unknown_placeholder() {
+        let err = Template::parse("{nope}").unwrap_err();
+        assert!(err.message().contains("Unknown placeholder '{nope}'"));
+    }
+
+    #[test]
+    fn parse_rejects_bad_spec() {
+        assert!(Template::parse("{name:.x}").is_err());
+        assert!(Template::parse("{name:<}").is_err());
+        assert!(Template::parse("oops}").is_err());
+    }
+
+    #[test]
+    fn parse_rejects_unterminated_field() {
+        let err = Template::parse("{name").unwrap_err();
+        assert!(err.message().contains("Unterminated field '{name'"));
+        assert!(Template::parse("{name} {behind").is_err());
+    }
+}
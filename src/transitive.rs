@@ -0,0 +1,144 @@
+//! Discovery of the dependencies vendored by vendored upstreams.
+//!
+//! An upstream may itself vendor content with `vendored` lines in its own
+//! `.gitattributes` files. Because vendored content keeps its upstream
+//! paths, such a nested dependency lands in the host at the same prefix it
+//! has in the upstream, as part of its parent's merge.
+
+use super::{
+    Macros, VendorDep, attributes, config, parse_macros, parse_vendor_lines, provenance,
+    upstream_tree, vendor_ref_name,
+};
+use git_filter_tree::FilterTree;
+use git2::{Error, Repository, Tree};
+use serde::Serialize;
+
+/// A dependency vendored by another vendored dependency.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Transitive {
+    pub name: String,
+    /// The pattern in the host, prefixed with the directory of the
+    /// upstream `.gitattributes` file declaring it.
+    pub pattern: String,
+    pub url: String,
+    pub branch: Option<String>,
+    /// The dependency vendoring this one.
+    pub parent: String,
+    /// How many dependencies removed from the host this one is; `1` for a
+    /// dependency of a direct dependency.
+    pub depth: usize,
+    /// The commit the parent vendored, from the parent's provenance record.
+    pub commit: Option<String>,
+}
+
+/// Follow the dependencies vendored by the fetched upstreams of `selected`,
+/// up to `max_depth` levels down, and return every parent-child edge found.
+///
+/// `fetch` is called on each transitive dependency before it is descended
+/// into, which happens once per name. Dependencies in `deps`, tracked by the
+/// host, are never descended into.
+pub(crate) fn resolve(
+    repo: &Repository,
+    deps: &[VendorDep],
+    selected: &[&VendorDep],
+    max_depth: usize,
+    mut fetch: impl FnMut(&VendorDep) -> Result<(), Error>,
+) -> Result<Vec<Transitive>, Error> {
+    let ref_prefix = config::Config::resolve(repo)?.ref_prefix;
+    let git_config = repo.config()?;
+
+    let mut known: Vec<String> = deps.iter().map(|dep| dep.name.clone()).collect();
+    let mut parents: Vec<VendorDep> = selected.iter().map(|&dep| dep.clone()).collect();
+    let mut resolved = Vec::new();
+    for depth in 1..=max_depth {
+        let mut next = Vec::new();
+        for parent in &parents {
+            let Ok(commit) = repo
+                .find_reference(&vendor_ref_name(&ref_prefix, &parent.name))
+                .and_then(|reference| reference.peel_to_commit())
+            else {
+                continue;
+            };
+            for (mut dep, transitive) in discover(repo, parent, &commit.tree()?, depth)? {
+                resolved.push(transitive);
+                if known.contains(&dep.name) {
+                    continue;
+                }
+                known.push(dep.name.clone());
+                config::apply_dep_overrides(&git_config, &mut dep)?;
+                fetch(&dep)?;
+                next.push(dep);
+            }
+        }
+        parents = next;
+    }
+
+    Ok(resolved)
+}
+
+/// The dependencies declared in `upstream`, the fetched tree of `parent`,
+/// whose content reaches the host through `parent`'s patterns.
+fn discover(
+    repo: &Repository,
+    parent: &VendorDep,
+    upstream: &Tree,
+    depth: usize,
+) -> Result<Vec<(VendorDep, Transitive)>, Error> {
+    let vendored = upstream_tree(repo, parent, upstream)?;
+
+    let files = attributes::upstream_files(repo, upstream)?;
+
+    // Macros defined at the root apply to every file below it.
+    let mut macros = Macros::new();
+    if let Some((_, content)) = files.iter().find(|(dir, _)| dir.is_empty()) {
+        parse_macros(content, &mut macros);
+    }
+
+    let mut found = Vec::new();
+    for (dir, content) in files {
+        for mut dep in parse_vendor_lines(&content, &macros)? {
+            dep.pattern = nest(&dir, &dep.pattern);
+            dep.extra_patterns = dep
+                .extra_patterns
+                .iter()
+                .map(|pattern| nest(&dir, pattern))
+                .collect();
+            if repo
+                .filter_by_patterns(&vendored, &dep.patterns())?
+                .is_empty()
+            {
+                continue;
+            }
+
+            let commit = provenance::read(repo, upstream, &dep.name)?.map(|record| record.commit);
+            let transitive = Transitive {
+                name: dep.name.clone(),
+                pattern: dep.pattern.clone(),
+                url: dep.url.clone(),
+                branch: dep.branch.clone(),
+                parent: parent.name.clone(),
+                depth,
+                commit,
+            };
+            found.push((dep, transitive));
+        }
+    }
+    Ok(found)
+}
+
+/// Prefix `pattern` from a `.gitattributes` file in `dir` (empty or ending
+/// in `/`) so that it is relative to the repository root.
+fn nest(dir: &str, pattern: &str) -> String {
+    format!("{dir}{}", pattern.trim_start_matches('/'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nest_prefixes_the_declaring_directory() {
+        assert_eq!(nest("", "third_party/bar/"), "third_party/bar/");
+        assert_eq!(nest("lib/", "/bar/**"), "lib/bar/**");
+    }
+}
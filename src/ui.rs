@@ -0,0 +1,197 @@
+//! Interactive terminal dashboard (`git vendor ui`).
+
+use git_vendor::{Overview, Vendor, VendorMergeOpts};
+use git2 as git;
+use ratatui::{
+    DefaultTerminal, Frame,
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Block, Cell, Row, Table, TableState},
+};
+use std::process;
+
+const HELP: &str = "j/k move  f fetch  F fetch all  d diff  u update  r refresh  q quit";
+
+struct App<'r> {
+    repo: &'r git::Repository,
+    pattern: Option<&'r str>,
+    deps: Vec<Overview>,
+    table: TableState,
+    message: String,
+}
+
+/// Run the dashboard until the user quits.
+pub fn run(
+    repo: &git::Repository,
+    pattern: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    git_vendor::set_quiet(true);
+
+    let mut app = App {
+        repo,
+        pattern,
+        deps: Vec::new(),
+        table: TableState::default(),
+        message: String::new(),
+    };
+    app.refresh();
+
+    let mut terminal = ratatui::init();
+    let result = app.event_loop(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+impl App<'_> {
+    fn event_loop(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Char('j') | KeyCode::Down => self.table.select_next(),
+                KeyCode::Char('k') | KeyCode::Up => self.table.select_previous(),
+                KeyCode::Char('r') => {
+                    self.refresh();
+                    self.message = "Refreshed".into();
+                }
+                KeyCode::Char('f') => {
+                    if let Some(dep) = self.selected() {
+                        let pattern = dep.pattern.clone();
+                        self.message = match self.repo.vendor_fetch(Some(&pattern), None) {
+                            Ok(()) => format!("Fetched {}", dep.name),
+                            Err(e) => format!("Error: {}", e.message()),
+                        };
+                        self.refresh();
+                    }
+                }
+                KeyCode::Char('F') => {
+                    self.message = match self.repo.vendor_fetch(self.pattern, None) {
+                        Ok(()) => "Fetched all dependencies".into(),
+                        Err(e) => format!("Error: {}", e.message()),
+                    };
+                    self.refresh();
+                }
+                KeyCode::Char('d') => {
+                    if let Some(dep) = self.selected() {
+                        let (reference, pattern) = (dep.reference.clone(), dep.pattern.clone());
+                        ratatui::restore();
+                        let status = process::Command::new("git")
+                            .args(["diff", "HEAD", &reference, "--", &pattern])
+                            .status();
+                        *terminal = ratatui::init();
+                        if let Err(e) = status {
+                            self.message = format!("Error: failed to run git diff: {e}");
+                        }
+                    }
+                }
+                KeyCode::Char('u') => {
+                    if let Some(dep) = self.selected() {
+                        let pattern = dep.pattern.clone();
+                        self.message = match self.repo.vendor_merge(
+                            Some(&pattern),
+                            &VendorMergeOpts::default(),
+                            None,
+                        ) {
+                            Ok(()) => format!("Updated {}", dep.name),
+                            Err(e) => format!("Error: {}", e.message()),
+                        };
+                        self.refresh();
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn selected(&self) -> Option<Overview> {
+        self.table
+            .selected()
+            .and_then(|i| self.deps.get(i))
+            .cloned()
+    }
+
+    fn refresh(&mut self) {
+        match self.repo.vendor_overview(self.pattern) {
+            Ok(deps) => self.deps = deps,
+            Err(e) => self.message = format!("Error: {}", e.message()),
+        }
+        match self.table.selected() {
+            _ if self.deps.is_empty() => self.table.select(None),
+            Some(i) if i >= self.deps.len() => self.table.select(Some(self.deps.len() - 1)),
+            None => self.table.select(Some(0)),
+            Some(_) => {}
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, message, help] = Layout::vertical([
+            Constraint::Min(0),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let header = Row::new(["Name", "Pattern", "Last merged", "Pending", "State"])
+            .style(Style::new().add_modifier(Modifier::BOLD));
+        let rows = self.deps.iter().map(|dep| {
+            let merged = match dep.age_days {
+                Some(days) => format!("{days} days ago"),
+                None => "never".into(),
+            };
+            let pending = if dep.fetched {
+                dep.pending.to_string()
+            } else {
+                "not fetched".into()
+            };
+            let (state, color) = if dep.missing {
+                ("missing", Color::Red)
+            } else if dep.dirty {
+                ("modified", Color::Red)
+            } else if dep.pending > 0 {
+                ("outdated", Color::Yellow)
+            } else {
+                ("ok", Color::Green)
+            };
+            Row::new([
+                Cell::from(dep.name.as_str()),
+                Cell::from(dep.pattern.as_str()),
+                Cell::from(merged),
+                Cell::from(pending),
+                Cell::from(state).style(Style::new().fg(color)),
+            ])
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Fill(2),
+                Constraint::Fill(2),
+                Constraint::Length(14),
+                Constraint::Length(12),
+                Constraint::Length(10),
+            ],
+        )
+        .header(header)
+        .block(Block::bordered().title(" Vendored dependencies "))
+        .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+
+        frame.render_stateful_widget(table, main, &mut self.table);
+        frame.render_widget(Line::from(self.message.as_str()), message);
+        frame.render_widget(
+            Line::from(HELP).style(Style::new().add_modifier(Modifier::DIM)),
+            help,
+        );
+    }
+}
@@ -0,0 +1,110 @@
+//! Tamper detection for vendored content.
+
+use super::{VendorDep, provenance};
+use git_filter_tree::FilterTree;
+use git2::{Error, Oid, Repository, Tree};
+
+/// The outcome of verifying one vendored dependency.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verification {
+    /// The vendored content matches the tree merged from the recorded commit.
+    Verified { commit: Oid, tree: Oid },
+    /// The vendored content differs from the tree recorded at merge time.
+    Modified { recorded: Oid, actual: Oid },
+    /// The recorded tree is not what the recorded upstream commit filters to.
+    Mismatch {
+        commit: Oid,
+        recorded: Oid,
+        upstream: Oid,
+    },
+    /// The content matches its record, but the recorded upstream commit is
+    /// not available locally to confirm the record itself.
+    Unverifiable { commit: Oid },
+    /// No provenance record exists for the dependency.
+    Unrecorded,
+}
+
+impl Verification {
+    /// Whether this outcome indicates content that does not correspond to
+    /// its recorded upstream commit.
+    pub fn is_failure(&self) -> bool {
+        matches!(self, Self::Modified { .. } | Self::Mismatch { .. })
+    }
+}
+
+/// Verify `dep`'s vendored content in `tree` against its provenance record.
+pub(crate) fn verify(
+    repo: &Repository,
+    tree: &Tree,
+    dep: &VendorDep,
+) -> Result<Verification, Error> {
+    let Some(record) = provenance::read(repo, tree, &dep.name)? else {
+        return Ok(Verification::Unrecorded);
+    };
+
+    let commit = parse_oid(&record.commit)?;
+    let recorded = parse_oid(&record.tree)?;
+
+    let actual = repo.filter_by_patterns(tree, &dep.patterns())?.id();
+    if actual != recorded {
+        return Ok(Verification::Modified { recorded, actual });
+    }
+
+    let upstream_commit = match repo.find_commit(commit) {
+        Ok(c) => c,
+        Err(_) => return Ok(Verification::Unverifiable { commit }),
+    };
+    let upstream_tree = upstream_commit.tree()?;
+    let upstream = super::upstream_tree(repo, dep, &upstream_tree)?.id();
+    if upstream != recorded {
+        return Ok(Verification::Mismatch {
+            commit,
+            recorded,
+            upstream,
+        });
+    }
+
+    Ok(Verification::Verified {
+        commit,
+        tree: recorded,
+    })
+}
+
+fn parse_oid(s: &str) -> Result<Oid, Error> {
+    Oid::from_str(s)
+        .map_err(|_| Error::from_str(&format!("Invalid object id in provenance record: {s}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failures_are_modified_and_mismatch() {
+        let oid = Oid::zero();
+        assert!(
+            Verification::Modified {
+                recorded: oid,
+                actual: oid
+            }
+            .is_failure()
+        );
+        assert!(
+            Verification::Mismatch {
+                commit: oid,
+                recorded: oid,
+                upstream: oid
+            }
+            .is_failure()
+        );
+        assert!(
+            !Verification::Verified {
+                commit: oid,
+                tree: oid
+            }
+            .is_failure()
+        );
+        assert!(!Verification::Unverifiable { commit: oid }.is_failure());
+        assert!(!Verification::Unrecorded.is_failure());
+    }
+}
@@ -1,6 +1,9 @@
 //! Integration tests for the `Vendor` trait methods on `Repository`.
 
-use git_vendor::{Vendor, VendorMergeOpts};
+use git_vendor::{
+    AnnotationKind, GraphFormat, Linguist, Provenance, SbomFormat, SetupOpts, SourceFormat,
+    StatusOpts, Vendor, VendorMergeOpts, error,
+};
 use git2::{Oid, Repository};
 use std::{fs, io::Write, path::Path, sync::Mutex};
 use tempfile::TempDir;
@@ -104,6 +107,8 @@ fn track_pattern_writes_gitattributes() {
         "https://github.com/owner/repo.git",
         Some("main"),
         None,
+        None,
+        false,
     )
     .unwrap();
 
@@ -116,13 +121,47 @@ fn track_pattern_writes_gitattributes() {
 }
 
 #[test]
-fn track_pattern_omits_branch_when_none() {
+fn track_and_untrack_keep_byte_order_mark_and_crlf() {
     let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
     let (repo, dir) = setup_repo();
     std::env::set_current_dir(dir.path()).unwrap();
 
-    repo.track_pattern("*.rs", "https://github.com/owner/repo.git", None, None)
+    let original = "\u{feff}*.md text\r\n";
+    write_gitattributes(dir.path(), original);
+    let url = "https://github.com/owner/repo.git";
+    repo.track_pattern("lib/", url, Some("main"), None, None, false)
         .unwrap();
+    repo.track_pattern("lib/", url, Some("next"), None, None, false)
+        .unwrap();
+
+    let content = fs::read_to_string(dir.path().join(".gitattributes")).unwrap();
+    assert_eq!(
+        content,
+        format!("{original}lib/ vendored name=owner/repo url={url} branch=next\r\n")
+    );
+
+    repo.untrack_pattern("lib/").unwrap();
+    assert_eq!(
+        fs::read_to_string(dir.path().join(".gitattributes")).unwrap(),
+        original
+    );
+}
+
+#[test]
+fn track_pattern_omits_branch_when_none() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    repo.track_pattern(
+        "*.rs",
+        "https://github.com/owner/repo.git",
+        None,
+        None,
+        None,
+        false,
+    )
+    .unwrap();
 
     let content = fs::read_to_string(dir.path().join(".gitattributes")).unwrap();
     assert!(content.contains("vendored"));
@@ -138,13 +177,244 @@ fn track_pattern_local_path_requires_name() {
     std::env::set_current_dir(dir.path()).unwrap();
 
     assert!(
-        repo.track_pattern("*.txt", "/local/path", Some("main"), None)
+        repo.track_pattern("*.txt", "/local/path", Some("main"), None, None, false)
             .is_err()
     );
     assert!(
-        repo.track_pattern("*.txt", "/local/path", Some("main"), Some("my-dep"))
-            .is_ok()
+        repo.track_pattern(
+            "*.txt",
+            "/local/path",
+            Some("main"),
+            Some("my-dep"),
+            None,
+            false
+        )
+        .is_ok()
+    );
+}
+
+#[test]
+fn track_pattern_writes_linguist_attributes() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    repo.track_pattern(
+        "vendor/a/",
+        "https://github.com/owner/a.git",
+        None,
+        None,
+        Some(Linguist::VendoredNoDiff),
+        false,
+    )
+    .unwrap();
+
+    repo.config()
+        .unwrap()
+        .set_str("vendor.linguist", "true")
+        .unwrap();
+    repo.track_pattern(
+        "vendor/b/",
+        "https://github.com/owner/b.git",
+        None,
+        None,
+        None,
+        false,
+    )
+    .unwrap();
+
+    let content = fs::read_to_string(dir.path().join(".gitattributes")).unwrap();
+    assert!(content.contains(
+        "vendor/a/ vendored name=owner/a url=https://github.com/owner/a.git linguist-vendored -diff"
+    ));
+    assert!(content.contains(
+        "vendor/b/ vendored name=owner/b url=https://github.com/owner/b.git linguist-vendored\n"
+    ));
+
+    repo.untrack_pattern("vendor/a/").unwrap();
+    let content = fs::read_to_string(dir.path().join(".gitattributes")).unwrap();
+    assert!(!content.contains("vendor/a/"));
+}
+
+#[test]
+fn manifest_is_the_source_of_truth_when_present() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let (_upstream_repo, upstream_dir) = setup_upstream(&[("lib/a.txt", b"a\n")]);
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+    fs::write(dir.path().join(".gitvendor.toml"), "").unwrap();
+
+    let url = upstream_dir.path().display().to_string();
+    repo.track_pattern(
+        "lib/",
+        &url,
+        Some("main"),
+        Some("test/upstream"),
+        Some(Linguist::Vendored),
+        false,
+    )
+    .unwrap();
+
+    let manifest = fs::read_to_string(dir.path().join(".gitvendor.toml")).unwrap();
+    assert!(manifest.contains("[[dependency]]\nname = \"test/upstream\"\npattern = \"lib/\"\n"));
+    assert!(manifest.contains("branch = \"main\"\n"));
+    let attrs = fs::read_to_string(dir.path().join(".gitattributes")).unwrap();
+    assert_eq!(attrs, "lib/ vendored linguist-vendored\n");
+
+    repo.vendor_commit_attributes("Track lib").unwrap();
+    repo.vendor_fetch(None, None).unwrap();
+    repo.vendor_merge(None, &VendorMergeOpts::default(), None)
+        .unwrap();
+    assert!(dir.path().join("lib/a.txt").exists());
+    assert_eq!(repo.vendor_overview(None).unwrap()[0].name, "test/upstream");
+
+    repo.untrack_pattern("lib/").unwrap();
+    let manifest = fs::read_to_string(dir.path().join(".gitvendor.toml")).unwrap();
+    assert!(!manifest.contains("lib/"));
+    let attrs = fs::read_to_string(dir.path().join(".gitattributes")).unwrap();
+    assert!(!attrs.contains("lib/"));
+}
+
+#[test]
+fn migrate_round_trips_between_formats() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    let original = "\
+*.txt diff
+vendor/a/ vendored name=owner/a url=https://github.com/owner/a.git branch=main depth=1 linguist-vendored
+";
+    fs::write(dir.path().join(".gitattributes"), original).unwrap();
+
+    assert_eq!(repo.vendor_migrate(SourceFormat::Manifest).unwrap(), 1);
+    let attrs = fs::read_to_string(dir.path().join(".gitattributes")).unwrap();
+    assert_eq!(attrs, "*.txt diff\nvendor/a/ vendored linguist-vendored\n");
+    let manifest = fs::read_to_string(dir.path().join(".gitvendor.toml")).unwrap();
+    assert!(manifest.contains("name = \"owner/a\"\n"));
+    assert!(manifest.contains("depth = 1\n"));
+    assert!(repo.vendor_migrate(SourceFormat::Manifest).is_err());
+
+    assert_eq!(repo.vendor_migrate(SourceFormat::Attributes).unwrap(), 1);
+    assert!(!dir.path().join(".gitvendor.toml").exists());
+    let attrs = fs::read_to_string(dir.path().join(".gitattributes")).unwrap();
+    assert_eq!(
+        attrs,
+        "*.txt diff\nvendor/a/ vendored name=owner/a url=https://github.com/owner/a.git \
+         branch=main depth=1 linguist-vendored\n"
+    );
+}
+
+#[test]
+fn remotes_follow_track_set_url_and_untrack() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+    repo.config()
+        .unwrap()
+        .set_bool("vendor.remotes", true)
+        .unwrap();
+
+    let (_upstream_repo, upstream_dir) = setup_upstream(&[("lib/a.txt", b"a\n")]);
+    let url = upstream_dir.path().display().to_string();
+    repo.track_pattern("lib/", &url, Some("main"), Some("test/lib"), None, false)
+        .unwrap();
+
+    let mut remote = repo.find_remote("vendor/test/lib").unwrap();
+    assert_eq!(remote.url(), Some(url.as_str()));
+    remote.fetch(&[] as &[&str], None, None).unwrap();
+    assert!(repo.find_reference("refs/vendor/test/lib").is_ok());
+
+    repo.vendor_set_url("lib/", "https://example.com/test/lib.git")
+        .unwrap();
+    let remote = repo.find_remote("vendor/test/lib").unwrap();
+    assert_eq!(remote.url(), Some("https://example.com/test/lib.git"));
+    let attrs = fs::read_to_string(dir.path().join(".gitattributes")).unwrap();
+    assert!(attrs.contains("url=https://example.com/test/lib.git"));
+    assert!(!attrs.contains(&url));
+
+    repo.untrack_pattern("lib/").unwrap();
+    assert!(repo.find_remote("vendor/test/lib").is_err());
+    assert!(repo.find_reference("refs/vendor/test/lib").is_ok());
+}
+
+#[test]
+fn track_pattern_validates_before_writing() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    let (_upstream_repo, upstream_dir) = setup_upstream(&[("lib.txt", b"v1\n")]);
+    let url = upstream_dir.path().display().to_string();
+
+    assert!(
+        repo.track_pattern("vendor/[lib", &url, None, Some("lib"), None, false)
+            .is_err()
+    );
+    assert!(
+        repo.track_pattern("*.txt", &url, Some("bad..branch"), Some("lib"), None, false)
+            .is_err()
     );
+    let err = repo
+        .track_pattern("*.txt", &url, Some("gone"), Some("lib"), None, true)
+        .unwrap_err();
+    assert!(err.message().contains("Branch 'gone' not found"));
+    assert!(!dir.path().join(".gitattributes").exists());
+
+    repo.track_pattern("*.txt", &url, Some("main"), Some("lib"), None, true)
+        .unwrap();
+    assert!(dir.path().join(".gitattributes").exists());
+}
+
+#[test]
+fn commit_attributes_leaves_other_staged_changes_uncommitted() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    let (_upstream_repo, upstream_dir) = setup_upstream(&[("lib.txt", b"v1\n")]);
+    let url = upstream_dir.path().display().to_string();
+
+    fs::write(dir.path().join("unrelated.md"), "work in progress\n").unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new("unrelated.md")).unwrap();
+    index.write().unwrap();
+
+    repo.track_pattern("*.txt", &url, Some("main"), Some("lib"), None, false)
+        .unwrap();
+    let commit = repo.vendor_commit_attributes("Track lib").unwrap();
+
+    let tree = repo.find_commit(commit).unwrap().tree().unwrap();
+    assert!(tree.get_name(".gitattributes").is_some());
+    assert!(tree.get_name("unrelated.md").is_none());
+    let index = repo.index().unwrap();
+    assert!(index.get_path(Path::new("unrelated.md"), 0).is_some());
+}
+
+#[test]
+fn commit_attributes_then_merge_keeps_tracking() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    let (_upstream_repo, upstream_dir) = setup_upstream(&[("lib.txt", b"v1\n")]);
+    let url = upstream_dir.path().display().to_string();
+
+    repo.track_pattern("*.txt", &url, Some("main"), Some("lib"), None, false)
+        .unwrap();
+    let head = repo.head().unwrap().target().unwrap();
+    let commit = repo.vendor_commit_attributes("Track lib").unwrap();
+    assert_ne!(commit, head);
+    assert_eq!(repo.vendor_commit_attributes("again").unwrap(), commit);
+
+    repo.vendor_fetch(Some("*.txt"), None).unwrap();
+    repo.vendor_merge(Some("*.txt"), &VendorMergeOpts::default(), None)
+        .unwrap();
+    assert!(dir.path().join("lib.txt").exists());
+    let attributes = fs::read_to_string(dir.path().join(".gitattributes")).unwrap();
+    assert!(attributes.contains("name=lib"));
 }
 
 #[test]
@@ -158,6 +428,8 @@ fn track_pattern_explicit_name_overrides_derived() {
         "https://github.com/owner/repo.git",
         Some("main"),
         Some("custom-name"),
+        None,
+        false,
     )
     .unwrap();
 
@@ -176,6 +448,8 @@ fn track_pattern_includes_branch_when_specified() {
         "https://github.com/owner/repo.git",
         Some("develop"),
         None,
+        None,
+        false,
     )
     .unwrap();
 
@@ -198,6 +472,8 @@ fn untrack_pattern_removes_vendor_lines() {
         "https://github.com/owner/repo.git",
         Some("main"),
         None,
+        None,
+        false,
     )
     .unwrap();
 
@@ -221,73 +497,2686 @@ fn untrack_pattern_is_noop_without_gitattributes() {
     assert!(repo.untrack_pattern("*.txt").is_ok());
 }
 
+// ---------------------------------------------------------------------------
+// clean
+// ---------------------------------------------------------------------------
+
+#[test]
+fn clean_removes_vendored_files_but_keeps_tracking() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let (_upstream_repo, upstream_dir) =
+        setup_upstream(&[("lib/a.txt", b"a\n"), ("lib/sub/b.txt", b"b\n")]);
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    let url = upstream_dir.path().display().to_string();
+    let config = format!("lib/ vendored name=test/upstream url={url} branch=main\n");
+    write_gitattributes(dir.path(), &config);
+    fs::write(dir.path().join("keep.txt"), "mine\n").unwrap();
+    commit_all(&repo, "vendor config");
+
+    repo.vendor_fetch(None, None).unwrap();
+    repo.vendor_merge(None, &VendorMergeOpts::default(), None)
+        .unwrap();
+
+    let expected = [
+        ".git-vendor/test/upstream.json",
+        "lib/a.txt",
+        "lib/sub/b.txt",
+    ];
+    assert_eq!(repo.vendor_clean(None, true).unwrap(), expected);
+    assert!(dir.path().join("lib/a.txt").exists());
+
+    assert_eq!(repo.vendor_clean(None, false).unwrap(), expected);
+    assert!(!dir.path().join("lib").exists());
+    assert!(!dir.path().join(".git-vendor").exists());
+    assert!(dir.path().join("keep.txt").exists());
+    assert_eq!(
+        fs::read_to_string(dir.path().join(".gitattributes")).unwrap(),
+        config
+    );
+    let index = repo.index().unwrap();
+    assert!(index.get_path(Path::new("lib/a.txt"), 0).is_none());
+    assert!(index.get_path(Path::new("keep.txt"), 0).is_some());
+}
+
+#[cfg(unix)]
+#[test]
+fn merge_and_clean_keep_non_utf8_file_names() {
+    use std::os::unix::ffi::OsStrExt;
+
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let (upstream_repo, upstream_dir) = setup_upstream(&[("lib/a.txt", b"a\n")]);
+    let name = std::ffi::OsStr::from_bytes(b"caf\xe9");
+    fs::create_dir_all(upstream_dir.path().join("lib").join(name)).unwrap();
+    fs::write(
+        upstream_dir.path().join("lib").join(name).join("b.txt"),
+        "b\n",
+    )
+    .unwrap();
+    commit_all(&upstream_repo, "add non-UTF-8 directory");
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    let url = upstream_dir.path().display().to_string();
+    write_gitattributes(
+        dir.path(),
+        &format!("lib/ vendored name=test/upstream url={url} branch=main\n"),
+    );
+    commit_all(&repo, "vendor config");
+
+    repo.vendor_fetch(None, None).unwrap();
+    repo.vendor_merge(None, &VendorMergeOpts::default(), None)
+        .unwrap();
+    let path = Path::new("lib").join(name).join("b.txt");
+    assert!(dir.path().join(&path).exists());
+    let head = repo.head().unwrap().peel_to_tree().unwrap();
+    assert!(head.get_path(&path).is_ok());
+
+    let removed = repo.vendor_clean(None, false).unwrap();
+    assert!(removed.contains(&"lib/caf\u{fffd}/b.txt".to_string()));
+    assert!(!dir.path().join(&path).exists());
+}
+
+// ---------------------------------------------------------------------------
+// restore
+// ---------------------------------------------------------------------------
+
+#[test]
+fn restore_discards_local_modifications() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let (_upstream_repo, upstream_dir) =
+        setup_upstream(&[("lib/a.txt", b"a\n"), ("lib/sub/b.txt", b"b\n")]);
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    let url = upstream_dir.path().display().to_string();
+    write_gitattributes(
+        dir.path(),
+        &format!("lib/ vendored name=test/upstream url={url} branch=main\n"),
+    );
+    commit_all(&repo, "vendor config");
+
+    repo.vendor_fetch(None, None).unwrap();
+    repo.vendor_merge(None, &VendorMergeOpts::default(), None)
+        .unwrap();
+    assert!(repo.vendor_restore(None).unwrap().is_empty());
+
+    fs::write(dir.path().join("lib/a.txt"), "edited\n").unwrap();
+    fs::remove_file(dir.path().join("lib/sub/b.txt")).unwrap();
+    fs::write(dir.path().join("lib/extra.txt"), "extra\n").unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new("lib/extra.txt")).unwrap();
+    index.write().unwrap();
+
+    assert_eq!(
+        repo.vendor_restore(None).unwrap(),
+        ["lib/a.txt", "lib/extra.txt", "lib/sub/b.txt"]
+    );
+    assert_eq!(
+        fs::read_to_string(dir.path().join("lib/a.txt")).unwrap(),
+        "a\n"
+    );
+    assert!(dir.path().join("lib/sub/b.txt").exists());
+    assert!(!dir.path().join("lib/extra.txt").exists());
+    let statuses = repo.statuses(None).unwrap();
+    assert!(statuses.is_empty(), "worktree should be clean");
+}
+
+#[test]
+fn sync_rederives_recorded_trees_in_a_fresh_clone() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let (upstream_repo, upstream_dir) =
+        setup_upstream(&[("lib/a.txt", b"a\n"), ("lib/b.txt", b"b\n")]);
+    let upstream_head = upstream_repo.head().unwrap().target().unwrap();
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+    let url = upstream_dir.path().display().to_string();
+    write_gitattributes(
+        dir.path(),
+        &format!("lib/ vendored name=test/upstream url={url} branch=main\n"),
+    );
+    commit_all(&repo, "vendor config");
+    repo.vendor_fetch(None, None).unwrap();
+    // A squash merge leaves the upstream commit out of the history.
+    let opts = VendorMergeOpts {
+        squash: true,
+        ..Default::default()
+    };
+    repo.vendor_merge(None, &opts, None).unwrap();
+    commit_all(&repo, "vendor upstream");
+    assert!(repo.vendor_sync(None, true).unwrap().is_empty());
+
+    // Commit an edit so the merged tree is no longer reachable.
+    fs::write(dir.path().join("lib/a.txt"), "edited\n").unwrap();
+    commit_all(&repo, "edit vendored file");
+
+    // Only the branch reaches a fresh clone, not the vendor refs.
+    for reference in repo.references_glob("refs/vendor*").unwrap() {
+        reference.unwrap().delete().unwrap();
+    }
+    let clone_dir = TempDir::new().unwrap();
+    let clone = git2::build::RepoBuilder::new()
+        .clone_local(git2::build::CloneLocal::None)
+        .clone(&dir.path().display().to_string(), clone_dir.path())
+        .unwrap();
+    std::env::set_current_dir(clone_dir.path()).unwrap();
+
+    assert!(clone.find_commit(upstream_head).is_err());
+    let differing = clone.vendor_sync(None, true).unwrap();
+    // The recorded upstream commit was fetched to derive the tree again.
+    assert!(clone.find_commit(upstream_head).is_ok());
+    assert_eq!(differing.len(), 1);
+    assert_eq!(differing[0].paths, ["lib/a.txt"]);
+    assert_eq!(
+        fs::read_to_string(clone_dir.path().join("lib/a.txt")).unwrap(),
+        "edited\n"
+    );
+
+    let synced = clone.vendor_sync(None, false).unwrap();
+    assert_eq!(synced[0].paths, ["lib/a.txt"]);
+    assert_eq!(
+        fs::read_to_string(clone_dir.path().join("lib/a.txt")).unwrap(),
+        "a\n"
+    );
+    assert!(clone.vendor_sync(None, true).unwrap().is_empty());
+}
+
+// ---------------------------------------------------------------------------
+// prune
+// ---------------------------------------------------------------------------
+
+#[test]
+fn prune_attributes_untracks_vanished_dependencies() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let (_upstream_repo, upstream_dir) = setup_upstream(&[("lib/a.txt", b"a\n")]);
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    let url = upstream_dir.path().display().to_string();
+    write_gitattributes(
+        dir.path(),
+        &format!(
+            "lib/ vendored name=test/upstream url={url} branch=main\n\
+             new/ vendored name=test/new url={url} branch=main\n"
+        ),
+    );
+    commit_all(&repo, "vendor config");
+
+    repo.vendor_fetch(Some("lib/"), None).unwrap();
+    repo.vendor_merge(Some("lib/"), &VendorMergeOpts::default(), None)
+        .unwrap();
+    assert!(repo.vendor_prune_attributes(true).unwrap().is_empty());
+
+    fs::remove_dir_all(dir.path().join("lib")).unwrap();
+    let mut index = repo.index().unwrap();
+    index.remove_dir(Path::new("lib"), 0).unwrap();
+    index.write().unwrap();
+    commit_all(&repo, "drop lib");
+
+    let overview = repo.vendor_overview(None).unwrap();
+    assert!(overview[0].missing);
+    // Tracked but never merged is not considered vanished.
+    assert!(!overview[1].missing);
+
+    let pruned = repo.vendor_prune_attributes(true).unwrap();
+    assert_eq!(pruned.len(), 1);
+    assert_eq!(pruned[0].name, "test/upstream");
+    assert!(repo.find_reference("refs/vendor/test/upstream").is_ok());
+
+    repo.vendor_prune_attributes(false).unwrap();
+    let attrs = fs::read_to_string(dir.path().join(".gitattributes")).unwrap();
+    assert!(!attrs.contains("lib/"));
+    assert!(attrs.contains("new/"));
+    assert!(repo.find_reference("refs/vendor/test/upstream").is_err());
+    assert!(!dir.path().join(".git-vendor/test/upstream.json").exists());
+}
+
 // ---------------------------------------------------------------------------
 // status
 // ---------------------------------------------------------------------------
 
 #[test]
-fn status_ok_with_no_deps() {
+fn status_iter_yields_selected_dependencies_in_order() {
     let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let (_upstream_repo, upstream_dir) = setup_upstream(&[("lib.txt", b"content\n")]);
+
     let (repo, dir) = setup_repo();
     std::env::set_current_dir(dir.path()).unwrap();
+    let url = upstream_dir.path().display();
+    write_gitattributes(
+        dir.path(),
+        &format!(
+            "c/ vendored name=test/c url={url} branch=main\n\
+             a/ vendored name=test/a url={url} branch=main\n\
+             b/ vendored name=test/b url={url} branch=main\n"
+        ),
+    );
+    commit_all(&repo, "vendor config");
+
+    let iter = repo.vendor_status_iter(None).unwrap();
+    assert_eq!(iter.len(), 3);
+    let names: Vec<String> = iter.map(|dep| dep.unwrap().name).collect();
+    assert_eq!(names, ["test/c", "test/a", "test/b"]);
+
+    let mut iter = repo.vendor_status_iter(Some("a/")).unwrap();
+    assert_eq!(iter.len(), 1);
+    assert_eq!(iter.next().unwrap().unwrap().name, "test/a");
+    assert_eq!(iter.len(), 0);
+    assert!(iter.next().is_none());
+}
+
+#[test]
+fn status_iter_writes_cache_when_consumed_or_dropped() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let (_upstream_repo, upstream_dir) = setup_upstream(&[("lib.txt", b"content\n")]);
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+    let url = upstream_dir.path().display();
+    write_gitattributes(
+        dir.path(),
+        &format!(
+            "a/ vendored name=test/a url={url} branch=main\n\
+             b/ vendored name=test/b url={url} branch=main\n"
+        ),
+    );
+    commit_all(&repo, "vendor config");
+    let cache = repo.path().join("vendor/status.json");
+    let _ = fs::remove_file(&cache);
+
+    // Dropped after the first summary: only it is cached.
+    let mut iter = repo.vendor_status_iter(None).unwrap();
+    iter.next().unwrap().unwrap();
+    assert!(!cache.exists());
+    drop(iter);
+    let written = fs::read_to_string(&cache).unwrap();
+    assert!(written.contains("test/a"), "{written}");
+    assert!(!written.contains("test/b"), "{written}");
+
+    // Consumed: written once exhausted, before being dropped.
+    fs::remove_file(&cache).unwrap();
+    let mut iter = repo.vendor_status_iter(None).unwrap();
+    for dep in iter.by_ref() {
+        dep.unwrap();
+    }
+    let written = fs::read_to_string(&cache).unwrap();
+    assert!(
+        written.contains("test/a") && written.contains("test/b"),
+        "{written}"
+    );
+    drop(iter);
+}
+
+#[test]
+fn status_ok_with_no_deps() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    assert!(repo.vendor_status(None, &StatusOpts::default()).is_ok());
+}
+
+#[test]
+fn status_ok_with_tracked_dep() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    write_gitattributes(
+        dir.path(),
+        "*.txt vendored name=o/r url=https://example.com/o/r.git branch=main\n",
+    );
+
+    assert!(repo.vendor_status(None, &StatusOpts::default()).is_ok());
+}
+
+#[test]
+fn status_ok_with_tracked_dep_no_branch() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    write_gitattributes(
+        dir.path(),
+        "*.txt vendored name=o/r url=https://example.com/o/r.git\n",
+    );
+
+    assert!(repo.vendor_status(None, &StatusOpts::default()).is_ok());
+}
+
+#[test]
+fn status_check_remotes_reports_missing_branch() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let (_upstream_repo, upstream_dir) = setup_upstream(&[("lib.txt", b"v1\n")]);
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    let url = upstream_dir.path().display();
+    let opts = StatusOpts {
+        check_remotes: true,
+        ..Default::default()
+    };
+
+    write_gitattributes(
+        dir.path(),
+        &format!("*.txt vendored name=test/upstream url={url} branch=main\n"),
+    );
+    assert!(repo.vendor_status(None, &opts).is_ok());
+
+    write_gitattributes(
+        dir.path(),
+        &format!("*.txt vendored name=test/upstream url={url} branch=gone\n"),
+    );
+    let err = repo.vendor_status(None, &opts).unwrap_err();
+    assert!(err.message().contains("1 upstream remotes need attention"));
+}
+
+// ---------------------------------------------------------------------------
+// fetch
+// ---------------------------------------------------------------------------
+
+#[test]
+fn fetch_prune_moves_deleted_branch_aside() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let (upstream_repo, upstream_dir) = setup_upstream(&[("lib.txt", b"v1\n")]);
+    let head = upstream_repo
+        .find_reference("refs/heads/main")
+        .unwrap()
+        .peel_to_commit()
+        .unwrap();
+    upstream_repo.branch("feature", &head, false).unwrap();
+    upstream_repo
+        .tag_lightweight("v1", head.as_object(), false)
+        .unwrap();
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+    repo.config()
+        .unwrap()
+        .set_bool("vendor.prune", true)
+        .unwrap();
+    let url = upstream_dir.path().display();
+    write_gitattributes(
+        dir.path(),
+        &format!("*.txt vendored name=test/upstream url={url} branch=feature\n"),
+    );
+
+    repo.vendor_fetch(None, None).unwrap();
+    assert!(
+        repo.find_reference("refs/vendor-tags/test/upstream/v1")
+            .is_ok()
+    );
+
+    upstream_repo
+        .find_reference("refs/heads/feature")
+        .unwrap()
+        .delete()
+        .unwrap();
+    upstream_repo
+        .find_reference("refs/tags/v1")
+        .unwrap()
+        .delete()
+        .unwrap();
+    repo.vendor_fetch(None, None).unwrap();
+    assert!(repo.find_reference("refs/vendor/test/upstream").is_err());
+    assert!(
+        repo.find_reference("refs/vendor-tags/test/upstream/v1")
+            .is_err()
+    );
+    assert_eq!(
+        repo.refname_to_id("refs/vendor-gone/test/upstream")
+            .unwrap(),
+        head.id()
+    );
+    assert!(repo.vendor_overview(None).unwrap()[0].branch_gone);
+
+    write_gitattributes(
+        dir.path(),
+        &format!("*.txt vendored name=test/upstream url={url} branch=main\n"),
+    );
+    repo.vendor_fetch(None, None).unwrap();
+    assert!(
+        repo.find_reference("refs/vendor-gone/test/upstream")
+            .is_err()
+    );
+    assert!(!repo.vendor_overview(None).unwrap()[0].branch_gone);
+}
+
+#[test]
+fn gc_expires_refs_of_untracked_dependencies() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let (_kept_repo, kept_dir) = setup_upstream(&[("lib.txt", b"kept\n")]);
+    let (dropped_repo, dropped_dir) = setup_upstream(&[("old.md", b"dropped\n")]);
+    let dropped = dropped_repo.refname_to_id("refs/heads/main").unwrap();
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+    let kept_line = format!(
+        "*.txt vendored name=test/kept url={} branch=main\n",
+        kept_dir.path().display()
+    );
+    write_gitattributes(
+        dir.path(),
+        &format!(
+            "{kept_line}*.md vendored name=test/dropped url={} branch=main\n",
+            dropped_dir.path().display()
+        ),
+    );
+    repo.vendor_fetch(None, None).unwrap();
+    write_gitattributes(dir.path(), &kept_line);
+
+    let opts = git_vendor::GcOpts {
+        expire: 0,
+        dry_run: true,
+        ..Default::default()
+    };
+    let report = repo.vendor_gc(&opts).unwrap();
+    assert_eq!(report.deleted_refs, vec!["refs/vendor/test/dropped"]);
+    assert_eq!(report.objects, None);
+    assert!(repo.find_reference("refs/vendor/test/dropped").is_ok());
+
+    let report = repo
+        .vendor_gc(&git_vendor::GcOpts {
+            dry_run: false,
+            ..opts
+        })
+        .unwrap();
+    assert_eq!(report.deleted_refs, vec!["refs/vendor/test/dropped"]);
+    let (before, after) = report.objects.unwrap();
+    assert!(after < before, "{before} -> {after}");
+
+    let repo = Repository::open(dir.path()).unwrap();
+    assert!(repo.find_reference("refs/vendor/test/dropped").is_err());
+    assert!(repo.find_commit(dropped).is_err());
+    assert!(repo.find_reference("refs/vendor/test/kept").is_ok());
+}
+
+#[test]
+fn fetch_through_cache_borrows_objects_until_dissociated() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let (upstream_repo, upstream_dir) = setup_upstream(&[("lib.txt", b"cached\n")]);
+    let head = upstream_repo.refname_to_id("refs/heads/main").unwrap();
+    let cache_dir = TempDir::new().unwrap();
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+    let mut config = repo.config().unwrap();
+    config
+        .set_str("vendor.cacheDir", cache_dir.path().to_str().unwrap())
+        .unwrap();
+    write_gitattributes(
+        dir.path(),
+        &format!(
+            "*.txt vendored name=test/upstream url={} branch=main\n",
+            upstream_dir.path().display()
+        ),
+    );
+
+    repo.vendor_fetch(None, None).unwrap();
+    assert_eq!(
+        repo.refname_to_id("refs/vendor/test/upstream").unwrap(),
+        head
+    );
+    let alternates = repo.path().join("objects/info/alternates");
+    let borrowed = fs::read_to_string(&alternates).unwrap();
+    let cache = Path::new(borrowed.trim()).parent().unwrap();
+    assert!(cache.starts_with(cache_dir.path()));
+    let cache = Repository::open_bare(cache).unwrap();
+    assert_eq!(cache.refname_to_id("refs/heads/main").unwrap(), head);
+
+    config.set_bool("vendor.dissociate", true).unwrap();
+    repo.vendor_fetch(None, None).unwrap();
+    assert!(!alternates.exists());
+
+    drop(cache);
+    drop(cache_dir);
+    let repo = Repository::open(dir.path()).unwrap();
+    let commit = repo.find_commit(head).unwrap();
+    assert!(commit.tree().unwrap().get_name("lib.txt").is_some());
+}
+#[test]
+fn fetch_errors_with_no_deps() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    let err = repo.vendor_fetch(None, None).unwrap_err();
+    assert!(err.message().contains("No vendored dependencies to fetch"));
+}
+
+#[test]
+fn fetch_with_jobs_fetches_every_dependency() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let (upstream_a, dir_a) = setup_upstream(&[("a.txt", b"a\n")]);
+    let (upstream_b, dir_b) = setup_upstream(&[("b.txt", b"b\n")]);
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+    repo.config().unwrap().set_i32("vendor.jobs", 2).unwrap();
+
+    write_gitattributes(
+        dir.path(),
+        &format!(
+            "a/ vendored name=test/a url={} branch=main\n\
+             b/ vendored name=test/b url={} branch=main\n",
+            dir_a.path().display(),
+            dir_b.path().display()
+        ),
+    );
+
+    repo.vendor_fetch(None, None).unwrap();
+
+    let head = |upstream: &git2::Repository| upstream.head().unwrap().target().unwrap();
+    assert_eq!(
+        repo.refname_to_id("refs/vendor/test/a").unwrap(),
+        head(&upstream_a)
+    );
+    assert_eq!(
+        repo.refname_to_id("refs/vendor/test/b").unwrap(),
+        head(&upstream_b)
+    );
+}
+
+#[test]
+fn locked_mode_resolves_to_recorded_commits() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let (upstream_repo, upstream_dir) = setup_upstream(&[("a.txt", b"1\n")]);
+    let first = upstream_repo.head().unwrap().target().unwrap();
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+    let url = upstream_dir.path().display();
+    write_gitattributes(
+        dir.path(),
+        &format!("*.txt vendored name=test/upstream url={url} branch=main\n"),
+    );
+    commit_all(&repo, "track");
+    repo.vendor_fetch(None, None).unwrap();
+    repo.vendor_merge(None, &VendorMergeOpts::default(), None)
+        .unwrap();
+
+    fs::write(upstream_dir.path().join("a.txt"), b"2\n").unwrap();
+    let second = commit_all(&upstream_repo, "second");
+    let vendor_ref = "refs/vendor/test/upstream";
+
+    repo.config()
+        .unwrap()
+        .set_bool("vendor.locked", true)
+        .unwrap();
+    repo.vendor_fetch(None, None).unwrap();
+    assert_eq!(repo.refname_to_id(vendor_ref).unwrap(), first);
+    repo.vendor_verify(None).unwrap();
+
+    // An unlocked fetch moves the vendor ref past the recorded commit.
+    repo.config()
+        .unwrap()
+        .set_bool("vendor.locked", false)
+        .unwrap();
+    repo.vendor_fetch(None, None).unwrap();
+    assert_eq!(repo.refname_to_id(vendor_ref).unwrap(), second);
+    repo.config()
+        .unwrap()
+        .set_bool("vendor.locked", true)
+        .unwrap();
+    let err = repo
+        .vendor_merge(None, &VendorMergeOpts::default(), None)
+        .unwrap_err();
+    assert!(err.message().contains("locked"), "{}", err.message());
+
+    // A definition disagreeing with the record is refused.
+    write_gitattributes(
+        dir.path(),
+        &format!("*.txt vendored name=test/upstream url={url} branch=other\n"),
+    );
+    assert!(repo.vendor_fetch(None, None).is_err());
+    assert!(repo.vendor_verify(None).is_err());
+    repo.config()
+        .unwrap()
+        .set_bool("vendor.locked", false)
+        .unwrap();
+    repo.vendor_verify(None).unwrap();
+}
+
+#[test]
+fn protocol_version_selects_fetch_backend() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let (upstream_repo, upstream_dir) = setup_upstream(&[("a.txt", b"a\n")]);
+    let head = upstream_repo.head().unwrap().target().unwrap();
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+    write_gitattributes(
+        dir.path(),
+        &format!(
+            "a/ vendored name=test/a url={} branch=main\n",
+            upstream_dir.path().display()
+        ),
+    );
+    let last_fetch = |repo: &Repository| {
+        let reflog = repo.reflog("refs/vendor/test/a").unwrap();
+        reflog.get(0).unwrap().message().unwrap().to_string()
+    };
+
+    // Protocol v2 fetches go through the git command line.
+    repo.vendor_fetch(None, None).unwrap();
+    assert_eq!(repo.refname_to_id("refs/vendor/test/a").unwrap(), head);
+    assert!(
+        last_fetch(&repo).contains("--no-tags"),
+        "{}",
+        last_fetch(&repo)
+    );
+
+    repo.config()
+        .unwrap()
+        .set_i32("vendor.protocolVersion", 0)
+        .unwrap();
+    repo.find_reference("refs/vendor/test/a")
+        .unwrap()
+        .delete()
+        .unwrap();
+    repo.vendor_fetch(None, None).unwrap();
+    assert_eq!(repo.refname_to_id("refs/vendor/test/a").unwrap(), head);
+    assert!(
+        !last_fetch(&repo).contains("--no-tags"),
+        "{}",
+        last_fetch(&repo)
+    );
+}
+
+#[test]
+fn fetch_after_losing_vendor_refs_negotiates_from_provenance() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let (upstream_repo, upstream_dir) = setup_upstream(&[("lib/a.txt", b"a\n")]);
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+    write_gitattributes(
+        dir.path(),
+        &format!(
+            "lib/ vendored name=test/upstream url=file://{} branch=main\n",
+            upstream_dir.path().display()
+        ),
+    );
+    commit_all(&repo, "vendor config");
+    repo.vendor_fetch(None, None).unwrap();
+    repo.vendor_merge(None, &VendorMergeOpts::default(), None)
+        .unwrap();
+
+    // Lose the vendor refs, then move upstream on.
+    repo.find_reference("refs/vendor/test/upstream")
+        .unwrap()
+        .delete()
+        .unwrap();
+    fs::write(upstream_dir.path().join("lib/b.txt"), "b\n").unwrap();
+    let mut index = upstream_repo.index().unwrap();
+    index.add_path(Path::new("lib/b.txt")).unwrap();
+    let tree = upstream_repo
+        .find_tree(index.write_tree().unwrap())
+        .unwrap();
+    let sig = upstream_repo.signature().unwrap();
+    let parent = upstream_repo.head().unwrap().peel_to_commit().unwrap();
+    let head = upstream_repo
+        .commit(Some("HEAD"), &sig, &sig, "add b", &tree, &[&parent])
+        .unwrap();
+
+    repo.vendor_fetch(None, None).unwrap();
+    assert_eq!(
+        repo.refname_to_id("refs/vendor/test/upstream").unwrap(),
+        head
+    );
+    assert_eq!(repo.vendor_overview(None).unwrap()[0].pending, 1);
+}
+
+#[test]
+fn libgit2_fetch_seeds_negotiation_from_provenance() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let (upstream_repo, upstream_dir) = setup_upstream(&[("lib/a.txt", b"a\n")]);
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+    repo.config()
+        .unwrap()
+        .set_i32("vendor.protocolVersion", 0)
+        .unwrap();
+    write_gitattributes(
+        dir.path(),
+        &format!(
+            "lib/ vendored name=test/upstream url={} branch=main\n",
+            upstream_dir.path().display()
+        ),
+    );
+    commit_all(&repo, "vendor config");
+    repo.vendor_fetch(None, None).unwrap();
+    repo.vendor_merge(None, &VendorMergeOpts::default(), None)
+        .unwrap();
+    let vendored = upstream_repo.head().unwrap().target().unwrap();
+
+    // Lose the vendor ref; the provenance commit is offered through a
+    // temporary ref while fetching.
+    repo.find_reference("refs/vendor/test/upstream")
+        .unwrap()
+        .delete()
+        .unwrap();
+    let mut seen = Vec::new();
+    let mut callbacks = git2::RemoteCallbacks::new();
+    callbacks.update_tips(|_, _, _| {
+        if seen.is_empty() {
+            seen = repo
+                .references_glob("refs/vendor-haves/*")
+                .unwrap()
+                .map(|reference| reference.unwrap().target().unwrap())
+                .collect();
+        }
+        true
+    });
+    let mut fetch_opts = git2::FetchOptions::new();
+    fetch_opts.remote_callbacks(callbacks);
+    repo.vendor_fetch(None, Some(&mut fetch_opts)).unwrap();
+    drop(fetch_opts);
+
+    assert_eq!(seen, [vendored]);
+    assert_eq!(
+        repo.refname_to_id("refs/vendor/test/upstream").unwrap(),
+        vendored
+    );
+    assert_eq!(
+        repo.references_glob("refs/vendor-haves/*").unwrap().count(),
+        0
+    );
+}
+
+#[test]
+fn unshallow_fetches_full_history_of_shallow_dependencies() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let (upstream_repo, upstream_dir) = setup_upstream(&[("a.txt", b"1\n")]);
+    let root = upstream_repo.head().unwrap().target().unwrap();
+    fs::write(upstream_dir.path().join("a.txt"), "2\n").unwrap();
+    let mut index = upstream_repo.index().unwrap();
+    index.add_path(Path::new("a.txt")).unwrap();
+    let tree = upstream_repo
+        .find_tree(index.write_tree().unwrap())
+        .unwrap();
+    let sig = upstream_repo.signature().unwrap();
+    let parent = upstream_repo.find_commit(root).unwrap();
+    upstream_repo
+        .commit(Some("HEAD"), &sig, &sig, "two", &tree, &[&parent])
+        .unwrap();
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+    write_gitattributes(
+        dir.path(),
+        &format!(
+            "*.txt vendored name=test/upstream url=file://{} branch=main depth=1\n",
+            upstream_dir.path().display()
+        ),
+    );
+    repo.vendor_fetch(None, None).unwrap();
+    assert!(repo.find_commit(root).is_err());
+
+    assert_eq!(repo.vendor_unshallow(None).unwrap(), ["test/upstream"]);
+    assert!(repo.find_commit(root).is_ok());
+    assert!(repo.vendor_unshallow(None).unwrap().is_empty());
+}
+
+#[test]
+fn fetch_tags_setting_and_attribute_control_tag_refs() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let (upstream_repo, upstream_dir) = setup_upstream(&[("a.txt", b"a\n")]);
+    let head = upstream_repo.head().unwrap().peel_to_commit().unwrap();
+    upstream_repo
+        .tag_lightweight("v1", head.as_object(), false)
+        .unwrap();
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+    let url = upstream_dir.path().display();
+    let tag = "refs/vendor-tags/test/upstream/v1";
+
+    repo.config()
+        .unwrap()
+        .set_bool("vendor.fetchTags", false)
+        .unwrap();
+    write_gitattributes(
+        dir.path(),
+        &format!("*.txt vendored name=test/upstream url={url} branch=main\n"),
+    );
+    repo.vendor_fetch(None, None).unwrap();
+    assert!(repo.find_reference("refs/vendor/test/upstream").is_ok());
+    assert!(repo.find_reference(tag).is_err());
+
+    // The attribute wins over the setting.
+    write_gitattributes(
+        dir.path(),
+        &format!("*.txt vendored name=test/upstream url={url} branch=main fetch-tags=true\n"),
+    );
+    repo.vendor_fetch(None, None).unwrap();
+    assert_eq!(repo.refname_to_id(tag).unwrap(), head.id());
+}
+
+#[test]
+fn tags_lists_fetched_and_remote_tags_by_version() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let (upstream_repo, upstream_dir) = setup_upstream(&[("a.txt", b"a\n")]);
+    let first = upstream_repo.head().unwrap().peel_to_commit().unwrap();
+    for tag in ["v1.2.0", "nightly"] {
+        upstream_repo
+            .tag_lightweight(tag, first.as_object(), false)
+            .unwrap();
+    }
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+    let url = upstream_dir.path().display();
+    write_gitattributes(
+        dir.path(),
+        &format!("*.txt vendored name=test/upstream url={url} branch=main\n"),
+    );
+    commit_all(&repo, "track");
+    repo.vendor_fetch(None, None).unwrap();
+    repo.vendor_merge(None, &VendorMergeOpts::default(), None)
+        .unwrap();
+
+    fs::write(upstream_dir.path().join("a.txt"), b"b\n").unwrap();
+    let second = commit_all(&upstream_repo, "second");
+    let sig = upstream_repo.signature().unwrap();
+    upstream_repo
+        .tag(
+            "v1.10.0",
+            &upstream_repo.find_object(second, None).unwrap(),
+            &sig,
+            "v1.10.0",
+            false,
+        )
+        .unwrap();
+
+    let listed = |remote| {
+        repo.vendor_tags("test/upstream", remote)
+            .unwrap()
+            .into_iter()
+            .map(|tag| (tag.name, tag.vendored))
+            .collect::<Vec<_>>()
+    };
+    let expected = [
+        ("nightly".to_string(), true),
+        ("v1.2.0".to_string(), true),
+        ("v1.10.0".to_string(), false),
+    ];
+    assert_eq!(listed(true), expected);
+    assert_eq!(listed(false).len(), 2);
+    repo.vendor_fetch(None, None).unwrap();
+    assert_eq!(listed(false), expected);
+
+    // The annotated tag is peeled to its commit.
+    let tags = repo.vendor_tags("*.txt", false).unwrap();
+    assert_eq!(tags[2].commit, second.to_string());
+    assert!(repo.vendor_tags("test/other", false).is_err());
+}
+
+#[test]
+fn changelog_lists_upstream_commits_since_merge() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let (upstream_repo, upstream_dir) = setup_upstream(&[
+        ("a.txt", b"1\n"),
+        ("CHANGELOG.md", b"# Changelog\n\n## 1.0.0\n- First\n"),
+    ]);
+    let first = upstream_repo.head().unwrap().peel_to_commit().unwrap();
+    upstream_repo
+        .tag_lightweight("v1.0.0", first.as_object(), false)
+        .unwrap();
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+    let url = upstream_dir.path().display();
+    write_gitattributes(
+        dir.path(),
+        &format!("*.txt vendored name=test/upstream url={url} branch=main\n"),
+    );
+    commit_all(&repo, "track");
+    repo.vendor_fetch(None, None).unwrap();
+
+    // Never merged: nothing to compare against.
+    let changelog = repo.vendor_changelog("test/upstream", false).unwrap();
+    assert!(changelog.from.is_none());
+    assert!(changelog.commits.is_empty());
+
+    repo.vendor_merge(None, &VendorMergeOpts::default(), None)
+        .unwrap();
+    fs::write(upstream_dir.path().join("a.txt"), b"2\n").unwrap();
+    fs::write(
+        upstream_dir.path().join("CHANGELOG.md"),
+        b"# Changelog\n\n## 1.1.0\n- Second\n\n## 1.0.0\n- First\n",
+    )
+    .unwrap();
+    commit_all(&upstream_repo, "Second change");
+    fs::write(upstream_dir.path().join("a.txt"), b"3\n").unwrap();
+    let third = commit_all(&upstream_repo, "Third change");
+    upstream_repo
+        .tag_lightweight(
+            "v1.1.0",
+            &upstream_repo.find_object(third, None).unwrap(),
+            false,
+        )
+        .unwrap();
+    repo.vendor_fetch(None, None).unwrap();
+
+    let changelog = repo.vendor_changelog("*.txt", false).unwrap();
+    let from = changelog.from.as_ref().unwrap();
+    assert_eq!(from.commit, first.id().to_string());
+    assert_eq!(from.label(), "v1.0.0");
+    assert_eq!(changelog.to.label(), "v1.1.0");
+    let summaries: Vec<&str> = changelog
+        .commits
+        .iter()
+        .map(|commit| commit.summary.as_str())
+        .collect();
+    assert_eq!(summaries, ["Third change", "Second change"]);
+    // A local upstream has no web interface to link to.
+    assert!(changelog.commits.iter().all(|commit| commit.url.is_none()));
+    assert!(changelog.compare_url.is_none());
+    assert!(changelog.notes.is_none());
+
+    let markdown = changelog.to_markdown();
+    assert!(markdown.starts_with("### test/upstream: v1.0.0 → v1.1.0\n"));
+    assert!(markdown.contains(": 2 commits."));
+    assert!(markdown.contains("Second change\n"));
+
+    let notes = repo
+        .vendor_changelog("test/upstream", true)
+        .unwrap()
+        .notes
+        .unwrap();
+    assert_eq!(notes.path, "CHANGELOG.md");
+    assert_eq!(notes.sections, ["## 1.1.0\n- Second"]);
+    assert!(repo.vendor_changelog("test/other", false).is_err());
+}
+
+#[test]
+fn pin_switches_dependency_to_tag_or_commit() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let (upstream_repo, upstream_dir) = setup_upstream(&[("a.txt", b"1\n")]);
+    let first = upstream_repo.head().unwrap().peel_to_commit().unwrap();
+    let sig = upstream_repo.signature().unwrap();
+    upstream_repo
+        .tag("v1.0.0", first.as_object(), &sig, "v1.0.0", false)
+        .unwrap();
+    fs::write(upstream_dir.path().join("a.txt"), b"2\n").unwrap();
+    let second = commit_all(&upstream_repo, "second");
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+    let url = upstream_dir.path().display();
+    write_gitattributes(
+        dir.path(),
+        &format!("*.txt vendored name=test/upstream url={url} branch=main\n"),
+    );
+    commit_all(&repo, "track");
+    repo.vendor_fetch(None, None).unwrap();
+
+    let dep = repo.vendor_pin("test/upstream", "v1.0.0").unwrap();
+    assert_eq!(dep.rev.as_deref(), Some("v1.0.0"));
+    assert_eq!(dep.branch, None);
+    let attrs = fs::read_to_string(dir.path().join(".gitattributes")).unwrap();
+    assert!(attrs.contains(" rev=v1.0.0"), "{attrs}");
+    assert!(!attrs.contains("branch="), "{attrs}");
+    commit_all(&repo, "pin");
+
+    // The annotated tag is peeled to its commit.
+    repo.vendor_fetch(None, None).unwrap();
+    assert_eq!(
+        repo.refname_to_id("refs/vendor/test/upstream").unwrap(),
+        first.id()
+    );
+    repo.vendor_merge(None, &VendorMergeOpts::default(), None)
+        .unwrap();
+    assert_eq!(fs::read(dir.path().join("a.txt")).unwrap(), b"1\n");
+
+    // An abbreviated id of a fetched commit is expanded.
+    let short = &second.to_string()[..8];
+    let dep = repo.vendor_pin("*.txt", short).unwrap();
+    assert_eq!(dep.rev, Some(second.to_string()));
+    repo.vendor_fetch(None, None).unwrap();
+    assert_eq!(
+        repo.refname_to_id("refs/vendor/test/upstream").unwrap(),
+        second
+    );
+
+    assert!(repo.vendor_pin("test/upstream", "v9.9.9").is_err());
+}
+
+#[test]
+fn unpin_follows_branch_again() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let (upstream_repo, upstream_dir) = setup_upstream(&[("a.txt", b"1\n")]);
+    let first = upstream_repo.head().unwrap().peel_to_commit().unwrap();
+    fs::write(upstream_dir.path().join("a.txt"), b"2\n").unwrap();
+    let second = commit_all(&upstream_repo, "second");
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+    let url = upstream_dir.path().display();
+    write_gitattributes(
+        dir.path(),
+        &format!(
+            "*.txt vendored name=test/upstream url={url} rev={}\n",
+            first.id()
+        ),
+    );
+    repo.vendor_fetch(None, None).unwrap();
+    assert_eq!(
+        repo.refname_to_id("refs/vendor/test/upstream").unwrap(),
+        first.id()
+    );
+
+    let dep = repo.vendor_unpin("test/upstream", None).unwrap();
+    assert_eq!((dep.rev, dep.branch), (None, None));
+    let attrs = fs::read_to_string(dir.path().join(".gitattributes")).unwrap();
+    assert!(!attrs.contains("rev="), "{attrs}");
+    repo.vendor_fetch(None, None).unwrap();
+    assert_eq!(
+        repo.refname_to_id("refs/vendor/test/upstream").unwrap(),
+        second
+    );
+
+    // Once unpinned, a branch must be named to change anything.
+    assert!(repo.vendor_unpin("test/upstream", None).is_err());
+    let dep = repo.vendor_unpin("*.txt", Some("main")).unwrap();
+    assert_eq!(dep.branch.as_deref(), Some("main"));
+}
+
+#[test]
+fn git_config_defaults_apply_to_track_fetch_and_merge() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let (_upstream_repo, upstream_dir) = setup_upstream(&[("lib/a.txt", b"a\n")]);
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+    let mut config = repo.config().unwrap();
+    config.set_str("vendor.defaultBranch", "main").unwrap();
+    config
+        .set_str("vendor.refPrefix", "refs/upstreams")
+        .unwrap();
+
+    let url = upstream_dir.path().display().to_string();
+    repo.track_pattern("lib/", &url, None, Some("test/upstream"), None, false)
+        .unwrap();
+    let attrs = fs::read_to_string(dir.path().join(".gitattributes")).unwrap();
+    assert!(attrs.contains("branch=main"), "{attrs}");
+    commit_all(&repo, "vendor config");
+
+    repo.vendor_fetch(None, None).unwrap();
+    assert!(repo.find_reference("refs/upstreams/test/upstream").is_ok());
+    assert!(repo.find_reference("refs/vendor/test/upstream").is_err());
+
+    repo.vendor_merge(None, &VendorMergeOpts::default(), None)
+        .unwrap();
+    assert!(dir.path().join("lib/a.txt").exists());
+    let overview = repo.vendor_overview(None).unwrap();
+    assert_eq!(overview[0].reference, "refs/upstreams/test/upstream");
+    assert_eq!(overview[0].pending, 0);
+}
+
+#[test]
+fn per_dependency_git_config_overrides_url() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let (upstream_repo, upstream_dir) = setup_upstream(&[("a.txt", b"a\n")]);
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+    repo.config()
+        .unwrap()
+        .set_str(
+            "vendor.test/a.url",
+            &upstream_dir.path().display().to_string(),
+        )
+        .unwrap();
+
+    write_gitattributes(
+        dir.path(),
+        "a/ vendored name=test/a url=/nonexistent/upstream branch=main\n",
+    );
+
+    repo.vendor_fetch(None, None).unwrap();
+    assert_eq!(
+        repo.refname_to_id("refs/vendor/test/a").unwrap(),
+        upstream_repo.head().unwrap().target().unwrap()
+    );
+    assert_eq!(
+        repo.vendor_overview(None).unwrap()[0].url,
+        upstream_dir.path().display().to_string()
+    );
+}
+
+#[test]
+fn fetch_refuses_when_offline() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let (_upstream_repo, upstream_dir) = setup_upstream(&[("a.txt", b"a\n")]);
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+    repo.config()
+        .unwrap()
+        .set_bool("vendor.offline", true)
+        .unwrap();
+
+    write_gitattributes(
+        dir.path(),
+        &format!(
+            "a/ vendored name=test/a url={} branch=main\n",
+            upstream_dir.path().display()
+        ),
+    );
+
+    let err = repo.vendor_fetch(None, None).unwrap_err();
+    assert!(err.message().contains("offline mode"));
+    assert!(repo.find_reference("refs/vendor/test/a").is_err());
+}
+
+// ---------------------------------------------------------------------------
+// merge
+// ---------------------------------------------------------------------------
+
+#[test]
+fn merge_errors_with_no_deps() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    let err = Vendor::vendor_merge(&repo, None, &Default::default(), None).unwrap_err();
+    assert!(err.message().contains("No vendored dependencies to merge"));
+}
+
+// ---------------------------------------------------------------------------
+// provenance
+// ---------------------------------------------------------------------------
+
+#[test]
+fn merge_commits_provenance_record() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let (upstream_repo, upstream_dir) = setup_upstream(&[("lib.txt", b"content\n")]);
+    let upstream_head = upstream_repo.head().unwrap().target().unwrap();
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    write_gitattributes(
+        dir.path(),
+        &format!(
+            "*.txt vendored name=test/upstream url={} branch=main\n",
+            upstream_dir.path().display(),
+        ),
+    );
+    commit_all(&repo, "vendor config");
+
+    repo.vendor_fetch(None, None).unwrap();
+    repo.vendor_merge(None, &VendorMergeOpts::default(), None)
+        .unwrap();
+
+    let head_tree = repo.head().unwrap().peel_to_tree().unwrap();
+    let entry = head_tree
+        .get_path(Path::new(".git-vendor/test/upstream.json"))
+        .expect("provenance record should be committed");
+    let blob = repo.find_blob(entry.id()).unwrap();
+    let provenance = Provenance::from_json(std::str::from_utf8(blob.content()).unwrap()).unwrap();
+
+    assert_eq!(provenance.name, "test/upstream");
+    assert_eq!(provenance.reference, "refs/heads/main");
+    assert_eq!(provenance.commit, upstream_head.to_string());
+    assert!(provenance.fetched_at.ends_with('Z'));
+    assert_eq!(provenance.merged_by.as_deref(), Some("Test <test@test>"));
+    assert_eq!(
+        repo.vendor_overview(None).unwrap()[0].merged_by,
+        provenance.merged_by
+    );
+    assert!(
+        dir.path().join(".git-vendor/test/upstream.json").exists(),
+        "provenance record should be in the working tree"
+    );
+}
+
+// ---------------------------------------------------------------------------
+// signature verification
+// ---------------------------------------------------------------------------
+
+#[test]
+fn merge_deepens_shallow_fetch_to_find_merge_base() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let (upstream_repo, upstream_dir) =
+        setup_upstream(&[("lib/a.txt", b"one\ntwo\nthree\n"), ("lib/b.txt", b"b\n")]);
+    let upstream_commit = |path: &str, content: &str| {
+        fs::write(upstream_dir.path().join(path), content).unwrap();
+        let mut index = upstream_repo.index().unwrap();
+        index.add_path(Path::new(path)).unwrap();
+        let tree = upstream_repo
+            .find_tree(index.write_tree().unwrap())
+            .unwrap();
+        let sig = upstream_repo.signature().unwrap();
+        let parent = upstream_repo.head().unwrap().peel_to_commit().unwrap();
+        upstream_repo
+            .commit(Some("HEAD"), &sig, &sig, path, &tree, &[&parent])
+            .unwrap()
+    };
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+    write_gitattributes(
+        dir.path(),
+        &format!(
+            "lib/ vendored name=test/upstream url=file://{} branch=main depth=1\n",
+            upstream_dir.path().display()
+        ),
+    );
+    commit_all(&repo, "vendor config");
+    repo.vendor_fetch(None, None).unwrap();
+    repo.vendor_merge(None, &VendorMergeOpts::default(), None)
+        .unwrap();
+    let vendored = repo.refname_to_id("refs/vendor/test/upstream").unwrap();
+
+    // Patch the vendored copy, then forget the vendored upstream commit.
+    fs::write(dir.path().join("lib/a.txt"), "one\ntwo\nthree\nlocal\n").unwrap();
+    commit_all(&repo, "patch vendored a");
+    repo.find_reference("refs/vendor/test/upstream")
+        .unwrap()
+        .delete()
+        .unwrap();
+    repo.vendor_gc(&git_vendor::GcOpts {
+        expire: 0,
+        ..Default::default()
+    })
+    .unwrap();
+
+    upstream_commit("lib/b.txt", "b2\n");
+    upstream_commit("lib/a.txt", "upstream\none\ntwo\nthree\n");
+    let repo = Repository::open(dir.path()).unwrap();
+    repo.vendor_fetch(None, None).unwrap();
+    assert!(repo.find_commit(vendored).is_err());
+
+    let no_deepen = VendorMergeOpts {
+        no_deepen: true,
+        ..Default::default()
+    };
+    let err = repo.vendor_merge(None, &no_deepen, None).unwrap_err();
+    assert!(err.message().contains("missing"), "{}", err.message());
+    assert_eq!(error::code(&err), error::Code::NoMergeBase);
+
+    repo.vendor_merge(None, &VendorMergeOpts::default(), None)
+        .unwrap();
+    assert!(repo.find_commit(vendored).is_ok());
+    assert_eq!(
+        fs::read_to_string(dir.path().join("lib/a.txt")).unwrap(),
+        "upstream\none\ntwo\nthree\nlocal\n"
+    );
+    assert_eq!(
+        fs::read_to_string(dir.path().join("lib/b.txt")).unwrap(),
+        "b2\n"
+    );
+}
+
+#[test]
+fn merge_verify_signatures_refuses_unsigned_commit() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let (_upstream_repo, upstream_dir) = setup_upstream(&[("lib.txt", b"content\n")]);
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    write_gitattributes(
+        dir.path(),
+        &format!(
+            "*.txt vendored name=test/upstream url={} branch=main verify-signatures\n",
+            upstream_dir.path().display(),
+        ),
+    );
+    commit_all(&repo, "vendor config");
+
+    repo.vendor_fetch(None, None).unwrap();
+    let err = repo
+        .vendor_merge(None, &VendorMergeOpts::default(), None)
+        .unwrap_err();
+    assert!(
+        err.message().contains("is not signed"),
+        "expected unsigned-commit error, got: {}",
+        err.message()
+    );
+    assert!(
+        !dir.path().join("lib.txt").exists(),
+        "unsigned content must not be merged"
+    );
+}
+
+/// A throwaway gpg home with a signing key, and a script running gpg with
+/// it to use as `gpg.program`; `None` when gpg is not installed.
+fn setup_gpg() -> Option<(TempDir, std::path::PathBuf)> {
+    let home = TempDir::new().unwrap();
+    let status = std::process::Command::new("gpg")
+        .arg("--homedir")
+        .arg(home.path())
+        .args(["--batch", "--passphrase", "", "--quick-gen-key"])
+        .args(["Test <test@test>", "ed25519", "sign", "never"])
+        .stderr(std::process::Stdio::null())
+        .status()
+        .ok()?;
+    if !status.success() {
+        return None;
+    }
+    let program = home.path().join("gpg.sh");
+    fs::write(
+        &program,
+        format!(
+            "#!/bin/sh\nexec gpg --homedir '{}' \"$@\"\n",
+            home.path().display()
+        ),
+    )
+    .unwrap();
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(&program, fs::Permissions::from_mode(0o755)).unwrap();
+    Some((home, program))
+}
+
+/// An armored detached signature of `data` made with `gpg`'s key.
+fn gpg_sign(gpg: &Path, data: &[u8]) -> String {
+    let mut child = std::process::Command::new(gpg)
+        .args(["--batch", "--pinentry-mode", "loopback", "--passphrase", ""])
+        .args(["--detach-sign", "--armor"])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(data).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Create the annotated tag `name` on `commit`, signed with `gpg` if given.
+fn tag_upstream(repo: &Repository, name: &str, commit: Oid, gpg: Option<&Path>) {
+    let mut tag = format!(
+        "object {commit}\ntype commit\ntag {name}\ntagger Test <test@test> 1767225600 +0000\n\n{name}\n"
+    );
+    if let Some(gpg) = gpg {
+        let signature = gpg_sign(gpg, tag.as_bytes());
+        tag.push_str(&signature);
+    }
+    let oid = repo
+        .odb()
+        .unwrap()
+        .write(git2::ObjectType::Tag, tag.as_bytes())
+        .unwrap();
+    repo.reference(&format!("refs/tags/{name}"), oid, true, "tag")
+        .unwrap();
+}
+
+#[test]
+fn merge_verify_signatures_checks_signed_tag_of_pin() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let Some((_home, gpg)) = setup_gpg() else {
+        return;
+    };
+
+    let (upstream_repo, upstream_dir) = setup_upstream(&[("lib.txt", b"content\n")]);
+    let head = upstream_repo.head().unwrap().target().unwrap();
+    tag_upstream(&upstream_repo, "v1", head, Some(&gpg));
+    tag_upstream(&upstream_repo, "v1-unsigned", head, None);
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+    repo.config()
+        .unwrap()
+        .set_str("gpg.program", gpg.to_str().unwrap())
+        .unwrap();
+
+    // The tag is signed though the commit it points to is not.
+    write_gitattributes(
+        dir.path(),
+        &format!(
+            "*.txt vendored name=test/upstream url={} rev=v1 verify-signatures\n",
+            upstream_dir.path().display(),
+        ),
+    );
+    commit_all(&repo, "vendor config");
+    repo.vendor_fetch(None, None).unwrap();
+    repo.vendor_merge(None, &VendorMergeOpts::default(), None)
+        .unwrap();
+    assert!(dir.path().join("lib.txt").exists());
+
+    write_gitattributes(
+        dir.path(),
+        &format!(
+            "*.txt vendored name=test/other url={} rev=v1-unsigned verify-signatures\n",
+            upstream_dir.path().display(),
+        ),
+    );
+    commit_all(&repo, "vendor unsigned tag");
+    repo.vendor_fetch(None, None).unwrap();
+    let err = repo
+        .vendor_merge(None, &VendorMergeOpts::default(), None)
+        .unwrap_err();
+    assert!(
+        err.message()
+            .contains("Upstream tag v1-unsigned is not signed"),
+        "{}",
+        err.message()
+    );
+}
+
+#[test]
+fn merge_verify_signatures_checks_commit_without_tag() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let Some((_home, gpg)) = setup_gpg() else {
+        return;
+    };
+
+    let (upstream_repo, upstream_dir) = setup_upstream(&[("lib.txt", b"content\n")]);
+    let unsigned = upstream_repo.head().unwrap().peel_to_commit().unwrap();
+    let sig = upstream_repo.signature().unwrap();
+    let buffer = upstream_repo
+        .commit_create_buffer(
+            &sig,
+            &sig,
+            "signed",
+            &unsigned.tree().unwrap(),
+            &[&unsigned],
+        )
+        .unwrap();
+    let signature = gpg_sign(&gpg, &buffer);
+    let signed = upstream_repo
+        .commit_signed(buffer.as_str().unwrap(), &signature, None)
+        .unwrap();
+    upstream_repo
+        .reference("refs/heads/main", signed, true, "sign")
+        .unwrap();
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+    repo.config()
+        .unwrap()
+        .set_str("gpg.program", gpg.to_str().unwrap())
+        .unwrap();
+    write_gitattributes(
+        dir.path(),
+        &format!(
+            "*.txt vendored name=test/upstream url={} branch=main verify-signatures\n",
+            upstream_dir.path().display(),
+        ),
+    );
+    commit_all(&repo, "vendor config");
+    repo.vendor_fetch(None, None).unwrap();
+    repo.vendor_merge(None, &VendorMergeOpts::default(), None)
+        .unwrap();
+    assert!(dir.path().join("lib.txt").exists());
+}
+
+// ---------------------------------------------------------------------------
+// tree pinning
+// ---------------------------------------------------------------------------
+
+#[test]
+fn merge_enforces_tree_pin() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let (_upstream_repo, upstream_dir) = setup_upstream(&[("lib.txt", b"content\n")]);
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    let url = upstream_dir.path().display().to_string();
+    write_gitattributes(
+        dir.path(),
+        &format!("*.txt vendored name=test/upstream url={url} branch=main tree=deadbeef\n"),
+    );
+    commit_all(&repo, "vendor config");
+
+    repo.vendor_fetch(None, None).unwrap();
+    let err = repo
+        .vendor_merge(None, &VendorMergeOpts::default(), None)
+        .unwrap_err();
+    assert!(
+        err.message().contains("does not match pinned tree"),
+        "expected tree pin error, got: {}",
+        err.message()
+    );
+
+    // Pinning the actual filtered tree lets the merge through.
+    let blob = repo.blob(b"content\n").unwrap();
+    let mut builder = repo.treebuilder(None).unwrap();
+    builder.insert("lib.txt", blob, 0o100644).unwrap();
+    let expected = builder.write().unwrap();
+
+    write_gitattributes(
+        dir.path(),
+        &format!("*.txt vendored name=test/upstream url={url} branch=main tree={expected}\n"),
+    );
+    commit_all(&repo, "pin tree");
+
+    repo.vendor_merge(None, &VendorMergeOpts::default(), None)
+        .unwrap();
+    assert!(dir.path().join("lib.txt").exists());
+}
+
+// ---------------------------------------------------------------------------
+// export-ignore
+// ---------------------------------------------------------------------------
+
+#[test]
+fn merge_honors_upstream_export_ignore() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let (_upstream_repo, upstream_dir) = setup_upstream(&[
+        (
+            ".gitattributes",
+            b"tests/ export-ignore\n*.yml export-ignore\n",
+        ),
+        ("lib/src.txt", b"source\n"),
+        ("lib/ci.yml", b"ci\n"),
+        ("lib/tests/data.txt", b"data\n"),
+    ]);
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    let url = upstream_dir.path().display().to_string();
+    write_gitattributes(
+        dir.path(),
+        &format!("lib/ vendored name=test/upstream url={url} branch=main honor-export-ignore\n"),
+    );
+    commit_all(&repo, "vendor config");
+
+    repo.vendor_fetch(None, None).unwrap();
+    repo.vendor_merge(None, &VendorMergeOpts::default(), None)
+        .unwrap();
+
+    assert!(dir.path().join("lib/src.txt").exists());
+    assert!(!dir.path().join("lib/ci.yml").exists());
+    assert!(!dir.path().join("lib/tests").exists());
+
+    repo.vendor_verify(None).unwrap();
+}
+
+#[test]
+fn merge_imports_upstream_attributes() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let (_upstream_repo, upstream_dir) = setup_upstream(&[
+        (".gitattributes", b"*.sh text eol=lf export-ignore\n"),
+        ("lib/run.sh", b"echo\n"),
+    ]);
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    let url = upstream_dir.path().display().to_string();
+    let config =
+        format!("lib/ vendored name=test/upstream url={url} branch=main import-attributes\n");
+    write_gitattributes(dir.path(), &config);
+    commit_all(&repo, "vendor config");
+
+    repo.vendor_fetch(None, None).unwrap();
+    repo.vendor_merge(None, &VendorMergeOpts::default(), None)
+        .unwrap();
+
+    let expected = format!(
+        "{config}\n\
+         # BEGIN git-vendor attributes test/upstream\n\
+         lib/**/*.sh text eol=lf\n\
+         # END git-vendor attributes test/upstream\n"
+    );
+    assert_eq!(
+        fs::read_to_string(dir.path().join(".gitattributes")).unwrap(),
+        expected
+    );
+
+    // The block is committed with the merge and left alone when unchanged.
+    let head_tree = repo.head().unwrap().peel_to_tree().unwrap();
+    let entry = head_tree.get_path(Path::new(".gitattributes")).unwrap();
+    let blob = repo.find_blob(entry.id()).unwrap();
+    assert_eq!(blob.content(), expected.as_bytes());
+}
+
+// ---------------------------------------------------------------------------
+// hooks
+// ---------------------------------------------------------------------------
+
+#[test]
+fn hooks_run_with_dependency_environment() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let (upstream_repo, upstream_dir) = setup_upstream(&[("lib.txt", b"content\n")]);
+    let upstream_head = upstream_repo.head().unwrap().target().unwrap();
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    write_gitattributes(
+        dir.path(),
+        &format!(
+            "*.txt vendored name=test/upstream url={} branch=main\n",
+            upstream_dir.path().display(),
+        ),
+    );
+    commit_all(&repo, "vendor config");
+
+    let log = dir.path().join(".git").join("hook.log");
+    let mut config = repo.config().unwrap();
+    for key in ["postFetchHook", "preMergeHook", "postMergeHook"] {
+        config
+            .set_str(
+                &format!("vendor.{key}"),
+                &format!(
+                    "echo \"$GIT_VENDOR_HOOK $GIT_VENDOR_NAME $GIT_VENDOR_NEW\" >> {}",
+                    log.display()
+                ),
+            )
+            .unwrap();
+    }
+
+    repo.vendor_fetch(None, None).unwrap();
+    repo.vendor_merge(None, &VendorMergeOpts::default(), None)
+        .unwrap();
+
+    let lines: Vec<String> = fs::read_to_string(&log)
+        .unwrap()
+        .lines()
+        .map(str::to_string)
+        .collect();
+    assert_eq!(
+        lines,
+        [
+            "vendor.postFetchHook",
+            "vendor.preMergeHook",
+            "vendor.postMergeHook"
+        ]
+        .map(|hook| format!("{hook} test/upstream {upstream_head}"))
+    );
+}
+
+#[test]
+fn failing_pre_merge_hook_aborts_merge() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let (_upstream_repo, upstream_dir) = setup_upstream(&[("lib.txt", b"content\n")]);
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    write_gitattributes(
+        dir.path(),
+        &format!(
+            "*.txt vendored name=test/upstream url={} branch=main\n",
+            upstream_dir.path().display(),
+        ),
+    );
+    commit_all(&repo, "vendor config");
+    repo.config()
+        .unwrap()
+        .set_str("vendor.preMergeHook", "exit 1")
+        .unwrap();
+
+    repo.vendor_fetch(None, None).unwrap();
+    let err = repo
+        .vendor_merge(None, &VendorMergeOpts::default(), None)
+        .unwrap_err();
+    assert!(err.message().contains("vendor.preMergeHook"));
+    assert!(!dir.path().join("lib.txt").exists());
+}
+
+// ---------------------------------------------------------------------------
+// install-hooks
+// ---------------------------------------------------------------------------
+
+#[test]
+fn staged_edits_flag_vendored_paths_only() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let (_upstream_repo, upstream_dir) = setup_upstream(&[("lib.txt", b"content\n")]);
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    write_gitattributes(
+        dir.path(),
+        &format!(
+            "*.txt vendored name=test/upstream url={} branch=main\n",
+            upstream_dir.path().display(),
+        ),
+    );
+    commit_all(&repo, "vendor config");
+
+    // A no-commit merge stages vendored content alongside its provenance
+    // record, which is a vendor update rather than a local edit.
+    repo.vendor_fetch(None, None).unwrap();
+    let opts = VendorMergeOpts {
+        no_commit: true,
+        ..Default::default()
+    };
+    repo.vendor_merge(None, &opts, None).unwrap();
+    assert!(repo.vendor_staged_edits().unwrap().is_empty());
+    commit_all(&repo, "merge vendor");
+
+    fs::write(dir.path().join("lib.txt"), "local edit\n").unwrap();
+    fs::write(dir.path().join("README.md"), "# host\n").unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new("lib.txt")).unwrap();
+    index.add_path(Path::new("README.md")).unwrap();
+    index.write().unwrap();
+
+    let edits = repo.vendor_staged_edits().unwrap();
+    assert_eq!(edits.len(), 1);
+    assert_eq!(edits[0].path, "lib.txt");
+    assert_eq!(edits[0].dep, "test/upstream");
+}
+
+#[test]
+fn install_hooks_preserves_foreign_hook() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    let hook = repo.vendor_install_hooks(true, false).unwrap();
+    let script = fs::read_to_string(&hook).unwrap();
+    assert!(script.contains("git-vendor pre-commit --block"));
+    assert!(script.contains("GIT_VENDOR_ALLOW_EDITS"));
+
+    // Reinstalling over our own hook is fine.
+    repo.vendor_install_hooks(false, false).unwrap();
+
+    fs::write(&hook, "#!/bin/sh\necho custom\n").unwrap();
+    assert!(repo.vendor_install_hooks(true, false).is_err());
+    repo.vendor_install_hooks(true, true).unwrap();
+}
+
+#[test]
+fn setup_is_idempotent_and_keeps_existing_values() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    write_gitattributes(
+        dir.path(),
+        "vendor/a/ vendored name=o/a url=https://example.com/o/a.git\n",
+    );
+    repo.config()
+        .unwrap()
+        .set_str("alias.vstatus", "status --short")
+        .unwrap();
+
+    let steps = repo.vendor_setup(&SetupOpts::default()).unwrap();
+    let step = |what: &str| steps.iter().find(|s| s.what.ends_with(what)).unwrap();
+    assert!(!step("alias.vstatus").changed);
+    assert!(step("alias.vstatus").skipped.is_some());
+    assert!(step("merge.vendored.driver").changed);
+    assert!(step("pre-commit").changed);
+    assert!(step("attributes").changed);
+
+    let config = repo.config().unwrap().snapshot().unwrap();
+    assert_eq!(config.get_str("alias.vstatus").unwrap(), "status --short");
+    assert_eq!(config.get_str("vendor.prune").unwrap(), "true");
+    let attributes = fs::read_to_string(repo.path().join("info/attributes")).unwrap();
+    assert!(attributes.contains("vendor/a/ merge=vendored -diff\n"));
+
+    let again = repo.vendor_setup(&SetupOpts::default()).unwrap();
+    assert!(again.iter().all(|step| !step.changed));
+
+    let forced = repo
+        .vendor_setup(&SetupOpts {
+            force: true,
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(
+        forced
+            .iter()
+            .filter(|step| step.changed)
+            .map(|step| step.what.as_str())
+            .collect::<Vec<_>>(),
+        ["alias.vstatus"]
+    );
+}
+
+#[test]
+fn explain_reports_where_values_come_from() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    write_gitattributes(
+        dir.path(),
+        "*.md text\n\
+         vendor/a/ vendored name=o/a url=https://example.com/o/a.git branch=main depth=2\n",
+    );
+    let mut config = repo.config().unwrap();
+    config.set_str("vendor.o/a.depth", "7").unwrap();
+    config
+        .set_str(
+            "url.https://mirror.example.com/.insteadOf",
+            "https://example.com/",
+        )
+        .unwrap();
+
+    let explanation = repo.vendor_explain("vendor/a/").unwrap();
+    assert_eq!(explanation.name, "o/a");
+    let value = |key: &str| {
+        let resolved = explanation.values.iter().find(|r| r.key == key).unwrap();
+        (resolved.value.as_str(), resolved.source.as_str())
+    };
+    assert_eq!(
+        value("url"),
+        ("https://example.com/o/a.git", ".gitattributes:2")
+    );
+    assert_eq!(value("fetch url").0, "https://mirror.example.com/o/a.git");
+    assert_eq!(
+        value("ref"),
+        ("refs/heads/main", "branch= in .gitattributes:2")
+    );
+    assert_eq!(value("depth"), ("7", "git config vendor.o/a.depth"));
+    assert_eq!(value("strategy"), ("merge commit", "default"));
+}
+
+#[test]
+fn scan_finds_untracked_vendored_directories() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    let files: [(&str, &str); 5] = [
+        (
+            "vendor/foo/package.json",
+            r#"{"repository": "github:acme/foo"}"#,
+        ),
+        ("vendor/github.com/o/r/r.go", "package r\n"),
+        ("lib/baz/LICENSE", "MIT\n"),
+        ("src/main.rs", "fn main() {}\n"),
+        ("vendor/tracked/LICENSE", "MIT\n"),
+    ];
+    for (path, content) in files {
+        let path = dir.path().join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+    write_gitattributes(
+        dir.path(),
+        "vendor/tracked/ vendored name=t/x url=https://example.com/t/x.git\n",
+    );
+
+    let candidates = repo.vendor_scan().unwrap();
+    let found: Vec<(&str, Option<&str>)> = candidates
+        .iter()
+        .map(|c| (c.pattern.as_str(), c.url.as_deref()))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            ("lib/baz/", None),
+            ("vendor/foo/", Some("https://github.com/acme/foo")),
+            ("vendor/github.com/o/r/", Some("https://github.com/o/r")),
+        ]
+    );
+    assert_eq!(candidates[1].name.as_deref(), Some("acme/foo"));
+}
+
+// ---------------------------------------------------------------------------
+// codeowners
+// ---------------------------------------------------------------------------
+
+#[test]
+fn codeowners_updates_managed_section() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    write_gitattributes(
+        dir.path(),
+        "vendor/a/ vendored name=o/a url=https://example.com/o/a.git codeowners=@org/a\n\
+         vendor/b/ vendored name=o/b url=https://example.com/o/b.git\n",
+    );
+    fs::write(dir.path().join("CODEOWNERS"), "* @maintainers\n").unwrap();
+    repo.config()
+        .unwrap()
+        .set_str("vendor.codeowners", "@org/third-party")
+        .unwrap();
+
+    assert!(repo.vendor_codeowners(true).is_err());
+    let path = repo.vendor_codeowners(false).unwrap();
+    assert_eq!(path, dir.path().join("CODEOWNERS"));
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "* @maintainers\n\n\
+         # BEGIN git-vendor\n\
+         vendor/a/ @org/a\n\
+         vendor/b/ @org/third-party\n\
+         # END git-vendor\n"
+    );
+    repo.vendor_codeowners(true).unwrap();
+}
+
+#[test]
+fn cargo_patch_points_cargo_at_vendored_crates() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    write_gitattributes(
+        dir.path(),
+        "vendor/foo/ vendored name=o/foo url=https://example.com/o/foo.git\n\
+         crates/ vendored name=o/bar url=https://example.com/o/bar.git\n",
+    );
+    fs::create_dir_all(dir.path().join("vendor/foo")).unwrap();
+    fs::create_dir_all(dir.path().join("crates/bar")).unwrap();
+    fs::write(
+        dir.path().join("vendor/foo/Cargo.toml"),
+        "[package]\nname = \"foo\"\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("crates/bar/Cargo.toml"),
+        "[package]\nname = \"bar\"\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("Cargo.toml"),
+        "[package]\nname = \"host\"\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("Cargo.lock"),
+        "[[package]]\nname = \"foo\"\nversion = \"1.0.0\"\n\
+         source = \"registry+https://github.com/rust-lang/crates.io-index\"\n",
+    )
+    .unwrap();
+    commit_all(&repo, "vendor crates");
+
+    assert!(repo.vendor_cargo_patch(true).is_err());
+    let path = repo.vendor_cargo_patch(false).unwrap();
+    assert_eq!(path, dir.path().join("Cargo.toml"));
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "[package]\nname = \"host\"\n\n\
+         # BEGIN git-vendor\n\
+         [patch.crates-io]\n\
+         foo = { path = \"vendor/foo\" }\n\
+         [patch.\"https://example.com/o/bar.git\"]\n\
+         bar = { path = \"crates/bar\" }\n\
+         # END git-vendor\n"
+    );
+    repo.vendor_cargo_patch(true).unwrap();
+
+    write_gitattributes(
+        dir.path(),
+        "vendor/foo/ vendored name=o/foo url=https://example.com/o/foo.git\n",
+    );
+    repo.vendor_cargo_patch(false).unwrap();
+    assert!(!fs::read_to_string(&path).unwrap().contains("crates/bar"));
+}
+
+// ---------------------------------------------------------------------------
+// outdated
+// ---------------------------------------------------------------------------
+
+#[test]
+fn outdated_reports_new_commits_and_latest_tag() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let (upstream_repo, upstream_dir) = setup_upstream(&[("lib.txt", b"v1\n")]);
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    write_gitattributes(
+        dir.path(),
+        &format!(
+            "*.txt vendored name=test/upstream url={} branch=main\n",
+            upstream_dir.path().display(),
+        ),
+    );
+    commit_all(&repo, "vendor config");
+
+    repo.vendor_fetch(None, None).unwrap();
+    repo.vendor_merge(None, &VendorMergeOpts::default(), None)
+        .unwrap();
+    assert!(repo.vendor_outdated(None).unwrap().is_empty());
+
+    fs::write(upstream_dir.path().join("lib.txt"), "v2\n").unwrap();
+    let v2 = commit_all(&upstream_repo, "v2");
+    upstream_repo
+        .tag_lightweight(
+            "v2.0.0",
+            &upstream_repo.find_object(v2, None).unwrap(),
+            false,
+        )
+        .unwrap();
+    fs::write(upstream_dir.path().join("lib.txt"), "v2+\n").unwrap();
+    commit_all(&upstream_repo, "after v2");
+
+    repo.vendor_fetch(None, None).unwrap();
+    let outdated = repo.vendor_outdated(None).unwrap();
+    assert_eq!(outdated.len(), 1);
+    assert_eq!(outdated[0].name, "test/upstream");
+    assert_eq!(outdated[0].new_commits, 2);
+    assert_eq!(outdated[0].latest_tag.as_deref(), Some("v2.0.0"));
+
+    // Upstream tags must not leak into the host's own tag namespace.
+    assert!(repo.find_reference("refs/tags/v2.0.0").is_err());
+}
+
+#[test]
+fn outdated_remote_fetches_upstreams_not_on_a_forge() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let (upstream_repo, upstream_dir) = setup_upstream(&[("lib.txt", b"v1\n")]);
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+    repo.config()
+        .unwrap()
+        .set_bool("vendor.forgeApi", true)
+        .unwrap();
+
+    write_gitattributes(
+        dir.path(),
+        &format!(
+            "*.txt vendored name=test/upstream url={} branch=main\n",
+            upstream_dir.path().display(),
+        ),
+    );
+    commit_all(&repo, "vendor config");
+
+    repo.vendor_fetch(None, None).unwrap();
+    repo.vendor_merge(None, &VendorMergeOpts::default(), None)
+        .unwrap();
+
+    fs::write(upstream_dir.path().join("lib.txt"), "v2\n").unwrap();
+    let v2 = commit_all(&upstream_repo, "v2");
+
+    // A local upstream has no forge API, so it is fetched instead.
+    assert!(repo.vendor_outdated(None).unwrap().is_empty());
+    let outdated = repo.vendor_outdated_remote(None).unwrap();
+    assert_eq!(outdated.len(), 1);
+    assert_eq!(outdated[0].latest, v2.to_string());
+    assert_eq!(outdated[0].new_commits, 1);
+}
+
+#[test]
+fn fetch_and_merge_with_events_enabled() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let (_upstream_repo, upstream_dir) = setup_upstream(&[("lib.txt", b"v1\n")]);
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    write_gitattributes(
+        dir.path(),
+        &format!(
+            "*.txt vendored name=test/upstream url={} branch=main\n",
+            upstream_dir.path().display(),
+        ),
+    );
+    commit_all(&repo, "vendor config");
+
+    git_vendor::events::set_events(true);
+    let result = repo
+        .vendor_fetch(None, None)
+        .and_then(|()| repo.vendor_merge(None, &VendorMergeOpts::default(), None));
+    git_vendor::events::set_events(false);
+    result.unwrap();
+
+    assert_eq!(
+        fs::read_to_string(dir.path().join("lib.txt")).unwrap(),
+        "v1\n"
+    );
+}
+
+#[test]
+fn bisect_finds_first_bad_upstream_commit() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let (upstream_repo, upstream_dir) = setup_upstream(&[("lib.txt", b"v1\n")]);
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    write_gitattributes(
+        dir.path(),
+        &format!(
+            "*.txt vendored name=test/upstream url={} branch=main\n",
+            upstream_dir.path().display(),
+        ),
+    );
+    commit_all(&repo, "vendor config");
+    repo.vendor_fetch(None, None).unwrap();
+    repo.vendor_merge(None, &VendorMergeOpts::default(), None)
+        .unwrap();
+
+    let mut commits = Vec::new();
+    for content in ["v2\n", "v3 bug\n", "v4 bug\n", "v5 bug\n"] {
+        fs::write(upstream_dir.path().join("lib.txt"), content).unwrap();
+        commits.push(commit_all(&upstream_repo, content.trim()));
+    }
+    repo.vendor_fetch(None, None).unwrap();
+
+    let bisection = repo
+        .vendor_bisect("test/upstream", None, None, "! grep -q bug lib.txt")
+        .unwrap();
+    assert_eq!(bisection.first_bad, commits[1].to_string());
+    assert_eq!(bisection.summary, "v3 bug");
+    assert!(bisection.maybe_first_bad.is_empty());
+
+    // The checkout and the repository's worktrees are left as they were.
+    assert_eq!(
+        fs::read_to_string(dir.path().join("lib.txt")).unwrap(),
+        "v1\n"
+    );
+    assert!(repo.worktrees().unwrap().is_empty());
+
+    let err = repo
+        .vendor_bisect("test/upstream", None, None, "true")
+        .unwrap_err();
+    assert!(err.message().contains("passes"), "{}", err.message());
+}
+
+#[test]
+fn blame_attributes_lines_to_upstream_commits() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let (upstream_repo, upstream_dir) = setup_upstream(&[("lib.txt", b"one\n")]);
+    let first = upstream_repo.head().unwrap().target().unwrap();
+    fs::write(upstream_dir.path().join("lib.txt"), "one\ntwo\nthree\n").unwrap();
+    let second = commit_all(&upstream_repo, "more lines");
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    write_gitattributes(
+        dir.path(),
+        &format!(
+            "*.txt vendored name=test/upstream url={} branch=main\n",
+            upstream_dir.path().display(),
+        ),
+    );
+    commit_all(&repo, "vendor config");
+    repo.vendor_fetch(None, None).unwrap();
+    repo.vendor_merge(None, &VendorMergeOpts::default(), None)
+        .unwrap();
+
+    fs::write(dir.path().join("lib.txt"), "one\ntwo\nlocal\n").unwrap();
+
+    let blame = repo.vendor_blame(Path::new("lib.txt")).unwrap();
+    assert_eq!(blame.name, "test/upstream");
+    assert_eq!(blame.upstream, second.to_string());
+    let commits: Vec<_> = blame.lines.iter().map(|l| l.commit.clone()).collect();
+    assert_eq!(
+        commits,
+        vec![Some(first.to_string()), Some(second.to_string()), None]
+    );
+    assert_eq!(blame.lines[2].content, "local");
+
+    assert!(repo.vendor_blame(Path::new(".gitattributes")).is_err());
+}
+
+#[test]
+fn merge_confirm_declined_leaves_head_unchanged() {
+    static SEEN: Mutex<Vec<git_vendor::MergeStat>> = Mutex::new(Vec::new());
+    fn decline(stat: &git_vendor::MergeStat) -> bool {
+        SEEN.lock().unwrap().push(stat.clone());
+        false
+    }
+
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let (_upstream_repo, upstream_dir) =
+        setup_upstream(&[("lib.txt", b"one\ntwo\n"), ("new.txt", b"new\n")]);
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    write_gitattributes(
+        dir.path(),
+        &format!(
+            "*.txt vendored name=test/upstream url={} branch=main\n",
+            upstream_dir.path().display(),
+        ),
+    );
+    fs::write(dir.path().join("lib.txt"), "one\n").unwrap();
+    let before = commit_all(&repo, "vendor config");
+    repo.vendor_fetch(None, None).unwrap();
+
+    let opts = VendorMergeOpts {
+        confirm: Some(decline),
+        ..Default::default()
+    };
+    repo.vendor_merge(None, &opts, None).unwrap();
+    assert_eq!(repo.head().unwrap().target(), Some(before));
+    assert_eq!(
+        fs::read_to_string(dir.path().join("lib.txt")).unwrap(),
+        "one\n"
+    );
+
+    let seen = SEEN.lock().unwrap();
+    assert_eq!(seen.len(), 1);
+    let files: Vec<_> = seen[0]
+        .files
+        .iter()
+        .map(|f| (f.path.as_str(), f.insertions, f.deletions))
+        .collect();
+    assert_eq!(files, vec![("lib.txt", 1, 0), ("new.txt", 1, 0)]);
+    assert_eq!(seen[0].counts(), (1, 1, 0));
+}
+
+#[test]
+fn diff_compares_vendored_content_with_upstream_revisions() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let (upstream_repo, upstream_dir) = setup_upstream(&[("lib.txt", b"v1\n")]);
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    write_gitattributes(
+        dir.path(),
+        &format!(
+            "*.txt vendored name=test/upstream url={} branch=main\n",
+            upstream_dir.path().display(),
+        ),
+    );
+    commit_all(&repo, "vendor config");
+    repo.vendor_fetch(None, None).unwrap();
+    repo.vendor_merge(None, &VendorMergeOpts::default(), None)
+        .unwrap();
+    assert_eq!(repo.vendor_diff("test/upstream", None).unwrap(), "");
+
+    // A branch that was never fetched is looked up upstream.
+    let v1 = upstream_repo.head().unwrap().peel_to_commit().unwrap();
+    upstream_repo.branch("backport", &v1, false).unwrap();
+    upstream_repo.set_head("refs/heads/backport").unwrap();
+    fs::write(upstream_dir.path().join("lib.txt"), "v1 patched\n").unwrap();
+    commit_all(&upstream_repo, "backport fix");
+    upstream_repo.set_head("refs/heads/main").unwrap();
+
+    let patch = repo.vendor_diff("test/upstream", Some("backport")).unwrap();
+    assert!(patch.contains("-v1\n+v1 patched\n"), "{patch}");
+    assert!(
+        repo.vendor_diff("test/upstream", Some("no-such-branch"))
+            .is_err()
+    );
+
+    // Two upstream revisions, filtered to the vendored content.
+    fs::write(upstream_dir.path().join("lib.txt"), "v2\n").unwrap();
+    fs::write(upstream_dir.path().join("README.md"), "not vendored\n").unwrap();
+    let v2 = commit_all(&upstream_repo, "v2");
+    let patch = repo
+        .vendor_diff_revisions("test/upstream", Some("backport"), Some(&v2.to_string()))
+        .unwrap();
+    assert!(patch.contains("-v1 patched\n+v2\n"), "{patch}");
+    assert!(!patch.contains("README.md"), "{patch}");
+
+    // Without revisions, from the commit last merged to the fetched one.
+    repo.vendor_fetch(None, None).unwrap();
+    let patch = repo
+        .vendor_diff_revisions("test/upstream", None, None)
+        .unwrap();
+    assert!(patch.contains("-v1\n+v2\n"), "{patch}");
+
+    // A side that resolves to nothing upstream is an error.
+    assert!(
+        repo.vendor_diff_revisions("test/upstream", Some("no-such-tag"), None)
+            .is_err()
+    );
+    assert!(
+        repo.vendor_diff_revisions("test/upstream", None, Some("no-such-tag"))
+            .is_err()
+    );
+}
+
+#[test]
+fn merge_recovers_base_from_vendored_commit_trailer() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let (upstream_repo, upstream_dir) = setup_upstream(&[("lib/a.txt", b"one\ntwo\nthree\n")]);
+    let vendored = upstream_repo.head().unwrap().target().unwrap();
+
+    // Vendored by hand before provenance records were kept.
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+    write_gitattributes(
+        dir.path(),
+        &format!(
+            "lib/ vendored name=test/upstream url={} branch=main\n",
+            upstream_dir.path().display(),
+        ),
+    );
+    fs::create_dir_all(dir.path().join("lib")).unwrap();
+    fs::write(dir.path().join("lib/a.txt"), "one\ntwo\nthree\n").unwrap();
+    commit_all(
+        &repo,
+        &format!("Vendor lib\n\nVendored-Commit: test/upstream {vendored}\n"),
+    );
+    fs::write(dir.path().join("lib/a.txt"), "one\ntwo\nthree\nlocal\n").unwrap();
+    commit_all(&repo, "patch vendored a");
+
+    fs::write(
+        upstream_dir.path().join("lib/a.txt"),
+        "upstream\none\ntwo\nthree\n",
+    )
+    .unwrap();
+    commit_all(&upstream_repo, "prepend");
+    repo.vendor_fetch(None, None).unwrap();
+
+    let overview = repo.vendor_overview(None).unwrap();
+    assert_eq!(overview[0].vendored, Some(vendored.to_string()));
+    assert_eq!(overview[0].merged_at, None);
+    let outdated = repo.vendor_outdated(None).unwrap();
+    assert_eq!(outdated[0].merged, Some(vendored.to_string()));
+    assert_eq!(outdated[0].new_commits, 1);
+
+    repo.vendor_merge(None, &VendorMergeOpts::default(), None)
+        .unwrap();
+    assert_eq!(
+        fs::read_to_string(dir.path().join("lib/a.txt")).unwrap(),
+        "upstream\none\ntwo\nthree\nlocal\n"
+    );
+}
+
+#[test]
+fn merge_attaches_provenance_notes() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let (upstream_repo, upstream_dir) = setup_upstream(&[("lib.txt", b"content\n")]);
+    let upstream_head = upstream_repo.head().unwrap().target().unwrap();
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+    repo.config()
+        .unwrap()
+        .set_bool("vendor.notes", true)
+        .unwrap();
+    write_gitattributes(
+        dir.path(),
+        &format!(
+            "*.txt vendored name=test/upstream url={} branch=main\n",
+            upstream_dir.path().display(),
+        ),
+    );
+    commit_all(&repo, "vendor config");
+    repo.vendor_fetch(None, None).unwrap();
+    repo.vendor_merge(None, &VendorMergeOpts::default(), None)
+        .unwrap();
+
+    let merge = repo.head().unwrap().target().unwrap();
+    let note = git_vendor::provenance::read_note(&repo, merge, "test/upstream")
+        .expect("merge commit should carry a provenance note");
+    assert_eq!(note.commit, upstream_head.to_string());
+    assert!(git_vendor::provenance::read_note(&repo, merge, "other/dep").is_none());
+
+    // The note outlives the record in the tree.
+    fs::remove_dir_all(dir.path().join(".git-vendor")).unwrap();
+    let mut index = repo.index().unwrap();
+    index.remove_dir(Path::new(".git-vendor"), 0).unwrap();
+    index.write().unwrap();
+    commit_all(&repo, "drop provenance records");
+    let overview = repo.vendor_overview(None).unwrap();
+    assert_eq!(overview[0].vendored, Some(upstream_head.to_string()));
+}
+
+#[test]
+fn track_and_untrack_quote_patterns_with_spaces() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    repo.track_pattern(
+        "third party/lib/",
+        "https://github.com/owner/repo.git",
+        Some("main"),
+        None,
+        None,
+        false,
+    )
+    .unwrap();
+
+    let ga = dir.path().join(".gitattributes");
+    let content = fs::read_to_string(&ga).unwrap();
+    assert!(
+        content.starts_with("\"third party/lib/\" vendored name=owner/repo"),
+        "{content}"
+    );
+    let deps = repo.vendor_overview(None).unwrap();
+    assert_eq!(deps.len(), 1);
+    assert_eq!(deps[0].pattern, "third party/lib/");
+
+    repo.untrack_pattern("third party/lib/").unwrap();
+    assert!(!fs::read_to_string(&ga).unwrap().contains("third party"));
+}
+
+#[test]
+fn stale_lists_unmerged_and_old_dependencies() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let (_upstream_repo, upstream_dir) = setup_upstream(&[("lib.txt", b"content\n")]);
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    write_gitattributes(
+        dir.path(),
+        &format!(
+            "*.txt vendored name=test/upstream url={} branch=main\n",
+            upstream_dir.path().display(),
+        ),
+    );
+    commit_all(&repo, "vendor config");
+
+    let stale = repo.vendor_stale(None, 90 * 86_400).unwrap();
+    assert_eq!(stale.len(), 1);
+    assert_eq!(stale[0].merged_at, None);
+
+    repo.vendor_fetch(None, None).unwrap();
+    repo.vendor_merge(None, &VendorMergeOpts::default(), None)
+        .unwrap();
+
+    assert!(repo.vendor_stale(None, 90 * 86_400).unwrap().is_empty());
+    let stale = repo.vendor_stale(None, 0).unwrap();
+    assert_eq!(stale.len(), 1);
+    assert_eq!(stale[0].age_days, Some(0));
+    assert_eq!(stale[0].merged_by.as_deref(), Some("Test <test@test>"));
+    assert!(repo.vendor_status(None, &StatusOpts::default()).is_ok());
+}
+
+#[test]
+fn overview_reports_pending_and_dirty_dependencies() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let (upstream_repo, upstream_dir) = setup_upstream(&[("lib.txt", b"v1\n")]);
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    write_gitattributes(
+        dir.path(),
+        &format!(
+            "*.txt vendored name=test/upstream url={} branch=main\n",
+            upstream_dir.path().display(),
+        ),
+    );
+    commit_all(&repo, "vendor config");
+
+    let overview = repo.vendor_overview(None).unwrap();
+    assert_eq!(overview.len(), 1);
+    assert!(!overview[0].fetched);
+    assert_eq!(overview[0].age_days, None);
+
+    repo.vendor_fetch(None, None).unwrap();
+    repo.vendor_merge(None, &VendorMergeOpts::default(), None)
+        .unwrap();
+
+    fs::write(upstream_dir.path().join("lib.txt"), "v2\n").unwrap();
+    commit_all(&upstream_repo, "v2");
+    repo.vendor_fetch(None, None).unwrap();
+
+    fs::write(dir.path().join("lib.txt"), "local edit\n").unwrap();
+    commit_all(&repo, "edit vendored file");
+
+    let overview = repo.vendor_overview(None).unwrap();
+    assert_eq!(overview[0].reference, "refs/vendor/test/upstream");
+    assert!(overview[0].fetched);
+    assert_eq!(overview[0].age_days, Some(0));
+    assert_eq!(overview[0].pending, 1);
+    assert!(overview[0].dirty);
+}
+
+// ---------------------------------------------------------------------------
+// verify
+// ---------------------------------------------------------------------------
+
+#[test]
+fn verify_detects_local_modification() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let (_upstream_repo, upstream_dir) = setup_upstream(&[("lib.txt", b"content\n")]);
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    write_gitattributes(
+        dir.path(),
+        &format!(
+            "*.txt vendored name=test/upstream url={} branch=main\n",
+            upstream_dir.path().display(),
+        ),
+    );
+    commit_all(&repo, "vendor config");
+
+    repo.vendor_fetch(None, None).unwrap();
+    repo.vendor_merge(None, &VendorMergeOpts::default(), None)
+        .unwrap();
+    repo.vendor_verify(None).unwrap();
+
+    fs::write(dir.path().join("lib.txt"), "tampered\n").unwrap();
+    commit_all(&repo, "tamper");
 
-    assert!(repo.vendor_status(None).is_ok());
+    let err = repo.vendor_verify(None).unwrap_err();
+    assert!(
+        err.message().contains("failed verification"),
+        "expected verification failure, got: {}",
+        err.message()
+    );
 }
 
 #[test]
-fn status_ok_with_tracked_dep() {
+fn annotations_point_at_the_defining_line() {
     let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let (upstream_repo, upstream_dir) = setup_upstream(&[("lib.txt", b"content\n")]);
+
     let (repo, dir) = setup_repo();
     std::env::set_current_dir(dir.path()).unwrap();
 
     write_gitattributes(
         dir.path(),
-        "*.txt vendored name=o/r url=https://example.com/o/r.git branch=main\n",
+        &format!(
+            "*.md text\n*.txt vendored name=test/upstream url={} branch=main\n",
+            upstream_dir.path().display(),
+        ),
     );
+    commit_all(&repo, "vendor config");
 
-    assert!(repo.vendor_status(None).is_ok());
-}
-
-#[test]
-fn status_ok_with_tracked_dep_no_branch() {
-    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
-    let (repo, dir) = setup_repo();
-    std::env::set_current_dir(dir.path()).unwrap();
+    repo.vendor_fetch(None, None).unwrap();
+    repo.vendor_merge(None, &VendorMergeOpts::default(), None)
+        .unwrap();
+    let all = [
+        AnnotationKind::Outdated,
+        AnnotationKind::Drift,
+        AnnotationKind::Policy,
+    ];
+    assert!(repo.vendor_annotations(None, &all).unwrap().is_empty());
+
+    fs::write(dir.path().join("lib.txt"), "tampered\n").unwrap();
+    commit_all(&repo, "tamper");
+    fs::write(upstream_dir.path().join("lib.txt"), "v2\n").unwrap();
+    commit_all(&upstream_repo, "v2");
+    repo.vendor_fetch(None, None).unwrap();
 
-    write_gitattributes(
-        dir.path(),
-        "*.txt vendored name=o/r url=https://example.com/o/r.git\n",
+    let annotations = repo.vendor_annotations(None, &all).unwrap();
+    let kinds: Vec<AnnotationKind> = annotations.iter().map(|a| a.kind).collect();
+    assert_eq!(kinds, [AnnotationKind::Drift, AnnotationKind::Outdated]);
+    assert!(
+        annotations[0]
+            .to_github()
+            .starts_with("::error file=.gitattributes,line=2,title=test/upstream::")
+    );
+    assert_eq!(
+        annotations[1].to_github(),
+        "::warning file=.gitattributes,line=2,title=test/upstream::test/upstream has 1 new upstream commit"
     );
 
-    assert!(repo.vendor_status(None).is_ok());
+    let outdated = repo
+        .vendor_annotations(None, &[AnnotationKind::Outdated])
+        .unwrap();
+    assert_eq!(outdated.len(), 1);
 }
 
-// ---------------------------------------------------------------------------
-// fetch
-// ---------------------------------------------------------------------------
-
 #[test]
-fn fetch_errors_with_no_deps() {
+fn verify_errors_with_no_deps() {
     let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
     let (repo, dir) = setup_repo();
     std::env::set_current_dir(dir.path()).unwrap();
 
-    let err = repo.vendor_fetch(None, None).unwrap_err();
-    assert!(err.message().contains("No vendored dependencies to fetch"));
+    let err = repo.vendor_verify(None).unwrap_err();
+    assert!(err.message().contains("No vendored dependencies to verify"));
 }
 
 // ---------------------------------------------------------------------------
-// merge
+// sbom
 // ---------------------------------------------------------------------------
 
 #[test]
-fn merge_errors_with_no_deps() {
+fn sbom_cyclonedx_lists_fetched_dependency() {
     let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let (upstream_repo, upstream_dir) = setup_upstream(&[("lib.txt", b"content\n")]);
+    let upstream_head = upstream_repo.head().unwrap().target().unwrap();
+
     let (repo, dir) = setup_repo();
     std::env::set_current_dir(dir.path()).unwrap();
 
-    let err = Vendor::vendor_merge(&repo, None, &Default::default(), None).unwrap_err();
-    assert!(err.message().contains("No vendored dependencies to merge"));
+    write_gitattributes(
+        dir.path(),
+        &format!(
+            "*.txt vendored name=test/upstream url={} branch=main\n",
+            upstream_dir.path().display(),
+        ),
+    );
+    repo.vendor_fetch(None, None).unwrap();
+
+    let document = repo.vendor_sbom(None, SbomFormat::CycloneDx).unwrap();
+    let bom: serde_json::Value = serde_json::from_str(&document).unwrap();
+
+    assert_eq!(bom["bomFormat"], "CycloneDX");
+    let component = &bom["components"][0];
+    assert_eq!(component["name"], "test/upstream");
+    assert_eq!(component["version"], upstream_head.to_string());
+    assert!(
+        component["purl"]
+            .as_str()
+            .unwrap()
+            .starts_with(&format!("pkg:generic/upstream@{upstream_head}?vcs_url="))
+    );
 }
 
 // ---------------------------------------------------------------------------
@@ -300,13 +3189,22 @@ fn bare_repo_rejects_all_operations() {
     let repo = Repository::init_bare(dir.path()).unwrap();
 
     assert!(
-        repo.track_pattern("*.txt", "https://github.com/o/r.git", None, None)
-            .is_err()
+        repo.track_pattern(
+            "*.txt",
+            "https://github.com/o/r.git",
+            None,
+            None,
+            None,
+            false
+        )
+        .is_err()
     );
     assert!(repo.untrack_pattern("*.txt").is_err());
-    assert!(repo.vendor_status(None).is_err());
+    assert!(repo.vendor_status(None, &StatusOpts::default()).is_err());
     assert!(repo.vendor_fetch(None, None).is_err());
     assert!(Vendor::vendor_merge(&repo, None, &VendorMergeOpts::default(), None).is_err());
+    assert!(repo.vendor_verify(None).is_err());
+    assert!(repo.vendor_sbom(None, SbomFormat::CycloneDx).is_err());
 }
 
 // ---------------------------------------------------------------------------
@@ -373,6 +3271,205 @@ fn merge_preserves_non_vendor_files() {
     );
 }
 
+#[test]
+fn overview_reuses_cached_status_until_content_changes() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let (_upstream_repo, upstream_dir) = setup_upstream(&[("lib.txt", b"content\n")]);
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+    write_gitattributes(
+        dir.path(),
+        &format!(
+            "*.txt vendored name=test/upstream url={} branch=main\n",
+            upstream_dir.path().display(),
+        ),
+    );
+    commit_all(&repo, "vendor config");
+    repo.vendor_fetch(None, None).unwrap();
+    repo.vendor_merge(None, &VendorMergeOpts::default(), None)
+        .unwrap();
+    assert_eq!(repo.vendor_overview(None).unwrap()[0].size, 8);
+
+    // A cached summary is used as long as nothing it depends on changed.
+    let cache = repo.path().join("vendor/status.json");
+    let json = fs::read_to_string(&cache).unwrap();
+    fs::write(&cache, json.replace("\"size\":8", "\"size\":12345")).unwrap();
+    assert_eq!(repo.vendor_overview(None).unwrap()[0].size, 12345);
+
+    // Summaries can also be taken one at a time.
+    let mut statuses = repo.vendor_status_iter(None).unwrap();
+    assert_eq!(statuses.len(), 1);
+    assert_eq!(statuses.next().unwrap().unwrap().size, 12345);
+    assert!(statuses.next().is_none());
+    drop(statuses);
+
+    // Committing to the vendored content invalidates it.
+    fs::write(dir.path().join("lib.txt"), "changed content\n").unwrap();
+    commit_all(&repo, "local edit");
+    let overview = repo.vendor_overview(None).unwrap();
+    assert_eq!(overview[0].size, 16);
+    assert!(overview[0].dirty);
+}
+
+#[cfg(unix)]
+#[test]
+fn foreach_runs_command_per_dependency() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let (a_repo, a_dir) = setup_upstream(&[("a/lib.txt", b"a\n")]);
+    let (_b_repo, b_dir) = setup_upstream(&[("b/lib.txt", b"b\n")]);
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+    write_gitattributes(
+        dir.path(),
+        &format!(
+            "a/ vendored name=test/a url={} branch=main\n\
+             b/ vendored name=test/b url={} branch=main\n",
+            a_dir.path().display(),
+            b_dir.path().display(),
+        ),
+    );
+    commit_all(&repo, "vendor config");
+    repo.vendor_fetch(None, None).unwrap();
+    repo.vendor_merge(Some("a/"), &VendorMergeOpts::default(), None)
+        .unwrap();
+
+    let out = dir.path().join("foreach.out");
+    let script = format!(
+        "echo \"$VENDOR_NAME $VENDOR_PATH $(basename \"$PWD\") $VENDOR_SHA\" >> {}",
+        out.display()
+    );
+    repo.vendor_foreach(None, std::slice::from_ref(&script))
+        .unwrap();
+    // b is not merged yet, so its command runs from the repository root.
+    let a_head = a_repo.head().unwrap().target().unwrap();
+    let root = dir.path().file_name().unwrap().to_str().unwrap();
+    assert_eq!(
+        fs::read_to_string(&out).unwrap(),
+        format!("test/a a a {a_head}\ntest/b b {root} \n")
+    );
+
+    // Concurrently, every command still runs; failures are reported.
+    fs::remove_file(&out).unwrap();
+    repo.config().unwrap().set_i64("vendor.jobs", 2).unwrap();
+    repo.vendor_foreach(None, &[script]).unwrap();
+    let mut lines: Vec<String> = fs::read_to_string(&out)
+        .unwrap()
+        .lines()
+        .map(str::to_string)
+        .collect();
+    lines.sort();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("test/a "));
+
+    let err = repo
+        .vendor_foreach(Some("b/"), &["test \"$VENDOR_NAME\" != test/b".into()])
+        .unwrap_err();
+    assert!(err.message().contains("test/b"), "{}", err.message());
+}
+
+#[test]
+fn merge_of_several_dependencies_commits_each() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let (_a_repo, a_dir) = setup_upstream(&[("a/lib.txt", b"a\n")]);
+    let (_b_repo, b_dir) = setup_upstream(&[("b/lib.txt", b"b\n"), ("b/sub/x.txt", b"x\n")]);
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+    repo.config()
+        .unwrap()
+        .set_bool("vendor.notes", true)
+        .unwrap();
+    fs::write(dir.path().join("README.md"), "# My Project\n").unwrap();
+    write_gitattributes(
+        dir.path(),
+        &format!(
+            "a/ vendored name=test/a url={} branch=main\n\
+             b/ vendored name=test/b url={} branch=main\n",
+            a_dir.path().display(),
+            b_dir.path().display(),
+        ),
+    );
+    let start = commit_all(&repo, "vendor config");
+    repo.vendor_fetch(None, None).unwrap();
+    repo.vendor_merge(None, &VendorMergeOpts::default(), None)
+        .unwrap();
+
+    // One commit per dependency, each with its own content and note.
+    let head = repo.head().unwrap().peel_to_commit().unwrap();
+    let first = head.parent(0).unwrap();
+    assert_eq!(first.parent_id(0).unwrap(), start);
+    assert_eq!(first.summary(), Some("Merge vendored dependency: test/a"));
+    assert_eq!(head.summary(), Some("Merge vendored dependency: test/b"));
+    assert!(
+        first
+            .tree()
+            .unwrap()
+            .get_path(Path::new("a/lib.txt"))
+            .is_ok()
+    );
+    assert!(
+        first
+            .tree()
+            .unwrap()
+            .get_path(Path::new("b/lib.txt"))
+            .is_err()
+    );
+    assert!(git_vendor::provenance::read_note(&repo, first.id(), "test/a").is_some());
+    assert!(git_vendor::provenance::read_note(&repo, head.id(), "test/b").is_some());
+
+    for path in ["README.md", "a/lib.txt", "b/lib.txt", "b/sub/x.txt"] {
+        assert!(
+            dir.path().join(path).exists(),
+            "{path} should be checked out"
+        );
+    }
+    let mut index = repo.index().unwrap();
+    assert_eq!(index.write_tree().unwrap(), head.tree_id());
+}
+
+#[test]
+fn squash_setting_does_not_block_merging_several_dependencies() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let (_a_repo, a_dir) = setup_upstream(&[("a/lib.txt", b"a\n")]);
+    let (_b_repo, b_dir) = setup_upstream(&[("b/lib.txt", b"b\n")]);
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+    repo.config()
+        .unwrap()
+        .set_bool("vendor.squash", true)
+        .unwrap();
+    write_gitattributes(
+        dir.path(),
+        &format!(
+            "a/ vendored name=test/a url={} branch=main\n\
+             b/ vendored name=test/b url={} branch=main\n",
+            a_dir.path().display(),
+            b_dir.path().display(),
+        ),
+    );
+    let start = commit_all(&repo, "vendor config");
+    repo.vendor_fetch(None, None).unwrap();
+    repo.vendor_merge(None, &VendorMergeOpts::default(), None)
+        .unwrap();
+
+    // Each dependency is committed as usual.
+    let head = repo.head().unwrap().peel_to_commit().unwrap();
+    assert_eq!(head.summary(), Some("Merge vendored dependency: test/b"));
+    assert_eq!(head.parent(0).unwrap().parent_id(0).unwrap(), start);
+
+    // An explicit --squash still needs a single dependency.
+    let opts = VendorMergeOpts {
+        squash: true,
+        ..Default::default()
+    };
+    assert!(repo.vendor_merge(None, &opts, None).is_err());
+}
+
 // ---------------------------------------------------------------------------
 // merge rejects dirty index
 // ---------------------------------------------------------------------------
@@ -415,6 +3512,7 @@ fn merge_rejects_dirty_index() {
         "expected dirty-index error, got: {}",
         err.message()
     );
+    assert_eq!(error::code(&err), error::Code::DirtyIndex);
 }
 
 // ---------------------------------------------------------------------------
@@ -493,6 +3591,101 @@ fn merge_vendors_subdirectory_from_upstream() {
     );
 }
 
+#[test]
+fn merge_vendors_every_pattern_of_a_grouped_dependency() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let (_upstream_repo, upstream_dir) = setup_upstream(&[
+        ("include/foo/foo.h", b"int foo(void);\n"),
+        ("src/foo/foo.c", b"int foo(void) { return 0; }\n"),
+        ("tests/foo.c", b"not vendored\n"),
+    ]);
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+    write_gitattributes(
+        dir.path(),
+        &format!(
+            "include/foo/** vendored name=test/foo url={} branch=main\n\
+             src/foo/** vendored name=test/foo\n",
+            upstream_dir.path().display(),
+        ),
+    );
+    commit_all(&repo, "initial");
+
+    repo.vendor_fetch(Some("src/foo/**"), None).unwrap();
+    repo.vendor_merge(None, &VendorMergeOpts::default(), None)
+        .unwrap();
+
+    assert!(dir.path().join("include/foo/foo.h").exists());
+    assert!(dir.path().join("src/foo/foo.c").exists());
+    assert!(!dir.path().join("tests/foo.c").exists());
+
+    let overview = repo.vendor_overview(None).unwrap();
+    assert_eq!(overview.len(), 1);
+    assert_eq!(overview[0].name, "test/foo");
+    repo.vendor_verify(None).unwrap();
+
+    repo.untrack_pattern("include/foo/**").unwrap();
+    let attrs = fs::read_to_string(dir.path().join(".gitattributes")).unwrap();
+    assert_eq!(
+        attrs,
+        format!(
+            "src/foo/** vendored name=test/foo url={} branch=main\n",
+            upstream_dir.path().display()
+        )
+    );
+}
+
+#[test]
+fn fetch_recursive_resolves_nested_dependencies() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let (_b_repo, b_dir) = setup_upstream(&[("third_party/b/b.txt", b"b\n")]);
+    let attributes = format!(
+        "third_party/b/ vendored name=test/b url={} branch=main\n\
+         other/ vendored name=test/c url=/nonexistent branch=main\n",
+        b_dir.path().display()
+    );
+    let (_a_repo, a_dir) = setup_upstream(&[
+        (".gitattributes", attributes.as_bytes()),
+        ("third_party/b/b.txt", b"b\n"),
+        ("other/c.txt", b"c\n"),
+    ]);
+
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+    write_gitattributes(
+        dir.path(),
+        &format!(
+            "third_party/ vendored name=test/a url={} branch=main\n",
+            a_dir.path().display()
+        ),
+    );
+    commit_all(&repo, "initial");
+
+    let resolved = repo.vendor_fetch_recursive(None, 3).unwrap();
+    assert_eq!(resolved.len(), 1);
+    assert_eq!(resolved[0].name, "test/b");
+    assert_eq!(resolved[0].pattern, "third_party/b/");
+    assert_eq!(resolved[0].parent, "test/a");
+    assert_eq!(resolved[0].depth, 1);
+    assert!(repo.find_reference("refs/vendor/test/b").is_ok());
+
+    assert!(repo.vendor_fetch_recursive(None, 0).unwrap().is_empty());
+
+    let graph: serde_json::Value =
+        serde_json::from_str(&repo.vendor_graph(None, 3, GraphFormat::Json).unwrap()).unwrap();
+    let edges: Vec<(&str, &str)> = graph["edges"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|edge| (edge["from"].as_str().unwrap(), edge["to"].as_str().unwrap()))
+        .collect();
+    let host = dir.path().file_name().unwrap().to_str().unwrap();
+    assert_eq!(edges, [(host, "test/a"), ("test/a", "test/b")]);
+}
+
 // ---------------------------------------------------------------------------
 // trailing-slash pattern (e.g. "pyo3/") must behave like "pyo3/**"
 // ---------------------------------------------------------------------------
@@ -555,3 +3748,193 @@ fn merge_vendors_subdirectory_trailing_slash_pattern() {
         "other/ from vendor must not appear in the host tree"
     );
 }
+
+#[test]
+fn import_cargo_pins_locked_git_crates_to_their_directories() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    let (upstream, upstream_dir) = setup_upstream(&[
+        ("Cargo.toml", b"[workspace]\nmembers = [\"crates/*\"]\n"),
+        ("crates/foo/Cargo.toml", b"[package]\nname = \"foo\"\n"),
+        ("crates/foo/src/lib.rs", b"pub fn foo() {}\n"),
+        ("crates/bar/Cargo.toml", b"[package]\nname = \"bar\"\n"),
+    ]);
+    let commit = upstream.head().unwrap().peel_to_commit().unwrap().id();
+    let url = format!("file://{}", upstream_dir.path().display());
+    let source = format!("git+{url}?branch=main#{commit}");
+    fs::write(
+        dir.path().join("Cargo.lock"),
+        format!(
+            "version = 4\n\n\
+             [[package]]\nname = \"foo\"\nversion = \"0.1.0\"\nsource = \"{source}\"\n\n\
+             [[package]]\nname = \"baz\"\nversion = \"0.1.0\"\nsource = \"{source}\"\n\n\
+             [[package]]\nname = \"host\"\nversion = \"0.1.0\"\n"
+        ),
+    )
+    .unwrap();
+    fs::create_dir_all(dir.path().join("vendor/foo")).unwrap();
+
+    let imported = repo
+        .vendor_import_cargo(&dir.path().join("vendor"), true)
+        .unwrap();
+    assert_eq!(imported.len(), 1);
+    assert_eq!(imported[0].rev, commit.to_string());
+    assert_eq!(imported[0].branch.as_deref(), Some("main"));
+    assert_eq!(
+        imported[0].packages,
+        [("foo".to_string(), "crates/foo/".to_string())]
+    );
+    assert_eq!(imported[0].skipped.len(), 1);
+    assert_eq!(imported[0].skipped[0].0, "baz");
+    assert_eq!(imported[0].replaces, [dir.path().join("vendor/foo")]);
+    assert!(!dir.path().join(".gitattributes").exists());
+
+    repo.vendor_import_cargo(Path::new("Cargo.lock"), false)
+        .unwrap();
+    let gitattributes = fs::read_to_string(dir.path().join(".gitattributes")).unwrap();
+    assert!(gitattributes.starts_with("crates/foo/ vendored "));
+    assert!(gitattributes.contains(&format!("url={url}")));
+    assert!(gitattributes.contains(&format!("rev={commit}")));
+    assert!(gitattributes.contains("branch=main"));
+
+    commit_all(&repo, "import");
+    repo.vendor_merge(None, &VendorMergeOpts::default(), None)
+        .unwrap();
+    assert!(dir.path().join("crates/foo/src/lib.rs").exists());
+    assert!(!dir.path().join("crates/bar").exists());
+}
+
+#[test]
+fn import_gomod_tracks_module_directories_and_exports_modules_txt() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    let (upstream, upstream_dir) = setup_upstream(&[
+        ("go.mod", b"module github.com/o/r\n"),
+        ("sub/go.mod", b"module github.com/o/r/sub\n\ngo 1.21\n"),
+        ("sub/a.go", b"package sub\n"),
+        ("sub/pkg/b.go", b"package pkg\n"),
+        ("sub/pkg/b_test.go", b"package pkg\n"),
+        ("sub/testdata/c.go", b"package testdata\n"),
+    ]);
+    let head = upstream.head().unwrap().peel_to_commit().unwrap();
+    for tag in ["v1.0.0", "sub/v1.0.0"] {
+        upstream
+            .tag_lightweight(tag, head.as_object(), false)
+            .unwrap();
+    }
+    repo.config()
+        .unwrap()
+        .set_str(
+            &format!("url.file://{}.insteadOf", upstream_dir.path().display()),
+            "https://github.com/o/r",
+        )
+        .unwrap();
+    fs::write(
+        dir.path().join("go.mod"),
+        "module example.com/host\n\n\
+         require (\n\
+         \tgithub.com/o/r v1.0.0\n\
+         \tgithub.com/o/r/sub v1.0.0 // indirect\n\
+         )\n",
+    )
+    .unwrap();
+
+    let imported = repo
+        .vendor_import_gomod(Path::new("go.mod"), false)
+        .unwrap();
+    assert_eq!(imported.len(), 2);
+    assert_eq!(imported[0].rev, "sub/v1.0.0");
+    assert_eq!(
+        imported[0].packages,
+        [("github.com/o/r/sub".to_string(), "sub/".to_string())]
+    );
+    assert_eq!(imported[1].rev, "v1.0.0");
+    assert!(imported[1].packages.is_empty());
+    assert_eq!(imported[1].skipped[0].0, "github.com/o/r");
+    let gitattributes = fs::read_to_string(dir.path().join(".gitattributes")).unwrap();
+    assert_eq!(
+        gitattributes,
+        "sub/ vendored name=o/r url=https://github.com/o/r rev=sub/v1.0.0\n"
+    );
+
+    commit_all(&repo, "import");
+    repo.vendor_fetch(None, None).unwrap();
+    repo.vendor_merge(None, &VendorMergeOpts::default(), None)
+        .unwrap();
+
+    let modules_txt = dir.path().join("vendor/modules.txt");
+    assert!(repo.vendor_export_gomod(&modules_txt, true).is_err());
+    repo.vendor_export_gomod(&modules_txt, false).unwrap();
+    assert_eq!(
+        fs::read_to_string(&modules_txt).unwrap(),
+        "# github.com/o/r/sub v1.0.0\n\
+         ## explicit; go 1.21\n\
+         github.com/o/r/sub\n\
+         github.com/o/r/sub/pkg\n"
+    );
+    repo.vendor_export_gomod(&modules_txt, true).unwrap();
+}
+
+#[test]
+fn import_npm_vendors_git_packages_under_third_party_node() {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let (repo, dir) = setup_repo();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    let (upstream, upstream_dir) = setup_upstream(&[
+        ("package.json", b"{\"name\": \"a\"}\n"),
+        ("index.js", b"module.exports = 1;\n"),
+    ]);
+    let commit = upstream.head().unwrap().peel_to_commit().unwrap().id();
+    let url = format!("file://{}", upstream_dir.path().display());
+    fs::write(
+        dir.path().join("package-lock.json"),
+        format!(
+            r#"{{
+  "lockfileVersion": 3,
+  "packages": {{
+    "": {{ "name": "host" }},
+    "node_modules/a": {{ "resolved": "git+{url}#{commit}" }},
+    "node_modules/c": {{ "resolved": "https://registry.npmjs.org/c/-/c-1.0.0.tgz" }}
+  }}
+}}"#
+        ),
+    )
+    .unwrap();
+
+    let imported = repo
+        .vendor_import_npm(Path::new("package-lock.json"), true)
+        .unwrap();
+    assert_eq!(imported.len(), 1);
+    assert_eq!(imported[0].rev, commit.to_string());
+    assert_eq!(
+        imported[0].packages,
+        [("a".to_string(), "third_party/node/a/".to_string())]
+    );
+    assert!(!dir.path().join(".gitattributes").exists());
+
+    repo.vendor_import_npm(Path::new("package-lock.json"), false)
+        .unwrap();
+    assert_eq!(
+        fs::read_to_string(dir.path().join(".gitattributes")).unwrap(),
+        format!(
+            "third_party/node/a/ vendored name=a url={url} rev={commit} prefix=third_party/node/a\n"
+        )
+    );
+
+    commit_all(&repo, "import");
+    repo.vendor_fetch(None, None).unwrap();
+    repo.vendor_merge(None, &VendorMergeOpts::default(), None)
+        .unwrap();
+    let head_tree = repo.head().unwrap().peel_to_tree().unwrap();
+    assert!(
+        head_tree
+            .get_path(Path::new("third_party/node/a/index.js"))
+            .is_ok()
+    );
+    assert!(head_tree.get_path(Path::new("index.js")).is_err());
+}